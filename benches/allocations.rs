@@ -0,0 +1,91 @@
+//! Count the heap allocations made while analyzing a large generated C file.
+//!
+//! Run with `cargo bench --bench allocations`.
+//! This doesn't use criterion because it measures allocations, not time.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use saltwater::{analyze, hir::Arena, Opt};
+
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static BYTES: AtomicUsize = AtomicUsize::new(0);
+static DEALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        DEALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+/// A program with `n` functions, each with a few dozen expression nodes.
+fn generate(n: usize) -> String {
+    let mut program = String::new();
+    for i in 0..n {
+        program.push_str(&format!(
+            "int f{i}(int a, int b) {{
+    int x = a + b * 2 - (a << 1) / (b | 1);
+    if (x > a && b < x) {{
+        x = x * 3 + a;
+    }} else {{
+        x = -x + ~b;
+    }}
+    for (int j = 0; j < 10; j++) {{
+        x = x + j * a - (j ^ b);
+    }}
+    return x ? x : a - b;
+}}
+",
+            i = i
+        ));
+    }
+    program.push_str("int main(void) { return f0(1, 2); }\n");
+    program
+}
+
+fn measure<T>(f: impl FnOnce() -> T) -> (T, usize, usize) {
+    let (allocations, bytes) = (
+        ALLOCATIONS.load(Ordering::Relaxed),
+        BYTES.load(Ordering::Relaxed),
+    );
+    let result = f();
+    (
+        result,
+        ALLOCATIONS.load(Ordering::Relaxed) - allocations,
+        BYTES.load(Ordering::Relaxed) - bytes,
+    )
+}
+
+fn main() {
+    let functions = 2000;
+    let program = generate(functions);
+    let deallocations = {
+        // the HIR borrows from the arena, so both are dropped at the end of this block
+        let arena = Arena::new();
+        let (analysis, allocations, bytes) = measure(|| analyze(&program, Opt::default(), &arena));
+        assert!(analysis.errors.is_empty(), "{:?}", analysis.errors);
+        println!(
+            "analyze ({} functions, {} bytes of source): {} allocations, {} bytes",
+            functions,
+            program.len(),
+            allocations,
+            bytes
+        );
+        DEALLOCATIONS.load(Ordering::Relaxed)
+    };
+    println!(
+        "drop: {} deallocations",
+        DEALLOCATIONS.load(Ordering::Relaxed) - deallocations
+    );
+}
@@ -12,7 +12,7 @@ fn examples(c: &mut Criterion) {
     let mut group = c.benchmark_group("Fibonacci");
     group.bench_function("rcc", |b| {
         b.iter(|| {
-            let module = initialize_jit_module();
+            let module = initialize_jit_module(0);
             black_box(compile(module, FIBONACCI, &opts))
         });
     });
@@ -20,7 +20,7 @@ fn examples(c: &mut Criterion) {
     let mut group = c.benchmark_group("Factorial");
     group.bench_function("rcc", |b| {
         b.iter(|| {
-            let module = initialize_jit_module();
+            let module = initialize_jit_module(0);
             black_box(compile(module, FACTORIAL, &opts))
         });
     });
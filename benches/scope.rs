@@ -0,0 +1,48 @@
+//! Time name resolution in a file with many globals and deeply nested blocks.
+//!
+//! Run with `cargo bench --bench scope`.
+//! This doesn't use criterion because a single run already takes long enough to measure.
+
+use std::time::Instant;
+
+use saltwater::{analyze, hir::Arena, Opt};
+
+/// `globals` file-scope variables, then a function nested `depth` blocks deep
+/// that refers to both the globals and the locals of every enclosing block.
+fn generate(globals: usize, depth: usize) -> String {
+    let mut program = String::new();
+    for i in 0..globals {
+        program.push_str(&format!("int g{};\n", i));
+    }
+    program.push_str("int f(void) {\n    int sum = 0;\n");
+    for d in 0..depth {
+        program.push_str(&format!(
+            "{{ int l{d} = g{g} + sum;\nsum = sum + l{d} + g{last};\n",
+            d = d,
+            g = d * 7 % globals,
+            last = globals - 1 - d % globals,
+        ));
+    }
+    for d in 0..depth {
+        program.push_str(&format!("sum = sum + l{} + l0;\n}}\n", depth - 1 - d));
+    }
+    program.push_str("    return sum;\n}\nint main(void) { return f(); }\n");
+    program
+}
+
+fn main() {
+    let (globals, depth) = (10_000, 1_000);
+    let program = generate(globals, depth);
+    let arena = Arena::new();
+    let start = Instant::now();
+    let analysis = analyze(&program, Opt::default(), &arena);
+    let elapsed = start.elapsed();
+    assert!(analysis.errors.is_empty(), "{:?}", analysis.errors);
+    println!(
+        "analyze ({} globals, {} nested blocks, {} bytes of source): {:?}",
+        globals,
+        depth,
+        program.len(),
+        elapsed
+    );
+}
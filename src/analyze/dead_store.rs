@@ -0,0 +1,538 @@
+//! Warnings for assignments to local variables that are never read (`-Wdead-store`)
+//!
+//! This is a backward liveness analysis over the HIR of a single function.
+//! Loops and `goto` are handled by iterating until the live variables stop changing.
+//!
+//! To keep false positives down, only plain assignments (`x = ...`) are checked,
+//! never initializers or compound assignments,
+//! and only variables which are read somewhere in the function are tracked,
+//! since `-Wunused-but-set-variable` already covers the rest.
+
+use std::collections::{HashMap, HashSet};
+
+use super::PureAnalyzer;
+use crate::data::{error::Warning, hir::*, Location, StorageClass};
+use crate::intern::InternedStr;
+
+/// The tracked variables which may be read before they are next assigned.
+type Live = HashSet<Symbol>;
+
+impl PureAnalyzer<'_> {
+    /// Warn about assignments to local variables which are overwritten or go out of scope before being read.
+    pub(super) fn check_dead_stores(&mut self, body: &[Stmt]) {
+        let mut candidates = Candidates::default();
+        candidates.stmts(body);
+        let tracked = candidates.tracked();
+        if tracked.is_empty() {
+            return;
+        }
+        let mut checker = Checker {
+            tracked,
+            ..Checker::default()
+        };
+        // `goto` can jump backwards, so iterate until the variables live at every label are known
+        loop {
+            let labels = checker.labels.clone();
+            checker.stmts(body, &mut Live::new());
+            if checker.labels == labels {
+                break;
+            }
+        }
+        let mut stores: Vec<_> = checker
+            .stores
+            .into_values()
+            .filter(|store| store.dead)
+            .collect();
+        stores.sort_by_key(|store| store.location.span);
+        for store in stores {
+            let name = store.symbol.get().id;
+            self.warn(Warning::DeadStore(name, store.side_effects), store.location);
+        }
+    }
+}
+
+/// Finds the variables that are worth checking.
+#[derive(Default)]
+struct Candidates {
+    declared: HashSet<Symbol>,
+    read: HashSet<Symbol>,
+    /// Variables whose address is used for something other than a load or store.
+    /// These could be read through a pointer at any time.
+    escaped: HashSet<Symbol>,
+}
+
+impl Candidates {
+    fn tracked(self) -> HashSet<Symbol> {
+        let Candidates {
+            declared,
+            read,
+            escaped,
+        } = self;
+        declared
+            .into_iter()
+            .filter(|symbol| read.contains(symbol) && !escaped.contains(symbol))
+            .collect()
+    }
+    fn stmts(&mut self, stmts: &[Stmt]) {
+        for stmt in stmts {
+            self.stmt(stmt);
+        }
+    }
+    fn stmt(&mut self, stmt: &Stmt) {
+        match &stmt.data {
+            StmtType::Compound(stmts) => self.stmts(stmts),
+            StmtType::Expr(expr) => self.expr(expr),
+            StmtType::Return(expr) => {
+                if let Some(expr) = expr {
+                    self.expr(expr);
+                }
+            }
+            StmtType::If(condition, body, otherwise) => {
+                self.expr(condition);
+                self.stmt(body);
+                if let Some(otherwise) = otherwise {
+                    self.stmt(otherwise);
+                }
+            }
+            StmtType::While(condition, body)
+            | StmtType::Do(body, condition)
+            | StmtType::Switch(condition, body) => {
+                self.expr(condition);
+                self.stmt(body);
+            }
+            StmtType::For(init, condition, post, body) => {
+                self.stmt(init);
+                for expr in condition.iter().chain(post) {
+                    self.expr(expr);
+                }
+                self.stmt(body);
+            }
+            StmtType::Case(_, inner) | StmtType::Default(inner) | StmtType::Label(_, inner) => {
+                self.stmt(inner)
+            }
+            StmtType::Decl(decls) => {
+                for decl in decls {
+                    self.decl(&decl.data);
+                }
+            }
+            StmtType::Goto(_) | StmtType::Continue | StmtType::Break => {}
+        }
+    }
+    fn decl(&mut self, decl: &Declaration) {
+        let meta = decl.symbol.get();
+        if (meta.storage_class == StorageClass::Auto
+            || meta.storage_class == StorageClass::Register)
+            && meta.ctype.is_scalar()
+            && !meta.qualifiers.volatile
+        {
+            self.declared.insert(decl.symbol);
+        }
+        if let Some(init) = &decl.init {
+            self.initializer(init);
+        }
+    }
+    fn initializer(&mut self, init: &Initializer) {
+        match init {
+            Initializer::Scalar(expr) => self.expr(expr),
+            Initializer::InitializerList(inits) => {
+                for init in inits {
+                    self.initializer(init);
+                }
+            }
+            Initializer::FunctionBody(_) => {}
+        }
+    }
+    fn expr(&mut self, expr: &Expr) {
+        match &expr.expr {
+            ExprType::Id(symbol) => {
+                self.escaped.insert(*symbol);
+            }
+            ExprType::Deref(inner) => match &inner.expr {
+                ExprType::Id(symbol) => {
+                    self.read.insert(*symbol);
+                }
+                _ => self.expr(inner),
+            },
+            ExprType::Let(_, init, body) => {
+                // `x += 1` is `tmp = &x, *tmp = *tmp + 1`, which reads `x` without letting it escape
+                match &init.expr {
+                    ExprType::Id(symbol) => {
+                        self.read.insert(*symbol);
+                    }
+                    _ => self.expr(init),
+                }
+                self.expr(body);
+            }
+            ExprType::Binary(BinaryOp::Assign, target, value) => {
+                // storing to a variable doesn't let its address escape
+                if !matches!(target.expr, ExprType::Id(_)) {
+                    self.expr(target);
+                }
+                self.expr(value);
+            }
+            ExprType::Binary(_, left, right) | ExprType::Comma(left, right) => {
+                self.expr(left);
+                self.expr(right);
+            }
+            ExprType::Ternary(condition, then, otherwise) => {
+                self.expr(condition);
+                self.expr(then);
+                self.expr(otherwise);
+            }
+            ExprType::FuncCall(func, args) => {
+                self.expr(func);
+                for arg in args {
+                    self.expr(arg);
+                }
+            }
+            ExprType::Member(inner, _)
+            | ExprType::Cast(inner)
+            | ExprType::Negate(inner)
+            | ExprType::BitwiseNot(inner)
+            | ExprType::StaticRef(inner)
+            | ExprType::Noop(inner) => self.expr(inner),
+            ExprType::Literal(_) | ExprType::Sizeof(_) => {}
+        }
+    }
+}
+
+/// Whether evaluating `expr` does anything besides compute a value.
+fn has_side_effects(expr: &Expr) -> bool {
+    match &expr.expr {
+        ExprType::FuncCall(_, _) => true,
+        ExprType::Binary(BinaryOp::Assign, _, _) => true,
+        ExprType::Binary(_, left, right)
+        | ExprType::Comma(left, right)
+        | ExprType::Let(_, left, right) => has_side_effects(left) || has_side_effects(right),
+        ExprType::Ternary(condition, then, otherwise) => {
+            has_side_effects(condition) || has_side_effects(then) || has_side_effects(otherwise)
+        }
+        ExprType::Member(inner, _)
+        | ExprType::Cast(inner)
+        | ExprType::Negate(inner)
+        | ExprType::BitwiseNot(inner)
+        | ExprType::StaticRef(inner)
+        | ExprType::Deref(inner)
+        | ExprType::Noop(inner) => has_side_effects(inner),
+        ExprType::Id(_) | ExprType::Literal(_) | ExprType::Sizeof(_) => false,
+    }
+}
+
+/// An assignment to a tracked variable.
+struct Store {
+    symbol: Symbol,
+    location: Location,
+    /// Whether the variable was live after the assignment the last time it was visited
+    dead: bool,
+    side_effects: bool,
+}
+
+#[derive(Default)]
+struct Checker<'hir> {
+    tracked: HashSet<Symbol>,
+    /// Each assignment to a tracked variable, from the last time it was visited.
+    ///
+    /// Since live sets only grow with each iteration, the last visit is the one that counts.
+    /// The HIR isn't modified while it's being checked, so expressions are identified by address.
+    stores: HashMap<*const Expr<'hir>, Store>,
+    /// The variables live at the start of each loop the last time the loop was checked.
+    ///
+    /// Starting from here instead of from scratch keeps nested loops from taking exponential time.
+    loop_heads: HashMap<*const Stmt<'hir>, Live>,
+    /// The variables live after each enclosing loop or `switch`
+    breaks: Vec<Live>,
+    /// The variables live at the next iteration of each enclosing loop
+    continues: Vec<Live>,
+    /// The joined variables live at each `case` or `default` label of each enclosing `switch`,
+    /// and whether it has a `default` label
+    switches: Vec<(Live, bool)>,
+    /// The variables live at each label
+    labels: HashMap<InternedStr, Live>,
+}
+
+impl<'hir> Checker<'hir> {
+    /// Turn the variables live after `stmts` into the variables live before them.
+    fn stmts(&mut self, stmts: &[Stmt<'hir>], live: &mut Live) {
+        for stmt in stmts.iter().rev() {
+            self.stmt(stmt, live);
+        }
+    }
+    fn stmt(&mut self, stmt: &Stmt<'hir>, live: &mut Live) {
+        match &stmt.data {
+            StmtType::Compound(stmts) => self.stmts(stmts, live),
+            StmtType::Expr(expr) => self.expr(expr, live),
+            StmtType::Decl(decls) => {
+                for decl in decls.iter().rev() {
+                    self.decl(&decl.data, live);
+                }
+            }
+            StmtType::If(condition, body, otherwise) => {
+                let mut otherwise_live = live.clone();
+                if let Some(otherwise) = otherwise {
+                    self.stmt(otherwise, &mut otherwise_live);
+                }
+                self.stmt(body, live);
+                live.extend(otherwise_live);
+                self.expr(condition, live);
+            }
+            StmtType::While(condition, body) => {
+                let exit = live.clone();
+                *live = self.fixpoint(stmt, |this, head| {
+                    this.breaks.push(exit.clone());
+                    this.continues.push(head.clone());
+                    let mut body_live = head.clone();
+                    this.stmt(body, &mut body_live);
+                    this.continues.pop();
+                    this.breaks.pop();
+                    body_live.extend(exit.iter().copied());
+                    this.expr(condition, &mut body_live);
+                    body_live
+                });
+            }
+            StmtType::For(init, condition, post, body) => {
+                let exit = live.clone();
+                *live = self.fixpoint(stmt, |this, head| {
+                    let mut next = head.clone();
+                    if let Some(post) = post {
+                        this.expr(post, &mut next);
+                    }
+                    this.breaks.push(exit.clone());
+                    this.continues.push(next.clone());
+                    this.stmt(body, &mut next);
+                    this.continues.pop();
+                    this.breaks.pop();
+                    // `for (;;)` can only be left with `break`
+                    if let Some(condition) = condition {
+                        next.extend(exit.iter().copied());
+                        this.expr(condition, &mut next);
+                    }
+                    next
+                });
+                self.stmt(init, live);
+            }
+            StmtType::Do(body, condition) => {
+                let exit = live.clone();
+                *live = self.fixpoint(stmt, |this, head| {
+                    let mut next = head.clone();
+                    next.extend(exit.iter().copied());
+                    this.expr(condition, &mut next);
+                    this.breaks.push(exit.clone());
+                    this.continues.push(next.clone());
+                    this.stmt(body, &mut next);
+                    this.continues.pop();
+                    this.breaks.pop();
+                    next
+                });
+            }
+            StmtType::Switch(condition, body) => {
+                self.breaks.push(live.clone());
+                self.switches.push((Live::new(), false));
+                let mut body_live = live.clone();
+                self.stmt(body, &mut body_live);
+                let (labels, has_default) = self.switches.pop().unwrap();
+                self.breaks.pop();
+                // the body can only be entered through a `case` or `default` label
+                if !has_default {
+                    live.extend(labels);
+                } else {
+                    *live = labels;
+                }
+                self.expr(condition, live);
+            }
+            StmtType::Case(_, inner) | StmtType::Default(inner) => {
+                self.stmt(inner, live);
+                if let Some((labels, has_default)) = self.switches.last_mut() {
+                    if let StmtType::Default(_) = stmt.data {
+                        *has_default = true;
+                    }
+                    labels.extend(live.iter().copied());
+                }
+            }
+            StmtType::Label(name, inner) => {
+                self.stmt(inner, live);
+                self.labels.insert(*name, live.clone());
+            }
+            StmtType::Goto(name) => {
+                *live = self.labels.get(name).cloned().unwrap_or_default();
+            }
+            StmtType::Continue => *live = self.continues.last().cloned().unwrap_or_default(),
+            StmtType::Break => *live = self.breaks.last().cloned().unwrap_or_default(),
+            StmtType::Return(expr) => {
+                live.clear();
+                if let Some(expr) = expr {
+                    self.expr(expr, live);
+                }
+            }
+        }
+    }
+    /// Run a loop until the variables live at its start stop changing.
+    ///
+    /// `once` takes the variables live at the start of the next iteration
+    /// and returns the ones live at the start of this iteration.
+    fn fixpoint<F>(&mut self, stmt: &Stmt<'hir>, mut once: F) -> Live
+    where
+        F: FnMut(&mut Self, &Live) -> Live,
+    {
+        let mut head = self
+            .loop_heads
+            .get(&(stmt as *const _))
+            .cloned()
+            .unwrap_or_default();
+        loop {
+            let mut next = once(self, &head);
+            next.extend(head.iter().copied());
+            if next == head {
+                self.loop_heads.insert(stmt, head.clone());
+                return head;
+            }
+            head = next;
+        }
+    }
+    fn decl(&mut self, decl: &Declaration<'hir>, live: &mut Live) {
+        // the variable doesn't exist before it's declared
+        live.remove(&decl.symbol);
+        if let Some(init) = &decl.init {
+            self.initializer(init, live);
+        }
+    }
+    fn initializer(&mut self, init: &Initializer<'hir>, live: &mut Live) {
+        match init {
+            Initializer::Scalar(expr) => self.expr(expr, live),
+            Initializer::InitializerList(inits) => {
+                for init in inits.iter().rev() {
+                    self.initializer(init, live);
+                }
+            }
+            Initializer::FunctionBody(_) => {}
+        }
+    }
+    /// Turn the variables live after evaluating `expr` into the variables live before it.
+    fn expr(&mut self, expr: &Expr<'hir>, live: &mut Live) {
+        match &expr.expr {
+            ExprType::Deref(inner) => match &inner.expr {
+                ExprType::Id(symbol) => self.read(*symbol, live),
+                _ => self.expr(inner, live),
+            },
+            ExprType::Binary(BinaryOp::Assign, target, value) => {
+                match target.expr {
+                    ExprType::Id(symbol) if self.tracked.contains(&symbol) => {
+                        let store = Store {
+                            symbol,
+                            location: expr.location,
+                            dead: !live.contains(&symbol),
+                            side_effects: has_side_effects(value),
+                        };
+                        self.stores.insert(expr, store);
+                        live.remove(&symbol);
+                    }
+                    _ => self.expr(target, live),
+                }
+                self.expr(value, live);
+            }
+            ExprType::Binary(BinaryOp::LogicalAnd, left, right)
+            | ExprType::Binary(BinaryOp::LogicalOr, left, right) => {
+                let mut right_live = live.clone();
+                self.expr(right, &mut right_live);
+                live.extend(right_live);
+                self.expr(left, live);
+            }
+            ExprType::Binary(_, left, right) | ExprType::Comma(left, right) => {
+                self.expr(right, live);
+                self.expr(left, live);
+            }
+            ExprType::Ternary(condition, then, otherwise) => {
+                let mut otherwise_live = live.clone();
+                self.expr(otherwise, &mut otherwise_live);
+                self.expr(then, live);
+                live.extend(otherwise_live);
+                self.expr(condition, live);
+            }
+            ExprType::Let(_, init, body) => {
+                self.expr(body, live);
+                match init.expr {
+                    ExprType::Id(symbol) => self.read(symbol, live),
+                    _ => self.expr(init, live),
+                }
+            }
+            ExprType::FuncCall(func, args) => {
+                for arg in args.iter().rev() {
+                    self.expr(arg, live);
+                }
+                self.expr(func, live);
+            }
+            ExprType::Member(inner, _)
+            | ExprType::Cast(inner)
+            | ExprType::Negate(inner)
+            | ExprType::BitwiseNot(inner)
+            | ExprType::StaticRef(inner)
+            | ExprType::Noop(inner) => self.expr(inner, live),
+            ExprType::Id(_) | ExprType::Literal(_) | ExprType::Sizeof(_) => {}
+        }
+    }
+    fn read(&mut self, symbol: Symbol, live: &mut Live) {
+        if self.tracked.contains(&symbol) {
+            live.insert(symbol);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analyze::{test::arena, Analyzer};
+    use crate::data::error::Warning;
+    use crate::parse::test::parser;
+
+    fn dead_stores(body: &str) -> Vec<Warning> {
+        let program = format!("int g(int *); int f(int cond) {{ {} }}", body);
+        let mut a = Analyzer::new(parser(&program), arena(), false);
+        for res in &mut a {
+            res.unwrap();
+        }
+        a.inner
+            .error_handler
+            .warnings
+            .into_iter()
+            .map(|w| w.data)
+            .filter(|w| w.name() == "dead-store")
+            .collect()
+    }
+
+    #[test]
+    fn test_dead_store() {
+        let x = |side_effects| vec![Warning::DeadStore("x".into(), side_effects)];
+        assert_eq!(dead_stores("int x; x = g(0); x = 0; return x;"), x(true));
+        assert_eq!(dead_stores("int x; x = 1; x = 2; return x;"), x(false));
+        assert_eq!(
+            dead_stores("int x = 0; while (cond) { x = 1; x = cond; } return x;"),
+            x(false)
+        );
+        assert_eq!(
+            dead_stores("int x = cond; cond = x; x = 1; return cond;"),
+            x(false)
+        );
+        assert_eq!(
+            dead_stores("int x; x = 1; if (cond) return 0; x = 2; return x;"),
+            x(false)
+        );
+        for live in &[
+            // volatile variables might be read by something else
+            "volatile int x; x = 1; x = 2; return x;",
+            // `g` could save the address and read through it later
+            "int x; g(&x); x = 1; x = 2; return x;",
+            "int x; int *p = &x; x = 1; return *p;",
+            "int x; x = 1; if (cond) x = 2; return x;",
+            "int x; x = 1; while (cond) { cond = x; x = 2; } return 0;",
+            "int x; for (x = 0; x < cond; x++) { } return 0;",
+            "int x; x = 1; x += 2; return x;",
+            "int x; x = 1; switch (cond) { case 1: return x; default: x = 2; } return x;",
+            "int x; x = 1; goto use; x = 2; use: return x;",
+            "int x; x = 1; cond && (x = 2); return x;",
+            // initializers aren't checked
+            "int x = 0; x = 1; return x;",
+            // only assigned, so `-Wunused-but-set-variable` warns instead
+            "int x; x = 1; return 0;",
+        ] {
+            assert_eq!(dead_stores(live), vec![], "{}", live);
+        }
+    }
+}
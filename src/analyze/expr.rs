@@ -1,23 +1,46 @@
-use super::PureAnalyzer;
+use super::{PureAnalyzer, BUILTIN_UNREACHABLE};
 use crate::arch;
-use crate::data::{hir::*, lex::ComparisonToken, *};
+use crate::data::{
+    error::{NotAssignable, Warning},
+    hir::*,
+    lex::ComparisonToken,
+    types::{FunctionType, StructType},
+    *,
+};
+use crate::ice;
 use crate::intern::InternedStr;
+use crate::stats::{self, Counter};
 
-impl PureAnalyzer {
-    pub fn expr(&mut self, expr: ast::Expr) -> Expr {
+/// `__func__` and its GNU aliases
+const FUNC_NAMES: &[&str] = &["__func__", "__FUNCTION__", "__PRETTY_FUNCTION__"];
+
+impl<'hir> PureAnalyzer<'hir> {
+    pub fn expr(&mut self, expr: ast::Expr) -> Expr<'hir> {
         use ast::ExprType::*;
 
         let _guard = self.recursion_check();
         let _guard2 = self.recursion_check();
+        ice::set_location(expr.location);
+        stats::count(Counter::ExpressionsAnalyzed);
         match expr.data {
+            // L"str"
+            Literal(lit @ crate::data::lex::Literal::WideStr(..)) => {
+                self.wide_string(lit, expr.location)
+            }
             // 1 | "str" | 'a'
             Literal(lit) => literal(lit, expr.location),
             // x
-            Id(id) => self.parse_id(id, expr.location),
+            Id(id) => {
+                let id = self.parse_id(id, expr.location);
+                if let ExprType::Id(symbol) = id.expr {
+                    self.read.insert(symbol);
+                }
+                id
+            }
             // (int)x
             Cast(ctype, inner) => {
                 let ctype = self.parse_typename(ctype, expr.location);
-                self.explicit_cast(*inner, ctype)
+                self.explicit_cast(*inner, ctype, expr.location)
             }
             Shift(left, right, direction) => {
                 let op = if direction {
@@ -25,106 +48,73 @@ impl PureAnalyzer {
                 } else {
                     BinaryOp::Shr
                 };
-                self.binary_helper(left, right, op, Self::parse_integer_op)
+                self.binary_helper(left, right, op, expr.location, Self::parse_integer_op)
+            }
+            BitwiseAnd(left, right) => self.binary_helper(
+                left,
+                right,
+                BinaryOp::BitwiseAnd,
+                expr.location,
+                Self::parse_integer_op,
+            ),
+            BitwiseOr(left, right) => self.binary_helper(
+                left,
+                right,
+                BinaryOp::BitwiseOr,
+                expr.location,
+                Self::parse_integer_op,
+            ),
+            Xor(left, right) => self.binary_helper(
+                left,
+                right,
+                BinaryOp::Xor,
+                expr.location,
+                Self::parse_integer_op,
+            ),
+            Compare(left, right, token) => {
+                self.relational_expr(*left, *right, token, expr.location)
             }
-            BitwiseAnd(left, right) => {
-                self.binary_helper(left, right, BinaryOp::BitwiseAnd, Self::parse_integer_op)
+            Mul(left, right) => {
+                self.binary_helper(left, right, BinaryOp::Mul, expr.location, Self::mul)
             }
-            BitwiseOr(left, right) => {
-                self.binary_helper(left, right, BinaryOp::BitwiseOr, Self::parse_integer_op)
+            Div(left, right) => {
+                self.binary_helper(left, right, BinaryOp::Div, expr.location, Self::mul)
             }
-            Xor(left, right) => {
-                self.binary_helper(left, right, BinaryOp::Xor, Self::parse_integer_op)
+            Mod(left, right) => {
+                self.binary_helper(left, right, BinaryOp::Mod, expr.location, Self::mul)
             }
-            Compare(left, right, token) => self.relational_expr(*left, *right, token),
-            Mul(left, right) => self.binary_helper(left, right, BinaryOp::Mul, Self::mul),
-            Div(left, right) => self.binary_helper(left, right, BinaryOp::Div, Self::mul),
-            Mod(left, right) => self.binary_helper(left, right, BinaryOp::Mod, Self::mul),
             Assign(lval, rval, token) => {
-                let lval = self.expr(*lval);
+                let lval = match *lval {
+                    // `x = 1` writes to `x`, but doesn't use its value
+                    ast::Expr {
+                        data: Id(id),
+                        location,
+                    } if token.data == lex::AssignmentToken::Equal => {
+                        let lval = self.parse_id(id, location);
+                        if let ExprType::Id(symbol) = lval.expr {
+                            self.written.insert(symbol);
+                        }
+                        lval
+                    }
+                    lval => self.expr(lval),
+                };
                 let rval = self.expr(*rval);
                 self.assignment_expr(lval, rval, token, expr.location)
             }
-            Add(left, right) => self.binary_helper(left, right, BinaryOp::Add, Self::add),
-            Sub(left, right) => self.binary_helper(left, right, BinaryOp::Sub, Self::add),
-            FuncCall(func, args) => self.func_call(*func, args),
-            Member(struct_, id) => {
-                let struct_ = self.expr(*struct_);
-                self.struct_member(struct_, id, expr.location)
+            Add(left, right) => {
+                self.binary_helper(left, right, BinaryOp::Add, expr.location, Self::add)
             }
-            // s->p desguars to (*s).p
-            DerefMember(inner, id) => {
-                let inner = self.expr(*inner);
-                let struct_type = match &inner.ctype {
-                    Type::Pointer(ctype, _) => match &**ctype {
-                        Type::Union(_) | Type::Struct(_) => (**ctype).clone(),
-                        other => {
-                            self.err(SemanticError::NotAStruct(other.clone()), inner.location);
-                            return inner;
-                        }
-                    },
-                    other => {
-                        self.err(SemanticError::NotAPointer(other.clone()), inner.location);
-                        return inner;
-                    }
-                };
-                // NOTE: when we pass `deref` to `struct_member`,
-                // it will always mark the resulting expression as an `lval`.
-                // To avoid a double dereference, we mark `deref` as an `rval`.
-                let deref = inner.indirection(false, struct_type);
-                self.struct_member(deref, id, expr.location)
+            Sub(left, right) => {
+                self.binary_helper(left, right, BinaryOp::Sub, expr.location, Self::add)
             }
+            FuncCall(func, args) => self.func_call(*func, args, expr.location),
+            Member(struct_, member) => self.member(*struct_, member, expr.location),
+            // s->p desguars to (*s).p
+            DerefMember(inner, member) => self.deref_member(*inner, member, expr.location),
             // `*p` or `a[i]`
-            Deref(inner) => {
-                let inner = self.expr(*inner);
-                match &inner.ctype {
-                    Type::Array(t, _) | Type::Pointer(t, _) => {
-                        let ctype = (**t).clone();
-                        inner.indirection(true, ctype)
-                    }
-                    _ => {
-                        self.err(
-                            SemanticError::NotAPointer(inner.ctype.clone()),
-                            expr.location,
-                        );
-                        inner
-                    }
-                }
-            }
+            Deref(inner) => self.deref(*inner, expr.location),
             // &x
-            // 6.5.3.2 Address and indirection operators
-            AddressOf(inner) => {
-                let inner = self.expr(*inner);
-                match inner.expr {
-                    // parse &*x as x
-                    // footnote 102: &*E is equivalent to E (even if E is a null pointer)
-                    ExprType::Deref(double_inner) => *double_inner,
-                    // footnote 121:
-                    // > the address of any part of an object declared with storage-class specifier register cannot be computed,
-                    // > either explicitly (by use of the unary & operator as discussed in 6.5.3.2)
-                    // > or implicitly (by converting an array name to a pointer as discussed in 6.3.2.1).
-                    ExprType::Id(ref sym) if sym.get().storage_class == StorageClass::Register => {
-                        self.err(
-                            SemanticError::InvalidAddressOf("variable declared with `register`"),
-                            expr.location,
-                        );
-                        inner
-                    }
-                    // > The operand of the unary & operator shall be either a function designator,
-                    // > the result of a [] or unary * operator,
-                    // > or an lvalue that designates an object that is not a bit-field and is not declared with the register storage-class specifier.
-                    _ if inner.lval => Expr {
-                        lval: false,
-                        location: expr.location,
-                        ctype: Type::Pointer(Box::new(inner.ctype.clone()), Qualifiers::default()),
-                        expr: inner.expr,
-                    },
-                    _ => {
-                        self.err(SemanticError::InvalidAddressOf("value"), expr.location);
-                        inner
-                    }
-                }
-            }
+            AddressOf(inner) => self.address_of(*inner, expr.location),
             // ++x
             PreIncrement(inner, increment) => {
                 self.increment_op(true, increment, *inner, expr.location)
@@ -149,61 +139,229 @@ impl PureAnalyzer {
             }
             SizeofExpr(inner) => {
                 let inner = self.expr(*inner);
+                // void f(int a[10]) { sizeof(a); }
+                if let ExprType::Id(symbol) = inner.expr {
+                    if let Some(original) = self.array_params.get(&symbol) {
+                        let warning =
+                            Warning::SizeofArrayArgument(original.clone(), inner.ctype.clone());
+                        self.warn(warning, expr.location);
+                    }
+                }
                 self.sizeof(inner.ctype, expr.location)
             }
-            BitwiseNot(inner) => self.bitwise_not(*inner),
+            BitwiseNot(inner) => self.bitwise_not(*inner, expr.location),
             UnaryPlus(inner) => self.unary_add(*inner, true, expr.location),
             Negate(inner) => self.unary_add(*inner, false, expr.location),
             // !x
-            LogicalNot(inner) => self.logical_not(*inner),
+            LogicalNot(inner) => self.logical_not(*inner, expr.location),
             // x && y
-            LogicalAnd(left, right) => {
-                self.binary_helper(left, right, BinaryOp::LogicalAnd, Self::logical_bin_op)
-            }
+            LogicalAnd(left, right) => self.binary_helper(
+                left,
+                right,
+                BinaryOp::LogicalAnd,
+                expr.location,
+                Self::logical_bin_op,
+            ),
             // x || y
-            LogicalOr(left, right) => {
-                self.binary_helper(left, right, BinaryOp::LogicalOr, Self::logical_bin_op)
-            }
+            LogicalOr(left, right) => self.binary_helper(
+                left,
+                right,
+                BinaryOp::LogicalOr,
+                expr.location,
+                Self::logical_bin_op,
+            ),
             // x, y
             // evaluate x, discarding its value, then yield the value of y
             // mostly used to have multiple side effects in a single statement, such as in a for loop:
             // `for(j = i, k = 0; k < n; j++, k++);`
             // see also https://stackoverflow.com/a/43561555/7669110
             Comma(left, right) => {
-                let left = self.expr(*left);
-                let right = self.expr(*right).rval();
+                let left = self.expr(*left).discard(self.arena);
+                let right = self.expr(*right).rval(self.arena);
                 Expr {
                     ctype: right.ctype.clone(),
                     lval: false,
-                    expr: ExprType::Comma(Box::new(left), Box::new(right)),
+                    expr: ExprType::Comma(self.arena.expr(left), self.arena.expr(right)),
                     location: expr.location,
                 }
             }
-            Ternary(condition, then, otherwise) => {
+            Ternary(condition, Some(then), otherwise) => {
                 self.ternary(*condition, *then, *otherwise, expr.location)
             }
+            Ternary(condition, None, otherwise) => {
+                self.omitted_ternary(*condition, *otherwise, expr.location)
+            }
+        }
+    }
+    // s.a
+    // 6.5.2.3 Structure and union members
+    fn member(
+        &mut self,
+        struct_: ast::Expr,
+        member: Locatable<InternedStr>,
+        location: Location,
+    ) -> Expr<'hir> {
+        let Locatable {
+            data: id,
+            location: op_location,
+        } = member;
+        let struct_ = self.expr(struct_);
+        // p.a where p is a pointer; treat it as p->a so we don't report more errors
+        if let Type::Pointer(ctype, _) = &struct_.ctype {
+            if let Type::Struct(_) | Type::Union(_) = &**ctype {
+                let struct_type = (**ctype).clone();
+                self.err(
+                    SemanticError::MemberOfPointer(struct_.ctype.clone()),
+                    op_location,
+                );
+                let deref = struct_.indirection(false, struct_type, self.arena);
+                return self.struct_member(deref, id, location);
+            }
+        }
+        self.struct_member(struct_, id, location)
+    }
+    // s->a
+    fn deref_member(
+        &mut self,
+        inner: ast::Expr,
+        member: Locatable<InternedStr>,
+        location: Location,
+    ) -> Expr<'hir> {
+        let Locatable {
+            data: id,
+            location: op_location,
+        } = member;
+        let inner = self.expr(inner);
+        let struct_type = match &inner.ctype {
+            Type::Pointer(ctype, _) => match &**ctype {
+                Type::Union(_) | Type::Struct(_) => (**ctype).clone(),
+                other => {
+                    self.err(SemanticError::NotAStruct(other.clone()), inner.location);
+                    return Expr::error(location);
+                }
+            },
+            // s->a where s is a struct; treat it as s.a
+            Type::Struct(_) | Type::Union(_) => {
+                self.err(
+                    SemanticError::NotAStructPointer(inner.ctype.clone()),
+                    op_location,
+                );
+                return self.struct_member(inner, id, location);
+            }
+            Type::Error => return Expr::error(location),
+            other => {
+                self.err(SemanticError::NotAPointer(other.clone()), inner.location);
+                return Expr::error(location);
+            }
+        };
+        // NOTE: when we pass `deref` to `struct_member`,
+        // it will always mark the resulting expression as an `lval`.
+        // To avoid a double dereference, we mark `deref` as an `rval`.
+        let deref = inner.indirection(false, struct_type, self.arena);
+        self.struct_member(deref, id, location)
+    }
+    // *p
+    // 6.5.3.2 Address and indirection operators
+    fn deref(&mut self, inner: ast::Expr, location: Location) -> Expr<'hir> {
+        let inner = self.expr(inner);
+        match &inner.ctype {
+            Type::Pointer(t, _) if **t == Type::Void => {
+                self.err(
+                    SemanticError::VoidDereference(inner.ctype.clone()),
+                    location,
+                );
+                Expr::error(location)
+            }
+            Type::Array(t, _) | Type::Pointer(t, _) => {
+                let ctype = (**t).clone();
+                inner.indirection(true, ctype, self.arena)
+            }
+            Type::Error => Expr::error(location),
+            _ => {
+                self.err(SemanticError::NotAPointer(inner.ctype.clone()), location);
+                Expr::error(location)
+            }
+        }
+    }
+    // &x
+    // 6.5.3.2 Address and indirection operators
+    fn address_of(&mut self, inner: ast::Expr, location: Location) -> Expr<'hir> {
+        // footnote 102: &*E is equivalent to E (even if E is a null pointer),
+        // so `E` may point to `void` or an incomplete type
+        if let ast::ExprType::Deref(pointer) = inner.data {
+            return self.address_of_deref(*pointer, location);
+        }
+        let inner = self.expr(inner);
+        if inner.is_error() {
+            return Expr::error(location);
+        }
+        match inner.expr {
+            // parse &*x as x
+            // footnote 102: &*E is equivalent to E (even if E is a null pointer)
+            ExprType::Deref(double_inner) => double_inner.take(),
+            // &a[i] is a + i; the pointer was never dereferenced
+            ExprType::Noop(pointer) if inner.lval => Expr {
+                lval: false,
+                location,
+                ..pointer.take()
+            },
+            // footnote 121:
+            // > the address of any part of an object declared with storage-class specifier register cannot be computed,
+            // > either explicitly (by use of the unary & operator as discussed in 6.5.3.2)
+            // > or implicitly (by converting an array name to a pointer as discussed in 6.3.2.1).
+            ExprType::Id(ref sym) if sym.get().storage_class == StorageClass::Register => {
+                self.err(
+                    SemanticError::InvalidAddressOf("variable declared with `register`"),
+                    location,
+                );
+                Expr::error(location)
+            }
+            // > The operand of the unary & operator shall be either a function designator,
+            // > the result of a [] or unary * operator,
+            // > or an lvalue that designates an object that is not a bit-field and is not declared with the register storage-class specifier.
+            _ if inner.lval && !inner.is_member_of_rvalue() => Expr {
+                lval: false,
+                location,
+                ctype: Type::Pointer(Box::new(inner.ctype.clone()), Qualifiers::default()),
+                expr: inner.expr,
+            },
+            _ => {
+                self.err(SemanticError::InvalidAddressOf("value"), location);
+                Expr::error(location)
+            }
         }
     }
     // only meant for use with `expr`
     // TODO: change ast::Expr to use `ExprType::Binary` as well, which would make this unnecessary
-    // TODO: these functions should have the locations of the parent expression, not the children
+    //
+    // `location` is the location of the whole expression, including any parentheses around the operands
     #[allow(clippy::boxed_local)]
     fn binary_helper<F>(
         &mut self,
         left: Box<ast::Expr>,
         right: Box<ast::Expr>,
         op: BinaryOp,
+        location: Location,
         expr_checker: F,
-    ) -> Expr
+    ) -> Expr<'hir>
     where
-        F: FnOnce(&mut Self, Expr, Expr, BinaryOp) -> Expr,
+        F: FnOnce(&mut Self, Expr<'hir>, Expr<'hir>, BinaryOp, Location) -> Expr<'hir>,
     {
         let left = self.expr(*left);
         let right = self.expr(*right);
-        expr_checker(self, left, right, op)
+        expr_checker(self, left, right, op, location)
     }
     // left OP right, where OP is an operation that requires integral types
-    fn parse_integer_op(&mut self, left: Expr, right: Expr, op: BinaryOp) -> Expr {
+    fn parse_integer_op(
+        &mut self,
+        left: Expr<'hir>,
+        right: Expr<'hir>,
+        op: BinaryOp,
+        location: Location,
+    ) -> Expr<'hir> {
+        if left.is_error() || right.is_error() {
+            return Expr::error(location);
+        }
         let non_scalar = if !left.ctype.is_integral() {
             Some(&left.ctype)
         } else if !right.ctype.is_integral() {
@@ -211,58 +369,111 @@ impl PureAnalyzer {
         } else {
             None
         };
-        let location = left.location.merge(right.location);
         if let Some(ctype) = non_scalar {
             self.err(SemanticError::NonIntegralExpr(ctype.clone()), location);
+            return Expr::error(location);
         }
-        let (promoted_expr, next) = Expr::binary_promote(left, right, &mut self.error_handler);
+        let (promoted_expr, next) =
+            Expr::binary_promote(left, right, self.arena, &mut self.error_handler);
         Expr {
             ctype: next.ctype.clone(),
-            expr: ExprType::Binary(op, Box::new(promoted_expr), Box::new(next)),
+            expr: ExprType::Binary(op, self.arena.expr(promoted_expr), self.arena.expr(next)),
             lval: false,
             location,
         }
     }
+    /// The variable in scope with the name closest to `name`, if it's close enough to be a typo.
+    fn similar_name(&self, name: InternedStr) -> Option<InternedStr> {
+        let name = name.resolve_and_clone();
+        // allow one typo for every three characters
+        let max_distance = std::cmp::max(name.chars().count() / 3, 1);
+        self.scope
+            .iter()
+            .filter(|(_, symbol)| symbol.get().storage_class != StorageClass::Typedef)
+            // unnamed parameters are in scope with an empty name
+            .filter(|(candidate, _)| **candidate != InternedStr::default())
+            .map(|(&candidate, _)| {
+                let candidate = candidate.resolve_and_clone();
+                (edit_distance(&name, &candidate), candidate)
+            })
+            .filter(|(distance, _)| *distance <= max_distance)
+            // break ties by name so the suggestion doesn't depend on the order of the HashMap
+            .min()
+            .map(|(_, candidate)| InternedStr::get_or_intern(candidate))
+    }
     // x
-    fn parse_id(&mut self, name: InternedStr, location: Location) -> Expr {
-        let mut pretend_zero = Expr::zero(location);
-        pretend_zero.ctype = Type::Error;
+    fn parse_id(&mut self, name: InternedStr, location: Location) -> Expr<'hir> {
         match self.scope.get(&name) {
+            None if FUNC_NAMES.contains(&name.resolve_and_clone().as_str()) => {
+                self.func_name(name, location)
+            }
             None => {
-                self.err(SemanticError::UndeclaredVar(name), location);
-                pretend_zero
+                let similar = self.similar_name(name);
+                self.err(SemanticError::UndeclaredVar(name, similar), location);
+                Expr::error(location)
             }
             Some(&symbol) => {
+                self.references.record_use(location, symbol);
                 let meta = symbol.get();
                 // typedef int i; return i + 1;
                 if meta.storage_class == StorageClass::Typedef {
                     self.err(SemanticError::TypedefInExpressionContext, location);
-                    return pretend_zero;
+                    return Expr::error(location);
                 }
-                if let Type::Enum(ident, members) = &meta.ctype {
-                    let mapper = |(member, value): &(InternedStr, i64)| {
-                        if name == *member {
-                            Some(*value)
-                        } else {
-                            None
-                        }
+                // enum e { A }; return A;
+                if let Some(&value) = self.enumerators.get(&symbol) {
+                    return Expr {
+                        ctype: meta.ctype.clone(),
+                        location,
+                        lval: false,
+                        expr: ExprType::Literal(Literal::Int(value)),
                     };
-                    let enumerator = members.iter().find_map(mapper);
-                    // enum e { A }; return A;
-                    if let Some(e) = enumerator {
-                        return Expr {
-                            ctype: Type::Enum(*ident, members.clone()),
-                            location,
-                            lval: false,
-                            expr: ExprType::Literal(Literal::Int(e)),
-                        };
-                    }
-                    // otherwise, `enum e { A } my_e; return my_e;`
                 }
+                self.warn_deprecated(symbol, location);
                 Expr::id(symbol, location)
             }
         }
     }
+    // 6.4.2.2 Predefined identifiers
+    // > The identifier __func__ shall be implicitly declared by the translator as if,
+    // > immediately following the opening brace of each function definition, the declaration
+    // > `static const char __func__[] = "function-name";` appeared
+    //
+    // The declaration is added to the start of the function once it's analyzed,
+    // so every use in the same function refers to the same array.
+    fn func_name(&mut self, name: InternedStr, location: Location) -> Expr<'hir> {
+        let func = match self.current_function {
+            Some(func) => func,
+            None => {
+                self.err(SemanticError::FuncNameOutsideFunction(name), location);
+                return Expr::error(location);
+            }
+        };
+        if let Some(decl) = &self.func_name {
+            return Expr::id(decl.data.symbol, location);
+        }
+        let mut string = func.resolve_and_clone().into_bytes();
+        string.push(b'\0');
+        let init = literal(Literal::Str(string), location);
+        let meta = Variable {
+            id: InternedStr::get_or_intern(FUNC_NAMES[0]),
+            ctype: init.ctype.clone(),
+            qualifiers: Qualifiers {
+                c_const: true,
+                ..Qualifiers::default()
+            },
+            storage_class: StorageClass::Static,
+            location,
+        };
+        let symbol = meta.insert();
+        let decl = Declaration {
+            symbol,
+            init: Some(Initializer::Scalar(self.arena.expr(init))),
+            attributes: LinkAttributes::default(),
+        };
+        self.func_name = Some(Locatable::new(decl, location));
+        Expr::id(symbol, location)
+    }
     // `left == right`, `left < right`, or similar
     // 6.5.9 Equality operators
     fn relational_expr(
@@ -270,20 +481,42 @@ impl PureAnalyzer {
         left: ast::Expr,
         right: ast::Expr,
         token: ComparisonToken,
-    ) -> Expr {
-        let location = left.location.merge(right.location);
+        location: Location,
+    ) -> Expr<'hir> {
         let mut left = self.expr(left);
         let mut right = self.expr(right);
+        if left.is_error() || right.is_error() {
+            return Expr::error(location);
+        }
 
         // i == i
         if left.ctype.is_arithmetic() && right.ctype.is_arithmetic() {
-            let tmp = Expr::binary_promote(left, right, &mut self.error_handler);
+            // `~c == 0xFF` is never true for an `unsigned char` `c`
+            let unsigned = |expr: &Expr| {
+                expr.ctype.is_integral() && !expr.ctype.is_signed()
+                    || matches!(expr.expr, ExprType::Literal(Literal::Int(i)) if i >= 0)
+                    || matches!(expr.expr, ExprType::Literal(Literal::UnsignedInt(_)))
+            };
+            for &(value, other) in &[(&left, &right), (&right, &left)] {
+                if promoted_bitwise_operand(value).is_some()
+                    && promoted_bitwise_operand(other).is_none()
+                    && unsigned(other)
+                {
+                    self.warn_promoted_bitwise(value, value.to_c_string(), location);
+                }
+            }
+            let tmp = Expr::binary_promote(left, right, self.arena, &mut self.error_handler);
             left = tmp.0;
             right = tmp.1;
         } else {
-            let (left_expr, right_expr) = (left.rval(), right.rval());
-            // p1 == p2
-            if !((left_expr.ctype.is_pointer() && left_expr.ctype == right_expr.ctype)
+            // lvalue conversion, and decay of arrays and functions to pointers
+            let (left_expr, right_expr) = (left.rval(self.arena), right.rval(self.arena));
+            let ordered =
+                token != ComparisonToken::EqualEqual && token != ComparisonToken::NotEqual;
+            // p1 == p2, a < a + 2
+            // ordered comparisons are only defined for pointers into the same array,
+            // but that can't be checked statically, so any compatible pointers are allowed
+            if !(left_expr.ctype.is_compatible_pointer(&right_expr.ctype)
                 // equality operations have different rules :(
                 || ((token == ComparisonToken::EqualEqual || token == ComparisonToken::NotEqual)
                     // shoot me now
@@ -296,111 +529,219 @@ impl PureAnalyzer {
                         // (int*)p1 == NULL
                         || (left_expr.ctype.is_pointer() && right_expr.is_null()))))
             {
-                self.err(
-                    SemanticError::InvalidRelationalType(
-                        token,
-                        left_expr.ctype.clone(),
-                        right_expr.ctype.clone(),
-                    ),
-                    location,
-                );
+                // (int*)p1 == (char*)p2
+                if distinct_object_pointers(&left_expr.ctype, &right_expr.ctype) {
+                    self.warn(
+                        Warning::DistinctPointerComparison(
+                            left_expr.ctype.clone(),
+                            right_expr.ctype.clone(),
+                        ),
+                        location,
+                    );
+                } else {
+                    self.err(
+                        SemanticError::InvalidRelationalType(
+                            token,
+                            left_expr.ctype.clone(),
+                            right_expr.ctype.clone(),
+                        ),
+                        location,
+                    );
+                }
+            // f < g
+            } else if ordered
+                && (left_expr.ctype.is_function_pointer() || left_expr.ctype.is_void_pointer())
+            {
+                let pointee = |ctype: &Type| match ctype {
+                    Type::Pointer(inner, _) => (**inner).clone(),
+                    _ => unreachable!("checked to be a pointer"),
+                };
+                let (left_type, right_type) =
+                    (pointee(&left_expr.ctype), pointee(&right_expr.ctype));
+                if left_type.is_function() {
+                    self.err(
+                        SemanticError::FunctionPointerOrdering(token, left_type, right_type),
+                        location,
+                    );
+                // (void*)p < (void*)q, which GNU C allows
+                } else {
+                    self.warn(
+                        Warning::VoidPointerOrdering(token, left_type, right_type),
+                        location,
+                    );
+                }
             }
             left = left_expr;
             right = right_expr;
         }
+        // both branches above call `rval`
         assert!(!left.lval && !right.lval);
+        // 6.5.8p6, 6.5.9p3: "The result has type int."
         Expr {
             lval: false,
             location,
             ctype: Type::Bool,
-            expr: ExprType::Binary(BinaryOp::Compare(token), Box::new(left), Box::new(right)),
+            expr: ExprType::Binary(
+                BinaryOp::Compare(token),
+                self.arena.expr(left),
+                self.arena.expr(right),
+            ),
         }
+        .bool_to_int(self.arena)
     }
     // `left OP right`, where OP is Mul, Div, or Mod
     // 6.5.5 Multiplicative operators
-    fn mul(&mut self, left: Expr, right: Expr, op: BinaryOp) -> Expr {
-        let location = left.location.merge(right.location);
+    fn mul(
+        &mut self,
+        left: Expr<'hir>,
+        right: Expr<'hir>,
+        op: BinaryOp,
+        location: Location,
+    ) -> Expr<'hir> {
+        if left.is_error() || right.is_error() {
+            return Expr::error(location);
+        }
 
         if op == BinaryOp::Mod && !(left.ctype.is_integral() && right.ctype.is_integral()) {
             self.err(
-                SemanticError::from(format!(
-                    "expected integers for both operators of %, got '{}' and '{}'",
-                    left.ctype, right.ctype
-                )),
+                SemanticError::NonIntegralModulo(left.ctype.clone(), right.ctype.clone()),
                 location,
             );
+            return Expr::error(location);
         } else if !(left.ctype.is_arithmetic() && right.ctype.is_arithmetic()) {
             self.err(
-                SemanticError::from(format!(
-                    "expected float or integer types for both operands of {}, got '{}' and '{}'",
-                    op, left.ctype, right.ctype
-                )),
+                SemanticError::NonArithmeticOperands(op, left.ctype.clone(), right.ctype.clone()),
                 location,
             );
+            return Expr::error(location);
         }
-        let (left, right) = Expr::binary_promote(left, right, &mut self.error_handler);
+        let (left, right) = Expr::binary_promote(left, right, self.arena, &mut self.error_handler);
         Expr {
             ctype: left.ctype.clone(),
             location,
             lval: false,
-            expr: ExprType::Binary(op, Box::new(left), Box::new(right)),
+            expr: ExprType::Binary(op, self.arena.expr(left), self.arena.expr(right)),
         }
     }
     // `a + b` or `a - b`
     // `op` should only be `Add` or `Sub`
     // 6.5.6 Additive operators
-    fn add(&mut self, mut left: Expr, mut right: Expr, op: BinaryOp) -> Expr {
+    fn add(
+        &mut self,
+        mut left: Expr<'hir>,
+        mut right: Expr<'hir>,
+        op: BinaryOp,
+        location: Location,
+    ) -> Expr<'hir> {
         let is_add = op == BinaryOp::Add;
-        let location = left.location.merge(right.location);
+        if left.is_error() || right.is_error() {
+            return Expr::error(location);
+        }
+        // `f + 1` is `&f + 1`, which is an error below
+        if left.ctype.is_function() {
+            left = left.rval(self.arena);
+        }
+        if right.ctype.is_function() {
+            right = right.rval(self.arena);
+        }
         match (&left.ctype, &right.ctype) {
             // `p + i`
             (Type::Pointer(to, _), i)
-            | (Type::Array(to, _), i) if i.is_integral() && to.is_complete() => {
+            | (Type::Array(to, _), i) if i.is_integral() => {
                 let to = to.clone();
-                let (left, right) = (left.rval(), right.rval());
-                return self.pointer_arithmetic(left, right, &*to, location);
+                let (left, right) = (left.rval(self.arena), right.rval(self.arena));
+                return self.pointer_arithmetic(left, right, &to, op, location);
             }
             // `i + p`
             (i, Type::Pointer(to, _))
                 // `i - p` for pointer p is not valid
-            | (i, Type::Array(to, _)) if i.is_integral() && is_add && to.is_complete() => {
+            | (i, Type::Array(to, _)) if i.is_integral() && is_add => {
                 let to = to.clone();
-                let (left, right) = (left.rval(), right.rval());
-                return self.pointer_arithmetic(right, left, &*to, location);
+                let (left, right) = (left.rval(self.arena), right.rval(self.arena));
+                return self.pointer_arithmetic(right, left, &to, op, location);
+            }
+            // `p1 - p2`
+            // `p1 + p2` for pointers p1 and p2 is not valid
+            (Type::Pointer(to, _), Type::Pointer(other, _))
+            | (Type::Pointer(to, _), Type::Array(other, _))
+            | (Type::Array(to, _), Type::Pointer(other, _))
+            | (Type::Array(to, _), Type::Array(other, _))
+                if !is_add && to == other && left.ctype.is_pointer_to_complete_object() =>
+            {
+                let (left, right) = (left.rval(self.arena), right.rval(self.arena));
+                return self.pointer_difference(left, right, location);
             }
             _ => {}
         };
         // `i + i`
-        let (ctype, lval) = if left.ctype.is_arithmetic() && right.ctype.is_arithmetic() {
-            let tmp = Expr::binary_promote(left, right, &mut self.error_handler);
+        let ctype = if left.ctype.is_arithmetic() && right.ctype.is_arithmetic() {
+            let tmp = Expr::binary_promote(left, right, self.arena, &mut self.error_handler);
             left = tmp.0;
             right = tmp.1;
-            (left.ctype.clone(), false)
-        // `p1 - p2`
-        // `p1 + p2` for pointers p1 and p2 is not valid
-        } else if !is_add && left.ctype.is_pointer_to_complete_object() && left.ctype == right.ctype
-        {
-            // not sure what type to use here, C11 standard doesn't mention it
-            (left.ctype.clone(), true)
+            left.ctype.clone()
         } else {
             self.err(
                 SemanticError::InvalidAdd(op, left.ctype.clone(), right.ctype.clone()),
                 location,
             );
-            (left.ctype.clone(), false)
+            return Expr::error(location);
         };
         Expr {
             ctype,
-            lval,
+            lval: false,
             location,
-            expr: ExprType::Binary(op, Box::new(left), Box::new(right)),
+            expr: ExprType::Binary(op, self.arena.expr(left), self.arena.expr(right)),
+        }
+    }
+    // `left - right`, where both are pointers to the same complete object type
+    // 6.5.6p9: the result is the difference of the subscripts, with type `ptrdiff_t`
+    fn pointer_difference(
+        &mut self,
+        left: Expr<'hir>,
+        right: Expr<'hir>,
+        location: Location,
+    ) -> Expr<'hir> {
+        // desugar to `((ptrdiff_t)left - (ptrdiff_t)right) / sizeof(*left)`
+        let ptrdiff = Type::Long(true);
+        let size = match &left.ctype {
+            Type::Pointer(to, _) => to.sizeof().unwrap_or(1),
+            _ => unreachable!("only pointers can be subtracted"),
+        };
+        let arena = self.arena;
+        let cast = |expr: Expr<'hir>| Expr {
+            lval: false,
+            location: expr.location,
+            ctype: ptrdiff.clone(),
+            expr: ExprType::Cast(arena.expr(expr)),
+        };
+        let bytes = Expr {
+            lval: false,
+            location,
+            ctype: ptrdiff.clone(),
+            expr: ExprType::Binary(
+                BinaryOp::Sub,
+                self.arena.expr(cast(left)),
+                self.arena.expr(cast(right)),
+            ),
+        };
+        let size = literal(Literal::Int(size as arch::PTRDIFF_T), location);
+        Expr {
+            lval: false,
+            location,
+            ctype: ptrdiff,
+            expr: ExprType::Binary(BinaryOp::Div, self.arena.expr(bytes), self.arena.expr(size)),
         }
     }
     // (int)i
     // 6.5.4 Cast operators
-    fn explicit_cast(&mut self, expr: ast::Expr, ctype: Type) -> Expr {
-        let location = expr.location;
-        let expr = self.expr(expr).rval();
+    //
+    // `location` is the location of the parenthesized type name
+    fn explicit_cast(&mut self, expr: ast::Expr, ctype: Type, location: Location) -> Expr<'hir> {
+        let expr = self.expr(expr).rval(self.arena);
+        let location = location.merge(expr.location);
+        if expr.is_error() || ctype == Type::Error {
+            return Expr::error(location);
+        }
         // (void)0;
         if ctype == Type::Void {
             // casting anything to void is allowed
@@ -408,7 +749,7 @@ impl PureAnalyzer {
                 lval: false,
                 ctype,
                 // this just signals to the backend to ignore this outer expr
-                expr: ExprType::Cast(Box::new(expr)),
+                expr: ExprType::Cast(self.arena.expr(expr)),
                 location,
             };
         }
@@ -431,29 +772,57 @@ impl PureAnalyzer {
         }
         Expr {
             lval: false,
-            expr: ExprType::Cast(Box::new(expr)),
+            expr: ExprType::Cast(self.arena.expr(expr)),
             ctype,
             location,
         }
     }
-    // `base + index`, where `pointee` is the type of `*base`
+    // `base + index` or `base - index`, where `pointee` is the type of `*base`
     // 6.5.6 Additive operators
     fn pointer_arithmetic(
         &mut self,
-        base: Expr,
-        index: Expr,
+        base: Expr<'hir>,
+        index: Expr<'hir>,
         pointee: &Type,
+        op: BinaryOp,
         location: Location,
-    ) -> Expr {
-        // the idea is to desugar to `base + sizeof(base)*index`
+    ) -> Expr<'hir> {
+        // the idea is to desugar to `base + sizeof(base)*index` (or `base - sizeof(base)*index`),
+        // scaling in `ptrdiff_t` so that negative indices stay negative
+        let ptrdiff = Type::Long(true);
+        let scratch = Arena::new();
+        let constant = match index
+            .clone_in(&scratch)
+            .const_fold()
+            .map(|folded| folded.expr)
+        {
+            Ok(ExprType::Literal(Literal::Int(i))) => Some(i),
+            Ok(ExprType::Literal(Literal::UnsignedInt(u))) => Some(u as arch::PTRDIFF_T),
+            Ok(ExprType::Literal(Literal::Char(c))) => Some(c.into()),
+            _ => None,
+        };
         let offset = Expr {
             lval: false,
             location: index.location,
-            expr: ExprType::Cast(Box::new(index)),
-            ctype: base.ctype.clone(),
+            expr: ExprType::Cast(self.arena.expr(index)),
+            ctype: ptrdiff.clone(),
         }
-        .rval();
+        .rval(self.arena);
         let size = match pointee.sizeof() {
+            // `fp + 1`
+            _ if pointee.is_function() => {
+                self.err(SemanticError::PointerArithmetic(pointee.clone()), location);
+                1
+            }
+            // `v + 1`, which GNU C allows as if `sizeof(void) == 1`
+            _ if *pointee == Type::Void => {
+                if self.standard.gnu {
+                    self.warn(Warning::VoidPointerArithmetic, location);
+                } else {
+                    self.err(SemanticError::PointerArithmetic(Type::Void), location);
+                }
+                1
+            }
             Ok(s) => s,
             Err(_) => {
                 self.err(
@@ -463,48 +832,148 @@ impl PureAnalyzer {
                 1
             }
         };
-        let size_literal = literal(Literal::UnsignedInt(size), offset.location);
-        let size_cast = Expr {
-            lval: false,
-            location: offset.location,
-            ctype: offset.ctype.clone(),
-            expr: ExprType::Cast(Box::new(size_literal)),
-        };
+        // sizes bigger than `PTRDIFF_MAX` can't be scaled without overflowing either
+        let scale = size as arch::PTRDIFF_T;
+        if let Some(i) = constant {
+            // a constant offset that fits is left for `const_fold`, so the index can still be printed
+            if scale < 0 || i.checked_mul(scale).is_none() {
+                self.warn(Warning::PointerOffsetOverflow(i, size), offset.location);
+                // wrap like GCC instead of failing to fold later
+                let bytes = literal(Literal::Int(i.wrapping_mul(scale)), offset.location);
+                return Expr {
+                    lval: false,
+                    location,
+                    ctype: base.ctype.clone(),
+                    expr: ExprType::Binary(op, self.arena.expr(base), self.arena.expr(bytes)),
+                };
+            }
+        }
+        let size_literal = literal(Literal::Int(scale), offset.location);
         let offset = Expr {
             lval: false,
             location: offset.location,
-            ctype: offset.ctype.clone(),
-            expr: ExprType::Binary(BinaryOp::Mul, Box::new(size_cast), Box::new(offset)),
+            ctype: ptrdiff,
+            expr: ExprType::Binary(
+                BinaryOp::Mul,
+                self.arena.expr(size_literal),
+                self.arena.expr(offset),
+            ),
         };
         Expr {
             lval: false,
             location,
             ctype: base.ctype.clone(),
-            expr: ExprType::Binary(BinaryOp::Add, Box::new(base), Box::new(offset)),
+            expr: ExprType::Binary(op, self.arena.expr(base), self.arena.expr(offset)),
+        }
+    }
+    // C89 3.3.2.2: "If the expression that precedes the parenthesized argument list in a function call
+    // consists solely of an identifier, and if no declaration is visible for this identifier,
+    // the identifier is implicitly declared exactly as if, in the innermost block containing the function call,
+    // the declaration `extern int identifier();` appeared."
+    fn implicit_function_declaration(&mut self, name: InternedStr, location: Location) {
+        self.warn(Warning::ImplicitFunctionDeclaration(name), location);
+        let ctype = Type::Function(FunctionType {
+            return_type: Box::new(Type::Int(true)),
+            params: Vec::new(),
+            varargs: false,
+        });
+        let meta = Variable {
+            id: name,
+            ctype,
+            qualifiers: Qualifiers::default(),
+            storage_class: StorageClass::Extern,
+            location,
+        };
+        let symbol = self.declare(meta, false, location);
+        // the backend has to see the declaration before the call
+        let decl = Declaration {
+            symbol,
+            init: None,
+            attributes: LinkAttributes::default(),
+        };
+        self.decl_side_channel.push(Locatable::new(decl, location));
+    }
+    /// Declare a compiler builtin the first time it's called.
+    ///
+    /// Returns `false` if `name` isn't a builtin.
+    fn builtin_declaration(&mut self, name: InternedStr, location: Location) -> bool {
+        // `void __builtin_unreachable(void);`, which is `_Noreturn` because of `NO_RETURN`
+        if name != InternedStr::get_or_intern(BUILTIN_UNREACHABLE) {
+            return false;
+        }
+        let void = Variable {
+            id: InternedStr::default(),
+            ctype: Type::Void,
+            qualifiers: Qualifiers::default(),
+            storage_class: StorageClass::Auto,
+            location,
+        };
+        let ctype = Type::Function(FunctionType {
+            return_type: Box::new(Type::Void),
+            params: vec![void.insert()],
+            varargs: false,
+        });
+        let meta = Variable {
+            id: name,
+            ctype,
+            qualifiers: Qualifiers::default(),
+            storage_class: StorageClass::Extern,
+            location,
+        };
+        let symbol = self.declare(meta, false, location);
+        let decl = Declaration {
+            symbol,
+            init: None,
+            attributes: LinkAttributes::default(),
+        };
+        self.decl_side_channel.push(Locatable::new(decl, location));
+        true
+    }
+    // `L"abc"`, `u"abc"`, or `U"abc"`
+    // 6.4.5 String literals
+    fn wide_string(&mut self, string: Literal, location: Location) -> Expr<'hir> {
+        let arch = arch::ArchData::new(arch::Target::host().triple());
+        match (arch.string_data(&string), &string) {
+            (Err(unit), Literal::WideStr(encoding, _)) => {
+                let err = SemanticError::CharacterTooLarge(unit, encoding.element_type());
+                self.err(err, location);
+                Expr::error(location)
+            }
+            _ => literal(string, location),
         }
     }
     // `func(args)`
     // 6.5.2.2 Function calls
-    fn func_call(&mut self, func: ast::Expr, args: Vec<ast::Expr>) -> Expr {
+    // `location` is the location of the whole call, including the arguments
+    fn func_call(
+        &mut self,
+        func: ast::Expr,
+        args: Vec<ast::Expr>,
+        location: Location,
+    ) -> Expr<'hir> {
+        if let ast::ExprType::Id(name) = func.data {
+            if self.scope.get(&name).is_none()
+                && !self.builtin_declaration(name, func.location)
+                && self.standard.implicit_function_declarations()
+            {
+                self.implicit_function_declaration(name, func.location);
+            }
+        }
         let mut func = self.expr(func);
         // if fp is a function pointer, fp() desugars to (*fp)()
-        match &func.ctype {
-            Type::Pointer(pointee, _) if pointee.is_function() => {
-                func = Expr {
-                    lval: false,
-                    location: func.location,
-                    ctype: (**pointee).clone(),
-                    expr: ExprType::Deref(Box::new(func)),
-                }
+        // the pointer may not be an lvalue: `get_callback()()`
+        if let Type::Pointer(pointee, _) = &func.ctype {
+            if pointee.is_function() {
+                let ctype = (**pointee).clone();
+                func = func.indirection(true, ctype, self.arena);
             }
-            _ => {}
-        };
+        }
         let functype = match &func.ctype {
             Type::Function(functype) => functype,
             Type::Error => return func, // we've already reported this error
             other => {
                 self.err(SemanticError::NotAFunction(other.clone()), func.location);
-                return func;
+                return Expr::error(func.location);
             }
         };
         let mut expected = functype.params.len();
@@ -517,41 +986,125 @@ impl PureAnalyzer {
             // `int f(int); f()` or `int f(int); f(1, 2)`
             && (args.len() < expected || args.len() > expected && !functype.varargs)
         {
-            self.err(
-                SemanticError::WrongArgumentNumber(args.len(), expected),
-                func.location,
-            );
+            // `f(1)` can point to where `f` was declared, but `fp(1)` can't
+            let declaration = match &func.expr {
+                ExprType::Id(symbol) => Some(symbol.get().location),
+                _ => None,
+            };
+            let err = SemanticError::WrongArgumentNumber {
+                actual: args.len(),
+                expected,
+                varargs: functype.varargs,
+                declaration,
+            };
+            self.err(err, func.location);
         }
+        // `printf("%d", i)`: only string literals can be checked
+        let format = match &func.expr {
+            ExprType::Id(symbol) => symbol.get().qualifiers.func.format,
+            _ => None,
+        };
+        let format_string = format
+            .and_then(|format| args.get(format.format_index - 1))
+            .and_then(|arg| match &arg.data {
+                ast::ExprType::Literal(Literal::Str(s)) => Some(arg.location.with(s.clone())),
+                _ => None,
+            });
         let mut promoted_args = vec![];
         for (i, arg) in args.into_iter().enumerate() {
             let arg = self.expr(arg);
+            // int f(int *); int i; f(i)
+            let param = functype
+                .params
+                .get(i)
+                .map(|param| param.get().ctype.clone());
+            if let Some(Type::Pointer(pointee, _)) = &param {
+                // functions already decay to pointers, so `f(g)` is fine
+                if arg.lval && !arg.ctype.is_function() && **pointee == arg.ctype {
+                    self.err(
+                        SemanticError::MissingAddressOf(arg.ctype.clone(), param.unwrap()),
+                        arg.location,
+                    );
+                    promoted_args.push(arg);
+                    continue;
+                }
+            }
             let promoted = match functype.params.get(i) {
                 // int f(int); f(1)
-                Some(expected) => arg
-                    .rval()
-                    .implicit_cast(&expected.get().ctype, &mut self.error_handler),
+                Some(expected) => {
+                    let expected = expected.get();
+                    let arg = arg.rval(self.arena);
+                    check_pointer_qualifiers(
+                        &arg,
+                        &expected.ctype,
+                        expected.qualifiers.c_const,
+                        &mut self.error_handler,
+                    );
+                    // say which argument was wrong, not just which conversion
+                    let mut conversion = ErrorHandler::new();
+                    let promoted = arg.implicit_cast(&expected.ctype, self.arena, &mut conversion);
+                    self.error_handler.warnings.append(&mut conversion.warnings);
+                    for err in conversion {
+                        let location = err.location;
+                        let err = match err.data {
+                            // `void f(struct a); struct b b; f(b)`
+                            Error::Semantic(SemanticError::InvalidCast(actual, ctype))
+                            | Error::Semantic(SemanticError::IncompatibleAggregate(
+                                ctype,
+                                actual,
+                            )) => {
+                                let err = SemanticError::InvalidArgument {
+                                    position: i + 1,
+                                    actual,
+                                    expected: ctype,
+                                    parameter: expected.location,
+                                };
+                                location.with(err.into())
+                            }
+                            _ => err,
+                        };
+                        self.error_handler.push_back(err);
+                    }
+                    promoted
+                }
                 // `int f(); f(1)` or `int f(int, ...); f(1, 2)`
                 None => self.default_promote(arg),
             };
             promoted_args.push(promoted);
         }
+        if let (Some(format), Some(format_string)) = (format, format_string) {
+            self.check_format(format, &format_string, &promoted_args);
+        }
+        // `int f(); f(1, 2)`: remember the call in case a prototype shows up later
+        if let (ExprType::Id(symbol), false) = (&func.expr, functype.has_prototype()) {
+            let args = promoted_args.iter().map(|arg| arg.ctype.clone()).collect();
+            self.unprototyped_calls
+                .entry(symbol.get().id)
+                .or_default()
+                .push(func.location.with(args));
+        }
         Expr {
-            location: func.location,
+            location,
             lval: false, // no move semantics here!
             ctype: *functype.return_type.clone(),
-            expr: ExprType::FuncCall(Box::new(func), promoted_args),
+            expr: ExprType::FuncCall(self.arena.expr(func), promoted_args),
         }
     }
     /// 'default promotions' from 6.5.2.2p6
-    fn default_promote(&mut self, expr: Expr) -> Expr {
-        let expr = expr.rval();
+    fn default_promote(&mut self, expr: Expr<'hir>) -> Expr<'hir> {
+        let expr = expr.rval(self.arena);
         let ctype = expr.ctype.clone().default_promote();
-        expr.implicit_cast(&ctype, &mut self.error_handler)
+        expr.implicit_cast(&ctype, self.arena, &mut self.error_handler)
     }
     // parse a struct member
     // used for both s.a and s->a
     // 6.5.2.3 Structure and union members
-    fn struct_member(&mut self, expr: Expr, id: InternedStr, location: Location) -> Expr {
+    fn struct_member(
+        &mut self,
+        expr: Expr<'hir>,
+        id: InternedStr,
+        location: Location,
+    ) -> Expr<'hir> {
         match &expr.ctype {
             Type::Struct(stype) | Type::Union(stype) => {
                 let members = stype.members();
@@ -561,25 +1114,26 @@ impl PureAnalyzer {
                         SemanticError::IncompleteDefinitionUsed(expr.ctype.clone()),
                         location,
                     );
-                    expr
+                    Expr::error(location)
                 // struct s { int i; }; s.i
                 } else if let Some(member) = members.iter().find(|member| member.id == id) {
                     Expr {
                         ctype: member.ctype.clone(),
                         lval: true,
                         location,
-                        expr: ExprType::Member(Box::new(expr), id),
+                        expr: ExprType::Member(self.arena.expr(expr), id),
                     }
                 // struct s { int i; }; s.j
                 } else {
                     self.err(SemanticError::NotAMember(id, expr.ctype.clone()), location);
-                    expr
+                    Expr::error(location)
                 }
             }
+            Type::Error => Expr::error(location),
             // (1).a
             _ => {
                 self.err(SemanticError::NotAStruct(expr.ctype.clone()), location);
-                expr
+                Expr::error(location)
             }
         }
     }
@@ -591,114 +1145,200 @@ impl PureAnalyzer {
         increment: bool,
         expr: ast::Expr,
         location: Location,
-    ) -> Expr {
+    ) -> Expr<'hir> {
         use crate::data::lex::AssignmentToken;
 
         let expr = self.expr(expr);
+        if expr.is_error() {
+            return Expr::error(location);
+        }
         if let Err(err) = expr.modifiable_lval() {
             self.err(err, location);
+            return Expr::error(location);
         } else if !(expr.ctype.is_arithmetic() || expr.ctype.is_pointer()) {
             self.err(
                 SemanticError::InvalidIncrement(expr.ctype.clone()),
                 expr.location,
             );
+            return Expr::error(location);
         }
-        // ++i is syntactic sugar for i+=1
-        if prefix {
-            let rval = Expr {
-                lval: false,
-                ctype: expr.ctype.clone(),
-                location,
-                expr: ExprType::Cast(Box::new(literal(Literal::Int(1), location))),
-            };
-            let op = if increment {
-                AssignmentToken::AddEqual
-            } else {
-                AssignmentToken::SubEqual
-            };
-            self.assignment_expr(expr, rval, op, location)
-        // i++ requires support from the backend
+        // `1` has to be an `int` and not the type of `expr`, so that `p + 1` on a pointer is valid
+        let one = Expr {
+            lval: false,
+            ctype: Type::Int(true),
+            location,
+            expr: ExprType::Literal(Literal::Int(1)),
+        };
+        let op = if increment {
+            AssignmentToken::AddEqual
+        } else {
+            AssignmentToken::SubEqual
+        };
+        // ++i is syntactic sugar for i+=1
+        if prefix {
+            self.assignment_expr(expr, one, Locatable::new(op, location), location)
         // 6.5.2.4 Postfix increment and decrement operators
         // evaluate the rvalue of `i` and as a side effect, increment the value at the stored address
         // ex: `int i = 0, j; j = i++;` leaves a value of 0 in j and a value of 1 in i
+        //
+        // i++ is `tmp = &i, old = *tmp, *tmp = old + 1, old`
         } else {
-            Expr {
-                lval: false,
-                ctype: expr.ctype.clone(),
-                // true, false: increment/decrement
-                expr: ExprType::PostIncrement(Box::new(expr), increment),
-                location,
-            }
+            self.evaluate_once(expr, |this, target| {
+                let old = target.clone_in(this.arena).rval(this.arena);
+                this.evaluate_once(old, |this, old| {
+                    let ctype = old.ctype.clone();
+                    let new_val = this
+                        .desugar_op(old.clone_in(this.arena), one, op, location)
+                        .implicit_cast(&target.ctype, this.arena, &mut this.error_handler);
+                    let assign = Expr {
+                        ctype: target.ctype.clone(),
+                        lval: false,
+                        location,
+                        expr: ExprType::Binary(
+                            BinaryOp::Assign,
+                            this.arena.expr(target),
+                            this.arena.expr(new_val),
+                        ),
+                    };
+                    Expr {
+                        ctype,
+                        lval: false,
+                        location,
+                        expr: ExprType::Comma(this.arena.expr(assign), this.arena.expr(old)),
+                    }
+                })
+            })
         }
     }
     // a[i] desugars to *(a + i)
     // 6.5.2.1 Array subscripting
-    fn index(&mut self, left: ast::Expr, right: ast::Expr, location: Location) -> Expr {
-        let left = self.expr(left).rval();
-        let right = self.expr(right).rval();
+    fn index(&mut self, left: ast::Expr, right: ast::Expr, location: Location) -> Expr<'hir> {
+        let left = self.expr(left);
+        let right = self.expr(right);
+        // register int a[3]; a[1]
+        // gcc allows this, but the array decays to a pointer, and 6.3.2.1p3 says that's undefined
+        for operand in &[&left, &right] {
+            if let ExprType::Id(sym) = operand.expr {
+                let meta = sym.get();
+                if meta.storage_class == StorageClass::Register
+                    && matches!(meta.ctype, Type::Array(..))
+                {
+                    self.warn(Warning::RegisterArraySubscript(meta.id), location);
+                }
+            }
+        }
+        let left = left.rval(self.arena);
+        let right = right.rval(self.arena);
 
         let (target_type, array, index) = match (&left.ctype, &right.ctype) {
             // p[i]
             (Type::Pointer(target, _), _) => ((**target).clone(), left, right),
             // i[p]
             (_, Type::Pointer(target, _)) => ((**target).clone(), right, left),
+            (Type::Error, _) | (_, Type::Error) => return Expr::error(location),
             (l, _) => {
                 self.err(SemanticError::NotAPointer(l.clone()), location);
-                return left;
+                return Expr::error(location);
             }
         };
-        let mut addr = self.pointer_arithmetic(array, index, &target_type, location);
-        addr.ctype = target_type;
+        // 6.5.2.1p1: "the other expression shall have integer type"
+        if !index.ctype.is_integral() {
+            if index.ctype != Type::Error {
+                self.err(
+                    SemanticError::NonIntegralSubscript(index.ctype.clone()),
+                    index.location,
+                );
+            }
+            return Expr::error(location);
+        }
+        let addr = self.pointer_arithmetic(array, index, &target_type, BinaryOp::Add, location);
         // `p + i` -> `*(p + i)`
-        addr.lval = true;
-        addr
+        addr.indirection(true, target_type, self.arena)
     }
     // _Alignof(int)
-    fn align(&mut self, ctype: Type, location: Location) -> Expr {
-        let align = ctype.alignof().unwrap_or_else(|err| {
-            self.err(err.into(), location);
+    fn align(&mut self, ctype: Type, location: Location) -> Expr<'hir> {
+        if ctype == Type::Error {
+            return Expr::error(location);
+        }
+        let align = match ctype.alignof() {
+            // `_Alignof(struct s)` without a definition
+            Ok(0) => Err("cannot take the alignment of an incomplete struct or union"),
+            result => result,
+        };
+        let align = align.unwrap_or_else(|err| {
+            self.err(SemanticError::InvalidSize(err), location);
             1
         });
         literal(Literal::UnsignedInt(align), location)
     }
     // sizeof(int)
     // 6.5.3.4 The sizeof and _Alignof operators
-    fn sizeof(&mut self, ctype: Type, location: Location) -> Expr {
-        let align = ctype.sizeof().unwrap_or_else(|err| {
-            self.err(err.into(), location);
+    fn sizeof(&mut self, ctype: Type, location: Location) -> Expr<'hir> {
+        // the error was already reported
+        if ctype == Type::Error {
+            return Expr::error(location);
+        }
+        let size = ctype.sizeof().unwrap_or_else(|err| {
+            self.err(SemanticError::InvalidSize(err), location);
             1
         });
-        literal(Literal::UnsignedInt(align), location)
+        literal(Literal::UnsignedInt(size), location)
     }
     // ~expr
     // 6.5.3.3 Unary arithmetic operators
-    fn bitwise_not(&mut self, expr: ast::Expr) -> Expr {
+    fn bitwise_not(&mut self, expr: ast::Expr, location: Location) -> Expr<'hir> {
         let expr = self.expr(expr);
-        if !expr.ctype.is_integral() {
+        if expr.is_error() {
+            Expr::error(location)
+        } else if !expr.ctype.is_integral() {
             self.err(
-                SemanticError::NonIntegralExpr(expr.ctype.clone()),
-                expr.location,
+                SemanticError::InvalidUnaryOperand("~", "integer", expr.ctype.clone()),
+                location,
             );
-            expr
+            Expr::error(location)
         } else {
-            let expr = expr.integer_promote(&mut self.error_handler);
+            let expr = expr.integer_promote(self.arena, &mut self.error_handler);
             Expr {
                 lval: false,
                 ctype: expr.ctype.clone(),
-                location: expr.location,
-                expr: ExprType::BitwiseNot(Box::new(expr)),
+                location,
+                expr: ExprType::BitwiseNot(self.arena.expr(expr)),
             }
         }
     }
+    // &*p
+    // 6.5.3.2p3: neither operator is evaluated and the result is not an lvalue
+    fn address_of_deref(&mut self, pointer: ast::Expr, location: Location) -> Expr<'hir> {
+        let pointer = self.expr(pointer).rval(self.arena);
+        if pointer.is_error() {
+            return Expr::error(location);
+        }
+        if !pointer.ctype.is_pointer() {
+            self.err(SemanticError::NotAPointer(pointer.ctype.clone()), location);
+            return Expr::error(location);
+        }
+        Expr {
+            lval: false,
+            location,
+            ..pointer
+        }
+    }
     // -x and +x
     // 6.5.3.3 Unary arithmetic operators
-    fn unary_add(&mut self, expr: ast::Expr, add: bool, location: Location) -> Expr {
+    fn unary_add(&mut self, expr: ast::Expr, add: bool, location: Location) -> Expr<'hir> {
         let expr = self.expr(expr);
+        if expr.is_error() {
+            return Expr::error(location);
+        }
         if !expr.ctype.is_arithmetic() {
-            self.err(SemanticError::NotArithmetic(expr.ctype.clone()), location);
-            return expr;
+            let op = if add { "+" } else { "-" };
+            self.err(
+                SemanticError::InvalidUnaryOperand(op, "float or integer", expr.ctype.clone()),
+                location,
+            );
+            return Expr::error(location);
         }
-        let expr = expr.integer_promote(&mut self.error_handler);
+        let expr = expr.integer_promote(self.arena, &mut self.error_handler);
         if add {
             Expr {
                 lval: false,
@@ -710,42 +1350,85 @@ impl PureAnalyzer {
                 lval: false,
                 ctype: expr.ctype.clone(),
                 location,
-                expr: ExprType::Negate(Box::new(expr)),
+                expr: ExprType::Negate(self.arena.expr(expr)),
+            }
+        }
+    }
+    /// The controlling expression of `if`, `while`, `do`, `for`, or the operand of `!`.
+    ///
+    /// These must be scalar after lvalue conversion (6.8.4.1p1, 6.8.5p2, 6.5.3.3p1);
+    /// the result is compared to 0 and has type `_Bool`.
+    pub(super) fn condition(&mut self, expr: Expr<'hir>) -> Expr<'hir> {
+        // `if (arr)`, `if (f)`, `if (&x)`: an array, function, or object is never at address 0
+        if let ExprType::Id(symbol) = &expr.expr {
+            let kind = match &expr.ctype {
+                Type::Array(..) if expr.lval => Some("array"),
+                Type::Function(_) => Some("function"),
+                Type::Pointer(..) if !expr.lval => Some("variable"),
+                _ => None,
+            };
+            if let Some(kind) = kind {
+                let name = symbol.get().id;
+                self.warn(Warning::AddressAlwaysTrue(kind, name), expr.location);
             }
         }
+        expr.truthy(self.arena, &mut self.error_handler)
     }
     // !expr
     // 6.5.3.3 Unary arithmetic operators
     // > The expression !E is equivalent to (0==E).
-    fn logical_not(&mut self, expr: ast::Expr) -> Expr {
+    fn logical_not(&mut self, expr: ast::Expr, location: Location) -> Expr<'hir> {
         let expr = self.expr(expr);
-        let boolean = expr.truthy(&mut self.error_handler);
+        // arrays and functions decay to pointers
+        if !(expr.is_error()
+            || expr.ctype.is_scalar()
+            || expr.ctype.is_function()
+            || matches!(expr.ctype, Type::Array(..)))
+        {
+            self.err(
+                SemanticError::InvalidUnaryOperand("!", "scalar", expr.ctype.clone()),
+                location,
+            );
+            return Expr::error(location);
+        }
+        let boolean = self.condition(expr);
         debug_assert_eq!(boolean.ctype, Type::Bool);
-        let zero = Expr::zero(boolean.location).implicit_cast(&Type::Bool, &mut self.error_handler);
+        let zero = Expr::zero(boolean.location).implicit_cast(
+            &Type::Bool,
+            self.arena,
+            &mut self.error_handler,
+        );
         Expr {
             lval: false,
-            location: boolean.location,
+            location,
             ctype: Type::Bool,
             expr: ExprType::Binary(
                 BinaryOp::Compare(ComparisonToken::EqualEqual),
-                Box::new(boolean),
-                Box::new(zero),
+                self.arena.expr(boolean),
+                self.arena.expr(zero),
             ),
         }
+        .bool_to_int(self.arena)
     }
     // a || b or a && b
     // NOTE: this short circuits if possible
     // 6.5.14 Logical OR operator and 6.5.13 Logical AND operator
-    fn logical_bin_op(&mut self, a: Expr, b: Expr, op: BinaryOp) -> Expr {
-        let a = a.implicit_cast(&Type::Bool, &mut self.error_handler);
-        let b = b.implicit_cast(&Type::Bool, &mut self.error_handler);
+    fn logical_bin_op(
+        &mut self,
+        a: Expr<'hir>,
+        b: Expr<'hir>,
+        op: BinaryOp,
+        location: Location,
+    ) -> Expr<'hir> {
+        let a = a.implicit_cast(&Type::Bool, self.arena, &mut self.error_handler);
+        let b = b.implicit_cast(&Type::Bool, self.arena, &mut self.error_handler);
         Expr {
             lval: false,
-            // TODO: this is wrong, it should be an int
             ctype: Type::Bool,
-            location: a.location,
-            expr: ExprType::Binary(op, Box::new(a), Box::new(b)),
+            location,
+            expr: ExprType::Binary(op, self.arena.expr(a), self.arena.expr(b)),
         }
+        .bool_to_int(self.arena)
     }
     // condition ? then : otherwise
     // like an `if` in Rust: evaluate `condition`, yield the value of `then` if true, otherwise yield the value of `otherwise`
@@ -755,141 +1438,268 @@ impl PureAnalyzer {
         then: ast::Expr,
         otherwise: ast::Expr,
         location: Location,
-    ) -> Expr {
-        let condition = self.expr(condition).truthy(&mut self.error_handler);
-        let mut then = self.expr(then).rval();
-        let mut otherwise = self.expr(otherwise).rval();
+    ) -> Expr<'hir> {
+        let condition = self
+            .expr(condition)
+            .truthy(self.arena, &mut self.error_handler);
+        let then = self.expr(then);
+        let otherwise = self.expr(otherwise);
+        self.conditional(condition, then, otherwise, location)
+    }
+    // condition ?: otherwise
+    // GNU extension: the same as `condition ? condition : otherwise`, but `condition` is only evaluated once
+    fn omitted_ternary(
+        &mut self,
+        condition: ast::Expr,
+        otherwise: ast::Expr,
+        location: Location,
+    ) -> Expr<'hir> {
+        let condition = self.expr(condition).rval(self.arena);
+        let otherwise = self.expr(otherwise);
+        if condition.is_error() {
+            return Expr::error(location);
+        }
+        self.evaluate_once(condition, |this, value| {
+            let condition = value
+                .clone_in(this.arena)
+                .truthy(this.arena, &mut this.error_handler);
+            this.conditional(condition, value, otherwise, location)
+        })
+    }
+    // the part of `ternary` shared with `omitted_ternary`, after all the operands have been analyzed
+    fn conditional(
+        &mut self,
+        condition: Expr<'hir>,
+        then: Expr<'hir>,
+        otherwise: Expr<'hir>,
+        location: Location,
+    ) -> Expr<'hir> {
+        let mut then = then.rval(self.arena);
+        let mut otherwise = otherwise.rval(self.arena);
+        if then.is_error() || otherwise.is_error() {
+            return Expr::error(location);
+        }
 
         if then.ctype.is_arithmetic() && otherwise.ctype.is_arithmetic() {
-            let (tmp1, tmp2) = Expr::binary_promote(then, otherwise, &mut self.error_handler);
+            let (tmp1, tmp2) =
+                Expr::binary_promote(then, otherwise, self.arena, &mut self.error_handler);
             then = tmp1;
             otherwise = tmp2;
+        } else if distinct_object_pointers(&then.ctype, &otherwise.ctype) {
+            self.warn(
+                Warning::PointerTypeMismatch(then.ctype.clone(), otherwise.ctype.clone()),
+                location,
+            );
+            otherwise.ctype = then.ctype.clone();
         } else if !pointer_promote(&mut then, &mut otherwise) {
             self.err(
                 SemanticError::IncompatibleTypes(then.ctype.clone(), otherwise.ctype.clone()),
                 location,
             );
+            return Expr::error(location);
         }
         Expr {
             ctype: then.ctype.clone(),
             lval: false,
             location,
-            expr: ExprType::Ternary(Box::new(condition), Box::new(then), Box::new(otherwise)),
+            expr: ExprType::Ternary(
+                self.arena.expr(condition),
+                self.arena.expr(then),
+                self.arena.expr(otherwise),
+            ),
         }
     }
 
     // `a = b` or `a += b`
+    //
+    // Errors about the assignment itself point at `token`; the result has the location of the whole expression.
     fn assignment_expr(
         &mut self,
-        lval: Expr,
-        rval: Expr,
-        token: lex::AssignmentToken,
+        lval: Expr<'hir>,
+        rval: Expr<'hir>,
+        token: Locatable<lex::AssignmentToken>,
         location: Location,
-    ) -> Expr {
+    ) -> Expr<'hir> {
+        if lval.is_error() || rval.is_error() {
+            return Expr::error(location);
+        }
         if let Err(err) = lval.modifiable_lval() {
-            self.err(err, location);
+            self.err(err, token.location);
         }
+        let token = token.data;
         // `a = b`
         if let lex::AssignmentToken::Equal = token {
-            let mut rval = rval.rval();
-            if rval.ctype != lval.ctype {
-                rval = rval.implicit_cast(&lval.ctype, &mut self.error_handler);
+            if promoted_bitwise_operand(&rval).as_ref() == Some(&lval.ctype) {
+                self.warn_promoted_bitwise(&rval, rval.to_c_string(), location);
             }
+            let rval = rval.assignment_conversion(
+                &lval.ctype,
+                lval.pointee_qualifiers().c_const,
+                self.arena,
+                &mut self.error_handler,
+            );
             return Expr {
                 ctype: lval.ctype.clone(),
                 lval: false, // `(i = j) = 4`; is invalid
                 location,
-                expr: ExprType::Binary(BinaryOp::Assign, Box::new(lval), Box::new(rval)),
+                expr: ExprType::Binary(
+                    BinaryOp::Assign,
+                    self.arena.expr(lval),
+                    self.arena.expr(rval),
+                ),
             };
         }
         // Complex assignment is tricky because the left side needs to be evaluated only once
         // Consider e.g. `*f() += 1`: `f()` should only be called once.
-        // The hack implemented here is to treat `*f()` as a variable then load and store it to memory:
-        // `tmp = &f(); *tmp = *tmp + 1;`
+        // This stores the address of `*f()` in a temporary, then loads and stores through it:
+        // `tmp = &*f(), *tmp = *tmp + 1`
         // see also footnote 113 which has a similar algorithm (but is more convoluted because of atomics)
-
-        // declare tmp in a new hidden scope
-        // We really should only be modifying the scope in `FunctionAnalyzer`,
-        // but assignment expressions can never appear in an initializer anyway.
-        self.scope.enter();
-        let tmp_name = "tmp".into();
-        let ctype = lval.ctype.clone();
-        // TODO: we could probably make these qualifiers stronger
-        let ptr_type = Type::Pointer(Box::new(ctype.clone()), Qualifiers::default());
-        let meta = Variable {
-            id: tmp_name,
-            ctype: ptr_type.clone(),
-            qualifiers: Qualifiers::NONE,
-            storage_class: StorageClass::Register,
+        // `flags &= ~MASK` is shown in warnings as written, since the desugared version uses `tmp`
+        let source = if matches!(lval.ctype, Type::Char(false) | Type::Short(false)) {
+            Some(format!(
+                "{} {} {}",
+                lval.to_c_string(),
+                token,
+                rval.to_c_string()
+            ))
+        } else {
+            None
         };
-        let tmp_var = self.declare(meta, true, location);
-
-        // NOTE: this does _not_ call rval() on `lval`
-        // there's no way to do this in C natively - the closest is `&var`, but that doesn't work on expressions
-        // `T tmp = &*f()` or `T tmp = &sum`
-        let init = Some(Initializer::Scalar(Box::new(lval)));
-        let decl = Declaration {
-            symbol: tmp_var,
-            init,
+        self.evaluate_once(lval, |this, target| {
+            // `*tmp + 1`
+            let current = target.clone_in(this.arena).rval(this.arena);
+            let new_val = this.desugar_op(current, rval.rval(this.arena), token, location);
+            // `flags &= ~MASK`
+            if let Some(source) = source {
+                if promoted_bitwise_operand(&new_val).as_ref() == Some(&target.ctype) {
+                    this.warn_promoted_bitwise(&new_val, source, location);
+                }
+            }
+            let new_val = new_val.implicit_cast(&target.ctype, this.arena, &mut this.error_handler);
+            // `*tmp = *tmp + 1`
+            Expr {
+                ctype: target.ctype.clone(),
+                lval: false,
+                location,
+                expr: ExprType::Binary(
+                    BinaryOp::Assign,
+                    this.arena.expr(target),
+                    this.arena.expr(new_val),
+                ),
+            }
+        })
+    }
+    // Evaluate `value` exactly once, then pass a way to refer to it to `use_value`.
+    //
+    // The value is stored in an anonymous temporary, which is never added to the scope,
+    // so it can't conflict with (or `-Wshadow` warn about) a user's variable.
+    // If `value` is an lval, the temporary holds its address, so `use_value` can still assign to it.
+    fn evaluate_once<F>(&mut self, value: Expr<'hir>, use_value: F) -> Expr<'hir>
+    where
+        F: FnOnce(&mut Self, Expr<'hir>) -> Expr<'hir>,
+    {
+        let location = value.location;
+        let ctype = value.ctype.clone();
+        let tmp_type = if value.lval {
+            // TODO: we could probably make these qualifiers stronger
+            Type::Pointer(Box::new(ctype.clone()), Qualifiers::default())
+        } else {
+            ctype.clone()
         };
-        self.decl_side_channel.push(Locatable::new(decl, location));
-        self.scope.exit();
-        // load `tmp`, i.e. `&*f()`, only evaluated once
-        let tmp = Expr {
-            expr: ExprType::Id(tmp_var),
-            ctype: ptr_type,
-            lval: true,
+        let tmp = Variable {
+            id: InternedStr::default(),
+            ctype: tmp_type.clone(),
+            qualifiers: Qualifiers::NONE,
+            storage_class: StorageClass::Register,
             location,
         }
-        // this `rval` is because we have the (pointless) address of `tmp`
-        // instead we want the address of the lval
-        .rval();
-
-        // before we had `&sum`, now we have `sum`
-        // `*tmp`, i.e. `*f()`
-        let lval_as_rval = Expr {
-            ctype: ctype.clone(),
-            lval: false,
+        .insert();
+        // load `tmp`; this `rval` is because `Id` gives us the (pointless) address of `tmp`
+        let mut var = Expr {
+            expr: ExprType::Id(tmp),
+            ctype: tmp_type,
+            lval: true,
             location,
-            // this clone is pretty cheap since `tmp_assign_expr` is just an id
-            expr: ExprType::Deref(Box::new(tmp.clone())),
-        };
-        // `*tmp` in an lval context
-        let target = tmp.indirection(true, ctype.clone());
-        // `*tmp + 1`
-        let new_val = self
-            .desugar_op(lval_as_rval, rval.rval(), token)
-            .implicit_cast(&target.ctype, &mut self.error_handler);
-
-        // *tmp = *f() + 1
+        }
+        .rval(self.arena);
+        if value.lval {
+            // `*tmp`, which designates the same object as `value`
+            var = var.indirection(true, ctype, self.arena);
+        }
+        let body = use_value(self, var);
         Expr {
-            ctype,
+            ctype: body.ctype.clone(),
             lval: false,
-            location,
-            expr: ExprType::Binary(BinaryOp::Assign, Box::new(target), Box::new(new_val)),
+            location: body.location,
+            expr: ExprType::Let(tmp, self.arena.expr(value), self.arena.expr(body)),
         }
     }
-    fn desugar_op(&mut self, left: Expr, right: Expr, token: lex::AssignmentToken) -> Expr {
+    fn desugar_op(
+        &mut self,
+        left: Expr<'hir>,
+        right: Expr<'hir>,
+        token: lex::AssignmentToken,
+        location: Location,
+    ) -> Expr<'hir> {
         use lex::AssignmentToken::*;
 
-        match token {
+        let (op, checker): (_, fn(&mut Self, _, _, _, _) -> _) = match token {
             Equal => unreachable!(),
-            OrEqual => self.parse_integer_op(left, right, BinaryOp::BitwiseOr),
-            AndEqual => self.parse_integer_op(left, right, BinaryOp::BitwiseAnd),
-            XorEqual => self.parse_integer_op(left, right, BinaryOp::Xor),
-            ShlEqual => self.parse_integer_op(left, right, BinaryOp::Shl),
-            ShrEqual => self.parse_integer_op(left, right, BinaryOp::Shr),
-            MulEqual => self.mul(left, right, BinaryOp::Mul),
-            DivEqual => self.mul(left, right, BinaryOp::Div),
-            ModEqual => self.mul(left, right, BinaryOp::Mod),
-            AddEqual => self.add(left, right, BinaryOp::Add),
-            SubEqual => self.add(left, right, BinaryOp::Sub),
+            OrEqual => (BinaryOp::BitwiseOr, Self::parse_integer_op),
+            AndEqual => (BinaryOp::BitwiseAnd, Self::parse_integer_op),
+            XorEqual => (BinaryOp::Xor, Self::parse_integer_op),
+            ShlEqual => (BinaryOp::Shl, Self::parse_integer_op),
+            ShrEqual => (BinaryOp::Shr, Self::parse_integer_op),
+            MulEqual => (BinaryOp::Mul, Self::mul),
+            DivEqual => (BinaryOp::Div, Self::mul),
+            ModEqual => (BinaryOp::Mod, Self::mul),
+            AddEqual => (BinaryOp::Add, Self::add),
+            SubEqual => (BinaryOp::Sub, Self::add),
+        };
+        checker(self, left, right, op, location)
+    }
+    // `c = ~c` or `~c == 0xFF`, where `value` is `~c` and `c` is an `unsigned char`
+    // `source` is how `value` is shown in the warning
+    fn warn_promoted_bitwise(&mut self, value: &Expr<'hir>, source: String, location: Location) {
+        let original = match promoted_bitwise_operand(value) {
+            Some(original) => original,
+            None => return,
+        };
+        // show what the constant part folds to, like `~0x80` in `flags & ~0x80`
+        let mut candidates = vec![value];
+        if let ExprType::Binary(_, left, right) = &value.expr {
+            candidates.push(right);
+            candidates.push(left);
         }
+        let arena = self.arena;
+        let folded = candidates.into_iter().find_map(|candidate| {
+            if let ExprType::Literal(_) = candidate.expr {
+                return None;
+            }
+            let value = match candidate.clone_in(arena).const_fold().ok()?.expr {
+                ExprType::Literal(Literal::Int(i)) => i as u64,
+                ExprType::Literal(Literal::UnsignedInt(u)) => u,
+                _ => return None,
+            };
+            // show the value in the type of the constant, so `~1u` isn't shown as negative
+            let shift = 64 - candidate.ctype.sizeof().ok()? * u64::from(arch::CHAR_BIT);
+            let value = if candidate.ctype.is_signed() {
+                (((value << shift) as i64) >> shift).to_string()
+            } else {
+                format!("{}u", value << shift >> shift)
+            };
+            Some((candidate.to_c_string(), value))
+        });
+        let promoted = original.clone().integer_promote();
+        self.warn(
+            Warning::PromotedBitwise(source, original, promoted, folded),
+            location,
+        );
     }
 }
 
 // literal
-pub(super) fn literal(literal: Literal, location: Location) -> Expr {
+pub(super) fn literal<'hir>(literal: Literal, location: Location) -> Expr<'hir> {
     use crate::data::types::ArrayType;
 
     let ctype = match &literal {
@@ -897,10 +1707,23 @@ pub(super) fn literal(literal: Literal, location: Location) -> Expr {
         Literal::Int(_) => Type::Long(true),
         Literal::UnsignedInt(_) => Type::Long(false),
         Literal::Float(_) => Type::Double,
+        // the rest of the compiler only has to deal with one kind of floating constant
+        Literal::SingleFloat(f) => {
+            return Expr {
+                lval: false,
+                ctype: Type::Float,
+                location,
+                expr: ExprType::Literal(Literal::Float((*f).into())),
+            }
+        }
         Literal::Str(s) => {
             let len = s.len() as arch::SIZE_T;
             Type::Array(Box::new(Type::Char(true)), ArrayType::Fixed(len))
         }
+        Literal::WideStr(encoding, units) => {
+            let len = units.len() as arch::SIZE_T;
+            Type::Array(Box::new(encoding.element_type()), ArrayType::Fixed(len))
+        }
     };
     Expr {
         lval: false,
@@ -910,13 +1733,40 @@ pub(super) fn literal(literal: Literal, location: Location) -> Expr {
     }
 }
 
+/// The Levenshtein distance between `a` and `b`:
+/// the number of characters that have to be inserted, deleted, or replaced to turn one into the other.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    // `previous[j]` is the distance between the first `i - 1` characters of `a` and the first `j` of `b`
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let replace = previous[j] + if a_char == *b_char { 0 } else { 1 };
+            let insert = current[j] + 1;
+            let delete = previous[j + 1] + 1;
+            current.push(replace.min(insert).min(delete));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Give `left` and `right` the same pointer type, returning whether that was possible.
+///
+/// Null pointer constants are converted to the other type,
+/// and any other pointer mixed with `void *` is converted to `void *` (6.5.15p6).
 fn pointer_promote(left: &mut Expr, right: &mut Expr) -> bool {
     if left.ctype == right.ctype {
         true
-    } else if left.ctype.is_void_pointer() || left.ctype.is_char_pointer() || left.is_null() {
+    } else if left.is_null() && right.ctype.is_pointer()
+        || right.ctype.is_void_pointer() && left.ctype.is_pointer() && !right.is_null()
+    {
         left.ctype = right.ctype.clone();
         true
-    } else if right.ctype.is_void_pointer() || right.ctype.is_char_pointer() || right.is_null() {
+    } else if right.is_null() && left.ctype.is_pointer()
+        || left.ctype.is_void_pointer() && right.ctype.is_pointer()
+    {
         right.ctype = left.ctype.clone();
         true
     } else {
@@ -924,21 +1774,191 @@ fn pointer_promote(left: &mut Expr, right: &mut Expr) -> bool {
     }
 }
 
+/// Warn if converting `expr` to the pointer type `target` removes `const` from the type it points to,
+/// or adds `const` to a nested pointer in a way that could be used to modify a `const` object.
+///
+/// `target_const` is whether the innermost type of `target` is `const`,
+/// which is stored in `Variable::qualifiers` instead of `Type`.
+pub(super) fn check_pointer_qualifiers(
+    expr: &Expr,
+    target: &Type,
+    target_const: bool,
+    error_handler: &mut ErrorHandler,
+) {
+    if !expr.ctype.is_pointer() || !target.is_pointer() || expr.is_null() {
+        return;
+    }
+    let source_const = expr.pointee_qualifiers().c_const;
+    // const int *p; int *q = p;
+    if source_const && !target_const {
+        error_handler.warn(
+            Warning::DiscardedQualifiers(expr.to_c_string()),
+            expr.location,
+        );
+    // char **p; const char **q = p;
+    // this would allow `*q = "const string"; **p = 'a';`, so every level in between must be `const`
+    } else if target_const && !source_const {
+        let mut pointee = match target {
+            Type::Pointer(inner, _) => &**inner,
+            _ => unreachable!("checked to be a pointer"),
+        };
+        while let Type::Pointer(inner, qualifiers) = pointee {
+            if !qualifiers.c_const {
+                error_handler.warn(
+                    Warning::UnsafeQualifierConversion(expr.to_c_string()),
+                    expr.location,
+                );
+                return;
+            }
+            pointee = inner;
+        }
+    }
+}
+
+/// Warn if `expr` is implicitly converted to the enum `to` from a different enum,
+/// or if it's a constant that isn't one of the values of `to`.
+///
+/// `0` is always allowed, since it's commonly used as a default value.
+fn check_enum_conversion(expr: &Expr, to: &Type, error_handler: &mut ErrorHandler) {
+    let members = match to {
+        Type::Enum(_, members) => members,
+        _ => return,
+    };
+    // enum shape s = RED;
+    if let Type::Enum(..) = expr.ctype {
+        error_handler.warn(
+            Warning::EnumConversion(expr.ctype.clone(), to.clone()),
+            expr.location,
+        );
+        return;
+    }
+    // enum color c = 17; c = RED + 1;
+    let scratch = Arena::new();
+    let value = match expr
+        .clone_in(&scratch)
+        .const_fold()
+        .map(|folded| folded.expr)
+    {
+        Ok(ExprType::Literal(Literal::Int(i))) => i,
+        Ok(ExprType::Literal(Literal::UnsignedInt(u))) => u as i64,
+        _ => return,
+    };
+    if value != 0 && !members.iter().any(|&(_, member)| member == value) {
+        error_handler.warn(Warning::AssignEnum(value, to.clone()), expr.location);
+    }
+}
+
+/// Whether `from` can be implicitly converted to `to` by adding qualifiers.
+///
+/// Standard (in the context `left = right`, i.e. casting `right` to `left`):
+/// > the left operand has atomic, qualified, or unqualified pointer type,
+/// > and (considering the type the left operand would have after lvalue conversion)
+/// > both operands are pointers to qualified or unqualified versions of compatible types,
+/// > and the type pointed to by the left has all the qualifiers of the type pointed to by the right;
+///
+/// The qualifiers of the pointers themselves don't matter, since the value is an rvalue.
+/// The qualifiers of the innermost type aren't part of `Type`, so only nested pointers are checked.
+fn qualification_conversion(from: &Type, to: &Type) -> bool {
+    match (from, to) {
+        (Type::Pointer(a, _), Type::Pointer(b, _)) => match (&**a, &**b) {
+            // int ** -> int *const *
+            (Type::Pointer(a, from), Type::Pointer(b, to)) => a == b && to.contains_all(*from),
+            // int * -> int *const
+            (a, b) => a == b,
+        },
+        _ => false,
+    }
+}
+
+/// If `expr` is `~c`, `c << n`, `~c >> n`, or `c & ~m` (or `|` or `^`)
+/// for an `unsigned char` or `unsigned short` `c`, return the type of `c` before it was promoted.
+///
+/// `c >> n` on its own is left out, since it has the same value as if `c` had never been promoted.
+fn promoted_bitwise_operand(expr: &Expr) -> Option<Type> {
+    let promoted_from = |operand: &Expr| match &operand.expr {
+        ExprType::Cast(inner) if matches!(inner.ctype, Type::Char(false) | Type::Short(false)) => {
+            Some(inner.ctype.clone())
+        }
+        _ => None,
+    };
+    let negated = |operand| matches!(without_widening(operand).expr, ExprType::BitwiseNot(_));
+    match &expr.expr {
+        ExprType::BitwiseNot(operand) | ExprType::Binary(BinaryOp::Shl, operand, _) => {
+            promoted_from(operand)
+        }
+        ExprType::Binary(BinaryOp::Shr, operand, _) => {
+            promoted_bitwise_operand(without_widening(operand))
+        }
+        ExprType::Binary(BinaryOp::BitwiseAnd, left, right)
+        | ExprType::Binary(BinaryOp::BitwiseOr, left, right)
+        | ExprType::Binary(BinaryOp::Xor, left, right) => {
+            if negated(right) {
+                promoted_from(left)
+            } else if negated(left) {
+                promoted_from(right)
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+// `(long)~c` -> `~c`, but `(unsigned char)~c` is left alone
+fn without_widening<'a, 'hir>(mut expr: &'a Expr<'hir>) -> &'a Expr<'hir> {
+    while let ExprType::Cast(inner) = &expr.expr {
+        let widening = inner.ctype.is_integral()
+            && inner.ctype.clone().integer_promote() == inner.ctype
+            && inner.ctype.sizeof().ok() <= expr.ctype.sizeof().ok();
+        if !widening {
+            break;
+        }
+        expr = inner;
+    }
+    expr
+}
+
+/// Whether `left` and `right` point to different object types, like `int *` and `char *`.
+///
+/// ISO C requires a diagnostic when these are mixed, but they still have the same representation,
+/// so the caller should warn instead of giving an error.
+fn distinct_object_pointers(left: &Type, right: &Type) -> bool {
+    match (left, right) {
+        (Type::Pointer(a, _), Type::Pointer(b, _)) => {
+            a != b && !a.is_function() && !b.is_function() && **a != Type::Void && **b != Type::Void
+        }
+        _ => false,
+    }
+}
+
 impl Type {
     #[inline]
-    fn is_void_pointer(&self) -> bool {
+    pub(crate) fn is_void_pointer(&self) -> bool {
         match self {
             Type::Pointer(t, _) => **t == Type::Void,
             _ => false,
         }
     }
+    /// Whether `self` is a pointer to a function, like `int (*)(int)`.
     #[inline]
-    fn is_char_pointer(&self) -> bool {
+    pub(crate) fn is_function_pointer(&self) -> bool {
         match self {
-            Type::Pointer(t, _) => match **t {
-                Type::Char(_) => true,
-                _ => false,
-            },
+            Type::Pointer(t, _) => t.is_function(),
+            _ => false,
+        }
+    }
+    /// Whether `self` is a pointer to an object or incomplete type, including `void *`.
+    #[inline]
+    pub(crate) fn is_object_pointer(&self) -> bool {
+        self.is_pointer() && !self.is_function_pointer()
+    }
+    /// Whether `self` and `other` are pointers to the same type.
+    ///
+    /// The qualifiers of the pointers themselves are ignored, since they don't apply to rvalues:
+    /// `f` decays to `int (*const)(int)`, but it can still be compared to `int (*fp)(int)`.
+    pub(crate) fn is_compatible_pointer(&self, other: &Type) -> bool {
+        match (self, other) {
+            (Type::Pointer(left, _), Type::Pointer(right, _)) => left == right,
             _ => false,
         }
     }
@@ -986,16 +2006,18 @@ impl Type {
             Bool => 0,
             Char(_) => 1,
             Short(_) => 2,
-            Int(_) => 3,
+            // enums are compatible with `int`, and have the same rank
+            Int(_) | Enum(_, _) => 3,
             Long(_) => 4,
             // don't make this 5 in case we add `long long` at some point
             _ => std::usize::MAX,
         }
     }
     // Subclause 2 of 6.3.1.1 Boolean, characters, and integers
-    fn integer_promote(self) -> Type {
+    pub(crate) fn integer_promote(self) -> Type {
         if self.rank() <= Type::Int(true).rank() {
-            if Type::Int(true).can_represent(&self) {
+            // the enum's own size isn't necessarily that of `int`, but its values always fit
+            if Type::Int(true).can_represent(&self) || matches!(self, Type::Enum(..)) {
                 Type::Int(true)
             } else {
                 Type::Int(false)
@@ -1005,7 +2027,7 @@ impl Type {
         }
     }
     // 6.3.1.8 Usual arithmetic conversions
-    fn binary_promote(mut left: Type, mut right: Type) -> Result<Type, Type> {
+    pub(crate) fn binary_promote(mut left: Type, mut right: Type) -> Result<Type, Type> {
         use Type::*;
         if left == Double || right == Double {
             return Ok(Double); // toil and trouble
@@ -1044,7 +2066,7 @@ impl Type {
     /// > the integer promotions are performed on each argument,
     /// > and arguments that have type float are promoted to double.
     /// > These are called the default argument promotions.
-    fn default_promote(self) -> Type {
+    pub(crate) fn default_promote(self) -> Type {
         if self.is_integral() {
             self.integer_promote()
         } else if self == Type::Float {
@@ -1053,6 +2075,20 @@ impl Type {
             self
         }
     }
+    /// Whether an argument of this type can be passed without a prototype
+    /// to a function defined with a parameter of type `param` (6.5.2.2p6).
+    pub(super) fn is_promoted_compatible(&self, param: &Type) -> bool {
+        let param = param.clone().default_promote();
+        match (self, &param) {
+            // `f(1u)` for `int f(int)` is allowed, and so is `f((void *)p)` for `int f(char *)`.
+            // Integer constants are `long`, so the sizes of integers aren't compared either.
+            (arg, param) if arg.is_integral() && param.is_integral() => true,
+            (Type::Pointer(..), Type::Pointer(..)) => true,
+            // already reported
+            (Type::Error, _) | (_, Type::Error) => true,
+            (arg, param) => arg == param,
+        }
+    }
     fn is_struct(&self) -> bool {
         match self {
             Type::Struct(_) | Type::Union(_) => true,
@@ -1068,8 +2104,8 @@ impl Type {
     }
 }
 
-impl Expr {
-    pub(super) fn zero(location: Location) -> Expr {
+impl<'hir> Expr<'hir> {
+    pub(super) fn zero(location: Location) -> Self {
         Expr {
             ctype: Type::Int(true),
             expr: ExprType::Literal(Literal::Int(0)),
@@ -1077,16 +2113,33 @@ impl Expr {
             location,
         }
     }
+    /// A placeholder for an expression that had an error.
+    ///
+    /// Its type is `Type::Error`, so expressions that use it don't report any more errors.
+    pub(super) fn error(location: Location) -> Self {
+        Expr {
+            ctype: Type::Error,
+            ..Expr::zero(location)
+        }
+    }
+    /// Whether an error has already been reported for this expression.
+    pub(super) fn is_error(&self) -> bool {
+        self.ctype == Type::Error
+    }
     // 6.3.2.3 Pointers
-    fn is_null(&self) -> bool {
-        // TODO: I think we need to const fold this to allow `(void*)0`
-        if let ExprType::Literal(token) = &self.expr {
-            match token {
-                Literal::Int(0) | Literal::UnsignedInt(0) | Literal::Char(0) => true,
-                _ => false,
+    pub(crate) fn is_null(&self) -> bool {
+        match &self.expr {
+            ExprType::Literal(token) => matches!(
+                token,
+                Literal::Int(0) | Literal::UnsignedInt(0) | Literal::Char(0)
+            ),
+            // 6.3.2.3p3: "An integer constant expression with the value 0,
+            // or such an expression cast to type void *, is called a null pointer constant."
+            // This is also what C2x `nullptr` desugars to.
+            ExprType::Cast(inner) => {
+                matches!(&self.ctype, Type::Pointer(to, _) if **to == Type::Void) && inner.is_null()
             }
-        } else {
-            false
+            _ => false,
         }
     }
     fn id(symbol: Symbol, location: Location) -> Self {
@@ -1106,54 +2159,96 @@ impl Expr {
     // TODO: this looks like the same as casting to _Bool, can we just offload to the backend instead?
     //
     // if (expr)
-    pub(crate) fn truthy(mut self, error_handler: &mut ErrorHandler) -> Expr {
-        self = self.rval();
+    pub(crate) fn truthy(
+        mut self,
+        arena: &'hir Arena<'hir>,
+        error_handler: &mut ErrorHandler,
+    ) -> Self {
+        self = self.rval(arena);
         if self.ctype == Type::Bool {
             return self;
         }
+        // `a < b` is an `int`, but there's no need to compare it to 0 again
+        if let ExprType::Cast(inner) = &mut self.expr {
+            if inner.ctype == Type::Bool && self.ctype.is_integral() {
+                return std::mem::replace(inner, Expr::zero(self.location));
+            }
+        }
+        if self.is_error() {
+            return Expr {
+                ctype: Type::Bool,
+                ..self
+            };
+        }
         if !self.ctype.is_scalar() {
             error_handler.error(
-                SemanticError::Generic(format!(
-                    "expression of type '{}' cannot be converted to bool",
-                    self.ctype
-                )),
+                SemanticError::NotConvertibleToBool(self.ctype.clone()),
                 self.location,
             );
             self.ctype = Type::Error;
         }
-        let zero = Expr::zero(self.location).implicit_cast(&self.ctype, error_handler);
+        let zero = Expr::zero(self.location).implicit_cast(&self.ctype, arena, error_handler);
         Expr {
             lval: false,
             location: self.location,
             ctype: Type::Bool,
             expr: ExprType::Binary(
                 BinaryOp::Compare(ComparisonToken::NotEqual),
-                Box::new(self),
-                Box::new(zero),
+                arena.expr(self),
+                arena.expr(zero),
             ),
         }
     }
 
+    // The result of `!`, `&&`, `||`, and the comparison operators is an `int`, not a `_Bool`.
+    //
+    // The backend computes these as booleans, so convert them here.
+    fn bool_to_int(self, arena: &'hir Arena<'hir>) -> Self {
+        debug_assert_eq!(self.ctype, Type::Bool);
+        Expr {
+            lval: false,
+            location: self.location,
+            ctype: Type::Int(true),
+            expr: ExprType::Cast(arena.expr(self)),
+        }
+    }
+
     // Perform an integer conversion, including all relevant casts.
     //
     // See `Type::integer_promote` for conversion rules.
-    fn integer_promote(self, error_handler: &mut ErrorHandler) -> Expr {
-        let expr = self.rval();
+    pub(super) fn integer_promote(
+        self,
+        arena: &'hir Arena<'hir>,
+        error_handler: &mut ErrorHandler,
+    ) -> Self {
+        let expr = self.rval(arena);
         let ctype = expr.ctype.clone().integer_promote();
-        expr.implicit_cast(&ctype, error_handler)
+        expr.implicit_cast(&ctype, arena, error_handler)
     }
 
     // Perform a binary conversion, including all relevant casts.
     //
     // See `Type::binary_promote` for conversion rules.
-    fn binary_promote(left: Expr, right: Expr, error_handler: &mut ErrorHandler) -> (Expr, Expr) {
-        let (left, right) = (left.rval(), right.rval());
+    fn binary_promote(
+        left: Self,
+        right: Self,
+        arena: &'hir Arena<'hir>,
+        error_handler: &mut ErrorHandler,
+    ) -> (Self, Self) {
+        let (left, right) = (left.rval(arena), right.rval(arena));
+        if left.is_error() || right.is_error() {
+            return (left, right);
+        }
         let ctype = Type::binary_promote(left.ctype.clone(), right.ctype.clone());
         match ctype {
-            Ok(promoted) => (
-                left.implicit_cast(&promoted, error_handler),
-                right.implicit_cast(&promoted, error_handler),
-            ),
+            Ok(promoted) => {
+                left.check_float_promotion(&promoted, error_handler);
+                right.check_float_promotion(&promoted, error_handler);
+                (
+                    left.implicit_cast(&promoted, arena, error_handler),
+                    right.implicit_cast(&promoted, arena, error_handler),
+                )
+            }
             Err(non_int) => {
                 // TODO: this location is wrong
                 error_handler.error(SemanticError::NonIntegralExpr(non_int), right.location);
@@ -1161,6 +2256,70 @@ impl Expr {
             }
         }
     }
+    /// Warn if converting this operand to the floating type `promoted` loses precision.
+    ///
+    /// `int` to `float` also rounds large values, but it's too common to warn about,
+    /// so only integers wider than the floating type are reported.
+    /// Constants are only reported if their value actually changes.
+    fn check_float_promotion(&self, promoted: &Type, error_handler: &mut ErrorHandler) {
+        let mantissa = match promoted.mantissa_bits() {
+            Some(bits) if self.ctype != *promoted => bits,
+            _ => return,
+        };
+        let scratch = Arena::new();
+        let folded = self
+            .clone_in(&scratch)
+            .const_fold()
+            .map(|folded| folded.expr);
+        if self.ctype.is_integral() {
+            let value = match &folded {
+                Ok(ExprType::Literal(Literal::Int(i))) => i128::from(*i),
+                Ok(ExprType::Literal(Literal::UnsignedInt(u))) => i128::from(*u),
+                Ok(ExprType::Literal(Literal::Char(c))) => i128::from(*c),
+                // `l + 1.0f`
+                _ => {
+                    if self.ctype.sizeof().ok() > promoted.sizeof().ok() {
+                        let warning = Warning::ImplicitIntFloatConversion(
+                            self.ctype.clone(),
+                            promoted.clone(),
+                        );
+                        error_handler.warn(warning, self.location);
+                    }
+                    return;
+                }
+            };
+            // `f == 16777217`: count the bits between the highest and lowest set bits
+            let magnitude = value.unsigned_abs();
+            let significant = match magnitude {
+                0 => 0,
+                _ => 128 - magnitude.leading_zeros() - magnitude.trailing_zeros(),
+            };
+            if significant > mantissa {
+                let converted = match promoted {
+                    Type::Float => f64::from(value as f32),
+                    _ => value as f64,
+                };
+                let original = match folded {
+                    Ok(ExprType::Literal(literal)) => literal,
+                    _ => unreachable!("only literals have values"),
+                };
+                let warning = Warning::InexactIntFloatConstant(
+                    self.ctype.clone(),
+                    promoted.clone(),
+                    original,
+                    Literal::Float(converted),
+                );
+                error_handler.warn(warning, self.location);
+            }
+        // `d == 0.1f`
+        } else if let Ok(ExprType::Literal(Literal::Float(f))) = folded {
+            // the shortest way to write the `float` is probably how it was written in the source
+            let single = f as f32;
+            if self.ctype == Type::Float && f.is_finite() && single.to_string().parse() != Ok(f) {
+                error_handler.warn(Warning::FloatConstantPromotion(single), self.location);
+            }
+        }
+    }
     // ensure an expression has a value. convert
     // - arrays -> pointers
     // - functions -> pointers
@@ -1168,7 +2327,7 @@ impl Expr {
     // 6.3.2.1 Lvalues, arrays, and function designators
     // >  Except when it is the operand of [a bunch of different operators],
     // > an lvalue that does not have array type is converted to the value stored in the designated object (and is no longer an lvalue)
-    pub(super) fn rval(self) -> Expr {
+    pub(super) fn rval(self, arena: &'hir Arena<'hir>) -> Self {
         match self.ctype {
             // a + 1 is the same as &a + 1
             Type::Array(to, _) => Expr {
@@ -1196,7 +2355,7 @@ impl Expr {
                 ctype: self.ctype.clone(),
                 lval: false,
                 location: self.location,
-                expr: ExprType::Deref(Box::new(self)),
+                expr: ExprType::Deref(arena.expr(self)),
             },
             _ => self,
         }
@@ -1210,7 +2369,7 @@ impl Expr {
     // `ctype` is the type of the resulting expression
     //
     // 6.5.3.2 Address and indirection operators
-    fn indirection(self, lval: bool, ctype: Type) -> Self {
+    fn indirection(self, lval: bool, ctype: Type, arena: &'hir Arena<'hir>) -> Self {
         Expr {
             location: self.location,
             ctype,
@@ -1218,54 +2377,94 @@ impl Expr {
             // this is super hacky but the only way I can think of to prevent
             // https://github.com/jyn514/rcc/issues/90
             // we need to call `self.rval()` so that if `self` is a variable we get its value, not its address.
-            expr: ExprType::Noop(Box::new(self.rval())),
+            expr: ExprType::Noop(arena.expr(self.rval(arena))),
         }
     }
 
+    // 6.5.16.1 Simple assignment
+    // Convert `self` as if assigning it to an object of type `ctype`.
+    // This is also used for `return` and for initializers, which convert the same way (6.8.6.4p3, 6.7.9p11).
+    // `const_pointee` is whether the innermost type of `ctype` is `const`.
+    pub(super) fn assignment_conversion(
+        self,
+        ctype: &Type,
+        const_pointee: bool,
+        arena: &'hir Arena<'hir>,
+        error_handler: &mut ErrorHandler,
+    ) -> Self {
+        let expr = self.rval(arena);
+        check_pointer_qualifiers(&expr, ctype, const_pointee, error_handler);
+        expr.implicit_cast(ctype, arena, error_handler)
+    }
+
     // float f = (double)1.0
     // 6.3 Conversions
-    pub(super) fn implicit_cast(self, ctype: &Type, error_handler: &mut ErrorHandler) -> Expr {
-        let mut expr = self.rval();
-        if &expr.ctype == ctype {
+    pub(super) fn implicit_cast(
+        self,
+        ctype: &Type,
+        arena: &'hir Arena<'hir>,
+        error_handler: &mut ErrorHandler,
+    ) -> Self {
+        let mut expr = self.rval(arena);
+        let cast = |expr: Expr<'hir>, ctype: &Type| Expr {
+            location: expr.location,
+            expr: ExprType::Cast(arena.expr(expr)),
+            lval: false,
+            ctype: ctype.clone(),
+        };
+        let from = &expr.ctype;
+        if from == ctype || expr.is_error() || *ctype == Type::Error {
+            expr
+        // struct a = struct b
+        } else if from.is_struct() && ctype.is_struct() {
+            error_handler.error(
+                SemanticError::IncompatibleAggregate(ctype.clone(), from.clone()),
+                expr.location,
+            );
             expr
+        // enum shape s = RED; enum color c = 17;
+        } else if from.is_arithmetic() && matches!(ctype, Type::Enum(..)) {
+            check_enum_conversion(&expr, ctype, error_handler);
+            cast(expr, ctype)
         // int -> long
-        } else if expr.ctype.is_arithmetic() && ctype.is_arithmetic()
-            // NULL -> int*
+        // the value may change, which `-Wconversion` checks separately
+        } else if from.is_arithmetic() && ctype.is_arithmetic()
+            // NULL -> int*, NULL -> int (*)(int)
             || expr.is_null() && ctype.is_pointer()
             // if ((int*)p)
-            || expr.ctype.is_pointer() && ctype.is_bool()
-            // p -> void*
-            || expr.ctype.is_pointer() && ctype.is_void_pointer()
-            // p -> char*
-            || expr.ctype.is_pointer() && ctype.is_char_pointer()
+            || from.is_pointer() && ctype.is_bool()
+            // int* -> void*, void* -> int*
+            || from.is_object_pointer() && ctype.is_void_pointer()
+            || from.is_void_pointer() && ctype.is_object_pointer()
         {
-            Expr {
-                location: expr.location,
-                expr: ExprType::Cast(Box::new(expr)),
-                lval: false,
-                ctype: ctype.clone(),
-            }
-        // `NULL -> int*` or `void* -> int*` or `char* -> int*`
-        } else if ctype.is_pointer()
-            && (expr.is_null() || expr.ctype.is_void_pointer() || expr.ctype.is_char_pointer())
+            cast(expr, ctype)
+        // int (*)(int) -> void*, void* -> int (*)(int)
+        // POSIX requires this to work for `dlsym`, but ISO C doesn't define it
+        } else if from.is_function_pointer() && ctype.is_void_pointer()
+            || from.is_void_pointer() && ctype.is_function_pointer()
         {
+            error_handler.warn(
+                Warning::FunctionPointerConversion(from.clone(), ctype.clone()),
+                expr.location,
+            );
+            cast(expr, ctype)
+        // int* -> int *const, int** -> int *const*
+        } else if qualification_conversion(from, ctype) {
             expr.ctype = ctype.clone();
             expr
-        } else if expr.ctype == Type::Error {
-            expr
+        // int* -> char*, int* -> int (*)(int)
+        } else if distinct_object_pointers(from, ctype)
+            || from.is_function_pointer() && ctype.is_object_pointer()
+            || from.is_object_pointer() && ctype.is_function_pointer()
+        {
+            error_handler.warn(
+                Warning::IncompatiblePointerTypes(from.clone(), ctype.clone()),
+                expr.location,
+            );
+            cast(expr, ctype)
         } else {
-            // allow implicit casts of const pointers
-            // Standard (in the context `left = right`, i.e. casting `right` to `left`)
-            // > the left operand has atomic, qualified, or unqualified pointer type,
-            // > and (considering the type the left operand would have after lvalue conversion)
-            // > both operands are pointers to qualified or unqualified versions of compatible types,
-            // > and the type pointed to by the left has all the qualifiers of the type pointed to by the right;
-            if let (Type::Pointer(a, from), Type::Pointer(b, to)) = (&expr.ctype, ctype) {
-                if *a == *b && from.contains_all(*to) {
-                    expr.ctype = ctype.clone();
-                    return expr;
-                }
-            }
+            // int -> int*, int* -> long, int (*)(int) -> int (*)(void), struct s -> int
+            // these all need an explicit cast, if they're allowed at all
             error_handler.error(
                 SemanticError::InvalidCast(expr.ctype.clone(), ctype.clone()),
                 expr.location,
@@ -1280,59 +2479,231 @@ impl Expr {
     fn modifiable_lval(&self) -> Result<(), SemanticError> {
         let err = |e| Err(SemanticError::NotAssignable(e));
         // rval
-        if !self.lval {
-            return err("rvalue".to_string());
+        if !self.lval || self.is_member_of_rvalue() {
+            return err(NotAssignable::Rvalue);
         }
         // incomplete type
         if !self.ctype.is_complete() {
-            return err(format!("expression with incomplete type '{}'", self.ctype));
-        }
-        // const-qualified type
-        // TODO: handle `*const`
-        if let ExprType::Id(sym) = &self.expr {
-            let meta = sym.get();
-            if meta.qualifiers.c_const {
-                return err(format!("variable '{}' with `const` qualifier", meta.id));
-            }
+            return err(NotAssignable::IncompleteType(self.ctype.clone()));
         }
         match &self.ctype {
             // array type
-            Type::Array(_, _) => err("array".to_string()),
+            Type::Array(_, _) => return err(NotAssignable::Array),
             // member with const-qualified type
             Type::Struct(stype) | Type::Union(stype) => {
-                if stype
-                    .members()
-                    .iter()
-                    .map(|sym| sym.qualifiers.c_const)
-                    .any(|x| x)
-                {
-                    err("struct or union with `const` qualified member".to_string())
+                if let Some(member) = const_member(stype) {
+                    return err(NotAssignable::HasConstMember(member));
+                }
+            }
+            _ => {}
+        }
+        // const-qualified type
+        match self.const_source() {
+            Some(source) => err(source),
+            None => Ok(()),
+        }
+    }
+    /// Whether this is a member of a struct that isn't an lvalue, like `f().a`.
+    ///
+    /// These are marked as lvalues so the backend loads them from memory,
+    /// but 6.5.2.3p3 says they aren't: "The value is that of the named member,
+    /// and is an lvalue if the first expression is an lvalue."
+    fn is_member_of_rvalue(&self) -> bool {
+        match &self.expr {
+            ExprType::Member(base, _) => match &base.expr {
+                // `p->a` is `(*p).a`, which is always an lvalue
+                ExprType::Noop(_) => false,
+                ExprType::Member(..) => base.is_member_of_rvalue(),
+                _ => !base.lval,
+            },
+            _ => false,
+        }
+    }
+    /// If this lvalue is `const`, the reason why.
+    ///
+    /// `const` can come from the variable itself, from a member of a struct,
+    /// from the struct containing a member, or from a pointer to `const` data.
+    fn const_source(&self) -> Option<NotAssignable> {
+        match &self.expr {
+            // const int i; i = 1;
+            ExprType::Id(sym) => {
+                let meta = sym.get();
+                if meta.is_const() {
+                    Some(NotAssignable::ConstVariable(meta.id))
                 } else {
-                    Ok(())
+                    None
                 }
             }
-            _ => Ok(()),
+            // struct { const int x; } s; s.x = 1;
+            ExprType::Member(base, id) => {
+                let member = match &base.ctype {
+                    Type::Struct(stype) | Type::Union(stype) => stype
+                        .members()
+                        .iter()
+                        .find(|member| member.id == *id)
+                        .cloned(),
+                    _ => None,
+                };
+                if member.is_some_and(|member| member.is_const()) {
+                    Some(NotAssignable::ConstMember(*id))
+                } else {
+                    // const struct s s; s.x = 1;
+                    base.const_source()
+                }
+            }
+            // int *const *pp; *pp = 0;
+            // const int *p; *p = 1;
+            ExprType::Noop(pointer) => {
+                let is_const = match &self.ctype {
+                    Type::Pointer(_, qualifiers) => qualifiers.c_const,
+                    _ => pointer.pointee_qualifiers().c_const,
+                };
+                if is_const {
+                    Some(NotAssignable::ReadOnlyLocation(self.to_c_string()))
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+    /// The qualifiers of the object this lvalue designates.
+    ///
+    /// Like `const_source`, this includes the qualifiers of the struct or union containing a member
+    /// and of the type a pointer points to, so `v.x` is `volatile` if `v` is.
+    fn lval_qualifiers(&self) -> Qualifiers {
+        match &self.expr {
+            ExprType::Id(sym) => sym.get().own_qualifiers(),
+            ExprType::Member(base, id) => {
+                let member = match &base.ctype {
+                    Type::Struct(stype) | Type::Union(stype) => stype
+                        .members()
+                        .iter()
+                        .find(|member| member.id == *id)
+                        .map(Variable::own_qualifiers),
+                    _ => None,
+                };
+                member.unwrap_or_default().union(base.lval_qualifiers())
+            }
+            ExprType::Noop(pointer) => match &self.ctype {
+                Type::Pointer(_, qualifiers) => *qualifiers,
+                _ => pointer.pointee_qualifiers(),
+            },
+            _ => Qualifiers::default(),
+        }
+    }
+    /// Whether reading or writing this lvalue is a `volatile` access.
+    pub(super) fn is_volatile(&self) -> bool {
+        self.lval && self.lval_qualifiers().volatile
+    }
+    /// Convert an expression whose value isn't used, like `x;` or the left side of `x, y`.
+    ///
+    /// Reading a `volatile` object is a side effect (5.1.2.3), so it still has to be loaded.
+    pub(super) fn discard(self, arena: &'hir Arena<'hir>) -> Self {
+        if self.is_volatile() && self.ctype.is_scalar() {
+            self.rval(arena)
+        } else {
+            self
+        }
+    }
+    /// The qualifiers of the innermost type this expression points to.
+    ///
+    /// This is only tracked for variables and members, since the qualifiers of the innermost type
+    /// are stored in `Variable`, not `Type`. Casts are assumed to remove qualifiers.
+    fn pointee_qualifiers(&self) -> Qualifiers {
+        match &self.expr {
+            // const int *p; const int a[3]; const int i; &i
+            ExprType::Id(sym) => sym.get().qualifiers,
+            ExprType::Member(base, id) => match &base.ctype {
+                Type::Struct(stype) | Type::Union(stype) => {
+                    let members = stype.members();
+                    let member = members.iter().find(|member| member.id == *id);
+                    member.map_or_else(Qualifiers::default, |member| {
+                        if member.ctype.is_pointer() {
+                            // struct { const int *p; } s; *s.p
+                            member.qualifiers
+                        } else {
+                            // const struct { int a[3]; } s; s.a[0]
+                            member.qualifiers.union(base.lval_qualifiers())
+                        }
+                    })
+                }
+                _ => Qualifiers::default(),
+            },
+            // p + 1
+            ExprType::Binary(BinaryOp::Add, left, right)
+            | ExprType::Binary(BinaryOp::Sub, left, right) => {
+                if left.ctype.is_pointer() {
+                    left.pointee_qualifiers()
+                } else {
+                    right.pointee_qualifiers()
+                }
+            }
+            ExprType::Ternary(_, then, otherwise) => then
+                .pointee_qualifiers()
+                .union(otherwise.pointee_qualifiers()),
+            // const char *f(void); f()
+            // the qualifiers of a function apply to the innermost type it returns
+            ExprType::FuncCall(func, _) => func.pointee_qualifiers(),
+            ExprType::Deref(inner)
+            | ExprType::Noop(inner)
+            | ExprType::StaticRef(inner)
+            | ExprType::Comma(_, inner)
+            | ExprType::Let(_, _, inner) => inner.pointee_qualifiers(),
+            _ => Qualifiers::default(),
         }
     }
 }
 
+/// The first `const` member of a struct or union, including members of nested structs.
+///
+/// This is why the struct can't be assigned to as a whole.
+fn const_member(stype: &StructType) -> Option<InternedStr> {
+    stype.members().iter().find_map(|member| {
+        if member.is_const() {
+            return Some(member.id);
+        }
+        let mut ctype = &member.ctype;
+        // struct { struct { const int x; } a[2]; }
+        while let Type::Array(inner, _) = ctype {
+            ctype = inner;
+        }
+        match ctype {
+            Type::Struct(inner) | Type::Union(inner) => const_member(inner),
+            _ => None,
+        }
+    })
+}
+
 impl Qualifiers {
     // return whether `self` has all the qualifiers of `right`
     // WARNING: this _must_ be updated if you add more fields to `Qualifiers`
     fn contains_all(self, other: Self) -> bool {
-        (self.c_const || !other.c_const) && (self.volatile || !other.volatile)
+        (self.c_const || !other.c_const)
+            && (self.volatile || !other.volatile)
+            && (self.restrict || !other.restrict)
+    }
+    // return the type qualifiers in either `self` or `other`; function specifiers come from `self`
+    // WARNING: this _must_ be updated if you add more fields to `Qualifiers`
+    fn union(self, other: Self) -> Self {
+        Qualifiers {
+            c_const: self.c_const || other.c_const,
+            volatile: self.volatile || other.volatile,
+            restrict: self.restrict || other.restrict,
+            func: self.func,
+        }
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::analyze::test::analyze;
+    use crate::analyze::test::{analyze, arena, assert_decl_display, decls};
     use crate::analyze::*;
-    pub(crate) fn expr(input: &str) -> CompileResult<Expr> {
+    pub(crate) fn expr(input: &str) -> CompileResult<Expr<'static>> {
         analyze(input, Parser::expr, PureAnalyzer::expr)
     }
-    fn get_location(r: &CompileResult<Expr>) -> Location {
+    fn get_location(r: &CompileResult<Expr<'static>>) -> Location {
         match r {
             Ok(expr) => expr.location,
             Err(err) => err.location(),
@@ -1343,14 +2714,8 @@ mod test {
         let location = get_location(&parsed);
         assert_eq!(parsed.unwrap(), literal(token, location));
     }
-    fn expr_with_scope<'a>(input: &'a str, variables: &[Symbol]) -> CompileResult<Expr> {
-        analyze(input, Parser::expr, |a, expr| {
-            for &meta in variables {
-                let id = meta.get().id;
-                a.scope.insert(id, meta);
-            }
-            a.expr(expr)
-        })
+    fn expr_with_scope(input: &str, variables: &[Symbol]) -> CompileResult<Expr<'static>> {
+        PureAnalyzer::with_scope(arena(), variables.iter().copied()).parse_expr_str(input)
     }
     fn assert_type(input: &str, ctype: Type) {
         match expr(input) {
@@ -1378,6 +2743,7 @@ mod test {
             id: InternedStr::get_or_intern("x"),
             qualifiers: Default::default(),
             storage_class: Default::default(),
+            location: Location::default(),
         }
         .insert();
         let parsed = expr_with_scope("x", &[x]);
@@ -1392,12 +2758,81 @@ mod test {
         );
     }
     #[test]
+    fn test_wide_strings() {
+        use crate::data::{error::SyntaxError, types::ArrayType, Encoding};
+        let array = |elem, len| Type::Array(Box::new(elem), ArrayType::Fixed(len));
+        assert_type("L\"ab\"", array(Type::Int(true), 3));
+        assert_type("u\"ab\"", array(Type::Short(false), 3));
+        assert_type("U\"ab\"", array(Type::Int(false), 3));
+        assert_type("u8\"ab\"", array(Type::Char(true), 3));
+        // the prefix of one string applies to the whole concatenation
+        let parsed = expr("\"a\" L\"b\" \"c\"").unwrap();
+        match parsed.expr {
+            ExprType::Literal(Literal::WideStr(Encoding::Wide, units)) => {
+                assert_eq!(units, [97, 98, 99, 0])
+            }
+            other => panic!("expected a wide string, got {:?}", other),
+        }
+        match expr("L\"a\" u\"b\"").unwrap_err().data {
+            Error::Syntax(SyntaxError::MixedStringPrefixes(Encoding::Wide, Encoding::Utf16)) => {}
+            other => panic!("expected mixed prefixes, got {}", other),
+        }
+        match expr("u\"\\x12345\"").unwrap_err().data {
+            Error::Semantic(SemanticError::CharacterTooLarge(0x12345, Type::Short(false))) => {}
+            other => panic!("expected a character that was too large, got {}", other),
+        }
+    }
+    #[test]
     fn test_mul() {
         assert_type("1*1.0", Type::Double);
         assert_type("1*2.0 / 1.3", Type::Double);
         assert_type("3%2", Type::Long(true));
     }
     #[test]
+    fn test_unary() {
+        assert_type("-'a'", Type::Int(true));
+        assert_type("+(char)1", Type::Int(true));
+        assert_type("-1.5", Type::Double);
+        assert_type("~(short)1", Type::Int(true));
+        assert_type("~1ul", Type::Long(false));
+        assert_type("!1.5", Type::Int(true));
+        assert_type("!0", Type::Int(true));
+        let p = Variable {
+            ctype: Type::Pointer(Box::new(Type::Char(true)), Qualifiers::default()),
+            id: InternedStr::get_or_intern("p"),
+            qualifiers: Default::default(),
+            storage_class: Default::default(),
+            location: Location::default(),
+        }
+        .insert();
+        assert_eq!(expr_with_scope("!p", &[p]).unwrap().ctype, Type::Int(true));
+        let err = |input| expr_with_scope(input, &[p]).unwrap_err().data;
+        let char_ptr = p.get().ctype.clone();
+        assert_eq!(
+            err("~1.0"),
+            SemanticError::InvalidUnaryOperand("~", "integer", Type::Double).into()
+        );
+        assert_eq!(
+            err("-p"),
+            SemanticError::InvalidUnaryOperand("-", "float or integer", char_ptr.clone()).into()
+        );
+        assert_eq!(
+            err("+p"),
+            SemanticError::InvalidUnaryOperand("+", "float or integer", char_ptr).into()
+        );
+        assert_eq!(
+            err("!(void)0"),
+            SemanticError::InvalidUnaryOperand("!", "scalar", Type::Void).into()
+        );
+        assert_eq!(
+            SemanticError::InvalidUnaryOperand("~", "integer", Type::Double).to_string(),
+            "expected integer type for operand of unary ~, got 'double'"
+        );
+        // constant folding
+        assert_decl_display("int a[~0 & 7];", "int a[7];");
+        assert_decl_display("int a[-(-3) + !0 + !1.5 + +4];", "int a[8];");
+    }
+    #[test]
     fn test_funcall() {
         let f = Variable {
             id: InternedStr::get_or_intern("f"),
@@ -1409,11 +2844,13 @@ mod test {
                     id: Default::default(),
                     qualifiers: Default::default(),
                     storage_class: StorageClass::Auto,
+                    location: Location::default(),
                 }
                 .insert()],
                 return_type: Box::new(Type::Int(true)),
                 varargs: false,
             }),
+            location: Location::default(),
         }
         .insert();
         assert!(expr_with_scope("f(1,2,3)", &[f]).is_err());
@@ -1425,6 +2862,217 @@ mod test {
             }) => true,
             _ => false,
         },);
+
+        // every error in `program`, along with the source text it points to
+        let errors = |program: &'static str| {
+            let text = move |location: Location| {
+                &program[location.span.start as usize..location.span.end as usize]
+            };
+            decls(program)
+                .into_iter()
+                .filter_map(Result::err)
+                .map(|err| {
+                    let notes: Vec<_> = err
+                        .data
+                        .notes()
+                        .into_iter()
+                        .map(|note| (note.data, text(note.location)))
+                        .collect();
+                    (err.data.to_string(), text(err.location), notes)
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // too many arguments points at the declaration
+        let errs = errors("int f(int a, int b);\nint g(void) { return f(1, 2, 3); }");
+        assert_eq!(
+            errs,
+            vec![(
+                "invalid program: too many arguments to function call: expected 2 arguments, found 3"
+                    .to_string(),
+                "f",
+                vec![("function declared here".to_string(), "f(int a, int b)")]
+            )]
+        );
+
+        // each bad argument is reported at its own location, and one doesn't hide the next
+        let errs = errors(
+            "int h(int x, int *p);\nint g(int *q) { int i = 0; return h(i, i + 1) + h(q, 2.5); }",
+        );
+        let expected = |position, actual, param_type, arg, param| {
+            (
+                format!(
+                    "invalid program: incompatible type '{}' for argument {}: expected '{}'",
+                    actual, position, param_type
+                ),
+                arg,
+                vec![(format!("parameter {} declared here", position), param)],
+            )
+        };
+        assert_eq!(
+            errs,
+            vec![
+                expected(2, "long", "int *", "i + 1", "int *p"),
+                expected(1, "int *", "int", "q", "int x"),
+                expected(2, "double", "int *", "2.5", "int *p"),
+            ]
+        );
+
+        // only the fixed parameters of a varargs function are checked
+        let errs = errors(
+            "struct s { int i; } s;\nint v(char *fmt, int n, ...);\nint g(void) { return v(1, 2, 3.0, &s, s) + v(\"\"); }",
+        );
+        assert_eq!(errs.len(), 2);
+        assert_eq!(errs[0].1, "1");
+        assert!(errs[0].0.contains("for argument 1: expected 'char *'"));
+        assert_eq!(
+            errs[1].0,
+            "invalid program: too few arguments to function call: expected at least 2 arguments, found 1"
+        );
+    }
+    #[test]
+    fn test_member_access() {
+        let mut analyzer = PureAnalyzer::new(arena());
+        for decl in &[
+            "struct S { int a; const char c; struct { long l; } in; } s, *p, f(void);",
+            "const struct S cs;",
+            "union U { int i; double d; } u, *up;",
+            "struct T *incomplete;",
+            "int x;",
+        ] {
+            analyzer.parse_external_decl_str(decl).unwrap();
+        }
+        // reads have the type of the member
+        for (input, ctype) in &[
+            ("s.a", Type::Int(true)),
+            ("p->c", Type::Char(true)),
+            ("s.in.l", Type::Long(true)),
+            ("p->in.l", Type::Long(true)),
+            ("up->d", Type::Double),
+            ("f().a", Type::Int(true)),
+        ] {
+            let expr = analyzer.parse_expr_str(input).unwrap();
+            assert_eq!(expr.ctype, *ctype, "{}", input);
+            assert!(matches!(expr.expr, ExprType::Member(..)), "{}", input);
+        }
+        for input in &[
+            "s.a = 1",
+            "p->a = 1",
+            "p->in.l += 2",
+            "u.i++",
+            "(*p).a = 3",
+            "&p->in",
+        ] {
+            analyzer.parse_expr_str(input).unwrap();
+        }
+        assert!(analyzer.warnings().is_empty());
+
+        let not_assignable = |analyzer: &mut PureAnalyzer, input| match analyzer
+            .parse_expr_str(input)
+            .unwrap_err()
+            .data
+        {
+            Error::Semantic(SemanticError::NotAssignable(reason)) => reason,
+            other => panic!("wrong error for {}: {}", input, other),
+        };
+        // `const` on the struct applies to its members
+        assert_eq!(
+            not_assignable(&mut analyzer, "cs.in.l = 1"),
+            NotAssignable::ConstVariable("cs".into())
+        );
+        assert_eq!(
+            not_assignable(&mut analyzer, "p->c = 1"),
+            NotAssignable::ConstMember("c".into())
+        );
+        // a member of an rvalue isn't an lvalue
+        for input in &["f().a = 1", "f().in.l++", "(x ? s : *p).a = 1"] {
+            assert_eq!(not_assignable(&mut analyzer, input), NotAssignable::Rvalue);
+        }
+        assert!(analyzer.parse_expr_str("&f().a").is_err());
+
+        let err =
+            |analyzer: &mut PureAnalyzer, input| analyzer.parse_expr_str(input).unwrap_err().data;
+        let struct_s = analyzer.parse_expr_str("s").unwrap().ctype;
+        let pointer = Type::Pointer(Box::new(struct_s.clone()), Qualifiers::default());
+        assert_eq!(
+            err(&mut analyzer, "x.y"),
+            SemanticError::NotAStruct(Type::Int(true)).into()
+        );
+        assert_eq!(
+            err(&mut analyzer, "x->y"),
+            SemanticError::NotAPointer(Type::Int(true)).into()
+        );
+        assert_eq!(
+            err(&mut analyzer, "s.b"),
+            SemanticError::NotAMember("b".into(), struct_s.clone()).into()
+        );
+        assert_eq!(
+            err(&mut analyzer, "p.a"),
+            SemanticError::MemberOfPointer(pointer).into()
+        );
+        assert_eq!(
+            err(&mut analyzer, "s->a"),
+            SemanticError::NotAStructPointer(struct_s).into()
+        );
+        assert!(matches!(
+            err(&mut analyzer, "incomplete->a"),
+            Error::Semantic(SemanticError::IncompleteDefinitionUsed(_))
+        ));
+    }
+    #[test]
+    fn test_call_conversions() {
+        let mut analyzer = PureAnalyzer::new(arena());
+        for decl in &[
+            "double f(long l, float x);",
+            "char v(int n, ...);",
+            "int (*fp)(double);",
+            "int i;",
+        ] {
+            analyzer.parse_external_decl_str(decl).unwrap();
+        }
+        let arg_types = |analyzer: &mut PureAnalyzer, input| {
+            let call = analyzer.parse_expr_str(input).unwrap();
+            let args = match call.expr {
+                ExprType::FuncCall(_, args) => args,
+                _ => panic!("not a call: {}", input),
+            };
+            let types: Vec<_> = args.into_iter().map(|arg| arg.ctype).collect();
+            (call.ctype, call.lval, types)
+        };
+        // arguments are converted to the type of their parameter
+        let (ctype, lval, args) = arg_types(&mut analyzer, "f('a', 1)");
+        assert_eq!((ctype, lval), (Type::Double, false));
+        assert_eq!(args, vec![Type::Long(true), Type::Float]);
+        // trailing arguments get the default argument promotions
+        let (ctype, _, args) = arg_types(&mut analyzer, "v('a', 'b', (short)1, 1.5f, 2L)");
+        assert_eq!(ctype, Type::Char(true));
+        let expected = vec![
+            Type::Int(true),
+            Type::Int(true),
+            Type::Int(true),
+            Type::Double,
+            Type::Long(true),
+        ];
+        assert_eq!(args, expected);
+        // calls through a function pointer are the same as through the function
+        let (ctype, lval, args) = arg_types(&mut analyzer, "fp(i)");
+        assert_eq!(
+            (ctype, lval, args),
+            (Type::Int(true), false, vec![Type::Double])
+        );
+        let (_, _, args) = arg_types(&mut analyzer, "(*fp)(i)");
+        assert_eq!(args, vec![Type::Double]);
+        assert!(analyzer.warnings().is_empty());
+
+        let err = analyzer.parse_expr_str("i(1)").unwrap_err();
+        assert_eq!(
+            err.data,
+            SemanticError::NotAFunction(Type::Int(true)).into()
+        );
+        assert!(analyzer.parse_expr_str("f(1)").is_err());
+        assert!(analyzer.parse_expr_str("f(1, 2, 3)").is_err());
+        assert!(analyzer.parse_expr_str("v()").is_err());
+        assert!(analyzer.parse_expr_str("f(1, 2) = 3").is_err());
     }
     #[test]
     fn test_type_errors() {
@@ -1443,4 +3091,1201 @@ mod test {
             Type::Pointer(Box::new(Type::Int(true)), Qualifiers::default()),
         );
     }
+    #[test]
+    fn test_ternary() {
+        assert_type("1 ? 2 : 3.0", Type::Double);
+        assert_type("1 ? 'a' : 2L", Type::Long(true));
+        assert_type("1 ? (char)1 : (short)2", Type::Int(true));
+        let int_p = Type::Pointer(Box::new(Type::Int(true)), Qualifiers::default());
+        let void_p = Type::Pointer(Box::new(Type::Void), Qualifiers::default());
+        assert_type("1 ? (int*)0 : 0", int_p.clone());
+        assert_type("1 ? 0 : (int*)0", int_p.clone());
+        // `(void*)0` is a null pointer constant, but `(void*)1` is not
+        assert_type("1 ? (int*)0 : (void*)0", int_p.clone());
+        assert_type("1 ? (int*)0 : (void*)1", void_p);
+        assert!(!expr("1 ? 2 : 3").unwrap().lval);
+
+        let err = expr("1 ? (int*)0 : 1.5").unwrap_err();
+        assert_eq!(
+            err.data,
+            SemanticError::IncompatibleTypes(int_p, Type::Double).into()
+        );
+        assert!(expr("1 ? (void*)1 : 1").is_err());
+        let mut analyzer = PureAnalyzer::new(arena());
+        analyzer
+            .parse_external_decl_str("struct s { int i; } x;")
+            .unwrap();
+        assert!(analyzer.parse_expr_str("x ? 1 : 2").is_err());
+    }
+    #[test]
+    fn test_sizeof() {
+        let size_t = |input| {
+            let expr = expr(input).unwrap();
+            assert_eq!(expr.ctype, Type::Long(false), "{}", input);
+            match expr.expr {
+                ExprType::Literal(Literal::UnsignedInt(size)) => size,
+                other => panic!("{}: not a constant: {:?}", input, other),
+            }
+        };
+        assert_eq!(size_t("sizeof(int)"), 4);
+        assert_eq!(size_t("sizeof \"abc\""), 4);
+        assert_eq!(size_t("sizeof(int[10])"), 40);
+        assert_eq!(size_t("sizeof 1.0"), 8);
+        // sizeof is a constant expression
+        let folded = expr("sizeof(int) * 2").unwrap().const_fold().unwrap();
+        assert_eq!(folded.expr, ExprType::Literal(Literal::UnsignedInt(8)));
+
+        let mut analyzer = PureAnalyzer::new(arena());
+        for decl in &["int arr[10];", "int f(void);", "struct s;"] {
+            analyzer.parse_external_decl_str(decl).unwrap();
+        }
+        // arrays don't decay
+        let size = analyzer.parse_expr_str("sizeof arr").unwrap();
+        assert_eq!(size.expr, ExprType::Literal(Literal::UnsignedInt(40)));
+        for (input, err) in &[
+            ("sizeof(void)", "cannot take `sizeof` void"),
+            ("sizeof f", "cannot take `sizeof` a function"),
+            ("sizeof(int(void))", "cannot take `sizeof` a function"),
+            (
+                "sizeof(struct s)",
+                "cannot take the size of an incomplete struct or union",
+            ),
+        ] {
+            assert_eq!(
+                analyzer.parse_expr_str(input).unwrap_err().data,
+                SemanticError::InvalidSize(err).into(),
+                "{}",
+                input
+            );
+        }
+    }
+    #[test]
+    fn test_alignof() {
+        let align = |input| {
+            let expr = expr(input).unwrap();
+            assert_eq!(expr.ctype, Type::Long(false), "{}", input);
+            match expr.expr {
+                ExprType::Literal(Literal::UnsignedInt(align)) => align,
+                other => panic!("{}: not a constant: {:?}", input, other),
+            }
+        };
+        assert_eq!(align("_Alignof(double)"), 8);
+        assert_eq!(align("_Alignof(char)"), 1);
+        assert_eq!(align("_Alignof(struct { char c; long l; })"), 8);
+        assert_eq!(align("_Alignof(union { char c; short s; })"), 2);
+        // arrays have the alignment of their elements
+        assert_eq!(align("_Alignof(int[10])"), 4);
+
+        let mut analyzer = PureAnalyzer::new(arena());
+        // usable as an array bound
+        let decl = analyzer
+            .parse_external_decl_str("int arr[_Alignof(double)];")
+            .unwrap();
+        assert_eq!(decl[0].data.symbol.get().ctype.sizeof(), Ok(32));
+        for (input, err) in &[
+            ("_Alignof(void)", "cannot take `alignof` void"),
+            ("_Alignof(int(void))", "cannot take `alignof` function"),
+        ] {
+            assert_eq!(
+                analyzer.parse_expr_str(input).unwrap_err().data,
+                SemanticError::InvalidSize(err).into(),
+                "{}",
+                input
+            );
+        }
+    }
+    #[test]
+    fn test_comma() {
+        assert_type("(1, 2.0)", Type::Double);
+        assert_type("(1.0, 'a')", Type::Char(true));
+        let mut analyzer = PureAnalyzer::new(arena());
+        analyzer.parse_external_decl_str("int a, b;").unwrap();
+        analyzer.parse_external_decl_str("int arr[3];").unwrap();
+        let comma = analyzer.parse_expr_str("(a, b)").unwrap();
+        assert_eq!((comma.ctype, comma.lval), (Type::Int(true), false));
+        // arrays decay, since the result is not an lvalue
+        assert_eq!(
+            analyzer.parse_expr_str("(a, arr)").unwrap().ctype,
+            Type::Pointer(Box::new(Type::Int(true)), Qualifiers::default())
+        );
+        let err = analyzer.parse_expr_str("(a, b) = 3").unwrap_err();
+        assert_eq!(
+            err.data,
+            SemanticError::NotAssignable(NotAssignable::Rvalue).into()
+        );
+    }
+    #[test]
+    fn test_no_cascading_errors() {
+        // all the errors from analyzing `input`, with `int x` in scope
+        fn errors(input: &str) -> Vec<Error> {
+            let x = Variable {
+                ctype: Type::Int(true),
+                id: InternedStr::get_or_intern("x"),
+                qualifiers: Default::default(),
+                storage_class: Default::default(),
+                location: Location::default(),
+            }
+            .insert();
+            let ast = crate::parse::test::parser(input).expr().unwrap();
+            let mut analyzer = PureAnalyzer::new(arena());
+            analyzer.scope.insert(x.get().id, x);
+            let expr = analyzer.expr(ast);
+            assert_eq!(expr.ctype, Type::Error, "{}", input);
+            std::iter::from_fn(|| analyzer.error_handler.pop_front())
+                .map(|err| err.data)
+                .collect()
+        }
+        let undeclared = || SemanticError::UndeclaredVar("undeclared_var".into(), None).into();
+        for input in &[
+            "undeclared_var + 1 * 2 < x",
+            "undeclared_var % 2",
+            "undeclared_var << 1 | x",
+            "-undeclared_var",
+            "~undeclared_var",
+            "*undeclared_var",
+            "&undeclared_var",
+            "undeclared_var()",
+            "undeclared_var[x]",
+            "undeclared_var.a",
+            "undeclared_var->a + 1",
+            "undeclared_var++",
+            "(int *)undeclared_var + 1",
+            "x ? undeclared_var : x",
+            "x = undeclared_var",
+            "undeclared_var = x",
+            "x += undeclared_var",
+            "undeclared_var ?: x",
+            "x ?: undeclared_var",
+            "sizeof undeclared_var",
+            "sizeof(undeclared_var + 1) * 2",
+            "_Alignof(undeclared_var)",
+        ] {
+            assert_eq!(errors(input), vec![undeclared()], "{}", input);
+        }
+        // errors other than undeclared variables also stop cascading
+        assert_eq!(errors("(1).a + 1 < x").len(), 1);
+        assert_eq!(errors("*x + 1").len(), 1);
+        assert_eq!(errors("1.0 % 2 * x").len(), 1);
+    }
+    #[test]
+    fn test_bool_results() {
+        let mut analyzer = PureAnalyzer::new(arena());
+        for decl in &["_Bool b;", "int i;", "double d;"] {
+            analyzer.parse_external_decl_str(decl).unwrap();
+        }
+        for input in &[
+            "i < 2", "d == d", "!i", "!d", "i && d", "b || b", "b + b", "-b",
+        ] {
+            let expr = analyzer.parse_expr_str(input).unwrap();
+            assert_eq!(expr.ctype, Type::Int(true), "{}", input);
+        }
+        assert_eq!(analyzer.parse_expr_str("b").unwrap().ctype, Type::Bool);
+        assert_eq!(
+            analyzer.parse_expr_str("(_Bool)d").unwrap().ctype,
+            Type::Bool
+        );
+        assert!(analyzer.warnings().is_empty());
+    }
+
+    #[test]
+    fn test_relational_decay() {
+        let mut analyzer = PureAnalyzer::new(arena());
+        analyzer
+            .parse_external_decl_str("int a[4], *p, f(int), (*fp)(int);")
+            .unwrap();
+        for input in &[
+            "a == p",
+            "p != a",
+            "a < a + 2",
+            "a + 3 >= a",
+            "f == fp",
+            "fp != f",
+            "&a[1] > p",
+            "a == 0",
+        ] {
+            match analyzer.parse_expr_str(input) {
+                Ok(expr) => assert_eq!(expr.ctype, Type::Int(true), "{}", input),
+                Err(err) => panic!("{}: {}", input, err.data),
+            }
+        }
+        // functions and objects can never be compared
+        for input in &["f == p", "fp < a"] {
+            let err = analyzer.parse_expr_str(input).unwrap_err();
+            assert!(
+                matches!(
+                    err.data,
+                    Error::Semantic(SemanticError::InvalidRelationalType(..))
+                ),
+                "{}: {}",
+                input,
+                err.data
+            );
+        }
+    }
+    #[test]
+    fn test_distinct_pointers() {
+        let mut analyzer = PureAnalyzer::new(arena());
+        analyzer
+            .parse_external_decl_str("int *p, (*fp)(int);")
+            .unwrap();
+        analyzer.parse_external_decl_str("char *q;").unwrap();
+        analyzer.parse_external_decl_str("void *v;").unwrap();
+        for input in &[
+            "p == v",
+            "v != q",
+            "p == 0",
+            "1 ? p : v",
+            "1 ? 0 : q",
+            "v = p",
+            "p = v",
+            "q = 0",
+        ] {
+            analyzer.parse_expr_str(input).unwrap();
+            assert_eq!(analyzer.warnings(), VecDeque::new(), "{}", input);
+        }
+        let warning = |analyzer: &mut PureAnalyzer, input| {
+            let expr = analyzer.parse_expr_str(input).unwrap();
+            let mut warnings = analyzer.warnings();
+            assert_eq!(warnings.len(), 1, "{}", input);
+            (expr.ctype, warnings.pop_front().unwrap().data)
+        };
+        let (int_p, char_p) = (
+            Type::Pointer(Box::new(Type::Int(true)), Default::default()),
+            Type::Pointer(Box::new(Type::Char(true)), Default::default()),
+        );
+        let (ctype, comparison) = warning(&mut analyzer, "p == q");
+        assert_eq!(ctype, Type::Int(true));
+        assert_eq!(
+            comparison,
+            Warning::DistinctPointerComparison(int_p.clone(), char_p.clone())
+        );
+        let message = comparison.to_string();
+        assert!(
+            message.contains("int *") && message.contains("char *"),
+            "{}",
+            message
+        );
+        let (ctype, conditional) = warning(&mut analyzer, "1 ? p : q");
+        assert_eq!(ctype, int_p);
+        assert_eq!(
+            conditional,
+            Warning::PointerTypeMismatch(int_p.clone(), char_p.clone())
+        );
+        let (ctype, assignment) = warning(&mut analyzer, "q = p");
+        assert_eq!(ctype, char_p);
+        assert_eq!(
+            assignment,
+            Warning::IncompatiblePointerTypes(int_p.clone(), char_p.clone())
+        );
+        let (_, assignment) = warning(&mut analyzer, "p = q");
+        assert_eq!(assignment, Warning::IncompatiblePointerTypes(char_p, int_p));
+        // function pointers are still errors in conditionals
+        assert!(analyzer.parse_expr_str("1 ? fp : q").is_err());
+    }
+    #[test]
+    fn test_pointer_ordering() {
+        let mut analyzer = PureAnalyzer::new(arena());
+        for decl in &[
+            "int f(int), g(int), (*fp)(int);",
+            "void *v, *w;",
+            "int *p, a[3];",
+        ] {
+            analyzer.parse_external_decl_str(decl).unwrap();
+        }
+        // equality is fine for every kind of pointer, and ordering is fine for object pointers
+        for input in &[
+            "f == g",
+            "fp != f",
+            "fp == 0",
+            "v == w",
+            "v != p",
+            "p < a",
+            "a + 1 >= p",
+            "p <= p",
+        ] {
+            analyzer.parse_expr_str(input).unwrap();
+            assert_eq!(analyzer.warnings(), VecDeque::new(), "{}", input);
+        }
+        for input in &["f < g", "fp <= f", "g > fp", "fp >= fp"] {
+            match analyzer.parse_expr_str(input).unwrap_err().data {
+                Error::Semantic(SemanticError::FunctionPointerOrdering(_, left, right)) => {
+                    assert!(left.is_function() && right.is_function(), "{}", input);
+                }
+                other => panic!("wrong error for {}: {}", input, other),
+            }
+        }
+        let err = analyzer
+            .parse_expr_str("f < g")
+            .unwrap_err()
+            .data
+            .to_string();
+        assert!(err.contains("'<'") && err.contains("int (int)"), "{}", err);
+        for (input, token) in &[
+            ("v < w", ComparisonToken::Less),
+            ("v >= w", ComparisonToken::GreaterEqual),
+        ] {
+            analyzer.parse_expr_str(input).unwrap();
+            let warnings: Vec<_> = analyzer.warnings().into_iter().map(|w| w.data).collect();
+            assert_eq!(
+                warnings,
+                vec![Warning::VoidPointerOrdering(*token, Type::Void, Type::Void)]
+            );
+        }
+    }
+    #[test]
+    fn test_pointer_arithmetic() {
+        let void_exprs = [
+            "v + 1", "2 + v", "v - 1", "v += 2", "v -= 1", "v++", "--v", "&v[1]",
+        ];
+        let function_exprs = [
+            "fp + 1", "1 + fp", "fp - 1", "fp += 1", "fp++", "--fp", "fp[0]", "f + 1",
+        ];
+        for standard in &["gnu11", "c11", "gnu89", "c89"] {
+            let standard: Standard = standard.parse().unwrap();
+            let mut analyzer = PureAnalyzer::new(arena()).standard(standard);
+            for decl in &["void *v;", "int f(int), (*fp)(int);"] {
+                analyzer.parse_external_decl_str(decl).unwrap();
+            }
+            for input in &void_exprs {
+                let result = analyzer.parse_expr_str(input);
+                if standard.gnu {
+                    let expr = result.unwrap();
+                    let warnings: Vec<_> =
+                        analyzer.warnings().into_iter().map(|w| w.data).collect();
+                    assert_eq!(warnings, vec![Warning::VoidPointerArithmetic], "{}", input);
+                    if *input != "&v[1]" {
+                        assert_eq!(expr.ctype, analyzer.parse_expr_str("v").unwrap().ctype);
+                    }
+                } else {
+                    assert_eq!(
+                        result.unwrap_err().data,
+                        SemanticError::PointerArithmetic(Type::Void).into(),
+                        "{}: {}",
+                        standard,
+                        input
+                    );
+                }
+            }
+            for input in &function_exprs {
+                match analyzer.parse_expr_str(input).unwrap_err().data {
+                    Error::Semantic(SemanticError::PointerArithmetic(pointee)) => {
+                        assert!(pointee.is_function(), "{}", input)
+                    }
+                    other => panic!("wrong error for {}: {}", input, other),
+                }
+            }
+        }
+        let err = SemanticError::PointerArithmetic(Type::Void).to_string();
+        assert_eq!(err, "arithmetic on a pointer to void");
+    }
+    #[test]
+    fn test_pointer_difference() {
+        let mut analyzer = PureAnalyzer::new(arena());
+        for decl in &["long *p, *q;", "long a[4];", "int *i;"] {
+            analyzer.parse_external_decl_str(decl).unwrap();
+        }
+        // `p - q` is `((ptrdiff_t)p - (ptrdiff_t)q) / 8`
+        for input in &["p - q", "a - p", "p - a", "a - a"] {
+            let expr = analyzer.parse_expr_str(input).unwrap();
+            assert_eq!(expr.ctype, Type::Long(true), "{}", input);
+            assert!(!expr.lval, "{}", input);
+            match &expr.expr {
+                ExprType::Binary(BinaryOp::Div, bytes, size) => {
+                    assert_eq!(size.expr, ExprType::Literal(Literal::Int(8)));
+                    assert_eq!(bytes.ctype, Type::Long(true));
+                }
+                _ => panic!("difference is not scaled: {}", input),
+            }
+        }
+        for input in &["p + q", "p - i"] {
+            assert!(analyzer.parse_expr_str(input).is_err(), "{}", input);
+        }
+    }
+    #[test]
+    fn test_pointer_scaling() {
+        let mut analyzer = PureAnalyzer::new(arena());
+        for decl in &["int *p;", "unsigned u;", "struct big { char c[1000]; } *b;"] {
+            analyzer.parse_external_decl_str(decl).unwrap();
+        }
+        // `p + u` is `p + (ptrdiff_t)4 * (ptrdiff_t)u`
+        for input in &["p + u", "p - u", "&p[u]"] {
+            let expr = analyzer.parse_expr_str(input).unwrap();
+            let offset = match expr.expr {
+                ExprType::Binary(_, _, offset) => offset,
+                _ => panic!("not pointer arithmetic: {}", input),
+            };
+            assert_eq!(offset.ctype, Type::Long(true), "{}", input);
+            match &offset.expr {
+                ExprType::Binary(BinaryOp::Mul, size, index) => {
+                    assert_eq!(size.expr, ExprType::Literal(Literal::Int(4)));
+                    assert_eq!(size.ctype, Type::Long(true));
+                    assert_eq!(index.ctype, Type::Long(true));
+                }
+                _ => panic!("offset is not scaled: {}", input),
+            }
+        }
+        // negative indices stay negative
+        let folded = analyzer.parse_expr_str("p - -2").unwrap().const_fold();
+        match folded.unwrap().expr {
+            ExprType::Binary(BinaryOp::Sub, _, offset) => {
+                assert_eq!(offset.expr, ExprType::Literal(Literal::Int(-8)))
+            }
+            _ => panic!("not pointer arithmetic"),
+        }
+        assert!(analyzer.warnings().is_empty());
+        for input in &[
+            "p + 0x4000000000000000",
+            "b - 0x40000000000000",
+            "&b[-0x40000000000000]",
+        ] {
+            analyzer.parse_expr_str(input).unwrap();
+            let warnings: Vec<_> = analyzer.warnings().into_iter().map(|w| w.data).collect();
+            assert_eq!(warnings.len(), 1, "{}", input);
+            assert_eq!(warnings[0].name(), "pointer-offset-overflow", "{}", input);
+        }
+        let warning = Warning::PointerOffsetOverflow(0x4000000000000000, 4).to_string();
+        assert_eq!(
+            warning,
+            "offset of 4611686018427387904 elements of size 4 overflows 'ptrdiff_t'"
+        );
+    }
+    #[test]
+    fn test_array_subscript() {
+        let mut analyzer = PureAnalyzer::new(arena());
+        for decl in &[
+            "int arr[3];",
+            "char *p;",
+            "struct s *incomplete;",
+            "double d;",
+        ] {
+            analyzer.parse_external_decl_str(decl).unwrap();
+        }
+        // `a[i]` is `*(a + i)`, in either order
+        for (input, ctype) in &[
+            ("arr[2]", Type::Int(true)),
+            ("2[arr]", Type::Int(true)),
+            ("4[p]", Type::Char(true)),
+            ("p[-1]", Type::Char(true)),
+        ] {
+            let expr = analyzer.parse_expr_str(input).unwrap();
+            assert_eq!(&expr.ctype, ctype, "{}", input);
+            assert!(expr.lval, "{}", input);
+            // lvalues are represented by their address
+            match expr.expr {
+                ExprType::Noop(addr) => {
+                    assert!(addr.ctype.is_pointer(), "{}", input);
+                    assert!(
+                        matches!(addr.expr, ExprType::Binary(BinaryOp::Add, ..)),
+                        "{}",
+                        input
+                    );
+                }
+                _ => panic!("not desugared to a dereference: {}", input),
+            }
+        }
+        for input in &["arr[0] = 5", "1[arr] += 2", "p[3] = 'a'"] {
+            analyzer.parse_expr_str(input).unwrap();
+        }
+        let err = |analyzer: &mut PureAnalyzer, input| analyzer.parse_expr_str(input).unwrap_err();
+        assert_eq!(
+            err(&mut analyzer, "incomplete[1]").data,
+            SemanticError::PointerAddUnknownSize(
+                analyzer.parse_expr_str("incomplete").unwrap().ctype
+            )
+            .into()
+        );
+        assert_eq!(
+            err(&mut analyzer, "arr[d]").data,
+            SemanticError::NonIntegralSubscript(Type::Double).into()
+        );
+        assert_eq!(
+            err(&mut analyzer, "p[p]").data,
+            SemanticError::NonIntegralSubscript(analyzer.parse_expr_str("p").unwrap().ctype).into()
+        );
+        assert_eq!(
+            err(&mut analyzer, "d[1]").data,
+            SemanticError::NotAPointer(Type::Double).into()
+        );
+    }
+    #[test]
+    fn test_conditions() {
+        let mut analyzer = PureAnalyzer::new(arena());
+        for decl in &[
+            "struct s { int i; } x;",
+            "int arr[3];",
+            "int f(void);",
+            "int i;",
+        ] {
+            analyzer.parse_external_decl_str(decl).unwrap();
+        }
+        for input in &[
+            "if (x) {}",
+            "while (x) {}",
+            "do ; while (x);",
+            "for (; x;) {}",
+        ] {
+            let err = analyzer.parse_stmt_str(input).unwrap_err();
+            assert_eq!(
+                err.data,
+                SemanticError::NotConvertibleToBool(analyzer.parse_expr_str("x").unwrap().ctype)
+                    .into(),
+                "{}",
+                input
+            );
+        }
+        assert!(analyzer.parse_expr_str("!x").is_err());
+
+        // arrays and functions decay to pointers, which are never null
+        for (input, kind, name) in &[
+            ("if (arr) {}", "array", "arr"),
+            ("while (f) {}", "function", "f"),
+            ("if (&i) {}", "variable", "i"),
+            ("if (!arr) {}", "array", "arr"),
+        ] {
+            analyzer.parse_stmt_str(input).unwrap();
+            let warnings: Vec<_> = analyzer.warnings().into_iter().map(|w| w.data).collect();
+            assert_eq!(
+                warnings,
+                vec![Warning::AddressAlwaysTrue(kind, (*name).into())],
+                "{}",
+                input
+            );
+        }
+        // assignments, commas, and missing conditions are fine
+        for input in &[
+            "if ((i = 1)) {}",
+            "while (i, 0) {}",
+            "for (;;) break;",
+            "if (i) {}",
+        ] {
+            analyzer.parse_stmt_str(input).unwrap();
+            assert_eq!(analyzer.warnings(), VecDeque::new(), "{}", input);
+        }
+    }
+    #[test]
+    fn test_enum_conversion() {
+        let mut analyzer = PureAnalyzer::new(arena());
+        for decl in &[
+            "enum color { RED = 1, GREEN, BLUE } c;",
+            "enum shape { CIRCLE, SQUARE } s;",
+            "int take(enum color);",
+            "int i;",
+        ] {
+            analyzer.parse_external_decl_str(decl).unwrap();
+        }
+        assert_eq!(analyzer.warnings(), VecDeque::new());
+        let color = analyzer.parse_expr_str("c").unwrap().ctype;
+        let shape = analyzer.parse_expr_str("s").unwrap().ctype;
+        for input in &[
+            "c = RED",
+            "c = 0",
+            "c = 3",
+            "c = RED + 1",
+            "c = c + 1",
+            "c++",
+            "c = i",
+            "c = (enum color)s",
+            "take(BLUE)",
+            "i = SQUARE",
+            "s = 1",
+            "c == RED",
+            "c + CIRCLE",
+        ] {
+            analyzer.parse_expr_str(input).unwrap();
+            assert_eq!(analyzer.warnings(), VecDeque::new(), "{}", input);
+        }
+        let mut warning = |input| {
+            analyzer.parse_expr_str(input).unwrap();
+            let warnings: Vec<_> = analyzer.warnings().into_iter().map(|w| w.data).collect();
+            assert_eq!(warnings.len(), 1, "{}", input);
+            warnings.into_iter().next().unwrap()
+        };
+        let conversion = Warning::EnumConversion(shape.clone(), color.clone());
+        assert_eq!(warning("c = CIRCLE"), conversion);
+        assert_eq!(warning("c = s"), conversion);
+        assert_eq!(warning("take(SQUARE)"), conversion);
+        assert_eq!(
+            warning("s = BLUE"),
+            Warning::EnumConversion(color.clone(), shape)
+        );
+        assert_eq!(warning("c = 17"), Warning::AssignEnum(17, color.clone()));
+        assert_eq!(
+            warning("c = BLUE + 1"),
+            Warning::AssignEnum(4, color.clone())
+        );
+        assert_eq!(warning("take(-1)"), Warning::AssignEnum(-1, color.clone()));
+        // initializers are checked the same way
+        analyzer
+            .parse_external_decl_str("enum color d = 17, e = GREEN;")
+            .unwrap();
+        let warnings: Vec<_> = analyzer.warnings().into_iter().map(|w| w.data).collect();
+        assert_eq!(warnings, vec![Warning::AssignEnum(17, color.clone())]);
+        let message = Warning::AssignEnum(17, color).to_string();
+        assert!(
+            message.contains("17") && message.contains("enum color"),
+            "{}",
+            message
+        );
+    }
+    #[test]
+    fn test_cast_location() {
+        let mut analyzer = PureAnalyzer::new(arena());
+        analyzer.parse_external_decl_str("double d;").unwrap();
+        analyzer
+            .parse_external_decl_str("struct s { int x; } st;")
+            .unwrap();
+        let span = |analyzer: &mut PureAnalyzer, input: &str| match analyzer.parse_expr_str(input) {
+            Ok(expr) => expr.location.span,
+            Err(err) => err.location().span,
+        };
+        // the location covers both the type name and the operand
+        for input in &[
+            "(long)d",
+            "(float *)d",
+            "(int)st",
+            "(int)(void)d",
+            "(char)(int)d",
+        ] {
+            let span = span(&mut analyzer, input);
+            assert_eq!(
+                (span.start, span.end as usize),
+                (0, input.len()),
+                "{}",
+                input
+            );
+        }
+        let input = "1 + (float *)d";
+        let span = span(&mut analyzer, input);
+        assert_eq!(span.start as usize, input.find('(').unwrap());
+        assert_eq!(span.end as usize, input.len());
+    }
+    #[test]
+    fn test_deref() {
+        let mut analyzer = PureAnalyzer::new(arena());
+        for decl in &[
+            "int *ip, x;",
+            "void *vp;",
+            "struct inc *sp;",
+            "const int *cp;",
+        ] {
+            analyzer.parse_external_decl_str(decl).unwrap();
+        }
+        let deref = analyzer.parse_expr_str("*ip").unwrap();
+        assert_eq!((deref.ctype, deref.lval), (Type::Int(true), true));
+        analyzer.parse_expr_str("*ip = 1").unwrap();
+        let void_ptr = Type::Pointer(Box::new(Type::Void), Qualifiers::default());
+        assert_eq!(
+            analyzer.parse_expr_str("*vp").unwrap_err().data,
+            SemanticError::VoidDereference(void_ptr.clone()).into()
+        );
+        // &*E is E, even for `void *` and pointers to incomplete types
+        let addr = analyzer.parse_expr_str("&*vp").unwrap();
+        assert_eq!((addr.ctype, addr.lval), (void_ptr, false));
+        let addr = analyzer.parse_expr_str("&*sp").unwrap();
+        assert!(addr.ctype.is_pointer());
+        assert_eq!(addr.ctype, analyzer.parse_expr_str("sp").unwrap().ctype);
+        // but `*x` still has to be a pointer
+        assert_eq!(
+            analyzer.parse_expr_str("&*x").unwrap_err().data,
+            SemanticError::NotAPointer(Type::Int(true)).into()
+        );
+        let err = analyzer.parse_expr_str("(*sp).m").unwrap_err().data;
+        assert!(err.to_string().contains("struct inc"), "{}", err);
+        let err = analyzer.parse_expr_str("sp->m").unwrap_err().data;
+        assert!(err.to_string().contains("struct inc"), "{}", err);
+        // *&x is x
+        let x = analyzer.parse_expr_str("*&x").unwrap();
+        assert_eq!((x.ctype, x.lval), (Type::Int(true), true));
+        // the pointee's qualifiers are kept
+        assert!(analyzer.parse_expr_str("*cp = 1").is_err());
+        assert!(analyzer.parse_expr_str("*&*cp = 1").is_err());
+    }
+    #[test]
+    fn test_promoted_bitwise() {
+        let mut analyzer = PureAnalyzer::new(arena());
+        for decl in &["unsigned char a, b, flags;", "unsigned short s;", "int i;"] {
+            analyzer.parse_external_decl_str(decl).unwrap();
+        }
+        for input in &[
+            "a | b",
+            "a = a | b",
+            "a = a >> 1",
+            "flags &= 0x7f",
+            "i = ~a",
+            "(unsigned char)~a == 0xFF",
+            "~a == ~b",
+            "~a == -1",
+        ] {
+            analyzer.parse_expr_str(input).unwrap();
+            assert_eq!(analyzer.warnings(), VecDeque::new(), "{}", input);
+        }
+        let mut warning = |input| {
+            analyzer.parse_expr_str(input).unwrap();
+            let mut warnings: Vec<_> = analyzer.warnings().into_iter().map(|w| w.data).collect();
+            assert_eq!(warnings.len(), 1, "{}: {:?}", input, warnings);
+            match warnings.pop().unwrap() {
+                Warning::PromotedBitwise(expr, original, promoted, folded) => {
+                    (expr, original, promoted, folded)
+                }
+                other => panic!("{}: wrong warning {}", input, other),
+            }
+        };
+        let (expr, original, promoted, folded) = warning("a = ~a");
+        assert_eq!(
+            (expr.as_str(), original, promoted, folded),
+            ("~a", Type::Char(false), Type::Int(true), None)
+        );
+        assert_eq!(warning("~a == 0xFF").1, Type::Char(false));
+        assert_eq!(warning("s = ~s >> 8").1, Type::Short(false));
+        assert_eq!(warning("a <<= 4").1, Type::Char(false));
+        let (expr, original, promoted, folded) = warning("flags &= ~0x80");
+        assert_eq!(
+            (expr.as_str(), original, promoted),
+            ("flags &= ~128", Type::Char(false), Type::Int(true))
+        );
+        assert_eq!(folded, Some(("~128".to_owned(), "-129".to_owned())));
+        let message = Warning::PromotedBitwise(
+            "flags & ~128".into(),
+            Type::Char(false),
+            Type::Int(true),
+            folded,
+        )
+        .to_string();
+        assert!(message.contains("'~128' is -129"), "{}", message);
+        // constants are folded
+        assert_eq!(
+            warning("~(unsigned char)15 == 240").3,
+            Some(("~(unsigned char)15".to_owned(), "-16".to_owned()))
+        );
+        // unsigned constants are shown as unsigned, and the operand is still only promoted to `int`
+        let (_, _, promoted, folded) = warning("flags = flags & ~(unsigned)1");
+        assert_eq!(promoted, Type::Int(true));
+        assert_eq!(folded.unwrap().1, "4294967294u");
+        let (_, _, promoted, folded) = warning("flags &= ~1u");
+        assert_eq!(promoted, Type::Int(true));
+        assert!(folded.unwrap().1.ends_with('u'));
+    }
+    #[test]
+    fn test_register() {
+        let mut analyzer = PureAnalyzer::new(arena());
+        analyzer.parse_external_decl_str("int *f(void);").unwrap();
+        analyzer
+            .parse_stmt_str("register int i = 1, a[3];")
+            .unwrap();
+        assert_eq!(
+            analyzer.parse_expr_str("&i").unwrap_err().data,
+            SemanticError::InvalidAddressOf("variable declared with `register`").into()
+        );
+        analyzer.parse_expr_str("i + 1").unwrap();
+        assert_eq!(analyzer.warnings(), VecDeque::new());
+        // gcc allows subscripting register arrays
+        analyzer.parse_expr_str("a[1]").unwrap();
+        let warnings: Vec<_> = analyzer.warnings().into_iter().map(|w| w.data).collect();
+        assert_eq!(warnings, vec![Warning::RegisterArraySubscript("a".into())]);
+        // the temporary this desugars to is `register`, but its address is never taken
+        analyzer.parse_expr_str("*f() += 1").unwrap();
+        assert_eq!(analyzer.warnings(), VecDeque::new());
+    }
+    #[test]
+    fn test_const_lvalues() {
+        let mut analyzer = PureAnalyzer::new(arena());
+        for decl in &[
+            "struct inner { const int c; int m; };",
+            "struct s { int x; const int y; int *p; const int *cp; struct inner in; int a[2]; };",
+            "struct outer { struct inner in[2]; };",
+            "int i, *ip, **pp;",
+            "const int *p, arr[3], *const *cpp, grid[2][2];",
+            "int *const q;",
+            "struct s s, *sp;",
+            "const struct s cs, *csp;",
+            "struct outer o;",
+        ] {
+            analyzer.parse_external_decl_str(decl).unwrap();
+        }
+        // mutation through a path without `const`, even if the object is also reachable with `const`
+        for input in &[
+            "p = &i",
+            "p = arr",
+            "*q = 1",
+            "q[1]++",
+            "ip = (int *)p",
+            "*(int *)p = 1",
+            "*pp = 0",
+            "s.x = 1",
+            "sp->x = 1",
+            "*s.p = 1",
+            "s.cp = 0",
+            "s.in.m = 1",
+            "s.a[0] = 1",
+            "csp = &s",
+            "o.in[0].m = 1",
+        ] {
+            analyzer.parse_expr_str(input).unwrap();
+        }
+        let assert_const = |analyzer: &mut PureAnalyzer, input, expected: NotAssignable| {
+            let err = analyzer.parse_expr_str(input).unwrap_err();
+            assert_eq!(
+                err.data,
+                SemanticError::NotAssignable(expected).into(),
+                "{}",
+                input
+            );
+        };
+        let location = |s: &str| NotAssignable::ReadOnlyLocation(s.into());
+        // const variables
+        assert_const(
+            &mut analyzer,
+            "q = 0",
+            NotAssignable::ConstVariable("q".into()),
+        );
+        assert_const(
+            &mut analyzer,
+            "cs.x = 1",
+            NotAssignable::ConstVariable("cs".into()),
+        );
+        assert_const(
+            &mut analyzer,
+            "cs.p = 0",
+            NotAssignable::ConstVariable("cs".into()),
+        );
+        // const members
+        assert_const(
+            &mut analyzer,
+            "s.y = 1",
+            NotAssignable::ConstMember("y".into()),
+        );
+        assert_const(
+            &mut analyzer,
+            "sp->y = 1",
+            NotAssignable::ConstMember("y".into()),
+        );
+        assert_const(
+            &mut analyzer,
+            "s.in.c++",
+            NotAssignable::ConstMember("c".into()),
+        );
+        // pointers to const
+        assert_const(&mut analyzer, "*p = 3", location("*p"));
+        assert_const(&mut analyzer, "--*p", location("*p"));
+        assert_const(&mut analyzer, "*s.cp = 1", location("*s.cp"));
+        assert_const(&mut analyzer, "*cpp = 0", location("*cpp"));
+        assert_const(&mut analyzer, "csp->x = 1", location("*csp"));
+        // const arrays
+        assert_const(&mut analyzer, "arr[1] = 4", location("*(arr + 1)"));
+        assert_const(&mut analyzer, "arr[0] += 1", location("*(arr + 0)"));
+        assert_const(&mut analyzer, "cs.a[1] = 1", location("*(cs.a + 1)"));
+        assert_const(&mut analyzer, "csp->a[0] = 1", location("*(csp->a + 0)"));
+        assert_const(
+            &mut analyzer,
+            "grid[0][1]++",
+            location("*(*(grid + 0) + 1)"),
+        );
+        // const struct containing a struct
+        assert_const(
+            &mut analyzer,
+            "cs.in.m = 1",
+            NotAssignable::ConstVariable("cs".into()),
+        );
+        assert_const(&mut analyzer, "csp->in.m = 1", location("*csp"));
+        // structs containing const members
+        assert_const(
+            &mut analyzer,
+            "s = s",
+            NotAssignable::HasConstMember("y".into()),
+        );
+        assert_const(
+            &mut analyzer,
+            "o = o",
+            NotAssignable::HasConstMember("c".into()),
+        );
+    }
+    #[test]
+    fn test_pointer_qualifiers() {
+        let mut analyzer = PureAnalyzer::new(arena());
+        for decl in &[
+            "int x, *ip, **ipp;",
+            "char *s, **pp;",
+            "const int *cp;",
+            "const char *cs, **cpp, *const *ccpp;",
+            "const void *cv;",
+            "void *v;",
+            "int take(const char *);",
+            "int mutate(char *);",
+            "const int *ret(int *p);",
+            "const int *ret(int *p) { return p; }",
+            "int *discard(const int *p);",
+            "const char **unsafe(char **p);",
+            "const int *arr[2] = { &x, 0 };",
+        ] {
+            analyzer.parse_external_decl_str(decl).unwrap();
+        }
+        assert_eq!(analyzer.warnings(), VecDeque::new());
+        // adding qualifiers is always fine
+        for input in &[
+            "take(s)",
+            "cp = ip",
+            "cv = &x",
+            "cv = ip",
+            "cs = s",
+            "ccpp = pp",
+            "cp = ret(&x)",
+            "cp = 0",
+            "s = 0",
+            "ipp = &ip",
+            "cpp = &cs",
+        ] {
+            analyzer.parse_expr_str(input).unwrap();
+            assert_eq!(analyzer.warnings(), VecDeque::new(), "{}", input);
+        }
+        let mut warning = |input| {
+            analyzer.parse_expr_str(input).unwrap();
+            let warnings: Vec<_> = analyzer.warnings().into_iter().map(|w| w.data).collect();
+            assert_eq!(warnings.len(), 1, "{}", input);
+            warnings.into_iter().next().unwrap()
+        };
+        // removing them warns
+        let discarded = |expr: &str| Warning::DiscardedQualifiers(expr.into());
+        assert_eq!(warning("ip = cp"), discarded("cp"));
+        assert_eq!(warning("mutate(cs)"), discarded("cs"));
+        assert_eq!(warning("v = cv"), discarded("cv"));
+        assert_eq!(warning("ip = ret(&x)"), discarded("ret(&x)"));
+        assert_eq!(warning("ip = arr[0]"), discarded("*(arr + 0)"));
+        // `char **` -> `const char **` would allow modifying a `const char` through a `char *`
+        assert_eq!(
+            warning("cpp = pp"),
+            Warning::UnsafeQualifierConversion("pp".into())
+        );
+        assert_eq!(
+            warning("cpp = &s"),
+            Warning::UnsafeQualifierConversion("&s".into())
+        );
+        // the same checks apply to initializers and return statements
+        let mut decl_warnings = |decl| {
+            analyzer.parse_external_decl_str(decl).unwrap();
+            analyzer
+                .warnings()
+                .into_iter()
+                .map(|w| w.data)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(decl_warnings("const void *vp = &x;"), vec![]);
+        assert_eq!(decl_warnings("const char *const *q = &s;"), vec![]);
+        assert_eq!(decl_warnings("int *r = &*cp;"), vec![discarded("cp")]);
+        assert_eq!(
+            decl_warnings("int *discard(const int *p) { return p; }"),
+            vec![discarded("p")]
+        );
+        assert_eq!(
+            decl_warnings("const char **unsafe(char **p) { return p; }"),
+            vec![Warning::UnsafeQualifierConversion("p".into())]
+        );
+    }
+    #[test]
+    fn test_float_promotion() {
+        let mut analyzer = PureAnalyzer::new(arena());
+        for decl in &[
+            "int i;",
+            "long l;",
+            "unsigned long ul;",
+            "float f;",
+            "double d;",
+        ] {
+            analyzer.parse_external_decl_str(decl).unwrap();
+        }
+        for input in &[
+            "i + 1.0f",
+            "l + 1.0",
+            "(float)l + 1.0f",
+            "f * 2",
+            "f != 0",
+            "f == 16777216",
+            "f == (float)16777217",
+            "f == 0.1f",
+            "d == 0.5f",
+            "d == (double)0.1f",
+        ] {
+            analyzer.parse_expr_str(input).unwrap();
+            assert_eq!(analyzer.warnings(), VecDeque::new(), "{}", input);
+        }
+        let mut warning = |input| {
+            analyzer.parse_expr_str(input).unwrap();
+            let warnings: Vec<_> = analyzer.warnings().into_iter().map(|w| w.data).collect();
+            assert_eq!(warnings.len(), 1, "{}", input);
+            warnings.into_iter().next().unwrap()
+        };
+        assert_eq!(
+            warning("l + 1.0f"),
+            Warning::ImplicitIntFloatConversion(Type::Long(true), Type::Float)
+        );
+        assert_eq!(
+            warning("f -= ul"),
+            Warning::ImplicitIntFloatConversion(Type::Long(false), Type::Float)
+        );
+        assert_eq!(
+            warning("f == 16777217"),
+            Warning::InexactIntFloatConstant(
+                Type::Long(true),
+                Type::Float,
+                Literal::Int(16_777_217),
+                Literal::Float(16_777_216.0)
+            )
+        );
+        assert_eq!(
+            warning("d < 1 + (1L << 53)"),
+            Warning::InexactIntFloatConstant(
+                Type::Long(true),
+                Type::Double,
+                Literal::Int(9_007_199_254_740_993),
+                Literal::Float(9_007_199_254_740_992.0)
+            )
+        );
+        assert_eq!(warning("d == 0.1f"), Warning::FloatConstantPromotion(0.1));
+    }
+    #[test]
+    fn test_implicit_cast_table() {
+        let mut analyzer = PureAnalyzer::new(arena());
+        analyzer
+            .parse_external_decl_str("int i, *p, (*fp)(int), (*gp)(void);")
+            .unwrap();
+        for decl in &[
+            "void *v;",
+            "char c;",
+            "double d;",
+            "_Bool b;",
+            "struct s { int x; } st;",
+        ] {
+            analyzer.parse_external_decl_str(decl).unwrap();
+        }
+        // allowed without a diagnostic
+        for input in &[
+            // arithmetic <-> arithmetic
+            "d = i",
+            "c = d",
+            "b = d",
+            // null -> any pointer
+            "p = 0",
+            "fp = 0",
+            "v = (void*)0",
+            // any pointer -> _Bool
+            "b = p",
+            "b = fp",
+            "b = v",
+            // object pointer <-> void *
+            "v = p",
+            "p = v",
+        ] {
+            analyzer.parse_expr_str(input).unwrap();
+            assert_eq!(analyzer.warnings(), VecDeque::new(), "{}", input);
+        }
+        let mut warning = |input| {
+            let ctype = analyzer.parse_expr_str(input).unwrap().ctype;
+            let mut warnings = analyzer.warnings();
+            assert_eq!(warnings.len(), 1, "{}", input);
+            (ctype, warnings.pop_front().unwrap().data)
+        };
+        // function pointer <-> void *, which is only diagnosed with -pedantic
+        for input in &["v = fp", "fp = v"] {
+            match warning(input) {
+                (ctype, Warning::FunctionPointerConversion(_, to)) => assert_eq!(ctype, to),
+                other => panic!("wrong warning for {}: {:?}", input, other),
+            }
+        }
+        // object pointer <-> function pointer
+        for input in &["p = fp", "fp = p"] {
+            match warning(input) {
+                (ctype, Warning::IncompatiblePointerTypes(_, to)) => assert_eq!(ctype, to),
+                other => panic!("wrong warning for {}: {:?}", input, other),
+            }
+        }
+        // everything else needs an explicit cast, if it's allowed at all
+        for input in &[
+            // integer <-> pointer
+            "i = p", "p = i", "c = p", "p = c", "i = fp", "fp = i",
+            // floating <-> pointer
+            "d = p", "p = d",   // different function types
+            "fp = gp", // structs
+            "i = st", "st = i",
+        ] {
+            assert!(analyzer.parse_expr_str(input).is_err(), "{}", input);
+        }
+        let err = analyzer.parse_expr_str("i = p").unwrap_err();
+        assert!(err
+            .data
+            .to_string()
+            .contains("help: use an explicit cast: (int)"));
+        let err = analyzer.parse_expr_str("p = i").unwrap_err();
+        assert!(err
+            .data
+            .to_string()
+            .contains("help: use an explicit cast: (int *)"));
+    }
+    #[test]
+    fn test_explicit_cast_table() {
+        let mut analyzer = PureAnalyzer::new(arena());
+        analyzer
+            .parse_external_decl_str("int i, *p, (*fp)(int), (*gp)(void);")
+            .unwrap();
+        for decl in &[
+            "void *v;",
+            "char c;",
+            "double d;",
+            "struct s { int x; } st;",
+        ] {
+            analyzer.parse_external_decl_str(decl).unwrap();
+        }
+        for input in &[
+            // everything allowed implicitly
+            "(double)i",
+            "(char)d",
+            "(_Bool)p",
+            "(_Bool)fp",
+            "(void *)p",
+            "(int *)v",
+            // implementation-defined: integer <-> pointer
+            "(long)p",
+            "(char)p",
+            "(int)fp",
+            "(int *)i",
+            "(int *)c",
+            "(int (*)(int))i",
+            // pointers to different types, without a warning
+            "(char *)p",
+            "(int *)fp",
+            "(int (*)(int))p",
+            "(int (*)(int))gp",
+            "(void *)fp",
+            "(int (*)(int))v",
+            // anything can be discarded
+            "(void)st",
+        ] {
+            analyzer.parse_expr_str(input).unwrap();
+            assert_eq!(analyzer.warnings(), VecDeque::new(), "{}", input);
+        }
+        for input in &[
+            "(double)p",
+            "(int *)d",
+            "(int)st",
+            "(struct s)i",
+            "(int)(void)i",
+        ] {
+            assert!(analyzer.parse_expr_str(input).is_err(), "{}", input);
+        }
+    }
+    #[test]
+    fn test_evaluated_once() {
+        // the temporaries holding `p` and `*p` are anonymous
+        let decl = crate::analyze::test::decl("int f(int *p) { *p += 1; p++; return *p ?: 2; }");
+        let dump = decl.unwrap().dump();
+        assert!(!dump.contains("tmp"), "{}", dump);
+        assert_eq!(dump.matches("let $").count(), 4, "{}", dump);
+    }
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("", ""), 0);
+        assert_eq!(edit_distance("count", "count"), 0);
+        assert_eq!(edit_distance("cout", "count"), 1);
+        assert_eq!(edit_distance("count", "cuont"), 2);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+    }
 }
@@ -0,0 +1,641 @@
+//! Checking the arguments of `printf`- and `scanf`-like functions (`-Wformat`)
+
+use super::PureAnalyzer;
+use crate::data::{error::Warning, hir::*, lex::Locatable, Location, Type};
+
+impl<'hir> PureAnalyzer<'hir> {
+    /// Check the arguments of a call to a function with `__attribute__((format))`.
+    ///
+    /// `format` is the string literal passed as the format string.
+    /// `args` are _all_ the arguments of the call, after promotions.
+    pub(super) fn check_format(
+        &mut self,
+        attribute: FormatAttribute,
+        format: &Locatable<Vec<u8>>,
+        args: &[Expr<'hir>],
+    ) {
+        let conversions = match parse_format(&format.data, attribute.kind) {
+            Some(conversions) => conversions,
+            // positional arguments, like `%1$d`; these aren't checked
+            None => return,
+        };
+        // `vprintf` and friends take a `va_list`, so only the format string itself is checked
+        let check_args = attribute.first_arg != 0;
+        let mut remaining = args
+            .iter()
+            .enumerate()
+            .skip(attribute.first_arg.saturating_sub(1));
+        for conversion in conversions {
+            let location = conversion_location(format, &conversion);
+            let text = String::from_utf8_lossy(&format.data[conversion.start..conversion.end]);
+            let expected = match conversion.expected {
+                Ok(expected) => expected,
+                Err(()) => {
+                    self.warn(Warning::InvalidFormatSpecifier(text.into_owned()), location);
+                    // the arguments after this can't be matched up with conversions
+                    return;
+                }
+            };
+            if !check_args {
+                continue;
+            }
+            for ctype in expected {
+                let (i, arg) = match remaining.next() {
+                    Some(arg) => arg,
+                    None => {
+                        self.warn(Warning::MissingFormatArgument(text.into_owned()), location);
+                        return;
+                    }
+                };
+                if arg.ctype.contains_error() {
+                    continue;
+                }
+                let unwidened = type_without_literal_widening(arg);
+                let compatible = format_compatible(&ctype, &arg.ctype)
+                    || format_compatible(&ctype, &unwidened)
+                    || (ctype.is_integral() && fits_in_int(arg));
+                if !compatible {
+                    // arguments are counted from 1, like in the attribute
+                    let lval = missing_address_of(&ctype, arg);
+                    let warning = Warning::FormatTypeMismatch(ctype, unwidened, i + 1, lval);
+                    self.warn(warning, location);
+                }
+            }
+        }
+        if check_args {
+            if let Some((_, extra)) = remaining.next() {
+                self.warn(Warning::ExtraFormatArgument, extra.location);
+            }
+        }
+    }
+}
+
+/// A single conversion specification in a format string, like `%-5ld`.
+#[derive(Debug, PartialEq)]
+struct Conversion {
+    /// The index of the `%`
+    start: usize,
+    /// The index just past the conversion specifier
+    end: usize,
+    /// The types of the arguments this conversion consumes, in order,
+    /// or `Err` if the conversion is invalid.
+    expected: Result<Vec<Type>, ()>,
+}
+
+/// A length modifier, like the `l` in `%ld`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Length {
+    None,
+    Char,
+    Short,
+    Long,
+    LongLong,
+    /// `z`, `t`, or `j`; all of these are `long` on the targets saltwater supports
+    Word,
+    LongDouble,
+}
+
+/// Find every conversion in `format`, a string literal including the trailing NUL byte.
+///
+/// Returns `None` if the format uses positional arguments (`%1$d`).
+fn parse_format(format: &[u8], kind: FormatKind) -> Option<Vec<Conversion>> {
+    // ignore the NUL terminator and anything after an embedded NUL
+    let len = format
+        .iter()
+        .position(|&c| c == b'\0')
+        .unwrap_or(format.len());
+    let format = &format[..len];
+    let mut conversions = Vec::new();
+    let mut i = 0;
+    while i < format.len() {
+        if format[i] != b'%' {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        i += 1;
+        let peek = |i: usize| format.get(i).copied();
+        let mut expected = Vec::new();
+        // `%*d` in scanf means "read an int, but don't store it anywhere"
+        let mut suppressed = false;
+        if kind == FormatKind::Scanf && peek(i) == Some(b'*') {
+            suppressed = true;
+            i += 1;
+        }
+        // flags
+        if kind == FormatKind::Printf {
+            while let Some(b'-') | Some(b'+') | Some(b' ') | Some(b'#') | Some(b'0') | Some(b'\'') =
+                peek(i)
+            {
+                i += 1;
+            }
+        }
+        // field width
+        let width_start = i;
+        while matches!(peek(i), Some(c) if c.is_ascii_digit()) {
+            i += 1;
+        }
+        if peek(i) == Some(b'$') && i != width_start {
+            return None;
+        }
+        if kind == FormatKind::Printf && i == width_start && peek(i) == Some(b'*') {
+            expected.push(Type::Int(true));
+            i += 1;
+        }
+        // precision
+        if kind == FormatKind::Printf && peek(i) == Some(b'.') {
+            i += 1;
+            if peek(i) == Some(b'*') {
+                expected.push(Type::Int(true));
+                i += 1;
+            } else {
+                while matches!(peek(i), Some(c) if c.is_ascii_digit()) {
+                    i += 1;
+                }
+            }
+        }
+        let (length, modifier_len) = match (peek(i), peek(i + 1)) {
+            (Some(b'h'), Some(b'h')) => (Length::Char, 2),
+            (Some(b'l'), Some(b'l')) => (Length::LongLong, 2),
+            (Some(b'h'), _) => (Length::Short, 1),
+            (Some(b'l'), _) => (Length::Long, 1),
+            (Some(b'q'), _) => (Length::LongLong, 1),
+            (Some(b'z'), _) | (Some(b't'), _) | (Some(b'j'), _) => (Length::Word, 1),
+            (Some(b'L'), _) => (Length::LongDouble, 1),
+            _ => (Length::None, 0),
+        };
+        i += modifier_len;
+        let specifier = match peek(i) {
+            Some(c) => c,
+            // `printf("%")` or `printf("%l")`
+            None => {
+                conversions.push(Conversion {
+                    start,
+                    end: i,
+                    expected: Err(()),
+                });
+                break;
+            }
+        };
+        i += 1;
+        // `%[abc]` or `%[^]abc]`
+        if kind == FormatKind::Scanf && specifier == b'[' {
+            if peek(i) == Some(b'^') {
+                i += 1;
+            }
+            if peek(i) == Some(b']') {
+                i += 1;
+            }
+            while matches!(peek(i), Some(c) if c != b']') {
+                i += 1;
+            }
+            if peek(i).is_none() {
+                conversions.push(Conversion {
+                    start,
+                    end: i,
+                    expected: Err(()),
+                });
+                break;
+            }
+            i += 1;
+        }
+        let argument = match kind {
+            FormatKind::Printf => printf_argument(specifier, length),
+            FormatKind::Scanf => scanf_argument(specifier, length),
+        };
+        let expected = match argument {
+            Ok(Some(ctype)) if !suppressed => {
+                expected.push(ctype);
+                Ok(expected)
+            }
+            Ok(_) => Ok(expected),
+            Err(()) => Err(()),
+        };
+        conversions.push(Conversion {
+            start,
+            end: i,
+            expected,
+        });
+    }
+    Some(conversions)
+}
+
+/// The integer type with the size given by a length modifier.
+fn integer(length: Length, signed: bool) -> Result<Type, ()> {
+    Ok(match length {
+        Length::None => Type::Int(signed),
+        Length::Char => Type::Char(signed),
+        Length::Short => Type::Short(signed),
+        Length::Long | Length::LongLong | Length::Word => Type::Long(signed),
+        Length::LongDouble => return Err(()),
+    })
+}
+
+fn pointer_to(ctype: Type) -> Type {
+    Type::Pointer(Box::new(ctype), Qualifiers::default())
+}
+
+/// The type of the argument `printf` expects for a conversion, or `None` for `%%`.
+///
+/// Types narrower than `int` are promoted, since they're passed through `...`.
+fn printf_argument(specifier: u8, length: Length) -> Result<Option<Type>, ()> {
+    let promoted = |length| match length {
+        Length::Char | Length::Short => Length::None,
+        other => other,
+    };
+    let ctype = match (specifier, length) {
+        (b'd', _) | (b'i', _) => integer(promoted(length), true)?,
+        (b'o', _) | (b'u', _) | (b'x', _) | (b'X', _) => integer(promoted(length), false)?,
+        (c, _) if b"fFeEgGaA".contains(&c) => match length {
+            Length::None | Length::Long | Length::LongDouble => Type::Double,
+            _ => return Err(()),
+        },
+        (b'c', Length::None) => Type::Int(true),
+        // `wint_t`
+        (b'c', Length::Long) => Type::Int(false),
+        (b's', Length::None) => pointer_to(Type::Char(true)),
+        // `wchar_t *`
+        (b's', Length::Long) => pointer_to(Type::Int(true)),
+        (b'p', Length::None) => pointer_to(Type::Void),
+        (b'n', _) => pointer_to(integer(length, true)?),
+        (b'%', Length::None) => return Ok(None),
+        _ => return Err(()),
+    };
+    Ok(Some(ctype))
+}
+
+/// The type of the argument `scanf` expects for a conversion, or `None` for `%%`.
+fn scanf_argument(specifier: u8, length: Length) -> Result<Option<Type>, ()> {
+    let pointee = match (specifier, length) {
+        (b'd', _) | (b'i', _) | (b'n', _) => integer(length, true)?,
+        (b'o', _) | (b'u', _) | (b'x', _) | (b'X', _) => integer(length, false)?,
+        (c, _) if b"fFeEgGaA".contains(&c) => match length {
+            Length::None => Type::Float,
+            Length::Long | Length::LongDouble => Type::Double,
+            _ => return Err(()),
+        },
+        (b's', Length::None) | (b'c', Length::None) | (b'[', Length::None) => Type::Char(true),
+        // `wchar_t`
+        (b's', Length::Long) | (b'c', Length::Long) | (b'[', Length::Long) => Type::Int(true),
+        (b'p', Length::None) => pointer_to(Type::Void),
+        (b'%', Length::None) => return Ok(None),
+        _ => return Err(()),
+    };
+    Ok(Some(pointer_to(pointee)))
+}
+
+/// Whether an argument of type `actual` can be passed to a conversion expecting `expected`.
+///
+/// Like GCC without `-Wformat-signedness`, this ignores the signedness of integers.
+fn format_compatible(expected: &Type, actual: &Type) -> bool {
+    use Type::*;
+    match (expected, actual) {
+        (Char(_), Char(_))
+        | (Short(_), Short(_))
+        | (Int(_), Int(_))
+        | (Int(_), Enum(_, _))
+        | (Long(_), Long(_))
+        | (Float, Float)
+        | (Double, Double) => true,
+        // `%p` takes any pointer
+        (Pointer(expected, _), Pointer(_, _)) if **expected == Void => true,
+        (Pointer(expected, _), Pointer(actual, _)) => format_compatible(expected, actual),
+        _ => false,
+    }
+}
+
+/// If `arg` is an lvalue whose address `expected` would accept, like `i` in `scanf("%d", i)`,
+/// returns the location of the lvalue.
+fn missing_address_of(expected: &Type, arg: &Expr) -> Option<Location> {
+    let pointee = match expected {
+        // `printf("%p", i)` takes any pointer, so `&i` is only one of many fixes
+        Type::Pointer(pointee, _) if **pointee != Type::Void => pointee,
+        _ => return None,
+    };
+    // `c` in `scanf("%c", c)` was promoted to `int` after being loaded
+    let arg = match &arg.expr {
+        ExprType::Cast(inner) if inner.location == arg.location => inner,
+        _ => arg,
+    };
+    match &arg.expr {
+        // `rval` loads from lvalues by dereferencing them
+        ExprType::Deref(lval) if lval.lval && format_compatible(pointee, &lval.ctype) => {
+            Some(lval.location)
+        }
+        _ => None,
+    }
+}
+
+/// Whether `arg` is an integer constant which fits in an `int`.
+///
+/// Integer literals in saltwater always have type `long`,
+/// but `printf("%d", 1)` is fine.
+fn fits_in_int(arg: &Expr) -> bool {
+    use crate::data::lex::Literal;
+    use std::convert::TryFrom;
+
+    if !arg.ctype.is_integral() {
+        return false;
+    }
+    let scratch = Arena::new();
+    match arg
+        .clone_in(&scratch)
+        .const_fold()
+        .map(|folded| folded.expr)
+    {
+        Ok(ExprType::Literal(Literal::Int(i))) => i32::try_from(i).is_ok(),
+        Ok(ExprType::Literal(Literal::UnsignedInt(u))) => u32::try_from(u).is_ok(),
+        _ => false,
+    }
+}
+
+/// The type `arg` would have if integer constants that fit in an `int` had type `int`.
+///
+/// Integer literals in saltwater always have type `long`, so `i + 1` for an `int i` is a `long`,
+/// but `printf("%d", i + 1)` is fine.
+fn type_without_literal_widening(arg: &Expr) -> Type {
+    use crate::data::hir::BinaryOp::*;
+
+    if fits_in_int(arg) {
+        return Type::Int(true);
+    }
+    match &arg.expr {
+        ExprType::Binary(op, left, right) if arg.ctype.is_integral() => {
+            let (left, right) = (unconverted_type(left), unconverted_type(right));
+            match op {
+                // 6.5.7p3: the type of a shift is the type of its promoted left operand
+                Shl | Shr => left.integer_promote(),
+                Add | Sub | Mul | Div | Mod | BitwiseAnd | BitwiseOr | Xor => {
+                    Type::binary_promote(left, right).unwrap_or_else(|_| arg.ctype.clone())
+                }
+                _ => arg.ctype.clone(),
+            }
+        }
+        _ => arg.ctype.clone(),
+    }
+}
+
+/// The type of an operand before it was implicitly converted by the usual arithmetic conversions.
+fn unconverted_type(operand: &Expr) -> Type {
+    match &operand.expr {
+        // explicit casts include the parenthesized type name in their location, implicit ones don't
+        ExprType::Cast(inner)
+            if inner.location == operand.location
+                && inner.ctype.is_integral()
+                && operand.ctype.is_integral() =>
+        {
+            type_without_literal_widening(inner)
+        }
+        _ => type_without_literal_widening(operand),
+    }
+}
+
+/// The location of `conversion` in the source, if it can be determined.
+///
+/// If the literal contains escape sequences or was concatenated from several strings,
+/// the offsets into the string no longer match the source, so the whole literal is used instead.
+fn conversion_location(format: &Locatable<Vec<u8>>, conversion: &Conversion) -> Location {
+    let span = format.location.span;
+    // `"abc"` is 5 bytes of source, but only 4 (including the NUL terminator) in the string
+    if (span.end - span.start) as usize != format.data.len() + 1 {
+        return format.location;
+    }
+    let start = span.start + 1 + conversion.start as u32;
+    Location {
+        span: (start..span.start + 1 + conversion.end as u32).into(),
+        ..format.location
+    }
+}
+
+impl Type {
+    fn contains_error(&self) -> bool {
+        match self {
+            Type::Error => true,
+            Type::Pointer(inner, _) => inner.contains_error(),
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyze::test::*;
+    use crate::analyze::Analyzer;
+    use crate::parse::test::parser;
+
+    const HEADER: &str = "int printf(const char *, ...); int scanf(const char *, ...);
+        int f(void) { int i; long l; unsigned u; double d; float fl; char *s; char c; short h;
+        ";
+
+    fn format_warnings(call: &str) -> Vec<Locatable<Warning>> {
+        let program = format!("{}{} return 0; }}", HEADER, call);
+        let mut a = Analyzer::new(parser(&program), arena(), false);
+        for res in &mut a {
+            res.unwrap();
+        }
+        a.inner
+            .error_handler
+            .warnings
+            .into_iter()
+            .filter(|w| w.data.name().starts_with("format"))
+            .collect()
+    }
+
+    #[test]
+    fn test_format_checking() {
+        let good = &[
+            r#"printf("hello, world\n");"#,
+            r#"printf("%d %i %c", i, c, h);"#,
+            r#"printf("%u %x %X %o", u, i, u, u);"#,
+            r#"printf("%ld %lu %lld %zu %td", l, l, l, sizeof(i), l);"#,
+            r#"printf("%f %e %g %.3f %lf", d, fl, d, d, d);"#,
+            r#"printf("%s %p %p %%", s, s, &i);"#,
+            r#"printf("%*d %-5.*s", i, i, i, s);"#,
+            r#"printf("%hhd %hd", c, h);"#,
+            r#"printf("%n", &i);"#,
+            r#"printf("%d %u %c", 1, -1, 'a');"#,
+            // `1` is a `long`, but `i + 1` is still an `int`
+            r#"printf("%d %d %d %x", i + 1, 2 * i - 1, c + 1, i << 2);"#,
+            r#"printf("%u %ld %ld", u + 1, l + 1, i + l);"#,
+            r#"printf("%1$d", l);"#,
+            r#"scanf("%d %ld %u %hd %hhd", &i, &l, &u, &h, &c);"#,
+            r#"scanf("%f %lf %s %c %[^\n]", &fl, &d, s, &c, s);"#,
+            r#"scanf("%*d %d", &i);"#,
+            r#"scanf("%[]abc] %%", s);"#,
+        ];
+        for call in good {
+            assert_eq!(format_warnings(call), vec![], "{}", call);
+        }
+        let long = || Type::Long(true);
+        let bad = &[
+            (
+                r#"printf("%d", l);"#,
+                Warning::FormatTypeMismatch(Type::Int(true), long(), 2, None),
+            ),
+            (
+                r#"printf("%s", i);"#,
+                Warning::FormatTypeMismatch(pointer_to(Type::Char(true)), Type::Int(true), 2, None),
+            ),
+            (
+                r#"printf("%f", i);"#,
+                Warning::FormatTypeMismatch(Type::Double, Type::Int(true), 2, None),
+            ),
+            (
+                r#"printf("%ld", i);"#,
+                Warning::FormatTypeMismatch(long(), Type::Int(true), 2, None),
+            ),
+            (
+                r#"printf("%d", (long)i + 1);"#,
+                Warning::FormatTypeMismatch(Type::Int(true), long(), 2, None),
+            ),
+            (
+                r#"printf("%d", l + 1);"#,
+                Warning::FormatTypeMismatch(Type::Int(true), long(), 2, None),
+            ),
+            (
+                r#"printf("%f", i + 1);"#,
+                Warning::FormatTypeMismatch(Type::Double, Type::Int(true), 2, None),
+            ),
+            (
+                r#"printf("%p", i);"#,
+                Warning::FormatTypeMismatch(pointer_to(Type::Void), Type::Int(true), 2, None),
+            ),
+            (
+                r#"printf("%*d", l, i);"#,
+                Warning::FormatTypeMismatch(Type::Int(true), long(), 2, None),
+            ),
+            (
+                r#"printf("%d", 10000000000);"#,
+                Warning::FormatTypeMismatch(Type::Int(true), long(), 2, None),
+            ),
+            (
+                r#"printf("%d %d", i);"#,
+                Warning::MissingFormatArgument("%d".into()),
+            ),
+            (r#"printf("%d", i, i);"#, Warning::ExtraFormatArgument),
+            (r#"printf("%%", i);"#, Warning::ExtraFormatArgument),
+            (
+                r#"printf("%y", i);"#,
+                Warning::InvalidFormatSpecifier("%y".into()),
+            ),
+            (
+                r#"printf("%Ld", i);"#,
+                Warning::InvalidFormatSpecifier("%Ld".into()),
+            ),
+            (
+                r#"printf("100%");"#,
+                Warning::InvalidFormatSpecifier("%".into()),
+            ),
+            (
+                r#"scanf("%f", &d);"#,
+                Warning::FormatTypeMismatch(
+                    pointer_to(Type::Float),
+                    pointer_to(Type::Double),
+                    2,
+                    None,
+                ),
+            ),
+            (
+                r#"scanf("%ld", &i);"#,
+                Warning::FormatTypeMismatch(
+                    pointer_to(long()),
+                    pointer_to(Type::Int(true)),
+                    2,
+                    None,
+                ),
+            ),
+            (
+                r#"scanf("%s", &s);"#,
+                Warning::FormatTypeMismatch(
+                    pointer_to(Type::Char(true)),
+                    pointer_to(pointer_to(Type::Char(true))),
+                    2,
+                    None,
+                ),
+            ),
+        ];
+        for (call, warning) in bad {
+            let warnings: Vec<_> = format_warnings(call).into_iter().map(|w| w.data).collect();
+            assert_eq!(&warnings, &[warning.clone()], "{}", call);
+        }
+    }
+
+    #[test]
+    fn test_format_location() {
+        let call = r#"printf("%d and %s", i, i);"#;
+        let warnings = format_warnings(call);
+        assert_eq!(warnings.len(), 1);
+        let span = warnings[0].location.span;
+        let start = HEADER.len() + call.find("%s").unwrap();
+        assert_eq!(span.start as usize, start);
+        assert_eq!(span.end as usize, start + 2);
+    }
+
+    #[test]
+    fn test_format_missing_address_of() {
+        for (call, arg, expected, actual) in &[
+            (r#"scanf("%d", i);"#, "i", Type::Int(true), Type::Int(true)),
+            // promoted to `int`, but `&c` is still a `char *`
+            (r#"scanf("%c", c);"#, "c", Type::Char(true), Type::Int(true)),
+            (
+                r#"printf("%d%n", i, i);"#,
+                "i)",
+                Type::Int(true),
+                Type::Int(true),
+            ),
+        ] {
+            let warnings = format_warnings(call);
+            assert_eq!(warnings.len(), 1, "{}", call);
+            let lval = match &warnings[0].data {
+                Warning::FormatTypeMismatch(e, a, _, Some(lval)) => {
+                    assert_eq!((e, a), (&pointer_to(expected.clone()), actual), "{}", call);
+                    lval
+                }
+                other => panic!("expected a missing '&' in {}, got {:?}", call, other),
+            };
+            let start = HEADER.len() + call.rfind(arg).unwrap();
+            assert_eq!(lval.span.start as usize, start, "{}", call);
+        }
+        // `%p` takes any pointer, and `s` isn't an lvalue of type `char`
+        for call in &[r#"printf("%p", i);"#, r#"scanf("%s", 1);"#] {
+            let warnings = format_warnings(call);
+            assert!(
+                matches!(warnings[0].data, Warning::FormatTypeMismatch(_, _, _, None)),
+                "{}",
+                call
+            );
+        }
+    }
+
+    #[test]
+    fn test_format_attribute() {
+        let program =
+            "int log_msg(int level, const char *fmt, ...) __attribute__((format(printf, 2, 3)));
+            int f(void); int f(void) { return log_msg(1, \"%s %d\", 1, 2); }";
+        let mut a = Analyzer::new(parser(program), arena(), false);
+        for res in &mut a {
+            res.unwrap();
+        }
+        let warnings: Vec<_> = a
+            .inner
+            .error_handler
+            .warnings
+            .into_iter()
+            .map(|w| w.data)
+            .collect();
+        assert_eq!(
+            warnings,
+            vec![Warning::FormatTypeMismatch(
+                pointer_to(Type::Char(true)),
+                Type::Int(true),
+                3,
+                None
+            )]
+        );
+        assert_decl_display(
+            "int vlog(const char *, __builtin_va_list) __attribute__((format(printf, 1, 0)));",
+            "__attribute__((format(printf, 1, 0))) int vlog(const char *, va_list);",
+        );
+    }
+}
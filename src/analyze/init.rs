@@ -1,56 +1,104 @@
 //! 6.7.9 Initialization
 
 use super::PureAnalyzer;
-use crate::data::{ast, error::SemanticError, hir::*, types, Location, Type};
+use crate::data::{ast, error::SemanticError, hir::*, types, Literal, Location, Type};
 
-impl PureAnalyzer {
+impl<'hir> PureAnalyzer<'hir> {
+    /// Each expression in an initializer is a full expression (6.8p4),
+    /// so `int r = f(i++, i++);` is checked for unsequenced modifications like an expression statement.
+    fn initializer_expr(&mut self, expr: ast::Expr) -> Expr<'hir> {
+        self.check_sequence_points(&expr);
+        self.expr(expr)
+    }
+    /// `qualifiers` are the qualifiers of the variable being initialized,
+    /// which apply to the innermost type of `ctype`.
+    /// `is_static` is whether the variable has static storage duration.
     pub(super) fn parse_initializer(
         &mut self,
         init: ast::Initializer,
         ctype: &Type,
+        qualifiers: Qualifiers,
+        is_static: bool,
         location: Location,
-    ) -> Initializer {
+    ) -> Initializer<'hir> {
         use ast::Initializer::{Aggregate, Scalar};
         // initializer_list
         let mut expr = match init {
-            Aggregate(list) => return self.check_aggregate_overflow(list, ctype, location),
-            Scalar(expr) => self.expr(*expr),
+            Aggregate(list) => {
+                return self.check_aggregate_overflow(list, ctype, is_static, location)
+            }
+            Scalar(expr) => self.initializer_expr(*expr),
         };
         // The only time (that I know of) that an expression will initialize a non-scalar
-        // is for character literals.
-        let is_char_array = match ctype {
-            Type::Array(inner, _) => inner.is_char(),
+        // is for string literals.
+        let is_string_array = match (ctype, &expr.expr) {
+            (_, ExprType::Literal(literal)) if literal.is_string() => {
+                ctype.is_initialized_by_string(literal)
+            }
+            (Type::Array(inner, _), _) => inner.is_char(),
             _ => false,
         };
         // See section 6.7.9 of the C11 standard:
         // The initializer for a scalar shall be a single expression, optionally enclosed in braces.
         // The initial value of the object is that of the expression (after conversion)
-        if !is_char_array {
-            expr = expr
-                .rval()
-                // if ctype is not a scalar, this will report an error, so we don't have to handle it specially
-                .implicit_cast(ctype, &mut self.error_handler);
-        }
-        if !expr.lval && self.scope.is_global() && ctype.is_pointer() {
-            expr = Expr {
-                lval: false,
-                location: expr.location,
-                ctype: expr.ctype.clone(),
-                expr: ExprType::StaticRef(Box::new(expr)),
+        if is_string_array {
+            let string_len = match &expr.expr {
+                ExprType::Literal(Literal::Str(string)) => Some(string.len()),
+                ExprType::Literal(Literal::WideStr(_, units)) => Some(units.len()),
+                _ => None,
             };
+            // char s[2] = "abc";
+            if let (Some(string_len), Type::Array(_, types::ArrayType::Fixed(len))) =
+                (string_len, ctype)
+            {
+                // the null terminator is only stored if there's room
+                let chars = (string_len as u64).saturating_sub(1);
+                if chars > *len {
+                    self.err(
+                        SemanticError::TooManyMembers(*len as usize, chars as usize),
+                        location,
+                    );
+                }
+            }
+        } else {
+            // if ctype is not a scalar, this will report an error, so we don't have to handle it specially
+            expr = expr.assignment_conversion(
+                ctype,
+                qualifiers.c_const,
+                self.arena,
+                &mut self.error_handler,
+            );
+        }
+        if is_static {
+            expr = self.static_ref(expr, ctype);
+        }
+        Initializer::Scalar(self.arena.expr(expr))
+    }
+
+    /// Mark a pointer initializer of a static variable, like `"abc"` in `static char *p = "abc";`,
+    /// as an address that has to be computed at link time.
+    fn static_ref(&self, expr: Expr<'hir>, ctype: &Type) -> Expr<'hir> {
+        if expr.lval || !ctype.is_pointer() {
+            return expr;
+        }
+        Expr {
+            lval: false,
+            location: expr.location,
+            ctype: expr.ctype.clone(),
+            expr: ExprType::StaticRef(self.arena.expr(expr)),
         }
-        Initializer::Scalar(Box::new(expr))
     }
 
     fn check_aggregate_overflow(
         &mut self,
         list: Vec<ast::Initializer>,
         ctype: &Type,
+        is_static: bool,
         location: Location,
-    ) -> Initializer {
+    ) -> Initializer<'hir> {
         let len = list.len();
         let mut iter = list.into_iter().peekable();
-        let init = self.aggregate_initializer(&mut iter, ctype, location);
+        let init = self.aggregate_initializer(&mut iter, ctype, is_static, location);
         let leftover = iter.count();
         if leftover > 0 {
             self.err(SemanticError::TooManyMembers(len - leftover, len), location);
@@ -66,8 +114,9 @@ impl PureAnalyzer {
         &mut self,
         list: &mut std::iter::Peekable<impl Iterator<Item = ast::Initializer>>,
         elem_type: &Type,
+        is_static: bool,
         location: Location,
-    ) -> Initializer {
+    ) -> Initializer<'hir> {
         use ast::Initializer::{Aggregate, Scalar};
 
         let mut elems = vec![];
@@ -92,10 +141,22 @@ impl PureAnalyzer {
             let next = match elem {
                 Aggregate(_) => match list.next() {
                     Some(Aggregate(inner_list)) => {
-                        self.check_aggregate_overflow(inner_list, &inner, location)
+                        self.check_aggregate_overflow(inner_list, &inner, is_static, location)
                     }
                     _ => unreachable!(),
                 },
+                // struct { char name[4]; } s = { "abc" };
+                // 6.7.9p14: a character array can be initialized by a string literal
+                Scalar(expr) if is_string_initializer(&inner, expr) => match list.next() {
+                    Some(init) => self.parse_initializer(
+                        init,
+                        &inner,
+                        Qualifiers::default(),
+                        is_static,
+                        location,
+                    ),
+                    None => unreachable!(),
+                },
                 Scalar(_) => {
                     // int a[][3] = {1,2,3}
                     //               ^
@@ -103,18 +164,23 @@ impl PureAnalyzer {
                     // see if we can short circuit int[][3] -> int[3]
                     if inner != Type::Error && !inner.is_scalar() {
                         // Note: this element is _not_ consumed
-                        self.aggregate_initializer(list, &inner, location)
+                        self.aggregate_initializer(list, &inner, is_static, location)
                     // type is scalar and initializer is scalar
                     // int a[][3] = {{1,2,3}}
                     } else {
                         let expr = match list.next() {
                             Some(Scalar(expr)) => self
-                                .expr(*expr)
-                                .rval()
-                                .implicit_cast(&inner, &mut self.error_handler),
+                                .initializer_expr(*expr)
+                                .rval(self.arena)
+                                .implicit_cast(&inner, self.arena, &mut self.error_handler),
                             _ => unreachable!(),
                         };
-                        Initializer::Scalar(Box::new(expr))
+                        let expr = if is_static {
+                            self.static_ref(expr, &inner)
+                        } else {
+                            expr
+                        };
+                        Initializer::Scalar(self.arena.expr(expr))
                     }
                 }
             };
@@ -134,7 +200,42 @@ impl PureAnalyzer {
     }
 }
 
+impl Initializer<'_> {
+    /// The length of the array this initializes, if it's an initializer list or string literal.
+    pub(super) fn array_len(&self) -> Option<u64> {
+        let len = match self {
+            Initializer::InitializerList(list) => list.len(),
+            Initializer::Scalar(expr) => match &expr.expr {
+                ExprType::Literal(Literal::Str(s)) => s.len(),
+                ExprType::Literal(Literal::WideStr(_, units)) => units.len(),
+                _ => return None,
+            },
+            Initializer::FunctionBody(_) => return None,
+        };
+        Some(len as u64)
+    }
+}
+
+/// Whether `expr` is a string literal which can initialize `ctype`.
+fn is_string_initializer(ctype: &Type, expr: &ast::Expr) -> bool {
+    matches!(&expr.data, ast::ExprType::Literal(literal) if ctype.is_initialized_by_string(literal))
+}
+
 impl Type {
+    /// Whether this is an array which the string literal `literal` can initialize (6.7.9p14-15).
+    ///
+    /// The prefix has to match the element type: `char s[] = L"abc";` is an error.
+    fn is_initialized_by_string(&self, literal: &Literal) -> bool {
+        match (self, literal) {
+            (Type::Array(inner, _), Literal::Str(_)) => inner.is_char(),
+            (Type::Array(inner, _), Literal::WideStr(encoding, _)) => {
+                // `wchar_t s[] = L"abc";`, where `wchar_t` is `int`
+                let element = encoding.element_type();
+                inner.is_integral() && inner.sizeof() == element.sizeof()
+            }
+            _ => false,
+        }
+    }
     /// Given a type, return the maximum number of initializers for that type
     fn type_len(&self) -> usize {
         use types::ArrayType;
@@ -143,8 +244,8 @@ impl Type {
             Type::Array(_, ArrayType::Fixed(size)) => *size as usize,
             Type::Array(_, ArrayType::Unbounded) => 0,
             Type::Struct(st) | Type::Union(st) => st.members().len(),
-            Type::Function { .. } | Type::Error => 1,
-            _ => unimplemented!("type checking for {}", self),
+            // `type_at` gives an error for everything else
+            _ => 1,
         }
     }
     /// Given a type and an index,
@@ -174,7 +275,7 @@ impl Type {
             }
             Type::Union(struct_type) => {
                 if index != 0 {
-                    return Err("can only initialize first member of an enum".into());
+                    return Err(SemanticError::NonFirstUnionMember);
                 }
                 let members = struct_type.members();
                 Ok(members
@@ -183,7 +284,8 @@ impl Type {
                     .unwrap_or(Type::Error))
             }
             Type::Function { .. } | Type::Error => Ok(Type::Error),
-            _ => unimplemented!("type checking for aggregate initializers of type {}", self),
+            // `__builtin_va_list l = {0};`
+            _ => Err(SemanticError::InvalidAggregateInitializer(self.clone())),
         }
     }
 }
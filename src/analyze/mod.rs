@@ -1,16 +1,31 @@
+mod dead_store;
 mod expr;
+mod format;
 mod init;
+mod sequence;
 mod stmt;
+mod uninit;
 
-use std::collections::{HashSet, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::convert::TryInto;
+use std::rc::Rc;
 
 use counter::Counter;
 
-use crate::data::{error::Warning, hir::*, lex::Keyword, *};
+use crate::arch::Target;
+use crate::data::{
+    error::{Shadowed, Warning},
+    hir::*,
+    lex::Keyword,
+    *,
+};
+use crate::fold::{const_eval, ConstValue};
 use crate::intern::InternedStr;
+use crate::lex::{PreProcessor, PreProcessorBuilder};
 use crate::parse::{Lexer, Parser};
-use crate::RecursionGuard;
+use crate::stats::{self, Phase};
+use crate::suppress_marked_extensions;
+use crate::{ice, RecursionGuard};
 
 pub(crate) type TagScope = Scope<InternedStr, TagEntry>;
 
@@ -19,7 +34,7 @@ pub(crate) enum TagEntry {
     Struct(StructRef),
     Union(StructRef),
     // list of (name, value)s
-    Enum(Vec<(InternedStr, i64)>),
+    Enum(Rc<Vec<(InternedStr, i64)>>),
 }
 
 /// The driver for `PureAnalyzer`.
@@ -27,9 +42,9 @@ pub(crate) enum TagEntry {
 /// This implements `Iterator` and ensures that declarations and errors are returned in the correct error.
 /// Use this if you want to compile an entire C program,
 /// or if it is important to show errors in the correct order relative to declarations.
-pub struct Analyzer<T: Lexer> {
+pub struct Analyzer<'hir, T: Lexer> {
     declarations: Parser<T>,
-    pub inner: PureAnalyzer,
+    pub inner: PureAnalyzer<'hir>,
     /// Whether to print each declaration as it is seen
     pub debug: bool,
 }
@@ -40,9 +55,11 @@ pub struct Analyzer<T: Lexer> {
 /// Use this if you need to analyze a specific AST data type without parsing a whole program.
 
 // The struct is used mostly for holding scopes and error handler.
-pub struct PureAnalyzer {
+pub struct PureAnalyzer<'hir> {
+    /// Where HIR expressions and statements are allocated
+    arena: &'hir Arena<'hir>,
     // in case a `Declaration` has multiple declarators
-    pending: VecDeque<Locatable<Declaration>>,
+    pending: VecDeque<Locatable<Declaration<'hir>>>,
     /// objects that are in scope
     /// C actually has 4 different scopes:
     /// 1. ordinary identifiers
@@ -57,26 +74,62 @@ pub struct PureAnalyzer {
     tag_scope: TagScope,
     /// Stores all variables that have been initialized so far
     initialized: HashSet<Symbol>,
+    /// What each identifier refers to, for editor tooling
+    references: References,
     /// Internal API which makes it easier to return errors lazily
     error_handler: ErrorHandler,
     /// Internal API which prevents segfaults due to stack overflow
     recursion_guard: RecursionGuard,
-    /// Hack to make compound assignment work
+    /// Hack to make implicit function declarations work
     ///
-    /// For `a += b`, `a` must only be evaluated once.
-    /// The way `assignment_expr` handles this is by desugaring to
-    /// `tmp = &a; *tmp = *tmp + b;`
-    /// However, the backend still has to see the declaration.
-    /// There's no way to return a statement from an expression,
+    /// C89 implicitly declares `extern int f();` when `f()` is called without a declaration.
+    /// The backend still has to see the declaration,
+    /// but there's no way to return a statement from an expression,
     /// so instead we store it in a side channel.
     ///
     /// TODO: this should be a field on `FunctionAnalyzer`, not `Analyzer`
-    decl_side_channel: Vec<Locatable<Declaration>>,
+    decl_side_channel: Vec<Locatable<Declaration<'hir>>>,
+    /// The name of the function currently being analyzed, used for `__func__`.
+    ///
+    /// TODO: this should also be a field on `FunctionAnalyzer`
+    current_function: Option<InternedStr>,
+    /// The declaration of `__func__` for the current function, once it's been used
+    func_name: Option<Locatable<Declaration<'hir>>>,
+    /// The parameters of the function currently being analyzed, for `-Wshadow`
+    current_params: Vec<Symbol>,
+    /// Whether this is a freestanding implementation, where `main` isn't special (5.1.2.1)
+    freestanding: bool,
+    /// Variables whose value has been used, for `-Wunused-variable`
+    read: HashSet<Symbol>,
+    /// Variables that have been assigned to with `=`, for `-Wunused-but-set-variable`
+    written: HashSet<Symbol>,
+    /// Declarations marked `__attribute__((unused))`
+    maybe_unused: HashSet<Symbol>,
+    /// The value of each enumeration constant, e.g. `A` in `enum e { A }`
+    enumerators: HashMap<Symbol, i64>,
+    /// Parameters declared as arrays, with the type they were written with, for `-Wsizeof-array-argument`
+    array_params: HashMap<Symbol, Type>,
+    /// Calls to functions without a prototype, with the promoted type of each argument,
+    /// to check against a later prototype with `-Wunprototyped-call` and `-Wunprototyped-call-type`
+    unprototyped_calls: HashMap<InternedStr, Vec<Locatable<Vec<Type>>>>,
+    /// Declarations marked `__attribute__((deprecated))`, with the message and location of the attribute
+    deprecated: HashMap<Symbol, (Option<String>, Location)>,
+    /// File-scope `static` declarations, and whether each is a definition, for `-Wunused-function`
+    file_statics: Vec<(Symbol, bool)>,
+    /// Names with a definition at file scope, which can be the target of `__attribute__((alias))`
+    defined: HashSet<InternedStr>,
+    /// Declarations marked `__attribute__((alias("target")))`, with the target and the location of the declaration
+    aliases: Vec<(InternedStr, InternedStr, Location)>,
+    /// Whether names starting with `_` are exempt from the unused warnings
+    allow_unused_underscore: bool,
+    /// Which version of C to accept
+    standard: Standard,
 }
 
-impl<T: Lexer> Iterator for Analyzer<T> {
-    type Item = CompileResult<Locatable<Declaration>>;
+impl<'hir, T: Lexer> Iterator for Analyzer<'hir, T> {
+    type Item = CompileResult<Locatable<Declaration<'hir>>>;
     fn next(&mut self) -> Option<Self::Item> {
+        let _analysis = stats::enter(Phase::Analysis);
         loop {
             // Instead of returning `SemanticResult`, the analyzer puts all errors into `error_handler`.
             // This simplifies the logic in `next` greatly.
@@ -92,9 +145,17 @@ impl<T: Lexer> Iterator for Analyzer<T> {
                 return Some(Ok(decl));
             }
             // Now do the real work.
-            let next = match self.declarations.next()? {
-                Err(err) => return Some(Err(err)),
-                Ok(decl) => decl,
+            let next = match self.declarations.next() {
+                Some(Err(err)) => return Some(Err(err)),
+                Some(Ok(decl)) => decl,
+                None => {
+                    // now that the whole translation unit has been seen,
+                    // we know which static functions were never used
+                    self.inner.warn_unused_statics();
+                    // and which aliases don't refer to anything
+                    self.inner.check_aliases();
+                    return self.inner.error_handler.pop_front().map(Err);
+                }
             };
             let decls = self.inner.parse_external_declaration(next);
             // TODO: if an error occurs, should we still add the declaration to `pending`?
@@ -103,35 +164,94 @@ impl<T: Lexer> Iterator for Analyzer<T> {
     }
 }
 
-impl<I: Lexer> Analyzer<I> {
-    pub fn new(parser: Parser<I>, debug: bool) -> Self {
+impl<'hir, I: Lexer> Analyzer<'hir, I> {
+    /// Create an analyzer which allocates HIR nodes in `arena`.
+    pub fn new(parser: Parser<I>, arena: &'hir Arena<'hir>, debug: bool) -> Self {
         Self {
             declarations: parser,
             debug,
-            inner: PureAnalyzer::new(),
+            inner: PureAnalyzer::new(arena),
         }
     }
-}
-
-impl Default for PureAnalyzer {
-    fn default() -> Self {
-        Self::new()
+    /// Analyze the program for a freestanding environment instead of a hosted one.
+    pub fn freestanding(mut self, freestanding: bool) -> Self {
+        self.inner.freestanding = freestanding;
+        self
+    }
+    /// Don't warn about unused declarations whose names start with `_`.
+    pub fn allow_unused_underscore(mut self, allow: bool) -> Self {
+        self.inner.allow_unused_underscore = allow;
+        self
+    }
+    /// Analyze the given version of C, e.g. allowing implicit function declarations in C89.
+    ///
+    /// This should be the same as the standard given to the parser.
+    pub fn standard(mut self, standard: Standard) -> Self {
+        self.inner.standard = standard;
+        self
+    }
+    /// Return all warnings seen so far, from both the parser and the analyzer.
+    ///
+    /// These warnings are consumed and will not be returned if you call
+    /// `warnings()` again.
+    pub fn warnings(&mut self) -> VecDeque<CompileWarning> {
+        let mut warnings = self.declarations.warnings();
+        warnings.extend(self.inner.warnings());
+        warnings
+    }
+    /// The locations of all the code marked with `__extension__` seen so far.
+    pub fn extensions(&self) -> &[Location] {
+        self.declarations.extensions()
+    }
+    /// The tokens the parser is reading from.
+    pub(crate) fn tokens_mut(&mut self) -> &mut I {
+        self.declarations.tokens_mut()
     }
 }
 
-impl PureAnalyzer {
-    pub fn new() -> Self {
+impl<'hir> PureAnalyzer<'hir> {
+    /// Create an analyzer which allocates HIR nodes in `arena`.
+    pub fn new(arena: &'hir Arena<'hir>) -> Self {
         Self {
+            arena,
             error_handler: ErrorHandler::new(),
             scope: Scope::new(),
             tag_scope: Scope::new(),
             pending: VecDeque::new(),
             initialized: HashSet::new(),
+            references: References::default(),
             recursion_guard: RecursionGuard::default(),
             decl_side_channel: Vec::new(),
+            current_function: None,
+            func_name: None,
+            current_params: Vec::new(),
+            freestanding: false,
+            read: HashSet::new(),
+            written: HashSet::new(),
+            maybe_unused: HashSet::new(),
+            enumerators: HashMap::new(),
+            array_params: HashMap::new(),
+            unprototyped_calls: HashMap::new(),
+            deprecated: HashMap::new(),
+            file_statics: Vec::new(),
+            defined: HashSet::new(),
+            aliases: Vec::new(),
+            allow_unused_underscore: false,
+            standard: Standard::default(),
         }
     }
 
+    /// Analyze fragments for a freestanding environment instead of a hosted one.
+    pub fn freestanding(mut self, freestanding: bool) -> Self {
+        self.freestanding = freestanding;
+        self
+    }
+    /// Analyze the given version of C, e.g. allowing implicit function declarations in C89.
+    pub fn standard(mut self, standard: Standard) -> Self {
+        self.standard = standard;
+        self
+    }
+
     /// Return all warnings seen so far.
     ///
     /// These warnings are consumed and will not be returned if you call
@@ -139,6 +259,158 @@ impl PureAnalyzer {
     pub fn warnings(&mut self) -> VecDeque<CompileWarning> {
         std::mem::take(&mut self.error_handler.warnings)
     }
+
+    /// Which declaration each identifier seen so far refers to.
+    pub fn references(&self) -> &References {
+        &self.references
+    }
+    /// Create an analyzer where `symbols` are already declared at file scope.
+    ///
+    /// This is useful for analyzing a fragment of a program with `parse_expr_str` or `parse_stmt_str`,
+    /// e.g. for a debugger or REPL that already knows which variables exist.
+    pub fn with_scope(arena: &'hir Arena<'hir>, symbols: impl IntoIterator<Item = Symbol>) -> Self {
+        let mut analyzer = Self::new(arena);
+        for symbol in symbols {
+            let meta = symbol.get();
+            if let Some(value) = meta.enum_value() {
+                analyzer.enumerators.insert(symbol, value);
+            }
+            analyzer.scope.insert(meta.id, symbol);
+        }
+        analyzer
+    }
+    /// All identifiers currently in scope, including ones declared by `parse_stmt_str`.
+    ///
+    /// If an identifier is shadowed, only the innermost declaration is returned.
+    pub fn scope(&self) -> HashMap<InternedStr, Symbol> {
+        let mut visible = HashMap::new();
+        for (&id, &symbol) in self.scope.iter() {
+            visible.entry(id).or_insert(symbol);
+        }
+        visible
+    }
+    /// Preprocess, parse, and analyze a single expression.
+    ///
+    /// Identifiers are looked up in the current scope (see `with_scope`).
+    /// Any tokens after the expression are an error.
+    /// Only the first error is returned; warnings are available through `warnings()`.
+    ///
+    /// The locations in the result point into a source map that is discarded afterwards,
+    /// so they are only useful for their offsets into `src`.
+    pub fn parse_expr_str(&mut self, src: &str) -> CompileResult<Expr<'hir>> {
+        let expr = self.parse_fragment(src, "expression", Parser::expr, Self::expr);
+        // implicit function declarations have nowhere to go
+        self.decl_side_channel.clear();
+        expr
+    }
+    /// Preprocess, parse, and analyze a single statement.
+    ///
+    /// The statement is analyzed as if it were in the body of a function returning `void`.
+    /// Declarations in the statement stay in scope for future calls,
+    /// and can be seen with `scope()`.
+    /// Only the first error is returned; warnings are available through `warnings()`.
+    pub fn parse_stmt_str(&mut self, src: &str) -> CompileResult<Stmt<'hir>> {
+        // fragments share a single block scope, like the statements of a function body
+        if self.scope.is_global() {
+            self.scope.enter();
+            self.tag_scope.enter();
+        }
+        self.parse_fragment(src, "statement", Parser::statement, |analyzer, stmt| {
+            let location = stmt.location;
+            let mut func = FunctionAnalyzer {
+                metadata: FunctionData {
+                    id: InternedStr::default(),
+                    location,
+                    return_type: Type::Void,
+                    return_qualifiers: Qualifiers::NONE,
+                    params: Vec::new(),
+                },
+                analyzer,
+                switches: Vec::new(),
+                loops: 0,
+                labels: HashMap::new(),
+                gotos: Vec::new(),
+            };
+            func.parse_stmt(stmt)
+        })
+    }
+    /// Preprocess, parse, and analyze a single external declaration:
+    /// either a function definition or a list of declarations, like `int i, *p;`.
+    ///
+    /// The declarations stay in scope for future calls.
+    /// This should not be mixed with `parse_stmt_str`,
+    /// since later declarations would no longer be at file scope.
+    /// Only the first error is returned; warnings are available through `warnings()`.
+    pub fn parse_external_decl_str(
+        &mut self,
+        src: &str,
+    ) -> CompileResult<Vec<Locatable<Declaration<'hir>>>> {
+        self.parse_fragment(
+            src,
+            "declaration",
+            Parser::external_declaration,
+            Self::parse_external_declaration,
+        )
+    }
+    /// Shared implementation of `parse_expr_str`, `parse_stmt_str`, and `parse_external_decl_str`.
+    ///
+    /// `what` describes what `parse` expects, for error messages.
+    fn parse_fragment<S, R>(
+        &mut self,
+        src: &str,
+        what: &'static str,
+        parse: impl FnOnce(&mut Parser<PreProcessor<'static>>) -> Result<S, Locatable<SyntaxError>>,
+        analyze: impl FnOnce(&mut Self, S) -> R,
+    ) -> CompileResult<R> {
+        let mut cpp = PreProcessorBuilder::new(format!("{}\n", src))
+            .standard(self.standard)
+            .build();
+        let mut first = match cpp.next_non_whitespace() {
+            Some(token) => token?,
+            None => return Err(cpp.eof().error(SyntaxError::EndOfFile(what))),
+        };
+        let scope = self.scope();
+        let is_typedef = |id| {
+            scope
+                .get(&id)
+                .is_some_and(|symbol| symbol.get().storage_class == StorageClass::Typedef)
+        };
+        // the parser only recognizes typedefs in the tokens it reads itself
+        if let Token::Id(id) = first.data {
+            if is_typedef(id) {
+                first.data = Token::Keyword(Keyword::UserTypedef(id));
+            }
+        }
+        let mut parser = Parser::new(first, cpp, false).standard(self.standard);
+        // let the parser know which identifiers are types
+        for &id in scope.keys() {
+            parser.typedefs.insert(id, is_typedef(id));
+        }
+        let parsed = parse(&mut parser);
+        let trailing = parsed.is_ok() && !parser.is_empty();
+        let trailing_location = parser.next_location();
+        let mut errors = parser.take_errors();
+        let mut warnings = parser.warnings();
+        suppress_marked_extensions(&mut warnings, parser.extensions());
+        self.error_handler.warnings.extend(warnings);
+        self.error_handler
+            .warnings
+            .extend(parser.tokens_mut().warnings());
+        let parsed = parsed?;
+        if let Some(err) = errors.pop_front() {
+            return Err(err);
+        } else if trailing {
+            return Err(trailing_location.error(SyntaxError::TrailingTokens(what)));
+        }
+        let result = analyze(self, parsed);
+        match self.error_handler.pop_front() {
+            Some(err) => {
+                while self.error_handler.pop_front().is_some() {}
+                Err(err)
+            }
+            None => Ok(result),
+        }
+    }
     // I type these a lot
     #[inline(always)]
     fn err(&mut self, e: SemanticError, l: Location) {
@@ -148,6 +420,48 @@ impl PureAnalyzer {
     fn warn(&mut self, w: Warning, l: Location) {
         self.error_handler.warn(w, l);
     }
+    /// Report a C99 feature used in C89.
+    ///
+    /// GNU89 allows these as extensions, so this is a pedantic warning there and an error otherwise.
+    fn c99_feature(&mut self, feature: &'static str, location: Location) {
+        if self.standard.gnu {
+            self.warn(Warning::C99Extension(feature), location);
+        } else {
+            self.err(
+                SemanticError::NotInStandard(feature, self.standard),
+                location,
+            );
+        }
+    }
+    /// Warn about a function definition that has no prototype, or no previous declaration with a prototype.
+    fn check_prototype(&mut self, definition: &Variable, is_main: bool, location: Location) {
+        let ftype = match &definition.ctype {
+            Type::Function(ftype) => ftype,
+            _ => return,
+        };
+        // int f() {}
+        if !ftype.has_prototype() {
+            self.warn(Warning::OldStyleDefinition, location);
+        }
+        // `main` is only called by the runtime, so it doesn't need a declaration
+        if definition.storage_class == StorageClass::Static || is_main {
+            return;
+        }
+        let previous = self
+            .scope
+            .get(&definition.id)
+            .map(|previous| previous.get());
+        let has_prototype = previous.is_some_and(|previous| match &previous.ctype {
+            // `static int f(void); int f(void) {}` has internal linkage, like `static`
+            Type::Function(ftype) => {
+                ftype.has_prototype() || previous.storage_class == StorageClass::Static
+            }
+            _ => false,
+        });
+        if !has_prototype {
+            self.warn(Warning::MissingPrototype(definition.id), location);
+        }
+    }
     fn recursion_check(&mut self) -> RecursionGuard {
         self.recursion_guard
             .recursion_check(&mut self.error_handler)
@@ -158,17 +472,23 @@ impl PureAnalyzer {
     fn parse_external_declaration(
         &mut self,
         next: Locatable<ast::ExternalDeclaration>,
-    ) -> Vec<Locatable<Declaration>> {
+    ) -> Vec<Locatable<Declaration<'hir>>> {
         use ast::ExternalDeclaration;
 
+        ice::set_location(next.location);
         match next.data {
             ExternalDeclaration::Function(func) => {
                 let id = func.id;
-                let (meta_ref, body) = FunctionAnalyzer::analyze(func, self, next.location);
+                let (meta_ref, body, attributes) =
+                    FunctionAnalyzer::analyze(func, self, next.location);
                 self.scope.insert(id, meta_ref);
+                if meta_ref.get().storage_class == StorageClass::Static {
+                    self.file_statics.push((meta_ref, true));
+                }
                 let decl = Declaration {
                     symbol: meta_ref,
                     init: Some(Initializer::FunctionBody(body)),
+                    attributes,
                 };
                 vec![Locatable::new(decl, next.location)]
             }
@@ -182,11 +502,17 @@ impl PureAnalyzer {
         &mut self,
         declaration: ast::Declaration,
         location: Location,
-    ) -> Vec<Locatable<Declaration>> {
+    ) -> Vec<Locatable<Declaration<'hir>>> {
         let original = self.parse_specifiers(declaration.specifiers, location);
 
-        if original.storage_class == Some(StorageClass::Auto) && self.scope.is_global() {
-            self.err(SemanticError::AutoAtGlobalScope, location);
+        if self.scope.is_global() {
+            match original.storage_class {
+                Some(StorageClass::Auto) => self.err(SemanticError::AutoAtGlobalScope, location),
+                Some(StorageClass::Register) => {
+                    self.err(SemanticError::RegisterAtGlobalScope, location)
+                }
+                _ => {}
+            }
         }
 
         // TODO: this is such a hack: https://github.com/jyn514/rcc/issues/371
@@ -195,10 +521,13 @@ impl PureAnalyzer {
         for d in declaration.declarators {
             let mut ctype =
                 self.parse_declarator(original.ctype.clone(), d.data.declarator.decl, d.location);
+            let mut qualifiers = original.qualifiers;
+            let mut attributes = apply_attributes(d.data.attributes, &mut qualifiers.func);
+            attributes.merge(original.attributes.clone());
 
-            if !ctype.is_function() && original.qualifiers.func != FunctionQualifiers::default() {
+            if !ctype.is_function() && qualifiers.func != FunctionQualifiers::default() {
                 self.err(
-                    SemanticError::FuncQualifiersNotAllowed(original.qualifiers.func),
+                    SemanticError::FuncQualifiersNotAllowed(qualifiers.func),
                     d.location,
                 );
             }
@@ -208,7 +537,7 @@ impl PureAnalyzer {
                 Some(i) => i,
                 // int i, ();
                 None => {
-                    self.err("declarations cannot be abstract".into(), d.location);
+                    self.err(SemanticError::AbstractDeclaration, d.location);
                     "<error>".into()
                 }
             };
@@ -219,21 +548,45 @@ impl PureAnalyzer {
                 ctype = Type::Error;
             }
             let init = if let Some(init) = d.data.init {
-                Some(self.parse_initializer(init, &ctype, d.location))
+                let is_static = self.scope.is_global() || sc == StorageClass::Static;
+                Some(self.parse_initializer(init, &ctype, qualifiers, is_static, d.location))
             } else {
                 None
             };
+            // `int a[] = {1, 2, 3};` has type `int [3]` (6.7.9p22)
+            if let (Type::Array(_, size @ types::ArrayType::Unbounded), Some(init)) =
+                (&mut ctype, &init)
+            {
+                if let Some(len) = init.array_len() {
+                    *size = types::ArrayType::Fixed(len);
+                }
+            }
             let symbol = Variable {
                 ctype,
                 id,
-                qualifiers: original.qualifiers,
+                qualifiers,
                 storage_class: sc,
+                location: d.location,
             };
             let symbol = self.declare(symbol, init.is_some(), d.location);
+            self.references.record_declaration(d.location, symbol);
             if init.is_some() {
                 self.initialized.insert(symbol);
             }
-            decls.push(Locatable::new(Declaration { symbol, init }, d.location));
+            let link = attributes.link.clone();
+            self.check_link_attributes(symbol, &link, init.is_some(), d.location);
+            self.record_attributes(symbol, attributes, d.location);
+            if sc == StorageClass::Static && self.scope.is_global() {
+                // `static int f();` is only a declaration, but `static int i;` is a definition
+                let is_definition = !symbol.get().ctype.is_function();
+                self.file_statics.push((symbol, is_definition));
+            }
+            let decl = Declaration {
+                symbol,
+                init,
+                attributes: link,
+            };
+            decls.push(Locatable::new(decl, d.location));
         }
         // int;
         if decls.is_empty() && !original.declared_compound_type {
@@ -341,7 +694,12 @@ impl PureAnalyzer {
                     }
                 }
                 // TODO: implement `long long` as a separate type
-                2 => ctype = Some(Type::Long(signed)),
+                2 => {
+                    if self.standard.version < Version::C99 {
+                        self.warn(Warning::C99Extension("'long long' types"), location);
+                    }
+                    ctype = Some(Type::Long(signed));
+                }
                 _ => {
                     self.err(SemanticError::TooLong(long_count), location);
                     ctype = Some(Type::Long(signed));
@@ -349,12 +707,17 @@ impl PureAnalyzer {
             }
         }
         // 6.7.3 Type qualifiers
-        let qualifiers = Qualifiers {
+        let mut qualifiers = Qualifiers {
             c_const: counter.get(&Const).is_some(),
             volatile: counter.get(&Volatile).is_some(),
+            restrict: counter.get(&Restrict).is_some(),
             func: FunctionQualifiers {
                 inline: counter.get(&Inline).is_some(),
                 no_return: counter.get(&NoReturn).is_some(),
+                returns_twice: false,
+                format: None,
+                constructor: None,
+                destructor: None,
             },
         };
         // 6.7.1 Storage-class specifiers
@@ -422,23 +785,30 @@ impl PureAnalyzer {
             }
         }
         let mut declared_compound_type = false;
+        let mut attributes = DeclAttributes::default();
         for compound in compounds {
             let parsed = match compound {
                 Unit(_) => unreachable!("already caught"),
-                DeclarationSpecifier::Typedef(name) => {
-                    let meta = self
-                        .scope
-                        .get(&name)
-                        .expect("scope of parser and analyzer should match")
-                        .get();
-                    assert_eq!(meta.storage_class, StorageClass::Typedef);
-                    meta.ctype.clone()
-                }
+                // the parser keeps track of typedefs separately, so it can get out of sync
+                DeclarationSpecifier::Typedef(name) => match self.scope.get(&name).copied() {
+                    Some(symbol) if symbol.get().storage_class == StorageClass::Typedef => {
+                        self.warn_deprecated(symbol, location);
+                        symbol.get().ctype.clone()
+                    }
+                    _ => {
+                        self.err(SemanticError::NotATypedef(name), location);
+                        Type::Error
+                    }
+                },
                 Struct(s) => self.struct_specifier(s, true, &mut declared_compound_type, location),
                 Union(s) => self.struct_specifier(s, false, &mut declared_compound_type, location),
                 Enum { name, members } => {
                     self.enum_specifier(name, members, &mut declared_compound_type, location)
                 }
+                Attributes(attributes_list) => {
+                    attributes.merge(apply_attributes(attributes_list, &mut qualifiers.func));
+                    continue;
+                }
             };
             // TODO: this should report the name of the typedef, not the type itself
             if let Some(existing) = &ctype {
@@ -467,15 +837,26 @@ impl PureAnalyzer {
             }
         }
         // `i;` or `const i;`, etc.
-        let ctype = ctype.unwrap_or_else(|| {
+        let mut ctype = ctype.unwrap_or_else(|| {
             self.warn(Warning::ImplicitInt, location);
             Type::Int(true)
         });
+        // `restrict int i;`, but not `typedef int *P; restrict P p;`
+        if qualifiers.restrict {
+            qualifiers.restrict = false;
+            if !ctype.restricts_object() {
+                self.err(SemanticError::InvalidRestrict(ctype.clone()), location);
+            } else if let Type::Pointer(_, pointer_qualifiers) = &mut ctype {
+                // `restrict P` is the same as `int *restrict`
+                pointer_qualifiers.restrict = true;
+            }
+        }
         ParsedType {
             qualifiers,
             storage_class,
             ctype,
             declared_compound_type,
+            attributes,
         }
     }
     // 6.7.2.1 Structure and union specifiers
@@ -491,22 +872,19 @@ impl PureAnalyzer {
             Some(members) => members,
             // struct s
             None => {
+                let keyword = if is_struct {
+                    Keyword::Struct
+                } else {
+                    Keyword::Union
+                };
                 let name = if let Some(name) = struct_spec.name {
                     name
                 } else {
                     // struct;
-                    let err = format!(
-                        "bare '{}' as type specifier is not allowed",
-                        if is_struct { "struct" } else { "union " }
-                    );
-                    self.error_handler.error(SemanticError::from(err), location);
+                    let err = SemanticError::BareTagSpecifier(keyword);
+                    self.error_handler.error(err, location);
                     return Type::Error;
                 };
-                let keyword = if is_struct {
-                    Keyword::Struct
-                } else {
-                    Keyword::Union
-                };
 
                 return match (is_struct, self.tag_scope.get(&name)) {
                     // `struct s *p;`
@@ -518,9 +896,8 @@ impl PureAnalyzer {
                     (_, Some(_)) => {
                         // `union s; struct s;`
                         if self.tag_scope.get_immediate(&name).is_some() {
-                            let kind = if is_struct { "struct" } else { "union " };
                             // TODO: say what the previous declaration was
-                            let err = SemanticError::from(format!("use of '{}' with type tag '{}' that does not match previous struct declaration", name, kind));
+                            let err = SemanticError::TagMismatch(name, keyword);
                             self.error_handler.push_back(Locatable::new(err, location));
                             Type::Error
                         } else {
@@ -537,7 +914,7 @@ impl PureAnalyzer {
             .flatten()
             .collect();
         if members.is_empty() {
-            self.err(SemanticError::from("cannot have empty struct"), location);
+            self.err(SemanticError::EmptyStruct, location);
             return Type::Error;
         }
         let constructor = if is_struct { Type::Struct } else { Type::Union };
@@ -549,20 +926,18 @@ impl PureAnalyzer {
                 let struct_ref = *struct_ref;
                 // struct s { int i; }; struct s { int i; };
                 if !struct_ref.get().is_empty() {
-                    self.err(
-                        SemanticError::from(format!(
-                            "redefinition of {} '{}'",
-                            if is_struct { "struct" } else { "union" },
-                            id
-                        )),
-                        location,
-                    );
+                    let keyword = if is_struct {
+                        Keyword::Struct
+                    } else {
+                        Keyword::Union
+                    };
+                    self.err(SemanticError::TagRedefinition(keyword, id), location);
                 }
                 struct_ref
             } else {
                 StructRef::new()
             };
-            struct_ref.update(members);
+            struct_ref.define(members, location);
             let entry = if is_struct {
                 TagEntry::Struct
             } else {
@@ -620,6 +995,7 @@ impl PureAnalyzer {
                 qualifiers: parsed_type.qualifiers,
                 ctype,
                 id: decl.id.expect("struct members should have an id"),
+                location,
             };
             // struct s { int i: 5 };
             if let Some(bitfield) = bitfield {
@@ -632,36 +1008,24 @@ impl PureAnalyzer {
                 };
                 let type_size = symbol.ctype.sizeof().unwrap_or(0);
                 if bit_size == 0 {
-                    let err = SemanticError::from(format!(
-                        "C does not have zero-sized types. hint: omit the declarator {}",
-                        symbol.id
-                    ));
-                    self.err(err, location);
+                    self.err(SemanticError::ZeroWidthBitfield(symbol.id), location);
                 // struct s { int i: 65 }
                 } else if bit_size > type_size * u64::from(crate::arch::CHAR_BIT) {
-                    let err = SemanticError::from(format!(
-                        "cannot have bitfield {} with size {} larger than containing type {}",
-                        symbol.id, bit_size, symbol.ctype
-                    ));
+                    let err = SemanticError::BitfieldTooWide {
+                        name: symbol.id,
+                        width: bit_size,
+                        ctype: symbol.ctype.clone(),
+                    };
                     self.err(err, location);
                 }
-                self.error_handler.warn(
-                    "bitfields are not implemented and will be ignored",
-                    location,
-                );
+                self.warn(Warning::IgnoredBitfield, location);
             }
             match symbol.ctype {
                 Type::Struct(StructType::Named(_, inner_members))
                 | Type::Union(StructType::Named(_, inner_members))
                     if inner_members.get().is_empty() =>
                 {
-                    self.err(
-                        SemanticError::from(format!(
-                            "cannot use type '{}' before it has been defined",
-                            symbol.ctype
-                        )),
-                        location,
-                    );
+                    self.err(SemanticError::IncompleteMember(symbol.ctype), location);
                     // add this as a member anyway because
                     // later code depends on structs being non-empty
                     symbol.ctype = Type::Error;
@@ -676,10 +1040,7 @@ impl PureAnalyzer {
                 .last()
                 .expect("should have seen at least one declaration");
             self.err(
-                SemanticError::from(format!(
-                    "cannot specify storage class '{}' for struct member '{}'",
-                    class, member.id,
-                )),
+                SemanticError::MemberStorageClass(class, member.id),
                 location,
             );
         }
@@ -702,7 +1063,7 @@ impl PureAnalyzer {
                     name
                 } else {
                     // enum;
-                    let err = SemanticError::from("bare 'enum' as type specifier is not allowed");
+                    let err = SemanticError::BareTagSpecifier(Keyword::Enum);
                     self.error_handler.error(err, location);
                     return Type::Error;
                 };
@@ -715,7 +1076,7 @@ impl PureAnalyzer {
                     // struct e; enum e my_e;
                     Some(_) => {
                         // TODO: say what the previous type was
-                        let err = SemanticError::from(format!("use of '{}' with type tag 'enum' that does not match previous struct declaration", name));
+                        let err = SemanticError::TagMismatch(name, Keyword::Enum);
                         self.error_handler.push_back(Locatable::new(err, location));
                         return Type::Error;
                     }
@@ -744,9 +1105,11 @@ impl PureAnalyzer {
                     ..Default::default()
                 },
                 storage_class: StorageClass::Register,
-                ctype: Type::Enum(None, vec![(name, discriminant)]),
+                ctype: Type::Enum(None, Rc::new(vec![(name, discriminant)])),
+                location,
             };
-            self.declare(tmp_symbol, false, location);
+            let symbol = self.declare(tmp_symbol, false, location);
+            self.enumerators.insert(symbol, discriminant);
             discriminant = discriminant.checked_add(1).unwrap_or_else(|| {
                 self.error_handler
                     .push_back(location.error(SemanticError::EnumOverflow));
@@ -758,35 +1121,31 @@ impl PureAnalyzer {
         }
         // enum e {}
         if members.is_empty() {
-            self.err(SemanticError::from("enums cannot be empty"), location)
+            self.err(SemanticError::EmptyEnum, location)
         }
+        let members = Rc::new(members);
         if let Some(id) = enum_name {
             // enum e { A }; enum e { A };
             if self
                 .tag_scope
-                .insert(id, TagEntry::Enum(members.clone()))
+                .insert(id, TagEntry::Enum(Rc::clone(&members)))
                 .is_some()
             {
-                self.err(format!("redefition of enum '{}'", id).into(), location);
-            }
-        }
-        let ctype = Type::Enum(enum_name, members);
-        match &ctype {
-            Type::Enum(_, members) => {
-                for &(id, _) in members {
-                    self.scope.insert(
-                        id,
-                        Variable {
-                            id,
-                            storage_class: StorageClass::Register,
-                            qualifiers: Qualifiers::NONE,
-                            ctype: ctype.clone(),
-                        }
-                        .insert(),
-                    );
-                }
+                self.err(SemanticError::TagRedefinition(Keyword::Enum, id), location);
             }
-            _ => unreachable!(),
+        }
+        let ctype = Type::Enum(enum_name, Rc::clone(&members));
+        for &(id, value) in members.iter() {
+            let symbol = Variable {
+                id,
+                storage_class: StorageClass::Register,
+                qualifiers: Qualifiers::NONE,
+                ctype: ctype.clone(),
+                location,
+            }
+            .insert();
+            self.scope.insert(id, symbol);
+            self.enumerators.insert(symbol, value);
         }
         ctype
     }
@@ -809,14 +1168,8 @@ impl PureAnalyzer {
     ) -> Type {
         if kind == Keyword::Enum {
             // see section 6.7.2.3 of the C11 standard
-            self.err(
-                SemanticError::from(format!(
-                    "cannot have forward reference to enum type '{}'",
-                    ident
-                )),
-                location,
-            );
-            return Type::Enum(Some(ident), vec![]);
+            self.err(SemanticError::EnumForwardReference(ident), location);
+            return Type::Enum(Some(ident), Rc::new(vec![]));
         }
         let struct_ref = StructRef::new();
         let (entry_type, tag_type): (fn(_) -> _, fn(_) -> _) = if kind == Keyword::Struct {
@@ -828,6 +1181,42 @@ impl PureAnalyzer {
         self.tag_scope.insert(ident, entry);
         tag_type(StructType::Named(ident, struct_ref))
     }
+    /// The qualifiers in `*const volatile` or `[restrict]`.
+    fn pointer_qualifiers(
+        &mut self,
+        qualifiers: Vec<ast::DeclarationSpecifier>,
+        location: Location,
+    ) -> Qualifiers {
+        use UnitSpecifier::*;
+
+        // we reuse `count_specifiers` even though we really only want the qualifiers
+        let (counter, compounds) = count_specifiers(qualifiers, &mut self.error_handler, location);
+        // TODO: this shouldn't allow `inline` or `_Noreturn`
+        let qualifiers = Qualifiers {
+            c_const: counter.get(&Const).is_some(),
+            volatile: counter.get(&Volatile).is_some(),
+            restrict: counter.get(&Restrict).is_some(),
+            func: FunctionQualifiers {
+                inline: counter.get(&Inline).is_some(),
+                no_return: counter.get(&NoReturn).is_some(),
+                returns_twice: false,
+                format: None,
+                constructor: None,
+                destructor: None,
+            },
+        };
+        for &q in counter.keys() {
+            if !q.is_qualifier() {
+                // *extern
+                self.err(SemanticError::NotAQualifier(q.into()), location);
+            }
+        }
+        for spec in compounds {
+            // *struct s {}
+            self.err(SemanticError::NotAQualifier(spec), location);
+        }
+        qualifiers
+    }
     /// Parse the declarator for a variable, given a starting type.
     /// e.g. for `int *p`, takes `start: Type::Int(true)` and returns `Type::Pointer(Type::Int(true))`
     ///
@@ -842,41 +1231,31 @@ impl PureAnalyzer {
         location: Location,
     ) -> Type {
         use crate::data::ast::DeclaratorType::*;
-        use crate::data::types::{ArrayType, FunctionType};
+        use crate::data::types::ArrayType;
 
         let _guard = self.recursion_check();
         match decl {
             End => current,
             Pointer { to, qualifiers } => {
-                use UnitSpecifier::*;
-
                 let inner = self.parse_declarator(current, *to, location);
-                // we reuse `count_specifiers` even though we really only want the qualifiers
-                let (counter, compounds) =
-                    count_specifiers(qualifiers, &mut self.error_handler, location);
-                // *const volatile
-                // TODO: this shouldn't allow `inline` or `_Noreturn`
-                let qualifiers = Qualifiers {
-                    c_const: counter.get(&Const).is_some(),
-                    volatile: counter.get(&Volatile).is_some(),
-                    func: FunctionQualifiers {
-                        inline: counter.get(&Inline).is_some(),
-                        no_return: counter.get(&NoReturn).is_some(),
-                    },
-                };
-                for &q in counter.keys() {
-                    if !q.is_qualifier() {
-                        // *extern
-                        self.err(SemanticError::NotAQualifier(q.into()), location);
-                    }
-                }
-                for spec in compounds {
-                    // *struct s {}
-                    self.err(SemanticError::NotAQualifier(spec), location);
+                let mut qualifiers = self.pointer_qualifiers(qualifiers, location);
+                // int (*restrict f)(void)
+                if qualifiers.restrict && inner.is_function() {
+                    let pointer = Type::Pointer(Box::new(inner.clone()), qualifiers);
+                    self.err(SemanticError::InvalidRestrict(pointer), location);
+                    qualifiers.restrict = false;
                 }
                 Type::Pointer(Box::new(inner), qualifiers)
             }
-            Array { of, size } => {
+            Array {
+                of,
+                size,
+                qualifiers,
+            } => {
+                // int a[const 5]; parameters have already been adjusted to pointers
+                if !qualifiers.is_empty() {
+                    self.err(SemanticError::QualifiedArray, location);
+                }
                 // int a[5]
                 let size = if let Some(expr) = size {
                     let size = Self::const_uint(self.expr(*expr)).unwrap_or_else(|err| {
@@ -895,166 +1274,270 @@ impl PureAnalyzer {
                 }
                 Type::Array(Box::new(of), size)
             }
-            Function(func) => {
-                // TODO: give a warning for `const int f();` somewhere
-                let return_type = self.parse_declarator(current, *func.return_type, location);
-                match &return_type {
-                    // int a()[]
-                    Type::Array(_, _) => self.err(
-                        SemanticError::IllegalReturnType(return_type.clone()),
-                        location,
-                    ),
-                    // int a()()
-                    Type::Function(_) => self.err(
-                        SemanticError::IllegalReturnType(return_type.clone()),
-                        location,
-                    ),
-                    _ => {}
-                }
+            Function(func) => self.function_declarator(current, func, location),
+        }
+    }
+    /// The type of a function declarator, like `int f(int a, char b[])`.
+    ///
+    /// This is separate from `parse_declarator` to keep its stack frame small.
+    fn function_declarator(
+        &mut self,
+        current: Type,
+        func: ast::FunctionDeclarator,
+        location: Location,
+    ) -> Type {
+        use crate::data::types::{ArrayType, FunctionType};
 
-                let mut names = HashSet::new();
-                let mut params = Vec::new();
-                for param in func.params {
-                    // TODO: this location should be that of the param, not of the function
-                    let mut param_type =
-                        self.parse_type(param.specifiers, param.declarator.decl, location);
+        // TODO: give a warning for `const int f();` somewhere
+        let return_type = self.parse_declarator(current, *func.return_type, location);
+        match &return_type {
+            // int a()[]
+            Type::Array(_, _) => self.err(
+                SemanticError::IllegalReturnType(return_type.clone()),
+                location,
+            ),
+            // int a()()
+            Type::Function(_) => self.err(
+                SemanticError::IllegalReturnType(return_type.clone()),
+                location,
+            ),
+            _ => {}
+        }
 
-                    // `int f(int a[])` -> `int f(int *a)`
-                    if let Type::Array(to, _) = param_type.ctype {
-                        param_type.ctype = Type::Pointer(to, Qualifiers::default());
-                    }
+        let mut names = HashSet::new();
+        let mut params = Vec::new();
+        let mut unused_params = Vec::new();
+        let mut array_params = Vec::new();
+        // earlier parameters can be used in the sizes of later ones: `int f(int n, int a[n])`
+        self.scope.enter();
+        for param in func.params {
+            let param_location = param.location;
+            let mut param = param.data;
+            // `int a[restrict]` qualifies the pointer `a` is adjusted to,
+            // and `int a[n]` is allowed even though `n` isn't a constant
+            let (array_qualifiers, array_size) = match &mut param.declarator.decl {
+                ast::DeclaratorType::Array {
+                    qualifiers, size, ..
+                } => (std::mem::take(qualifiers), size.take()),
+                _ => (Vec::new(), None),
+            };
+            let array_qualifiers = self.pointer_qualifiers(array_qualifiers, param_location);
+            let array_size = array_size.and_then(|size| self.parameter_array_size(*size));
+            let mut param_type =
+                self.parse_type(param.specifiers, param.declarator.decl, param_location);
 
-                    // C11 Standard 6.7.6.3 paragraph 8
-                    // "A declaration of a parameter as 'function returning type' shall be
-                    //  adjusted to 'pointer to function returning type', as in 6.3.2.1."
-                    // `int f(int g())` -> `int f(int (*g)())`
-                    if param_type.ctype.is_function() {
-                        param_type.ctype =
-                            Type::Pointer(Box::new(param_type.ctype), Qualifiers::default());
-                    }
+            // `int f(int a[])` -> `int f(int *a)`
+            if let Type::Array(to, size) = param_type.ctype {
+                let size = array_size.map_or(size, ArrayType::Fixed);
+                array_params.push((params.len(), Type::Array(to.clone(), size)));
+                param_type.ctype = Type::Pointer(to, array_qualifiers);
+            }
 
-                    // int a(extern int i)
-                    if let Some(sc) = param_type.storage_class {
-                        self.err(SemanticError::ParameterStorageClass(sc), location);
-                    }
-                    let id = if let Some(name) = param.declarator.id {
-                        // int f(int a, int a)
-                        if names.contains(&name) {
-                            self.err(SemanticError::DuplicateParameter(name), location)
-                        }
-                        names.insert(name);
-                        name
-                    } else {
-                        // int f(int)
-                        InternedStr::default()
-                    };
-                    let meta = Variable {
-                        ctype: param_type.ctype,
-                        id,
-                        qualifiers: param_type.qualifiers,
-                        storage_class: StorageClass::Auto,
-                    };
-                    params.push(meta);
+            // C11 Standard 6.7.6.3 paragraph 8
+            // "A declaration of a parameter as 'function returning type' shall be
+            //  adjusted to 'pointer to function returning type', as in 6.3.2.1."
+            // `int f(int g())` -> `int f(int (*g)())`
+            if param_type.ctype.is_function() {
+                param_type.ctype = Type::Pointer(Box::new(param_type.ctype), Qualifiers::default());
+            }
+
+            // int a(extern int i)
+            // 6.7.6.3p2: "The only storage-class specifier that shall occur in a parameter declaration is register."
+            let storage_class = match param_type.storage_class {
+                None => StorageClass::Auto,
+                Some(StorageClass::Register) => StorageClass::Register,
+                Some(sc) => {
+                    self.err(SemanticError::ParameterStorageClass(sc), param_location);
+                    StorageClass::Auto
                 }
-                // int f(void);
-                let is_void = match params.as_slice() {
-                    [Variable {
-                        ctype: Type::Void, ..
-                    }] => true,
-                    _ => false,
-                };
-                // int f(void, int) or int f(int, void) or ...
-                if !is_void
-                    && params.iter().any(|param| match param.ctype {
-                        Type::Void => true,
-                        _ => false,
-                    })
-                {
-                    self.err(SemanticError::InvalidVoidParameter, location);
-                // int f(void, ...)
-                } else if func.varargs && is_void {
-                    self.err(SemanticError::VoidVarargs, location);
-                // int f(...)
-                } else if func.varargs && params.is_empty() {
-                    self.err(SemanticError::VarargsWithoutParam, location);
+            };
+            let id = if let Some(name) = param.declarator.id {
+                // int f(int a, int a)
+                if names.contains(&name) {
+                    self.err(SemanticError::DuplicateParameter(name), param_location)
                 }
-                Type::Function(FunctionType {
-                    params: params.into_iter().map(|m| m.insert()).collect(),
-                    return_type: Box::new(return_type),
-                    varargs: func.varargs,
-                })
+                names.insert(name);
+                name
+            } else {
+                // int f(int)
+                InternedStr::default()
+            };
+            let meta = Variable {
+                ctype: param_type.ctype,
+                id,
+                qualifiers: param_type.qualifiers,
+                storage_class,
+                location: param_location,
+            };
+            if param_type.attributes.maybe_unused {
+                unused_params.push(params.len());
+            }
+            if meta.id != InternedStr::default() {
+                self.scope.insert(meta.id, meta.clone().insert());
+            }
+            params.push(meta);
+        }
+        self.scope.exit();
+        // `int f();` is the same as `int f(void);` in C2x
+        if params.is_empty() && !func.varargs && self.standard.version >= Version::C2x {
+            params.push(Variable {
+                ctype: Type::Void,
+                id: InternedStr::default(),
+                qualifiers: Qualifiers::default(),
+                storage_class: StorageClass::Auto,
+                location,
+            });
+        }
+        // int f(void);
+        let is_void = matches!(
+            params.as_slice(),
+            [Variable {
+                ctype: Type::Void,
+                ..
+            }]
+        );
+        // int f(void, int) or int f(int, void) or ...
+        if !is_void && params.iter().any(|param| matches!(param.ctype, Type::Void)) {
+            self.err(SemanticError::InvalidVoidParameter, location);
+        // int f(void, ...)
+        } else if func.varargs && is_void {
+            self.err(SemanticError::VoidVarargs, location);
+        // int f(...)
+        } else if func.varargs && params.is_empty() {
+            self.err(SemanticError::VarargsWithoutParam, location);
+        }
+        let params: Vec<_> = params.into_iter().map(|m| m.insert()).collect();
+        for i in unused_params {
+            self.maybe_unused.insert(params[i]);
+        }
+        for (i, original) in array_params {
+            self.array_params.insert(params[i], original);
+        }
+        let ftype = FunctionType {
+            params,
+            return_type: Box::new(return_type),
+            varargs: func.varargs,
+        };
+        // int f();
+        if !ftype.has_prototype() {
+            self.warn(Warning::StrictPrototype, location);
+        }
+        Type::Function(ftype)
+    }
+    /// The length of an array parameter, like `int a[5]`.
+    ///
+    /// The array is adjusted to a pointer, so unlike other arrays, the size doesn't have to be a constant.
+    fn parameter_array_size(&mut self, size: ast::Expr) -> Option<crate::arch::SIZE_T> {
+        let size = self.expr(size);
+        if !size.ctype.is_integral() {
+            self.err(SemanticError::NonIntegralLength, size.location);
+            return None;
+        }
+        match Self::const_uint(size) {
+            Ok(size) => Some(size),
+            // `int a[n]`
+            Err(err) if matches!(err.data, Error::Semantic(SemanticError::NotConstant(_))) => None,
+            Err(err) => {
+                self.error_handler.push_back(err);
+                None
             }
         }
     }
     // used for arrays like `int a[BUF_SIZE - 1];` and enums like `enum { A = 1 }`
-    fn const_literal(expr: Expr) -> CompileResult<Literal> {
+    fn const_integer(expr: Expr<'hir>) -> CompileResult<ConstValue> {
         let location = expr.location;
-        expr.const_fold()?.into_literal().or_else(|runtime_expr| {
-            Err(Locatable::new(
-                SemanticError::NotConstant(runtime_expr).into(),
-                location,
-            ))
-        })
+        match const_eval(&expr, &Target::host())? {
+            value @ ConstValue::Int(_) | value @ ConstValue::Uint(_) => Ok(value),
+            _ => Err(location.error(SemanticError::NonIntegralLength)),
+        }
     }
     /// Return an unsigned integer that can be evaluated at compile time, or an error otherwise.
-    fn const_uint(expr: Expr) -> CompileResult<crate::arch::SIZE_T> {
-        use Literal::*;
-
+    fn const_uint(expr: Expr<'hir>) -> CompileResult<crate::arch::SIZE_T> {
         let location = expr.location;
-        match Self::const_literal(expr)? {
-            UnsignedInt(i) => Ok(i),
-            Int(i) => {
-                if i < 0 {
-                    Err(Locatable::new(
-                        SemanticError::NegativeLength.into(),
-                        location,
-                    ))
-                } else {
-                    Ok(i as u64)
-                }
-            }
-            Char(c) => Ok(c.into()),
-            Str(_) | Float(_) => Err(Locatable::new(
-                SemanticError::NonIntegralLength.into(),
-                location,
-            )),
+        match Self::const_integer(expr)? {
+            ConstValue::Int(i) if i < 0 => Err(location.error(SemanticError::NegativeLength)),
+            ConstValue::Int(i) => Ok(i as u64),
+            ConstValue::Uint(u) => Ok(u),
+            _ => unreachable!("const_integer only returns integers"),
         }
     }
     /// Return a signed integer that can be evaluated at compile time, or an error otherwise.
-    fn const_sint(expr: Expr) -> CompileResult<i64> {
-        use Literal::*;
-
+    fn const_sint(expr: Expr<'hir>) -> CompileResult<i64> {
         let location = expr.location;
-        match Self::const_literal(expr)? {
-            UnsignedInt(u) => match u.try_into() {
-                Ok(i) => Ok(i),
-                Err(_) => Err(Locatable::new(
-                    SemanticError::ConstOverflow { is_positive: true }.into(),
-                    location,
-                )),
-            },
-            Int(i) => Ok(i),
-            Char(c) => Ok(c.into()),
-            Str(_) | Float(_) => Err(Locatable::new(
-                SemanticError::NonIntegralLength.into(),
-                location,
-            )),
+        match Self::const_integer(expr)? {
+            ConstValue::Int(i) => Ok(i),
+            ConstValue::Uint(u) => u
+                .try_into()
+                .map_err(|_| location.error(SemanticError::ConstOverflow { is_positive: true })),
+            _ => unreachable!("const_integer only returns integers"),
         }
     }
     /// Given some variable that we've already parsed (`decl`), perform various checks and add it to the current scope.
     ///
     /// In particular, this checks that
-    /// - for any function `main()`, it has a signature compatible with that required by the C standard
+    /// - for the function `main()` with external linkage, it has a signature compatible with that required by the C standard
     /// - either this variable has not yet been seen in this scope
     ///     - or it is a global variable that is compatible with the previous declaration (see below)
     ///
     /// This returns an opaque index to the `Metadata`.
     fn declare(&mut self, mut decl: Variable, init: bool, location: Location) -> Symbol {
-        if decl.id == "main".into() {
+        if self.is_main(&decl) {
             if let Type::Function(ftype) = &decl.ctype {
                 // int main(int)
+                // this is only a warning because embedded code often uses `void main()`
                 if !ftype.is_main_func_signature() {
-                    self.err(SemanticError::IllegalMainSignature, location);
+                    self.warn(Warning::NonStandardMainSignature, location);
+                }
+            }
+        }
+        // 7.13.2.1: `longjmp` returns to the `setjmp` call, so `setjmp` returns more than once
+        // glibc doesn't mark these with `__attribute__((returns_twice))`, so do it here.
+        if decl.ctype.is_function() && RETURNS_TWICE.contains(&decl.id.resolve_and_clone().as_str())
+        {
+            decl.qualifiers.func.returns_twice = true;
+        }
+        // likewise, glibc only marks `exit` and friends with `__attribute__((noreturn))` for GCC
+        if decl.ctype.is_function() && NO_RETURN.contains(&decl.id.resolve_and_clone().as_str()) {
+            decl.qualifiers.func.no_return = true;
+        }
+        if decl.ctype.is_function() && decl.qualifiers.func.format.is_none() {
+            let name = decl.id.resolve_and_clone();
+            if let Some(&(_, kind, format_index, first_arg)) =
+                FORMAT_FUNCTIONS.iter().find(|(known, ..)| *known == name)
+            {
+                decl.qualifiers.func.format = Some(FormatAttribute {
+                    kind,
+                    format_index,
+                    first_arg,
+                });
+            } else if let Some(existing) = self.scope.get(&decl.id) {
+                // `int f(const char *, ...) __attribute__((format(printf, 1, 2))); int f(const char *, ...);`
+                // the attribute still applies to the second declaration
+                let existing = existing.get();
+                if existing.ctype.is_function() {
+                    decl.qualifiers.func.format = existing.qualifiers.func.format;
+                }
+            }
+        }
+        if let Type::Function(ftype) = &decl.ctype {
+            // the startup code doesn't pass any arguments or look at the return value
+            let func = decl.qualifiers.func;
+            let signature_ok = ftype.takes_no_arguments() && *ftype.return_type == Type::Void;
+            for &(attribute, priority) in &[
+                ("constructor", func.constructor),
+                ("destructor", func.destructor),
+            ] {
+                if priority.is_some() && !signature_ok {
+                    self.warn(Warning::ConstructorSignature(attribute, decl.id), location);
+                }
+            }
+            // `void f(void) __attribute__((constructor)); void f(void) {}` still runs `f` before `main`
+            if let Some(existing) = self.scope.get(&decl.id) {
+                let existing = existing.get();
+                if existing.ctype.is_function() {
+                    let func = &mut decl.qualifiers.func;
+                    func.constructor = func.constructor.or(existing.qualifiers.func.constructor);
+                    func.destructor = func.destructor.or(existing.qualifiers.func.destructor);
                 }
             }
         }
@@ -1064,11 +1547,25 @@ impl PureAnalyzer {
             self.warn(Warning::ExtraneousExtern, location);
             decl.storage_class = StorageClass::Auto;
         }
+        // `extern int i;` and `int f();` in a block refer to the same object as any global declaration
+        if decl.storage_class != StorageClass::Extern && !decl.ctype.is_function() {
+            self.warn_shadow(decl.id, None, location);
+        }
+        if let Type::Function(ftype) = &decl.ctype {
+            if ftype.has_prototype() {
+                self.check_unprototyped_calls(&decl, ftype, init);
+            }
+        }
         let id = decl.id;
         let symbol = decl.insert();
         if let Some(existing_ref) = self.scope.insert(id, symbol) {
+            // `int f(void) __attribute__((deprecated)); int f(void);` is still deprecated
+            if let Some(deprecated) = self.deprecated.get(&existing_ref).cloned() {
+                self.deprecated.insert(symbol, deprecated);
+            }
             let existing = existing_ref.get();
             let meta = symbol.get();
+            let previous = Some(existing.location);
             // 6.2.2p4
             // > For an identifier declared with the storage-class specifier extern in a scope in which a prior declaration of that identifier is visible,
             // > if the prior declaration specifies internal or external linkage,
@@ -1079,7 +1576,7 @@ impl PureAnalyzer {
             // special case redefining the same type
             if self.scope.is_global()
                 // int i; int i;
-                && (existing == meta
+                && (existing.same_declaration(&meta)
                     // `static int i; extern int i;` or `int i; extern int i;`
                     || ((existing.storage_class == StorageClass::Static
                         || existing.storage_class == StorageClass::Auto)
@@ -1097,11 +1594,26 @@ impl PureAnalyzer {
             {
                 // int i = 1; int i = 2;
                 if init && self.initialized.contains(&existing_ref) {
-                    self.err(SemanticError::Redefinition(id), location);
+                    self.err(SemanticError::Redefinition(id, previous), location);
+                }
+            } else if existing.ctype == meta.ctype && existing.qualifiers == meta.qualifiers {
+                // 6.7p3: only declarations with linkage and typedefs of the same type can be repeated in a block
+                // `{ extern int i; extern int i; }` and `{ typedef int I; typedef int I; }`
+                let has_linkage = |storage_class: StorageClass, ctype: &Type| {
+                    storage_class == StorageClass::Extern || ctype.is_function()
+                };
+                let both_linked = has_linkage(existing.storage_class, &existing.ctype)
+                    && has_linkage(meta.storage_class, &meta.ctype);
+                let both_typedefs = existing.storage_class == StorageClass::Typedef
+                    && meta.storage_class == StorageClass::Typedef;
+                // { int i; int i; }
+                if !both_linked && !both_typedefs {
+                    self.err(SemanticError::Redefinition(id, previous), location);
                 }
             } else {
                 // extern int i; static int i;
-                let err = SemanticError::IncompatibleRedeclaration(id, existing_ref, symbol);
+                let err =
+                    SemanticError::IncompatibleRedeclaration(id, existing_ref, symbol, previous);
                 self.err(err, location);
             }
         }
@@ -1109,36 +1621,270 @@ impl PureAnalyzer {
     }
 }
 
-impl types::FunctionType {
-    // check if this is a valid signature for 'main'
-    fn is_main_func_signature(&self) -> bool {
-        // main must return 'int' and must not be variadic
-        if *self.return_type != Type::Int(true) || self.varargs {
-            return false;
+impl<'hir> PureAnalyzer<'hir> {
+    /// Warn if declaring `id` in the current scope would hide a declaration from an enclosing scope (`-Wshadow`).
+    ///
+    /// `except` is a declaration that it's fine to shadow,
+    /// such as a function whose parameter has the same name as the function.
+    fn warn_shadow(&mut self, id: InternedStr, except: Option<Symbol>, location: Location) {
+        // redeclarations in the same scope are checked by `declare`
+        if self.scope.is_global()
+            || id == InternedStr::default()
+            || self.scope.get_immediate(&id).is_some()
+        {
+            return;
         }
-        // allow 'main()''
-        if self.params.is_empty() {
-            return true;
+        let shadowed = match self.scope.get(&id) {
+            Some(&shadowed) if Some(shadowed) != except => shadowed,
+            _ => return,
+        };
+        let meta = shadowed.get();
+        let kind = if meta.storage_class == StorageClass::Typedef {
+            Shadowed::Typedef
+        } else if self.enumerators.contains_key(&shadowed) {
+            Shadowed::EnumConstant
+        } else if self.current_params.contains(&shadowed) {
+            Shadowed::Parameter
+        } else if self.scope.get_global(&id) == Some(&shadowed) {
+            Shadowed::Global
+        } else {
+            Shadowed::Local
+        };
+        let previous = Some(meta.location);
+        self.warn(Warning::Shadow(id, kind, previous), location);
+    }
+    /// Remember the attributes of `symbol` that are checked when it's used.
+    ///
+    /// A redeclaration without the attributes doesn't remove them.
+    fn record_attributes(
+        &mut self,
+        symbol: Symbol,
+        attributes: DeclAttributes,
+        location: Location,
+    ) {
+        if attributes.maybe_unused {
+            self.maybe_unused.insert(symbol);
         }
-        // so the borrow-checker doesn't complain
-        let meta: Vec<_> = self.params.iter().map(|param| param.get()).collect();
+        if let Some(message) = attributes.deprecated {
+            self.deprecated.insert(symbol, (message, location));
+        }
+    }
+    /// Check `__attribute__((weak))` and `__attribute__((alias))` on `symbol`,
+    /// and remember what it defines so the targets of aliases can be checked later.
+    fn check_link_attributes(
+        &mut self,
+        symbol: Symbol,
+        attributes: &LinkAttributes,
+        init: bool,
+        location: Location,
+    ) {
+        let meta = symbol.get();
+        let (sc, is_function) = (meta.storage_class, meta.ctype.is_function());
+        // `{ int i __attribute__((weak)); }`
+        let has_linkage = sc != StorageClass::Typedef
+            && (self.scope.is_global() || sc == StorageClass::Extern || is_function);
+        // `static int i __attribute__((weak));` can't be overridden by anything
+        if attributes.weak && (!has_linkage || sc == StorageClass::Static) {
+            self.err(
+                SemanticError::AttributeNeedsLinkage("weak", meta.id),
+                location,
+            );
+        }
+        // `int i;` is a tentative definition, but `extern int i;` and `int f();` aren't definitions
+        let is_definition =
+            init || (self.scope.is_global() && !is_function && sc != StorageClass::Extern);
+        if let Some(target) = attributes.alias {
+            if !has_linkage {
+                self.err(
+                    SemanticError::AttributeNeedsLinkage("alias", meta.id),
+                    location,
+                );
+            // `int i __attribute__((alias("j"))) = 1;` or `int i; int i __attribute__((alias("j")));`
+            } else if init || self.defined.contains(&meta.id) {
+                self.err(SemanticError::AliasRedefinition(meta.id), location);
+            } else {
+                self.aliases.push((meta.id, target, location));
+            }
+        } else if is_definition && has_linkage && self.scope.is_global() {
+            // `int i __attribute__((alias("j"))); int i = 1;`
+            if self.aliases.iter().any(|&(alias, ..)| alias == meta.id) {
+                self.err(SemanticError::AliasRedefinition(meta.id), location);
+            }
+            self.defined.insert(meta.id);
+        }
+    }
+    /// Check that every alias refers to something defined in this translation unit.
+    ///
+    /// This can only be done once the whole translation unit has been seen,
+    /// since the target can be defined after the alias.
+    fn check_aliases(&mut self) {
+        for (alias, target, location) in std::mem::take(&mut self.aliases) {
+            if !self.defined.contains(&target) {
+                self.err(SemanticError::UndefinedAliasTarget(alias, target), location);
+            }
+        }
+    }
+    /// `-Wdeprecated-declarations`: warn about a use of `symbol` if it was marked deprecated.
+    ///
+    /// Like in GCC, uses inside a deprecated function (including its own definition) aren't warned about.
+    fn warn_deprecated(&mut self, symbol: Symbol, location: Location) {
+        let (message, declared) = match self.deprecated.get(&symbol) {
+            Some(deprecated) => deprecated.clone(),
+            None => return,
+        };
+        let in_deprecated_function = self
+            .current_function
+            .and_then(|name| self.scope.get_global(&name))
+            .is_some_and(|function| self.deprecated.contains_key(function));
+        if !in_deprecated_function {
+            let warning = Warning::Deprecated(symbol.get().id, message, declared);
+            self.warn(warning, location);
+        }
+    }
+    /// `-Wunprototyped-call` and `-Wunprototyped-call-type`: check earlier calls to `decl` against its prototype, `ftype`.
+    ///
+    /// Only calls made before the prototype was seen are checked; later calls are errors instead.
+    fn check_unprototyped_calls(
+        &mut self,
+        decl: &Variable,
+        ftype: &types::FunctionType,
+        defined: bool,
+    ) {
+        let calls = match self.unprototyped_calls.remove(&decl.id) {
+            Some(calls) => calls,
+            None => return,
+        };
+        let params: Vec<_> = match ftype.params.as_slice() {
+            // int f(void)
+            [param] if param.get().ctype == Type::Void => Vec::new(),
+            params => params
+                .iter()
+                .map(|param| param.get().ctype.clone())
+                .collect(),
+        };
+        for call in calls {
+            let actual = call.data.len();
+            if actual < params.len() || actual > params.len() && !ftype.varargs {
+                let warning = Warning::UnprototypedArgumentNumber {
+                    name: decl.id,
+                    actual,
+                    expected: params.len(),
+                    defined,
+                    declaration: decl.location,
+                };
+                self.warn(warning, call.location);
+                continue;
+            }
+            for (i, (arg, param)) in call.data.into_iter().zip(&params).enumerate() {
+                if !arg.is_promoted_compatible(param) {
+                    let warning = Warning::UnprototypedArgumentType {
+                        name: decl.id,
+                        position: i + 1,
+                        actual: arg,
+                        expected: param.clone(),
+                        defined,
+                        declaration: decl.location,
+                    };
+                    self.warn(warning, call.location);
+                }
+            }
+        }
+    }
+    /// Whether `decl` is the `main` function of the program (5.1.2.2.1).
+    ///
+    /// `static int main()` or a local variable named `main` are not special,
+    /// and neither is anything in a freestanding environment.
+    fn is_main(&self, decl: &Variable) -> bool {
+        !self.freestanding
+            && decl.id == "main".into()
+            && self.scope.is_global()
+            && decl.storage_class != StorageClass::Static
+    }
+}
+
+impl types::FunctionType {
+    /// Whether the parameters were declared, i.e. this isn't `int f()`.
+    ///
+    /// Functions without a prototype can be called with any arguments.
+    pub(crate) fn has_prototype(&self) -> bool {
+        !self.params.is_empty() || self.varargs
+    }
+    /// Whether this is `void f(void)` or `void f()`, where it's safe to call `f` with no arguments.
+    fn takes_no_arguments(&self) -> bool {
+        match self.params.as_slice() {
+            [] => !self.varargs,
+            [param] => param.get().ctype == Type::Void,
+            _ => false,
+        }
+    }
+    // check if this is a valid signature for 'main'
+    fn is_main_func_signature(&self) -> bool {
+        // main must return 'int' and must not be variadic
+        if *self.return_type != Type::Int(true) || self.varargs {
+            return false;
+        }
+        // allow 'main()''
+        if self.params.is_empty() {
+            return true;
+        }
+        // so the borrow-checker doesn't complain
+        let meta: Vec<_> = self.params.iter().map(|param| param.get()).collect();
         let types: Vec<_> = meta.iter().map(|param| &param.ctype).collect();
         match types.as_slice() {
             // allow 'main(void)'
             [Type::Void] => true,
-            // TODO: allow 'int main(int argc, char *argv[], char *environ[])'
-            [Type::Int(true), Type::Pointer(t, _)] | [Type::Int(true), Type::Array(t, _)] => {
-                match &**t {
-                    Type::Pointer(inner, _) => inner.is_char(),
-                    _ => false,
-                }
+            [Type::Int(true), argv] => argv.is_char_pointer_array(),
+            // 'int main(int argc, char *argv[], char *envp[])' is a common extension (J.5.1)
+            [Type::Int(true), argv, envp] => {
+                argv.is_char_pointer_array() && envp.is_char_pointer_array()
             }
             _ => false,
         }
     }
 }
 
+impl Variable {
+    /// The value of `A` in `enum e { A = 1 };`, if this is an enumeration constant.
+    ///
+    /// This searches all the members of the enum, so prefer `PureAnalyzer::enumerators` where possible.
+    fn enum_value(&self) -> Option<i64> {
+        match &self.ctype {
+            Type::Enum(_, members) if self.storage_class == StorageClass::Register => members
+                .iter()
+                .find(|&&(member, _)| member == self.id)
+                .map(|&(_, value)| value),
+            _ => None,
+        }
+    }
+    /// Whether `self` and `other` declare the same thing, possibly in different places.
+    fn same_declaration(&self, other: &Self) -> bool {
+        self.ctype == other.ctype
+            && self.storage_class == other.storage_class
+            && self.qualifiers == other.qualifiers
+            && self.id == other.id
+    }
+}
+
 impl Type {
+    /// Whether this can be `restrict`-qualified, i.e. it's a pointer to an object or incomplete type.
+    fn restricts_object(&self) -> bool {
+        match self {
+            Type::Pointer(to, _) => !to.is_function(),
+            // already reported
+            Type::Error => true,
+            _ => false,
+        }
+    }
+    /// `char **` or `char *[]`
+    fn is_char_pointer_array(&self) -> bool {
+        match self {
+            Type::Pointer(t, _) | Type::Array(t, _) => match &**t {
+                Type::Pointer(inner, _) => inner.is_char(),
+                _ => false,
+            },
+            _ => false,
+        }
+    }
     #[inline]
     fn is_char(&self) -> bool {
         match self {
@@ -1151,12 +1897,20 @@ impl Type {
 /// Analyze a single function
 ///
 /// This is separate from `Analyzer` so that `metadata` does not have to be an `Option`.
-struct FunctionAnalyzer<'a> {
+struct FunctionAnalyzer<'a, 'hir> {
     /// the function we are currently compiling.
     /// used for checking return types
     metadata: FunctionData,
     /// We need this for the scopes, as well as for parsing expressions
-    analyzer: &'a mut PureAnalyzer,
+    analyzer: &'a mut PureAnalyzer<'hir>,
+    /// the labels seen so far in each enclosing `switch`, innermost last
+    switches: Vec<stmt::SwitchLabels>,
+    /// the number of enclosing loops, for checking `break` and `continue`
+    loops: usize,
+    /// each label declared so far, and where
+    labels: HashMap<InternedStr, Location>,
+    /// each `goto` seen so far, checked against `labels` at the end of the function
+    gotos: Vec<Locatable<InternedStr>>,
 }
 
 #[derive(Debug)]
@@ -1169,23 +1923,37 @@ struct FunctionData {
     location: Location,
     /// the return type of the function
     return_type: Type,
+    /// the qualifiers of the innermost return type, like `const` in `const char *f(void)`
+    return_qualifiers: Qualifiers,
+    /// the parameters of the function, for `-Wunused-parameter`
+    params: Vec<Symbol>,
 }
 
-impl FunctionAnalyzer<'_> {
+impl<'hir> FunctionAnalyzer<'_, 'hir> {
     /// Performs semantic analysis on the function and adds it to `METADATA_STORE`.
-    /// Returns the analyzed statements.
+    /// Returns the analyzed statements, and the attributes that change how the function is linked.
     fn analyze(
         func: ast::FunctionDefinition,
-        analyzer: &mut PureAnalyzer,
+        analyzer: &mut PureAnalyzer<'hir>,
         location: Location,
-    ) -> (Symbol, Vec<Stmt>) {
+    ) -> (Symbol, Vec<Stmt<'hir>>, LinkAttributes) {
         let parsed_func = analyzer.parse_type(func.specifiers, func.declarator.into(), location);
         // saltwater ignores `inline` and `_Noreturn`
-        if parsed_func.qualifiers != Qualifiers::default() {
-            analyzer.error_handler.warn(
-                Warning::FunctionQualifiersIgnored(parsed_func.qualifiers),
-                location,
-            );
+        // `const char *f(void)` returns a pointer to `const`, which isn't ignored
+        let returns_pointer = match &parsed_func.ctype {
+            Type::Function(ftype) => ftype.return_type.is_pointer(),
+            _ => false,
+        };
+        // constructors and destructors aren't ignored either
+        let mut ignored = parsed_func.qualifiers;
+        ignored.func.constructor = None;
+        ignored.func.destructor = None;
+        if ignored != Qualifiers::default()
+            && !(returns_pointer && ignored.func == FunctionQualifiers::default())
+        {
+            analyzer
+                .error_handler
+                .warn(Warning::FunctionQualifiersIgnored(ignored), location);
         }
         let sc = match parsed_func.storage_class {
             None => StorageClass::Extern,
@@ -1201,8 +1969,14 @@ impl FunctionAnalyzer<'_> {
             id: func.id,
             qualifiers: parsed_func.qualifiers,
             storage_class: sc,
+            location,
         };
+        let is_main = analyzer.is_main(&metadata);
+        analyzer.check_prototype(&metadata, is_main, location);
         let symbol = analyzer.declare(metadata, true, location);
+        let link = parsed_func.attributes.link.clone();
+        analyzer.check_link_attributes(symbol, &link, true, location);
+        analyzer.record_attributes(symbol, parsed_func.attributes, location);
         let func_type = match parsed_func.ctype {
             Type::Function(ftype) => ftype,
             _ => unreachable!(),
@@ -1212,12 +1986,18 @@ impl FunctionAnalyzer<'_> {
             location,
             id: func.id,
             return_type: *func_type.return_type,
+            return_qualifiers: parsed_func.qualifiers,
+            params: func_type.params.clone(),
         };
         assert!(analyzer.scope.is_global());
         assert!(analyzer.tag_scope.is_global());
         let mut func_analyzer = FunctionAnalyzer {
             metadata: tmp_metadata,
             analyzer,
+            switches: Vec::new(),
+            loops: 0,
+            labels: HashMap::new(),
+            gotos: Vec::new(),
         };
         func_analyzer.enter_scope();
         for (i, param) in func_type.params.into_iter().enumerate() {
@@ -1229,24 +2009,58 @@ impl FunctionAnalyzer<'_> {
                     location,
                 );
             }
+            // `int f(int f)` is fine, since the function can't be referred to by name anyway
+            func_analyzer
+                .analyzer
+                .warn_shadow(meta.id, Some(symbol), location);
             // TODO: I think this should go through `declare` instead,
             // but that requires having a mutable `Metadata`
             func_analyzer.analyzer.scope.insert(meta.id, param);
         }
-        let stmts = func
-            .body
-            .into_iter()
-            .map(|s| func_analyzer.parse_stmt(s))
-            .collect();
+        func_analyzer.analyzer.current_function = Some(func.id);
+        func_analyzer.analyzer.current_params = func_analyzer.metadata.params.clone();
+        func_analyzer.check_declarations_first(&func.body);
+        let mut stmts = func_analyzer.block_items(func.body);
+        // `static const char __func__[] = "f";` comes before anything that uses it
+        if let Some(decl) = func_analyzer.analyzer.func_name.take() {
+            let location = decl.location;
+            stmts.insert(0, location.with(StmtType::Decl(vec![decl])));
+        }
+        func_analyzer.check_gotos();
+        func_analyzer.analyzer.current_function = None;
+        func_analyzer.analyzer.current_params.clear();
+        let end = location.span.end;
+        let closing_brace = Location {
+            span: (end.saturating_sub(1)..end).into(),
+            ..location
+        };
+        let return_type = &func_analyzer.metadata.return_type;
+        if *return_type != Type::Void && FunctionAnalyzer::falls_through(&stmts) {
+            if is_main {
+                // 5.1.2.2.3: "reaching the } that terminates the main function returns a value of 0"
+                if *return_type == Type::Int(true) {
+                    let zero = Expr::zero(closing_brace);
+                    stmts.push(closing_brace.with(StmtType::Return(Some(zero))));
+                }
+            } else {
+                // 6.9.1p12: "If the } that terminates a function is reached,
+                // and the value of the function call is used by the caller, the behavior is undefined."
+                func_analyzer
+                    .analyzer
+                    .warn(Warning::MissingReturn(func.id), closing_brace);
+            }
+        }
+        func_analyzer.analyzer.check_uninitialized(&stmts);
+        func_analyzer.analyzer.check_dead_stores(&stmts);
         // TODO: this location should be the end of the function, not the start
         func_analyzer.leave_scope(location);
         assert!(analyzer.tag_scope.is_global());
         assert!(analyzer.scope.is_global());
-        (symbol, stmts)
+        (symbol, stmts, link)
     }
 }
 
-impl FunctionAnalyzer<'_> {
+impl<'hir> FunctionAnalyzer<'_, 'hir> {
     fn err(&mut self, err: SemanticError, location: Location) {
         self.analyzer.err(err, location);
     }
@@ -1255,7 +2069,8 @@ impl FunctionAnalyzer<'_> {
         self.analyzer.tag_scope.enter();
     }
     fn leave_scope(&mut self, location: Location) {
-        for object in self.analyzer.scope.get_all_immediate().values() {
+        self.warn_unused_locals();
+        for (_, object) in self.analyzer.scope.immediate() {
             let object = object.get();
             match &object.ctype {
                 Type::Struct(StructType::Named(name, members))
@@ -1278,6 +2093,85 @@ impl FunctionAnalyzer<'_> {
         self.analyzer.scope.exit();
         self.analyzer.tag_scope.exit();
     }
+    /// Warn about variables in the current scope that were never used.
+    fn warn_unused_locals(&mut self) {
+        let symbols: Vec<_> = self
+            .analyzer
+            .scope
+            .immediate()
+            .map(|(_, &symbol)| symbol)
+            .collect();
+        let mut unused = Vec::new();
+        for symbol in symbols {
+            let meta = symbol.get();
+            if self.analyzer.read.contains(&symbol)
+                || !self.analyzer.may_warn_unused(symbol)
+                // `extern int i;` and `int f();` aren't definitions
+                || meta.storage_class == StorageClass::Extern
+                || meta.ctype.is_function()
+                || self.analyzer.enumerators.contains_key(&symbol)
+            {
+                continue;
+            }
+            let warning = if self.metadata.params.contains(&symbol) {
+                Warning::UnusedParameter(meta.id)
+            } else if self.analyzer.written.contains(&symbol) {
+                Warning::UnusedButSetVariable(meta.id)
+            } else {
+                Warning::UnusedVariable(meta.id)
+            };
+            unused.push(meta.location.with(warning));
+        }
+        // a name redeclared in the same scope keeps its first position, so sort by location
+        unused.sort_by_key(|warning| warning.location.span.start);
+        for warning in unused {
+            self.analyzer.warn(warning.data, warning.location);
+        }
+    }
+}
+
+impl<'hir> PureAnalyzer<'hir> {
+    /// Whether the unused warnings apply to `symbol` at all.
+    ///
+    /// Typedefs, parameters without names, and declarations marked `unused` are never warned about.
+    fn may_warn_unused(&self, symbol: Symbol) -> bool {
+        let meta = symbol.get();
+        let name = meta.id.resolve_and_clone();
+        !(meta.storage_class == StorageClass::Typedef
+            || meta.id == InternedStr::default()
+            || self.maybe_unused.contains(&symbol)
+            || (self.allow_unused_underscore && name.starts_with('_')))
+    }
+    /// Warn about static functions and variables that were never used in this translation unit.
+    ///
+    /// `static inline` functions are often defined in headers without being used,
+    /// so they are not warned about.
+    fn warn_unused_statics(&mut self) {
+        let statics = std::mem::take(&mut self.file_statics);
+        // `static int f(); int g() { return f(); } static int f() { return 1; }`
+        // uses `f`, even though the definition itself is never referenced
+        let mut used: HashSet<_> = statics
+            .iter()
+            .filter(|(symbol, _)| self.read.contains(symbol) || self.written.contains(symbol))
+            .map(|(symbol, _)| symbol.get().id)
+            .collect();
+        for (symbol, is_definition) in statics {
+            let meta = symbol.get();
+            if !is_definition
+                || used.contains(&meta.id)
+                || meta.qualifiers.func.inline
+                // called by the C runtime
+                || meta.qualifiers.func.constructor.is_some()
+                || meta.qualifiers.func.destructor.is_some()
+                || !self.may_warn_unused(symbol)
+            {
+                continue;
+            }
+            // only warn once for `static int i; static int i = 1;`
+            used.insert(meta.id);
+            self.warn(Warning::UnusedStatic(meta.id), meta.location);
+        }
+    }
 }
 
 struct ParsedType {
@@ -1287,10 +2181,199 @@ struct ParsedType {
     ctype: Type,
     // TODO: this is fishy
     declared_compound_type: bool,
+    /// `__attribute__((unused))` and `__attribute__((deprecated))`
+    attributes: DeclAttributes,
+}
+
+/// The attributes of a declaration which aren't part of its type.
+#[derive(Clone, Debug, Default)]
+struct DeclAttributes {
+    /// `__attribute__((unused))`
+    maybe_unused: bool,
+    /// `__attribute__((deprecated))`, with its message if it has one
+    deprecated: Option<Option<String>>,
+    /// `__attribute__((weak))` and `__attribute__((alias("target")))`
+    link: LinkAttributes,
+}
+
+impl DeclAttributes {
+    /// Combine the attributes on the declaration specifiers with the ones on a declarator.
+    fn merge(&mut self, other: DeclAttributes) {
+        self.maybe_unused |= other.maybe_unused;
+        if self.deprecated.is_none() {
+            self.deprecated = other.deprecated;
+        }
+        self.link.weak |= other.link.weak;
+        if self.link.alias.is_none() {
+            self.link.alias = other.link.alias;
+        }
+    }
 }
 
 use ast::{DeclarationSpecifier, UnitSpecifier};
 
+/// Functions which return twice, even if they aren't declared with `__attribute__((returns_twice))`
+const RETURNS_TWICE: &[&str] = &["setjmp", "_setjmp", "sigsetjmp", "__sigsetjmp"];
+
+/// Functions which never return, even if they aren't declared with `_Noreturn`
+const NO_RETURN: &[&str] = &[
+    "exit",
+    "_Exit",
+    "_exit",
+    "quick_exit",
+    "abort",
+    "longjmp",
+    "_longjmp",
+    "siglongjmp",
+    "__assert_fail",
+    BUILTIN_UNREACHABLE,
+];
+
+/// `__builtin_unreachable()`, which is declared the first time it's called and compiled to a trap
+pub(crate) const BUILTIN_UNREACHABLE: &str = "__builtin_unreachable";
+
+/// Functions which take a format string, even if they aren't declared with `__attribute__((format))`.
+///
+/// Each entry is the name, the kind of format, the format parameter, and the first variadic argument.
+/// glibc renames the `scanf` family to `__isoc99_*` in C99 mode.
+#[rustfmt::skip]
+const FORMAT_FUNCTIONS: &[(&str, FormatKind, usize, usize)] = &[
+    ("printf", FormatKind::Printf, 1, 2),
+    ("fprintf", FormatKind::Printf, 2, 3),
+    ("dprintf", FormatKind::Printf, 2, 3),
+    ("sprintf", FormatKind::Printf, 2, 3),
+    ("snprintf", FormatKind::Printf, 3, 4),
+    ("vprintf", FormatKind::Printf, 1, 0),
+    ("vfprintf", FormatKind::Printf, 2, 0),
+    ("vdprintf", FormatKind::Printf, 2, 0),
+    ("vsprintf", FormatKind::Printf, 2, 0),
+    ("vsnprintf", FormatKind::Printf, 3, 0),
+    ("scanf", FormatKind::Scanf, 1, 2),
+    ("fscanf", FormatKind::Scanf, 2, 3),
+    ("sscanf", FormatKind::Scanf, 2, 3),
+    ("vscanf", FormatKind::Scanf, 1, 0),
+    ("vfscanf", FormatKind::Scanf, 2, 0),
+    ("vsscanf", FormatKind::Scanf, 2, 0),
+    ("__isoc99_scanf", FormatKind::Scanf, 1, 2),
+    ("__isoc99_fscanf", FormatKind::Scanf, 2, 3),
+    ("__isoc99_sscanf", FormatKind::Scanf, 2, 3),
+    ("__isoc99_vscanf", FormatKind::Scanf, 1, 0),
+    ("__isoc99_vfscanf", FormatKind::Scanf, 2, 0),
+    ("__isoc99_vsscanf", FormatKind::Scanf, 2, 0),
+];
+
+/// Apply GNU `__attribute__`s to a declaration.
+///
+/// Attributes saltwater doesn't know about are ignored.
+/// Returns the attributes which apply to the declaration rather than its type.
+fn apply_attributes(
+    attributes: Vec<ast::Attribute>,
+    func: &mut FunctionQualifiers,
+) -> DeclAttributes {
+    let mut parsed = DeclAttributes::default();
+    for attribute in attributes {
+        // `__noreturn__` is the same as `noreturn`
+        let name = attribute.name.resolve_and_clone();
+        match name.trim_start_matches("__").trim_end_matches("__") {
+            "noreturn" => func.no_return = true,
+            "returns_twice" => func.returns_twice = true,
+            // `[[maybe_unused]]` is the C2x spelling
+            "unused" | "maybe_unused" => parsed.maybe_unused = true,
+            // `deprecated` or `deprecated("use g instead")`
+            "deprecated" => {
+                let message = match attribute.args.as_slice() {
+                    [ast::Expr {
+                        data: ast::ExprType::Literal(lex::Literal::Str(message)),
+                        ..
+                    }] => {
+                        let message = String::from_utf8_lossy(message);
+                        Some(message.trim_end_matches('\0').to_owned())
+                    }
+                    _ => None,
+                };
+                parsed.deprecated = Some(message);
+            }
+            "format" => {
+                if let Some(format) = format_attribute(&attribute.args) {
+                    func.format = Some(format);
+                }
+            }
+            // `constructor` or `constructor(101)`
+            "constructor" => func.constructor = Some(init_priority(&attribute.args)),
+            "destructor" => func.destructor = Some(init_priority(&attribute.args)),
+            "weak" => parsed.link.weak = true,
+            // `alias("target")`
+            "alias" => {
+                if let [ast::Expr {
+                    data: ast::ExprType::Literal(lex::Literal::Str(target)),
+                    ..
+                }] = attribute.args.as_slice()
+                {
+                    let target = String::from_utf8_lossy(target);
+                    parsed.link.alias = Some(target.trim_end_matches('\0').into());
+                }
+            }
+            _ => {}
+        }
+    }
+    parsed
+}
+
+/// The priority given to `__attribute__((constructor(priority)))` or `__attribute__((destructor(priority)))`.
+///
+/// Returns the default priority if there isn't one, or if it doesn't fit in a `u16`.
+fn init_priority(args: &[ast::Expr]) -> u16 {
+    use ast::ExprType;
+    use lex::Literal;
+
+    let priority = match args {
+        [ast::Expr {
+            data: ExprType::Literal(Literal::Int(i)),
+            ..
+        }] => (*i).try_into().ok(),
+        [ast::Expr {
+            data: ExprType::Literal(Literal::UnsignedInt(i)),
+            ..
+        }] => (*i).try_into().ok(),
+        _ => None,
+    };
+    priority.unwrap_or(DEFAULT_INIT_PRIORITY)
+}
+
+/// Parse the arguments of `__attribute__((format(printf, 1, 2)))`.
+///
+/// Returns `None` for format kinds other than `printf` and `scanf`, or if the arguments are malformed.
+fn format_attribute(args: &[ast::Expr]) -> Option<FormatAttribute> {
+    use ast::ExprType;
+    use lex::Literal;
+
+    let index = |arg: &ast::Expr| match arg.data {
+        ExprType::Literal(Literal::Int(i)) => i.try_into().ok(),
+        ExprType::Literal(Literal::UnsignedInt(i)) => i.try_into().ok(),
+        _ => None,
+    };
+    let (kind, format_index, first_arg) = match args {
+        [kind, format_index, first_arg] => (kind, index(format_index)?, index(first_arg)?),
+        _ => return None,
+    };
+    let kind = match &kind.data {
+        ExprType::Id(name) => match name.resolve_and_clone().trim_matches('_') {
+            "printf" | "gnu_printf" => FormatKind::Printf,
+            "scanf" | "gnu_scanf" => FormatKind::Scanf,
+            _ => return None,
+        },
+        _ => return None,
+    };
+    if format_index == 0 || (first_arg != 0 && first_arg <= format_index) {
+        return None;
+    }
+    Some(FormatAttribute {
+        kind,
+        format_index,
+        first_arg,
+    })
+}
+
 fn count_specifiers(
     specifiers: Vec<DeclarationSpecifier>,
     error_handler: &mut ErrorHandler,
@@ -1350,6 +2433,11 @@ pub(crate) mod test {
     use crate::lex::PreProcessor;
     use crate::parse::test::*;
 
+    /// An arena that lives for the rest of the test, so the HIR can be returned from helpers.
+    pub(crate) fn arena() -> &'static Arena<'static> {
+        Box::leak(Box::new(Arena::new()))
+    }
+
     pub(crate) fn analyze<'c, 'input: 'c, P, A, R, S, E>(
         input: &'input str,
         parse_func: P,
@@ -1357,12 +2445,12 @@ pub(crate) mod test {
     ) -> CompileResult<R>
     where
         P: Fn(&mut Parser<PreProcessor<'c>>) -> Result<S, E>,
-        A: Fn(&mut PureAnalyzer, S) -> R,
+        A: Fn(&mut PureAnalyzer<'static>, S) -> R,
         CompileError: From<E>,
     {
         let mut p = parser(input);
         let ast = parse_func(&mut p)?;
-        let mut a = PureAnalyzer::new();
+        let mut a = PureAnalyzer::new(arena());
         let e = analyze_func(&mut a, ast);
         if let Some(err) = a.error_handler.pop_front() {
             return Err(err);
@@ -1370,22 +2458,22 @@ pub(crate) mod test {
         Ok(e)
     }
 
-    fn maybe_decl(s: &str) -> Option<CompileResult<Declaration>> {
+    fn maybe_decl(s: &str) -> Option<CompileResult<Declaration<'static>>> {
         decls(s).into_iter().next()
     }
 
-    pub(crate) fn decl(s: &str) -> CompileResult<Declaration> {
+    pub(crate) fn decl(s: &str) -> CompileResult<Declaration<'static>> {
         maybe_decl(s).unwrap_or_else(|| panic!("expected a declaration or error: '{}'", s))
     }
 
-    pub(crate) fn decls(s: &str) -> Vec<CompileResult<Declaration>> {
-        Analyzer::new(parser(s), false)
+    pub(crate) fn decls(s: &str) -> Vec<CompileResult<Declaration<'static>>> {
+        Analyzer::new(parser(s), arena(), false)
             .map(|o| o.map(|l| l.data))
             .collect()
     }
 
     pub(crate) fn assert_errs_decls(input: &str, errs: usize, warnings: usize, decls: usize) {
-        let mut a = Analyzer::new(parser(input), false);
+        let mut a = Analyzer::new(parser(input), arena(), false);
         let (mut a_errs, mut a_decls) = (0, 0);
         for res in &mut a {
             if res.is_err() {
@@ -1408,7 +2496,7 @@ pub(crate) mod test {
         };
     }
 
-    pub(crate) fn analyze_expr(s: &str) -> CompileResult<Expr> {
+    pub(crate) fn analyze_expr(s: &str) -> CompileResult<Expr<'static>> {
         analyze(s, Parser::expr, PureAnalyzer::expr)
     }
 
@@ -1430,7 +2518,7 @@ pub(crate) mod test {
         assert_decl_display(s, s);
     }
 
-    fn match_type(lexed: CompileResult<Declaration>, given_type: Type) -> bool {
+    fn match_type(lexed: CompileResult<Declaration<'static>>, given_type: Type) -> bool {
         fn type_helper(ctype: &Type, given_type: &Type) -> bool {
             match (ctype, given_type) {
                 // because the parameters use `MetadataRef`,
@@ -1488,6 +2576,21 @@ pub(crate) mod test {
             Error::Semantic(SemanticError::AutoAtGlobalScope) => {}
             _ => panic!("wrong error"),
         }
+        match decl("register int i;").unwrap_err().data {
+            Error::Semantic(SemanticError::RegisterAtGlobalScope) => {}
+            _ => panic!("wrong error"),
+        }
+        let f = decl("int f(register int i);").unwrap().symbol.get();
+        match &f.ctype {
+            Type::Function(func) => {
+                assert_eq!(func.params[0].get().storage_class, StorageClass::Register)
+            }
+            _ => panic!("not a function"),
+        }
+        match decl("int f(static int i);").unwrap_err().data {
+            Error::Semantic(SemanticError::ParameterStorageClass(StorageClass::Static)) => {}
+            _ => panic!("wrong error"),
+        }
     }
 
     #[test]
@@ -1580,6 +2683,17 @@ pub(crate) mod test {
         assert_same("int a[(int)1];", "int a[1];");
     }
     #[test]
+    fn test_local_array_bounds() {
+        // the bound of a local array comes from its initializer, the same as at file scope
+        let mut analyzer = PureAnalyzer::new(arena());
+        analyzer.parse_stmt_str("int a[] = {1, 2, 3};").unwrap();
+        analyzer.parse_stmt_str("char s[] = \"abc\";").unwrap();
+        let a = analyzer.parse_expr_str("a").unwrap();
+        assert_eq!(a.ctype, Array(Box::new(Int(true)), ArrayType::Fixed(3)));
+        let s = analyzer.parse_expr_str("s").unwrap();
+        assert_eq!(s.ctype, Array(Box::new(Char(true)), ArrayType::Fixed(4)));
+    }
+    #[test]
     fn test_pointers() {
         for &pointer in &[
             "void *a;",
@@ -1658,6 +2772,7 @@ pub(crate) mod test {
                         ),
                         qualifiers: Default::default(),
                         storage_class: Default::default(),
+                        location: Location::default(),
                     }
                     .insert()],
                     varargs: false,
@@ -1673,7 +2788,8 @@ pub(crate) mod test {
                     id: Default::default(),
                     ctype: Int(true),
                     qualifiers: Default::default(),
-                    storage_class: Default::default()
+                    storage_class: Default::default(),
+                    location: Location::default(),
                 }
                 .insert()],
                 varargs: true,
@@ -1691,6 +2807,7 @@ pub(crate) mod test {
                     ctype: Pointer(Box::new(Int(true)), Qualifiers::default()),
                     qualifiers: Default::default(),
                     storage_class: Default::default(),
+                    location: Location::default(),
                 }
                 .insert()],
                 varargs: false
@@ -1700,6 +2817,719 @@ pub(crate) mod test {
         assert!(decl("int b[static 10];").is_err());
     }
     #[test]
+    fn test_restrict() {
+        assert_no_change("int *restrict p;");
+        assert_no_change("int *const restrict *volatile restrict p;");
+        assert_decl_display("int *__restrict__ p;", "int *restrict p;");
+        assert_decl_display("int (*restrict p)[3];", "int (*restrict p)[3];");
+        let typedef = decls("typedef int *P; restrict P p;");
+        assert_eq!(typedef[1].as_ref().unwrap().to_string(), "int *restrict p;");
+        // array parameters are adjusted to qualified pointers
+        assert_decl_display(
+            "void f(int a[restrict], int b[static restrict 4], int c[const volatile]);",
+            "void f(int *restrict a, int *restrict b, int *const volatile c);",
+        );
+        let restrict_error = |input| match decl(input).unwrap_err().data {
+            Error::Semantic(SemanticError::InvalidRestrict(ctype)) => ctype.to_string(),
+            other => panic!("expected invalid restrict for {}, got {}", input, other),
+        };
+        assert_eq!(restrict_error("restrict int i;"), "int");
+        assert_eq!(restrict_error("restrict int *p;"), "int");
+        assert!(restrict_error("int (*restrict f)(void);").starts_with("int (*restrict"));
+        assert_eq!(
+            decl("int a[restrict 5];").unwrap_err().data,
+            SemanticError::QualifiedArray.into()
+        );
+        assert_eq!(
+            decl("void f(int a[][restrict 5]);").unwrap_err().data,
+            SemanticError::QualifiedArray.into()
+        );
+        // `restrict` on a parameter doesn't change the type of the function
+        for compatible in &[
+            "void f(int *restrict p); void f(int *p) {}",
+            "void f(int *p); void f(int *restrict p) {}",
+            "void f(int p[restrict]); void f(int *p);",
+        ] {
+            for result in decls(compatible) {
+                assert!(result.is_ok(), "{}: {:?}", compatible, result);
+            }
+        }
+        assert!(decls("void f(int *restrict *p); void f(int **p);")
+            .into_iter()
+            .any(|result| result.is_err()));
+    }
+    #[test]
+    fn test_array_parameters() {
+        assert_decl_display("void f(int a[5]);", "void f(int *a);");
+        assert_decl_display("void f(const int a[]);", "void f(const int *a);");
+        assert_decl_display("void f(int a[][3]);", "void f(int (*a)[3]);");
+        // the size doesn't have to be a constant, since it's adjusted away
+        assert_decl_display("void f(int n, int a[n]);", "void f(int n, int *a);");
+        assert_decl_display(
+            "void f(int n, int a[n * 2][4]);",
+            "void f(int n, int (*a)[4]);",
+        );
+        // but it still has to be a valid length
+        assert!(decl("void f(int a[-1]);").is_err());
+        assert!(decl("void f(int a[m]);").is_err());
+        for compatible in &[
+            "void f(int a[5]); void f(int *a);",
+            "void f(int a[5]); void f(int a[]) {}",
+            "void f(int n, int a[n]); void f(int n, int a[3]);",
+            "void f(const int a[]); void f(const int *a);",
+        ] {
+            for result in decls(compatible) {
+                assert!(result.is_ok(), "{}: {:?}", compatible, result);
+            }
+        }
+        assert!(decls("void f(const int a[]); void f(int *a);")
+            .into_iter()
+            .any(|result| result.is_err()));
+
+        let sizeof_warnings = |input| {
+            let mut a = Analyzer::new(parser(input), arena(), false);
+            for res in &mut a {
+                res.unwrap();
+            }
+            a.inner
+                .error_handler
+                .warnings
+                .into_iter()
+                .map(|w| w.data)
+                .filter(|w| w.name() == "sizeof-array-argument")
+                .map(|w| w.to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            sizeof_warnings("unsigned long f(int a[10]) { return sizeof(a); }"),
+            vec!["sizeof on array function parameter will return size of 'int *' instead of 'int [10]'"]
+        );
+        assert_eq!(
+            sizeof_warnings("int f(int n, int a[n]) { return sizeof a; }").len(),
+            1
+        );
+        // the elements, or a pointer parameter, are fine
+        assert!(sizeof_warnings("int f(int a[10]) { return sizeof a[0]; }").is_empty());
+        assert!(sizeof_warnings("int f(int *a) { return sizeof a; }").is_empty());
+        assert!(sizeof_warnings("int f(int a[10]) { int b[10]; return sizeof b; }").is_empty());
+    }
+    #[test]
+    fn test_unprototyped_calls() {
+        let warnings = |input| {
+            let mut a = Analyzer::new(parser(input), arena(), false);
+            let errors = (&mut a).filter(|res| res.is_err()).count();
+            let warnings = a
+                .inner
+                .error_handler
+                .warnings
+                .into_iter()
+                .filter(|w| w.data.name().starts_with("unprototyped-call"))
+                .collect::<Vec<_>>();
+            (errors, warnings)
+        };
+        let program = "int f();
+            int g(void) { return f(1, 2, 3); }
+            int f(int a) { return a; }";
+        let (errors, mismatches) = warnings(program);
+        assert_eq!(errors, 0);
+        assert_eq!(mismatches.len(), 1);
+        let mismatch = &mismatches[0];
+        assert_eq!(
+            mismatch.data.to_string(),
+            "call to 'f' with 3 arguments, but it is defined taking 1"
+        );
+        let call = program.find("f(1").unwrap() as u32;
+        assert_eq!(mismatch.location.span.start, call);
+        let notes = mismatch.data.notes();
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0].data, "'f' is defined here");
+        let definition = program.rfind("int f").unwrap() as u32;
+        assert_eq!(notes[0].location.span.start, definition);
+
+        // a later prototype is checked too, including the types of the arguments
+        let (_, mismatches) = warnings("int f(); int g(void) { return f(1.5); } int f(int);");
+        assert_eq!(
+            mismatches[0].data.to_string(),
+            "argument 1 in call to 'f' has type 'double', but it is declared taking 'int'"
+        );
+        // calls that match the definition are fine
+        for matching in &[
+            "int f(); int g(void) { return f(1) + f('a'); } int f(int a) { return a; }",
+            "int f(); int g(char *p) { return f(p, 2.0f); } int f(void *p, double d);",
+            "int f(); int g(void) { return f(); } int f(void) { return 0; }",
+            "int f(); int g(void) { return f(1, 2); } int f(int a, ...);",
+        ] {
+            assert_eq!(warnings(matching), (0, vec![]), "{}", matching);
+        }
+        // calls after the definition are checked like any other call
+        let (errors, mismatches) =
+            warnings("int f(); int f(int a) { return a; } int g(void) { return f(1, 2); }");
+        assert_eq!((errors, mismatches.len()), (1, 0));
+    }
+    #[test]
+    fn test_main_signature() {
+        let warnings = |input| {
+            let mut a = Analyzer::new(parser(input), arena(), false);
+            for res in &mut a {
+                res.unwrap();
+            }
+            a.inner
+                .error_handler
+                .warnings
+                .into_iter()
+                .map(|w| w.data)
+                // `argc` and `argv` are unused, but that's not what's being tested
+                .filter(|w| w.name() == "main")
+                .collect::<Vec<_>>()
+        };
+        for valid in &[
+            "int main(void) { return 0; }",
+            "int main() { return 0; }",
+            "int main(int argc, char **argv) { return 0; }",
+            "int main(int argc, char *argv[], char *envp[]) { return 0; }",
+            // not the program's `main`
+            "static int main(int x) { return x; }",
+            "int f(void) { int main(int); return 0; }",
+        ] {
+            assert_eq!(warnings(valid), vec![], "{}", valid);
+        }
+        for invalid in &[
+            "void main() {}",
+            "int main(int);",
+            "int main(char **);",
+            "int main(int, char **, ...);",
+            "long main(void);",
+        ] {
+            assert_eq!(
+                warnings(invalid),
+                vec![Warning::NonStandardMainSignature],
+                "{}",
+                invalid
+            );
+        }
+    }
+    #[test]
+    fn test_unused() {
+        let warnings = |input, allow_underscore| {
+            let mut a = Analyzer::new(parser(input), arena(), false)
+                .allow_unused_underscore(allow_underscore);
+            for res in &mut a {
+                res.unwrap();
+            }
+            a.inner
+                .error_handler
+                .warnings
+                .into_iter()
+                .map(|w| w.data)
+                // `inline` is ignored, but that's not what's being tested
+                .filter(|w| w.name().starts_with("unused"))
+                .collect::<Vec<_>>()
+        };
+        let unused = |input| warnings(input, false);
+        let x = || InternedStr::get_or_intern("x");
+        assert_eq!(
+            unused("int f(void) { int x; return 0; }"),
+            vec![Warning::UnusedVariable(x())]
+        );
+        assert_eq!(
+            unused("int f(void) { int x = 1; x = 2; return 0; }"),
+            vec![Warning::UnusedButSetVariable(x())]
+        );
+        assert_eq!(
+            unused("int f(int x) { return 0; }"),
+            vec![Warning::UnusedParameter(x())]
+        );
+        assert_eq!(
+            unused("static int x(void) { return 0; }"),
+            vec![Warning::UnusedStatic(x())]
+        );
+        assert_eq!(unused("static int x;"), vec![Warning::UnusedStatic(x())]);
+        for used in &[
+            "int f(void) { int x; (void)x; return 0; }",
+            "int f(int x) { (void)x; return 0; }",
+            "int f(void) { int x = 1; x += 1; return x; }",
+            "int f(void) { int x; return sizeof(x); }",
+            "int f(void) { __attribute__((unused)) int x; return 0; }",
+            "int f(void) { int x __attribute__((unused)); return 0; }",
+            "int f(int x __attribute__((unused))) { return 0; }",
+            "int f(int) ;",
+            "int f(void) { typedef int x; enum { A }; extern int y; int g(void); return 0; }",
+            // called from `g`, even though `g` itself is never called
+            "static int f(void) { return 0; } static int g(void) { return f(); } int h(void) { return g(); }",
+            // declared before it's used, defined after
+            "static int f(void); int g(void) { return f(); } static int f(void) { return 0; }",
+            "static inline int f(void) { return 0; }",
+            "static int x; int f(void) { x = 1; return 0; }",
+        ] {
+            assert_eq!(unused(used), vec![], "{}", used);
+        }
+        let static_f =
+            unused("static int f(void) { return 0; } static int g(void) { return f(); }");
+        assert_eq!(static_f, vec![Warning::UnusedStatic("g".into())]);
+        assert_eq!(
+            warnings("int f(int _x) { int _y; return 0; }", true),
+            vec![]
+        );
+        assert_eq!(warnings("static int _x;", true), vec![]);
+    }
+    #[test]
+    fn test_main_implicit_return() {
+        let body = |input| match decl(input).unwrap().init {
+            Some(Initializer::FunctionBody(body)) => body,
+            _ => panic!("expected a function definition: {}", input),
+        };
+        let is_return_zero = |stmt: &Stmt<'static>| match &stmt.data {
+            StmtType::Return(Some(expr)) => expr.expr == ExprType::Literal(Literal::Int(0)),
+            _ => false,
+        };
+        let implicit = body("int main() {}");
+        assert_eq!(implicit.len(), 1);
+        assert!(is_return_zero(&implicit[0]));
+        let implicit = body("int main() { if (1) return 1; }");
+        assert_eq!(implicit.len(), 2);
+        assert!(is_return_zero(&implicit[1]));
+        // control can't reach the end
+        assert_eq!(body("int main() { return 1; }").len(), 1);
+        assert_eq!(
+            body("int main() { if (1) return 1; else { return 2; } }").len(),
+            1
+        );
+        // not the program's `main`
+        assert!(body("static int main() {}").is_empty());
+        assert!(body("void main() {}").is_empty());
+        assert!(body("int f() {}").is_empty());
+    }
+    #[test]
+    fn test_shadow() {
+        let shadows = |input| {
+            let mut a = Analyzer::new(parser(input), arena(), false);
+            for res in &mut a {
+                res.unwrap();
+            }
+            a.inner
+                .error_handler
+                .warnings
+                .into_iter()
+                .filter_map(|w| match w.data {
+                    Warning::Shadow(name, kind, previous) => {
+                        Some((name.resolve_and_clone(), kind, previous.is_some()))
+                    }
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        let x = |kind| vec![("x".to_owned(), kind, true)];
+        assert_eq!(
+            shadows("int x; int f(void) { int x = 1; return x; }"),
+            x(Shadowed::Global)
+        );
+        assert_eq!(
+            shadows("int f(int x) { { int x = 2; return x; } }"),
+            x(Shadowed::Parameter)
+        );
+        assert_eq!(
+            shadows("int f(void) { int x = 1; { int x = 2; return x; } }"),
+            x(Shadowed::Local)
+        );
+        assert_eq!(
+            shadows("int x; int f(int x) { return x; }"),
+            x(Shadowed::Global)
+        );
+        assert_eq!(
+            shadows("enum { x }; int f(void) { int x = 1; return x; }"),
+            x(Shadowed::EnumConstant)
+        );
+        for no_shadow in &[
+            // sibling scopes
+            "int f(void) { { int x = 1; (void)x; } { int x = 2; (void)x; } return 0; }",
+            // a parameter with the same name as its function
+            "int x(int x) { return x; }",
+            // struct members aren't in the same namespace
+            "int x; struct s { int x; };",
+            "int x; int f(void) { extern int x; return x; }",
+            "int f(void) { int x = 1; x += 1; return x; }",
+        ] {
+            assert_eq!(shadows(no_shadow), vec![], "{}", no_shadow);
+        }
+    }
+    #[test]
+    fn test_block_redeclaration() {
+        let errors = |input| {
+            let mut a = Analyzer::new(parser(input), arena(), false);
+            (&mut a)
+                .filter_map(Result::err)
+                .map(|err| err.data)
+                .collect::<Vec<_>>()
+        };
+        let redefinition = errors("int f(void) { int x = 1; int x = 2; return x; }");
+        match redefinition.as_slice() {
+            [Error::Semantic(err @ SemanticError::Redefinition(name, Some(_)))] => {
+                assert_eq!(*name, "x".into());
+                assert_eq!(err.notes().len(), 1);
+            }
+            other => panic!("expected a redefinition, got {:?}", other),
+        }
+        assert!(matches!(
+            errors("int f(void) { int x; long x; return 0; }").as_slice(),
+            [Error::Semantic(SemanticError::IncompatibleRedeclaration(
+                ..
+            ))]
+        ));
+        for ok in &[
+            "int f(void) { typedef int I; typedef int I; I i = 1; return i; }",
+            "typedef int I; typedef int I; I i;",
+            "int g; int f(void) { extern int g; extern int g; return g; }",
+            "int f(void) { int h(void); int h(void); return h(); }",
+        ] {
+            assert_eq!(errors(ok), vec![], "{}", ok);
+        }
+    }
+    #[test]
+    fn test_missing_return() {
+        let diagnostics = |input| {
+            let mut a = Analyzer::new(parser(input), arena(), false);
+            let errors: Vec<_> = (&mut a)
+                .filter_map(Result::err)
+                .map(|err| err.data)
+                .collect();
+            let warnings: Vec<_> = a
+                .inner
+                .error_handler
+                .warnings
+                .into_iter()
+                .map(|w| w.data)
+                .filter(|w| w.name() == "return-type")
+                .collect();
+            (errors, warnings)
+        };
+        let f = || InternedStr::get_or_intern("f");
+        assert_eq!(
+            diagnostics("int f(int x) { if (x) return 1; }"),
+            (vec![], vec![Warning::MissingReturn(f())])
+        );
+        assert_eq!(
+            diagnostics("int f(void) { return; }"),
+            (vec![SemanticError::MissingReturnValue(f()).into()], vec![])
+        );
+        assert_eq!(
+            diagnostics("void f(void) { return 1; }"),
+            (vec![SemanticError::ReturnFromVoid(f()).into()], vec![])
+        );
+        // the return value is converted to the return type
+        assert_eq!(diagnostics("int f(void) { return 1.5; }"), (vec![], vec![]));
+        let (errors, _) = diagnostics("int f(void) { struct s { int i; } s; return s; }");
+        assert_eq!(errors.len(), 1);
+        for returns in &[
+            "int f(int x) { if (x == 1) return 1; else if (x == 2) return 2; else return 3; }",
+            "int f(void) { while (1) {} }",
+            "int f(void) { for (;;) {} }",
+            "int f(void) { do {} while (1); }",
+            "void exit(int); int f(void) { exit(1); }",
+            "_Noreturn void die(void); int f(void) { die(); }",
+            "int f(int x) { if (x) return 1; __builtin_unreachable(); }",
+            "int f(int x) { switch (x) { case 1: return 1; default: return 0; } }",
+            "int f(int x) { switch (x) { default: if (x) return 1; else return 2; } }",
+            "int f(int x) { switch (x) { case 1: while (x) { break; } default: return 0; } }",
+            "int f(int x) { switch (x) { case 1: { default: return 0; } } }",
+            "int f(int x) { do { return x; } while (x); }",
+            "int f(int x) { do { if (x) return 1; else return 2; } while (1); }",
+            "int main(void) {}",
+            "void f(void) {}",
+        ] {
+            assert_eq!(diagnostics(returns), (vec![], vec![]), "{}", returns);
+        }
+        for falls_through in &[
+            "int f(void) { while (1) { break; } }",
+            "int f(int x) { for (; x; ) {} }",
+            "int f(int x) { switch (x) { case 1: return 1; } }",
+            "int f(int x) { switch (x) { case 1: break; default: return 0; } }",
+            "int f(int x) { switch (x) { default: return 0; case 1: x++; } }",
+            "int f(int x) { switch (x) { case 1: switch (x) { default: return 0; } } }",
+            "int f(int x) { do { if (x) continue; return x; } while (x); }",
+            "int f(int x) { do { if (x) break; return x; } while (1); }",
+        ] {
+            assert_eq!(
+                diagnostics(falls_through),
+                (vec![], vec![Warning::MissingReturn(f())]),
+                "{}",
+                falls_through
+            );
+        }
+    }
+    #[test]
+    fn test_deprecated() {
+        let deprecated = |input| {
+            let mut a = Analyzer::new(parser(input), arena(), false);
+            for res in &mut a {
+                res.unwrap();
+            }
+            a.inner
+                .error_handler
+                .warnings
+                .into_iter()
+                .filter_map(|w| match w.data {
+                    Warning::Deprecated(name, message, declared) => {
+                        assert!(declared.span.start < w.location.span.start);
+                        Some((name.resolve_and_clone(), message))
+                    }
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            deprecated(
+                "int f(void) __attribute__((deprecated(\"use g instead\")));
+                int main(void) { return f(); }"
+            ),
+            vec![("f".to_owned(), Some("use g instead".to_owned()))]
+        );
+        assert_eq!(
+            deprecated("__attribute__((deprecated)) int x; int g(void) { return x + x; }"),
+            vec![("x".to_owned(), None), ("x".to_owned(), None)]
+        );
+        assert_eq!(
+            deprecated("typedef int old_t __attribute__((__deprecated__)); old_t y;"),
+            vec![("old_t".to_owned(), None)]
+        );
+        // a redeclaration doesn't remove the attribute
+        assert_eq!(
+            deprecated(
+                "int f(void) __attribute__((deprecated)); int f(void);
+                int g(void) { return f(); }"
+            ),
+            vec![("f".to_owned(), None)]
+        );
+        for no_warning in &[
+            "__attribute__((deprecated)) int f(int n) { return n ? f(n - 1) : 0; }",
+            "__attribute__((deprecated)) int x; __attribute__((deprecated)) int f(void) { return x; }",
+            "int x __attribute__((deprecated)) = 1;",
+        ] {
+            assert_eq!(deprecated(no_warning), vec![], "{}", no_warning);
+        }
+    }
+    #[test]
+    fn test_constructor_attributes() {
+        let warnings = |input| {
+            let mut a = Analyzer::new(parser(input), arena(), false);
+            for res in &mut a {
+                res.unwrap();
+            }
+            let warnings = a.inner.error_handler.warnings.into_iter();
+            warnings.map(|w| w.data).collect::<Vec<_>>()
+        };
+        let func = |input| decl(input).unwrap().symbol.get().qualifiers.func;
+        let constructor = func("void f(void) __attribute__((constructor));");
+        assert_eq!(constructor.constructor, Some(DEFAULT_INIT_PRIORITY));
+        assert_eq!(constructor.destructor, None);
+        assert_eq!(
+            func("__attribute__((destructor(101))) void f();").destructor,
+            Some(101)
+        );
+        // a priority that's out of range is ignored
+        assert_eq!(
+            func("void f(void) __attribute__((constructor(70000)));").constructor,
+            Some(DEFAULT_INIT_PRIORITY)
+        );
+        // the definition is still a constructor
+        let definition = decls("void f(void) __attribute__((constructor(200))); void f(void) {}")
+            .pop()
+            .unwrap()
+            .unwrap();
+        let definition = definition.symbol.get().qualifiers.func;
+        assert_eq!(definition.constructor, Some(200));
+        assert_decl_display(
+            "void f(void) __attribute__((constructor(101), destructor));",
+            "__attribute__((constructor(101))) __attribute__((destructor)) void f(void);",
+        );
+
+        assert_eq!(
+            warnings("static void f(void) __attribute__((constructor)) {}"),
+            vec![]
+        );
+        assert_eq!(
+            warnings("int f(void) __attribute__((constructor));"),
+            vec![Warning::ConstructorSignature("constructor", "f".into())]
+        );
+        assert_eq!(
+            warnings("void g(int x) __attribute__((destructor));"),
+            vec![Warning::ConstructorSignature("destructor", "g".into())]
+        );
+        assert!(matches!(
+            decl("int x __attribute__((constructor));")
+                .unwrap_err()
+                .data,
+            Error::Semantic(SemanticError::FuncQualifiersNotAllowed(_))
+        ));
+    }
+    #[test]
+    fn test_link_attributes() {
+        use SemanticError::*;
+
+        let errors = |input| -> Vec<SemanticError> {
+            decls(input)
+                .into_iter()
+                .filter_map(Result::err)
+                .map(|err| match err.data {
+                    Error::Semantic(err) => err,
+                    other => panic!("expected a semantic error, got {}", other),
+                })
+                .collect()
+        };
+        for ok in &[
+            "int f(void) { return 0; } int g(void) __attribute__((alias(\"f\")));",
+            // the target can be defined after the alias
+            "extern int y __attribute__((alias(\"x\"))); int x = 1;",
+            "int x; int y __attribute__((alias(\"x\")));",
+            "static int f(void) { return 0; } int g(void) __attribute__((weak, alias(\"f\")));",
+            "void f(void) __attribute__((weak)); void f(void) {}",
+            "extern int x __attribute__((weak));",
+            "int f(void) { extern int x __attribute__((weak)); return x; }",
+        ] {
+            assert_eq!(errors(ok), vec![], "{}", ok);
+        }
+        let undefined = || vec![UndefinedAliasTarget("g".into(), "f".into())];
+        assert_eq!(
+            errors("int g(void) __attribute__((alias(\"f\")));"),
+            undefined()
+        );
+        // a declaration isn't enough
+        assert_eq!(
+            errors("int f(void); int g(void) __attribute__((alias(\"f\")));"),
+            undefined()
+        );
+        assert_eq!(
+            errors("extern int f; extern int g __attribute__((alias(\"f\")));"),
+            undefined()
+        );
+        assert_eq!(
+            errors("int f(void) { int x __attribute__((weak)) = 1; return x; }"),
+            vec![AttributeNeedsLinkage("weak", "x".into())]
+        );
+        assert_eq!(
+            errors("int f(void) { static int y __attribute__((alias(\"f\"))); return y; }"),
+            vec![AttributeNeedsLinkage("alias", "y".into())]
+        );
+        assert_eq!(
+            errors("static int x __attribute__((weak));"),
+            vec![AttributeNeedsLinkage("weak", "x".into())]
+        );
+        assert_eq!(
+            errors(
+                "int f(void) { return 0; } int g(void) __attribute__((alias(\"f\")));
+                int g(void) { return 1; }"
+            ),
+            vec![AliasRedefinition("g".into())]
+        );
+        assert_eq!(
+            errors("int x = 1; int y __attribute__((alias(\"x\"))) = 2;"),
+            vec![AliasRedefinition("y".into())]
+        );
+        assert_decl_display(
+            "extern int y __attribute__((weak, alias(\"x\")));",
+            "extern int y __attribute__((weak)) __attribute__((alias(\"x\")));",
+        );
+    }
+    #[test]
+    fn test_switch_labels() {
+        let diagnostics = |input: &str| {
+            let mut a = Analyzer::new(parser(input), arena(), false);
+            let errors: Vec<_> = (&mut a).filter_map(Result::err).collect();
+            let warnings: Vec<_> = a
+                .inner
+                .error_handler
+                .warnings
+                .into_iter()
+                .map(|w| w.data)
+                .collect();
+            (errors, warnings)
+        };
+        let switch = |ctype, cases| {
+            format!(
+                "int f({0} x); int f({0} x) {{ switch (x) {{ {1} }} return 0; }}",
+                ctype, cases
+            )
+        };
+        // the note points to the first label
+        let (errors, warnings) = diagnostics(&switch("int", "case 1: break; case 1L: break;"));
+        assert_eq!(warnings, vec![]);
+        assert_eq!(errors.len(), 1);
+        let previous = errors[0].data.notes().pop().unwrap().location;
+        assert!(previous.span.start < errors[0].location.span.start);
+        assert_eq!(
+            errors[0].data.to_string(),
+            "invalid program: duplicate case value `1`"
+        );
+        assert_eq!(errors[0].data.notes()[0].data, "previous case is here");
+        // values are compared after conversion, so these are the same case
+        let (errors, _) = diagnostics(&switch("unsigned", "case -1: case 4294967295: break;"));
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].data.to_string(),
+            "invalid program: duplicate case value `4294967295`"
+        );
+        let (errors, _) = diagnostics(&switch("int", "default: break; case 1: default: break;"));
+        assert_eq!(errors.len(), 1);
+        match &errors[0].data {
+            Error::Semantic(SemanticError::DuplicateDefault(Some(_))) => {}
+            other => panic!("expected a duplicate default, got {}", other),
+        }
+        // nested switches have their own labels
+        let (errors, _) = diagnostics(&switch(
+            "int",
+            "case 1: switch (x) { case 1: default: break; } default: break;",
+        ));
+        assert_eq!(errors, vec![]);
+
+        assert_eq!(
+            diagnostics(&switch("char", "case 300: break;")),
+            (vec![], vec![Warning::CaseOutOfRange(300, Type::Char(true))])
+        );
+        assert_eq!(
+            diagnostics(&switch("unsigned char", "case -1: break;")),
+            (vec![], vec![Warning::CaseOutOfRange(-1, Type::Char(false))])
+        );
+        for in_range in &[
+            switch("char", "case -128: case 127: break;"),
+            switch("unsigned", "case -1: break;"),
+            switch("long", "case 4294967296: break;"),
+            switch("_Bool", "case 0: case 1: break;"),
+        ] {
+            assert_eq!(diagnostics(in_range), (vec![], vec![]), "{}", in_range);
+        }
+        // enums promote to `int`
+        let (errors, _) = diagnostics(
+            "enum e { A, B }; int f(enum e x); int f(enum e x) { switch (x) { case B: case 1u: break; } return 0; }",
+        );
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            diagnostics(
+                "enum e { A }; int f(enum e x); int f(enum e x) { switch (x) { case -1: break; } return 0; }"
+            ),
+            (vec![], vec![])
+        );
+        for (ctype, name) in &[("double", "double"), ("int *", "int *")] {
+            let (errors, _) = diagnostics(&switch(ctype, "default: break;"));
+            assert_eq!(errors.len(), 1);
+            assert_eq!(
+                errors[0].data.to_string(),
+                format!(
+                    "invalid program: switch expressions must have an integer type (got {})",
+                    name
+                )
+            );
+        }
+        // 6.8.4.2p3: the case must be an integer constant expression, even if the value is integral
+        let (errors, _) = diagnostics(&switch("int", "case 2.0: break;"));
+        assert_eq!(errors.len(), 1);
+        let many_cases: String = (0..1000)
+            .map(|i| format!("case {}: return {};", i, i * 2))
+            .collect();
+        assert_eq!(
+            diagnostics(&switch("int", &(many_cases + " default: break;"))),
+            (vec![], vec![])
+        );
+    }
+    #[test]
     fn test_inline_keyword() {
         // Correct usage
         assert!(match_type(
@@ -1711,6 +3541,7 @@ pub(crate) mod test {
                     ctype: Type::Void,
                     qualifiers: Qualifiers::default(),
                     storage_class: StorageClass::default(),
+                    location: Location::default(),
                 }
                 .insert()],
                 varargs: false,
@@ -1728,6 +3559,25 @@ pub(crate) mod test {
         assert!(decl("typedef a inline int;").is_err());
     }
     #[test]
+    fn test_returns_twice() {
+        let func_quals = |s| decl(s).unwrap().symbol.get().qualifiers.func;
+        assert!(func_quals("int setjmp(void *);").returns_twice);
+        assert!(func_quals("int _setjmp(void *);").returns_twice);
+        assert!(func_quals("int f(void) __attribute__((returns_twice));").returns_twice);
+        assert!(func_quals("__attribute__((__returns_twice__)) int f(void);").returns_twice);
+        assert!(!func_quals("int f(void);").returns_twice);
+        assert!(!func_quals("int f(void) __attribute__((unused, aligned(8)));").returns_twice);
+        let noreturn = func_quals("void f(void) __attribute__((noreturn));");
+        assert!(noreturn.no_return && !noreturn.returns_twice);
+        // only the declarator with the attribute is affected
+        let decls = decls("int f(void) __attribute__((returns_twice)), g(void);");
+        let quals: Vec<_> = decls
+            .into_iter()
+            .map(|d| d.unwrap().symbol.get().qualifiers.func.returns_twice)
+            .collect();
+        assert_eq!(quals, vec![true, false]);
+    }
+    #[test]
     fn test_complex() {
         // cdecl: declare bar as const pointer to array 10 of pointer to function (int) returning volatile pointer to char
         assert!(match_type(
@@ -1748,6 +3598,7 @@ pub(crate) mod test {
                                 storage_class: Default::default(),
                                 id: Default::default(),
                                 qualifiers: Qualifiers::NONE,
+                                location: Location::default(),
                             }
                             .insert()],
                             varargs: false,
@@ -1776,6 +3627,7 @@ pub(crate) mod test {
                         storage_class: Default::default(),
                         id: Default::default(),
                         qualifiers: Default::default(),
+                        location: Location::default(),
                     }
                     .insert()],
                     varargs: false,
@@ -1844,6 +3696,70 @@ pub(crate) mod test {
         assert!(decl("int f(int a, int a);").is_err());
     }
     #[test]
+    fn test_struct_identity() {
+        // the same definition is compatible with itself, in any scope
+        let same =
+            "struct s { int i; } x; void f(void) { struct s y; x = y; y = x; { struct s z = x; } }";
+        assert!(decls(same).into_iter().all(|decl| decl.is_ok()), "{}", same);
+
+        let mismatch = "struct a { int x; } a; struct b { int x; } b; void f(void) { a = b; }";
+        let errs: Vec<_> = decls(mismatch)
+            .into_iter()
+            .filter_map(Result::err)
+            .collect();
+        assert_eq!(errs.len(), 1);
+        assert_eq!(
+            errs[0].data.to_string(),
+            "invalid program: assigning to 'struct a' from incompatible type 'struct b'"
+        );
+        let notes: Vec<_> = errs[0].data.notes().into_iter().map(|n| n.data).collect();
+        assert_eq!(
+            notes,
+            vec!["'struct a' is defined here", "'struct b' is defined here"]
+        );
+
+        // the same tag in sibling scopes is two different types
+        let siblings = "void f(void) { { struct s { int i; } a; } { struct s { int i; } b; } }";
+        let body = match decl(siblings).unwrap().init {
+            Some(Initializer::FunctionBody(body)) => body,
+            other => panic!("expected a function body, got {:?}", other),
+        };
+        let ctypes: Vec<_> = body
+            .iter()
+            .map(|stmt| match &stmt.data {
+                StmtType::Compound(inner) => match &inner[0].data {
+                    StmtType::Decl(decls) => decls[0].data.symbol.get().ctype.clone(),
+                    other => panic!("expected a declaration, got {:?}", other),
+                },
+                other => panic!("expected a block, got {:?}", other),
+            })
+            .collect();
+        assert_eq!(ctypes[0].to_string(), ctypes[1].to_string());
+        assert_ne!(ctypes[0], ctypes[1]);
+        let (first, second) = match (&ctypes[0], &ctypes[1]) {
+            (Struct(first), Struct(second)) => (first.definition(), second.definition()),
+            _ => panic!("expected structs, got {:?}", ctypes),
+        };
+        assert!(first.is_some() && second.is_some());
+        assert_ne!(first, second);
+
+        // passing the wrong struct by value
+        let argument = "struct a { int x; } a; struct b { int x; } b; void take(struct a); void f(void) { take(b); }";
+        let errs: Vec<_> = decls(argument)
+            .into_iter()
+            .filter_map(Result::err)
+            .collect();
+        assert_eq!(errs.len(), 1, "{}", argument);
+        assert!(matches!(
+            errs[0].data,
+            Error::Semantic(SemanticError::InvalidArgument { position: 1, .. })
+        ));
+        assert_eq!(
+            errs[0].data.to_string(),
+            "invalid program: incompatible type 'struct b' for argument 1: expected 'struct a'"
+        );
+    }
+    #[test]
     fn default_type_specifier_warns() {
         let default_type_decls = &[
             "i;",
@@ -1878,35 +3794,76 @@ pub(crate) mod test {
         );
     }
 
+    fn semantic_err(s: &str) -> SemanticError {
+        match decl(s).unwrap_err().data {
+            Error::Semantic(err) => err,
+            other => panic!("expected a semantic error, got {:?}", other),
+        }
+    }
+
     #[test]
     fn enum_declaration() {
-        assert!(decl("enum;").is_err());
-        assert!(decl("enum e;").is_err());
+        assert_eq!(
+            semantic_err("enum;"),
+            SemanticError::BareTagSpecifier(Keyword::Enum)
+        );
+        assert_eq!(
+            semantic_err("enum e;"),
+            SemanticError::EnumForwardReference("e".into())
+        );
         assert!(decl("enum e {};").is_err());
+        assert_eq!(
+            semantic_err("struct e; enum e x;"),
+            SemanticError::TagMismatch("e".into(), Keyword::Enum)
+        );
         assert!(decl("enum e { A }").is_err());
         assert!(maybe_decl("enum { A };").is_none());
         assert!(match_type(
             decl("enum { A } E;"),
-            Type::Enum(None, vec![("A".into(), 0)])
+            Type::Enum(None, Rc::new(vec![("A".into(), 0)]))
         ));
         assert!(match_type(
             decl("enum e { A = 1, B } E;"),
-            Type::Enum(Some("e".into()), vec![("A".into(), 1), ("B".into(), 2)])
+            Type::Enum(
+                Some("e".into()),
+                Rc::new(vec![("A".into(), 1), ("B".into(), 2)])
+            )
         ));
         assert!(match_type(
             decl("enum { A = -5, B, C = 2, D } E;"),
             Type::Enum(
                 None,
-                vec![
+                Rc::new(vec![
                     ("A".into(), -5),
                     ("B".into(), -4),
                     ("C".into(), 2),
                     ("D".into(), 3)
-                ]
+                ])
             )
         ));
     }
 
+    #[test]
+    fn enum_constants_share_members() {
+        // looking up a constant in a big enum shouldn't copy all its members
+        let members: Vec<_> = (0..500).map(|i| format!("M{}", i)).collect();
+        let program = format!("enum e {{ {} }} x = M499;", members.join(", "));
+        let decl = decl(&program).unwrap();
+        let members = match &decl.symbol.get().ctype {
+            Type::Enum(_, members) => Rc::clone(members),
+            other => panic!("expected an enum, got {}", other),
+        };
+        let init = match decl.init {
+            Some(Initializer::Scalar(expr)) => expr,
+            other => panic!("expected a scalar initializer, got {:?}", other),
+        };
+        assert_eq!(init.expr, ExprType::Literal(Literal::Int(499)));
+        match &init.ctype {
+            Type::Enum(_, shared) => assert!(Rc::ptr_eq(shared, &members)),
+            other => panic!("expected an enum, got {}", other),
+        }
+    }
+
     #[test]
     fn typedef_signed() {
         let mut ds = decls("typedef unsigned uint; uint i;").into_iter();
@@ -1921,7 +3878,41 @@ pub(crate) mod test {
         assert!(decl("struct { int:5; } a;").is_err());
         assert!(decl("struct { int a:5; } b;").is_ok());
         assert!(decl("struct { int a:5, b:6; } c;").is_ok());
-        assert!(decl("struct { extern int a:5; } d;").is_err());
+        assert_eq!(
+            semantic_err("struct { extern int a:5; } d;"),
+            SemanticError::MemberStorageClass(StorageClass::Extern, "a".into())
+        );
+        assert_eq!(
+            semantic_err("struct { int a:0; } e;"),
+            SemanticError::ZeroWidthBitfield("a".into())
+        );
+        assert_eq!(
+            semantic_err("struct { char a:9; } f;"),
+            SemanticError::BitfieldTooWide {
+                name: "a".into(),
+                width: 9,
+                ctype: Type::Char(true),
+            }
+        );
+    }
+    #[test]
+    fn struct_errors() {
+        assert_eq!(
+            semantic_err("struct;"),
+            SemanticError::BareTagSpecifier(Keyword::Struct)
+        );
+        assert_eq!(
+            semantic_err("union s; struct s x;"),
+            SemanticError::TagMismatch("s".into(), Keyword::Struct)
+        );
+        assert_eq!(
+            semantic_err("struct s { int i; }; struct s { int i; };"),
+            SemanticError::TagRedefinition(Keyword::Struct, "s".into())
+        );
+        assert!(matches!(
+            semantic_err("struct s; struct t { struct s member; };"),
+            SemanticError::IncompleteMember(Type::Struct(_))
+        ));
     }
     #[test]
     fn lol() {
@@ -1955,4 +3946,258 @@ int main() {
             "extern int f(void) {\n    return (int)(1);\n}\n",
         );
     }
+    #[test]
+    fn test_unused_value() {
+        let unused = |body: &str| {
+            let program = format!(
+                "int g(void); volatile int v, *vp;
+                struct s {{ int x; }}; volatile struct s vs;
+                #define CHECK(x) (x) == 0
+                #define IGNORE(x) ((void)(x))
+                int f(int x, int *p) {{ {} return 0; }}",
+                body
+            );
+            let mut a = Analyzer::new(parser(&program), arena(), false);
+            for res in &mut a {
+                res.unwrap();
+            }
+            a.inner
+                .error_handler
+                .warnings
+                .into_iter()
+                .filter(|w| w.data == Warning::UnusedValue)
+                .count()
+        };
+        for no_effect in &[
+            "x == 1;",
+            "x + 1;",
+            "x & 4;",
+            "-x;",
+            "x;",
+            "1;",
+            "(long)x;",
+            "g(), x;",
+            "x == g();",
+            // only the `int` pointed to is `volatile`
+            "vp;",
+        ] {
+            assert_eq!(unused(no_effect), 1, "{}", no_effect);
+        }
+        for has_effect in &[
+            "x = 1;",
+            "x += 1;",
+            "g();",
+            "x++;",
+            "--x;",
+            "v;",
+            "x, g();",
+            "(void)x;",
+            "(void)g();",
+            "(void)(x == 1);",
+            "*p;",
+            "*vp;",
+            "vs.x;",
+            "x && g();",
+            "CHECK(x);",
+            "IGNORE(x);",
+        ] {
+            assert_eq!(unused(has_effect), 0, "{}", has_effect);
+        }
+    }
+    #[test]
+    fn test_constant_condition() {
+        let constant = |body: &str| {
+            let program = format!(
+                "#define DEBUG 0
+                #define SWAP(a, b) do {{ int t = a; a = b; b = t; }} while (0)
+                int f(int x, int y) {{ {} return x; }}",
+                body
+            );
+            let mut a = Analyzer::new(parser(&program), arena(), false);
+            for res in &mut a {
+                res.unwrap();
+            }
+            a.inner
+                .error_handler
+                .warnings
+                .into_iter()
+                .filter_map(|w| match w.data {
+                    Warning::ConstantCondition(value) => Some(value),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(constant("do { x++; } while (0);"), vec![false]);
+        assert_eq!(constant("while (0) x++;"), vec![false]);
+        assert_eq!(constant("if (1) x++;"), vec![true]);
+        assert_eq!(constant("if (2 - 2) x++;"), vec![false]);
+        for silent in &[
+            "SWAP(x, y);",
+            "if (DEBUG) x++;",
+            "while (1) break;",
+            "for (;;) break;",
+            "for (; 1;) break;",
+            "if (x) x++;",
+        ] {
+            assert_eq!(constant(silent), Vec::<bool>::new(), "{}", silent);
+        }
+    }
+    #[test]
+    fn test_prototypes() {
+        let warnings = |program: &str| {
+            let mut a = Analyzer::new(parser(program), arena(), false);
+            for res in &mut a {
+                res.unwrap();
+            }
+            a.inner
+                .error_handler
+                .warnings
+                .into_iter()
+                .map(|w| w.data)
+                .filter(|w| {
+                    matches!(
+                        w,
+                        Warning::MissingPrototype(_)
+                            | Warning::StrictPrototype
+                            | Warning::OldStyleDefinition
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        let missing = || Warning::MissingPrototype("f".into());
+        assert_eq!(warnings("int f(void) { return 0; }"), vec![missing()]);
+        // a declaration without a prototype doesn't count
+        assert_eq!(
+            warnings("int f(); int f(int x) { return x; }"),
+            vec![Warning::StrictPrototype, missing()]
+        );
+        assert_eq!(
+            warnings("int f() { return 0; }"),
+            vec![
+                Warning::StrictPrototype,
+                Warning::OldStyleDefinition,
+                missing()
+            ]
+        );
+        assert_eq!(warnings("int (*fp)();"), vec![Warning::StrictPrototype]);
+        for no_warning in &[
+            "int f(void); int f(void) { return 0; }",
+            "int f(int x, ...); int f(int x, ...) { return x; }",
+            "static int f(void) { return 0; }",
+            "static int f(void); int f(void) { return 0; }",
+            "int main(void) { return 0; }",
+            "int (*fp)(int);",
+        ] {
+            assert_eq!(warnings(no_warning), vec![], "{}", no_warning);
+        }
+    }
+
+    #[test]
+    fn fragments() {
+        let x = Variable {
+            ctype: Type::Int(true),
+            id: "x".into(),
+            qualifiers: Default::default(),
+            storage_class: Default::default(),
+            location: Location::default(),
+        }
+        .insert();
+        let mut analyzer = PureAnalyzer::with_scope(arena(), vec![x]);
+        let stmt = analyzer.parse_stmt_str("int y = x;").unwrap();
+        assert!(matches!(stmt.data, StmtType::Decl(_)));
+        let scope = analyzer.scope();
+        assert_eq!(scope.get(&"x".into()), Some(&x));
+        let y = *scope.get(&"y".into()).expect("y should be in scope");
+        assert_eq!(y.get().storage_class, StorageClass::Auto);
+
+        let expr = analyzer.parse_expr_str("x + y").unwrap();
+        assert_eq!(expr.ctype, Type::Int(true));
+
+        assert!(analyzer.parse_expr_str("z").is_err());
+        assert!(analyzer.parse_expr_str("x y").is_err());
+        assert!(analyzer.parse_expr_str("").is_err());
+        assert!(analyzer.parse_stmt_str("int y;").is_err());
+        assert!(analyzer.warnings().is_empty());
+        analyzer.parse_stmt_str("if (x = 1) {}").unwrap();
+        let warnings = analyzer.warnings();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].data, Warning::AssignmentInCondition);
+    }
+
+    #[test]
+    fn test_func_name_is_const() {
+        use crate::data::error::NotAssignable;
+
+        // 6.4.2.2: `__func__` is declared as `static const char __func__[]`
+        let err = decl("void f(void) { __func__[0] = 'x'; }").unwrap_err();
+        assert!(
+            matches!(
+                err.data,
+                Error::Semantic(SemanticError::NotAssignable(
+                    NotAssignable::ReadOnlyLocation(_)
+                ))
+            ),
+            "{:?}",
+            err
+        );
+        let mut analyzer = PureAnalyzer::new(arena());
+        let decls = analyzer
+            .parse_external_decl_str(
+                "void f(void) { char *p = __func__; const char *q = __func__; }",
+            )
+            .unwrap();
+        let discarded: Vec<_> = analyzer
+            .warnings()
+            .into_iter()
+            .map(|w| w.data)
+            .filter(|w| matches!(w, Warning::DiscardedQualifiers(_)))
+            .collect();
+        assert_eq!(
+            discarded,
+            vec![Warning::DiscardedQualifiers("__func__".into())]
+        );
+        // every use refers to the same array, declared at the start of the function
+        let body = match &decls[0].data.init {
+            Some(Initializer::FunctionBody(body)) => body,
+            _ => panic!("expected a function body"),
+        };
+        match &body[0].data {
+            StmtType::Decl(decls) => {
+                let meta = decls[0].data.symbol.get();
+                assert_eq!(meta.storage_class, StorageClass::Static);
+                assert!(meta.qualifiers.c_const);
+            }
+            other => panic!("expected `__func__` to be declared first, got {:?}", other),
+        }
+        assert_eq!(body.len(), 3);
+    }
+
+    #[test]
+    fn declaration_fragments() {
+        let mut analyzer = PureAnalyzer::new(arena());
+        let decls = analyzer
+            .parse_external_decl_str("int sq(int x) { return x * x; }")
+            .unwrap();
+        assert_eq!(decls.len(), 1);
+        assert!(matches!(
+            decls[0].data.init,
+            Some(Initializer::FunctionBody(_))
+        ));
+        // only one declaration at a time
+        let err = analyzer
+            .parse_external_decl_str("typedef int T; T i = 1, *p;")
+            .unwrap_err();
+        assert!(matches!(err.data, Error::Syntax(_)));
+
+        analyzer.parse_external_decl_str("typedef int T;").unwrap();
+        // a typedef as the very first token
+        let decls = analyzer.parse_external_decl_str("T i = 1, *p;").unwrap();
+        assert_eq!(decls.len(), 2);
+        let scope = analyzer.scope();
+        let i = scope.get(&"i".into()).expect("i should be in scope");
+        assert_eq!(i.get().ctype, Type::Int(true));
+        let expr = analyzer.parse_expr_str("sq(i)").unwrap();
+        assert_eq!(expr.ctype, Type::Int(true));
+        assert!(analyzer.parse_external_decl_str("int j").is_err());
+    }
 }
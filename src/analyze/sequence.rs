@@ -0,0 +1,231 @@
+//! Warnings for variables that are modified and also read or modified elsewhere in the same expression
+//! without a sequence point in between (`-Wsequence-point`), like `a[i++] = b[i]` or `i = i++`.
+//!
+//! This works on the AST, before assignments and increments are desugared into temporaries.
+//! To keep false positives down, only variables named directly are tracked, never `*p` or `s.x`,
+//! so everything this warns about is undefined behavior (6.5p2).
+
+use super::PureAnalyzer;
+use crate::data::ast::{Expr, ExprType};
+use crate::data::{error::Warning, Location};
+use crate::intern::InternedStr;
+
+/// The variables a subexpression accesses.
+#[derive(Default)]
+struct Accesses {
+    /// Modifications which aren't sequenced before the value of the subexpression is computed.
+    writes: Vec<InternedStr>,
+    /// Modifications which are, like `i++` in `(i++, 0)` or `f(i++)`.
+    sequenced: Vec<InternedStr>,
+    reads: Vec<InternedStr>,
+}
+
+impl Accesses {
+    fn read(name: InternedStr) -> Self {
+        Accesses {
+            reads: vec![name],
+            ..Accesses::default()
+        }
+    }
+    fn modifies(&self, name: InternedStr) -> bool {
+        self.writes.contains(&name) || self.sequenced.contains(&name)
+    }
+    fn merge(mut self, other: Accesses) -> Self {
+        self.writes.extend(other.writes);
+        self.sequenced.extend(other.sequenced);
+        self.reads.extend(other.reads);
+        self
+    }
+    // everything in `self` happens before the next subexpression is evaluated
+    fn sequence(mut self) -> Self {
+        self.sequenced.append(&mut self.writes);
+        self
+    }
+}
+
+impl PureAnalyzer<'_> {
+    /// Warn about unsequenced modifications in the full expression `expr`.
+    pub(super) fn check_sequence_points(&mut self, expr: &Expr) {
+        let mut conflicts = Vec::new();
+        self.accesses(expr, &mut conflicts);
+        let mut warned = Vec::new();
+        for (name, location) in conflicts {
+            if !warned.contains(&name) {
+                warned.push(name);
+                self.warn(Warning::SequencePoint(name), location);
+            }
+        }
+    }
+
+    fn accesses(&mut self, expr: &Expr, conflicts: &mut Vec<(InternedStr, Location)>) -> Accesses {
+        use ExprType::*;
+
+        let _guard = self.recursion_check();
+
+        let location = expr.location;
+        match &expr.data {
+            Id(name) => Accesses::read(*name),
+            // `sizeof` and `_Alignof` don't evaluate their operand
+            Literal(_) | SizeofType(_) | SizeofExpr(_) | AlignofType(_) | AlignofExpr(_) => {
+                Accesses::default()
+            }
+            // `&i` doesn't read `i`
+            AddressOf(inner) if matches!(inner.data, Id(_)) => Accesses::default(),
+            Cast(_, inner)
+            | Deref(inner)
+            | AddressOf(inner)
+            | UnaryPlus(inner)
+            | Negate(inner)
+            | BitwiseNot(inner)
+            | LogicalNot(inner)
+            | Member(inner, _)
+            | DerefMember(inner, _) => self.accesses(inner, conflicts),
+            PostIncrement(inner, _) | PreIncrement(inner, _) => match inner.data {
+                Id(name) => Accesses {
+                    writes: vec![name],
+                    ..Accesses::default()
+                },
+                _ => self.accesses(inner, conflicts),
+            },
+            Assign(target, value, _) => match target.data {
+                // `i = i + 1` only reads `i` to compute the value being stored, which is fine
+                Id(name) => {
+                    let mut value = self.accesses(value, conflicts);
+                    if value.writes.contains(&name) {
+                        conflicts.push((name, location));
+                    }
+                    value.writes.push(name);
+                    value
+                }
+                _ => self.unsequenced(&[target, value], location, conflicts),
+            },
+            BitwiseOr(left, right)
+            | BitwiseAnd(left, right)
+            | Xor(left, right)
+            | Mul(left, right)
+            | Div(left, right)
+            | Mod(left, right)
+            | Add(left, right)
+            | Sub(left, right)
+            | Shift(left, right, _)
+            | Compare(left, right, _)
+            | Index(left, right) => self.unsequenced(&[left, right], location, conflicts),
+            // 6.5.2.2p10: "There is a sequence point after the evaluations of the function designator
+            // and the actual arguments but before the actual call."
+            FuncCall(func, args) => {
+                let operands: Vec<&Expr> = std::iter::once(&**func).chain(args).collect();
+                self.unsequenced(&operands, location, conflicts).sequence()
+            }
+            // the left operand is sequenced before the right
+            LogicalOr(left, right) | LogicalAnd(left, right) | Comma(left, right) => {
+                let left = self.accesses(left, conflicts).sequence();
+                left.merge(self.accesses(right, conflicts))
+            }
+            Ternary(condition, then, otherwise) => {
+                let mut result = self.accesses(condition, conflicts).sequence();
+                if let Some(then) = then {
+                    result = result.merge(self.accesses(then, conflicts));
+                }
+                result.merge(self.accesses(otherwise, conflicts))
+            }
+        }
+    }
+
+    /// The accesses of `operands`, which can be evaluated in any order.
+    ///
+    /// Any variable modified by one operand and read or modified by another is a conflict at `location`.
+    fn unsequenced(
+        &mut self,
+        operands: &[&Expr],
+        location: Location,
+        conflicts: &mut Vec<(InternedStr, Location)>,
+    ) -> Accesses {
+        operands.iter().fold(Accesses::default(), |seen, operand| {
+            let next = self.accesses(operand, conflicts);
+            for (earlier, later) in &[(&seen, &next), (&next, &seen)] {
+                for &name in earlier.writes.iter().chain(&earlier.sequenced) {
+                    if later.modifies(name) || later.reads.contains(&name) {
+                        conflicts.push((name, location));
+                    }
+                }
+            }
+            seen.merge(next)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analyze::{test::arena, Analyzer};
+    use crate::data::error::Warning;
+    use crate::parse::test::parser;
+
+    fn sequence_points(body: &str) -> Vec<Warning> {
+        let program = format!(
+            "int f(int); int g(int, int); int a[3], b[3], *p;
+            void h(int i, int j) {{ {} }}",
+            body
+        );
+        let mut a = Analyzer::new(parser(&program), arena(), false);
+        for res in &mut a {
+            res.unwrap();
+        }
+        a.inner
+            .error_handler
+            .warnings
+            .into_iter()
+            .map(|w| w.data)
+            .filter(|w| w.name() == "sequence-point")
+            .collect()
+    }
+
+    #[test]
+    fn test_sequence_points() {
+        let i = vec![Warning::SequencePoint("i".into())];
+        for undefined in &[
+            "a[i++] = b[i];",
+            "a[i] = i++;",
+            "i = i++;",
+            "i += i++;",
+            "i++ + i++;",
+            "j = i++ + i;",
+            "g(i++, i);",
+            "g(i++, i++);",
+            "g(i = 1, i = 2);",
+            // the calls to `f` can't overlap, but both increments can happen before either call
+            "g(f(i++), f(i++));",
+            "f(i++) + i;",
+            "(i++, 0) + i;",
+            "a[i] = f(i++);",
+            // each variable is only warned about once
+            "i = i++ + i++;",
+            // initializers are full expressions too
+            "int r = g(i++, i++);",
+            "int k = 0, r[2] = { i++ + i, 0 };",
+        ] {
+            assert_eq!(sequence_points(undefined), i, "{}", undefined);
+        }
+        for defined in &[
+            "i = i + 1;",
+            "i += 1;",
+            "i++;",
+            "a[i] = b[i];",
+            "i = f(i++);",
+            "i = (i++, 0);",
+            "i++, i++;",
+            "i++ && i;",
+            "i++ ? i : 0;",
+            "j = i++;",
+            "a[i++] = b[j++];",
+            // only variables named directly are tracked
+            "*p = p[0]++;",
+            "sizeof(i++) + i;",
+            // the initializers of different declarators are sequenced
+            "int k = i++, l = i++;",
+            // and so are the expressions in an initializer list (6.7.9p23)
+            "int r[2] = { i++, i++ };",
+        ] {
+            assert_eq!(sequence_points(defined), vec![], "{}", defined);
+        }
+    }
+}
@@ -1,65 +1,146 @@
 use super::FunctionAnalyzer;
-use crate::data::{ast, error::SemanticError, hir::*, lex::Locatable, Location};
+use crate::arch::{Target, CHAR_BIT};
+use crate::data::{
+    ast,
+    error::{SemanticError, Warning},
+    hir::*,
+    lex::{AssignmentToken, Locatable},
+    CompileError, Location, Type, Version,
+};
+use crate::fold::{const_eval, ConstValue};
+use crate::ice;
+use std::collections::HashMap;
 
-impl FunctionAnalyzer<'_> {
+/// The labels of a `switch` statement, used to detect duplicate cases.
+pub(super) struct SwitchLabels {
+    /// the type of the controlling expression, before integer promotion
+    ctype: Type,
+    /// each case value, after conversion to the promoted type, and where it was first seen
+    cases: HashMap<i128, Location>,
+    default: Option<Location>,
+}
+
+/// Convert `value` to the integer type `ctype`, wrapping if it does not fit.
+fn convert_integer(value: i128, ctype: &Type) -> i128 {
+    let bits = match ctype {
+        Type::Bool => return (value != 0).into(),
+        // enums are always compatible with `int`
+        Type::Enum(..) => return convert_integer(value, &Type::Int(true)),
+        _ => match ctype.sizeof() {
+            Ok(size) => size as u32 * u32::from(CHAR_BIT),
+            Err(_) => return value,
+        },
+    };
+    let shift = 128 - bits;
+    if ctype.is_signed() {
+        (value << shift) >> shift
+    } else {
+        ((value as u128) << shift >> shift) as i128
+    }
+}
+
+impl<'hir> FunctionAnalyzer<'_, 'hir> {
     #[inline(always)]
-    fn expr(&mut self, expr: ast::Expr) -> Expr {
+    fn expr(&mut self, expr: ast::Expr) -> Expr<'hir> {
+        self.analyzer.check_sequence_points(&expr);
         self.analyzer.expr(expr)
     }
-    pub(crate) fn parse_stmt(&mut self, stmt: ast::Stmt) -> Stmt {
+    /// The controlling expression of an `if`, `while`, `do`, or `for` statement
+    ///
+    /// `while (1)` is the idiomatic way to write an infinite loop,
+    /// so for loops (`is_loop`), only conditions that are always false are warned about.
+    fn condition(&mut self, condition: ast::Expr, is_loop: bool) -> Expr<'hir> {
+        // `if (x = 1)` was probably meant to be `if (x == 1)`;
+        // like GCC and Clang, `if ((x = 1))` silences the warning
+        if let ast::ExprType::Assign(lval, _, token) = &condition.data {
+            let parenthesized = condition.location.span.start < lval.location.span.start;
+            if token.data == AssignmentToken::Equal && !parenthesized {
+                self.analyzer
+                    .warn(Warning::AssignmentInCondition, token.location);
+            }
+        }
+        let condition = self.expr(condition);
+        let condition = self.analyzer.condition(condition);
+        // `do { ... } while (0)` and `if (DEBUG)` are how macros are meant to be written
+        if !condition.location.is_from_macro() && !condition.is_error() {
+            let value = match const_eval(&condition, &Target::host()) {
+                Ok(ConstValue::Int(i)) => Some(i != 0),
+                Ok(ConstValue::Uint(u)) => Some(u != 0),
+                _ => None,
+            };
+            if let Some(value) = value.filter(|&value| !(is_loop && value)) {
+                self.analyzer
+                    .warn(Warning::ConstantCondition(value), condition.location);
+            }
+        }
+        condition
+    }
+    pub(crate) fn parse_stmt(&mut self, stmt: ast::Stmt) -> Stmt<'hir> {
         use ast::StmtType::*;
         use StmtType as S;
 
+        ice::set_location(stmt.location);
         // ugh so much boilerplate
         let data = match stmt.data {
             Compound(stmts) => {
+                self.check_declarations_first(&stmts);
                 // 6.2.1 Scopes of identifiers
                 self.enter_scope();
-                let mut parsed = Vec::new();
-                for inner in stmts {
-                    parsed.push(self.parse_stmt(inner));
-                }
+                let parsed = self.block_items(stmts);
                 self.leave_scope(stmt.location);
                 S::Compound(parsed)
             }
             // 6.8.3 Expression and null statements
-            Expr(expr) => S::Expr(self.expr(expr)),
+            Expr(expr) => {
+                let expr = self.expr(expr).discard(self.analyzer.arena);
+                // errors are replaced with `0`, which would always warn
+                if !expr.is_error() && expr.has_no_effect() && !expr.location.is_from_macro() {
+                    self.analyzer.warn(Warning::UnusedValue, expr.location);
+                }
+                S::Expr(expr)
+            }
             // 6.8.4.1 The if statement
             If(condition, then, otherwise) => {
-                let condition = self
-                    .expr(condition)
-                    .truthy(&mut self.analyzer.error_handler);
+                let condition = self.condition(condition, false);
                 let then = self.parse_stmt(*then);
-                let otherwise = otherwise.map(|s| Box::new(self.parse_stmt(*s)));
-                S::If(condition, Box::new(then), otherwise)
+                let otherwise =
+                    otherwise.map(|s| -> &mut _ { self.analyzer.arena.stmt(self.parse_stmt(*s)) });
+                S::If(condition, self.analyzer.arena.stmt(then), otherwise)
             }
             // 6.8.4.2 The switch statement
             Switch(value, body) => {
-                let value = self.expr(value).rval();
-                if !value.ctype.is_integral() {
+                let mut value = self.expr(value).rval(self.analyzer.arena);
+                let ctype = value.ctype.clone();
+                if !ctype.is_integral() && !value.is_error() {
                     self.err(
-                        SemanticError::NonIntegralSwitch(value.ctype.clone()),
+                        SemanticError::NonIntegralSwitch(ctype.clone()),
                         stmt.location,
                     )
+                } else if ctype.is_integral() {
+                    // 6.8.4.2p5: the integer promotions are performed on the controlling expression
+                    value = value
+                        .integer_promote(self.analyzer.arena, &mut self.analyzer.error_handler);
                 }
-                let body = self.parse_stmt(*body);
-                S::Switch(value, Box::new(body))
+                self.switches.push(SwitchLabels {
+                    ctype,
+                    cases: HashMap::new(),
+                    default: None,
+                });
+                let body = self.switch_body(*body);
+                self.switches.pop();
+                S::Switch(value, self.analyzer.arena.stmt(body))
             }
             // 6.8.5.2 The do statement
             Do(body, condition) => {
-                let body = self.parse_stmt(*body);
-                let condition = self
-                    .expr(condition)
-                    .truthy(&mut self.analyzer.error_handler);
-                S::Do(Box::new(body), condition)
+                let body = self.loop_body(*body);
+                let condition = self.condition(condition, true);
+                S::Do(self.analyzer.arena.stmt(body), condition)
             }
             // 6.8.5.1 The while statement
             While(condition, body) => {
-                let condition = self
-                    .expr(condition)
-                    .truthy(&mut self.analyzer.error_handler);
-                let body = self.parse_stmt(*body);
-                S::While(condition, Box::new(body))
+                let condition = self.condition(condition, true);
+                let body = self.loop_body(*body);
+                S::While(condition, self.analyzer.arena.stmt(body))
             }
             // 6.8.5.3 The for statement
             For {
@@ -70,30 +151,80 @@ impl FunctionAnalyzer<'_> {
             } => {
                 // TODO: maybe a sanity check here that the init statement is only an expression or declaration?
                 // Or encode that in the type somehow?
+                if let ast::StmtType::Decl(_) = initializer.data {
+                    if self.analyzer.standard.version < Version::C99 {
+                        self.analyzer
+                            .c99_feature("declarations in 'for' loops", initializer.location);
+                    }
+                }
                 self.enter_scope();
                 let initializer = self.parse_stmt(*initializer);
                 let condition = condition
-                    .map(|e| Box::new(self.expr(*e).truthy(&mut self.analyzer.error_handler)));
-                let post_loop = post_loop.map(|e| Box::new(self.expr(*e)));
-                let body = self.parse_stmt(*body);
+                    .map(|e| -> &mut _ { self.analyzer.arena.expr(self.condition(*e, true)) });
+                let post_loop =
+                    post_loop.map(|e| -> &mut _ { self.analyzer.arena.expr(self.expr(*e)) });
+                let body = self.loop_body(*body);
                 self.leave_scope(stmt.location);
-                S::For(Box::new(initializer), condition, post_loop, Box::new(body))
+                S::For(
+                    self.analyzer.arena.stmt(initializer),
+                    condition,
+                    post_loop,
+                    self.analyzer.arena.stmt(body),
+                )
             }
             // 6.8.1 Labeled statements
-            // TODO: all of these should have semantic checking here, not in the backend
             Label(name, inner) => {
+                // 6.2.1p3: labels have function scope, so they can't be shadowed by an inner block
+                if let Some(&previous) = self.labels.get(&name) {
+                    self.err(
+                        SemanticError::LabelRedeclaration(name, previous),
+                        stmt.location,
+                    );
+                } else {
+                    self.labels.insert(name, stmt.location);
+                }
                 let inner = self.parse_stmt(*inner);
-                S::Label(name, Box::new(inner))
+                S::Label(name, self.analyzer.arena.stmt(inner))
             }
             Case(expr, inner) => self.case_statement(*expr, *inner, stmt.location),
             // 6.8.1 Labeled statements
-            Default(inner) => S::Default(Box::new(self.parse_stmt(*inner))),
+            Default(inner) => {
+                if let Some(switch) = self.switches.last_mut() {
+                    if let Some(previous) = switch.default {
+                        let err = SemanticError::DuplicateDefault(Some(previous));
+                        self.err(err, stmt.location);
+                    } else {
+                        switch.default = Some(stmt.location);
+                    }
+                } else {
+                    let err = SemanticError::CaseOutsideSwitch { is_default: true };
+                    self.err(err, stmt.location);
+                }
+                S::Default(self.analyzer.arena.stmt(self.parse_stmt(*inner)))
+            }
             // 6.8.6.1 The goto statement
-            Goto(label) => S::Goto(label),
+            Goto(label) => {
+                self.gotos.push(stmt.location.with(label));
+                S::Goto(label)
+            }
             // 6.8.6.2 The continue statement
-            Continue => S::Continue,
+            // > A continue statement shall appear only in or as a loop body.
+            Continue => {
+                if self.loops == 0 {
+                    self.err(SemanticError::ContinueOutsideLoop, stmt.location);
+                }
+                S::Continue
+            }
             // 6.8.6.3 The break statement
-            Break => S::Break,
+            // > A break statement shall appear only in or as a switch body or loop body.
+            Break => {
+                if self.loops == 0 && self.switches.is_empty() {
+                    self.err(SemanticError::BreakOutsideLoop, stmt.location);
+                }
+                S::Break
+            }
+            // only used for `-Wimplicit-fallthrough`, see `switch_body`
+            Fallthrough => S::default(),
             Return(value) => self.return_statement(value, stmt.location),
             // 6.7 Declarations
             Decl(decls) => S::Decl(self.analyzer.parse_declaration(decls, stmt.location)),
@@ -108,43 +239,175 @@ impl FunctionAnalyzer<'_> {
         };
         Locatable::new(data, stmt.location)
     }
+    /// Before C99, all declarations in a block had to come before any statements.
+    pub(super) fn check_declarations_first(&mut self, stmts: &[ast::Stmt]) {
+        if self.analyzer.standard.version >= Version::C99 {
+            return;
+        }
+        let is_decl = |stmt: &ast::Stmt| matches!(stmt.data, ast::StmtType::Decl(_));
+        let late_decl = stmts
+            .iter()
+            .skip_while(|stmt| is_decl(stmt))
+            .find(|stmt| is_decl(stmt));
+        if let Some(decl) = late_decl {
+            self.analyzer
+                .c99_feature("declarations after statements", decl.location);
+        }
+    }
+    /// The body of a `switch` statement.
+    ///
+    /// This warns if a `case` with statements falls through to the next label,
+    /// unless it ends with `__attribute__((fallthrough));`.
+    fn switch_body(&mut self, body: ast::Stmt) -> Stmt<'hir> {
+        let stmts = match body.data {
+            ast::StmtType::Compound(stmts) => stmts,
+            _ => return self.parse_stmt(body),
+        };
+        self.check_declarations_first(&stmts);
+        self.enter_scope();
+        let mut parsed: Vec<Stmt<'hir>> = Vec::new();
+        let mut seen_label = false;
+        let mut marked_fallthrough = false;
+        for stmt in stmts {
+            if let ast::StmtType::Case(..) | ast::StmtType::Default(_) = stmt.data {
+                // `case 1: case 2:` is parsed as `Case(1, Case(2))`, so it's never seen here
+                let falls_through = parsed
+                    .last()
+                    .is_some_and(|prev| prev.data.falls_through() && !prev.data.is_empty_case());
+                if seen_label && falls_through && !marked_fallthrough {
+                    self.analyzer
+                        .warn(Warning::ImplicitFallthrough, stmt.location);
+                }
+                seen_label = true;
+            }
+            marked_fallthrough = stmt.data.ends_with_fallthrough();
+            self.check_reachable(parsed.last(), &stmt);
+            parsed.push(self.parse_stmt(stmt));
+        }
+        self.leave_scope(body.location);
+        Locatable::new(StmtType::Compound(parsed), body.location)
+    }
+    /// The statements of a compound statement or function body, in the current scope.
+    pub(super) fn block_items(&mut self, stmts: Vec<ast::Stmt>) -> Vec<Stmt<'hir>> {
+        let mut parsed = Vec::new();
+        for stmt in stmts {
+            self.check_reachable(parsed.last(), &stmt);
+            parsed.push(self.parse_stmt(stmt));
+        }
+        parsed
+    }
+    /// Warn if `next` can never run because `previous` called a function that doesn't return.
+    ///
+    /// Labels can still be reached by a jump, so they aren't reported.
+    fn check_reachable(&mut self, previous: Option<&Stmt<'hir>>, next: &ast::Stmt) {
+        use ast::StmtType::*;
+
+        let after_noreturn = match previous.map(|prev| &prev.data) {
+            Some(StmtType::Expr(expr)) => expr.is_noreturn_call(),
+            _ => false,
+        };
+        let reported = match &next.data {
+            Label(..) | Case(..) | Default(_) | Decl(_) | Break | Return(_) => false,
+            // `exit(1);;`
+            Compound(stmts) => !stmts.is_empty(),
+            _ => true,
+        };
+        if after_noreturn && reported {
+            self.analyzer.warn(Warning::UnreachableCode, next.location);
+        }
+    }
+    /// Check that every `goto` in the function refers to a label that was declared.
+    ///
+    /// This has to wait until the end of the function, since `goto` can jump forward.
+    pub(super) fn check_gotos(&mut self) {
+        for goto in std::mem::take(&mut self.gotos) {
+            if !self.labels.contains_key(&goto.data) {
+                self.err(SemanticError::UndeclaredLabel(goto.data), goto.location);
+            }
+        }
+    }
+    /// The body of a `while`, `do`, or `for` loop, where `break` and `continue` are allowed.
+    fn loop_body(&mut self, body: ast::Stmt) -> Stmt<'hir> {
+        self.loops += 1;
+        let body = self.parse_stmt(body);
+        self.loops -= 1;
+        body
+    }
     // 6.8.1 Labeled statements
     fn case_statement(
         &mut self,
         expr: ast::Expr,
         inner: ast::Stmt,
         location: Location,
-    ) -> StmtType {
-        use super::expr::literal;
-        use crate::data::lex::Literal;
-
-        let expr = match self.expr(expr).const_fold() {
-            Ok(e) => e,
-            Err(err) => {
-                self.analyzer.error_handler.push_back(err);
-                Expr::zero(location)
+    ) -> StmtType<'hir> {
+        if self.switches.is_empty() {
+            let err = SemanticError::CaseOutsideSwitch { is_default: false };
+            self.err(err, location);
+        }
+        let expr = self.expr(expr);
+        let value = match const_eval(&expr, &Target::host()) {
+            Ok(ConstValue::Int(i)) => Some(i128::from(i)),
+            Ok(ConstValue::Uint(u)) => Some(i128::from(u)),
+            Ok(_) => {
+                self.err(SemanticError::NonIntegralExpr(expr.ctype), location);
+                None
             }
-        };
-        let int = match expr.into_literal() {
-            Ok(Literal::Int(i)) => i as u64,
-            Ok(Literal::UnsignedInt(u)) => u,
-            Ok(Literal::Char(c)) => c.into(),
-            Ok(other) => {
-                let ctype = literal(other, location).ctype;
-                self.err(SemanticError::NonIntegralExpr(ctype), location);
-                0
-            }
-            Err(other) => {
-                self.err(SemanticError::NotConstant(other), location);
-                0
+            Err(err) => {
+                self.analyzer
+                    .error_handler
+                    .push_back(CompileError::from(err));
+                None
             }
         };
+        let int = value.map_or(0, |value| self.check_case_value(value, location) as u64);
         let inner = self.parse_stmt(inner);
-        StmtType::Case(int, Box::new(inner))
+        StmtType::Case(int, self.analyzer.arena.stmt(inner))
+    }
+    /// Check a `case` label against the others in the same `switch`.
+    ///
+    /// 6.8.4.2p5: case values are converted to the promoted type of the controlling expression,
+    /// so `case 1:` and `case 1L:` are duplicates.
+    ///
+    /// Returns the converted value, which is what the backend compares against.
+    fn check_case_value(&mut self, value: i128, location: Location) -> i128 {
+        let switch = match self.switches.last_mut() {
+            Some(switch) => switch,
+            // reported by `case_statement`
+            None => return value,
+        };
+        if !switch.ctype.is_integral() {
+            return value;
+        }
+        let promoted = switch.ctype.clone().integer_promote();
+        let converted = convert_integer(value, &promoted);
+        if let Some(previous) = switch.cases.get(&converted) {
+            let err = SemanticError::DuplicateCase {
+                value: converted,
+                previous: Some(*previous),
+            };
+            self.err(err, location);
+            return converted;
+        }
+        switch.cases.insert(converted, location);
+        // `switch ((char)x) { case 300: }` can never match, even though 300 fits in an `int`
+        if convert_integer(converted, &switch.ctype) != converted {
+            let warning = Warning::CaseOutOfRange(converted, switch.ctype.clone());
+            self.analyzer.warn(warning, location);
+        }
+        converted
+    }
+    /// Whether control can reach the end of `stmts` without a jump.
+    ///
+    /// This is deliberately conservative so that it agrees with the backend, which rejects code after a `return`.
+    /// The only loops assumed not to terminate are ones with a constant condition and no `break`,
+    /// and the only calls assumed not to return are to `_Noreturn` functions.
+    /// A `switch` can only be left by falling through if it has no `default` or contains a `break`.
+    pub(super) fn falls_through(stmts: &[Stmt<'hir>]) -> bool {
+        stmts.last().is_none_or(|stmt| stmt.data.falls_through())
     }
     // 6.8.6.4 The return statement
     // A value of `None` for `expr` means `return;`
-    fn return_statement(&mut self, expr: Option<ast::Expr>, location: Location) -> StmtType {
+    fn return_statement(&mut self, expr: Option<ast::Expr>, location: Location) -> StmtType<'hir> {
         use crate::data::Type;
 
         let expr = expr.map(|e| self.expr(e));
@@ -170,28 +433,202 @@ impl FunctionAnalyzer<'_> {
             }
             // int f() { return 1; }
             (Some(expr), true) => {
-                let expr = expr.rval();
-                if expr.ctype != *ret_type {
-                    StmtType::Return(Some(
-                        expr.implicit_cast(ret_type, &mut self.analyzer.error_handler),
-                    ))
-                } else {
-                    StmtType::Return(Some(expr))
-                }
+                // 6.8.6.4p3: the value is converted as if by assignment
+                let expr = expr.assignment_conversion(
+                    ret_type,
+                    self.metadata.return_qualifiers.c_const,
+                    self.analyzer.arena,
+                    &mut self.analyzer.error_handler,
+                );
+                StmtType::Return(Some(expr))
             }
         }
     }
 }
 
+impl<'hir> StmtType<'hir> {
+    fn falls_through(&self) -> bool {
+        match self {
+            StmtType::Return(_) | StmtType::Goto(_) | StmtType::Break | StmtType::Continue => false,
+            StmtType::Compound(stmts) => FunctionAnalyzer::falls_through(stmts),
+            StmtType::If(_, body, Some(otherwise)) => {
+                body.data.falls_through() || otherwise.data.falls_through()
+            }
+            StmtType::Label(_, inner) | StmtType::Case(_, inner) | StmtType::Default(inner) => {
+                inner.data.falls_through()
+            }
+            // `exit(1);`
+            StmtType::Expr(expr) => !expr.is_noreturn_call(),
+            // `while (1) {}` or `for (;;) {}`
+            StmtType::While(condition, body) => {
+                !condition.is_constant_true() || body.data.contains_break()
+            }
+            StmtType::For(_, condition, _, body) => {
+                condition
+                    .as_ref()
+                    .is_some_and(|condition| !condition.is_constant_true())
+                    || body.data.contains_break()
+            }
+            // `do { return x; } while (c);` never evaluates `c`
+            StmtType::Do(body, condition) => {
+                let reaches_condition = body.data.falls_through() || body.data.contains_continue();
+                (reaches_condition && !condition.is_constant_true()) || body.data.contains_break()
+            }
+            // `switch (x) { case 1: return 1; default: return 0; }`
+            StmtType::Switch(_, body) => {
+                !body.data.contains_default()
+                    || body.data.contains_break()
+                    || body.data.falls_through()
+            }
+            _ => true,
+        }
+    }
+    /// Whether this contains a `continue` for the loop this statement is in.
+    ///
+    /// `continue`s in nested loops don't count, but ones in nested `switch`es do.
+    fn contains_continue(&self) -> bool {
+        match self {
+            StmtType::Continue => true,
+            StmtType::Compound(stmts) => stmts.iter().any(|stmt| stmt.data.contains_continue()),
+            StmtType::If(_, body, otherwise) => {
+                body.data.contains_continue()
+                    || otherwise
+                        .as_ref()
+                        .is_some_and(|otherwise| otherwise.data.contains_continue())
+            }
+            StmtType::Label(_, inner)
+            | StmtType::Case(_, inner)
+            | StmtType::Default(inner)
+            | StmtType::Switch(_, inner) => inner.data.contains_continue(),
+            _ => false,
+        }
+    }
+    /// Whether this contains a `default` label for the `switch` this statement is in.
+    ///
+    /// `default`s in nested `switch`es don't count, but ones in nested loops do.
+    fn contains_default(&self) -> bool {
+        match self {
+            StmtType::Default(_) => true,
+            StmtType::Compound(stmts) => stmts.iter().any(|stmt| stmt.data.contains_default()),
+            StmtType::If(_, body, otherwise) => {
+                body.data.contains_default()
+                    || otherwise
+                        .as_ref()
+                        .is_some_and(|otherwise| otherwise.data.contains_default())
+            }
+            StmtType::Label(_, inner)
+            | StmtType::Case(_, inner)
+            | StmtType::Do(inner, _)
+            | StmtType::While(_, inner)
+            | StmtType::For(_, _, _, inner) => inner.data.contains_default(),
+            _ => false,
+        }
+    }
+    /// Whether this contains a `break` that would leave this statement.
+    ///
+    /// `break`s in nested loops and `switch`es don't count.
+    fn contains_break(&self) -> bool {
+        match self {
+            StmtType::Break => true,
+            StmtType::Compound(stmts) => stmts.iter().any(|stmt| stmt.data.contains_break()),
+            StmtType::If(_, body, otherwise) => {
+                body.data.contains_break()
+                    || otherwise
+                        .as_ref()
+                        .is_some_and(|otherwise| otherwise.data.contains_break())
+            }
+            StmtType::Label(_, inner) | StmtType::Case(_, inner) | StmtType::Default(inner) => {
+                inner.data.contains_break()
+            }
+            _ => false,
+        }
+    }
+    /// `case 1: ;` or `default: {}`
+    fn is_empty_case(&self) -> bool {
+        match self {
+            StmtType::Compound(stmts) => stmts.is_empty(),
+            StmtType::Label(_, inner) | StmtType::Case(_, inner) | StmtType::Default(inner) => {
+                inner.data.is_empty_case()
+            }
+            _ => false,
+        }
+    }
+}
+
+impl<'hir> Expr<'hir> {
+    /// Whether this is a call to a function which never returns, like `exit`.
+    pub(super) fn is_noreturn_call(&self) -> bool {
+        match &self.expr {
+            ExprType::FuncCall(func, _) => match &func.expr {
+                ExprType::Id(symbol) => symbol.get().qualifiers.func.no_return,
+                _ => false,
+            },
+            ExprType::Cast(inner) | ExprType::Noop(inner) => inner.is_noreturn_call(),
+            _ => false,
+        }
+    }
+    /// Whether this is an expression statement that was probably meant to do something, like `x == 1;`.
+    ///
+    /// Casting to `void` is the usual way to say the value is deliberately ignored,
+    /// so `(void)x;` doesn't count.
+    fn has_no_effect(&self) -> bool {
+        match &self.expr {
+            ExprType::Binary(op, _, _) => !matches!(
+                op,
+                BinaryOp::Assign | BinaryOp::LogicalAnd | BinaryOp::LogicalOr
+            ),
+            // `f(), x;`, but not `x, f();`
+            ExprType::Comma(_, right) => right.has_no_effect(),
+            ExprType::Cast(inner) => self.ctype != Type::Void && inner.has_no_effect(),
+            ExprType::Negate(_) | ExprType::BitwiseNot(_) => true,
+            // reading a `volatile` variable counts as a side effect
+            ExprType::Id(_) | ExprType::Literal(_) | ExprType::Sizeof(_) => !self.is_volatile(),
+            // converting a variable to an rvalue, as in `(int)x;`
+            ExprType::Deref(inner) if matches!(inner.expr, ExprType::Id(_)) => !inner.is_volatile(),
+            _ => false,
+        }
+    }
+    /// Whether this is a constant that's always true, like the `1` in `while (1)`.
+    fn is_constant_true(&self) -> bool {
+        use crate::data::lex::Literal;
+
+        let scratch = Arena::new();
+        match self
+            .clone_in(&scratch)
+            .const_fold()
+            .map(|folded| folded.expr)
+        {
+            Ok(ExprType::Literal(Literal::Float(f))) => f != 0.0,
+            Ok(ExprType::Literal(literal)) => !literal.is_zero(),
+            _ => false,
+        }
+    }
+}
+
+impl ast::StmtType {
+    /// Whether this statement is, or ends with, `__attribute__((fallthrough));`
+    fn ends_with_fallthrough(&self) -> bool {
+        use ast::StmtType::*;
+        match self {
+            Fallthrough => true,
+            Compound(stmts) => stmts
+                .last()
+                .is_some_and(|stmt| stmt.data.ends_with_fallthrough()),
+            Label(_, inner) | Case(_, inner) | Default(inner) => inner.data.ends_with_fallthrough(),
+            _ => false,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::analyze::test::{analyze, analyze_expr};
+    use crate::analyze::test::{analyze, analyze_expr, decls};
     use crate::analyze::FunctionData;
     use crate::data::*;
     use crate::Parser;
 
-    fn parse_stmt(stmt: &str) -> CompileResult<Stmt> {
+    fn parse_stmt(stmt: &str) -> CompileResult<Stmt<'static>> {
         analyze(stmt, Parser::statement, |a, stmt| {
             let mut func_analyzer = FunctionAnalyzer {
                 analyzer: a,
@@ -199,7 +636,13 @@ mod tests {
                     id: "<test func>".into(),
                     location: Location::default(),
                     return_type: Type::Int(true),
+                    return_qualifiers: Qualifiers::NONE,
+                    params: Vec::new(),
                 },
+                switches: Vec::new(),
+                loops: 0,
+                labels: HashMap::new(),
+                gotos: Vec::new(),
             };
             func_analyzer.parse_stmt(stmt)
         })
@@ -212,11 +655,127 @@ mod tests {
         let expected = Ok(Stmt {
             data: StmtType::Expr(analyze_expr("1").unwrap()),
             location: Location {
-                file: Location::default().file,
                 span: (0..2).into(),
+                ..Location::default()
             },
         });
         assert_eq!(parsed, expected);
         assert_eq!(parsed.unwrap().location, expected.unwrap().location);
     }
+    #[test]
+    fn test_jump_placement() {
+        let err = |stmt: &str| parse_stmt(stmt).unwrap_err().data;
+        assert_eq!(err("break;"), SemanticError::BreakOutsideLoop.into());
+        assert_eq!(err("continue;"), SemanticError::ContinueOutsideLoop.into());
+        assert_eq!(
+            err("switch (1) { case 1: continue; }"),
+            SemanticError::ContinueOutsideLoop.into()
+        );
+        assert_eq!(
+            err("case 1: ;"),
+            SemanticError::CaseOutsideSwitch { is_default: false }.into()
+        );
+        assert_eq!(
+            err("default: ;"),
+            SemanticError::CaseOutsideSwitch { is_default: true }.into()
+        );
+        assert!(matches!(
+            err("switch (1) { default: ; default: ; }"),
+            Error::Semantic(SemanticError::DuplicateDefault(_))
+        ));
+        assert_eq!(
+            SemanticError::ContinueOutsideLoop.to_string(),
+            "'continue' statement not in a loop"
+        );
+        for stmt in &[
+            "while (1) break;",
+            "do continue; while (0);",
+            "for (;;) { if (1) continue; break; }",
+            "switch (1) { case 1: break; }",
+            "while (1) switch (1) { case 1: continue; default: break; }",
+            // Duff's device: a case label can be inside a loop inside the switch
+            "switch (1) { case 0: do { case 1: ; } while (0); }",
+        ] {
+            assert!(parse_stmt(stmt).is_ok(), "{}", stmt);
+        }
+    }
+    #[test]
+    fn test_labels() {
+        let errors = |program: &str| -> Vec<_> {
+            decls(program)
+                .into_iter()
+                .filter_map(Result::err)
+                .map(|err| err.data)
+                .collect()
+        };
+        // labels are per-function
+        assert_eq!(
+            errors("void f(void) { goto end; end: ; } void g(void) { goto end; end: ; }"),
+            vec![]
+        );
+        assert_eq!(
+            errors("void f(void) { goto end; } void g(void) { end: ; }"),
+            vec![SemanticError::UndeclaredLabel("end".into()).into()]
+        );
+        // labels have function scope, even in an inner block
+        let duplicate = errors("void f(void) { end: ; { end: ; } }");
+        assert_eq!(duplicate.len(), 1);
+        assert!(matches!(
+            &duplicate[0],
+            Error::Semantic(SemanticError::LabelRedeclaration(name, _)) if *name == "end".into()
+        ));
+        // jumping past an initializer is allowed
+        assert_eq!(
+            errors("int f(void) { goto inside; { int x = 1; inside: return x; } }"),
+            vec![]
+        );
+    }
+    fn return_value(func: &str) -> Expr<'static> {
+        let func = decls(func).pop().expect("expected a declaration").unwrap();
+        let body = match func.init {
+            Some(Initializer::FunctionBody(body)) => body,
+            other => panic!("expected a function body, got {:?}", other),
+        };
+        match &body[0].data {
+            StmtType::Return(Some(expr)) => expr.clone_in(crate::analyze::test::arena()),
+            other => panic!("expected a return statement, got {:?}", other),
+        }
+    }
+    #[test]
+    fn test_return_conversion() {
+        // converted to the return type, like an assignment
+        let double = return_value("double f(void) { return 1; }");
+        assert_eq!(double.ctype, Type::Double);
+        assert!(matches!(double.expr, ExprType::Cast(_)));
+        let null = return_value("int *f(void) { return 0; }");
+        assert_eq!(
+            null.ctype,
+            Type::Pointer(Box::new(Type::Int(true)), Qualifiers::NONE)
+        );
+        assert!(matches!(null.expr, ExprType::Cast(_)));
+        // structs must be the same type
+        let same = return_value("struct s { int i; } x; struct s f(void) { return x; }");
+        assert!(matches!(same.ctype, Type::Struct(_)));
+
+        // the error is the same as for assignment, and points to the returned expression
+        let errors = |program: &str| -> Vec<_> {
+            decls(program)
+                .into_iter()
+                .filter_map(Result::err)
+                .map(|err| (err.data.to_string(), err.location.span))
+                .collect()
+        };
+        let returned = errors("int f(int *p) { return p; }");
+        let assigned = errors("int f(int *p) { int i; i = p; }");
+        assert_eq!(returned.len(), 1);
+        assert_eq!(returned[0].0, assigned[0].0);
+        assert_eq!(returned[0].1, (23..24).into());
+        let mismatch =
+            errors("struct a { int x; } a; struct b { int x; } b; struct a f(void) { return b; }");
+        assert_eq!(mismatch.len(), 1);
+        assert_eq!(
+            mismatch[0].0,
+            "invalid program: assigning to 'struct a' from incompatible type 'struct b'"
+        );
+    }
 }
@@ -0,0 +1,444 @@
+//! Warnings for reading local variables before they're assigned (`-Wuninitialized`)
+//!
+//! This is a forward dataflow analysis over the HIR of a single function.
+//! Loops and `goto` are handled by iterating until the state stops changing.
+
+use std::collections::{HashMap, HashSet};
+
+use super::PureAnalyzer;
+use crate::data::{error::Warning, hir::*, Location, StorageClass};
+use crate::intern::InternedStr;
+
+/// Whether a local variable has been assigned at some point in the function.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Init {
+    Unassigned,
+    /// Assigned on some paths, but not all
+    Maybe,
+    Definite,
+}
+
+impl Init {
+    fn join(self, other: Init) -> Init {
+        if self == other {
+            self
+        } else {
+            Init::Maybe
+        }
+    }
+}
+
+/// The assignment state of every tracked local, or `None` if this point is unreachable.
+type State = Option<HashMap<Symbol, Init>>;
+
+/// The state at a point reachable from both `left` and `right`.
+fn join(left: State, right: State) -> State {
+    match (left, right) {
+        (None, state) | (state, None) => state,
+        // a variable only tracked on one side was declared after the other side jumped here,
+        // so it hasn't been assigned on that path: `goto inside; { int x = 1; inside: return x; }`
+        (Some(mut left), Some(mut right)) => {
+            for (symbol, init) in left.iter_mut() {
+                let other = right.remove(symbol).unwrap_or(Init::Unassigned);
+                *init = init.join(other);
+            }
+            for (symbol, init) in right {
+                left.insert(symbol, init.join(Init::Unassigned));
+            }
+            Some(left)
+        }
+    }
+}
+
+fn join_into(target: &mut State, other: State) {
+    *target = join(target.take(), other);
+}
+
+impl PureAnalyzer<'_> {
+    /// Warn about reads of local variables which may not have been assigned yet.
+    pub(super) fn check_uninitialized(&mut self, body: &[Stmt]) {
+        let mut checker = Checker::default();
+        // `goto` can jump backwards, so iterate until the state at every label is known
+        loop {
+            let labels = checker.labels.clone();
+            checker.stmts(body, &mut Some(HashMap::new()));
+            if checker.labels == labels {
+                break;
+            }
+        }
+        let mut reads: Vec<_> = checker.reads.into_iter().map(|(_, read)| read).collect();
+        reads.sort_by_key(|(_, location, _)| location.span);
+        // only warn once per variable
+        let mut warned = HashSet::new();
+        for (symbol, location, init) in reads {
+            if init == Init::Definite || !warned.insert(symbol) {
+                continue;
+            }
+            let name = symbol.get().id;
+            let warning = if init == Init::Unassigned {
+                Warning::Uninitialized(name)
+            } else {
+                Warning::MaybeUninitialized(name)
+            };
+            self.warn(warning, location);
+        }
+    }
+}
+
+#[derive(Default)]
+struct Checker<'hir> {
+    /// The state of the variable at each read, from the last time the read was visited.
+    ///
+    /// Since states only become less certain with each iteration,
+    /// the last visit is the one that counts.
+    /// The HIR isn't modified while it's being checked, so expressions are identified by address.
+    reads: HashMap<*const Expr<'hir>, (Symbol, Location, Init)>,
+    /// The state at the start of each loop body the last time the loop was checked.
+    ///
+    /// Starting from here instead of from scratch keeps nested loops from taking exponential time.
+    loop_entries: HashMap<*const Stmt<'hir>, State>,
+    /// The joined state at each `break` in each enclosing loop or `switch`
+    breaks: Vec<State>,
+    /// The joined state at each `continue` in each enclosing loop
+    continues: Vec<State>,
+    /// The state at the start of each enclosing `switch`, and whether it has a `default` label
+    switches: Vec<(State, bool)>,
+    /// The joined state at each `goto` for each label
+    labels: HashMap<InternedStr, State>,
+    /// Every local whose declaration has been seen, even if it was unreachable.
+    ///
+    /// A tracked variable that isn't in the state was declared after a `goto` that skipped it.
+    tracked: HashSet<Symbol>,
+}
+
+impl<'hir> Checker<'hir> {
+    fn stmts(&mut self, stmts: &[Stmt<'hir>], state: &mut State) {
+        for stmt in stmts {
+            self.stmt(stmt, state);
+        }
+    }
+    fn stmt(&mut self, stmt: &Stmt<'hir>, state: &mut State) {
+        match &stmt.data {
+            StmtType::Compound(stmts) => self.stmts(stmts, state),
+            StmtType::Expr(expr) => self.expr(expr, state),
+            StmtType::Decl(decls) => {
+                for decl in decls {
+                    self.decl(&decl.data, state);
+                }
+            }
+            StmtType::If(condition, body, otherwise) => {
+                self.expr(condition, state);
+                let mut otherwise_state = state.clone();
+                self.stmt(body, state);
+                if let Some(otherwise) = otherwise {
+                    self.stmt(otherwise, &mut otherwise_state);
+                }
+                join_into(state, otherwise_state);
+            }
+            StmtType::While(condition, body) => {
+                self.loop_stmt(Some(condition), body, None, state);
+            }
+            StmtType::For(init, condition, post, body) => {
+                self.stmt(init, state);
+                self.loop_stmt(condition.as_deref(), body, post.as_deref(), state);
+            }
+            StmtType::Do(body, condition) => self.do_loop(body, condition, state),
+            StmtType::Switch(condition, body) => {
+                self.expr(condition, state);
+                self.switches.push((state.clone(), false));
+                self.breaks.push(None);
+                // the body can only be entered through a `case` or `default` label
+                let mut body_state = None;
+                self.stmt(body, &mut body_state);
+                let breaks = self.breaks.pop().unwrap();
+                let (_, has_default) = self.switches.pop().unwrap();
+                if !has_default {
+                    join_into(&mut body_state, state.take());
+                }
+                *state = join(body_state, breaks);
+            }
+            StmtType::Case(_, inner) | StmtType::Default(inner) => {
+                if let Some((start, has_default)) = self.switches.last_mut() {
+                    if let StmtType::Default(_) = stmt.data {
+                        *has_default = true;
+                    }
+                    join_into(state, start.clone());
+                }
+                self.stmt(inner, state);
+            }
+            StmtType::Label(name, inner) => {
+                if let Some(gotos) = self.labels.get(name) {
+                    join_into(state, gotos.clone());
+                }
+                self.stmt(inner, state);
+            }
+            StmtType::Goto(name) => {
+                let target = self.labels.entry(*name).or_insert(None);
+                join_into(target, state.take());
+            }
+            StmtType::Continue => {
+                if let Some(target) = self.continues.last_mut() {
+                    join_into(target, state.take());
+                }
+            }
+            StmtType::Break => {
+                if let Some(target) = self.breaks.last_mut() {
+                    join_into(target, state.take());
+                }
+            }
+            StmtType::Return(expr) => {
+                if let Some(expr) = expr {
+                    self.expr(expr, state);
+                }
+                *state = None;
+            }
+        }
+    }
+    /// `while (condition) body` or `for (; condition; post) body`
+    fn loop_stmt(
+        &mut self,
+        condition: Option<&Expr<'hir>>,
+        body: &Stmt<'hir>,
+        post: Option<&Expr<'hir>>,
+        state: &mut State,
+    ) {
+        let once = |this: &mut Self, entry: State| {
+            let mut after_condition = entry;
+            if let Some(condition) = condition {
+                this.expr(condition, &mut after_condition);
+            }
+            this.breaks.push(None);
+            this.continues.push(None);
+            let mut end = after_condition.clone();
+            this.stmt(body, &mut end);
+            join_into(&mut end, this.continues.pop().unwrap());
+            if let Some(post) = post {
+                this.expr(post, &mut end);
+            }
+            let breaks = this.breaks.pop().unwrap();
+            // `for (;;)` can only be left with `break`
+            let exit = if condition.is_some() {
+                join(after_condition, breaks)
+            } else {
+                breaks
+            };
+            (exit, end)
+        };
+        *state = self.fixpoint(body, state.take(), once);
+    }
+    /// `do body while (condition);`
+    fn do_loop(&mut self, body: &Stmt<'hir>, condition: &Expr<'hir>, state: &mut State) {
+        let once = |this: &mut Self, mut entry: State| {
+            this.breaks.push(None);
+            this.continues.push(None);
+            this.stmt(body, &mut entry);
+            join_into(&mut entry, this.continues.pop().unwrap());
+            this.expr(condition, &mut entry);
+            let breaks = this.breaks.pop().unwrap();
+            (join(entry.clone(), breaks), entry)
+        };
+        *state = self.fixpoint(body, state.take(), once);
+    }
+    /// Run a loop until the state at the start of the body stops changing.
+    ///
+    /// `once` runs the loop a single time and returns the state after the loop
+    /// and the state when jumping back to the start.
+    fn fixpoint<F>(&mut self, body: &Stmt<'hir>, start: State, mut once: F) -> State
+    where
+        F: FnMut(&mut Self, State) -> (State, State),
+    {
+        let previous = self.loop_entries.get(&(body as *const _)).cloned();
+        let mut entry = join(start.clone(), previous.flatten());
+        loop {
+            let (exit, back) = once(self, entry.clone());
+            let next = join(start.clone(), back);
+            if next == entry {
+                self.loop_entries.insert(body, entry);
+                return exit;
+            }
+            entry = next;
+        }
+    }
+    fn decl(&mut self, decl: &Declaration<'hir>, state: &mut State) {
+        let tracked = {
+            let meta = decl.symbol.get();
+            (meta.storage_class == StorageClass::Auto
+                || meta.storage_class == StorageClass::Register)
+                && meta.ctype.is_scalar()
+        };
+        if tracked {
+            self.tracked.insert(decl.symbol);
+            if let Some(map) = state {
+                map.insert(decl.symbol, Init::Unassigned);
+            }
+        }
+        if let Some(init) = &decl.init {
+            self.initializer(init, state);
+            self.assign(decl.symbol, state);
+        }
+    }
+    fn initializer(&mut self, init: &Initializer<'hir>, state: &mut State) {
+        match init {
+            Initializer::Scalar(expr) => self.expr(expr, state),
+            Initializer::InitializerList(inits) => {
+                for init in inits {
+                    self.initializer(init, state);
+                }
+            }
+            Initializer::FunctionBody(_) => {}
+        }
+    }
+    fn expr(&mut self, expr: &Expr<'hir>, state: &mut State) {
+        match &expr.expr {
+            // the address of `symbol`, which could be used to assign it
+            ExprType::Id(symbol) => self.assign(*symbol, state),
+            ExprType::Deref(inner) => match &inner.expr {
+                ExprType::Id(symbol) => self.read(*symbol, expr, state),
+                _ => self.expr(inner, state),
+            },
+            ExprType::Binary(BinaryOp::Assign, target, value) => {
+                self.expr(value, state);
+                self.expr(target, state);
+            }
+            ExprType::Binary(BinaryOp::LogicalAnd, left, right)
+            | ExprType::Binary(BinaryOp::LogicalOr, left, right) => {
+                self.expr(left, state);
+                let mut right_state = state.clone();
+                self.expr(right, &mut right_state);
+                join_into(state, right_state);
+            }
+            ExprType::Binary(_, left, right) | ExprType::Comma(left, right) => {
+                self.expr(left, state);
+                self.expr(right, state);
+            }
+            ExprType::Ternary(condition, then, otherwise) => {
+                self.expr(condition, state);
+                let mut otherwise_state = state.clone();
+                self.expr(then, state);
+                self.expr(otherwise, &mut otherwise_state);
+                join_into(state, otherwise_state);
+            }
+            ExprType::Let(_, init, body) => {
+                // `x += 1` stores the address of `x`, but it also reads the value
+                if let ExprType::Id(symbol) = init.expr {
+                    self.read(symbol, init, state);
+                }
+                self.expr(init, state);
+                self.expr(body, state);
+            }
+            ExprType::FuncCall(func, args) => {
+                self.expr(func, state);
+                for arg in args {
+                    self.expr(arg, state);
+                }
+                // nothing after `exit(1)` is reachable
+                if expr.is_noreturn_call() {
+                    *state = None;
+                }
+            }
+            ExprType::Member(inner, _)
+            | ExprType::Cast(inner)
+            | ExprType::Negate(inner)
+            | ExprType::BitwiseNot(inner)
+            | ExprType::StaticRef(inner)
+            | ExprType::Noop(inner) => self.expr(inner, state),
+            ExprType::Literal(_) | ExprType::Sizeof(_) => {}
+        }
+    }
+    fn read(&mut self, symbol: Symbol, expr: &Expr<'hir>, state: &State) {
+        if let (Some(map), true) = (state, self.tracked.contains(&symbol)) {
+            let init = map.get(&symbol).copied().unwrap_or(Init::Unassigned);
+            self.reads.insert(expr, (symbol, expr.location, init));
+        }
+    }
+    fn assign(&mut self, symbol: Symbol, state: &mut State) {
+        if let (Some(map), true) = (state, self.tracked.contains(&symbol)) {
+            map.insert(symbol, Init::Definite);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analyze::{test::arena, Analyzer};
+    use crate::data::error::Warning;
+    use crate::parse::test::parser;
+
+    fn uninitialized(body: &str) -> Vec<Warning> {
+        let program = format!(
+            "void g(int *); void abort(void); int f(int cond) {{ {} }}",
+            body
+        );
+        let mut a = Analyzer::new(parser(&program), arena(), false);
+        for res in &mut a {
+            res.unwrap();
+        }
+        a.inner
+            .error_handler
+            .warnings
+            .into_iter()
+            .map(|w| w.data)
+            .filter(|w| w.name().ends_with("uninitialized"))
+            .collect()
+    }
+
+    #[test]
+    fn test_uninitialized() {
+        let x = || "x".into();
+        assert_eq!(
+            uninitialized("int x; return x;"),
+            vec![Warning::Uninitialized(x())]
+        );
+        assert_eq!(
+            uninitialized("int x; if (cond) x = 1; return x;"),
+            vec![Warning::MaybeUninitialized(x())]
+        );
+        assert_eq!(
+            uninitialized("int x; while (cond) { cond = x; x = 1; } return 0;"),
+            vec![Warning::MaybeUninitialized(x())]
+        );
+        assert_eq!(
+            uninitialized(
+                "int x; switch (cond) { case 1: x = 1; break; case 2: x = 2; } return x;"
+            ),
+            vec![Warning::MaybeUninitialized(x())]
+        );
+        assert_eq!(
+            uninitialized("int x; again: if (cond) return x; x = 1; goto again;"),
+            vec![Warning::MaybeUninitialized(x())]
+        );
+        // the initializer is skipped by jumping into the block
+        assert_eq!(
+            uninitialized("goto inside; { int x = 1; inside: return x; }"),
+            vec![Warning::Uninitialized(x())]
+        );
+        assert_eq!(
+            uninitialized("if (cond) goto inside; { int x = 1; inside: return x; }"),
+            vec![Warning::MaybeUninitialized(x())]
+        );
+        // only warn once per variable
+        assert_eq!(
+            uninitialized("int x; cond = x; return x;"),
+            vec![Warning::Uninitialized(x())]
+        );
+        for initialized in &[
+            "int x = 1; return x;",
+            "int x; x = 1; return x;",
+            "int x; if (cond) x = 1; else x = 2; return x;",
+            "int x; g(&x); return x;",
+            "int x; int *p = &x; *p = 1; return x;",
+            "int x; for (;;) { x = 1; break; } return x;",
+            "int x; do { x = 1; } while (cond); return x;",
+            "int x; switch (cond) { case 1: x = 1; break; default: x = 2; } return x;",
+            "int x; if (cond) return 0; else x = 1; return x;",
+            "int x; if (cond) x = 1; else abort(); return x;",
+            "int x; goto assign; use: return x; assign: x = 1; goto use;",
+            "goto inside; { int x; inside: x = 1; return x; }",
+            "int x; cond && (x = 1); return 0;",
+            "static int x; return x;",
+            "return cond;",
+        ] {
+            assert_eq!(uninitialized(initialized), vec![], "{}", initialized);
+        }
+    }
+}
@@ -2,7 +2,7 @@
 
 use std::cmp::max;
 
-use target_lexicon::Triple;
+use target_lexicon::{Endianness, Triple};
 
 use crate::data::{
     types::{ArrayType, StructType},
@@ -25,6 +25,153 @@ const CHAR_SIZE: u16 = 1;
 /// information like ABI and endianness.
 pub(crate) const TARGET: Triple = Triple::host();
 
+/// The platform a program is compiled for.
+///
+/// All supported targets currently use the same data model (see `x64.rs`),
+/// so this only affects predefined macros and how static data is written.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Target {
+    triple: Triple,
+}
+
+impl Target {
+    /// The platform the compiler is running on.
+    pub fn host() -> Self {
+        Self::new(TARGET)
+    }
+    /// The platform described by `triple`.
+    pub fn new(triple: Triple) -> Self {
+        Target { triple }
+    }
+    /// The target triple, e.g. `x86_64-unknown-linux-gnu`.
+    pub fn triple(&self) -> &Triple {
+        &self.triple
+    }
+    /// The number of bits in a pointer.
+    pub fn pointer_bits(&self) -> u32 {
+        u32::from(PTR_SIZE * CHAR_BIT)
+    }
+}
+
+impl Default for Target {
+    fn default() -> Self {
+        Self::host()
+    }
+}
+
+/// Writes constants to memory using the byte order and pointer width of a target.
+///
+/// Everything that produces bytes for static data should go through this
+/// instead of calling `to_le_bytes` or `to_ne_bytes`,
+/// so that cross-compiling doesn't leak the byte order of the host.
+///
+/// Relocations (e.g. `int *p = &x;`) are written by cranelift,
+/// which already knows the byte order of the target.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(not(feature = "codegen"), allow(dead_code))]
+pub(crate) struct ArchData {
+    big_endian: bool,
+    ptr_size: u16,
+}
+
+#[cfg_attr(not(feature = "codegen"), allow(dead_code))]
+impl ArchData {
+    /// Get the data layout for `triple`.
+    ///
+    /// Panics if `triple` has an unknown byte order.
+    pub(crate) fn new(triple: &Triple) -> Self {
+        let endianness = triple
+            .endianness()
+            .expect("target should be big or little endian");
+        ArchData {
+            big_endian: endianness == Endianness::Big,
+            ptr_size: PTR_SIZE,
+        }
+    }
+    /// Write the lowest `size` bytes of `value` to the start of `buf`.
+    ///
+    /// Signed values should be sign-extended to 64 bits first.
+    pub(crate) fn write_int(&self, buf: &mut [u8], value: u64, size: usize) {
+        assert!(size <= 8, "integers can be at most 8 bytes");
+        if self.big_endian {
+            buf[..size].copy_from_slice(&value.to_be_bytes()[8 - size..]);
+        } else {
+            buf[..size].copy_from_slice(&value.to_le_bytes()[..size]);
+        }
+    }
+    /// Write `value` to the start of `buf` as a float of `size` bytes.
+    pub(crate) fn write_float(&self, buf: &mut [u8], value: f64, size: usize) {
+        match size {
+            4 => self.write_int(buf, (value as f32).to_bits().into(), size),
+            8 => self.write_int(buf, value.to_bits(), size),
+            _ => unreachable!("floats must be 4 or 8 bytes, got {}", size),
+        }
+    }
+    /// Write a pointer with the constant address `value` to the start of `buf`.
+    ///
+    /// For addresses only known at link time, this writes the placeholder the relocation fills in.
+    pub(crate) fn write_ptr(&self, buf: &mut [u8], value: u64) {
+        self.write_int(buf, value, self.ptr_size.into());
+    }
+    /// Convert a string literal to the bytes stored for it, including the null terminator.
+    ///
+    /// This is the only place string literals are converted,
+    /// so the analyzer, static initializers, and string constants in functions all agree.
+    /// If an element is too large for the element type, like `u"\x12345"`, returns that element.
+    ///
+    /// # Panics
+    /// If `literal` is not a string.
+    pub(crate) fn string_data(&self, literal: &Literal) -> Result<StringData, u32> {
+        let (encoding, units) = match literal {
+            // escapes were already checked by the lexer
+            Literal::Str(bytes) => {
+                return Ok(StringData {
+                    bytes: bytes.clone(),
+                    element_size: 1,
+                })
+            }
+            Literal::WideStr(encoding, units) => (encoding, units),
+            _ => unreachable!("only strings have string data, got {}", literal),
+        };
+        let element_size = encoding.element_type().sizeof().unwrap() as usize;
+        let max = u64::MAX >> (64 - 8 * element_size);
+        let mut bytes = vec![0; units.len() * element_size];
+        for (&unit, buf) in units.iter().zip(bytes.chunks_mut(element_size)) {
+            if u64::from(unit) > max {
+                return Err(unit);
+            }
+            self.write_int(buf, unit.into(), element_size);
+        }
+        Ok(StringData {
+            bytes,
+            element_size,
+        })
+    }
+}
+
+/// A string literal as it's stored in the program.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct StringData {
+    /// The elements in the byte order of the target
+    pub(crate) bytes: Vec<u8>,
+    /// The size of each element in bytes
+    pub(crate) element_size: usize,
+}
+
+impl Encoding {
+    /// The type of each element of a string with this prefix:
+    /// `wchar_t`, `char16_t`, or `char32_t`.
+    ///
+    /// These must match the typedefs in `stddef.h` and `uchar.h`.
+    pub(crate) fn element_type(self) -> Type {
+        match self {
+            Encoding::Wide => Int(true),
+            Encoding::Utf16 => Short(false),
+            Encoding::Utf32 => Int(false),
+        }
+    }
+}
+
 mod x64;
 pub(crate) use x64::*;
 
@@ -33,10 +180,15 @@ impl StructType {
     #[cfg_attr(not(feature = "codegen"), allow(dead_code))]
     pub(crate) fn offset(&self, member: InternedStr) -> u64 {
         let members = self.members();
-        let mut current_offset = 0;
+        let mut current_offset: u64 = 0;
         for formal in members.iter() {
             if formal.id == member {
-                return current_offset;
+                // the end of the previous member may not be aligned for this one
+                let align = formal
+                    .ctype
+                    .alignof()
+                    .expect("structs should have valid size and alignment");
+                return current_offset.div_ceil(align) * align;
             }
             current_offset = Self::next_offset(current_offset, &formal.ctype)
                 .expect("structs should have valid size and alignment");
@@ -57,6 +209,10 @@ impl StructType {
     /// Calculate the size of a struct: the sum of all member sizes
     pub(crate) fn struct_size(&self) -> Result<SIZE_T, &'static str> {
         let symbols = &self.members();
+        // `struct s;` without a definition
+        if symbols.is_empty() {
+            return Err("cannot take the size of an incomplete struct or union");
+        }
 
         symbols
             .iter()
@@ -137,6 +293,16 @@ impl Type {
             Error => Err("cannot take `sizeof` <type error>"),
         }
     }
+    /// The number of significant bits in a floating type, or `None` for other types.
+    ///
+    /// Integers with more significant bits than this can't be converted to the type exactly.
+    pub(crate) fn mantissa_bits(&self) -> Option<u32> {
+        match self {
+            Float => Some(FLOAT_MANTISSA),
+            Double => Some(DOUBLE_MANTISSA),
+            _ => None,
+        }
+    }
     /// Get the alignment of a type in bytes.
     pub fn alignof(&self) -> Result<SIZE_T, &'static str> {
         match self {
@@ -192,6 +358,7 @@ mod tests {
             ctype,
             qualifiers: Qualifiers::NONE,
             storage_class: StorageClass::Auto,
+            location: Location::default(),
         }
     }
     fn struct_for_types(types: Vec<Type>) -> Type {
@@ -244,9 +411,10 @@ mod tests {
         let four = type_for_size(4);
         let eight = type_for_size(8);
         let sixteen = type_for_size(16);
-        assert_offset(vec![four, two], 1, 4);
+        assert_offset(vec![four.clone(), two], 1, 4);
         assert_offset(vec![eight.clone(), sixteen.clone()], 1, 8);
-        assert_offset(vec![sixteen.clone(), eight], 1, 16);
+        assert_offset(vec![sixteen.clone(), eight.clone()], 1, 16);
+        assert_offset(vec![four.clone(), eight], 1, 8);
         let twenty_four = type_for_size(24);
         assert_offset(vec![twenty_four, sixteen], 1, 24);
     }
@@ -265,6 +433,53 @@ mod tests {
         ]);
         assert_eq!(ty.alignof(), Ok(8));
     }
+    #[test]
+    fn write_int() {
+        let little = ArchData::new(&"x86_64-unknown-linux-gnu".parse().unwrap());
+        let big = ArchData::new(&"powerpc64-unknown-linux-gnu".parse().unwrap());
+        let mut buf = [0; 8];
+        little.write_int(&mut buf, 0x0102_0304, 4);
+        assert_eq!(buf, [4, 3, 2, 1, 0, 0, 0, 0]);
+        big.write_int(&mut buf, 0x0102_0304, 4);
+        assert_eq!(buf, [1, 2, 3, 4, 0, 0, 0, 0]);
+        // negative numbers are truncated, not saturated
+        big.write_int(&mut buf, -2_i64 as u64, 2);
+        assert_eq!(buf, [0xff, 0xfe, 3, 4, 0, 0, 0, 0]);
+        big.write_ptr(&mut buf, 0);
+        assert_eq!(buf, [0; 8]);
+        big.write_float(&mut buf, 1.0, 4);
+        assert_eq!(buf[..4], 1.0_f32.to_be_bytes());
+        little.write_float(&mut buf, 1.0, 8);
+        assert_eq!(buf, 1.0_f64.to_le_bytes());
+    }
+
+    #[test]
+    fn string_data() {
+        let little = ArchData::new(&"x86_64-unknown-linux-gnu".parse().unwrap());
+        let big = ArchData::new(&"powerpc64-unknown-linux-gnu".parse().unwrap());
+        let wide = Literal::WideStr(Encoding::Wide, vec![0x41, 0x1f600, 0]);
+        let data = little.string_data(&wide).unwrap();
+        assert_eq!(data.element_size, 4);
+        assert_eq!(data.bytes, [0x41, 0, 0, 0, 0, 0xf6, 1, 0, 0, 0, 0, 0]);
+        let data = big.string_data(&wide).unwrap();
+        assert_eq!(data.bytes, [0, 0, 0, 0x41, 0, 1, 0xf6, 0, 0, 0, 0, 0]);
+
+        let utf16 = Literal::WideStr(Encoding::Utf16, vec![0xd83d, 0xde00, 0]);
+        let data = little.string_data(&utf16).unwrap();
+        assert_eq!(data.element_size, 2);
+        assert_eq!(data.bytes, [0x3d, 0xd8, 0x00, 0xde, 0, 0]);
+        // `u"\x12345"`
+        let too_large = Literal::WideStr(Encoding::Utf16, vec![0x12345, 0]);
+        assert_eq!(little.string_data(&too_large), Err(0x12345));
+
+        // narrow strings are already bytes
+        let narrow = Literal::Str(b"hi\0".to_vec());
+        let data = big.string_data(&narrow).unwrap();
+        assert_eq!(
+            (data.bytes.as_slice(), data.element_size),
+            (&b"hi\0"[..], 1)
+        );
+    }
 
     proptest! {
         // https://github.com/jyn514/rcc/pull/325#issuecomment-596297785
@@ -3,9 +3,15 @@
 pub(crate) type SIZE_T = u64;
 #[allow(dead_code)]
 pub(crate) const SIZE_MAX: SIZE_T = SIZE_T::max_value();
+// the type of `p1 - p2`, and of the scaled index in `p + i`
+#[allow(non_camel_case_types)]
+pub(crate) type PTRDIFF_T = i64;
 
 pub(crate) const FLOAT_SIZE: u16 = 4;
 pub(crate) const DOUBLE_SIZE: u16 = 8;
+// bits of precision in IEEE 754 binary32 and binary64, including the implicit leading bit
+pub(crate) const FLOAT_MANTISSA: u32 = 24;
+pub(crate) const DOUBLE_MANTISSA: u32 = 53;
 
 pub(crate) const LONG_SIZE: u16 = 8;
 pub(crate) const INT_SIZE: u16 = 4;
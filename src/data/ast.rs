@@ -7,12 +7,14 @@ use crate::intern::InternedStr;
 pub type Program = Vec<Declaration>;
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
 pub enum ExternalDeclaration {
     Function(FunctionDefinition),
     Declaration(Declaration),
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
 pub struct FunctionDefinition {
     pub specifiers: Vec<DeclarationSpecifier>,
     pub id: InternedStr,
@@ -33,12 +35,14 @@ impl FunctionDefinition {
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
 pub struct TypeName {
     pub specifiers: Vec<DeclarationSpecifier>,
     pub declarator: Declarator,
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
 pub enum DeclarationSpecifier {
     Unit(UnitSpecifier),
     Struct(StructSpecifier),
@@ -51,9 +55,22 @@ pub enum DeclarationSpecifier {
     // NOTE: _not_ the same as UnitSpecifier::Typedef
     // that represents the `typedef` keyword, this represents a name that has been typedef-ed
     Typedef(InternedStr),
+    // __attribute__((noreturn, aligned(8)))
+    Attributes(Vec<Attribute>),
+}
+
+/// A GNU attribute, such as `noreturn` or `aligned(8)`.
+///
+/// See <https://gcc.gnu.org/onlinedocs/gcc/Attribute-Syntax.html>
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct Attribute {
+    pub name: InternedStr,
+    pub args: Vec<Expr>,
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
 pub enum UnitSpecifier {
     // types
     Char,
@@ -98,6 +115,7 @@ impl From<UnitSpecifier> for DeclarationSpecifier {
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
 pub struct StructSpecifier {
     pub name: Option<InternedStr>,
     /// Some([]): `struct s {}`
@@ -106,12 +124,14 @@ pub struct StructSpecifier {
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
 pub struct StructDeclarationList {
     pub specifiers: Vec<DeclarationSpecifier>,
     pub declarators: Vec<StructDeclarator>,
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
 pub struct StructDeclarator {
     /// optional since this could be only padding bits
     pub decl: Option<Declarator>,
@@ -119,35 +139,42 @@ pub struct StructDeclarator {
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
 pub struct Declaration {
     pub specifiers: Vec<DeclarationSpecifier>,
     pub declarators: Vec<Locatable<InitDeclarator>>,
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
 pub struct InitDeclarator {
     pub init: Option<Initializer>,
     pub declarator: Declarator,
+    /// attributes after the declarator: `void f() __attribute__((noreturn));`
+    pub attributes: Vec<Attribute>,
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
 pub enum Initializer {
     Scalar(Box<Expr>),
     Aggregate(Vec<Initializer>),
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
 pub struct Declarator {
     pub decl: DeclaratorType,
     pub id: Option<InternedStr>,
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
 pub struct FunctionDeclarator {
     pub return_type: Box<DeclaratorType>,
     // TODO: maybe support K&R C?
     //DeclarationList
-    pub params: Vec<TypeName>,
+    pub params: Vec<Locatable<TypeName>>,
     pub varargs: bool,
 }
 
@@ -158,6 +185,7 @@ impl From<FunctionDeclarator> for DeclaratorType {
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
 pub enum DeclaratorType {
     // No more declarator, e.g. for abstract params
     End,
@@ -168,6 +196,8 @@ pub enum DeclaratorType {
     Array {
         of: Box<DeclaratorType>,
         size: Option<Box<Expr>>,
+        /// `int a[restrict]`, only allowed for parameters
+        qualifiers: Vec<DeclarationSpecifier>,
     },
     Function(FunctionDeclarator),
 }
@@ -176,6 +206,7 @@ pub type Stmt = Locatable<StmtType>;
 pub type CompoundStatement = Vec<Stmt>;
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
 pub enum StmtType {
     Compound(CompoundStatement),
     If(Expr, Box<Stmt>, Option<Box<Stmt>>),
@@ -200,11 +231,14 @@ pub enum StmtType {
     Break,
     Return(Option<Expr>),
     Decl(Declaration),
+    /// `__attribute__((fallthrough));` or `[[fallthrough]];`
+    Fallthrough,
 }
 
 pub type Expr = Locatable<ExprType>;
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
 pub enum ExprType {
     // primary
     Id(InternedStr),
@@ -212,8 +246,10 @@ pub enum ExprType {
 
     // postfix
     FuncCall(Box<Expr>, Vec<Expr>),
-    Member(Box<Expr>, InternedStr),
-    DerefMember(Box<Expr>, InternedStr),
+    /// The location of the member is the location of the `.` and the member name
+    Member(Box<Expr>, Locatable<InternedStr>),
+    /// The location of the member is the location of the `->` and the member name
+    DerefMember(Box<Expr>, Locatable<InternedStr>),
     // post increment/decrement
     PostIncrement(Box<Expr>, bool),
     // a[i]
@@ -249,11 +285,13 @@ pub enum ExprType {
     // Token: make >, <, <=, ... part of the same variant
     Compare(Box<Expr>, Box<Expr>, ComparisonToken),
     // Token: allow extended assignment
-    Assign(Box<Expr>, Box<Expr>, AssignmentToken),
+    /// The token's location is the location of the operator
+    Assign(Box<Expr>, Box<Expr>, Locatable<AssignmentToken>),
 
     // misfits
     // Ternary: if ? then : else
-    Ternary(Box<Expr>, Box<Expr>, Box<Expr>),
+    // `then` is missing for the GNU extension `if ?: else`
+    Ternary(Box<Expr>, Option<Box<Expr>>, Box<Expr>),
     Comma(Box<Expr>, Box<Expr>),
 }
 
@@ -332,6 +370,9 @@ impl Display for Declaration {
 impl Display for InitDeclarator {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{}", self.declarator)?;
+        if !self.attributes.is_empty() {
+            write!(f, " __attribute__(({}))", joined(&self.attributes, ", "))?;
+        }
         if let Some(init) = &self.init {
             write!(f, " = {}", init)?;
         }
@@ -383,7 +424,18 @@ impl Display for DeclarationSpecifier {
             Union(spec) => write!(f, "union {}", spec),
             Struct(spec) => write!(f, "struct {}", spec),
             Typedef(name) => write!(f, "{}", name),
+            Attributes(attrs) => write!(f, "__attribute__(({}))", joined(attrs, ", ")),
+        }
+    }
+}
+
+impl Display for Attribute {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        if !self.args.is_empty() {
+            write!(f, "({})", joined(&self.args, ", "))?;
         }
+        Ok(())
     }
 }
 
@@ -472,15 +524,26 @@ impl DeclaratorType {
         }
         for declarator_type in unrolled_type[..unrolled_type.len() - 1].iter() {
             match declarator_type {
-                DeclaratorType::Array { size, .. } => {
-                    if let Some(size) = size {
-                        write!(f, "[{}]", size)?;
-                    } else {
-                        write!(f, "[]")?;
+                DeclaratorType::Array {
+                    size, qualifiers, ..
+                } => {
+                    let qualifiers = qualifiers
+                        .iter()
+                        .map(|q| q.to_string())
+                        .collect::<Vec<_>>()
+                        .join(" ");
+                    match (size, qualifiers.as_str()) {
+                        (Some(size), "") => write!(f, "[{}]", size)?,
+                        (Some(size), _) => write!(f, "[{} {}]", qualifiers, size)?,
+                        (None, _) => write!(f, "[{}]", qualifiers)?,
                     }
                 }
                 DeclaratorType::Function(function_declarator) => {
-                    write!(f, "({}", joined(function_declarator.params.iter(), ", "))?;
+                    write!(
+                        f,
+                        "({}",
+                        joined_locatable(&function_declarator.params, ", ")
+                    )?;
                     if function_declarator.varargs {
                         write!(f, ", ...")?;
                     }
@@ -519,7 +582,7 @@ impl FunctionDeclarator {
             write!(f, "{}", name)?;
         }
         // print_post
-        write!(f, "({}", joined(&self.params, ", "))?;
+        write!(f, "({}", joined_locatable(&self.params, ", "))?;
         if self.varargs {
             write!(f, ", ...")?;
         }
@@ -571,6 +634,7 @@ impl StmtType {
             StmtType::Return(Some(expr)) => write!(f, "return {};", expr),
             StmtType::Break => write!(f, "break;"),
             StmtType::Continue => write!(f, "continue;"),
+            StmtType::Fallthrough => write!(f, "__attribute__((fallthrough));"),
             StmtType::Default(stmt) => {
                 writeln!(f, "default:")?;
                 stmt.data.pretty_print(f, depth + 1)
@@ -645,14 +709,17 @@ impl Display for Expr {
                 write!(f, "({}) {} ({})", val, if *left { "<<" } else { ">>" }, by)
             }
             ExprType::Compare(left, right, token) => write!(f, "({}) {} ({})", left, token, right),
-            ExprType::Assign(left, right, token) => write!(f, "({}) {} ({})", left, token, right),
-            ExprType::Ternary(cond, left, right) => {
+            ExprType::Assign(left, right, token) => {
+                write!(f, "({}) {} ({})", left, token.data, right)
+            }
+            ExprType::Ternary(cond, Some(left), right) => {
                 write!(f, "({}) ? ({}) : ({})", cond, left, right)
             }
+            ExprType::Ternary(cond, None, right) => write!(f, "({}) ?: ({})", cond, right),
             ExprType::FuncCall(left, params) => write!(f, "({})({})", left, joined(params, ", ")),
             ExprType::Cast(ctype, expr) => write!(f, "({})({})", ctype, expr),
-            ExprType::Member(compound, id) => write!(f, "({}).{}", compound, id),
-            ExprType::DerefMember(compound, id) => write!(f, "({})->{}", compound, id),
+            ExprType::Member(compound, id) => write!(f, "({}).{}", compound, id.data),
+            ExprType::DerefMember(compound, id) => write!(f, "({})->{}", compound, id.data),
             ExprType::PreIncrement(expr, inc) => {
                 write!(f, "{}({})", if *inc { "++" } else { "--" }, expr)
             }
@@ -678,4 +745,22 @@ mod test {
     fn test_declaration_display() {
         assert_no_change("int (*(*f))();");
     }
+
+    #[cfg(feature = "serialize")]
+    #[test]
+    fn serialize_round_trip() {
+        use crate::data::{ast::ExternalDeclaration, Locatable};
+        use crate::parse::test::parse_all;
+
+        let program = "struct s { int i; } x = { 1 }; enum e { A = 2 };
+            static int f(int a, ...) { for (;;) if (a >= 'c') return -a ? 1.5 : sizeof(x); }";
+        let decls: Vec<_> = parse_all(program).into_iter().map(Result::unwrap).collect();
+        let json = serde_json::to_string(&decls).unwrap();
+        let parsed: Vec<Locatable<ExternalDeclaration>> = serde_json::from_str(&json).unwrap();
+        assert_eq!(decls.len(), parsed.len());
+        for (decl, parsed) in decls.iter().zip(&parsed) {
+            assert_eq!(decl.data, parsed.data);
+            assert_eq!(decl.location, parsed.location);
+        }
+    }
 }
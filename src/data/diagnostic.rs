@@ -0,0 +1,135 @@
+//! Delivering errors and warnings to the user as they are found.
+//!
+//! `analyze_with_sink` sends every diagnostic to a `DiagnosticSink`
+//! as soon as the declaration it belongs to has been analyzed,
+//! instead of collecting them all until the end of the program.
+
+use std::collections::VecDeque;
+use std::fmt::{self, Display};
+use std::io;
+
+use super::error::{CompileError, CompileWarning, Error, Warning};
+use super::lex::Location;
+use super::source::PresumedLocation;
+
+/// What a `Diagnostic` is reporting.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DiagnosticKind {
+    Error(Error),
+    Warning(Warning),
+}
+
+/// An error or warning, along with where it is.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub kind: DiagnosticKind,
+    pub location: Location,
+    /// Where `location` starts, after applying `#line` directives.
+    ///
+    /// The source map is still being built while diagnostics are emitted,
+    /// so this is looked up ahead of time.
+    pub presumed: PresumedLocation<'static>,
+}
+
+impl Diagnostic {
+    pub fn is_error(&self) -> bool {
+        matches!(self.kind, DiagnosticKind::Error(_))
+    }
+}
+
+impl Display for Diagnostic {
+    /// Show the diagnostic like GCC does with `-fno-diagnostics-show-caret`:
+    /// `file.c:1:9: warning: message [-Wname]`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let PresumedLocation { name, line, column } = &self.presumed;
+        write!(f, "{}:{}:{}: ", name, line, column)?;
+        match &self.kind {
+            DiagnosticKind::Error(error) => write!(f, "error: {}", error),
+            DiagnosticKind::Warning(warning) => {
+                write!(f, "warning: {} [-W{}]", warning, warning.name())
+            }
+        }
+    }
+}
+
+/// Where diagnostics go.
+///
+/// Diagnostics are emitted in source order:
+/// all diagnostics for a declaration are emitted before any for the declaration after it.
+pub trait DiagnosticSink {
+    fn emit(&mut self, diag: Diagnostic);
+    /// Whether to stop compiling as soon as possible.
+    ///
+    /// This is checked after every declaration, in addition to `Opt::max_errors`.
+    fn should_abort(&self) -> bool {
+        false
+    }
+}
+
+impl<S: DiagnosticSink + ?Sized> DiagnosticSink for &mut S {
+    fn emit(&mut self, diag: Diagnostic) {
+        (**self).emit(diag)
+    }
+    fn should_abort(&self) -> bool {
+        (**self).should_abort()
+    }
+}
+
+/// Remember all diagnostics, to be looked at after compiling. This is what `analyze` uses.
+#[derive(Clone, Debug, Default)]
+pub struct CollectingSink {
+    pub errors: VecDeque<CompileError>,
+    pub warnings: VecDeque<CompileWarning>,
+}
+
+impl CollectingSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl DiagnosticSink for CollectingSink {
+    fn emit(&mut self, diag: Diagnostic) {
+        match diag.kind {
+            DiagnosticKind::Error(error) => self.errors.push_back(diag.location.with(error)),
+            DiagnosticKind::Warning(warning) => {
+                self.warnings.push_back(diag.location.with(warning))
+            }
+        }
+    }
+}
+
+/// Write each diagnostic on its own line, using its `Display` impl.
+///
+/// The first error from the writer stops all further output and is returned by `into_inner`.
+#[derive(Debug)]
+pub struct WriteSink<W: io::Write> {
+    writer: W,
+    error: Option<io::Error>,
+}
+
+impl<W: io::Write> WriteSink<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            error: None,
+        }
+    }
+    /// Return the writer, or the error that happened while writing to it.
+    pub fn into_inner(self) -> io::Result<W> {
+        match self.error {
+            Some(err) => Err(err),
+            None => Ok(self.writer),
+        }
+    }
+}
+
+impl<W: io::Write> DiagnosticSink for WriteSink<W> {
+    fn emit(&mut self, diag: Diagnostic) {
+        if self.error.is_none() {
+            if let Err(err) = writeln!(self.writer, "{}", diag) {
+                self.error = Some(err);
+            }
+        }
+    }
+}
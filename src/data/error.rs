@@ -1,7 +1,6 @@
 use std::collections::VecDeque;
 use thiserror::Error;
 
-use super::hir::Expr;
 use super::*;
 
 use super::Radix;
@@ -15,6 +14,38 @@ pub type RecoverableResult<T, E = CompileError> = Result<T, (E, T)>;
 pub type CompileResult<T> = Result<T, CompileError>;
 pub type CompileError = Locatable<Error>;
 pub type CompileWarning = Locatable<Warning>;
+/// A message pointing to a location other than the main one for an error,
+/// such as a previous declaration.
+pub type Note = Locatable<String>;
+
+/// A suggested edit that fixes a diagnostic, such as inserting a missing `;`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FixIt {
+    /// What the edit does, e.g. "use '->' to access a member through a pointer"
+    pub message: String,
+    /// The text to replace. If the span is empty, `replacement` is inserted at the start instead.
+    pub location: Location,
+    pub replacement: String,
+}
+
+impl FixIt {
+    fn new(message: &str, location: Location, replacement: &str) -> Self {
+        FixIt {
+            message: message.to_owned(),
+            location,
+            replacement: replacement.to_owned(),
+        }
+    }
+    /// Replace the bytes `start..end` relative to the start of `location`.
+    fn replace(message: &str, location: Location, start: u32, end: u32, replacement: &str) -> Self {
+        let start = location.span.start + start;
+        let location = Location {
+            span: (start..location.span.start + end).into(),
+            ..location
+        };
+        Self::new(message, location, replacement)
+    }
+}
 
 /// ErrorHandler is a struct that hold errors generated by the compiler
 ///
@@ -58,9 +89,12 @@ impl<T> ErrorHandler<T> {
         self.errors.pop_front()
     }
 
-    /// Shortcut for adding a warning
-    pub(crate) fn warn<W: Into<Warning>>(&mut self, warning: W, location: Location) {
-        self.warnings.push_back(location.with(warning.into()));
+    /// Add a warning.
+    ///
+    /// All warnings should go through this function,
+    /// so that they can be filtered by `WarningOptions`.
+    pub(crate) fn warn(&mut self, warning: Warning, location: Location) {
+        self.warnings.push_back(location.with(warning));
     }
 
     /// Shortcut for adding an error
@@ -105,12 +139,26 @@ pub enum Error {
 
     #[error("invalid token: {0}")]
     Lex(#[from] LexError),
+
+    /// A warning that was turned into an error by `-Werror`
+    #[error("{0} [-Werror={}]", .0.name())]
+    Warning(Warning),
+
+    /// The compiler panicked while processing the code at this location.
+    ///
+    /// This is always a bug in the compiler.
+    #[error("internal compiler error: {0}")]
+    InternalCompilerError(String),
 }
 
 /// Semantic errors are non-exhaustive and may have new variants added at any time
 #[derive(Clone, Debug, Error, PartialEq)]
 #[non_exhaustive]
 pub enum SemanticError {
+    /// An error with no dedicated variant.
+    ///
+    /// This is only used for messages that are genuinely dynamic,
+    /// such as errors reported by the code generator.
     #[error("{0}")]
     Generic(String),
 
@@ -124,6 +172,14 @@ pub enum SemanticError {
     #[error("'{0}' is not a qualifier and cannot be used for pointers")]
     NotAQualifier(ast::DeclarationSpecifier),
 
+    /// `restrict int i;` or `int (*restrict f)(void);`
+    #[error("restrict requires a pointer to an object type (got '{0}')")]
+    InvalidRestrict(Type),
+
+    /// `int a[const 5];` outside of a parameter list
+    #[error("type qualifiers in array sizes are only allowed in function parameters")]
+    QualifiedArray,
+
     #[error("'{}' is too long for {}", vec!["long"; *.0].join(" "), env!("CARGO_PKG_NAME"))]
     TooLong(usize),
 
@@ -142,17 +198,59 @@ pub enum SemanticError {
     #[error("only function-scoped variables can have an `auto` storage class")]
     AutoAtGlobalScope,
 
+    #[error("only function-scoped variables can have a `register` storage class")]
+    RegisterAtGlobalScope,
+
     #[error("cannot have empty program")]
     EmptyProgram,
 
+    #[error("bare '{0}' as type specifier is not allowed")]
+    BareTagSpecifier(lex::Keyword),
+
+    /// (tag, keyword used for the tag)
+    #[error("use of '{0}' with type tag '{1}' that does not match previous struct declaration")]
+    TagMismatch(InternedStr, lex::Keyword),
+
+    #[error("redefinition of {0} '{1}'")]
+    TagRedefinition(lex::Keyword, InternedStr),
+
+    #[error("cannot have forward reference to enum type '{0}'")]
+    EnumForwardReference(InternedStr),
+
+    #[error("cannot have empty struct")]
+    EmptyStruct,
+
+    #[error("enums cannot be empty")]
+    EmptyEnum,
+
+    // struct member errors
+    #[error("C does not have zero-sized types. hint: omit the declarator {0}")]
+    ZeroWidthBitfield(InternedStr),
+
+    #[error("cannot have bitfield {name} with size {width} larger than containing type {ctype}")]
+    BitfieldTooWide {
+        name: InternedStr,
+        width: u64,
+        ctype: Type,
+    },
+
+    #[error("cannot use type '{0}' before it has been defined")]
+    IncompleteMember(Type),
+
+    #[error("cannot specify storage class '{0}' for struct member '{1}'")]
+    MemberStorageClass(StorageClass, InternedStr),
+
     // Declarator errors
+    #[error("declarations cannot be abstract")]
+    AbstractDeclaration,
+
     #[error("expected an integer")]
     NonIntegralLength,
 
     #[error("arrays must have a positive length")]
     NegativeLength,
 
-    #[error("function parameters always have a storage class of `auto`")]
+    #[error("function parameters can only have a `register` storage class")]
     ParameterStorageClass(StorageClass),
 
     #[error("duplicate parameter name '{0}' in function declaration")]
@@ -180,9 +278,21 @@ pub enum SemanticError {
     #[error("variable has incomplete type 'void'")]
     VoidType,
 
+    #[error("'{0}' is not a type in this scope")]
+    NotATypedef(InternedStr),
+
     // expression errors
-    #[error("use of undeclared identifier '{0}'")]
-    UndeclaredVar(InternedStr),
+    /// (name, declared variable with a similar name)
+    #[error("use of undeclared identifier '{0}'{}",
+            .1.map_or(String::new(), |similar| format!("; did you mean '{}'?", similar)))]
+    UndeclaredVar(InternedStr, Option<InternedStr>),
+
+    #[error("'{0}' is only allowed inside a function")]
+    FuncNameOutsideFunction(InternedStr),
+
+    /// `u"\x12345"`, where an element of a wide string doesn't fit in the element type
+    #[error("character 0x{0:x} is too large for '{1}'")]
+    CharacterTooLarge(u32, Type),
 
     #[error("expected expression, got typedef")]
     TypedefInExpressionContext,
@@ -197,7 +307,7 @@ pub enum SemanticError {
     NonIntegralExpr(Type),
 
     #[error("cannot implicitly convert '{0}' to '{1}'{}",
-        if .1.is_pointer() {
+        if .1.is_pointer() || .0.is_pointer() && .1.is_integral() {
             format!(". help: use an explicit cast: ({})", .1)
         } else {
             String::new()
@@ -205,22 +315,62 @@ pub enum SemanticError {
     ]
     InvalidCast(Type, Type),
 
-    // String is the reason it couldn't be assigned
+    /// `x = y` where `x` and `y` are different struct or union types, even if they have the same tag
+    #[error("assigning to '{0}' from incompatible type '{1}'")]
+    IncompatibleAggregate(Type, Type),
+
     #[error("cannot assign to {0}")]
-    NotAssignable(String),
+    NotAssignable(NotAssignable),
+
+    #[error("expression of type '{0}' cannot be converted to bool")]
+    NotConvertibleToBool(Type),
+
+    /// The reason the size or alignment couldn't be calculated
+    #[error("{0}")]
+    InvalidSize(&'static str),
 
     #[error("invalid operators for '{0}' (expected either arithmetic types or pointer operation, got '{1} {0} {2}'")]
     InvalidAdd(hir::BinaryOp, Type, Type),
 
+    #[error("expected integers for both operators of %, got '{0}' and '{1}'")]
+    NonIntegralModulo(Type, Type),
+
+    #[error("expected float or integer types for both operands of {0}, got '{1}' and '{2}'")]
+    NonArithmeticOperands(hir::BinaryOp, Type, Type),
+
     #[error("cannot perform pointer arithmetic when size of pointed type '{0}' is unknown")]
     PointerAddUnknownSize(Type),
 
+    /// `p + 1` where `p` points to a function, or to `void` outside of GNU C
+    #[error("arithmetic on a pointer to {}", if .0.is_function() { format!("function type '{}'", .0) } else { .0.to_string() })]
+    PointerArithmetic(Type),
+
     #[error("called object of type '{0}' is not a function")]
     NotAFunction(Type),
 
-    #[error("too {} arguments to function call: expected {0}, have {1}", if .1 > .0 { "many" } else { "few" })]
-    /// (actual, expected)
-    WrongArgumentNumber(usize, usize),
+    #[error("too {} arguments to function call: expected {}{expected} argument{}, found {actual}",
+            if .actual > .expected { "many" } else { "few" },
+            if *(.varargs) { "at least " } else { "" },
+            if *(.expected) == 1 { "" } else { "s" })]
+    WrongArgumentNumber {
+        actual: usize,
+        expected: usize,
+        /// the function takes `...` after its fixed parameters
+        varargs: bool,
+        /// where the function was declared, if it was called by name
+        declaration: Option<Location>,
+    },
+
+    /// `f(i)` for `int f(int *)`
+    #[error("incompatible type '{actual}' for argument {position}: expected '{expected}'")]
+    InvalidArgument {
+        /// counting from 1
+        position: usize,
+        actual: Type,
+        expected: Type,
+        /// where the parameter was declared
+        parameter: Location,
+    },
 
     #[error("{0} has not yet been defined")]
     IncompleteDefinitionUsed(Type),
@@ -234,17 +384,33 @@ pub enum SemanticError {
     #[error("cannot use '->' operator on type that is not a pointer")]
     NotAStructPointer(Type),
 
+    #[error("member reference type '{0}' is a pointer; did you mean to use '->'?")]
+    MemberOfPointer(Type),
+
+    /// (argument type, parameter type)
+    #[error("cannot implicitly convert '{0}' to '{1}'; take the address with '&'")]
+    MissingAddressOf(Type, Type),
+
     #[error("cannot dereference expression of non-pointer type '{0}'")]
     NotAPointer(Type),
 
+    /// `p[1.5]` or `p[q]`
+    #[error("array subscript is not an integer (got '{0}')")]
+    NonIntegralSubscript(Type),
+
+    /// `*p` where `p` is `void *`; only `&*p` is allowed
+    #[error("cannot dereference expression of type '{0}', which points to void")]
+    VoidDereference(Type),
+
     #[error("cannot take address of {0}")]
     InvalidAddressOf(&'static str),
 
     #[error("cannot increment or decrement value of type '{0}'")]
     InvalidIncrement(Type),
 
-    #[error("cannot use unary plus on expression of non-arithmetic type '{0}'")]
-    NotArithmetic(Type),
+    /// `-p`, `~1.0`, or `!s`; the second field is the kind of type the operator needs
+    #[error("expected {1} type for operand of unary {0}, got '{2}'")]
+    InvalidUnaryOperand(&'static str, &'static str, Type),
 
     #[error("incompatible types in ternary expression: '{0}' cannot be converted to '{1}'")]
     IncompatibleTypes(Type, Type),
@@ -268,8 +434,9 @@ pub enum SemanticError {
         current: u64,
     },
 
+    // the expression is printed as C, since errors can't borrow from the HIR arena
     #[error("not a constant expression: {0}")]
-    NotConstant(Expr),
+    NotConstant(String),
 
     #[error("cannot dereference NULL pointer")]
     NullPointerDereference,
@@ -277,6 +444,10 @@ pub enum SemanticError {
     #[error("invalid types for '{0}' (expected arithmetic types or compatible pointers, got {1} {0} {2}")]
     InvalidRelationalType(lex::ComparisonToken, Type, Type),
 
+    /// `f < g`; only pointers to objects can be ordered
+    #[error("ordered comparison '{0}' of pointers to functions ('{1}' and '{2}')")]
+    FunctionPointerOrdering(lex::ComparisonToken, Type, Type),
+
     #[error("cannot cast pointer to float or vice versa")]
     FloatPointerCast(Type),
 
@@ -294,10 +465,9 @@ pub enum SemanticError {
     #[error("unreachable statement")]
     UnreachableStatement,
 
-    // TODO: this error should happen way before codegen
-    #[cfg(feature = "codegen")]
-    #[error("redeclaration of label {0}")]
-    LabelRedeclaration(cranelift::prelude::Block),
+    /// (label, previous declaration)
+    #[error("redeclaration of label '{0}'")]
+    LabelRedeclaration(InternedStr, Location),
 
     #[error("use of undeclared label {0}")]
     UndeclaredLabel(InternedStr),
@@ -305,9 +475,26 @@ pub enum SemanticError {
     #[error("{}case outside of switch statement", if *(.is_default) { "default " } else { "" })]
     CaseOutsideSwitch { is_default: bool },
 
-    #[error("cannot have multiple {}cases in a switch statement",
-            if *(.is_default) { "default " } else { "" } )]
-    DuplicateCase { is_default: bool },
+    #[error("'break' statement not in a loop or switch")]
+    BreakOutsideLoop,
+
+    #[error("'continue' statement not in a loop")]
+    ContinueOutsideLoop,
+
+    /// the value is after conversion to the promoted type of the controlling expression
+    #[error("duplicate case value `{value}`")]
+    DuplicateCase {
+        value: i128,
+        /// the first label with the same value
+        previous: Option<Location>,
+    },
+
+    #[error("multiple default labels in one switch")]
+    DuplicateDefault(Option<Location>),
+
+    /// (the feature, the standard which doesn't have it)
+    #[error("{0} are not allowed in -std={1}")]
+    NotInStandard(&'static str, Standard),
 
     // Initializer errors
     #[error("initializers cannot be empty")]
@@ -319,6 +506,12 @@ pub enum SemanticError {
     #[error("too many initializers (declared with {0} elements, found {1})")]
     TooManyMembers(usize, usize),
 
+    #[error("'{0}' cannot be initialized with an initializer list")]
+    InvalidAggregateInitializer(Type),
+
+    #[error("can only initialize first member of a union")]
+    NonFirstUnionMember,
+
     // Function definition errors
     #[error("illegal storage class {0} for function (only `static` and `extern` are allowed)")]
     InvalidFuncStorageClass(StorageClass),
@@ -329,19 +522,29 @@ pub enum SemanticError {
     #[error("forward declaration of {0} is never completed (used in {1})")]
     ForwardDeclarationIncomplete(InternedStr, InternedStr),
 
-    #[error("illegal signature for main function (expected 'int main(void)' or 'int main(int, char **)'")]
-    IllegalMainSignature,
-
     // declaration errors
+    /// (name, previous definition)
     #[error("redefinition of '{0}'")]
-    Redefinition(InternedStr),
+    Redefinition(InternedStr, Option<Location>),
 
+    /// (name, previous symbol, new symbol, previous declaration)
     #[error("redeclaration of '{0}' with different type or qualifiers (originally {}, now {})", .1.get(), .2.get())]
-    IncompatibleRedeclaration(InternedStr, hir::Symbol, hir::Symbol),
+    IncompatibleRedeclaration(InternedStr, hir::Symbol, hir::Symbol, Option<Location>),
 
     #[error("'{0}' can only appear on functions")]
     FuncQualifiersNotAllowed(hir::FunctionQualifiers),
 
+    /// (attribute, name)
+    #[error("'{0}' attribute can't be used on '{1}', which doesn't have external linkage")]
+    AttributeNeedsLinkage(&'static str, InternedStr),
+
+    /// (alias, target)
+    #[error("alias '{0}' refers to '{1}', which isn't defined in this translation unit")]
+    UndefinedAliasTarget(InternedStr, InternedStr),
+
+    #[error("'{0}' is defined both normally and as an alias")]
+    AliasRedefinition(InternedStr),
+
     // stmt errors
     // new with the new parser
     #[error("switch expressions must have an integer type (got {0})")]
@@ -354,6 +557,43 @@ pub enum SemanticError {
     ReturnFromVoid(InternedStr),
 }
 
+/// The reason an expression can't be assigned to.
+#[derive(Clone, Debug, PartialEq)]
+pub enum NotAssignable {
+    Rvalue,
+    IncompleteType(Type),
+    /// The name of the variable
+    ConstVariable(InternedStr),
+    Array,
+    /// The name of a `const` member, like `x` in `s.x`
+    ConstMember(InternedStr),
+    /// A struct or union that can't be assigned as a whole; holds the name of the `const` member,
+    /// which may be nested inside another member
+    HasConstMember(InternedStr),
+    /// An object reached through a pointer to `const`, like `*p` or `a[i]`, printed as C
+    ReadOnlyLocation(String),
+}
+
+impl std::fmt::Display for NotAssignable {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            NotAssignable::Rvalue => write!(f, "rvalue"),
+            NotAssignable::IncompleteType(ctype) => {
+                write!(f, "expression with incomplete type '{}'", ctype)
+            }
+            NotAssignable::ConstVariable(id) => {
+                write!(f, "variable '{}' with `const` qualifier", id)
+            }
+            NotAssignable::Array => write!(f, "array"),
+            NotAssignable::ConstMember(id) => write!(f, "member '{}' with `const` qualifier", id),
+            NotAssignable::HasConstMember(id) => {
+                write!(f, "struct or union with `const` qualified member '{}'", id)
+            }
+            NotAssignable::ReadOnlyLocation(expr) => write!(f, "read-only location '{}'", expr),
+        }
+    }
+}
+
 /// Syntax errors are non-exhaustive and may have new variants added at any time
 #[derive(Clone, Debug, Error, PartialEq)]
 #[non_exhaustive]
@@ -382,6 +622,29 @@ pub enum SyntaxError {
     #[error("expected declarator in declaration")]
     ExpectedDeclarator,
 
+    /// The token that was found instead
+    #[error("expected ';', got '{0}'")]
+    ExpectedSemicolon(Token),
+
+    #[error("expected '{expected}', got '{}'",
+        .found.as_ref().map_or("<end-of-file>".into(),
+                               |t| std::borrow::Cow::Owned(t.to_string())))]
+    ExpectedToken {
+        expected: Token,
+        found: Option<Token>,
+    },
+
+    #[error("expected variable name or '(', got '{}'",
+        .0.as_ref().map_or("<end-of-file>".into(),
+                           |t| std::borrow::Cow::Owned(t.to_string())))]
+    ExpectedVariableOrParen(Option<Token>),
+
+    #[error("unclosed '{{' delimiter at end of file")]
+    UnclosedBrace,
+
+    #[error("struct members must have an id")]
+    MissingMemberName,
+
     #[error("empty type name")]
     ExpectedType,
 
@@ -402,6 +665,13 @@ pub enum SyntaxError {
 
     #[error("`static` for array sizes is only allowed in function declarations")]
     StaticInConcreteArray,
+
+    #[error("unexpected tokens after {0}")]
+    TrailingTokens(&'static str),
+
+    /// `L"a" u"b"`; strings with different prefixes can't be concatenated
+    #[error("cannot concatenate {0}\"\" and {1}\"\" string literals")]
+    MixedStringPrefixes(super::Encoding, super::Encoding),
 }
 
 /// Preprocessing errors are non-exhaustive and may have new variants added at any time
@@ -488,6 +758,10 @@ pub enum CppError {
     /// After parsing an `#if` expression, there were tokens left over.
     #[error("trailing tokens in `#if` expression")]
     TooManyTokens,
+
+    /// A `#line` directive gave a line number less than 1 or greater than 2147483647.
+    #[error("#line number must be between 1 and 2147483647, got {0}")]
+    LineOutOfRange(i64),
 }
 
 /// Lex errors are non-exhaustive and may have new variants added at any time
@@ -552,17 +826,13 @@ pub enum LexError {
 #[non_exhaustive]
 /// errors are non-exhaustive and may have new variants added at any time
 pub enum Warning {
-    // for compatibility
-    #[error("{0}")]
-    Generic(String),
-
     /// A #warning directive was present, followed by the tokens in this variant.
     // TODO: this allocates a string for each token,
     // might be worth separating out into a function at some point
     #[error("#warning {}", (.0).iter().map(|t| t.to_string()).collect::<Vec<_>>().join(" "))]
     User(Vec<Token>),
 
-    #[error("extraneous semicolon in {0}")]
+    #[error("extraneous semicolon {0}")]
     ExtraneousSemicolon(&'static str),
 
     #[error("'{0}' qualifier on return type has no effect")]
@@ -589,11 +859,638 @@ pub enum Warning {
 
     #[error("this is a definition, not a declaration, the 'extern' keyword has no effect")]
     ExtraneousExtern,
+
+    #[error("nonstandard signature for main function (expected 'int main(void)' or 'int main(int, char **)')")]
+    NonStandardMainSignature,
+
+    #[error("unknown character escape '\\{0}'")]
+    UnknownEscape(char),
+
+    #[error("binary number literals are an extension")]
+    BinaryLiteral,
+
+    #[error("bitfields are not implemented and will be ignored")]
+    IgnoredBitfield,
+
+    #[error("conversion to {0} loses precision ({1} != {2})")]
+    LossyConstantConversion(Type, Literal, Literal),
+
+    #[error("conversion from double to float loses precision ({} is different from {} by more than DBL_EPSILON ({}))",
+            .0, f64::EPSILON, f64::from(*.0 as f32))]
+    LossyFloatConversion(f64),
+
+    #[error("unknown warning option '-W{0}'")]
+    UnknownWarningOption(String),
+
+    #[error("using the result of an assignment as a condition without parentheses")]
+    AssignmentInCondition,
+
+    #[error("unused variable '{0}'")]
+    UnusedVariable(InternedStr),
+
+    #[error("unused parameter '{0}'")]
+    UnusedParameter(InternedStr),
+
+    #[error("variable '{0}' set but not used")]
+    UnusedButSetVariable(InternedStr),
+
+    #[error("'{0}' defined but not used")]
+    UnusedStatic(InternedStr),
+
+    #[error("this statement may fall through to the next label")]
+    ImplicitFallthrough,
+
+    /// The last field is the argument, if taking its address would fix the mismatch.
+    #[error("format specifies type '{0}', but argument {2} has type '{1}'")]
+    FormatTypeMismatch(Type, Type, usize, Option<Location>),
+
+    #[error("'{0}' in format string expects a matching argument")]
+    MissingFormatArgument(String),
+
+    #[error("data argument not used by format string")]
+    ExtraFormatArgument,
+
+    #[error("invalid conversion specifier '{0}' in format string")]
+    InvalidFormatSpecifier(String),
+
+    #[error("variable '{0}' is used uninitialized")]
+    Uninitialized(InternedStr),
+
+    #[error("variable '{0}' may be used uninitialized")]
+    MaybeUninitialized(InternedStr),
+
+    #[error("control reaches end of non-void function '{0}'")]
+    MissingReturn(InternedStr),
+
+    /// The location is where the shadowed declaration was declared, if known.
+    #[error("declaration of '{0}' shadows {1}")]
+    Shadow(InternedStr, Shadowed, Option<Location>),
+
+    /// The value is the case label after conversion to the promoted type of the controlling expression.
+    #[error("case value {0} is not in the range of '{1}'; this case is unreachable")]
+    CaseOutOfRange(i128, Type),
+
+    /// The boolean is whether the right side of the assignment has side effects,
+    /// in which case only the store is dead, not the whole statement.
+    #[error("{}", if *.1 { format!("value stored to '{}' is never read", .0) } else { format!("'{}' is assigned a value that is never read", .0) })]
+    DeadStore(InternedStr, bool),
+
+    /// The location is where the declaration was marked deprecated.
+    #[error("'{0}' is deprecated{}", .1.as_ref().map(|message| format!(": {}", message)).unwrap_or_default())]
+    Deprecated(InternedStr, Option<String>, Location),
+
+    #[error("implicit declaration of function '{0}'")]
+    ImplicitFunctionDeclaration(InternedStr),
+
+    /// A C99 feature used in C89, which is only allowed as an extension.
+    #[error("{0} are a C99 extension")]
+    C99Extension(&'static str),
+
+    /// A C2x feature used before C2x, which is only allowed as an extension.
+    #[error("{0} are a C2x extension")]
+    C2xExtension(&'static str),
+
+    /// An expression statement like `x == 1;` that computes a value and throws it away.
+    #[error("statement has no effect")]
+    UnusedValue,
+
+    #[error("no previous prototype for function '{0}'")]
+    MissingPrototype(InternedStr),
+
+    /// `int f();` instead of `int f(void);`
+    #[error("function declaration isn't a prototype")]
+    StrictPrototype,
+
+    /// `int f() {}` instead of `int f(void) {}`
+    ///
+    /// Only empty parameter lists are warned about. Definitions with an identifier list,
+    /// like `int f(a) int a; {}`, can't be parsed at all, and `int f(a) {}` is parsed
+    /// as a prototype with an implicit `int` parameter (`-Wimplicit-int`).
+    #[error("old-style function definition")]
+    OldStyleDefinition,
+
+    /// `a ?: b`, which is `a ? a : b` with `a` only evaluated once
+    #[error("use of GNU ?: conditional expression extension, omitting middle operand")]
+    GnuConditionalOmittedOperand,
+
+    /// `p == q` where `p` and `q` point to different object types, like `int *` and `char *`
+    #[error("comparison of distinct pointer types ('{0}' and '{1}')")]
+    DistinctPointerComparison(Type, Type),
+
+    /// `c ? p : q` where `p` and `q` point to different object types
+    #[error("pointer type mismatch in conditional expression ('{0}' and '{1}')")]
+    PointerTypeMismatch(Type, Type),
+
+    /// An implicit conversion between pointers to different object types, like `char *q = p;` for `int *p`
+    #[error("incompatible pointer types converting '{0}' to '{1}'")]
+    IncompatiblePointerTypes(Type, Type),
+
+    /// An implicit conversion between a function pointer and `void *`, which ISO C leaves undefined
+    #[error("ISO C forbids conversion between function pointer '{0}' and '{1}'")]
+    FunctionPointerConversion(Type, Type),
+
+    /// `v < w` where `v` and `w` are `void *`, which is a GNU extension
+    #[error("ordered comparison '{0}' of pointers to '{1}' and '{2}' is a GNU extension")]
+    VoidPointerOrdering(lex::ComparisonToken, Type, Type),
+
+    /// `v + 1` where `v` is a `void *`, which GNU C treats as though `sizeof(void)` were 1
+    #[error("arithmetic on a pointer to void is a GNU extension")]
+    VoidPointerArithmetic,
+
+    /// `a[i]` where `a` is an array declared `register`, which computes the array's address
+    #[error("ISO C forbids subscripting register array '{0}'")]
+    RegisterArraySubscript(InternedStr),
+
+    /// `c = ~c`, `flags &= ~MASK`, or `~c == 0xFF` where `c` is an `unsigned char`.
+    ///
+    /// The operation is done on `c` promoted to `int`, so the result can have bits set that don't fit in `c`.
+    /// The last field is a constant operand and its folded value written as a C constant, if there is one.
+    #[error("'{0}' operates on '{1}' promoted to '{2}', which sets bits that don't fit in '{1}'{}",
+        .3.as_ref().map_or_else(String::new, |(expr, value)| format!(" ('{}' is {})", expr, value)))]
+    PromotedBitwise(String, Type, Type, Option<(String, String)>),
+
+    /// `a[i++] = b[i]` or `i = i++`, where `i` is modified and also accessed without a sequence point in between
+    #[error("operation on '{0}' may be undefined")]
+    SequencePoint(InternedStr),
+
+    /// `p + i` where `i` is a constant and `i * sizeof(*p)` doesn't fit in a `ptrdiff_t`
+    #[error("offset of {0} elements of size {1} overflows 'ptrdiff_t'")]
+    PointerOffsetOverflow(crate::arch::PTRDIFF_T, crate::arch::SIZE_T),
+
+    /// An implicit conversion from one enum type to another, like `enum shape s = RED;`
+    #[error("implicit conversion from '{0}' to different enumeration type '{1}'")]
+    EnumConversion(Type, Type),
+
+    /// A constant converted to an enum that doesn't have an enumerator with that value
+    #[error("integer constant {0} is not a value of '{1}'")]
+    AssignEnum(i64, Type),
+
+    /// A pointer to `const` implicitly converted to a pointer to a non-`const` type
+    #[error("conversion of '{0}' discards `const` qualifier from pointer target type")]
+    DiscardedQualifiers(String),
+
+    /// `char **` converted to `const char **`, which could be used to modify a `const char`
+    #[error("converting '{0}' adds `const` to a nested pointer type without making every level in between `const`")]
+    UnsafeQualifierConversion(String),
+
+    /// `if (arr)`, `if (f)`, or `if (&x)`, which can never be null
+    #[error("address of {0} '{1}' will always evaluate to 'true'")]
+    AddressAlwaysTrue(&'static str, InternedStr),
+
+    /// `if (0)` or `while (0)`, where the condition is a constant.
+    ///
+    /// Conditions from macros, like `do { ... } while (0)`, are not reported.
+    #[error("condition is always {0}")]
+    ConstantCondition(bool),
+
+    /// `while (x);` or `if (x);`, which is usually a typo.
+    ///
+    /// This also warns for deliberate loops like `while (spin());`;
+    /// write `{}` or put the `;` on its own line to show the body is meant to be empty.
+    #[error("{0} has empty body")]
+    EmptyBody(&'static str),
+
+    /// A statement right after a call to a function that never returns, like `exit(1); x = 2;`.
+    ///
+    /// `break` and `return` are allowed, since they're often written defensively after the call.
+    #[error("code will never be executed")]
+    UnreachableCode,
+
+    /// `sizeof(a)` where `a` is a parameter declared as an array, like `void f(int a[10])`.
+    ///
+    /// The parameter was adjusted to a pointer, so this is the size of the pointer, not the array.
+    #[error("sizeof on array function parameter will return size of '{1}' instead of '{0}'")]
+    SizeofArrayArgument(Type, Type),
+
+    /// A call through a declaration without a prototype, like `int f(); f(1, 2);`,
+    /// with a different number of arguments than a later prototype or definition.
+    #[error("call to '{name}' with {actual} argument{}, but it is {} taking {expected}",
+            if *(.actual) == 1 { "" } else { "s" },
+            if *(.defined) { "defined" } else { "declared" })]
+    UnprototypedArgumentNumber {
+        name: InternedStr,
+        actual: usize,
+        expected: usize,
+        /// whether `declaration` is the definition of the function
+        defined: bool,
+        declaration: Location,
+    },
+
+    /// A call through a declaration without a prototype, like `int f(); f(1.0);`,
+    /// where an argument doesn't match the type of the parameter in a later prototype or definition.
+    #[error("argument {position} in call to '{name}' has type '{actual}', but it is {} taking '{expected}'",
+            if *(.defined) { "defined" } else { "declared" })]
+    UnprototypedArgumentType {
+        name: InternedStr,
+        /// counting from 1
+        position: usize,
+        actual: Type,
+        expected: Type,
+        defined: bool,
+        declaration: Location,
+    },
+
+    /// An integer converted to a floating type by the usual arithmetic conversions,
+    /// where the integer is wider than the floating type, like `long + float`.
+    #[error("implicit conversion from '{0}' to '{1}' may lose precision")]
+    ImplicitIntFloatConversion(Type, Type),
+
+    /// An integer constant that can't be represented exactly in the floating type it's converted to,
+    /// like `f == 16777217` for a `float f`.
+    #[error("implicit conversion from '{0}' to '{1}' changes value from {2} to {3}")]
+    InexactIntFloatConstant(Type, Type, Literal, Literal),
+
+    /// A `float` constant converted to `double`, like `d == 0.1f`,
+    /// where the `float` is only an approximation of the number written.
+    #[error("float constant '{0}f' is converted to 'double' as {}", f64::from(*.0))]
+    FloatConstantPromotion(f32),
+
+    /// (attribute, function)
+    #[error("{0} '{1}' should take no parameters and return 'void'")]
+    ConstructorSignature(&'static str, InternedStr),
 }
 
-impl<T: Into<String>> From<T> for Warning {
-    fn from(msg: T) -> Warning {
-        Warning::Generic(msg.into())
+/// What kind of declaration was hidden by a declaration in an inner scope, for `-Wshadow`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Shadowed {
+    Local,
+    Parameter,
+    Global,
+    /// Shadowing a typedef changes how the name parses
+    Typedef,
+    /// Shadowing an enum constant changes the meaning of the name in constant expressions
+    EnumConstant,
+}
+
+impl std::fmt::Display for Shadowed {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(match self {
+            Shadowed::Local => "a previous local",
+            Shadowed::Parameter => "a parameter",
+            Shadowed::Global => "a global declaration",
+            Shadowed::Typedef => "a typedef; it is no longer a type name in this scope",
+            Shadowed::EnumConstant => {
+                "an enumeration constant; it no longer refers to the constant in this scope"
+            }
+        })
+    }
+}
+
+/// Which flags enable a warning.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum WarningGroup {
+    /// Enabled unless turned off with `-Wno-<name>`
+    Default,
+    /// Enabled by `-Wall`
+    All,
+    /// Enabled by `-Wextra`
+    Extra,
+    /// Enabled by `-pedantic`; these are extensions to the selected standard
+    Pedantic,
+}
+
+/// The name and group of every warning.
+///
+/// Each name is the same as the one GCC or Clang uses for the equivalent warning, if there is one.
+const WARNINGS: &[(&str, WarningGroup)] = &[
+    ("cpp", WarningGroup::Default),
+    ("extra-semi", WarningGroup::Default),
+    ("ignored-qualifiers", WarningGroup::Extra),
+    ("duplicate-decl-specifier", WarningGroup::Default),
+    ("ignored-cast-qualifiers", WarningGroup::Extra),
+    ("empty-declaration", WarningGroup::Default),
+    ("unknown-pragmas", WarningGroup::All),
+    ("variadic-macros", WarningGroup::Default),
+    ("implicit-int", WarningGroup::Default),
+    ("extern-initializer", WarningGroup::Default),
+    ("main", WarningGroup::Default),
+    ("unknown-escape-sequence", WarningGroup::Default),
+    ("gnu-binary-literal", WarningGroup::Pedantic),
+    ("unsupported-bitfields", WarningGroup::Default),
+    ("constant-conversion", WarningGroup::Default),
+    ("float-conversion", WarningGroup::Default),
+    ("unknown-warning-option", WarningGroup::Default),
+    ("parentheses", WarningGroup::Default),
+    ("unused-variable", WarningGroup::All),
+    ("unused-parameter", WarningGroup::Extra),
+    ("unused-but-set-variable", WarningGroup::All),
+    ("unused-function", WarningGroup::All),
+    ("implicit-fallthrough", WarningGroup::Extra),
+    ("format", WarningGroup::Default),
+    ("format-insufficient-args", WarningGroup::Default),
+    ("format-extra-args", WarningGroup::Default),
+    ("format-invalid-specifier", WarningGroup::Default),
+    ("uninitialized", WarningGroup::Default),
+    ("maybe-uninitialized", WarningGroup::Default),
+    ("return-type", WarningGroup::Default),
+    ("shadow", WarningGroup::All),
+    ("switch-outside-range", WarningGroup::Default),
+    ("dead-store", WarningGroup::All),
+    ("deprecated-declarations", WarningGroup::Default),
+    ("implicit-function-declaration", WarningGroup::Default),
+    ("c99-extensions", WarningGroup::Pedantic),
+    ("c2x-extensions", WarningGroup::Pedantic),
+    ("unused-value", WarningGroup::Default),
+    ("missing-prototypes", WarningGroup::Extra),
+    ("strict-prototypes", WarningGroup::Extra),
+    ("old-style-definition", WarningGroup::Extra),
+    ("gnu-conditional-omitted-operand", WarningGroup::Pedantic),
+    ("compare-distinct-pointer-types", WarningGroup::Default),
+    ("pointer-type-mismatch", WarningGroup::Default),
+    ("incompatible-pointer-types", WarningGroup::Default),
+    ("function-pointer-conversion", WarningGroup::Pedantic),
+    ("void-pointer-ordering", WarningGroup::Pedantic),
+    ("pointer-arith", WarningGroup::Pedantic),
+    ("register-array-subscript", WarningGroup::Pedantic),
+    ("promoted-bitwise", WarningGroup::Extra),
+    ("sequence-point", WarningGroup::All),
+    ("pointer-offset-overflow", WarningGroup::Default),
+    ("enum-conversion", WarningGroup::Extra),
+    ("assign-enum", WarningGroup::Extra),
+    ("discarded-qualifiers", WarningGroup::Default),
+    (
+        "incompatible-pointer-types-discards-qualifiers",
+        WarningGroup::Default,
+    ),
+    ("pointer-bool-conversion", WarningGroup::Default),
+    ("constant-condition", WarningGroup::Extra),
+    ("empty-body", WarningGroup::Default),
+    ("unreachable-code", WarningGroup::Extra),
+    ("sizeof-array-argument", WarningGroup::Default),
+    ("unprototyped-call", WarningGroup::Default),
+    ("unprototyped-call-type", WarningGroup::Default),
+    ("implicit-int-float-conversion", WarningGroup::Default),
+    ("implicit-const-int-float-conversion", WarningGroup::Default),
+    ("float-constant-promotion", WarningGroup::Default),
+    ("constructor-signature", WarningGroup::Default),
+];
+
+impl Warning {
+    /// The name used to refer to this warning in flags, e.g. `-Wno-<name>`.
+    pub fn name(&self) -> &'static str {
+        use Warning::*;
+        match self {
+            User(_) => "cpp",
+            ExtraneousSemicolon(_) => "extra-semi",
+            FunctionQualifiersIgnored(_) => "ignored-qualifiers",
+            DuplicateSpecifier(_, _) => "duplicate-decl-specifier",
+            IgnoredQualifier(_) => "ignored-cast-qualifiers",
+            EmptyDeclaration => "empty-declaration",
+            IgnoredPragma => "unknown-pragmas",
+            IgnoredVariadic => "variadic-macros",
+            ImplicitInt => "implicit-int",
+            ExtraneousExtern => "extern-initializer",
+            NonStandardMainSignature => "main",
+            UnknownEscape(_) => "unknown-escape-sequence",
+            BinaryLiteral => "gnu-binary-literal",
+            IgnoredBitfield => "unsupported-bitfields",
+            LossyConstantConversion(_, _, _) => "constant-conversion",
+            LossyFloatConversion(_) => "float-conversion",
+            UnknownWarningOption(_) => "unknown-warning-option",
+            AssignmentInCondition => "parentheses",
+            UnusedVariable(_) => "unused-variable",
+            UnusedParameter(_) => "unused-parameter",
+            UnusedButSetVariable(_) => "unused-but-set-variable",
+            UnusedStatic(_) => "unused-function",
+            ImplicitFallthrough => "implicit-fallthrough",
+            FormatTypeMismatch(_, _, _, _) => "format",
+            MissingFormatArgument(_) => "format-insufficient-args",
+            ExtraFormatArgument => "format-extra-args",
+            InvalidFormatSpecifier(_) => "format-invalid-specifier",
+            Uninitialized(_) => "uninitialized",
+            MaybeUninitialized(_) => "maybe-uninitialized",
+            MissingReturn(_) => "return-type",
+            Shadow(_, _, _) => "shadow",
+            CaseOutOfRange(_, _) => "switch-outside-range",
+            DeadStore(_, _) => "dead-store",
+            Deprecated(_, _, _) => "deprecated-declarations",
+            ImplicitFunctionDeclaration(_) => "implicit-function-declaration",
+            C99Extension(_) => "c99-extensions",
+            C2xExtension(_) => "c2x-extensions",
+            UnusedValue => "unused-value",
+            MissingPrototype(_) => "missing-prototypes",
+            StrictPrototype => "strict-prototypes",
+            OldStyleDefinition => "old-style-definition",
+            GnuConditionalOmittedOperand => "gnu-conditional-omitted-operand",
+            DistinctPointerComparison(_, _) => "compare-distinct-pointer-types",
+            PointerTypeMismatch(_, _) => "pointer-type-mismatch",
+            IncompatiblePointerTypes(_, _) => "incompatible-pointer-types",
+            FunctionPointerConversion(_, _) => "function-pointer-conversion",
+            VoidPointerOrdering(_, _, _) => "void-pointer-ordering",
+            VoidPointerArithmetic => "pointer-arith",
+            RegisterArraySubscript(_) => "register-array-subscript",
+            PromotedBitwise(_, _, _, _) => "promoted-bitwise",
+            SequencePoint(_) => "sequence-point",
+            PointerOffsetOverflow(_, _) => "pointer-offset-overflow",
+            EnumConversion(_, _) => "enum-conversion",
+            AssignEnum(_, _) => "assign-enum",
+            DiscardedQualifiers(_) => "discarded-qualifiers",
+            UnsafeQualifierConversion(_) => "incompatible-pointer-types-discards-qualifiers",
+            AddressAlwaysTrue(_, _) => "pointer-bool-conversion",
+            ConstantCondition(_) => "constant-condition",
+            EmptyBody(_) => "empty-body",
+            UnreachableCode => "unreachable-code",
+            SizeofArrayArgument(..) => "sizeof-array-argument",
+            UnprototypedArgumentNumber { .. } => "unprototyped-call",
+            UnprototypedArgumentType { .. } => "unprototyped-call-type",
+            ImplicitIntFloatConversion(..) => "implicit-int-float-conversion",
+            InexactIntFloatConstant(..) => "implicit-const-int-float-conversion",
+            FloatConstantPromotion(_) => "float-constant-promotion",
+            ConstructorSignature(_, _) => "constructor-signature",
+        }
+    }
+    /// Other locations that help explain this warning, such as a previous declaration.
+    pub fn notes(&self) -> Vec<Note> {
+        match self {
+            Warning::Shadow(_, _, Some(previous)) => {
+                vec![previous.with("previous declaration is here".to_owned())]
+            }
+            Warning::Deprecated(name, _, declared) => {
+                vec![declared.with(format!("'{}' was marked deprecated here", name))]
+            }
+            Warning::UnprototypedArgumentNumber {
+                name,
+                defined,
+                declaration,
+                ..
+            }
+            | Warning::UnprototypedArgumentType {
+                name,
+                defined,
+                declaration,
+                ..
+            } => {
+                let kind = if *defined { "defined" } else { "declared" };
+                vec![declaration.with(format!("'{}' is {} here", name, kind))]
+            }
+            _ => Vec::new(),
+        }
+    }
+    /// Suggested edits that would fix this warning, if it were reported at `location`.
+    pub fn fix_its(&self, location: Location) -> Vec<FixIt> {
+        match self {
+            Warning::AssignmentInCondition => vec![FixIt::new(
+                "use '==' to compare for equality",
+                location,
+                "==",
+            )],
+            Warning::ImplicitFallthrough => vec![FixIt::replace(
+                "insert '__attribute__((fallthrough));' to silence this warning",
+                location,
+                0,
+                0,
+                "__attribute__((fallthrough)); ",
+            )],
+            Warning::EmptyBody(_) => vec![FixIt::new(
+                "replace ';' with '{}' to silence this warning",
+                location,
+                "{}",
+            )],
+            // `location` is the conversion in the format string, not the argument
+            Warning::FormatTypeMismatch(_, _, _, Some(argument)) => vec![FixIt::replace(
+                "take the address with '&'",
+                *argument,
+                0,
+                0,
+                "&",
+            )],
+            _ => Vec::new(),
+        }
+    }
+    /// How this warning is reported if no flags are passed.
+    pub fn default_level(&self) -> WarningLevel {
+        match self.group() {
+            WarningGroup::Default => WarningLevel::Warn,
+            WarningGroup::All | WarningGroup::Extra | WarningGroup::Pedantic => {
+                WarningLevel::Ignore
+            }
+        }
+    }
+    /// Which flags enable this warning.
+    pub fn group(&self) -> WarningGroup {
+        let name = self.name();
+        WARNINGS
+            .iter()
+            .find(|(known, _)| *known == name)
+            .map(|(_, group)| *group)
+            .expect("every warning should be in WARNINGS")
+    }
+}
+
+/// How a warning should be reported.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum WarningLevel {
+    /// Don't report the warning at all.
+    Ignore,
+    /// Report the warning, but keep compiling.
+    Warn,
+    /// Report the warning as an error.
+    Error,
+}
+
+/// A single `-W` flag.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum WarningFlag {
+    /// `-Wall` or `-Wextra`
+    Group(WarningGroup),
+    /// `-W<name>` or `-Wno-<name>`
+    Enable(&'static str, bool),
+    /// `-Werror` or `-Wno-error`
+    AllErrors(bool),
+    /// `-Werror=<name>` or `-Wno-error=<name>`
+    Error(&'static str, bool),
+    /// `-pedantic` or `-pedantic-errors`
+    Pedantic(bool),
+}
+
+/// Which warnings to report, set by `-W` flags.
+///
+/// Flags are applied in order, so later flags override earlier ones:
+/// `-Wno-main -Wmain` reports `-Wmain` warnings, but `-Wmain -Wno-main` does not.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WarningOptions {
+    flags: Vec<WarningFlag>,
+}
+
+impl WarningOptions {
+    /// Add a `-W` flag, without the leading `-W`. For example, `all`, `no-main` or `error=main`.
+    ///
+    /// `pedantic` and `error=pedantic` are the same as `-pedantic` and `-pedantic-errors`.
+    ///
+    /// If the flag doesn't name a known warning, it is ignored and
+    /// `Warning::UnknownWarningOption` is returned instead.
+    pub fn add_flag(&mut self, flag: &str) -> Result<(), Warning> {
+        let known = |name: &str| {
+            WARNINGS
+                .iter()
+                .find(|(known, _)| *known == name)
+                .map(|(known, _)| *known)
+                .ok_or_else(|| Warning::UnknownWarningOption(flag.into()))
+        };
+        let parsed = match flag {
+            "all" => WarningFlag::Group(WarningGroup::All),
+            "extra" => WarningFlag::Group(WarningGroup::Extra),
+            "error" => WarningFlag::AllErrors(true),
+            "no-error" => WarningFlag::AllErrors(false),
+            "pedantic" => WarningFlag::Pedantic(false),
+            "error=pedantic" => WarningFlag::Pedantic(true),
+            _ => {
+                if let Some(name) = flag.strip_prefix("error=") {
+                    WarningFlag::Error(known(name)?, true)
+                } else if let Some(name) = flag.strip_prefix("no-error=") {
+                    WarningFlag::Error(known(name)?, false)
+                } else if let Some(name) = flag.strip_prefix("no-") {
+                    WarningFlag::Enable(known(name)?, false)
+                } else {
+                    WarningFlag::Enable(known(flag)?, true)
+                }
+            }
+        };
+        self.flags.push(parsed);
+        Ok(())
+    }
+    /// How `warning` should be reported.
+    pub fn level(&self, warning: &Warning) -> WarningLevel {
+        let (name, group) = (warning.name(), warning.group());
+        let mut enabled = warning.default_level() != WarningLevel::Ignore;
+        let mut error = false;
+        for flag in &self.flags {
+            match *flag {
+                WarningFlag::Group(flag_group) => enabled |= flag_group == group,
+                WarningFlag::Enable(flag_name, enable) if flag_name == name => enabled = enable,
+                WarningFlag::AllErrors(all) => error = all,
+                // like GCC, `-Werror=<name>` also enables the warning
+                WarningFlag::Error(flag_name, true) if flag_name == name => {
+                    enabled = true;
+                    error = true;
+                }
+                WarningFlag::Error(flag_name, false) if flag_name == name => error = false,
+                WarningFlag::Pedantic(errors) if group == WarningGroup::Pedantic => {
+                    enabled = true;
+                    error |= errors;
+                }
+                WarningFlag::Enable(..) | WarningFlag::Error(..) | WarningFlag::Pedantic(_) => {}
+            }
+        }
+        match (enabled, error) {
+            (false, _) => WarningLevel::Ignore,
+            (true, false) => WarningLevel::Warn,
+            (true, true) => WarningLevel::Error,
+        }
+    }
+    /// Remove the warnings that are disabled,
+    /// and return the ones that should be reported as errors.
+    pub fn apply(&self, warnings: &mut VecDeque<CompileWarning>) -> VecDeque<CompileError> {
+        let mut errors = VecDeque::new();
+        warnings.retain(|warning| match self.level(&warning.data) {
+            WarningLevel::Ignore => false,
+            WarningLevel::Warn => true,
+            WarningLevel::Error => {
+                errors.push_back(warning.location.with(Error::Warning(warning.data.clone())));
+                false
+            }
+        });
+        errors
     }
 }
 
@@ -601,6 +1498,18 @@ impl CompileError {
     pub fn location(&self) -> Location {
         self.location
     }
+    /// Other locations that help explain this error.
+    ///
+    /// This includes the notes from [`Error::notes`],
+    /// as well as every macro the error was expanded from, innermost first.
+    pub fn notes(&self) -> Vec<Note> {
+        let mut notes = self.data.notes();
+        notes.extend(self.location.expansions().into_iter().map(|expansion| {
+            let message = format!("in expansion of macro '{}'", expansion.name);
+            expansion.site.with(message)
+        }));
+        notes
+    }
     pub fn is_lex_err(&self) -> bool {
         self.data.is_lex_err()
     }
@@ -610,9 +1519,45 @@ impl CompileError {
     pub fn is_semantic_err(&self) -> bool {
         self.data.is_semantic_err()
     }
+    pub fn is_internal_compiler_error(&self) -> bool {
+        self.data.is_internal_compiler_error()
+    }
 }
 
 impl Error {
+    /// A stable name for this kind of error, e.g. `undeclared-var` or `syntax`.
+    ///
+    /// Semantic errors each have their own code, and warnings turned into errors use the name of the warning.
+    /// Every other kind of error shares a single code.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::Semantic(err) => err.code(),
+            Error::Syntax(_) => "syntax",
+            Error::PreProcessor(_) => "preprocessor",
+            Error::Lex(_) => "lex",
+            Error::Warning(warning) => warning.name(),
+            Error::InternalCompilerError(_) => "internal-compiler-error",
+        }
+    }
+    /// Other locations that help explain this error, such as a previous declaration.
+    pub fn notes(&self) -> Vec<Note> {
+        match self {
+            Error::Semantic(err) => err.notes(),
+            Error::Warning(warning) => warning.notes(),
+            _ => Vec::new(),
+        }
+    }
+    /// Suggested edits that would fix this error, if it were reported at `location`.
+    pub fn fix_its(&self, location: Location) -> Vec<FixIt> {
+        match self {
+            Error::Semantic(err) => err.fix_its(location),
+            Error::Syntax(SyntaxError::ExpectedSemicolon(_)) => {
+                vec![FixIt::new("insert ';'", location, ";")]
+            }
+            Error::Warning(warning) => warning.fix_its(location),
+            _ => Vec::new(),
+        }
+    }
     pub fn is_lex_err(&self) -> bool {
         if let Error::Lex(_) = self {
             true
@@ -634,6 +1579,9 @@ impl Error {
             false
         }
     }
+    pub fn is_internal_compiler_error(&self) -> bool {
+        matches!(self, Error::InternalCompilerError(_))
+    }
 }
 
 impl From<Locatable<String>> for CompileError {
@@ -666,22 +1614,206 @@ impl From<Locatable<LexError>> for CompileError {
     }
 }
 
-impl From<Locatable<String>> for Locatable<SemanticError> {
-    fn from(err: Locatable<String>) -> Self {
-        err.map(SemanticError::Generic)
+impl SemanticError {
+    /// A stable name for this kind of error, e.g. `undeclared-var`.
+    ///
+    /// Unlike the message, this does not depend on the types or names involved.
+    pub fn code(&self) -> &'static str {
+        use SemanticError::*;
+        match self {
+            Generic(_) => "generic",
+            InvalidSpecifier { .. } => "invalid-specifier",
+            NotAQualifier(_) => "not-a-qualifier",
+            InvalidRestrict(_) => "invalid-restrict",
+            QualifiedArray => "qualified-array",
+            TooLong(_) => "too-long",
+            ConflictingStorageClass(_, _) => "conflicting-storage-class",
+            ConflictingType(_, _) => "conflicting-type",
+            CannotBeSigned(_) => "cannot-be-signed",
+            ConflictingSigned => "conflicting-signed",
+            AutoAtGlobalScope => "auto-at-global-scope",
+            RegisterAtGlobalScope => "register-at-global-scope",
+            EmptyProgram => "empty-program",
+            BareTagSpecifier(_) => "bare-tag-specifier",
+            TagMismatch(_, _) => "tag-mismatch",
+            TagRedefinition(_, _) => "tag-redefinition",
+            EnumForwardReference(_) => "enum-forward-reference",
+            EmptyStruct => "empty-struct",
+            EmptyEnum => "empty-enum",
+            ZeroWidthBitfield(_) => "zero-width-bitfield",
+            BitfieldTooWide { .. } => "bitfield-too-wide",
+            IncompleteMember(_) => "incomplete-member",
+            MemberStorageClass(_, _) => "member-storage-class",
+            AbstractDeclaration => "abstract-declaration",
+            NonIntegralLength => "non-integral-length",
+            NegativeLength => "negative-length",
+            ParameterStorageClass(_) => "parameter-storage-class",
+            DuplicateParameter(_) => "duplicate-parameter",
+            IllegalReturnType(_) => "illegal-return-type",
+            ArrayStoringFunction(_) => "array-storing-function",
+            InvalidVoidParameter => "invalid-void-parameter",
+            VoidVarargs => "void-varargs",
+            VarargsWithoutParam => "varargs-without-param",
+            EnumOverflow => "enum-overflow",
+            VoidType => "void-type",
+            NotATypedef(_) => "not-a-typedef",
+            UndeclaredVar(_, _) => "undeclared-var",
+            FuncNameOutsideFunction(_) => "func-name-outside-function",
+            TypedefInExpressionContext => "typedef-in-expression-context",
+            IllegalStorageClass(_) => "illegal-storage-class",
+            IdInTypeName(_) => "id-in-type-name",
+            NonIntegralExpr(_) => "non-integral-expr",
+            InvalidCast(_, _) => "invalid-cast",
+            IncompatibleAggregate(_, _) => "incompatible-aggregate",
+            NotAssignable(_) => "not-assignable",
+            NotConvertibleToBool(_) => "not-convertible-to-bool",
+            InvalidSize(_) => "invalid-size",
+            InvalidAdd(_, _, _) => "invalid-add",
+            NonIntegralModulo(_, _) => "non-integral-modulo",
+            NonArithmeticOperands(_, _, _) => "non-arithmetic-operands",
+            PointerAddUnknownSize(_) => "pointer-add-unknown-size",
+            CharacterTooLarge(_, _) => "character-too-large",
+            PointerArithmetic(_) => "pointer-arithmetic",
+            NotAFunction(_) => "not-a-function",
+            WrongArgumentNumber { .. } => "wrong-argument-number",
+            InvalidArgument { .. } => "invalid-argument",
+            IncompleteDefinitionUsed(_) => "incomplete-definition-used",
+            NotAMember(_, _) => "not-a-member",
+            NotAStruct(_) => "not-a-struct",
+            NotAStructPointer(_) => "not-a-struct-pointer",
+            MemberOfPointer(_) => "member-of-pointer",
+            MissingAddressOf(_, _) => "missing-address-of",
+            NotAPointer(_) => "not-a-pointer",
+            NonIntegralSubscript(_) => "non-integral-subscript",
+            VoidDereference(_) => "void-dereference",
+            InvalidAddressOf(_) => "invalid-address-of",
+            InvalidIncrement(_) => "invalid-increment",
+            InvalidUnaryOperand(_, _, _) => "invalid-unary-operand",
+            IncompatibleTypes(_, _) => "incompatible-types",
+            ConstOverflow { .. } => "const-overflow",
+            DivideByZero => "divide-by-zero",
+            NegativeShift { .. } => "negative-shift",
+            TooManyShiftBits { .. } => "too-many-shift-bits",
+            NotConstant(_) => "not-constant",
+            NullPointerDereference => "null-pointer-dereference",
+            InvalidRelationalType(_, _, _) => "invalid-relational-type",
+            FunctionPointerOrdering(_, _, _) => "function-pointer-ordering",
+            FloatPointerCast(_) => "float-pointer-cast",
+            NonScalarCast(_) => "non-scalar-cast",
+            VoidCast => "void-cast",
+            StructCast => "struct-cast",
+            UnreachableStatement => "unreachable-statement",
+            LabelRedeclaration(_, _) => "label-redeclaration",
+            UndeclaredLabel(_) => "undeclared-label",
+            CaseOutsideSwitch { .. } => "case-outside-switch",
+            BreakOutsideLoop => "break-outside-loop",
+            ContinueOutsideLoop => "continue-outside-loop",
+            DuplicateCase { .. } => "duplicate-case",
+            DuplicateDefault(_) => "duplicate-default",
+            NotInStandard(_, _) => "not-in-standard",
+            EmptyInitializer => "empty-initializer",
+            AggregateInitializingScalar(_, _) => "aggregate-initializing-scalar",
+            TooManyMembers(_, _) => "too-many-members",
+            InvalidAggregateInitializer(_) => "invalid-aggregate-initializer",
+            NonFirstUnionMember => "non-first-union-member",
+            InvalidFuncStorageClass(_) => "invalid-func-storage-class",
+            MissingParamName(_, _) => "missing-param-name",
+            ForwardDeclarationIncomplete(_, _) => "forward-declaration-incomplete",
+            Redefinition(_, _) => "redefinition",
+            IncompatibleRedeclaration(_, _, _, _) => "incompatible-redeclaration",
+            FuncQualifiersNotAllowed(_) => "func-qualifiers-not-allowed",
+            AttributeNeedsLinkage(_, _) => "attribute-needs-linkage",
+            UndefinedAliasTarget(_, _) => "undefined-alias-target",
+            AliasRedefinition(_) => "alias-redefinition",
+            NonIntegralSwitch(_) => "non-integral-switch",
+            MissingReturnValue(_) => "missing-return-value",
+            ReturnFromVoid(_) => "return-from-void",
+        }
     }
-}
-
-impl<S: Into<String>> From<S> for SemanticError {
-    fn from(err: S) -> Self {
-        SemanticError::Generic(err.into())
+    /// Other locations that help explain this error, such as a previous declaration.
+    pub fn notes(&self) -> Vec<Note> {
+        use SemanticError::*;
+        let note = |location: &Location, msg: &str| location.with(msg.to_owned());
+        match self {
+            Redefinition(_, Some(previous)) => vec![note(previous, "previous definition is here")],
+            IncompatibleRedeclaration(_, _, _, Some(previous)) => {
+                vec![note(previous, "previous declaration is here")]
+            }
+            LabelRedeclaration(_, previous) => vec![note(previous, "previous label is here")],
+            WrongArgumentNumber {
+                declaration: Some(declaration),
+                ..
+            } => vec![note(declaration, "function declared here")],
+            InvalidArgument {
+                position,
+                parameter,
+                actual,
+                expected,
+            } => {
+                let declared = parameter.with(format!("parameter {} declared here", position));
+                std::iter::once(declared)
+                    .chain(aggregate_definitions(&[expected, actual]))
+                    .collect()
+            }
+            DuplicateCase {
+                previous: Some(previous),
+                ..
+            } => vec![note(previous, "previous case is here")],
+            DuplicateDefault(Some(previous)) => vec![note(previous, "previous default is here")],
+            IncompatibleAggregate(target, value) => aggregate_definitions(&[target, value]),
+            _ => Vec::new(),
+        }
+    }
+    /// Suggested edits that would fix this error, if it were reported at `location`.
+    pub fn fix_its(&self, location: Location) -> Vec<FixIt> {
+        use SemanticError::*;
+        match self {
+            UndeclaredVar(_, Some(similar)) => vec![FixIt::new(
+                &format!("did you mean '{}'?", similar),
+                location,
+                &similar.resolve_and_clone(),
+            )],
+            // `location` starts at the `.` or `->`
+            MemberOfPointer(_) => vec![FixIt::replace(
+                "use '->' to access a member through a pointer",
+                location,
+                0,
+                1,
+                "->",
+            )],
+            NotAStructPointer(_) => vec![FixIt::replace(
+                "use '.' to access a member of a struct or union",
+                location,
+                0,
+                2,
+                ".",
+            )],
+            MissingAddressOf(_, _) => vec![FixIt::replace(
+                "take the address with '&'",
+                location,
+                0,
+                0,
+                "&",
+            )],
+            _ => Vec::new(),
+        }
     }
 }
 
-impl<S: Into<String>> From<S> for SyntaxError {
-    fn from(err: S) -> Self {
-        SyntaxError::Generic(err.into())
-    }
+/// Where each struct or union in `types` was defined.
+///
+/// Both types in a mismatch may print the same if they have the same tag, so this shows where each one came from.
+fn aggregate_definitions(types: &[&Type]) -> Vec<Note> {
+    types
+        .iter()
+        .filter_map(|ctype| match ctype {
+            Type::Struct(stype) | Type::Union(stype) => {
+                let definition = stype.definition()?;
+                Some(definition.with(format!("'{}' is defined here", ctype)))
+            }
+            _ => None,
+        })
+        .collect()
 }
 
 pub(crate) trait Recover {
@@ -713,6 +1845,487 @@ impl<T, E: Into<CompileError>> Recover for RecoverableResult<T, Vec<E>> {
 mod tests {
     use super::*;
 
+    /// One of each warning
+    fn all_warnings() -> Vec<Warning> {
+        use Warning::*;
+        let pointer = |to| Type::Pointer(Box::new(to), Default::default());
+        vec![
+            User(vec![]),
+            ExtraneousSemicolon("at top level"),
+            FunctionQualifiersIgnored(Default::default()),
+            DuplicateSpecifier(ast::UnitSpecifier::Int, 2),
+            IgnoredQualifier(Default::default()),
+            EmptyDeclaration,
+            IgnoredPragma,
+            IgnoredVariadic,
+            ImplicitInt,
+            ExtraneousExtern,
+            NonStandardMainSignature,
+            UnknownEscape('q'),
+            BinaryLiteral,
+            IgnoredBitfield,
+            LossyConstantConversion(Type::Char(true), Literal::Int(44), Literal::Int(300)),
+            LossyFloatConversion(0.1),
+            UnknownWarningOption("foo".into()),
+            AssignmentInCondition,
+            UnusedVariable("x".into()),
+            UnusedParameter("x".into()),
+            UnusedButSetVariable("x".into()),
+            UnusedStatic("f".into()),
+            ImplicitFallthrough,
+            FormatTypeMismatch(Type::Long(true), Type::Int(true), 2, None),
+            MissingFormatArgument("%d".into()),
+            ExtraFormatArgument,
+            InvalidFormatSpecifier("%y".into()),
+            Uninitialized("x".into()),
+            MaybeUninitialized("x".into()),
+            MissingReturn("f".into()),
+            Shadow("x".into(), Shadowed::Local, None),
+            CaseOutOfRange(300, Type::Char(true)),
+            DeadStore("x".into(), false),
+            Deprecated("f".into(), None, Location::default()),
+            ImplicitFunctionDeclaration("f".into()),
+            C99Extension("'long long' types"),
+            C2xExtension("[[]] attributes"),
+            UnusedValue,
+            MissingPrototype("f".into()),
+            StrictPrototype,
+            OldStyleDefinition,
+            GnuConditionalOmittedOperand,
+            DistinctPointerComparison(pointer(Type::Int(true)), pointer(Type::Char(true))),
+            PointerTypeMismatch(pointer(Type::Int(true)), pointer(Type::Char(true))),
+            IncompatiblePointerTypes(pointer(Type::Int(true)), pointer(Type::Char(true))),
+            FunctionPointerConversion(pointer(Type::Void), pointer(Type::Void)),
+            VoidPointerOrdering(lex::ComparisonToken::Less, Type::Void, Type::Void),
+            VoidPointerArithmetic,
+            RegisterArraySubscript("a".into()),
+            PromotedBitwise(
+                "~c".into(),
+                Type::Char(false),
+                Type::Int(true),
+                Some(("~c".into(), "-1".into())),
+            ),
+            SequencePoint("i".into()),
+            PointerOffsetOverflow(1 << 62, 4),
+            EnumConversion(
+                Type::Enum(Some("shape".into()), Default::default()),
+                Type::Enum(Some("color".into()), Default::default()),
+            ),
+            AssignEnum(17, Type::Enum(Some("color".into()), Default::default())),
+            DiscardedQualifiers("p".into()),
+            UnsafeQualifierConversion("p".into()),
+            AddressAlwaysTrue("array", "a".into()),
+            ConstantCondition(false),
+            EmptyBody("while loop"),
+            UnreachableCode,
+            SizeofArrayArgument(
+                Type::Array(Box::new(Type::Int(true)), types::ArrayType::Fixed(10)),
+                pointer(Type::Int(true)),
+            ),
+            UnprototypedArgumentNumber {
+                name: "f".into(),
+                actual: 3,
+                expected: 1,
+                defined: true,
+                declaration: Location::default(),
+            },
+            UnprototypedArgumentType {
+                name: "f".into(),
+                position: 1,
+                actual: Type::Double,
+                expected: Type::Int(true),
+                defined: false,
+                declaration: Location::default(),
+            },
+            ImplicitIntFloatConversion(Type::Long(true), Type::Float),
+            InexactIntFloatConstant(
+                Type::Long(true),
+                Type::Float,
+                Literal::Int(16_777_217),
+                Literal::Float(16_777_216.0),
+            ),
+            FloatConstantPromotion(0.1),
+            ConstructorSignature("constructor", "f".into()),
+        ]
+    }
+
+    /// One of each semantic error.
+    ///
+    /// If you add a variant to `SemanticError`, add it here too.
+    fn all_semantic_errors() -> Vec<SemanticError> {
+        use SemanticError::*;
+        let int = || Type::Int(true);
+        let specifier = || ast::DeclarationSpecifier::Unit(ast::UnitSpecifier::Int);
+        let symbol = || {
+            hir::Variable {
+                id: "x".into(),
+                qualifiers: Default::default(),
+                storage_class: StorageClass::Auto,
+                ctype: int(),
+                location: Location::default(),
+            }
+            .insert()
+        };
+        vec![
+            Generic("bad code".into()),
+            InvalidSpecifier {
+                existing: specifier(),
+                new: specifier(),
+            },
+            NotAQualifier(specifier()),
+            InvalidRestrict(int()),
+            QualifiedArray,
+            TooLong(3),
+            ConflictingStorageClass(StorageClass::Auto, StorageClass::Static),
+            ConflictingType(int(), Type::Char(true)),
+            CannotBeSigned(Type::Void),
+            ConflictingSigned,
+            AutoAtGlobalScope,
+            RegisterAtGlobalScope,
+            EmptyProgram,
+            BareTagSpecifier(Keyword::Struct),
+            TagMismatch("s".into(), Keyword::Union),
+            TagRedefinition(Keyword::Enum, "e".into()),
+            EnumForwardReference("e".into()),
+            EmptyStruct,
+            EmptyEnum,
+            ZeroWidthBitfield("x".into()),
+            BitfieldTooWide {
+                name: "x".into(),
+                width: 65,
+                ctype: int(),
+            },
+            IncompleteMember(int()),
+            MemberStorageClass(StorageClass::Extern, "x".into()),
+            AbstractDeclaration,
+            NonIntegralLength,
+            NegativeLength,
+            ParameterStorageClass(StorageClass::Static),
+            DuplicateParameter("x".into()),
+            IllegalReturnType(int()),
+            ArrayStoringFunction(int()),
+            InvalidVoidParameter,
+            VoidVarargs,
+            VarargsWithoutParam,
+            EnumOverflow,
+            VoidType,
+            NotATypedef("t".into()),
+            UndeclaredVar("x".into(), None),
+            FuncNameOutsideFunction("__func__".into()),
+            TypedefInExpressionContext,
+            IllegalStorageClass(StorageClass::Static),
+            IdInTypeName("x".into()),
+            NonIntegralExpr(Type::Double),
+            InvalidCast(int(), Type::Void),
+            IncompatibleAggregate(
+                Type::Struct(StructType::Named("a".into(), Default::default())),
+                Type::Struct(StructType::Named("b".into(), Default::default())),
+            ),
+            NotAssignable(super::NotAssignable::Rvalue),
+            NotConvertibleToBool(Type::Void),
+            InvalidSize("cannot take `sizeof` void"),
+            InvalidAdd(hir::BinaryOp::Add, int(), Type::Void),
+            NonIntegralModulo(int(), Type::Double),
+            NonArithmeticOperands(hir::BinaryOp::Mul, int(), Type::Void),
+            PointerAddUnknownSize(Type::Void),
+            CharacterTooLarge(0x12345, Type::Short(false)),
+            PointerArithmetic(Type::Void),
+            NotAFunction(int()),
+            WrongArgumentNumber {
+                actual: 1,
+                expected: 2,
+                varargs: false,
+                declaration: None,
+            },
+            InvalidArgument {
+                position: 1,
+                actual: int(),
+                expected: Type::Pointer(Box::new(int()), Default::default()),
+                parameter: Location::default(),
+            },
+            IncompleteDefinitionUsed(int()),
+            NotAMember("x".into(), int()),
+            NotAStruct(int()),
+            NotAStructPointer(int()),
+            MemberOfPointer(int()),
+            MissingAddressOf(int(), int()),
+            NotAPointer(int()),
+            NonIntegralSubscript(Type::Double),
+            VoidDereference(Type::Pointer(
+                Box::new(Type::Void),
+                hir::Qualifiers::default(),
+            )),
+            InvalidAddressOf("register variable"),
+            InvalidIncrement(Type::Void),
+            InvalidUnaryOperand("-", "float or integer", Type::Void),
+            IncompatibleTypes(int(), Type::Void),
+            ConstOverflow { is_positive: true },
+            DivideByZero,
+            NegativeShift { is_left: true },
+            TooManyShiftBits {
+                is_left: true,
+                maximum: 32,
+                ctype: int(),
+                current: 33,
+            },
+            NotConstant("x".into()),
+            NullPointerDereference,
+            InvalidRelationalType(lex::ComparisonToken::Less, int(), Type::Void),
+            FunctionPointerOrdering(lex::ComparisonToken::Less, Type::Void, Type::Void),
+            FloatPointerCast(Type::Double),
+            NonScalarCast(Type::Void),
+            VoidCast,
+            StructCast,
+            UnreachableStatement,
+            LabelRedeclaration("l".into(), Location::default()),
+            UndeclaredLabel("l".into()),
+            CaseOutsideSwitch { is_default: true },
+            BreakOutsideLoop,
+            ContinueOutsideLoop,
+            DuplicateCase {
+                value: 1,
+                previous: None,
+            },
+            DuplicateDefault(None),
+            NotInStandard("'//' comments", Standard::default()),
+            EmptyInitializer,
+            AggregateInitializingScalar(int(), 2),
+            TooManyMembers(1, 2),
+            InvalidAggregateInitializer(int()),
+            NonFirstUnionMember,
+            InvalidFuncStorageClass(StorageClass::Auto),
+            MissingParamName(1, int()),
+            ForwardDeclarationIncomplete("s".into(), "f".into()),
+            Redefinition("x".into(), None),
+            IncompatibleRedeclaration("x".into(), symbol(), symbol(), None),
+            FuncQualifiersNotAllowed(Default::default()),
+            AttributeNeedsLinkage("weak", "x".into()),
+            UndefinedAliasTarget("g".into(), "f".into()),
+            AliasRedefinition("g".into()),
+            NonIntegralSwitch(Type::Double),
+            MissingReturnValue("f".into()),
+            ReturnFromVoid("f".into()),
+        ]
+    }
+
+    #[test]
+    fn test_semantic_error_codes() {
+        let errors = all_semantic_errors();
+        let mut codes: Vec<_> = errors.iter().map(SemanticError::code).collect();
+        codes.sort_unstable();
+        codes.dedup();
+        assert_eq!(codes.len(), errors.len(), "error codes should be unique");
+        for code in codes {
+            assert!(
+                code.bytes().all(|c| c.is_ascii_lowercase() || c == b'-'),
+                "error codes should be kebab-case: {}",
+                code
+            );
+        }
+        // the codes for other kinds of errors don't overlap with semantic errors
+        let other: Vec<Error> = vec![
+            SyntaxError::EndOfFile("expression").into(),
+            CppError::EndOfFile("identifier").into(),
+            LexError::UnterminatedComment.into(),
+            Error::InternalCompilerError("oops".into()),
+        ];
+        for err in &other {
+            assert!(
+                !errors.iter().any(|e| e.code() == err.code()),
+                "{}",
+                err.code()
+            );
+        }
+        let err: Error = SemanticError::MissingReturnValue("f".into()).into();
+        assert_eq!(err.code(), "missing-return-value");
+        let warning = Error::Warning(Warning::ExtraneousSemicolon("at top level"));
+        assert_eq!(warning.code(), "extra-semi");
+    }
+
+    #[test]
+    fn test_semantic_error_display() {
+        let display = |err: SemanticError| err.to_string();
+        assert_eq!(
+            display(SemanticError::TagMismatch("s".into(), Keyword::Union)),
+            "use of 's' with type tag 'union' that does not match previous struct declaration"
+        );
+        assert_eq!(
+            display(SemanticError::TagRedefinition(Keyword::Enum, "e".into())),
+            "redefinition of enum 'e'"
+        );
+        assert_eq!(
+            display(SemanticError::BitfieldTooWide {
+                name: "x".into(),
+                width: 65,
+                ctype: Type::Int(true),
+            }),
+            "cannot have bitfield x with size 65 larger than containing type int"
+        );
+        assert_eq!(
+            display(SemanticError::NotAssignable(NotAssignable::ConstVariable(
+                "x".into()
+            ))),
+            "cannot assign to variable 'x' with `const` qualifier"
+        );
+    }
+
+    #[test]
+    fn test_warning_names() {
+        let warnings = all_warnings();
+        let mut names: Vec<_> = warnings.iter().map(Warning::name).collect();
+        names.sort_unstable();
+        names.dedup();
+        assert_eq!(
+            names.len(),
+            warnings.len(),
+            "warning names should be unique"
+        );
+        // if this fails, a variant is missing from `all_warnings` or `WARNINGS`
+        assert_eq!(names.len(), WARNINGS.len());
+        for warning in &warnings {
+            // panics if the name is missing from `WARNINGS`
+            warning.group();
+        }
+    }
+
+    #[test]
+    fn test_warning_display() {
+        let display = |warning: Warning| warning.to_string();
+        assert_eq!(
+            display(Warning::ExtraneousSemicolon("at top level")),
+            "extraneous semicolon at top level"
+        );
+        assert_eq!(
+            display(Warning::ExtraneousSemicolon(
+                "in struct declaration is not allowed by ISO"
+            )),
+            "extraneous semicolon in struct declaration is not allowed by ISO"
+        );
+        assert_eq!(
+            display(Warning::UnknownEscape('q')),
+            "unknown character escape '\\q'"
+        );
+        assert_eq!(
+            display(Warning::BinaryLiteral),
+            "binary number literals are an extension"
+        );
+        assert_eq!(
+            display(Warning::IgnoredBitfield),
+            "bitfields are not implemented and will be ignored"
+        );
+        assert_eq!(
+            display(Warning::LossyConstantConversion(
+                Type::Char(true),
+                Literal::Int(44),
+                Literal::Int(300)
+            )),
+            "conversion to char loses precision (44 != 300)"
+        );
+        assert_eq!(
+            SemanticError::NonIntegralModulo(Type::Double, Type::Int(true)).to_string(),
+            "expected integers for both operators of %, got 'double' and 'int'"
+        );
+        assert_eq!(
+            SemanticError::NonArithmeticOperands(
+                hir::BinaryOp::Mul,
+                Type::Pointer(Box::new(Type::Int(true)), Default::default()),
+                Type::Int(true)
+            )
+            .to_string(),
+            "expected float or integer types for both operands of *, got 'int *' and 'int'"
+        );
+    }
+
+    #[test]
+    fn test_default_level() {
+        for warning in all_warnings() {
+            let expected = match warning.group() {
+                WarningGroup::Default => WarningLevel::Warn,
+                _ => WarningLevel::Ignore,
+            };
+            assert_eq!(warning.default_level(), expected);
+            assert_eq!(WarningOptions::default().level(&warning), expected);
+        }
+    }
+
+    #[test]
+    fn test_warning_options() {
+        let level = |flags: &[&str], warning: Warning| {
+            let mut options = WarningOptions::default();
+            for flag in flags {
+                options.add_flag(flag).unwrap();
+            }
+            options.level(&warning)
+        };
+        assert_eq!(level(&[], Warning::ImplicitInt), WarningLevel::Warn);
+        assert_eq!(level(&[], Warning::IgnoredPragma), WarningLevel::Ignore);
+        assert_eq!(level(&["all"], Warning::IgnoredPragma), WarningLevel::Warn);
+        // -Wall doesn't include -Wextra
+        assert_eq!(
+            level(&["all"], Warning::EmptyDeclaration),
+            WarningLevel::Warn
+        );
+        assert_eq!(
+            level(&["all"], Warning::IgnoredQualifier(Default::default())),
+            WarningLevel::Ignore
+        );
+        assert_eq!(
+            level(&["extra"], Warning::IgnoredQualifier(Default::default())),
+            WarningLevel::Warn
+        );
+        assert_eq!(
+            level(&["no-implicit-int"], Warning::ImplicitInt),
+            WarningLevel::Ignore
+        );
+        assert_eq!(
+            level(&["no-implicit-int", "error"], Warning::ImplicitInt),
+            WarningLevel::Ignore
+        );
+        assert_eq!(
+            level(&["error=unknown-pragmas"], Warning::IgnoredPragma),
+            WarningLevel::Error
+        );
+        assert_eq!(
+            level(
+                &["error=unknown-pragmas", "no-error"],
+                Warning::IgnoredPragma
+            ),
+            WarningLevel::Warn
+        );
+        // -pedantic only affects extensions, and -Wall doesn't include it
+        let extension = Warning::C99Extension("'long long' types");
+        assert_eq!(level(&["all"], extension.clone()), WarningLevel::Ignore);
+        assert_eq!(level(&["pedantic"], extension.clone()), WarningLevel::Warn);
+        assert_eq!(
+            level(&["error=pedantic"], extension.clone()),
+            WarningLevel::Error
+        );
+        assert_eq!(
+            level(&["error=pedantic"], Warning::ImplicitInt),
+            WarningLevel::Warn
+        );
+        assert_eq!(
+            level(&["pedantic", "no-c99-extensions"], extension.clone()),
+            WarningLevel::Ignore
+        );
+        assert_eq!(
+            level(&["error=pedantic", "no-error=c99-extensions"], extension),
+            WarningLevel::Warn
+        );
+    }
+
+    #[test]
+    fn test_unknown_warning_option() {
+        let mut options = WarningOptions::default();
+        for flag in &["not-a-warning", "no-not-a-warning", "error=not-a-warning"] {
+            assert_eq!(
+                options.add_flag(flag),
+                Err(Warning::UnknownWarningOption(flag.to_string()))
+            );
+        }
+        assert_eq!(options, WarningOptions::default());
+    }
+
     fn dummy_error() -> CompileError {
         Location::default().with(Error::Lex(LexError::UnterminatedComment))
     }
@@ -8,59 +8,200 @@ use std::rc::Rc;
 #[cfg(test)]
 use proptest_derive::Arbitrary;
 
-use super::lex::{ComparisonToken, Keyword, Literal, Locatable};
+use super::lex::{ComparisonToken, Keyword, Literal, Locatable, Span};
 use super::types::Type;
 use super::*;
 use crate::intern::InternedStr;
 
-pub type Stmt = Locatable<StmtType>;
+mod dump;
+mod print;
+#[cfg(feature = "serialize")]
+mod serialize;
+mod visit;
 
-#[derive(Clone, Debug, PartialEq)]
-pub enum StmtType {
-    Compound(Vec<Stmt>),
-    If(Expr, Box<Stmt>, Option<Box<Stmt>>),
-    Do(Box<Stmt>, Expr),
-    While(Expr, Box<Stmt>),
+pub use print::{CPrinter, PrintOptions};
+#[cfg(feature = "serialize")]
+pub use serialize::TranslationUnit;
+pub use visit::{
+    walk_declaration, walk_declaration_mut, walk_expr, walk_expr_mut, walk_initializer,
+    walk_initializer_mut, walk_stmt, walk_stmt_mut, walk_type, walk_type_mut, Visitor, VisitorMut,
+};
+
+pub type Stmt<'hir> = Locatable<StmtType<'hir>>;
+
+/// Storage for the expressions and statements of the HIR.
+///
+/// The children of a node are references into the arena instead of separate allocations,
+/// so building a tree allocates a few large chunks instead of one box per node,
+/// and dropping it frees the chunks without recursing through the tree.
+/// Every node lives as long as the arena; nothing is freed before then.
+///
+/// ```
+/// use saltwater::{analyze, hir::Arena, Opt};
+///
+/// let arena = Arena::new();
+/// let analysis = analyze("int i = 1 + 2;", Opt::default(), &arena);
+/// assert!(analysis.errors.is_empty());
+/// assert!(arena.exprs() > 0);
+/// ```
+#[derive(Default)]
+pub struct Arena<'hir> {
+    exprs: typed_arena::Arena<Expr<'hir>>,
+    stmts: typed_arena::Arena<Stmt<'hir>>,
+}
+
+impl<'hir> Arena<'hir> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Move `expr` into the arena.
+    pub fn expr(&'hir self, expr: Expr<'hir>) -> &'hir mut Expr<'hir> {
+        self.exprs.alloc(expr)
+    }
+    /// Move `stmt` into the arena.
+    pub fn stmt(&'hir self, stmt: Stmt<'hir>) -> &'hir mut Stmt<'hir> {
+        self.stmts.alloc(stmt)
+    }
+    /// The number of expressions that have been allocated so far.
+    pub fn exprs(&self) -> usize {
+        self.exprs.len()
+    }
+    /// The number of statements that have been allocated so far.
+    pub fn stmts(&self) -> usize {
+        self.stmts.len()
+    }
+}
+
+impl<'hir> Expr<'hir> {
+    /// Move the expression out of `self`, leaving a placeholder of type `Type::Error` behind.
+    ///
+    /// This is how to rebuild a node that lives in an arena,
+    /// since nodes can't be moved out of the arena.
+    pub fn take(&mut self) -> Expr<'hir> {
+        let placeholder = Expr {
+            expr: ExprType::Literal(Literal::Int(0)),
+            ctype: Type::Error,
+            lval: false,
+            location: self.location,
+        };
+        std::mem::replace(self, placeholder)
+    }
+    /// Copy this expression and all its subexpressions into `arena`.
+    pub fn clone_in<'a>(&self, arena: &'a Arena<'a>) -> Expr<'a> {
+        let copy = |expr: &Expr| -> &'a mut Expr<'a> { arena.expr(expr.clone_in(arena)) };
+        let expr = match &self.expr {
+            ExprType::Id(symbol) => ExprType::Id(*symbol),
+            ExprType::Literal(literal) => ExprType::Literal(literal.clone()),
+            ExprType::FuncCall(func, args) => ExprType::FuncCall(
+                copy(func),
+                args.iter().map(|arg| arg.clone_in(arena)).collect(),
+            ),
+            ExprType::Member(inner, member) => ExprType::Member(copy(inner), *member),
+            ExprType::Cast(inner) => ExprType::Cast(copy(inner)),
+            ExprType::Sizeof(ctype) => ExprType::Sizeof(ctype.clone()),
+            ExprType::Deref(inner) => ExprType::Deref(copy(inner)),
+            ExprType::Negate(inner) => ExprType::Negate(copy(inner)),
+            ExprType::BitwiseNot(inner) => ExprType::BitwiseNot(copy(inner)),
+            ExprType::Binary(op, left, right) => ExprType::Binary(*op, copy(left), copy(right)),
+            ExprType::Ternary(condition, then, otherwise) => {
+                ExprType::Ternary(copy(condition), copy(then), copy(otherwise))
+            }
+            ExprType::Comma(left, right) => ExprType::Comma(copy(left), copy(right)),
+            ExprType::StaticRef(inner) => ExprType::StaticRef(copy(inner)),
+            ExprType::Noop(inner) => ExprType::Noop(copy(inner)),
+            ExprType::Let(symbol, init, body) => ExprType::Let(*symbol, copy(init), copy(body)),
+        };
+        Expr {
+            expr,
+            ctype: self.ctype.clone(),
+            lval: self.lval,
+            location: self.location,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serialize", serde(bound(deserialize = "'hir: 'static")))]
+pub enum StmtType<'hir> {
+    Compound(Vec<Stmt<'hir>>),
+    If(
+        Expr<'hir>,
+        &'hir mut Stmt<'hir>,
+        Option<&'hir mut Stmt<'hir>>,
+    ),
+    Do(&'hir mut Stmt<'hir>, Expr<'hir>),
+    While(Expr<'hir>, &'hir mut Stmt<'hir>),
     // for(int i = 1, j = 2; i < 4; ++i) body
     // for(i = 1; ; ++i) body
     // for (;;) ;
-    For(Box<Stmt>, Option<Box<Expr>>, Option<Box<Expr>>, Box<Stmt>),
-    Switch(Expr, Box<Stmt>),
-    Label(InternedStr, Box<Stmt>),
-    Case(u64, Box<Stmt>),
-    Default(Box<Stmt>),
-    Expr(Expr),
+    For(
+        &'hir mut Stmt<'hir>,
+        Option<&'hir mut Expr<'hir>>,
+        Option<&'hir mut Expr<'hir>>,
+        &'hir mut Stmt<'hir>,
+    ),
+    Switch(Expr<'hir>, &'hir mut Stmt<'hir>),
+    Label(InternedStr, &'hir mut Stmt<'hir>),
+    Case(u64, &'hir mut Stmt<'hir>),
+    Default(&'hir mut Stmt<'hir>),
+    Expr(Expr<'hir>),
     Goto(InternedStr),
     Continue,
     Break,
-    Return(Option<Expr>),
-    Decl(Vec<Locatable<Declaration>>),
+    Return(Option<Expr<'hir>>),
+    Decl(Vec<Locatable<Declaration<'hir>>>),
 }
 
-impl Default for StmtType {
+impl Default for StmtType<'_> {
     fn default() -> Self {
         StmtType::Compound(Vec::new())
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
-pub struct Declaration {
+impl<'hir> Stmt<'hir> {
+    /// Move the statement out of `self`, leaving an empty compound statement behind.
+    pub fn take(&mut self) -> Stmt<'hir> {
+        Locatable::new(std::mem::take(&mut self.data), self.location)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serialize", serde(bound(deserialize = "'hir: 'static")))]
+pub struct Declaration<'hir> {
     pub symbol: Symbol,
-    pub init: Option<Initializer>,
+    pub init: Option<Initializer<'hir>>,
+    /// How the declaration is linked, if it's different from the default for its storage class
+    pub attributes: LinkAttributes,
 }
 
-#[derive(Clone, Debug, PartialEq)]
-pub enum Initializer {
-    Scalar(Box<Expr>),                 // int i = 5;
-    InitializerList(Vec<Initializer>), // int a[] = { 1, 2, 3 };
-    FunctionBody(Vec<Stmt>),           // int f() { return 0; }
+/// The attributes of a declaration which change the symbol it's compiled to.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct LinkAttributes {
+    /// `__attribute__((weak))`: a definition in another object file takes precedence over this one
+    pub weak: bool,
+    /// `__attribute__((alias("target")))`: this declaration is another name for the definition of `target`
+    pub alias: Option<InternedStr>,
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serialize", serde(bound(deserialize = "'hir: 'static")))]
+pub enum Initializer<'hir> {
+    Scalar(&'hir mut Expr<'hir>),            // int i = 5;
+    InitializerList(Vec<Initializer<'hir>>), // int a[] = { 1, 2, 3 };
+    FunctionBody(Vec<Stmt<'hir>>),           // int f() { return 0; }
 }
 
 /// Holds the metadata for an expression.
-#[derive(Clone, Debug, PartialEq)]
-pub struct Expr {
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serialize", serde(bound(deserialize = "'hir: 'static")))]
+pub struct Expr<'hir> {
     /// expr: holds the actual expression
-    pub expr: ExprType,
+    pub expr: ExprType<'hir>,
 
     /// ctype: holds the type of the expression
     pub ctype: Type,
@@ -112,48 +253,143 @@ impl Symbol {
     pub fn get(self) -> Rc<Variable> {
         SYMBOL_TABLE.with(|store| store.borrow().get(self))
     }
+    /// Change the metadata for a symbol.
+    ///
+    /// Only used to fill in placeholders while deserializing.
+    #[cfg(feature = "serialize")]
+    pub(crate) fn replace(self, metadata: Variable) {
+        SYMBOL_TABLE.with(|store| store.borrow_mut().0[self.0] = Rc::new(metadata));
+    }
+}
+
+/// Variables the analyzer makes up have no name, so they are shown as `$` followed by a number.
+impl Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let id = self.get().id;
+        if id == InternedStr::default() {
+            write!(f, "${}", self.0)
+        } else {
+            write!(f, "{}", id)
+        }
+    }
 }
 
 impl Variable {
     pub(crate) fn insert(self) -> Symbol {
         SYMBOL_TABLE.with(|store| store.borrow_mut().insert(self))
     }
+    /// Whether the variable itself is `const`, as opposed to the data it points to.
+    ///
+    /// `qualifiers` apply to the innermost type of the declarator, so `const int *p` is a
+    /// modifiable pointer to `const int`. `int *const p` stores `const` in the pointer type instead.
+    pub(crate) fn is_const(&self) -> bool {
+        self.own_qualifiers().c_const
+    }
+    /// The qualifiers of the variable itself, as opposed to the data it points to.
+    pub(crate) fn own_qualifiers(&self) -> Qualifiers {
+        match &self.ctype {
+            Type::Pointer(_, qualifiers) => *qualifiers,
+            _ => self.qualifiers,
+        }
+    }
 }
 
-#[derive(Clone, Debug, PartialEq)]
-pub enum ExprType {
+/// Which declaration each identifier in a program refers to.
+///
+/// This is meant for editor tooling, such as go-to-definition and find-all-references.
+/// Use `Symbol::get` to find where a symbol was declared, along with its type and storage class.
+#[derive(Clone, Debug, Default)]
+pub struct References {
+    /// Identifiers that were used in an expression, in the order they were analyzed
+    uses: Vec<(Location, Symbol)>,
+    /// The identifiers being declared
+    declarations: Vec<(Location, Symbol)>,
+}
+
+impl References {
+    pub(crate) fn record_use(&mut self, location: Location, symbol: Symbol) {
+        self.uses.push((location, symbol));
+    }
+    pub(crate) fn record_declaration(&mut self, location: Location, symbol: Symbol) {
+        self.declarations.push((location, symbol));
+    }
+    /// The symbol for the identifier at `location`, if there is one.
+    ///
+    /// This finds both uses of a variable and its declarations,
+    /// except for function definitions, which are only found where they are used.
+    /// If more than one identifier contains `location`, the smallest one is returned.
+    pub fn symbol_at(&self, location: Location) -> Option<Symbol> {
+        self.uses
+            .iter()
+            .chain(&self.declarations)
+            .filter(|(found, _)| found.contains(location))
+            .min_by_key(|(found, _)| found.span.end - found.span.start)
+            .map(|&(_, symbol)| symbol)
+    }
+    /// The symbol for the identifier containing byte `offset` of `file`, if there is one.
+    pub fn symbol_at_offset(&self, file: codespan::FileId, offset: u32) -> Option<Symbol> {
+        let span = Span {
+            start: offset,
+            end: offset,
+        };
+        self.symbol_at(Location {
+            span,
+            file,
+            expansion: None,
+        })
+    }
+    /// Every place `symbol` was used, not including its declaration.
+    pub fn references(&self, symbol: Symbol) -> impl Iterator<Item = Location> + '_ {
+        self.uses
+            .iter()
+            .filter(move |&&(_, used)| used == symbol)
+            .map(|&(location, _)| location)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serialize", serde(bound(deserialize = "'hir: 'static")))]
+pub enum ExprType<'hir> {
     // primary expressions
     // This stores a reference to the metadata for the identifier,
     // which can be looked up using a `metadata_store`.
     Id(Symbol),
     Literal(Literal),
-    FuncCall(Box<Expr>, Vec<Expr>),
-    Member(Box<Expr>, InternedStr),
+    FuncCall(&'hir mut Expr<'hir>, Vec<Expr<'hir>>),
+    Member(&'hir mut Expr<'hir>, InternedStr),
 
     // unary expressions
-    // post increment/decrement
-    PostIncrement(Box<Expr>, bool),
-    Cast(Box<Expr>),
+    Cast(&'hir mut Expr<'hir>),
     Sizeof(Type),
-    Deref(Box<Expr>),
-    Negate(Box<Expr>),
-    BitwiseNot(Box<Expr>),
+    Deref(&'hir mut Expr<'hir>),
+    Negate(&'hir mut Expr<'hir>),
+    BitwiseNot(&'hir mut Expr<'hir>),
 
     // binary expressions
-    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    Binary(BinaryOp, &'hir mut Expr<'hir>, &'hir mut Expr<'hir>),
 
     // misfits
     // Ternary: if ? then : else
-    Ternary(Box<Expr>, Box<Expr>, Box<Expr>),
-    Comma(Box<Expr>, Box<Expr>),
+    Ternary(
+        &'hir mut Expr<'hir>,
+        &'hir mut Expr<'hir>,
+        &'hir mut Expr<'hir>,
+    ),
+    Comma(&'hir mut Expr<'hir>, &'hir mut Expr<'hir>),
     // &expr in static context
     // requires cooperation with the linker
-    StaticRef(Box<Expr>),
+    StaticRef(&'hir mut Expr<'hir>),
     // used to work around various bugs, see places this is constructed for details
-    Noop(Box<Expr>),
+    Noop(&'hir mut Expr<'hir>),
+    // Evaluate the first expression once and store it in the variable, then yield the second.
+    // The variable is made up by the analyzer and has no name.
+    // This is how `a += b`, `a++`, and `a ?: b` evaluate `a` only once.
+    Let(Symbol, &'hir mut Expr<'hir>, &'hir mut Expr<'hir>),
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
 pub enum BinaryOp {
     // binary expressions
     LogicalOr,
@@ -202,100 +438,266 @@ impl Display for BinaryOp {
 /// For abstract function parameters, e.g. `int f(int)`, the `id` will resolve to the empty string.
 /// Furthermore, it is guaranteed to be equal to `InternedStr::default()`.
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
 pub struct Variable {
     pub ctype: Type,
     pub storage_class: StorageClass,
+    /// The qualifiers of the innermost type, e.g. `const` in both `const int i` and `const int *p`
     pub qualifiers: Qualifiers,
     pub id: InternedStr,
+    /// Where the variable was declared
+    pub location: Location,
 }
 
 #[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(test, derive(Arbitrary))]
 pub struct Qualifiers {
     pub volatile: bool,
     pub c_const: bool,
+    /// Only allowed on pointers to objects, `int *restrict p`
+    pub restrict: bool,
     pub func: FunctionQualifiers,
 }
 
 #[cfg_attr(test, derive(Arbitrary))]
 #[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
 pub struct FunctionQualifiers {
     pub inline: bool,
     pub no_return: bool,
+    /// `__attribute__((returns_twice))`, or a function like `setjmp`
+    pub returns_twice: bool,
+    /// `__attribute__((format(printf, 1, 2)))`, or a function like `printf`
+    pub format: Option<FormatAttribute>,
+    /// `__attribute__((constructor(priority)))`: run the function before `main`
+    pub constructor: Option<u16>,
+    /// `__attribute__((destructor(priority)))`: run the function after `main` returns or `exit` is called
+    pub destructor: Option<u16>,
+}
+
+/// The priority of a constructor or destructor declared without one.
+///
+/// Constructors with a lower priority run first, and destructors with a lower priority run last.
+pub const DEFAULT_INIT_PRIORITY: u16 = 65535;
+
+/// The arguments of `__attribute__((format(kind, format_index, first_arg)))`.
+///
+/// Both indices count from 1, like in the attribute.
+#[cfg_attr(test, derive(Arbitrary))]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct FormatAttribute {
+    pub kind: FormatKind,
+    /// The parameter holding the format string
+    pub format_index: usize,
+    /// The first argument checked against the format string,
+    /// or 0 for functions that take a `va_list`, like `vprintf`.
+    pub first_arg: usize,
 }
 
+/// Whether a format string is interpreted like `printf` or like `scanf`.
+#[cfg_attr(test, derive(Arbitrary))]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub enum FormatKind {
+    Printf,
+    Scanf,
+}
+
+/// A symbol table with nested scopes.
+///
+/// Rather than keeping a map for each scope and searching them from the inside out,
+/// every name maps to a chain of its declarations, innermost last.
+/// Each scope remembers which names it declared, so leaving it only has to pop those chains.
 #[derive(Debug)]
-pub(crate) struct Scope<K: Hash + Eq, V>(Vec<HashMap<K, V>>);
+pub(crate) struct Scope<K: Hash + Eq, V> {
+    /// each visible name, with the depth of the scope it was declared in
+    chains: HashMap<K, Vec<(usize, V)>>,
+    /// the names declared in each scope, in order; the file scope is first
+    declared: Vec<Vec<K>>,
+}
 
 impl Qualifiers {
     pub(crate) fn has_func_qualifiers(self) -> bool {
-        self.func.inline || self.func.no_return
+        self.func.inline
+            || self.func.no_return
+            || self.func.returns_twice
+            || self.func.format.is_some()
+            || self.func.constructor.is_some()
+            || self.func.destructor.is_some()
     }
     pub(crate) const NONE: Qualifiers = Qualifiers {
         c_const: false,
         volatile: false,
+        restrict: false,
         func: FunctionQualifiers {
             inline: false,
             no_return: false,
+            returns_twice: false,
+            format: None,
+            constructor: None,
+            destructor: None,
         },
     };
 }
 
+impl Display for LinkAttributes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.weak {
+            write!(f, " {}((weak))", Keyword::Attribute)?;
+        }
+        if let Some(target) = self.alias {
+            write!(f, " {}((alias(\"{}\")))", Keyword::Attribute, target)?;
+        }
+        Ok(())
+    }
+}
+
 impl Display for FunctionQualifiers {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match (self.inline, self.no_return) {
-            (true, true) => write!(f, "{} {}", Keyword::Inline, Keyword::NoReturn),
-            (true, false) => write!(f, "{}", Keyword::NoReturn),
-            (false, true) => write!(f, "{}", Keyword::NoReturn),
-            (false, false) => Ok(()),
+        let mut parts = Vec::new();
+        if self.inline {
+            parts.push(Keyword::Inline.to_string());
+        }
+        if self.no_return {
+            parts.push(Keyword::NoReturn.to_string());
         }
+        if self.returns_twice {
+            parts.push(format!("{}((returns_twice))", Keyword::Attribute));
+        }
+        if let Some(format) = self.format {
+            let kind = match format.kind {
+                FormatKind::Printf => "printf",
+                FormatKind::Scanf => "scanf",
+            };
+            parts.push(format!(
+                "{}((format({}, {}, {})))",
+                Keyword::Attribute,
+                kind,
+                format.format_index,
+                format.first_arg
+            ));
+        }
+        for (name, priority) in &[
+            ("constructor", self.constructor),
+            ("destructor", self.destructor),
+        ] {
+            match priority {
+                Some(DEFAULT_INIT_PRIORITY) => {
+                    parts.push(format!("{}(({}))", Keyword::Attribute, name))
+                }
+                Some(priority) => {
+                    parts.push(format!("{}(({}({})))", Keyword::Attribute, name, priority))
+                }
+                None => {}
+            }
+        }
+        write!(f, "{}", parts.join(" "))
     }
 }
 
-impl<K: Hash + Eq, V> Scope<K, V> {
+impl<K: Hash + Eq + Clone, V> Scope<K, V> {
     #[inline]
     pub(crate) fn new() -> Self {
-        Self(vec![HashMap::new()])
+        Self {
+            chains: HashMap::new(),
+            declared: vec![Vec::new()],
+        }
     }
     #[inline]
     pub(crate) fn enter(&mut self) {
-        self.0.push(HashMap::<K, V>::new())
+        self.declared.push(Vec::new())
     }
-    #[inline]
     pub(crate) fn exit(&mut self) {
-        self.0.pop();
+        for name in self.declared.pop().unwrap_or_default() {
+            if let Some(chain) = self.chains.get_mut(&name) {
+                chain.pop();
+                if chain.is_empty() {
+                    self.chains.remove(&name);
+                }
+            }
+        }
     }
+    /// The depth of the current scope, where the file scope is 0.
+    #[inline]
+    fn depth(&self) -> usize {
+        self.declared.len() - 1
+    }
+    #[inline]
     pub(crate) fn get(&self, name: &K) -> Option<&V> {
-        self.iter()
-            .filter_map(|(key, value)| if key == name { Some(value) } else { None })
-            .next()
+        self.chains.get(name)?.last().map(|(_, value)| value)
     }
     // returns whether the _immediate_ scope contains `name`
-    #[inline]
     pub(crate) fn insert(&mut self, key: K, value: V) -> Option<V> {
-        self.0.last_mut().unwrap().insert(key, value)
+        let depth = self.depth();
+        let chain = self.chains.entry(key.clone()).or_default();
+        match chain.last_mut() {
+            Some((declared_at, existing)) if *declared_at == depth => {
+                Some(std::mem::replace(existing, value))
+            }
+            _ => {
+                chain.push((depth, value));
+                self.declared[depth].push(key);
+                None
+            }
+        }
     }
+    /// Look up `name` in the outermost (file) scope only.
     #[inline]
-    pub(crate) fn get_immediate(&self, name: &K) -> Option<&V> {
-        self.0.last().unwrap().get(name)
+    pub(crate) fn get_global(&self, name: &K) -> Option<&V> {
+        match self.chains.get(name)?.first()? {
+            (0, value) => Some(value),
+            _ => None,
+        }
     }
     #[inline]
-    pub(crate) fn get_all_immediate(&mut self) -> &mut HashMap<K, V> {
-        self.0.last_mut().unwrap()
+    pub(crate) fn get_immediate(&self, name: &K) -> Option<&V> {
+        match self.chains.get(name)?.last()? {
+            (depth, value) if *depth == self.depth() => Some(value),
+            _ => None,
+        }
+    }
+    /// Everything declared in the current scope, in the order it was first declared.
+    pub(crate) fn immediate(&self) -> impl Iterator<Item = (&K, &V)> {
+        let names = self.declared.last().into_iter().flatten();
+        names.filter_map(move |name| Some((name, self.get_immediate(name)?)))
     }
     pub(crate) fn is_global(&self) -> bool {
-        self.0.len() == 1
+        self.declared.len() == 1
     }
     pub(crate) fn _remove(&mut self, key: &K) -> Option<V> {
-        debug_assert!(!self.0.is_empty());
-        self.0.last_mut().unwrap().remove(key)
+        let depth = self.depth();
+        let chain = self.chains.get_mut(key)?;
+        if chain.last()?.0 != depth {
+            return None;
+        }
+        let (_, value) = chain.pop()?;
+        if chain.is_empty() {
+            self.chains.remove(key);
+        }
+        self.declared[depth].retain(|name| name != key);
+        Some(value)
     }
+    /// Every declaration in scope, including shadowed ones, from the innermost scope outwards.
     pub(crate) fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
-        self.0.iter().rev().flatten()
+        self.declared
+            .iter()
+            .enumerate()
+            .rev()
+            .flat_map(move |(depth, names)| {
+                names.iter().filter_map(move |name| {
+                    let chain = self.chains.get(name)?;
+                    let (_, value) = chain
+                        .iter()
+                        .rfind(|(declared_at, _)| *declared_at == depth)?;
+                    Some((name, value))
+                })
+            })
     }
 }
 
-impl<K: Eq + Hash, V> Default for Scope<K, V> {
+impl<K: Eq + Hash + Clone, V> Default for Scope<K, V> {
     fn default() -> Self {
         Self::new()
     }
@@ -337,26 +739,27 @@ impl Display for Qualifiers {
             (false, false) => "",
         }
         .to_owned();
-        let func_quals = match (self.func.inline, self.func.no_return) {
-            (true, true) => "inline _Noreturn",
-            (true, false) => "inline",
-            (false, true) => "_Noreturn",
-            (false, false) => "",
-        };
+        if self.restrict {
+            if !basic_quals.is_empty() {
+                basic_quals.push(' ');
+            }
+            basic_quals.push_str("restrict");
+        }
+        let func_quals = self.func.to_string();
         if basic_quals != "" && func_quals != "" {
             basic_quals.push(' ');
         }
-        basic_quals.push_str(func_quals);
+        basic_quals.push_str(&func_quals);
         write!(f, "{}", basic_quals)
     }
 }
 
-impl Display for Expr {
+impl Display for Expr<'_> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match &self.expr {
             ExprType::Comma(left, right) => write!(f, "{}, {}", *left, *right),
             ExprType::Literal(token) => write!(f, "{}", token),
-            ExprType::Id(symbol) => write!(f, "{}", symbol.get().id),
+            ExprType::Id(symbol) => write!(f, "{}", symbol),
             ExprType::Binary(op, left, right) => write!(f, "({}) {} ({})", left, op, right),
             ExprType::BitwiseNot(expr) => write!(f, "(~{})", expr),
             ExprType::Deref(expr) => write!(f, "*({})", expr),
@@ -369,16 +772,14 @@ impl Display for Expr {
             ExprType::Cast(expr) => write!(f, "({})({})", self.ctype, expr),
             ExprType::Sizeof(ty) => write!(f, "sizeof({})", ty),
             ExprType::Member(compound, id) => write!(f, "({}).{}", compound, id),
-            ExprType::PostIncrement(expr, inc) => {
-                write!(f, "({}){}", expr, if *inc { "++" } else { "--" })
-            }
             ExprType::StaticRef(expr) => write!(f, "&{}", expr),
             ExprType::Noop(expr) => write!(f, "{}", expr),
+            ExprType::Let(symbol, init, body) => write!(f, "({} = {}, {})", symbol, init, body),
         }
     }
 }
 
-impl Display for Initializer {
+impl Display for Initializer<'_> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Initializer::Scalar(expr) => write!(f, "{}", expr),
@@ -398,13 +799,13 @@ impl Display for Initializer {
     }
 }
 
-impl Display for StmtType {
+impl Display for StmtType<'_> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         self.pretty_print(f, 0)
     }
 }
 
-impl StmtType {
+impl StmtType<'_> {
     fn pretty_print(&self, f: &mut fmt::Formatter, depth: usize) -> fmt::Result {
         write!(f, "{}", "    ".repeat(depth))?;
         match self {
@@ -483,9 +884,9 @@ impl Display for Variable {
     }
 }
 
-impl Display for Declaration {
+impl Display for Declaration<'_> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.symbol.get())?;
+        write!(f, "{}{}", self.symbol.get(), self.attributes)?;
         match &self.init {
             Some(Initializer::FunctionBody(body)) => {
                 writeln!(f, " {{")?;
@@ -528,9 +929,40 @@ impl Eq for Symbol {}
 
 #[cfg(test)]
 mod tests {
+    use super::Scope;
     use crate::analyze::{test::analyze, PureAnalyzer};
     use crate::{Locatable, Parser};
 
+    #[test]
+    fn scope_shadowing() {
+        let mut scope = Scope::new();
+        assert_eq!(scope.insert("x", 1), None);
+        assert_eq!(scope.insert("y", 2), None);
+        scope.enter();
+        assert_eq!(scope.get_immediate(&"x"), None);
+        assert_eq!(scope.insert("x", 3), None);
+        assert_eq!(scope.get(&"x"), Some(&3));
+        assert_eq!(scope.get_global(&"x"), Some(&1));
+        assert_eq!(scope.get(&"y"), Some(&2));
+        // redeclaring in the same scope replaces the entry
+        assert_eq!(scope.insert("x", 4), Some(3));
+        assert_eq!(
+            scope.iter().collect::<Vec<_>>(),
+            vec![(&"x", &4), (&"x", &1), (&"y", &2)]
+        );
+        scope.enter();
+        assert_eq!(scope.insert("z", 5), None);
+        assert_eq!(scope._remove(&"x"), None);
+        assert_eq!(scope._remove(&"z"), Some(5));
+        assert_eq!(scope.immediate().count(), 0);
+        scope.exit();
+        assert_eq!(scope.immediate().collect::<Vec<_>>(), vec![(&"x", &4)]);
+        scope.exit();
+        assert!(scope.is_global());
+        assert_eq!(scope.get(&"x"), Some(&1));
+        assert_eq!(scope.get_global(&"z"), None);
+    }
+
     #[test]
     fn type_display() {
         let types = [
@@ -0,0 +1,230 @@
+//! A tree-shaped dump of the HIR, used for `--emit=hir`.
+//!
+//! Unlike the `Display` impls, which print something close to C,
+//! this shows every node on its own line, including implicit casts and `Noop`s,
+//! along with the type of each expression and whether it is an lvalue.
+//! The format is meant to be stable so it can be compared against expected output.
+
+use std::fmt::Write;
+
+use super::*;
+
+impl Declaration<'_> {
+    /// Show the tree of this declaration, one node per line.
+    pub fn dump(&self) -> String {
+        let mut dumper = Dumper::default();
+        dumper.decl(self);
+        dumper.out
+    }
+}
+
+impl StmtType<'_> {
+    /// Show the tree of this statement, one node per line.
+    pub fn dump(&self) -> String {
+        let mut dumper = Dumper::default();
+        dumper.stmt(self);
+        dumper.out
+    }
+}
+
+impl Expr<'_> {
+    /// Show the tree of this expression, one node per line.
+    pub fn dump(&self) -> String {
+        let mut dumper = Dumper::default();
+        dumper.expr(self);
+        dumper.out
+    }
+}
+
+#[derive(Default)]
+struct Dumper {
+    out: String,
+    depth: usize,
+    /// The variables made up by the analyzer, numbered in the order they're seen.
+    ///
+    /// `Symbol`s depend on everything analyzed before, so they aren't stable enough to show.
+    temporaries: HashMap<Symbol, usize>,
+}
+
+impl Dumper {
+    fn line(&mut self, args: fmt::Arguments) {
+        for _ in 0..self.depth {
+            self.out.push_str("  ");
+        }
+        // writing to a `String` can't fail
+        self.out.write_fmt(args).unwrap();
+        self.out.push('\n');
+    }
+    /// The name of a variable, or `$` and a number for a variable the analyzer made up.
+    fn name(&mut self, symbol: Symbol) -> String {
+        let id = symbol.get().id;
+        if id != InternedStr::default() {
+            return id.resolve_and_clone();
+        }
+        let next = self.temporaries.len();
+        format!("${}", self.temporaries.entry(symbol).or_insert(next))
+    }
+    /// Show a node with `children` indented below it.
+    fn node(&mut self, args: fmt::Arguments, children: impl FnOnce(&mut Self)) {
+        self.line(args);
+        self.depth += 1;
+        children(self);
+        self.depth -= 1;
+    }
+    /// A missing optional child, such as the `else` of an `if`.
+    fn none(&mut self) {
+        self.line(format_args!("<none>"));
+    }
+    fn decl(&mut self, decl: &Declaration) {
+        let meta = decl.symbol.get();
+        let qualifiers = if meta.qualifiers == Qualifiers::default() {
+            String::new()
+        } else {
+            format!(" {}", meta.qualifiers)
+        };
+        let header = format_args!(
+            "declaration {} '{}' {}{}",
+            meta.id, meta.ctype, meta.storage_class, qualifiers
+        );
+        self.node(header, |this| match &decl.init {
+            Some(init) => this.initializer(init),
+            None => {}
+        });
+    }
+    fn initializer(&mut self, init: &Initializer) {
+        match init {
+            Initializer::Scalar(expr) => self.expr(expr),
+            Initializer::InitializerList(inits) => {
+                self.node(format_args!("initializer-list"), |this| {
+                    for init in inits {
+                        this.initializer(init);
+                    }
+                })
+            }
+            Initializer::FunctionBody(stmts) => self.node(format_args!("body"), |this| {
+                for stmt in stmts {
+                    this.stmt(&stmt.data);
+                }
+            }),
+        }
+    }
+    fn stmt(&mut self, stmt: &StmtType) {
+        match stmt {
+            StmtType::Compound(stmts) => self.node(format_args!("compound"), |this| {
+                for stmt in stmts {
+                    this.stmt(&stmt.data);
+                }
+            }),
+            StmtType::If(condition, then, otherwise) => self.node(format_args!("if"), |this| {
+                this.expr(condition);
+                this.stmt(&then.data);
+                match otherwise {
+                    Some(otherwise) => this.stmt(&otherwise.data),
+                    None => this.none(),
+                }
+            }),
+            StmtType::Do(body, condition) => self.node(format_args!("do"), |this| {
+                this.stmt(&body.data);
+                this.expr(condition);
+            }),
+            StmtType::While(condition, body) => self.node(format_args!("while"), |this| {
+                this.expr(condition);
+                this.stmt(&body.data);
+            }),
+            StmtType::For(init, condition, post, body) => self.node(format_args!("for"), |this| {
+                this.stmt(&init.data);
+                for expr in &[condition, post] {
+                    match expr {
+                        Some(expr) => this.expr(expr),
+                        None => this.none(),
+                    }
+                }
+                this.stmt(&body.data);
+            }),
+            StmtType::Switch(condition, body) => self.node(format_args!("switch"), |this| {
+                this.expr(condition);
+                this.stmt(&body.data);
+            }),
+            StmtType::Label(name, inner) => self.node(format_args!("label {}", name), |this| {
+                this.stmt(&inner.data)
+            }),
+            StmtType::Case(value, inner) => self.node(format_args!("case {}", value), |this| {
+                this.stmt(&inner.data)
+            }),
+            StmtType::Default(inner) => {
+                self.node(format_args!("default"), |this| this.stmt(&inner.data))
+            }
+            StmtType::Expr(expr) => self.node(format_args!("expr"), |this| this.expr(expr)),
+            StmtType::Goto(name) => self.line(format_args!("goto {}", name)),
+            StmtType::Continue => self.line(format_args!("continue")),
+            StmtType::Break => self.line(format_args!("break")),
+            StmtType::Return(value) => self.node(format_args!("return"), |this| {
+                if let Some(value) = value {
+                    this.expr(value);
+                }
+            }),
+            StmtType::Decl(decls) => self.node(format_args!("decl"), |this| {
+                for decl in decls {
+                    this.decl(&decl.data);
+                }
+            }),
+        }
+    }
+    fn expr(&mut self, expr: &Expr) {
+        let category = if expr.lval { "lvalue" } else { "rvalue" };
+        let (kind, children): (String, Vec<&Expr>) = match &expr.expr {
+            ExprType::Id(symbol) => (format!("id {}", self.name(*symbol)), vec![]),
+            ExprType::Literal(literal) => (format!("literal {}", literal), vec![]),
+            ExprType::FuncCall(func, args) => {
+                let mut children = vec![&**func];
+                children.extend(args);
+                ("call".into(), children)
+            }
+            ExprType::Member(inner, member) => (format!("member .{}", member), vec![inner]),
+            ExprType::Cast(inner) => ("cast".into(), vec![inner]),
+            ExprType::Sizeof(ctype) => (format!("sizeof '{}'", ctype), vec![]),
+            ExprType::Deref(inner) => ("deref".into(), vec![inner]),
+            ExprType::Negate(inner) => ("negate".into(), vec![inner]),
+            ExprType::BitwiseNot(inner) => ("bitwise-not".into(), vec![inner]),
+            ExprType::Binary(op, left, right) => (format!("binary {}", op), vec![left, right]),
+            ExprType::Ternary(condition, then, otherwise) => {
+                ("ternary".into(), vec![condition, then, otherwise])
+            }
+            ExprType::Comma(left, right) => ("comma".into(), vec![left, right]),
+            ExprType::StaticRef(inner) => ("static-ref".into(), vec![inner]),
+            ExprType::Noop(inner) => ("noop".into(), vec![inner]),
+            ExprType::Let(symbol, init, body) => {
+                (format!("let {}", self.name(*symbol)), vec![init, body])
+            }
+        };
+        let header = format_args!("{} '{}' {}", kind, expr.ctype, category);
+        self.node(header, |this| {
+            for child in children {
+                this.expr(child);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analyze::test::analyze_expr;
+
+    #[test]
+    fn test_dump_expr() {
+        // the implicit comparison and casts are shown
+        let dump = analyze_expr("1 ? 2 : 3.0").unwrap().dump();
+        assert_eq!(
+            dump,
+            "ternary 'double' rvalue
+  binary != '_Bool' rvalue
+    literal 1 'long' rvalue
+    cast 'long' rvalue
+      literal 0 'int' rvalue
+  cast 'double' rvalue
+    literal 2 'long' rvalue
+  literal 3 'double' rvalue
+"
+        );
+    }
+}
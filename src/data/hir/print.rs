@@ -0,0 +1,1299 @@
+//! Printing the HIR as C, used for `--emit=c`.
+//!
+//! Unlike the `Display` impls, which are only meant to be read,
+//! this prints code that compiles and that analyzes back to the same HIR.
+//! The analyzer desugars a lot of C, so the printer undoes the desugaring it can recognize:
+//! loads of variables, `&x`, `a[i]`, `p->a`, pointer arithmetic, `!x`, and conditions
+//! are printed the way they would have been written.
+//! Conversions are left out where the analyzer would insert them again,
+//! unless `PrintOptions::implicit_casts` is set.
+
+use std::collections::HashSet;
+
+use super::*;
+use crate::data::types::{ArrayType, FunctionType, StructType};
+
+/// How to print the HIR as C.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct PrintOptions {
+    /// Print every conversion as a cast, including the ones the analyzer inserted.
+    ///
+    /// The HIR doesn't record which casts were in the source, so this is the only way to see them all.
+    /// The output still compiles, but it may not analyze back to exactly the same HIR.
+    pub implicit_casts: bool,
+}
+
+impl Declaration<'_> {
+    /// Print this declaration as C,
+    /// after the definitions of the structs, unions, and enums it uses.
+    pub fn to_c_string(&self) -> String {
+        self.to_c_string_with(PrintOptions::default())
+    }
+    pub fn to_c_string_with(&self, options: PrintOptions) -> String {
+        let mut printer = CPrinter::new(options);
+        printer.declaration(self);
+        printer.finish()
+    }
+}
+
+impl StmtType<'_> {
+    /// Print this statement as C.
+    pub fn to_c_string(&self) -> String {
+        self.to_c_string_with(PrintOptions::default())
+    }
+    pub fn to_c_string_with(&self, options: PrintOptions) -> String {
+        let mut printer = CPrinter::new(options);
+        printer.stmt(self);
+        printer.finish()
+    }
+}
+
+impl Expr<'_> {
+    /// Print this expression as C.
+    pub fn to_c_string(&self) -> String {
+        self.to_c_string_with(PrintOptions::default())
+    }
+    pub fn to_c_string_with(&self, options: PrintOptions) -> String {
+        let mut printer = CPrinter::new(options);
+        printer.expr(self, COMMA);
+        printer.finish()
+    }
+}
+
+// The precedence of each kind of expression, from loosest to tightest.
+// An operand is put in parentheses if it binds more loosely than its position allows.
+const COMMA: u8 = 1;
+const ASSIGN: u8 = 2;
+const TERNARY: u8 = 3;
+const LOGICAL_OR: u8 = 4;
+const UNARY: u8 = 14;
+const POSTFIX: u8 = 15;
+
+fn precedence(op: BinaryOp) -> u8 {
+    use BinaryOp::*;
+    match op {
+        Assign => ASSIGN,
+        LogicalOr => LOGICAL_OR,
+        LogicalAnd => 5,
+        BitwiseOr => 6,
+        Xor => 7,
+        BitwiseAnd => 8,
+        Compare(ComparisonToken::EqualEqual) | Compare(ComparisonToken::NotEqual) => 9,
+        Compare(_) => 10,
+        Shl | Shr => 11,
+        Add | Sub => 12,
+        Mul | Div | Mod => 13,
+    }
+}
+
+/// Prints a translation unit as C, one declaration at a time.
+///
+/// Each struct, union, and enum is defined once, before the first declaration that uses it.
+pub struct CPrinter {
+    options: PrintOptions,
+    out: String,
+    depth: usize,
+    /// The structs, unions, and enums that have already been defined, such as `struct s`
+    defined: HashSet<String>,
+    /// The return type of the function being printed
+    return_type: Option<Type>,
+    /// The types of the controlling expressions of the `switch`es being printed
+    switches: Vec<Type>,
+}
+
+impl CPrinter {
+    pub fn new(options: PrintOptions) -> Self {
+        CPrinter {
+            options,
+            out: String::new(),
+            depth: 0,
+            defined: HashSet::new(),
+            return_type: None,
+            switches: Vec::new(),
+        }
+    }
+    /// Print a declaration at file scope.
+    pub fn declaration(&mut self, decl: &Declaration) {
+        let mut tags = Tags::default();
+        tags.visit_decl(decl);
+        for tag in tags.0 {
+            self.define(&tag);
+        }
+        self.indent();
+        let meta = decl.symbol.get();
+        match &decl.init {
+            Some(Initializer::FunctionBody(body)) => {
+                let (prefix, declarator) = declaration_parts(&meta, decl.symbol);
+                self.out.push_str(&prefix);
+                self.out.push(' ');
+                self.out.push_str(&declarator);
+                self.out.push_str(" {\n");
+                let return_type = match &meta.ctype {
+                    Type::Function(ftype) => Some((*ftype.return_type).clone()),
+                    _ => None,
+                };
+                let outer = std::mem::replace(&mut self.return_type, return_type);
+                self.block_contents(body);
+                self.return_type = outer;
+                self.out.push_str("}\n\n");
+            }
+            _ => {
+                self.local_declaration(decl);
+                self.out.push_str(";\n");
+            }
+        }
+    }
+    /// Return everything that's been printed.
+    pub fn finish(self) -> String {
+        self.out
+    }
+
+    fn indent(&mut self) {
+        for _ in 0..self.depth {
+            self.out.push_str("    ");
+        }
+    }
+    /// Write `inner` in parentheses if `needed`.
+    fn parens(&mut self, needed: bool, inner: impl FnOnce(&mut Self)) {
+        if needed {
+            self.out.push('(');
+        }
+        inner(self);
+        if needed {
+            self.out.push(')');
+        }
+    }
+    /// Write a prefix operator followed by its operand,
+    /// with a space in between if they would otherwise lex as one token, like `- -x`.
+    fn prefix(&mut self, op: char, operand: impl FnOnce(&mut Self)) {
+        self.out.push(op);
+        let start = self.out.len();
+        operand(self);
+        if op == '-' && self.out[start..].starts_with('-') {
+            self.out.insert(start, ' ');
+        }
+    }
+
+    /* declarations */
+    /// Define `ctype`, a struct, union, or enum, unless it's already been defined or is incomplete.
+    fn define(&mut self, ctype: &Type) {
+        let key = specifier(ctype);
+        let body = match ctype {
+            Type::Struct(stype) | Type::Union(stype) => {
+                let members = stype.members();
+                if members.is_empty() || !self.defined.insert(key.clone()) {
+                    return;
+                }
+                // members of other struct types have to be defined first
+                for member in members.iter() {
+                    let mut tags = Tags::default();
+                    tags.visit_type(&member.ctype);
+                    for tag in tags.0 {
+                        self.define(&tag);
+                    }
+                }
+                struct_body(&members)
+            }
+            Type::Enum(_, members) => {
+                if !self.defined.insert(key.clone()) {
+                    return;
+                }
+                enum_body(members)
+            }
+            _ => unreachable!("only structs, unions, and enums are defined"),
+        };
+        self.indent();
+        self.out.push_str(&format!("{} {};\n", key, body));
+    }
+    /// A declaration without the trailing semicolon.
+    fn local_declaration(&mut self, decl: &Declaration) {
+        let (prefix, declarator) = declaration_parts(&decl.symbol.get(), decl.symbol);
+        self.out.push_str(&prefix);
+        self.out.push(' ');
+        self.declarator_and_init(decl, declarator);
+    }
+    fn declarator_and_init(&mut self, decl: &Declaration, declarator: String) {
+        self.out.push_str(&declarator);
+        if let Some(init) = &decl.init {
+            self.out.push_str(" = ");
+            self.initializer(init);
+        }
+    }
+    fn initializer(&mut self, init: &Initializer) {
+        match init {
+            Initializer::Scalar(expr) => self.scalar_initializer(expr),
+            Initializer::InitializerList(inits) => {
+                self.out.push('{');
+                for (i, init) in inits.iter().enumerate() {
+                    self.out.push_str(if i == 0 { " " } else { ", " });
+                    self.initializer(init);
+                }
+                self.out.push_str(" }");
+            }
+            Initializer::FunctionBody(_) => {
+                unreachable!("function bodies are only printed by `declaration`")
+            }
+        }
+    }
+
+    fn scalar_initializer(&mut self, expr: &Expr) {
+        if expr.lval {
+            // `int *tmp = &x`, from desugaring `x += 1`
+            self.prefix('&', |this| this.expr(expr, UNARY))
+        } else {
+            self.converted(expr, &expr.ctype, ASSIGN)
+        }
+    }
+
+    /* statements */
+    fn block_contents(&mut self, stmts: &[Stmt]) {
+        self.depth += 1;
+        for stmt in stmts {
+            self.stmt(&stmt.data);
+        }
+        self.depth -= 1;
+    }
+    /// Print a statement on its own line.
+    fn stmt(&mut self, stmt: &StmtType) {
+        self.indent();
+        self.stmt_inline(stmt);
+    }
+    /// The body of an `if` or loop: a block starts on the same line, anything else is indented on the next.
+    fn body(&mut self, stmt: &Stmt) {
+        if let StmtType::Compound(_) = stmt.data {
+            self.out.push(' ');
+            self.stmt_inline(&stmt.data);
+        } else {
+            self.out.push('\n');
+            self.depth += 1;
+            self.stmt(&stmt.data);
+            self.depth -= 1;
+        }
+    }
+    /// Print a statement starting at the current position, ending with a newline.
+    fn stmt_inline(&mut self, stmt: &StmtType) {
+        match stmt {
+            StmtType::Compound(stmts) if stmts.is_empty() => self.out.push_str("{}\n"),
+            StmtType::Compound(stmts) => {
+                self.out.push_str("{\n");
+                self.block_contents(stmts);
+                self.indent();
+                self.out.push_str("}\n");
+            }
+            StmtType::If(condition, then, otherwise) => {
+                self.out.push_str("if (");
+                self.condition(condition, COMMA);
+                self.out.push(')');
+                match (&then.data, otherwise) {
+                    // `if (a) { if (b) x; } else y;` needs the braces, or the `else` would go with `if (b)`
+                    (StmtType::If(_, _, None), Some(_)) => {
+                        self.out.push_str(" {\n");
+                        self.depth += 1;
+                        self.stmt(&then.data);
+                        self.depth -= 1;
+                        self.indent();
+                        self.out.push_str("}\n");
+                    }
+                    _ => self.body(then),
+                }
+                if let Some(otherwise) = otherwise {
+                    self.indent();
+                    self.out.push_str("else");
+                    if let StmtType::If(..) = otherwise.data {
+                        self.out.push(' ');
+                        self.stmt_inline(&otherwise.data);
+                    } else {
+                        self.body(otherwise);
+                    }
+                }
+            }
+            StmtType::Do(body, condition) => {
+                self.out.push_str("do");
+                self.body(body);
+                self.indent();
+                self.out.push_str("while (");
+                self.condition(condition, COMMA);
+                self.out.push_str(");\n");
+            }
+            StmtType::While(condition, body) => {
+                self.out.push_str("while (");
+                self.condition(condition, COMMA);
+                self.out.push(')');
+                self.body(body);
+            }
+            StmtType::For(init, condition, post_loop, body) => {
+                let decls = match &init.data {
+                    StmtType::Decl(decls) => shared_prefix(decls),
+                    _ => None,
+                };
+                let inline_init = match &init.data {
+                    StmtType::Expr(_) => true,
+                    StmtType::Compound(stmts) => stmts.is_empty(),
+                    _ => decls.is_some(),
+                };
+                // anything else, like declarations of different types, goes in a block before the loop
+                if !inline_init {
+                    self.out.push_str("{\n");
+                    self.depth += 1;
+                    self.stmt(&init.data);
+                    self.indent();
+                }
+                self.out.push_str("for (");
+                match (&init.data, decls) {
+                    (StmtType::Expr(expr), _) if inline_init => self.expr(expr, COMMA),
+                    (StmtType::Decl(decls), Some(prefix)) => {
+                        self.out.push_str(&prefix);
+                        self.out.push(' ');
+                        for (i, decl) in decls.iter().enumerate() {
+                            if i != 0 {
+                                self.out.push_str(", ");
+                            }
+                            let (_, declarator) =
+                                declaration_parts(&decl.data.symbol.get(), decl.data.symbol);
+                            self.declarator_and_init(&decl.data, declarator);
+                        }
+                    }
+                    _ => {}
+                }
+                self.out.push(';');
+                if let Some(condition) = condition {
+                    self.out.push(' ');
+                    self.condition(condition, COMMA);
+                }
+                self.out.push(';');
+                if let Some(post_loop) = post_loop {
+                    self.out.push(' ');
+                    self.expr(post_loop, COMMA);
+                }
+                self.out.push(')');
+                self.body(body);
+                if !inline_init {
+                    self.depth -= 1;
+                    self.indent();
+                    self.out.push_str("}\n");
+                }
+            }
+            StmtType::Switch(condition, body) => {
+                self.out.push_str("switch (");
+                self.expr(condition, COMMA);
+                self.out.push(')');
+                self.switches.push(condition.ctype.clone());
+                self.body(body);
+                self.switches.pop();
+            }
+            StmtType::Label(name, inner) => {
+                self.out.push_str(&format!("{}: ", name));
+                self.stmt_inline(&inner.data);
+            }
+            StmtType::Case(value, inner) => {
+                // the value was converted to `u64`, so convert it back for signed `switch`es
+                let signed = self.switches.last().map_or(true, Type::is_signed);
+                if signed {
+                    self.out.push_str(&format!("case {}: ", *value as i64));
+                } else if *value > i64::MAX as u64 {
+                    self.out.push_str(&format!("case {}u: ", value));
+                } else {
+                    self.out.push_str(&format!("case {}: ", value));
+                }
+                self.stmt_inline(&inner.data);
+            }
+            StmtType::Default(inner) => {
+                self.out.push_str("default: ");
+                self.stmt_inline(&inner.data);
+            }
+            StmtType::Expr(expr) => {
+                self.expr(expr, COMMA);
+                self.out.push_str(";\n");
+            }
+            StmtType::Goto(label) => self.out.push_str(&format!("goto {};\n", label)),
+            StmtType::Continue => self.out.push_str("continue;\n"),
+            StmtType::Break => self.out.push_str("break;\n"),
+            StmtType::Return(None) => self.out.push_str("return;\n"),
+            StmtType::Return(Some(value)) => {
+                self.out.push_str("return ");
+                match self.return_type.clone() {
+                    Some(return_type) => self.converted(value, &return_type, COMMA),
+                    None => self.expr(value, COMMA),
+                }
+                self.out.push_str(";\n");
+            }
+            StmtType::Decl(decls) => {
+                for (i, decl) in decls.iter().enumerate() {
+                    if i != 0 {
+                        self.indent();
+                    }
+                    self.local_declaration(&decl.data);
+                    self.out.push_str(";\n");
+                }
+            }
+        }
+    }
+
+    /* expressions */
+    /// Print `expr`, in parentheses if it binds more loosely than `prec`.
+    fn expr(&mut self, expr: &Expr, prec: u8) {
+        match &expr.expr {
+            ExprType::Id(symbol) => self.designator(expr, prec, |this, _| {
+                let name = match name(*symbol) {
+                    // only temporaries can be referred to without a name
+                    id if id.is_empty() => temporary(*symbol),
+                    id => id,
+                };
+                this.out.push_str(&name)
+            }),
+            ExprType::Literal(literal) => self.literal(literal, prec),
+            ExprType::FuncCall(func, args) => {
+                self.parens(prec > POSTFIX, |this| this.call(func, args))
+            }
+            ExprType::Member(base, member) => self.designator(expr, prec, |this, prec| {
+                this.parens(prec > POSTFIX, |this| match &base.expr {
+                    // `p->a` is `(*p).a`, but with the dereference marked as an rvalue
+                    ExprType::Noop(pointer) if !base.lval => {
+                        this.expr(pointer, POSTFIX);
+                        this.out.push_str(&format!("->{}", member));
+                    }
+                    _ => {
+                        this.expr(base, POSTFIX);
+                        this.out.push_str(&format!(".{}", member));
+                    }
+                })
+            }),
+            ExprType::Let(symbol, init, body) => self.evaluate_once(*symbol, init, body, prec),
+            // the analyzer converts every comparison and logical operator to `int`
+            ExprType::Cast(inner) if !self.options.implicit_casts && is_boolean_result(expr) => {
+                self.expr(inner, prec)
+            }
+            ExprType::Cast(inner) => self.parens(prec > UNARY, |this| {
+                this.out.push_str(&format!("({})", type_name(&expr.ctype)));
+                this.expr(inner, UNARY);
+            }),
+            ExprType::Sizeof(ctype) => self.parens(prec > UNARY, |this| {
+                this.out.push_str(&format!("sizeof({})", type_name(ctype)))
+            }),
+            // loading the value of an lvalue doesn't need an operator
+            ExprType::Deref(inner) if inner.lval => self.expr(inner, prec),
+            // `fp()` is desugared to `(*fp)()`
+            ExprType::Deref(inner) => self.parens(prec > UNARY, |this| {
+                this.prefix('*', |this| this.expr(inner, UNARY))
+            }),
+            ExprType::Noop(inner) => self.designator(expr, prec, |this, prec| {
+                this.parens(prec > UNARY, |this| {
+                    this.prefix('*', |this| this.expr(inner, UNARY))
+                })
+            }),
+            ExprType::Negate(inner) | ExprType::BitwiseNot(inner) => {
+                let op = if let ExprType::Negate(_) = expr.expr {
+                    '-'
+                } else {
+                    '~'
+                };
+                self.parens(prec > UNARY, |this| {
+                    this.prefix(op, |this| match this.implicit_arithmetic(inner) {
+                        Some(promoted)
+                            if promoted.ctype.clone().integer_promote() == inner.ctype =>
+                        {
+                            this.expr(promoted, UNARY)
+                        }
+                        _ => this.expr(inner, UNARY),
+                    })
+                })
+            }
+            ExprType::Binary(op, left, right) => self.binary(expr, *op, left, right, prec),
+            ExprType::Ternary(condition, then, otherwise) => {
+                let elide = self.promoted_from(then, otherwise, &expr.ctype);
+                self.parens(prec > TERNARY, |this| {
+                    this.condition(condition, LOGICAL_OR);
+                    this.out.push_str(" ? ");
+                    this.operand(then, COMMA, elide);
+                    this.out.push_str(" : ");
+                    this.operand(otherwise, TERNARY, elide);
+                })
+            }
+            ExprType::Comma(left, right) => self.parens(prec > COMMA, |this| {
+                this.expr(left, COMMA);
+                this.out.push_str(", ");
+                this.expr(right, ASSIGN);
+            }),
+            // this only tells the backend the address is known at link time
+            ExprType::StaticRef(inner) => self.expr(inner, prec),
+        }
+    }
+    /// Print `let symbol = init in body`, which the analyzer makes for `x += 1`, `x++`, and `x ?: y`.
+    fn evaluate_once(&mut self, symbol: Symbol, init: &Expr, body: &Expr, prec: u8) {
+        match &body.expr {
+            // `x += 1` is `tmp = &x, *tmp = *tmp + 1`
+            ExprType::Binary(BinaryOp::Assign, _, value) if init.lval => {
+                if let Some((op, left, right)) = compound_assignment(value) {
+                    if is_increment_one(right) {
+                        return self.parens(prec > UNARY, |this| {
+                            this.out.push_str(&format!("{}{}", op, op));
+                            this.expr(init, UNARY);
+                        });
+                    }
+                    let elide = self.promoted_from(left, right, &left.ctype);
+                    return self.parens(prec > ASSIGN, |this| {
+                        this.expr(init, UNARY);
+                        this.out.push_str(&format!(" {}= ", op));
+                        this.operand(right, ASSIGN, elide);
+                    });
+                }
+            }
+            // `x++` is `tmp = &x, old = *tmp, (*tmp = old + 1, old)`
+            ExprType::Let(_, _, inner) if init.lval => {
+                if let ExprType::Comma(assign, _) = &inner.expr {
+                    if let ExprType::Binary(BinaryOp::Assign, _, value) = &assign.expr {
+                        if let Some((op, _, _)) = compound_assignment(value) {
+                            return self.parens(prec > POSTFIX, |this| {
+                                this.expr(init, POSTFIX);
+                                this.out.push_str(&format!("{}{}", op, op));
+                            });
+                        }
+                    }
+                }
+            }
+            // `x ?: y` is `tmp = x, tmp ? tmp : y`
+            ExprType::Ternary(_, then, otherwise) if !init.lval => {
+                let elide = self.promoted_from(then, otherwise, &body.ctype);
+                return self.parens(prec > TERNARY, |this| {
+                    this.expr(init, LOGICAL_OR);
+                    this.out.push_str(" ?: ");
+                    this.operand(otherwise, TERNARY, elide);
+                });
+            }
+            _ => {}
+        }
+        // anything else is printed as a GNU statement expression
+        let meta = symbol.get();
+        let (base, declarator) = declarator(&meta.ctype, temporary(symbol));
+        self.out
+            .push_str(&format!("({{ {} {} = ", specifier(base), declarator));
+        self.scalar_initializer(init);
+        self.out.push_str("; ");
+        self.expr(body, COMMA);
+        self.out.push_str("; })");
+    }
+    /// Print an expression that designates an object, like `x` or `s.a`,
+    /// taking its address if it was used as an rvalue of pointer type.
+    fn designator(&mut self, expr: &Expr, prec: u8, object: impl FnOnce(&mut Self, u8)) {
+        if needs_address_of(expr) {
+            self.parens(prec > UNARY, |this| {
+                this.prefix('&', |this| object(this, UNARY))
+            })
+        } else {
+            object(self, prec)
+        }
+    }
+    fn binary(&mut self, expr: &Expr, op: BinaryOp, left: &Expr, right: &Expr, prec: u8) {
+        let op_prec = precedence(op);
+        match op {
+            BinaryOp::Assign => self.parens(prec > ASSIGN, |this| {
+                this.expr(left, UNARY);
+                this.out.push_str(" = ");
+                this.converted(right, &left.ctype, ASSIGN);
+            }),
+            BinaryOp::LogicalAnd | BinaryOp::LogicalOr => self.parens(prec > op_prec, |this| {
+                this.converted(left, &Type::Bool, op_prec);
+                this.out.push_str(&format!(" {} ", op));
+                this.converted(right, &Type::Bool, op_prec + 1);
+            }),
+            // `a[i]` and `p + i` are desugared to `p + sizeof(*p) * i`
+            BinaryOp::Add | BinaryOp::Sub if pointer_offset(expr).is_some() => {
+                let (base, index) = pointer_offset(expr).unwrap();
+                if expr.lval {
+                    self.parens(prec > POSTFIX, |this| {
+                        this.expr(base, POSTFIX);
+                        this.out.push('[');
+                        this.expr(index, COMMA);
+                        this.out.push(']');
+                    })
+                } else {
+                    self.parens(prec > op_prec, |this| {
+                        this.expr(base, op_prec);
+                        this.out.push_str(&format!(" {} ", op));
+                        this.expr(index, op_prec + 1);
+                    })
+                }
+            }
+            // `!x` is desugared to `(x != 0) == 0`
+            BinaryOp::Compare(ComparisonToken::EqualEqual)
+                if !self.options.implicit_casts
+                    && left.ctype == Type::Bool
+                    && right.ctype == Type::Bool
+                    && is_condition_zero(right) =>
+            {
+                self.parens(prec > UNARY, |this| {
+                    this.out.push('!');
+                    this.condition(left, UNARY);
+                })
+            }
+            _ => {
+                let elide = self.promoted_from(left, right, &left.ctype);
+                self.parens(prec > op_prec, |this| {
+                    this.operand(left, op_prec, elide);
+                    this.out.push_str(&format!(" {} ", op));
+                    this.operand(right, op_prec + 1, elide);
+                })
+            }
+        }
+    }
+    fn call(&mut self, func: &Expr, args: &[Expr]) {
+        let ftype = match &func.ctype {
+            Type::Function(ftype) => Some(ftype),
+            _ => None,
+        };
+        match &func.expr {
+            // `fp()` is desugared to `(*fp)()`
+            ExprType::Deref(pointer) if !pointer.lval => self.expr(pointer, POSTFIX),
+            _ => self.expr(func, POSTFIX),
+        }
+        self.out.push('(');
+        for (i, arg) in args.iter().enumerate() {
+            if i != 0 {
+                self.out.push_str(", ");
+            }
+            let param = ftype.and_then(|ftype| prototype_param(ftype, i));
+            match param {
+                Some(param) => self.converted(arg, &param, ASSIGN),
+                // the default argument promotions
+                None => match self.implicit_arithmetic(arg) {
+                    Some(inner) if inner.ctype.clone().default_promote() == arg.ctype => {
+                        self.expr(inner, ASSIGN)
+                    }
+                    _ => self.expr(arg, ASSIGN),
+                },
+            }
+        }
+        self.out.push(')');
+    }
+    /// Print `expr`, which was converted to `target`, without the conversion if the analyzer would add it back.
+    fn converted(&mut self, expr: &Expr, target: &Type, prec: u8) {
+        match &expr.expr {
+            ExprType::Cast(inner)
+                if !self.options.implicit_casts
+                    && expr.ctype == *target
+                    && is_implicit_conversion(inner, target) =>
+            {
+                self.expr(inner, prec)
+            }
+            _ => self.expr(expr, prec),
+        }
+    }
+    /// Print the operand of an arithmetic operator, leaving out the usual arithmetic conversions if `elide` is set.
+    fn operand(&mut self, expr: &Expr, prec: u8, elide: bool) {
+        match self.implicit_arithmetic(expr) {
+            Some(inner) if elide => self.expr(inner, prec),
+            _ => self.expr(expr, prec),
+        }
+    }
+    /// If `expr` is a conversion between arithmetic types that might have been implicit,
+    /// return the expression that was converted.
+    fn implicit_arithmetic<'a, 'hir>(&self, expr: &'a Expr<'hir>) -> Option<&'a Expr<'hir>> {
+        match &expr.expr {
+            ExprType::Cast(inner)
+                if !self.options.implicit_casts
+                    && expr.ctype.is_arithmetic()
+                    && inner.ctype.is_arithmetic() =>
+            {
+                Some(inner)
+            }
+            _ => None,
+        }
+    }
+    /// Whether the usual arithmetic conversions on `left` and `right`, without their casts, give `ctype`.
+    fn promoted_from(&self, left: &Expr, right: &Expr, ctype: &Type) -> bool {
+        if !left.ctype.is_arithmetic() || left.ctype != right.ctype {
+            return false;
+        }
+        let left = self.implicit_arithmetic(left).unwrap_or(left).ctype.clone();
+        let right = self
+            .implicit_arithmetic(right)
+            .unwrap_or(right)
+            .ctype
+            .clone();
+        Type::binary_promote(left, right).as_ref() == Ok(ctype)
+    }
+    /// Print the controlling expression of an `if`, loop, `?:`, or `!`.
+    ///
+    /// The analyzer turns `if (x)` into `if (x != 0)` with a zero that couldn't have come from the source.
+    fn condition(&mut self, condition: &Expr, prec: u8) {
+        match &condition.expr {
+            ExprType::Binary(BinaryOp::Compare(ComparisonToken::NotEqual), value, zero)
+                if !self.options.implicit_casts && is_condition_zero(zero) =>
+            {
+                self.expr(value, prec)
+            }
+            _ => self.expr(condition, prec),
+        }
+    }
+    fn literal(&mut self, literal: &Literal, prec: u8) {
+        match literal {
+            // `-9223372036854775808` would overflow before it was negated
+            Literal::Int(i64::MIN) => self.out.push_str(&format!("({} - 1)", i64::MIN + 1)),
+            Literal::Int(i) => self.parens(*i < 0 && prec > UNARY, |this| {
+                this.out.push_str(&i.to_string())
+            }),
+            Literal::UnsignedInt(u) => self.out.push_str(&format!("{}u", u)),
+            Literal::Float(f) if f.is_nan() => self.out.push_str("(0.0 / 0.0)"),
+            Literal::Float(f) if f.is_infinite() => {
+                let sign = if *f < 0.0 { "-" } else { "" };
+                self.out.push_str(&format!("({}1.0 / 0.0)", sign));
+            }
+            // unlike `Display`, `Debug` always prints a decimal point or exponent
+            Literal::Float(f) => self.parens(f.is_sign_negative() && prec > UNARY, |this| {
+                this.out.push_str(&format!("{:?}", f))
+            }),
+            Literal::Char(c) => self.out.push_str(&format!("'{}'", escape(&[*c], b'\''))),
+            Literal::Str(s) => {
+                let s = s.strip_suffix(&[0]).unwrap_or(s);
+                self.out.push_str(&format!("\"{}\"", escape(s, b'"')));
+            }
+            Literal::WideStr(..) | Literal::SingleFloat(_) => {
+                self.out.push_str(&literal.to_string())
+            }
+        }
+    }
+}
+
+/// The structs, unions, and enums used in part of the HIR, in the order they're first seen.
+#[derive(Default)]
+struct Tags(Vec<Type>);
+
+impl Visitor for Tags {
+    fn visit_type(&mut self, ctype: &Type) {
+        match ctype {
+            Type::Struct(StructType::Named(..))
+            | Type::Union(StructType::Named(..))
+            | Type::Enum(Some(_), _) => self.0.push(ctype.clone()),
+            // anonymous structs are printed inline, but their members may not be
+            Type::Struct(StructType::Anonymous(members))
+            | Type::Union(StructType::Anonymous(members)) => {
+                for member in members.iter() {
+                    self.visit_type(&member.ctype);
+                }
+            }
+            _ => {}
+        }
+        walk_type(self, ctype);
+    }
+}
+
+/// The name of a variable, renaming the ones that aren't valid identifiers.
+fn name(symbol: Symbol) -> String {
+    let id = symbol.get().id.resolve_and_clone();
+    let mut chars = id.chars();
+    let is_identifier = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if is_identifier || id.is_empty() {
+        id
+    } else {
+        format!("__tmp{}", symbol.0)
+    }
+}
+
+/// Split a declaration into the part shared by every declarator, like `static const int`,
+/// and the declarator itself, like `*p[3]`.
+fn declaration_parts(meta: &Variable, symbol: Symbol) -> (String, String) {
+    let mut prefix = String::new();
+    match meta.storage_class {
+        StorageClass::Auto => {}
+        // functions are `extern` by default
+        StorageClass::Extern if meta.ctype.is_function() => {}
+        other => prefix.push_str(&format!("{} ", other)),
+    }
+    if meta.qualifiers != Qualifiers::default() {
+        prefix.push_str(&format!("{} ", meta.qualifiers));
+    }
+    let (base, declarator) = declarator(&meta.ctype, name(symbol));
+    prefix.push_str(&specifier(base));
+    (prefix, declarator)
+}
+
+/// The prefix shared by all of `decls`, if they can be printed as a single declaration.
+fn shared_prefix(decls: &[Locatable<Declaration>]) -> Option<String> {
+    let mut prefixes = decls.iter().map(|decl| {
+        let symbol = decl.data.symbol;
+        declaration_parts(&symbol.get(), symbol).0
+    });
+    let first = prefixes.next()?;
+    if prefixes.all(|prefix| prefix == first) {
+        Some(first)
+    } else {
+        None
+    }
+}
+
+/// Split `ctype` into the type at the root of the declarator and the declarator around `inner`:
+/// `int (*p)[3]` is `int` and `(*p)[3]`.
+fn declarator(ctype: &Type, inner: String) -> (&Type, String) {
+    match ctype {
+        Type::Pointer(to, qualifiers) => {
+            let mut pointer = String::from("*");
+            if *qualifiers != Qualifiers::default() {
+                pointer.push_str(&qualifiers.to_string());
+                if !inner.is_empty() {
+                    pointer.push(' ');
+                }
+            }
+            pointer.push_str(&inner);
+            // `int *a[3]` is an array of pointers, `int (*a)[3]` is a pointer to an array
+            if let Type::Array(..) | Type::Function(_) = **to {
+                pointer = format!("({})", pointer);
+            }
+            declarator(to, pointer)
+        }
+        Type::Array(of, ArrayType::Fixed(len)) => declarator(of, format!("{}[{}]", inner, len)),
+        Type::Array(of, ArrayType::Unbounded) => declarator(of, format!("{}[]", inner)),
+        Type::Function(ftype) => {
+            let params = params(ftype);
+            declarator(&ftype.return_type, format!("{}({})", inner, params))
+        }
+        _ => (ctype, inner),
+    }
+}
+
+fn params(ftype: &FunctionType) -> String {
+    let mut params: Vec<_> = ftype
+        .params
+        .iter()
+        .map(|&param| {
+            let (prefix, declarator) = declaration_parts(&param.get(), param);
+            if declarator.is_empty() {
+                prefix
+            } else {
+                format!("{} {}", prefix, declarator)
+            }
+        })
+        .collect();
+    if ftype.varargs {
+        params.push("...".into());
+    }
+    params.join(", ")
+}
+
+/// A type name, like in a cast: `char *(*)(float)`.
+fn type_name(ctype: &Type) -> String {
+    let (base, declarator) = declarator(ctype, String::new());
+    if declarator.is_empty() {
+        specifier(base)
+    } else {
+        format!("{} {}", specifier(base), declarator)
+    }
+}
+
+fn specifier(ctype: &Type) -> String {
+    let sign = |signed: bool| if signed { "" } else { "unsigned " };
+    match ctype {
+        Type::Void => "void".into(),
+        Type::Bool => "_Bool".into(),
+        Type::Char(signed) => format!("{}char", sign(*signed)),
+        Type::Short(signed) => format!("{}short", sign(*signed)),
+        Type::Int(signed) => format!("{}int", sign(*signed)),
+        Type::Long(signed) => format!("{}long", sign(*signed)),
+        Type::Float => "float".into(),
+        Type::Double => "double".into(),
+        Type::Struct(stype) => format!("struct {}", struct_specifier(stype)),
+        Type::Union(stype) => format!("union {}", struct_specifier(stype)),
+        Type::Enum(Some(name), _) => format!("enum {}", name),
+        Type::Enum(None, members) => format!("enum {}", enum_body(members)),
+        Type::VaList => "__builtin_va_list".into(),
+        // there's no way to write this, but it can only happen if there were errors
+        Type::Error => "int".into(),
+        Type::Pointer(..) | Type::Array(..) | Type::Function(_) => {
+            unreachable!("derived types are part of the declarator")
+        }
+    }
+}
+
+fn struct_specifier(stype: &StructType) -> String {
+    match stype {
+        StructType::Named(name, _) => name.resolve_and_clone(),
+        StructType::Anonymous(members) => struct_body(members),
+    }
+}
+
+fn struct_body(members: &[Variable]) -> String {
+    let mut body = String::from("{");
+    for member in members {
+        let (prefix, declarator) = declarator(&member.ctype, member.id.resolve_and_clone());
+        let qualifiers = if member.qualifiers == Qualifiers::default() {
+            String::new()
+        } else {
+            format!("{} ", member.qualifiers)
+        };
+        body.push_str(&format!(
+            " {}{} {};",
+            qualifiers,
+            specifier(prefix),
+            declarator
+        ));
+    }
+    body.push_str(" }");
+    body
+}
+
+fn enum_body(members: &[(InternedStr, i64)]) -> String {
+    let members: Vec<_> = members
+        .iter()
+        .map(|(name, value)| format!("{} = {}", name, value))
+        .collect();
+    format!("{{ {} }}", members.join(", "))
+}
+
+/// The parameter type argument `i` is converted to, if the function has a prototype that includes it.
+fn prototype_param(ftype: &FunctionType, i: usize) -> Option<Type> {
+    let param = ftype.params.get(i)?.get();
+    if param.ctype == Type::Void {
+        None
+    } else {
+        Some(param.ctype.clone())
+    }
+}
+
+/// Whether `expr` is an rvalue of pointer type made by taking the address of an object,
+/// as opposed to an array or function decaying to a pointer.
+fn needs_address_of(expr: &Expr) -> bool {
+    if expr.lval {
+        return false;
+    }
+    let object = match &expr.expr {
+        ExprType::Id(symbol) => symbol.get().ctype.clone(),
+        ExprType::Member(base, member) => match &base.ctype {
+            Type::Struct(stype) | Type::Union(stype) => {
+                match stype.members().iter().find(|m| m.id == *member) {
+                    Some(member) => member.ctype.clone(),
+                    None => return false,
+                }
+            }
+            _ => return false,
+        },
+        ExprType::Noop(pointer) => match &pointer.ctype {
+            Type::Pointer(to, _) => (**to).clone(),
+            _ => return false,
+        },
+        _ => return false,
+    };
+    let decayed = match &object {
+        Type::Array(to, _) => Type::Pointer(to.clone(), Qualifiers::default()),
+        // `f` and `&f` have the same value, but only `&f` keeps the parameters of `f`
+        // instead of taking the ones of whatever it's converted to
+        Type::Function(function) => {
+            return match &expr.ctype {
+                Type::Pointer(to, qualifiers) if *qualifiers == Qualifiers::default() => {
+                    matches!(&**to, Type::Function(to) if to.params == function.params)
+                }
+                _ => false,
+            }
+        }
+        // structs used as rvalues keep their type
+        _ => object.clone(),
+    };
+    expr.ctype != object && expr.ctype != decayed
+}
+
+/// If `expr` is `p + sizeof(*p) * i` or `p - sizeof(*p) * i`, return `p` and `i`.
+fn pointer_offset<'a, 'hir>(expr: &'a Expr<'hir>) -> Option<(&'a Expr<'hir>, &'a Expr<'hir>)> {
+    let (base, offset) = match &expr.expr {
+        ExprType::Binary(BinaryOp::Add, base, offset)
+        | ExprType::Binary(BinaryOp::Sub, base, offset)
+            if base.ctype.is_pointer() =>
+        {
+            (base, offset)
+        }
+        _ => return None,
+    };
+    match &offset.expr {
+        ExprType::Binary(BinaryOp::Mul, size, index) => match (&size.expr, &index.expr) {
+            (ExprType::Literal(Literal::Int(_)), ExprType::Cast(index)) => Some((base, index)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// If `value` is the new value in `x op= y`, return `op`, the converted `x`, and `y`.
+fn compound_assignment<'a, 'hir>(
+    value: &'a Expr<'hir>,
+) -> Option<(BinaryOp, &'a Expr<'hir>, &'a Expr<'hir>)> {
+    // the result of `op` is converted back to the type of `x`
+    let value = match &value.expr {
+        ExprType::Cast(inner) => inner,
+        _ => value,
+    };
+    if let Some((base, index)) = pointer_offset(value) {
+        if let ExprType::Binary(op, _, _) = value.expr {
+            return Some((op, base, index));
+        }
+    }
+    match &value.expr {
+        ExprType::Binary(op, left, right) => Some((*op, left, right)),
+        _ => None,
+    }
+}
+
+/// Whether `one` is the `1` the analyzer adds for `++` and `--`.
+///
+/// Like in `is_condition_zero`, an `int` literal can't have come from the source.
+fn is_increment_one(one: &Expr) -> bool {
+    let one = match &one.expr {
+        ExprType::Cast(inner) => inner,
+        _ => one,
+    };
+    one.expr == ExprType::Literal(Literal::Int(1)) && one.ctype == Type::Int(true)
+}
+
+/// A name for a temporary the analyzer made up, which has no name of its own.
+fn temporary(symbol: Symbol) -> String {
+    format!("__tmp{}", symbol.0)
+}
+
+/// Whether `zero` is the `0` the analyzer compares against when converting a scalar to `_Bool`.
+///
+/// Integer literals in the source are `long`, so an `int` zero must have come from the analyzer.
+fn is_condition_zero(zero: &Expr) -> bool {
+    let zero = match &zero.expr {
+        ExprType::Cast(inner) => inner,
+        _ => zero,
+    };
+    zero.expr == ExprType::Literal(Literal::Int(0)) && zero.ctype == Type::Int(true)
+}
+
+/// Whether `expr` is the `int` result of a comparison or of `!`, `&&`, or `||`,
+/// which the analyzer computes as a `_Bool` and then converts.
+fn is_boolean_result(expr: &Expr) -> bool {
+    match &expr.expr {
+        ExprType::Cast(inner) if expr.ctype == Type::Int(true) && inner.ctype == Type::Bool => {
+            matches!(
+                inner.expr,
+                ExprType::Binary(
+                    BinaryOp::Compare(_) | BinaryOp::LogicalAnd | BinaryOp::LogicalOr,
+                    _,
+                    _
+                )
+            )
+        }
+        _ => false,
+    }
+}
+
+/// Whether the analyzer converts `expr` to `target` by itself when assigning, returning, or passing arguments.
+fn is_implicit_conversion(expr: &Expr, target: &Type) -> bool {
+    let points_to_object =
+        |ctype: &Type| matches!(ctype, Type::Pointer(to, _) if !to.is_function());
+    expr.ctype.is_arithmetic() && target.is_arithmetic()
+        || expr.is_null() && target.is_pointer()
+        || expr.ctype.is_pointer() && (target.is_bool() || target.is_void_pointer())
+        // `int *` to `char *`, which is allowed with a warning
+        || points_to_object(&expr.ctype) && points_to_object(target)
+}
+
+/// Escape the characters in a string or character literal.
+fn escape(bytes: &[u8], quote: u8) -> String {
+    let mut escaped = String::new();
+    for &byte in bytes {
+        match byte {
+            b'\n' => escaped.push_str("\\n"),
+            b'\t' => escaped.push_str("\\t"),
+            b'\r' => escaped.push_str("\\r"),
+            b'\\' => escaped.push_str("\\\\"),
+            _ if byte == quote => {
+                escaped.push('\\');
+                escaped.push(char::from(byte));
+            }
+            b' '..=b'~' => escaped.push(char::from(byte)),
+            // three digits, so a digit after it isn't part of the escape
+            _ => escaped.push_str(&format!("\\{:03o}", byte)),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CPrinter, PrintOptions};
+    use crate::analyze::test::decls;
+
+    const PRELUDE: &str = "
+        struct s { int a; double d; struct s *next; int arr[3]; };
+        union u { int i; float f; };
+        int i, a[10], *p, **pp, (*ap)[10];
+        unsigned u; long l; char c; short sh; double d; float f; _Bool b;
+        struct s s, *sp; union u un;
+        int g(int x, double y);
+        int h();
+        int (*fp)(int, double);
+        int v(const char *fmt, ...);
+        ";
+
+    /// The dump of every declaration in `program`, and the C they print as.
+    fn analyze(program: &str, options: PrintOptions) -> (String, String) {
+        let mut dump = String::new();
+        let mut printer = CPrinter::new(options);
+        for decl in decls(program) {
+            let decl = decl.unwrap_or_else(|err| panic!("error in {}: {}", program, err.data));
+            dump.push_str(&decl.dump());
+            printer.declaration(&decl);
+        }
+        (dump, printer.finish())
+    }
+
+    /// Check that `program` prints as C that analyzes to the same HIR.
+    fn assert_round_trip(program: &str) {
+        let (original, printed) = analyze(program, PrintOptions::default());
+        let (reparsed, reprinted) = analyze(&printed, PrintOptions::default());
+        assert_eq!(original, reparsed, "printed as:\n{}", printed);
+        assert_eq!(printed, reprinted);
+    }
+
+    #[test]
+    fn round_trip_exprs() {
+        let exprs = [
+            "i + 1",
+            "i * (l + 2)",
+            "(i + 1) * 2",
+            "i - (2 - 3)",
+            "i - 2 - 3",
+            "u + i",
+            "c + sh",
+            "d * f",
+            "i << 2 >> 1",
+            "i < l == (b != 0)",
+            "i & 1 | i ^ 2",
+            "-i",
+            "~c",
+            "- -i",
+            "-(-1)",
+            "!i",
+            "!p",
+            "!(i < 2)",
+            "!!d",
+            "i && p || d",
+            "i ? d : 1",
+            "b ? i : l",
+            "i > 0 ? i : -i",
+            "i = 1",
+            "i = l = 2",
+            "d = i",
+            "b = p",
+            "p = 0",
+            "p = &i",
+            "p = a",
+            "ap = &a",
+            "*p = 1",
+            "**pp",
+            "a[i] = a[2]",
+            "p[1]",
+            "2[a]",
+            "p + 1",
+            "1 + p",
+            "&a[3]",
+            "*(p + 1)",
+            "p = &*p",
+            "s.a",
+            "sp->next->a",
+            "(*sp).d",
+            "&s.arr[1]",
+            "&sp->d",
+            "s.arr",
+            "un.f = f",
+            "s = *sp",
+            "g(i, 1)",
+            "g(c, f)",
+            "h(c, f)",
+            "v(\"%d\\n\", c, f)",
+            "fp(1, 2.0)",
+            "(*fp)(1, 2.0)",
+            "fp = g",
+            "fp = &g",
+            "i++",
+            "p--",
+            "++i",
+            "--p",
+            "*p++",
+            "i += 2",
+            "*p -= 1",
+            "p += 2",
+            "p - 1",
+            "d *= i",
+            "c <<= 1",
+            "s.a |= sp->a",
+            "i ?: l",
+            "p ?: a",
+            "(void)i",
+            "(long)i + (long)i",
+            "(int)c + i",
+            "(char)i",
+            "(unsigned)d",
+            "(char *)p",
+            "i, l",
+            "g((i, 2), 3)",
+            "'a' + '\\n' + '\\''",
+            "\"a\\\"b\\\\c\\001\" [1]",
+            "1.5 + 1e100 + 2.0",
+            "4294967295u",
+            "sizeof(struct s) + sizeof i",
+        ];
+        for expr in &exprs {
+            assert_round_trip(&format!("{} void test(void) {{ {}; }}", PRELUDE, expr));
+        }
+    }
+
+    #[test]
+    fn round_trip_stmts() {
+        let bodies = [
+            "if (i) i = 2; else if (p) i = 3; else { i = 4; }",
+            "if (i) { if (l) i = 2; } else i = 3;",
+            "while (i < 10) i++;",
+            "do { i--; } while (i);",
+            "for (;;) break;",
+            "for (i = 0; i < 10; i++) continue;",
+            "for (int j = 0, k[2] = {1, 2}; j < k[1]; j++) {}",
+            "for (int j = 0, *q = &j; j; q++) {}",
+            "switch (i) { case -1: i = 2; case 3: case 4: break; default: i = 0; }",
+            "switch (u) { case 4294967295u: break; }",
+            "start: i++; if (i < 10) goto start;",
+            "char str[] = \"hi\"; int arr[2][2] = {{1, 2}, {3, 4}}; static double x = 1;",
+            "struct t { int x; struct t *self; } t = {1, 0}; t.self = &t;",
+            "struct { char c; } anonymous; anonymous.c = 'x';",
+            "const int k = 1; register long r = k; i = r;",
+        ];
+        for body in &bodies {
+            assert_round_trip(&format!("{} void test(void) {{ {} }}", PRELUDE, body));
+        }
+        let functions = [
+            "int ret(char x) { return x; }",
+            "double half(int x) { return x / 2.0; }",
+            "static int *first(int *xs, unsigned len) { return len ? xs : 0; }",
+            "int apply(int (*f)(int), int x) { return f(x); }",
+            "char *(*lookup(int i))[3] { return 0; }",
+            "int sum(int n, ...) { return n; }",
+        ];
+        for function in &functions {
+            assert_round_trip(&format!("{} {}", PRELUDE, function));
+        }
+    }
+
+    #[test]
+    fn implicit_casts() {
+        let program = "long f(int i, char c) { return i + c; }";
+        let (_, printed) = analyze(program, PrintOptions::default());
+        assert_eq!(printed, "long f(int i, char c) {\n    return i + c;\n}\n\n");
+        let options = PrintOptions {
+            implicit_casts: true,
+        };
+        let (_, printed) = analyze(program, options);
+        assert_eq!(
+            printed,
+            "long f(int i, char c) {\n    return (long)(i + (int)c);\n}\n\n"
+        );
+    }
+
+    #[test]
+    fn compound_assignment() {
+        // the temporary the analyzer makes up isn't printed
+        let (_, printed) = analyze("int f(int *p) { return *p += 1; }", PrintOptions::default());
+        assert!(printed.contains("return *p += 1;"), "{}", printed);
+        assert!(!printed.contains("__tmp"), "{}", printed);
+    }
+}
@@ -0,0 +1,398 @@
+//! `Serialize` and `Deserialize` for the HIR, enabled by the `serialize` feature.
+//!
+//! Most of the HIR derives both traits directly.
+//! The exceptions are `Symbol` and `StructRef`, which are indices into thread-local tables:
+//! they are written as numeric ids, and the metadata they refer to is written once,
+//! in the side tables of a [`TranslationUnit`].
+//! Ids are numbered in the order they are first reached from the declarations,
+//! so they do not depend on anything else that was compiled on the same thread.
+//!
+//! Subexpressions and substatements normally live in an [`Arena`],
+//! but deserializing has no arena to put them in, so they are leaked instead.
+//! This is fine for tools that read a program once, but not for long-running processes.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use serde::de::Error as _;
+use serde::ser::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::*;
+use crate::data::types::{StructRef, StructType};
+
+/// A list of declarations, along with the metadata for every symbol and struct they use.
+///
+/// `Symbol`s and `StructRef`s can only be serialized or deserialized as part of a `TranslationUnit`,
+/// since they are meaningless without their side tables.
+///
+/// Deserializing creates new symbols and structs on the current thread,
+/// so comparing the result with the original will only succeed for programs without symbols.
+/// Compare the `dump` of each declaration instead.
+#[derive(Debug, PartialEq)]
+pub struct TranslationUnit<'hir> {
+    pub declarations: Vec<Locatable<Declaration<'hir>>>,
+}
+
+impl<'hir> From<Vec<Locatable<Declaration<'hir>>>> for TranslationUnit<'hir> {
+    fn from(declarations: Vec<Locatable<Declaration<'hir>>>) -> Self {
+        Self { declarations }
+    }
+}
+
+thread_local!(
+    /// The ids for the `TranslationUnit` currently being serialized or deserialized, if any.
+    static CONTEXT: RefCell<Option<Context>> = Default::default()
+);
+
+enum Context {
+    Serialize {
+        symbols: HashMap<Symbol, usize>,
+        structs: HashMap<usize, usize>,
+    },
+    /// Ids are mapped to placeholders as they are seen,
+    /// and the placeholders are filled in from the side tables at the end.
+    Deserialize {
+        symbols: HashMap<usize, Symbol>,
+        structs: HashMap<usize, StructRef>,
+    },
+}
+
+/// Sets `CONTEXT` until it is dropped, even if (de)serialization returns early.
+struct ContextGuard(Option<Context>);
+
+impl ContextGuard {
+    fn enter(context: Context) -> Self {
+        ContextGuard(CONTEXT.with(|current| current.replace(Some(context))))
+    }
+}
+
+impl Drop for ContextGuard {
+    fn drop(&mut self) {
+        CONTEXT.with(|current| current.replace(self.0.take()));
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename = "TranslationUnit")]
+struct SerializeTables<'a, 'hir> {
+    declarations: &'a [Locatable<Declaration<'hir>>],
+    symbols: Vec<Rc<Variable>>,
+    structs: Vec<Rc<Vec<Variable>>>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "TranslationUnit")]
+struct DeserializeTables {
+    declarations: Vec<Locatable<Declaration<'static>>>,
+    symbols: Vec<Variable>,
+    structs: Vec<Vec<Variable>>,
+}
+
+impl Serialize for TranslationUnit<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut ids = Ids::default();
+        for decl in &self.declarations {
+            ids.visit_decl(&decl.data);
+        }
+        let tables = SerializeTables {
+            declarations: &self.declarations,
+            symbols: ids.symbols.iter().map(|symbol| symbol.get()).collect(),
+            structs: ids.structs.iter().map(|s| s.get()).collect(),
+        };
+        let _guard = ContextGuard::enter(Context::Serialize {
+            symbols: ids.symbol_ids,
+            structs: ids.struct_ids,
+        });
+        tables.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for TranslationUnit<'static> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let guard = ContextGuard::enter(Context::Deserialize {
+            symbols: HashMap::new(),
+            structs: HashMap::new(),
+        });
+        let tables = DeserializeTables::deserialize(deserializer)?;
+        let (symbol_count, struct_count) = (tables.symbols.len(), tables.structs.len());
+        for (id, metadata) in tables.symbols.into_iter().enumerate() {
+            symbol_for_id(id)
+                .map_err(D::Error::custom)?
+                .replace(metadata);
+        }
+        for (id, members) in tables.structs.into_iter().enumerate() {
+            struct_for_id(id).map_err(D::Error::custom)?.update(members);
+        }
+        // anything not in the side tables would be left as a placeholder
+        CONTEXT.with(|context| match &*context.borrow() {
+            Some(Context::Deserialize { symbols, structs }) => {
+                if let Some(id) = symbols.keys().find(|&&id| id >= symbol_count) {
+                    Err(D::Error::custom(format!(
+                        "symbol {} is not in the side table",
+                        id
+                    )))
+                } else if let Some(id) = structs.keys().find(|&&id| id >= struct_count) {
+                    Err(D::Error::custom(format!(
+                        "struct {} is not in the side table",
+                        id
+                    )))
+                } else {
+                    Ok(())
+                }
+            }
+            _ => unreachable!("the context was set at the start of deserialize"),
+        })?;
+        drop(guard);
+        Ok(TranslationUnit {
+            declarations: tables.declarations,
+        })
+    }
+}
+
+/// Collect every symbol and struct reachable from a declaration, in the order they are reached.
+#[derive(Default)]
+struct Ids {
+    symbols: Vec<Symbol>,
+    symbol_ids: HashMap<Symbol, usize>,
+    structs: Vec<StructRef>,
+    struct_ids: HashMap<usize, usize>,
+}
+
+impl Ids {
+    fn symbol(&mut self, symbol: Symbol) {
+        if self.symbol_ids.contains_key(&symbol) {
+            return;
+        }
+        self.symbol_ids.insert(symbol, self.symbols.len());
+        self.symbols.push(symbol);
+        self.visit_type(&symbol.get().ctype);
+    }
+    fn struct_ref(&mut self, struct_ref: StructRef) {
+        if self.struct_ids.contains_key(&struct_ref.index()) {
+            return;
+        }
+        self.struct_ids
+            .insert(struct_ref.index(), self.structs.len());
+        self.structs.push(struct_ref);
+        self.members(&struct_ref.get());
+    }
+    fn members(&mut self, members: &[Variable]) {
+        for member in members {
+            self.visit_type(&member.ctype);
+        }
+    }
+}
+
+impl Visitor for Ids {
+    fn visit_decl(&mut self, decl: &Declaration) {
+        self.symbol(decl.symbol);
+        walk_declaration(self, decl);
+    }
+    fn visit_expr(&mut self, expr: &Expr) {
+        if let ExprType::Id(symbol) | ExprType::Let(symbol, ..) = expr.expr {
+            self.symbol(symbol);
+        }
+        walk_expr(self, expr);
+    }
+    // `walk_type` doesn't look at struct members, so that has to be done here.
+    fn visit_type(&mut self, ctype: &Type) {
+        match ctype {
+            Type::Function(ftype) => {
+                for &param in &ftype.params {
+                    self.symbol(param);
+                }
+            }
+            Type::Struct(StructType::Named(_, struct_ref))
+            | Type::Union(StructType::Named(_, struct_ref)) => self.struct_ref(*struct_ref),
+            Type::Struct(StructType::Anonymous(members))
+            | Type::Union(StructType::Anonymous(members)) => self.members(members),
+            _ => {}
+        }
+        walk_type(self, ctype);
+    }
+}
+
+// There's no arena to allocate in while deserializing, so subexpressions are leaked (see the module docs).
+impl<'de> Deserialize<'de> for &'static mut Expr<'static> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Expr::deserialize(deserializer).map(|expr| Box::leak(Box::new(expr)))
+    }
+}
+
+impl<'de> Deserialize<'de> for &'static mut Stmt<'static> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Stmt::deserialize(deserializer).map(|stmt| Box::leak(Box::new(stmt)))
+    }
+}
+
+const NO_CONTEXT: &str = "symbols and structs can only be serialized as part of a TranslationUnit";
+
+/// Return the placeholder for `id`, creating it if this is the first time `id` was seen.
+fn symbol_for_id(id: usize) -> Result<Symbol, &'static str> {
+    CONTEXT.with(|context| match &mut *context.borrow_mut() {
+        Some(Context::Deserialize { symbols, .. }) => Ok(*symbols.entry(id).or_insert_with(|| {
+            Variable {
+                ctype: Type::Error,
+                storage_class: StorageClass::default(),
+                qualifiers: Qualifiers::default(),
+                id: InternedStr::default(),
+                location: placeholder_location(),
+            }
+            .insert()
+        })),
+        _ => Err(NO_CONTEXT),
+    })
+}
+
+/// A location for placeholders, which is never seen once deserialization finishes.
+fn placeholder_location() -> Location {
+    let mut files = crate::SourceMap::default();
+    let file = files.add("<placeholder>", String::new().into());
+    Location {
+        span: (0..0).into(),
+        file,
+        expansion: None,
+    }
+}
+
+fn struct_for_id(id: usize) -> Result<StructRef, &'static str> {
+    CONTEXT.with(|context| match &mut *context.borrow_mut() {
+        Some(Context::Deserialize { structs, .. }) => {
+            Ok(*structs.entry(id).or_insert_with(StructRef::new))
+        }
+        _ => Err(NO_CONTEXT),
+    })
+}
+
+impl Serialize for Symbol {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let id = CONTEXT.with(|context| match &*context.borrow() {
+            Some(Context::Serialize { symbols, .. }) => symbols.get(self).copied(),
+            _ => None,
+        });
+        match id {
+            Some(id) => serializer.serialize_u64(id as u64),
+            None => Err(S::Error::custom(format!(
+                "{} (while serializing '{}')",
+                NO_CONTEXT,
+                self.get().id
+            ))),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Symbol {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let id = usize::deserialize(deserializer)?;
+        symbol_for_id(id).map_err(D::Error::custom)
+    }
+}
+
+impl Serialize for StructRef {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let id = CONTEXT.with(|context| match &*context.borrow() {
+            Some(Context::Serialize { structs, .. }) => structs.get(&self.index()).copied(),
+            _ => None,
+        });
+        match id {
+            Some(id) => serializer.serialize_u64(id as u64),
+            None => Err(S::Error::custom(NO_CONTEXT)),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for StructRef {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let id = usize::deserialize(deserializer)?;
+        struct_for_id(id).map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyze::{test::arena, Analyzer};
+    use crate::parse::test::parser;
+
+    fn analyze_program(program: &str) -> Vec<Locatable<Declaration<'static>>> {
+        Analyzer::new(parser(program), arena(), false)
+            .map(|decl| decl.unwrap())
+            .collect()
+    }
+
+    fn round_trip(program: &str) -> (TranslationUnit<'static>, TranslationUnit<'static>) {
+        let original = TranslationUnit::from(analyze_program(program));
+        let json = serde_json::to_string(&original).unwrap();
+        let parsed: TranslationUnit = serde_json::from_str(&json).unwrap();
+        assert_eq!(serde_json::to_string(&parsed).unwrap(), json);
+        (original, parsed)
+    }
+
+    fn assert_same_structure(program: &str) {
+        let (original, parsed) = round_trip(program);
+        assert_eq!(original.declarations.len(), parsed.declarations.len());
+        for (original, parsed) in original.declarations.iter().zip(&parsed.declarations) {
+            assert_eq!(original.location, parsed.location);
+            // `Type`'s `PartialEq` recurses forever on two copies of a self-referential struct
+            let (original_meta, parsed_meta) =
+                (original.data.symbol.get(), parsed.data.symbol.get());
+            assert_eq!(original_meta.id, parsed_meta.id);
+            assert_eq!(original_meta.storage_class, parsed_meta.storage_class);
+            assert_eq!(original_meta.qualifiers, parsed_meta.qualifiers);
+            assert_eq!(
+                original_meta.ctype.to_string(),
+                parsed_meta.ctype.to_string()
+            );
+            assert_eq!(original.data.dump(), parsed.data.dump());
+        }
+    }
+
+    #[test]
+    fn round_trip_programs() {
+        assert_same_structure("int i = 1 + 2; double d = 1.5; char *s = \"hi\";");
+        assert_same_structure(
+            "int square(int x) { return x * x; }
+            int main(void) {
+                int total = 0;
+                for (int i = 0; i < 3; i++) total += square(i);
+                return total == 5 ? 0 : 1;
+            }",
+        );
+        assert_same_structure(
+            "struct list { int value; struct list *next; };
+            union u { struct { int x; } inner; long l; };
+            enum e { A, B = 5 };
+            int sum(struct list *l) { return l ? l->value + sum(l->next) : 0; }
+            int (*callback)(int arg);",
+        );
+    }
+
+    #[test]
+    fn recursive_structs() {
+        let (_, parsed) = round_trip("struct s { int i; struct s *next; } s;");
+        let ctype = parsed.declarations[0].data.symbol.get().ctype.clone();
+        let struct_ref = match &ctype {
+            Type::Struct(StructType::Named(_, struct_ref)) => *struct_ref,
+            _ => panic!("expected a named struct, got {:?}", ctype),
+        };
+        match &struct_ref.get()[1].ctype {
+            Type::Pointer(inner, _) => assert_eq!(**inner, ctype),
+            other => panic!("expected a pointer, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn symbols_need_a_translation_unit() {
+        let program = analyze_program("int i;");
+        let err = serde_json::to_string(&program[0]).unwrap_err();
+        assert!(err.to_string().contains("TranslationUnit"), "{}", err);
+        assert!(serde_json::from_str::<Symbol>("0").is_err());
+        let missing = r#"{"declarations":[],"symbols":[],"structs":[]}"#;
+        assert!(serde_json::from_str::<TranslationUnit>(missing).is_ok());
+        let json = serde_json::to_string(&TranslationUnit::from(program)).unwrap();
+        let missing = json.replace(r#""symbols":[{"#, r#""symbols":[],"unused":[{"#);
+        let err = serde_json::from_str::<TranslationUnit>(&missing).unwrap_err();
+        assert!(err.to_string().contains("not in the side table"), "{}", err);
+    }
+}
@@ -0,0 +1,356 @@
+//! Traversals of the HIR.
+//!
+//! Implement `Visitor` (or `VisitorMut` to change the tree) and override the methods
+//! for the nodes you care about. The default methods visit every child
+//! by calling the matching `walk_*` function, which an overridden method can also call
+//! to keep going after it has looked at a node.
+//!
+//! The walkers match every variant, so adding a node to the HIR means deciding how to walk it here.
+
+use super::*;
+
+/// Look at each node in the HIR.
+pub trait Visitor {
+    fn visit_decl(&mut self, decl: &Declaration) {
+        walk_declaration(self, decl)
+    }
+    fn visit_initializer(&mut self, init: &Initializer) {
+        walk_initializer(self, init)
+    }
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        walk_stmt(self, stmt)
+    }
+    fn visit_expr(&mut self, expr: &Expr) {
+        walk_expr(self, expr)
+    }
+    fn visit_type(&mut self, ctype: &Type) {
+        walk_type(self, ctype)
+    }
+}
+
+/// Visit the type of the declared symbol, then its initializer or function body.
+pub fn walk_declaration<V: Visitor + ?Sized>(visitor: &mut V, decl: &Declaration) {
+    visitor.visit_type(&decl.symbol.get().ctype);
+    if let Some(init) = &decl.init {
+        visitor.visit_initializer(init);
+    }
+}
+
+pub fn walk_initializer<V: Visitor + ?Sized>(visitor: &mut V, init: &Initializer) {
+    match init {
+        Initializer::Scalar(expr) => visitor.visit_expr(expr),
+        Initializer::InitializerList(inits) => {
+            for init in inits {
+                visitor.visit_initializer(init);
+            }
+        }
+        Initializer::FunctionBody(stmts) => {
+            for stmt in stmts {
+                visitor.visit_stmt(stmt);
+            }
+        }
+    }
+}
+
+/// Visit the children of `stmt` in the order they appear in the source.
+pub fn walk_stmt<V: Visitor + ?Sized>(visitor: &mut V, stmt: &Stmt) {
+    match &stmt.data {
+        StmtType::Compound(stmts) => {
+            for stmt in stmts {
+                visitor.visit_stmt(stmt);
+            }
+        }
+        StmtType::If(condition, then, otherwise) => {
+            visitor.visit_expr(condition);
+            visitor.visit_stmt(then);
+            if let Some(otherwise) = otherwise {
+                visitor.visit_stmt(otherwise);
+            }
+        }
+        StmtType::Do(body, condition) => {
+            visitor.visit_stmt(body);
+            visitor.visit_expr(condition);
+        }
+        StmtType::While(condition, body) | StmtType::Switch(condition, body) => {
+            visitor.visit_expr(condition);
+            visitor.visit_stmt(body);
+        }
+        StmtType::For(init, condition, post_loop, body) => {
+            visitor.visit_stmt(init);
+            for expr in condition.iter().chain(post_loop) {
+                visitor.visit_expr(expr);
+            }
+            visitor.visit_stmt(body);
+        }
+        StmtType::Label(_, inner) | StmtType::Case(_, inner) | StmtType::Default(inner) => {
+            visitor.visit_stmt(inner)
+        }
+        StmtType::Expr(expr) | StmtType::Return(Some(expr)) => visitor.visit_expr(expr),
+        StmtType::Decl(decls) => {
+            for decl in decls {
+                visitor.visit_decl(&decl.data);
+            }
+        }
+        StmtType::Goto(_) | StmtType::Continue | StmtType::Break | StmtType::Return(None) => {}
+    }
+}
+
+/// Visit the type of `expr`, then its subexpressions.
+pub fn walk_expr<V: Visitor + ?Sized>(visitor: &mut V, expr: &Expr) {
+    visitor.visit_type(&expr.ctype);
+    match &expr.expr {
+        ExprType::Id(_) | ExprType::Literal(_) => {}
+        ExprType::Sizeof(ctype) => visitor.visit_type(ctype),
+        ExprType::FuncCall(func, args) => {
+            visitor.visit_expr(func);
+            for arg in args {
+                visitor.visit_expr(arg);
+            }
+        }
+        ExprType::Member(inner, _)
+        | ExprType::Cast(inner)
+        | ExprType::Deref(inner)
+        | ExprType::Negate(inner)
+        | ExprType::BitwiseNot(inner)
+        | ExprType::StaticRef(inner)
+        | ExprType::Noop(inner) => visitor.visit_expr(inner),
+        ExprType::Binary(_, left, right)
+        | ExprType::Comma(left, right)
+        | ExprType::Let(_, left, right) => {
+            visitor.visit_expr(left);
+            visitor.visit_expr(right);
+        }
+        ExprType::Ternary(condition, then, otherwise) => {
+            visitor.visit_expr(condition);
+            visitor.visit_expr(then);
+            visitor.visit_expr(otherwise);
+        }
+    }
+}
+
+/// Visit the types that make up `ctype`.
+///
+/// This doesn't visit the members of structs and unions, since a struct can point to itself.
+pub fn walk_type<V: Visitor + ?Sized>(visitor: &mut V, ctype: &Type) {
+    match ctype {
+        Type::Pointer(inner, _) | Type::Array(inner, _) => visitor.visit_type(inner),
+        Type::Function(ftype) => {
+            visitor.visit_type(&ftype.return_type);
+            for param in &ftype.params {
+                visitor.visit_type(&param.get().ctype);
+            }
+        }
+        Type::Void
+        | Type::Bool
+        | Type::Char(_)
+        | Type::Short(_)
+        | Type::Int(_)
+        | Type::Long(_)
+        | Type::Float
+        | Type::Double
+        | Type::Union(_)
+        | Type::Struct(_)
+        | Type::Enum(_, _)
+        | Type::VaList
+        | Type::Error => {}
+    }
+}
+
+/// Change nodes in the HIR in place.
+///
+/// The metadata for symbols is shared and can't be changed,
+/// so this doesn't visit the types of declarations or function parameters.
+pub trait VisitorMut {
+    fn visit_decl_mut(&mut self, decl: &mut Declaration) {
+        walk_declaration_mut(self, decl)
+    }
+    fn visit_initializer_mut(&mut self, init: &mut Initializer) {
+        walk_initializer_mut(self, init)
+    }
+    fn visit_stmt_mut(&mut self, stmt: &mut Stmt) {
+        walk_stmt_mut(self, stmt)
+    }
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        walk_expr_mut(self, expr)
+    }
+    fn visit_type_mut(&mut self, ctype: &mut Type) {
+        walk_type_mut(self, ctype)
+    }
+}
+
+/// Visit the initializer or function body of `decl`.
+pub fn walk_declaration_mut<V: VisitorMut + ?Sized>(visitor: &mut V, decl: &mut Declaration) {
+    if let Some(init) = &mut decl.init {
+        visitor.visit_initializer_mut(init);
+    }
+}
+
+pub fn walk_initializer_mut<V: VisitorMut + ?Sized>(visitor: &mut V, init: &mut Initializer) {
+    match init {
+        Initializer::Scalar(expr) => visitor.visit_expr_mut(expr),
+        Initializer::InitializerList(inits) => {
+            for init in inits {
+                visitor.visit_initializer_mut(init);
+            }
+        }
+        Initializer::FunctionBody(stmts) => {
+            for stmt in stmts {
+                visitor.visit_stmt_mut(stmt);
+            }
+        }
+    }
+}
+
+/// Visit the children of `stmt` in the order they appear in the source.
+pub fn walk_stmt_mut<V: VisitorMut + ?Sized>(visitor: &mut V, stmt: &mut Stmt) {
+    match &mut stmt.data {
+        StmtType::Compound(stmts) => {
+            for stmt in stmts {
+                visitor.visit_stmt_mut(stmt);
+            }
+        }
+        StmtType::If(condition, then, otherwise) => {
+            visitor.visit_expr_mut(condition);
+            visitor.visit_stmt_mut(then);
+            if let Some(otherwise) = otherwise {
+                visitor.visit_stmt_mut(otherwise);
+            }
+        }
+        StmtType::Do(body, condition) => {
+            visitor.visit_stmt_mut(body);
+            visitor.visit_expr_mut(condition);
+        }
+        StmtType::While(condition, body) | StmtType::Switch(condition, body) => {
+            visitor.visit_expr_mut(condition);
+            visitor.visit_stmt_mut(body);
+        }
+        StmtType::For(init, condition, post_loop, body) => {
+            visitor.visit_stmt_mut(init);
+            for expr in condition.iter_mut().chain(post_loop) {
+                visitor.visit_expr_mut(expr);
+            }
+            visitor.visit_stmt_mut(body);
+        }
+        StmtType::Label(_, inner) | StmtType::Case(_, inner) | StmtType::Default(inner) => {
+            visitor.visit_stmt_mut(inner)
+        }
+        StmtType::Expr(expr) | StmtType::Return(Some(expr)) => visitor.visit_expr_mut(expr),
+        StmtType::Decl(decls) => {
+            for decl in decls {
+                visitor.visit_decl_mut(&mut decl.data);
+            }
+        }
+        StmtType::Goto(_) | StmtType::Continue | StmtType::Break | StmtType::Return(None) => {}
+    }
+}
+
+/// Visit the type of `expr`, then its subexpressions.
+pub fn walk_expr_mut<V: VisitorMut + ?Sized>(visitor: &mut V, expr: &mut Expr) {
+    visitor.visit_type_mut(&mut expr.ctype);
+    match &mut expr.expr {
+        ExprType::Id(_) | ExprType::Literal(_) => {}
+        ExprType::Sizeof(ctype) => visitor.visit_type_mut(ctype),
+        ExprType::FuncCall(func, args) => {
+            visitor.visit_expr_mut(func);
+            for arg in args {
+                visitor.visit_expr_mut(arg);
+            }
+        }
+        ExprType::Member(inner, _)
+        | ExprType::Cast(inner)
+        | ExprType::Deref(inner)
+        | ExprType::Negate(inner)
+        | ExprType::BitwiseNot(inner)
+        | ExprType::StaticRef(inner)
+        | ExprType::Noop(inner) => visitor.visit_expr_mut(inner),
+        ExprType::Binary(_, left, right)
+        | ExprType::Comma(left, right)
+        | ExprType::Let(_, left, right) => {
+            visitor.visit_expr_mut(left);
+            visitor.visit_expr_mut(right);
+        }
+        ExprType::Ternary(condition, then, otherwise) => {
+            visitor.visit_expr_mut(condition);
+            visitor.visit_expr_mut(then);
+            visitor.visit_expr_mut(otherwise);
+        }
+    }
+}
+
+/// Visit the types that make up `ctype`, except for function parameters.
+pub fn walk_type_mut<V: VisitorMut + ?Sized>(visitor: &mut V, ctype: &mut Type) {
+    match ctype {
+        Type::Pointer(inner, _) | Type::Array(inner, _) => visitor.visit_type_mut(inner),
+        Type::Function(ftype) => visitor.visit_type_mut(&mut ftype.return_type),
+        Type::Void
+        | Type::Bool
+        | Type::Char(_)
+        | Type::Short(_)
+        | Type::Int(_)
+        | Type::Long(_)
+        | Type::Float
+        | Type::Double
+        | Type::Union(_)
+        | Type::Struct(_)
+        | Type::Enum(_, _)
+        | Type::VaList
+        | Type::Error => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyze::test::{analyze_expr, arena};
+    use crate::{analyze, Opt};
+
+    #[test]
+    fn count_calls() {
+        #[derive(Default)]
+        struct Calls(usize);
+        impl Visitor for Calls {
+            fn visit_expr(&mut self, expr: &Expr) {
+                if let ExprType::FuncCall(..) = expr.expr {
+                    self.0 += 1;
+                }
+                walk_expr(self, expr);
+            }
+        }
+
+        let program = "int f(int x);
+            int g(void) {
+                int y = f(1) + f(f(2));
+                for (int i = 0; i < f(3); i++) {
+                    if (i) return f(i);
+                }
+                return y;
+            }";
+        let analysis = analyze(program, Opt::default(), arena());
+        assert!(analysis.errors.is_empty(), "{:?}", analysis.errors);
+        let mut calls = Calls::default();
+        for decl in &analysis.hir {
+            calls.visit_decl(&decl.data);
+        }
+        assert_eq!(calls.0, 5);
+    }
+
+    #[test]
+    fn rewrite_literals() {
+        struct Double;
+        impl VisitorMut for Double {
+            fn visit_expr_mut(&mut self, expr: &mut Expr) {
+                if let ExprType::Literal(Literal::Int(i)) = &mut expr.expr {
+                    *i *= 2;
+                }
+                walk_expr_mut(self, expr);
+            }
+        }
+
+        let mut expr = analyze_expr("1 + 2 * (3 ? 4 : 5)").unwrap();
+        Double.visit_expr_mut(&mut expr);
+        assert_eq!(
+            expr.const_fold().unwrap().expr,
+            ExprType::Literal(Literal::Int(2 + 4 * 8))
+        );
+    }
+}
@@ -1,5 +1,8 @@
 use std::borrow::Borrow;
 use std::cmp::Ordering;
+use std::sync::RwLock;
+
+use lazy_static::lazy_static;
 
 #[cfg(test)]
 use proptest_derive::Arbitrary;
@@ -10,15 +13,62 @@ use crate::intern::InternedStr;
 // holds where a piece of code came from
 // should almost always be immutable
 #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
 pub struct Span {
     pub start: u32,
     pub end: u32,
 }
 
 #[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
 pub struct Location {
     pub span: Span,
     pub file: codespan::FileId,
+    /// The macro expansion this location came from, if any.
+    ///
+    /// Tokens from the body of a macro have the location of the macro invocation,
+    /// so this is the only way to tell them apart from tokens the user wrote.
+    pub expansion: Option<ExpansionId>,
+}
+
+/// An opaque identifier for a macro [`Expansion`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct ExpansionId(u32);
+
+/// A single expansion of a macro.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Expansion {
+    /// The name of the macro that was expanded.
+    pub name: InternedStr,
+    /// Where the macro was expanded.
+    ///
+    /// If the invocation itself came from another macro, this has its own `expansion`.
+    pub site: Location,
+}
+
+lazy_static! {
+    static ref EXPANSIONS: RwLock<Vec<Expansion>> = RwLock::new(Vec::new());
+}
+
+impl ExpansionId {
+    /// Record a new expansion and return an identifier for it.
+    ///
+    /// # Panics
+    /// This function will panic if another thread panicked while accessing the expansion table.
+    pub fn new(expansion: Expansion) -> Self {
+        let mut expansions = EXPANSIONS
+            .write()
+            .expect("failed to lock expansion table for writing");
+        expansions.push(expansion);
+        ExpansionId((expansions.len() - 1) as u32)
+    }
+    /// Look up the expansion for this identifier.
+    pub fn get(self) -> Expansion {
+        EXPANSIONS
+            .read()
+            .expect("failed to lock expansion table for reading")[self.0 as usize]
+    }
 }
 
 use std::ops::Range;
@@ -32,6 +82,7 @@ impl From<Range<u32>> for Span {
 }
 
 #[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
 pub struct Locatable<T> {
     pub data: T,
     pub location: Location,
@@ -47,6 +98,7 @@ impl<T> Locatable<T> {
 }
 
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(test, derive(Arbitrary))]
 pub enum Keyword {
     // statements
@@ -111,9 +163,19 @@ pub enum Keyword {
     StaticAssert,
     Alignas,
     Alignof,
+
+    // C2x constants
+    True,
+    False,
+    Nullptr,
+
+    // GNU extensions
+    Attribute,
+    Extension,
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(test, derive(Arbitrary))]
 pub enum AssignmentToken {
     Equal,
@@ -130,6 +192,7 @@ pub enum AssignmentToken {
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(test, derive(Arbitrary))]
 pub enum ComparisonToken {
     Less,
@@ -141,16 +204,98 @@ pub enum ComparisonToken {
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(test, derive(Arbitrary))]
 pub enum Literal {
     // literals
     Int(i64),
     UnsignedInt(u64),
     Float(f64),
+    /// A floating constant with an `f` suffix, like `1.5f`, which has type `float`.
+    SingleFloat(f32),
     Str(Vec<u8>),
+    /// `L"abc"`, `u"abc"`, or `U"abc"`: the code units of the string, including the null terminator.
+    ///
+    /// `u8"abc"` is a `Str`, since its elements are `char`s.
+    WideStr(Encoding, Vec<u32>),
     Char(u8),
 }
 
+/// The prefix of a wide string literal, which decides the type of its elements (6.4.5).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(test, derive(Arbitrary))]
+pub enum Encoding {
+    /// `L"abc"`, an array of `wchar_t`
+    Wide,
+    /// `u"abc"`, an array of `char16_t` holding UTF-16
+    Utf16,
+    /// `U"abc"`, an array of `char32_t` holding UTF-32
+    Utf32,
+}
+
+impl Literal {
+    /// Whether this is a string literal, with or without a prefix.
+    pub fn is_string(&self) -> bool {
+        matches!(self, Literal::Str(_) | Literal::WideStr(..))
+    }
+    /// Join two adjacent string literals (6.4.5p5).
+    ///
+    /// If either string has a prefix, so does the result.
+    /// Returns the prefixes as an error if they're different, like `L"a" u"b"`.
+    ///
+    /// # Panics
+    /// If either literal is not a string.
+    pub fn concat(self, other: Literal) -> Result<Literal, (Encoding, Encoding)> {
+        use Literal::{Str, WideStr};
+        // everything but the null terminator
+        fn strip(mut units: Vec<u32>) -> Vec<u32> {
+            units.pop();
+            units
+        }
+        Ok(match (self, other) {
+            (Str(mut left), Str(right)) => {
+                left.pop();
+                left.extend(right);
+                Str(left)
+            }
+            (Str(left), WideStr(encoding, right)) => {
+                let mut units = encoding.encode(&left[..left.len() - 1]);
+                units.extend(right);
+                WideStr(encoding, units)
+            }
+            (WideStr(encoding, left), Str(right)) => {
+                let mut units = strip(left);
+                units.extend(encoding.encode(&right));
+                WideStr(encoding, units)
+            }
+            (WideStr(encoding, left), WideStr(other, right)) if encoding == other => {
+                let mut units = strip(left);
+                units.extend(right);
+                WideStr(encoding, units)
+            }
+            (WideStr(encoding, _), WideStr(other, _)) => return Err((encoding, other)),
+            (left, right) => {
+                unreachable!("concatenating {} and {}, which aren't strings", left, right)
+            }
+        })
+    }
+}
+
+impl Encoding {
+    /// The code units for `s` in this encoding.
+    ///
+    /// `s` is usually UTF-8, but escapes like `"\xff"` can make it invalid,
+    /// in which case each byte is its own code unit.
+    pub fn encode(self, s: &[u8]) -> Vec<u32> {
+        match std::str::from_utf8(s) {
+            Ok(s) if self == Encoding::Utf16 => s.encode_utf16().map(u32::from).collect(),
+            Ok(s) => s.chars().map(u32::from).collect(),
+            Err(_) => s.iter().copied().map(u32::from).collect(),
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(test, derive(Arbitrary))]
 pub enum Token {
@@ -198,6 +343,33 @@ pub enum Token {
     Hash,        // #, used for preprocessing
 }
 
+/// Source text that doesn't affect the meaning of a program,
+/// returned by the lexer's lossless mode.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Trivia {
+    /// Spaces, tabs, and newlines
+    Whitespace(String),
+    Comment(Comment),
+    /// A backslash immediately followed by a newline, which joins two lines together
+    LineSplice,
+}
+
+/// A comment, along with whether it was a `//` or `/* */` comment.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Comment {
+    /// The text of the comment, without the `//` or `/* */`
+    pub text: String,
+    pub kind: CommentKind,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CommentKind {
+    /// `// comment`
+    Line,
+    /// `/* comment */`
+    Block,
+}
+
 /* impls */
 impl PartialOrd for Location {
     /// NOTE: this only compares the start of the spans, it ignores the end
@@ -207,23 +379,73 @@ impl PartialOrd for Location {
 }
 
 impl Location {
+    /// The smallest location covering both `self` and `other`.
+    ///
+    /// Spans from different files can't be combined, which can happen when a macro is expanded
+    /// in one file but its arguments come from another.
+    /// These are merged at the sites of their outermost macro expansions instead,
+    /// and if those are still in different files, this is just `self`.
     pub fn merge<O: Borrow<Self>>(&self, other: O) -> Self {
         use std::cmp::{max, min};
 
         let other = other.borrow();
+        if self.file != other.file {
+            let (start, end) = (self.expansion_site(), other.expansion_site());
+            return if start.file == end.file {
+                start.merge(end)
+            } else {
+                *self
+            };
+        }
         Location {
             span: Span {
                 start: min(self.span.start, other.span.start),
                 end: max(self.span.end, other.span.end),
             },
-            // TODO: what should happen if these come from different files?
             file: self.file,
+            expansion: self.expansion.or(other.expansion),
         }
     }
+    /// Where the outermost macro this location came from was expanded, or `self` if it isn't from a macro.
+    pub fn expansion_site(&self) -> Location {
+        self.expansions()
+            .last()
+            .map_or(*self, |expansion| expansion.site)
+    }
     /// WARNING: the location for `original` will be on the _left_, not on the right
     pub fn maybe_merge<O: Borrow<Self>>(&self, original: Option<O>) -> Self {
         original.map_or(*self, |l| l.borrow().merge(self))
     }
+    /// Whether `other` is entirely inside this location.
+    pub fn contains(&self, other: Location) -> bool {
+        self.file == other.file
+            && self.span.start <= other.span.start
+            && other.span.end <= self.span.end
+    }
+
+    /// The location of tokens produced by expanding the macro `name` at this location.
+    pub fn expanded_from(self, name: InternedStr) -> Location {
+        let site = self;
+        Location {
+            expansion: Some(ExpansionId::new(Expansion { name, site })),
+            ..self
+        }
+    }
+    /// Whether this location came from the body of a macro, rather than code the user wrote.
+    pub fn is_from_macro(&self) -> bool {
+        self.expansion.is_some()
+    }
+    /// All the macros this location was expanded from, innermost first.
+    pub fn expansions(&self) -> Vec<Expansion> {
+        let mut expansions = Vec::new();
+        let mut current = self.expansion;
+        while let Some(id) = current {
+            let expansion = id.get();
+            current = expansion.site.expansion;
+            expansions.push(expansion);
+        }
+        expansions
+    }
 
     pub fn with<T>(self, data: T) -> Locatable<T> {
         Locatable {
@@ -289,11 +511,12 @@ impl AssignmentToken {
 #[cfg(test)]
 impl Default for Location {
     fn default() -> Self {
-        let mut files = crate::Files::default();
+        let mut files = crate::SourceMap::default();
         let id = files.add("<test suite>", String::new().into());
         Self {
             span: (0..1).into(),
             file: id,
+            expansion: None,
         }
     }
 }
@@ -312,11 +535,33 @@ impl std::fmt::Display for Keyword {
             Keyword::ThreadLocal => write!(f, "_Thread_local"),
             Keyword::StaticAssert => write!(f, "_Static_assert"),
             Keyword::VaList => write!(f, "va_list"),
+            Keyword::Attribute => write!(f, "__attribute__"),
+            Keyword::Extension => write!(f, "__extension__"),
             _ => write!(f, "{}", &format!("{:?}", self).to_lowercase()),
         }
     }
 }
 
+/// This is the exact text of the trivia in the source.
+impl std::fmt::Display for Trivia {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Trivia::Whitespace(whitespace) => write!(f, "{}", whitespace),
+            Trivia::Comment(comment) => write!(f, "{}", comment),
+            Trivia::LineSplice => writeln!(f, "\\"),
+        }
+    }
+}
+
+impl std::fmt::Display for Comment {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.kind {
+            CommentKind::Line => write!(f, "//{}", self.text),
+            CommentKind::Block => write!(f, "/*{}*/", self.text),
+        }
+    }
+}
+
 impl std::fmt::Display for Token {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         use Token::*;
@@ -371,6 +616,7 @@ impl std::fmt::Display for Literal {
             Int(i) => write!(f, "{}", i),
             UnsignedInt(u) => write!(f, "{}", u),
             Float(n) => write!(f, "{}", n),
+            SingleFloat(n) => write!(f, "{}f", n),
             Str(s) => {
                 let mut escaped = s
                     .iter()
@@ -389,11 +635,34 @@ impl std::fmt::Display for Literal {
 
                 write!(f, "\"{}\"", String::from_utf8_lossy(&escaped))
             }
+            WideStr(encoding, units) => {
+                // like `Str`, leave off the null terminator
+                let units = &units[..units.len().saturating_sub(1)];
+                write!(f, "{}\"", encoding)?;
+                for &unit in units {
+                    match std::char::from_u32(unit) {
+                        Some(c @ '"') | Some(c @ '\\') => write!(f, "\\{}", c)?,
+                        Some(c) if !c.is_control() => write!(f, "{}", c)?,
+                        _ => write!(f, "\\x{:x}", unit)?,
+                    }
+                }
+                write!(f, "\"")
+            }
             Char(c) => write!(f, "'{}'", char::from(*c).escape_default()),
         }
     }
 }
 
+impl std::fmt::Display for Encoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(match self {
+            Encoding::Wide => "L",
+            Encoding::Utf16 => "u",
+            Encoding::Utf32 => "U",
+        })
+    }
+}
+
 impl std::fmt::Display for ComparisonToken {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         use ComparisonToken::*;
@@ -463,6 +732,36 @@ pub(crate) mod test {
         }
     }
 
+    #[test]
+    fn merge_locations() {
+        let mut files = codespan::Files::new();
+        let (header, main) = (files.add("header.h", "x"), files.add("main.c", "y"));
+        let location = |file, span: std::ops::Range<u32>| Location {
+            span: span.into(),
+            file,
+            expansion: None,
+        };
+        let (left, right) = (location(main, 8..9), location(main, 2..4));
+        assert_eq!(left.merge(right), location(main, 2..9));
+        assert_eq!(right.merge(left), location(main, 2..9));
+
+        // `M(y)` at 20..24 in main.c, where `M` is defined in header.h
+        let site = location(main, 20..24);
+        let from_header = Location {
+            expansion: Some(site.expanded_from("M".into()).expansion.unwrap()),
+            ..location(header, 0..1)
+        };
+        assert_eq!(from_header.expansion_site(), site);
+        assert_eq!(from_header.merge(location(main, 22..23)), site);
+        assert_eq!(
+            location(main, 30..31).merge(from_header),
+            location(main, 20..31)
+        );
+        // nothing sensible can be done for two different files without a macro
+        let unrelated = location(header, 0..1);
+        assert_eq!(unrelated.merge(location(main, 2..4)), unrelated);
+    }
+
     #[test]
     fn str_display_escape() {
         let token = r#""Hello, world\n\r\t""#;
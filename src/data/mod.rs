@@ -1,13 +1,19 @@
 pub mod ast;
+pub mod diagnostic;
 pub mod error;
 pub mod hir;
 pub mod lex;
+pub mod source;
 pub mod types;
 
 pub use crate::intern::InternedStr;
+pub use diagnostic::{CollectingSink, Diagnostic, DiagnosticKind, DiagnosticSink, WriteSink};
 pub(crate) use error::ErrorHandler;
-pub use error::{CompileError, CompileResult, CompileWarning, Error, SemanticError, SyntaxError};
-pub use lex::{Literal, Locatable, Location, Token};
+pub use error::{
+    CompileError, CompileResult, CompileWarning, Error, SemanticError, SyntaxError, WarningOptions,
+};
+pub use lex::{Comment, CommentKind, Encoding, Literal, Locatable, Location, Token, Trivia};
+pub use source::{Source, SourceMap};
 pub use types::Type;
 pub use types::{StructRef, StructType};
 
@@ -18,6 +24,7 @@ use lex::Keyword;
 
 // used by both `ast` and `hir`
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
 pub enum StorageClass {
     Static,
     Extern,
@@ -85,6 +92,108 @@ impl TryFrom<u32> for Radix {
     }
 }
 
+/// A version of the C standard.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Version {
+    /// ANSI C, also known as C90
+    C89,
+    C99,
+    C11,
+    /// C17 has no new features, only fixes to C11
+    C17,
+    /// The upcoming standard, C23
+    C2x,
+}
+
+/// The dialect of C to accept, the same as `-std=` for `cc`.
+///
+/// The default is `gnu11`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Standard {
+    pub version: Version,
+    /// Whether GNU extensions which conflict with the standard are allowed,
+    /// such as `//` comments and mixed declarations in C89.
+    pub gnu: bool,
+}
+
+impl Default for Standard {
+    fn default() -> Self {
+        Standard {
+            version: Version::C11,
+            gnu: true,
+        }
+    }
+}
+
+impl Standard {
+    /// The value of `__STDC_VERSION__`, which isn't defined for C89.
+    pub fn stdc_version(self) -> Option<i64> {
+        #[allow(clippy::inconsistent_digit_grouping)]
+        match self.version {
+            Version::C89 => None,
+            Version::C99 => Some(1999_01),
+            Version::C11 => Some(2011_12),
+            Version::C17 => Some(2017_10),
+            // what GCC uses until the standard is published
+            Version::C2x => Some(2020_00),
+        }
+    }
+    /// Whether `//` starts a comment.
+    pub fn line_comments(self) -> bool {
+        self.version >= Version::C99 || self.gnu
+    }
+    /// Whether calling an undeclared function implicitly declares it as `extern int f();`.
+    ///
+    /// This was removed in C99.
+    pub fn implicit_function_declarations(self) -> bool {
+        self.version == Version::C89
+    }
+    /// Whether attributes can be written as `[[attribute]]`.
+    ///
+    /// GNU modes accept these before C2x, like GCC does.
+    pub fn standard_attributes(self) -> bool {
+        self.version >= Version::C2x || self.gnu
+    }
+}
+
+impl std::str::FromStr for Standard {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Standard, String> {
+        let (gnu, version) = if let Some(version) = s.strip_prefix("gnu") {
+            (true, version)
+        } else if let Some(version) = s.strip_prefix('c') {
+            (false, version)
+        } else if s == "iso9899:1990" {
+            (false, "89")
+        } else {
+            return Err(format!("unknown standard '{}'", s));
+        };
+        let version = match version {
+            "89" | "90" => Version::C89,
+            "99" | "9x" => Version::C99,
+            "11" | "1x" => Version::C11,
+            "17" | "18" => Version::C17,
+            "2x" | "23" => Version::C2x,
+            _ => return Err(format!("unknown standard '{}'", s)),
+        };
+        Ok(Standard { version, gnu })
+    }
+}
+
+impl Display for Standard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let version = match self.version {
+            Version::C89 => "89",
+            Version::C99 => "99",
+            Version::C11 => "11",
+            Version::C17 => "17",
+            Version::C2x => "2x",
+        };
+        let prefix = if self.gnu { "gnu" } else { "c" };
+        write!(f, "{}{}", prefix, version)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::analyze::test::analyze;
@@ -109,4 +218,27 @@ mod tests {
             assert_eq!(&parsed_ty.to_string(), *ty);
         }
     }
+    #[test]
+    fn parse_standard() {
+        use super::{Standard, Version};
+
+        for (flag, version, gnu) in &[
+            ("c89", Version::C89, false),
+            ("c90", Version::C89, false),
+            ("iso9899:1990", Version::C89, false),
+            ("gnu89", Version::C89, true),
+            ("c99", Version::C99, false),
+            ("gnu11", Version::C11, true),
+            ("c18", Version::C17, false),
+            ("c2x", Version::C2x, false),
+            ("gnu23", Version::C2x, true),
+        ] {
+            let std: Standard = flag.parse().unwrap();
+            assert_eq!((std.version, std.gnu), (*version, *gnu), "{}", flag);
+        }
+        for flag in &["c", "gnu", "c98", "c++11", "ansi"] {
+            assert!(flag.parse::<Standard>().is_err(), "{}", flag);
+        }
+        assert_eq!(Standard::default().to_string(), "gnu11");
+    }
 }
@@ -0,0 +1,289 @@
+//! Every file loaded while compiling a program, and how to find things in them.
+//!
+//! Each `Location` has a `FileId` that points into a `SourceMap`.
+//! The map owns the main file, every `#include`d header,
+//! and the `<builtin>` and `<command line>` pseudo-files that hold predefined macros.
+
+use std::borrow::Cow;
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+pub use codespan::FileId;
+
+use super::lex::{Location, Span};
+use crate::intern::InternedStr;
+
+/// The contents of a file in a `SourceMap`.
+///
+/// Used to store extra metadata about the file, like the absolute filename.
+///
+/// NOTE: If `path` is empty (e.g. by using `my_string.into()`),
+/// then the path will be relative to the _compiler_, not to the current file.
+/// This is recommended only for test code and proof of concepts,
+/// since it does not adhere to the C standard.
+#[derive(Debug, Clone)]
+pub struct Source {
+    pub code: Rc<str>,
+    pub path: PathBuf,
+}
+
+impl AsRef<str> for Source {
+    fn as_ref(&self) -> &str {
+        self.code.as_ref()
+    }
+}
+
+/// A line and column in a file. Both start at 1.
+///
+/// The column counts characters, not bytes.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct LineColumn {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Where a location claims to be, after taking `#line` directives into account.
+///
+/// This is what diagnostics should show to the user.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PresumedLocation<'a> {
+    pub name: Cow<'a, str>,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl PresumedLocation<'_> {
+    pub fn into_owned(self) -> PresumedLocation<'static> {
+        PresumedLocation {
+            name: Cow::Owned(self.name.into_owned()),
+            line: self.line,
+            column: self.column,
+        }
+    }
+}
+
+/// A single macro expansion.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Expansion {
+    /// The name of the macro that was expanded
+    pub name: InternedStr,
+    /// The name of the macro in its `#define`
+    pub definition: Location,
+    /// The name of the macro where it was used.
+    ///
+    /// All tokens produced by the expansion have this location.
+    pub site: Location,
+}
+
+/// `#line <line> "<name>"`, which changes the presumed location of every following line.
+#[derive(Clone, Debug)]
+struct LineDirective {
+    file: FileId,
+    /// The actual line following the directive
+    actual_line: usize,
+    /// The presumed line of `actual_line`
+    presumed_line: usize,
+    /// The presumed file name, if the directive or an earlier one in the same file gave one
+    name: Option<Rc<str>>,
+}
+
+/// All files loaded by the preprocessor.
+///
+/// Files are never removed, so a `FileId` stays valid as long as the map does.
+/// Adding the same contents twice still gives two different ids.
+#[derive(Debug, Default)]
+pub struct SourceMap {
+    files: codespan::Files<Source>,
+    line_directives: Vec<LineDirective>,
+    expansions: Vec<Expansion>,
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a new file to the map. `name` is what will be shown in diagnostics.
+    pub fn add(&mut self, name: impl Into<OsString>, source: Source) -> FileId {
+        self.files.add(name, source)
+    }
+
+    /// The name of the file, as given to `add`.
+    ///
+    /// For `#include`d files, this is the name in the `#include` directive.
+    pub fn name(&self, file: FileId) -> &OsStr {
+        self.files.name(file)
+    }
+    /// The contents of the file, along with its path.
+    pub fn source(&self, file: FileId) -> &Source {
+        self.files.source(file)
+    }
+    pub fn path(&self, file: FileId) -> &Path {
+        &self.source(file).path
+    }
+
+    /// The line and column of the byte at `offset`.
+    ///
+    /// Offsets past the end of the file are treated as pointing to the end of the file.
+    pub fn line_column(&self, file: FileId, offset: u32) -> LineColumn {
+        let offset = offset.min(self.source(file).code.len() as u32);
+        let location = self
+            .files
+            .location(file, offset)
+            .expect("offset should be in bounds after clamping");
+        LineColumn {
+            line: location.line.to_usize() + 1,
+            column: location.column.to_usize() + 1,
+        }
+    }
+
+    /// The byte offsets of a line, including the trailing newline if there is one.
+    ///
+    /// `line` starts at 1. Lines past the end of the file are empty.
+    pub fn line_span(&self, file: FileId, line: usize) -> Span {
+        let len = self.source(file).code.len() as u32;
+        let index = codespan::LineIndex::from(line.saturating_sub(1) as u32);
+        match self.files.line_span(file, index) {
+            Ok(span) => (span.start().to_usize() as u32..span.end().to_usize() as u32).into(),
+            Err(_) => (len..len).into(),
+        }
+    }
+
+    /// The text of a line, without the trailing newline.
+    ///
+    /// `line` starts at 1. Lines past the end of the file are empty.
+    pub fn line_text(&self, file: FileId, line: usize) -> &str {
+        let span = self.line_span(file, line);
+        self.source(file).code[span.start as usize..span.end as usize]
+            .trim_end_matches(&['\r', '\n'][..])
+    }
+
+    /// Where the start of `location` claims to be, after applying `#line` directives.
+    ///
+    /// Without any `#line` directives, this is the file name and `line_column`.
+    pub fn presumed_location(&self, location: Location) -> PresumedLocation<'_> {
+        let actual = self.line_column(location.file, location.span.start);
+        let directive = self
+            .line_directives
+            .iter()
+            .rev()
+            .find(|d| d.file == location.file && d.actual_line <= actual.line);
+        let (name, line) = match directive {
+            Some(d) => (
+                d.name.as_deref().map(Cow::Borrowed),
+                d.presumed_line + (actual.line - d.actual_line),
+            ),
+            None => (None, actual.line),
+        };
+        PresumedLocation {
+            name: name.unwrap_or_else(|| self.name(location.file).to_string_lossy()),
+            line,
+            column: actual.column,
+        }
+    }
+
+    /// Record a `#line` directive on `directive_line` of `file`.
+    ///
+    /// The line after the directive will be presumed to be `line`.
+    /// If `name` is `None`, the presumed file name stays the same.
+    pub(crate) fn add_line_directive(
+        &mut self,
+        file: FileId,
+        directive_line: usize,
+        line: usize,
+        name: Option<String>,
+    ) {
+        let name = name.map(Rc::from).or_else(|| {
+            self.line_directives
+                .iter()
+                .rev()
+                .find(|d| d.file == file)
+                .and_then(|d| d.name.clone())
+        });
+        self.line_directives.push(LineDirective {
+            file,
+            actual_line: directive_line + 1,
+            presumed_line: line,
+            name,
+        });
+    }
+
+    pub(crate) fn add_expansion(&mut self, expansion: Expansion) {
+        self.expansions.push(expansion);
+    }
+    /// Every macro expansion seen by the preprocessor, in order.
+    ///
+    /// Only the outermost expansion of each token is recorded;
+    /// macros used inside the replacement list of another macro are not.
+    pub fn expansions(&self) -> &[Expansion] {
+        &self.expansions
+    }
+    /// The macro expansion that produced the token at `location`, if any.
+    pub fn expansion(&self, location: Location) -> Option<&Expansion> {
+        self.expansions
+            .iter()
+            .rev()
+            .find(|expansion| expansion.site.contains(location))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(code: &str) -> Source {
+        Source {
+            code: code.into(),
+            path: PathBuf::new(),
+        }
+    }
+
+    #[test]
+    fn identical_files() {
+        let mut map = SourceMap::new();
+        let first = map.add("a.h", source("int i;\n"));
+        let second = map.add("a.h", source("int i;\n"));
+        assert_ne!(first, second);
+        assert_eq!(map.name(first), map.name(second));
+    }
+
+    #[test]
+    fn lines() {
+        let mut map = SourceMap::new();
+        let file = map.add("a.c", source("int i;\n\tint j;\r\n"));
+        let at = |offset| map.line_column(file, offset);
+        assert_eq!(at(0), LineColumn { line: 1, column: 1 });
+        assert_eq!(at(9), LineColumn { line: 2, column: 3 });
+        assert_eq!(at(1000), LineColumn { line: 3, column: 1 });
+        assert_eq!(map.line_text(file, 2), "\tint j;");
+        assert_eq!(map.line_span(file, 2), (7..16).into());
+        assert_eq!(map.line_text(file, 10), "");
+    }
+
+    #[test]
+    fn line_directives() {
+        let mut map = SourceMap::new();
+        let file = map.add(
+            "a.c",
+            source("a\n#line 10\nb\nc\n#line 1 \"b.c\"\nd\n#line 5\ne\n"),
+        );
+        map.add_line_directive(file, 2, 10, None);
+        map.add_line_directive(file, 5, 1, Some("b.c".into()));
+        map.add_line_directive(file, 7, 5, None);
+        let presumed = |offset| {
+            let location = Location {
+                file,
+                span: (offset..offset).into(),
+                expansion: None,
+            };
+            let presumed = map.presumed_location(location);
+            (presumed.name.into_owned(), presumed.line)
+        };
+        assert_eq!(presumed(0), ("a.c".into(), 1));
+        assert_eq!(presumed(11), ("a.c".into(), 10));
+        assert_eq!(presumed(13), ("a.c".into(), 11));
+        assert_eq!(presumed(29), ("b.c".into(), 1));
+        assert_eq!(presumed(39), ("b.c".into(), 5));
+    }
+}
@@ -1,8 +1,9 @@
-use super::hir::{Symbol, Variable};
+use super::hir::{Qualifiers, Symbol, Variable};
 use crate::intern::InternedStr;
 #[cfg(test)]
 use proptest_derive::Arbitrary;
 use std::fmt::{self, Formatter};
+use std::rc::Rc;
 pub use struct_ref::{StructRef, StructType};
 
 mod struct_ref {
@@ -10,6 +11,18 @@ mod struct_ref {
     use std::rc::Rc;
 
     use super::Variable;
+    use crate::data::Location;
+
+    /// A single struct definition.
+    #[derive(Default)]
+    struct Definition {
+        /// Rc: A hack so that the members can be accessed across function boundaries,
+        /// see the documentation for `StructRef::get`.
+        /// Vec<Variable>: The members of the struct.
+        members: Rc<Vec<Variable>>,
+        /// Where the struct was defined, if it has been defined in the source.
+        location: Option<Location>,
+    }
 
     thread_local!(
         /// The global storage for all struct definitions.
@@ -18,24 +31,17 @@ mod struct_ref {
         /// RefCell: A container with interior mutability, used because `LocalKey`
         /// returns an immutable reference.
         /// Vec: A growable list of definitions.
-        /// Rc: A hack so that the members can be accessed across function boundaries,
-        /// see the documentation for `StructRef::get`.
-        /// Vec<Symbol>: The members of a single struct definition.
-        static TYPES: RefCell<Vec<Rc<Vec<Variable>>>> = Default::default()
+        static TYPES: RefCell<Vec<Definition>> = Default::default()
     );
 
     /// A reference to a struct definition. Allows self-referencing structs.
-    #[derive(Copy, Clone, Debug, Eq)]
+    ///
+    /// Two references are the same type only if they refer to the same definition:
+    /// `struct s` declared in two different scopes is two different types,
+    /// even if the members are the same.
+    #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
     pub struct StructRef(usize);
 
-    impl PartialEq for StructRef {
-        fn eq(&self, other: &Self) -> bool {
-            // see if we can do this the cheap way first;
-            // otherwise fall back to comparing every member
-            self.0 == other.0 || self.get() == other.get()
-        }
-    }
-
     impl Default for StructRef {
         fn default() -> Self {
             Self::new()
@@ -48,7 +54,7 @@ mod struct_ref {
             TYPES.with(|list| {
                 let mut types = list.borrow_mut();
                 let index = types.len();
-                types.push(Rc::new(vec![]));
+                types.push(Definition::default());
                 StructRef(index)
             })
         }
@@ -68,7 +74,12 @@ mod struct_ref {
         // from a closure, this uses an Rc so that it can be `clone`d cheaply.
         // The clone is necessary so the members do not reference TYPES.
         pub fn get(self) -> Rc<Vec<Variable>> {
-            TYPES.with(|list| list.borrow()[self.0].clone())
+            TYPES.with(|list| list.borrow()[self.0].members.clone())
+        }
+
+        /// Where the struct was defined, or `None` if it was only forward declared.
+        pub fn definition(self) -> Option<Location> {
+            TYPES.with(|list| list.borrow()[self.0].location)
         }
 
         /// Change the definition for a struct.
@@ -89,13 +100,29 @@ mod struct_ref {
         {
             TYPES.with(|list| {
                 let mut types = list.borrow_mut();
-                types[self.0] = members.into();
+                types[self.0].members = members.into();
             });
         }
+
+        /// Define a forward-declared struct at `location`.
+        pub(crate) fn define<V>(self, members: V, location: Location)
+        where
+            V: Into<Rc<Vec<Variable>>>,
+        {
+            self.update(members);
+            TYPES.with(|list| list.borrow_mut()[self.0].location = Some(location));
+        }
+
+        /// The position of this struct in the global list of definitions.
+        #[cfg(feature = "serialize")]
+        pub(crate) fn index(self) -> usize {
+            self.0
+        }
     }
 
     /// Structs can be either named or anonymous.
-    #[derive(Clone, Debug, PartialEq)]
+    #[derive(Clone, Debug)]
+    #[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
     pub enum StructType {
         /// Named structs can have forward declarations and be defined at any point
         /// in the program. In order to support self referential structs, named structs
@@ -109,6 +136,19 @@ mod struct_ref {
         Anonymous(Rc<Vec<Variable>>),
     }
 
+    impl PartialEq for StructType {
+        /// Every struct definition is a distinct type, even if the tags and members are the same.
+        fn eq(&self, other: &Self) -> bool {
+            match (self, other) {
+                (StructType::Named(_, left), StructType::Named(_, right)) => left == right,
+                (StructType::Anonymous(left), StructType::Anonymous(right)) => {
+                    Rc::ptr_eq(left, right)
+                }
+                _ => false,
+            }
+        }
+    }
+
     impl StructType {
         /// Get the members of a struct, regardless of which variant it is
         pub fn members(&self) -> Rc<Vec<Variable>> {
@@ -130,10 +170,20 @@ mod struct_ref {
                 StructType::Named(_, struct_ref) => struct_ref.get().is_empty(),
             }
         }
+        /// Where the struct was defined, if it's known.
+        ///
+        /// This is `None` for anonymous structs and for forward declarations.
+        pub fn definition(&self) -> Option<Location> {
+            match self {
+                StructType::Anonymous(_) => None,
+                StructType::Named(_, struct_ref) => struct_ref.definition(),
+            }
+        }
     }
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
 pub enum Type {
     Void,
     Bool,
@@ -149,8 +199,10 @@ pub enum Type {
     Function(FunctionType),
     Union(StructType),
     Struct(StructType),
-    /// Enums should always have members, since tentative definitions are not allowed
-    Enum(Option<InternedStr>, Vec<(InternedStr, i64)>),
+    /// Enums should always have members, since tentative definitions are not allowed.
+    ///
+    /// The members are shared so that copying the type of an enum variable is cheap.
+    Enum(Option<InternedStr>, Rc<Vec<(InternedStr, i64)>>),
     /// This is the type used for variadic arguments.
     VaList,
     /// A semantic error occured while parsing this type.
@@ -158,6 +210,7 @@ pub enum Type {
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(test, derive(Arbitrary))]
 pub enum ArrayType {
     Fixed(u64),
@@ -166,6 +219,7 @@ pub enum ArrayType {
 
 // NOTE: K&R declarations are not supported at this time
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
 pub struct FunctionType {
     // TODO: allow FunctionQualifiers as well
     pub return_type: Box<Type>,
@@ -240,13 +294,26 @@ impl Type {
             _ => false,
         }
     }
+    /// This type with a top-level `restrict` removed.
+    ///
+    /// `restrict` on a parameter doesn't affect whether two function types are compatible (6.7.6.3p15).
+    fn without_restrict(&self) -> Type {
+        match self {
+            Type::Pointer(to, qualifiers) => Type::Pointer(
+                to.clone(),
+                Qualifiers {
+                    restrict: false,
+                    ..*qualifiers
+                },
+            ),
+            other => other.clone(),
+        }
+    }
 }
 
 impl PartialEq for FunctionType {
     fn eq(&self, other: &Self) -> bool {
         // no prototype: any parameters are allowed
-        // TODO: issue a warning if a function has empty parameters, it's a holdover
-        // from C89
         self.params.is_empty()
             || other.params.is_empty()
             || self.varargs == other.varargs
@@ -257,8 +324,9 @@ impl PartialEq for FunctionType {
                 .zip(other.params.iter())
                 .all(|(a, b)| {
                     let (this_param, other_param) = (a.get(), b.get());
-                    this_param.ctype == other_param.ctype
-                        && this_param.qualifiers == other_param.qualifiers
+                    this_param.ctype.without_restrict() == other_param.ctype.without_restrict()
+                        && Qualifiers { restrict: false, ..this_param.qualifiers }
+                            == Qualifiers { restrict: false, ..other_param.qualifiers }
                 })
     }
 }
@@ -431,6 +499,7 @@ pub(super) fn print_type(
 #[cfg(test)]
 pub(crate) mod tests {
     use proptest::prelude::*;
+    use std::rc::Rc;
 
     use super::{ArrayType, InternedStr, Type};
     use crate::data::hir::Qualifiers;
@@ -447,7 +516,7 @@ pub(crate) mod tests {
             Just(Type::Double),
             // enum
             any::<(Option<InternedStr>, Vec<(InternedStr, i64)>)>()
-                .prop_map(|(name, members)| Type::Enum(name, members)),
+                .prop_map(|(name, members)| Type::Enum(name, Rc::new(members))),
             Just(Type::VaList),
             Just(Type::Error),
         ];
@@ -0,0 +1,328 @@
+//! Dead code elimination on the HIR, before it's handed to the backend.
+//!
+//! This runs at `-O1` and above. It removes:
+//! - branches of `if` statements with a constant condition
+//! - `while` loops with a condition that is always false
+//! - `static` functions and objects that are never used by anything that is kept
+//!
+//! It's deliberately conservative: code containing a label (including `case` and `default`)
+//! is always kept, since it could be reached with a jump from outside,
+//! and nothing with external linkage, declared `volatile`, or run by the C runtime
+//! (`__attribute__((constructor))` and `destructor`) is removed.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::arch::Target;
+use crate::data::hir::*;
+use crate::data::{InternedStr, Locatable, StorageClass};
+use crate::fold::{const_eval, ConstValue};
+
+/// Remove code from `program` that can never run or is never used.
+pub(crate) fn eliminate_dead_code<'hir>(
+    mut program: Vec<Locatable<Declaration<'hir>>>,
+) -> Vec<Locatable<Declaration<'hir>>> {
+    for decl in &mut program {
+        if let Some(Initializer::FunctionBody(body)) = &mut decl.data.init {
+            body.iter_mut().for_each(prune);
+        }
+    }
+    let used = used_names(&program);
+    program.retain(|decl| {
+        let symbol = decl.data.symbol;
+        !is_internal(symbol) || used.contains(&symbol.get().id)
+    });
+    program
+}
+
+/// Whether `symbol` can only be used from this translation unit, and is safe to remove if it isn't.
+fn is_internal(symbol: Symbol) -> bool {
+    let meta = symbol.get();
+    let func = meta.qualifiers.func;
+    meta.storage_class == StorageClass::Static
+        && !meta.qualifiers.volatile
+        && func.constructor.is_none()
+        && func.destructor.is_none()
+}
+
+/// The names of everything used by a declaration that will be kept.
+///
+/// Names are compared instead of symbols so that `extern` declarations at block scope,
+/// and local variables that shadow a `static`, keep it alive; this can only keep too much.
+fn used_names(program: &[Locatable<Declaration>]) -> HashSet<InternedStr> {
+    let mut internal: HashMap<InternedStr, Vec<&Declaration>> = HashMap::new();
+    let mut pending = Vec::new();
+    for decl in program {
+        if is_internal(decl.data.symbol) {
+            let name = decl.data.symbol.get().id;
+            internal.entry(name).or_default().push(&decl.data);
+        } else {
+            if let Some(init) = &decl.data.init {
+                init.names(&mut pending);
+            }
+            // `void g(void) __attribute__((alias("f")));` uses `f`
+            pending.extend(decl.data.attributes.alias);
+        }
+    }
+    let mut used = HashSet::new();
+    while let Some(name) = pending.pop() {
+        if used.insert(name) {
+            for decl in internal.get(&name).into_iter().flatten() {
+                if let Some(init) = &decl.init {
+                    init.names(&mut pending);
+                }
+                pending.extend(decl.attributes.alias);
+            }
+        }
+    }
+    used
+}
+
+/// Remove dead branches from `stmt` and everything inside it.
+fn prune(stmt: &mut Stmt) {
+    match &mut stmt.data {
+        StmtType::Compound(stmts) => stmts.iter_mut().for_each(prune),
+        StmtType::If(_, body, otherwise) => {
+            prune(body);
+            if let Some(otherwise) = otherwise {
+                prune(otherwise);
+            }
+        }
+        StmtType::Do(body, _)
+        | StmtType::While(_, body)
+        | StmtType::Switch(_, body)
+        | StmtType::Label(_, body)
+        | StmtType::Case(_, body)
+        | StmtType::Default(body) => prune(body),
+        StmtType::For(init, _, _, body) => {
+            prune(init);
+            prune(body);
+        }
+        _ => {}
+    }
+    match &mut stmt.data {
+        StmtType::If(condition, body, otherwise) => match constant_condition(condition) {
+            // `if (1) body else otherwise`
+            Some(true) => {
+                if otherwise.as_ref().is_some_and(|o| o.data.contains_label()) {
+                    return;
+                }
+                *otherwise = None;
+                // the backend rejects statements after a jump, so only unwrap `body` if it can't jump
+                if !body.data.contains_jump() {
+                    stmt.data = std::mem::take(&mut body.data);
+                }
+            }
+            // `if (0) body else otherwise`
+            Some(false) => {
+                if body.data.contains_label() {
+                    return;
+                }
+                body.data = StmtType::default();
+                match otherwise {
+                    Some(otherwise) if otherwise.data.contains_jump() => {}
+                    Some(otherwise) => stmt.data = std::mem::take(&mut otherwise.data),
+                    None => stmt.data = StmtType::default(),
+                }
+            }
+            None => {}
+        },
+        // `while (0)`
+        StmtType::While(condition, body)
+            if constant_condition(condition) == Some(false) && !body.data.contains_label() =>
+        {
+            stmt.data = StmtType::default();
+        }
+        _ => {}
+    }
+}
+
+/// The value of `condition`, if it's a constant expression.
+fn constant_condition(condition: &Expr) -> Option<bool> {
+    match const_eval(condition, &Target::host()) {
+        Ok(ConstValue::Int(i)) => Some(i != 0),
+        Ok(ConstValue::Uint(u)) => Some(u != 0),
+        _ => None,
+    }
+}
+
+impl<'hir> StmtType<'hir> {
+    /// Every statement directly inside this one.
+    fn children(&self) -> Vec<&Stmt<'hir>> {
+        match self {
+            StmtType::Compound(stmts) => stmts.iter().collect(),
+            StmtType::If(_, body, otherwise) => {
+                let mut children = vec![&**body];
+                children.extend(otherwise.as_deref());
+                children
+            }
+            StmtType::Do(body, _)
+            | StmtType::While(_, body)
+            | StmtType::Switch(_, body)
+            | StmtType::Label(_, body)
+            | StmtType::Case(_, body)
+            | StmtType::Default(body) => vec![body],
+            StmtType::For(init, _, _, body) => vec![init, body],
+            _ => Vec::new(),
+        }
+    }
+    /// Whether a jump from outside this statement could land inside it.
+    fn contains_label(&self) -> bool {
+        match self {
+            StmtType::Label(..) | StmtType::Case(..) | StmtType::Default(_) => true,
+            _ => self.children().iter().any(|s| s.data.contains_label()),
+        }
+    }
+    /// Whether this contains a `return`, `goto`, `break`, or `continue`.
+    fn contains_jump(&self) -> bool {
+        match self {
+            StmtType::Return(_) | StmtType::Goto(_) | StmtType::Break | StmtType::Continue => true,
+            _ => self.children().iter().any(|s| s.data.contains_jump()),
+        }
+    }
+    /// Add every name this refers to to `names`.
+    fn names(&self, names: &mut Vec<InternedStr>) {
+        match self {
+            StmtType::If(condition, ..)
+            | StmtType::Do(_, condition)
+            | StmtType::While(condition, _)
+            | StmtType::Switch(condition, _)
+            | StmtType::Expr(condition)
+            | StmtType::Return(Some(condition)) => condition.names(names),
+            StmtType::For(_, condition, post_loop, _) => {
+                for expr in condition.iter().chain(post_loop) {
+                    expr.names(names);
+                }
+            }
+            StmtType::Decl(decls) => {
+                for decl in decls {
+                    names.push(decl.data.symbol.get().id);
+                    if let Some(init) = &decl.data.init {
+                        init.names(names);
+                    }
+                }
+            }
+            _ => {}
+        }
+        for child in self.children() {
+            child.data.names(names);
+        }
+    }
+}
+
+impl Initializer<'_> {
+    fn names(&self, names: &mut Vec<InternedStr>) {
+        match self {
+            Initializer::Scalar(expr) => expr.names(names),
+            Initializer::InitializerList(inits) => inits.iter().for_each(|i| i.names(names)),
+            Initializer::FunctionBody(body) => body.iter().for_each(|s| s.data.names(names)),
+        }
+    }
+}
+
+impl Expr<'_> {
+    fn names(&self, names: &mut Vec<InternedStr>) {
+        match &self.expr {
+            ExprType::Id(symbol) => names.push(symbol.get().id),
+            ExprType::Literal(_) | ExprType::Sizeof(_) => {}
+            ExprType::FuncCall(func, args) => {
+                func.names(names);
+                args.iter().for_each(|arg| arg.names(names));
+            }
+            ExprType::Member(inner, _)
+            | ExprType::Cast(inner)
+            | ExprType::Deref(inner)
+            | ExprType::Negate(inner)
+            | ExprType::BitwiseNot(inner)
+            | ExprType::StaticRef(inner)
+            | ExprType::Noop(inner) => inner.names(names),
+            ExprType::Binary(_, left, right)
+            | ExprType::Comma(left, right)
+            | ExprType::Let(_, left, right) => {
+                left.names(names);
+                right.names(names);
+            }
+            ExprType::Ternary(condition, then, otherwise) => {
+                condition.names(names);
+                then.names(names);
+                otherwise.names(names);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::eliminate_dead_code;
+    use crate::analyze::test::arena;
+    use crate::{check_semantics, Opt};
+
+    /// The C for `program` after dead code elimination.
+    fn eliminate(program: &str) -> String {
+        let hir = check_semantics(program, Opt::default(), arena())
+            .result
+            .unwrap_or_else(|err| panic!("error in {}: {:?}", program, err));
+        eliminate_dead_code(hir)
+            .iter()
+            .map(|decl| decl.data.to_string())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn unused_statics() {
+        let kept = eliminate(
+            "static int unused(void) { return 1; }
+            static int helper(void) { return 2; }
+            static int (*table)(void) = helper;
+            static int only_used_by_unused(void) { return 3; }
+            static int calls(void) { return only_used_by_unused(); }
+            static volatile int flag;
+            static int counter;
+            int main(void) { return table() + counter; }",
+        );
+        for name in &["helper", "table", "flag", "counter", "main"] {
+            assert!(kept.contains(name), "{} was removed:\n{}", name, kept);
+        }
+        for name in &["unused", "only_used_by_unused", "calls"] {
+            assert!(!kept.contains(name), "{} was kept:\n{}", name, kept);
+        }
+    }
+
+    #[test]
+    fn constant_branches() {
+        let kept = eliminate(
+            "int then_branch(void), else_branch(void);
+            int f(void) {
+                if (0) { then_branch(); } else { else_branch(); }
+                if (1) else_branch(); else then_branch();
+                while (0) then_branch();
+                return 0;
+            }",
+        );
+        assert!(kept.contains("(else_branch)()"), "{}", kept);
+        assert!(!kept.contains("(then_branch)()"), "{}", kept);
+        // statics only used by removed code are removed too
+        let kept = eliminate(
+            "static int debug_only(void) { return 1; }
+            int f(void) { if (0) return debug_only(); return 0; }",
+        );
+        assert!(!kept.contains("debug_only"), "{}", kept);
+    }
+
+    #[test]
+    fn labels_keep_code() {
+        let kept = eliminate(
+            "int marker(void);
+            int f(int x) {
+                if (x) goto inside;
+                if (0) { inside: return marker(); }
+                switch (x) { if (0) { case 1: return marker() + 1; } }
+                return 0;
+            }",
+        );
+        assert_eq!(kept.matches("(marker)()").count(), 2, "{}", kept);
+        // a branch that jumps can't be unwrapped, since the backend rejects code after a jump
+        let kept = eliminate("int f(void) { if (1) return 1; return 2; }");
+        assert!(kept.contains("if"), "{}", kept);
+    }
+}
@@ -1,7 +1,8 @@
-use crate::arch::CHAR_BIT;
+use crate::arch::{ArchData, Target, CHAR_BIT};
 use crate::data::hir::*;
 use crate::data::lex::Literal::*;
 use crate::data::*;
+use crate::stats::{self, Phase};
 use std::ops::{Add, Div, Mul, Sub};
 
 macro_rules! fold_int_bin_op {
@@ -46,7 +47,7 @@ fn fold_scalar_bin_op(
 
 macro_rules! fold_compare_op {
 ($left: expr, $right: expr, $constructor: ident, $op: tt, $compare: expr) => {{
-        let (left, right) = ($left.const_fold()?, $right.const_fold()?);
+        let (left, right) = ($left, $right);
         match (&left.expr, &right.expr) {
             (ExprType::Literal(a), ExprType::Literal(b)) => {
                 match (a, b) {
@@ -55,15 +56,21 @@ macro_rules! fold_compare_op {
                     #[allow(clippy::float_cmp)]
                     (Float(a), Float(b)) => ExprType::Literal(Int((a $op b) as i64)),
                     (Char(a), Char(b)) => ExprType::Literal(Int((a $op b) as i64)),
-                    (_, _) => ExprType::Binary(BinaryOp::Compare($compare), Box::new(left), Box::new(right)),
+                    (_, _) => ExprType::Binary(BinaryOp::Compare($compare), left, right),
                 }
             }
-            _ => ExprType::Binary(BinaryOp::Compare($compare), Box::new(left), Box::new(right)),
+            _ => ExprType::Binary(BinaryOp::Compare($compare), left, right),
         }
     }}
 }
 
-impl Expr {
+/// Fold the expression in `slot`, reusing its place in the arena.
+fn fold<'hir>(slot: &'hir mut Expr<'hir>) -> CompileResult<&'hir mut Expr<'hir>> {
+    *slot = slot.take().const_fold()?;
+    Ok(slot)
+}
+
+impl<'hir> Expr<'hir> {
     pub(crate) fn is_zero(&self) -> bool {
         if let ExprType::Literal(token) = &self.expr {
             match *token {
@@ -89,44 +96,33 @@ impl Expr {
     }
 
     /// Returns a `Literal` if this is a literal, or the original expression otherwise
-    pub fn into_literal(self) -> Result<Literal, Expr> {
+    pub fn into_literal(self) -> Result<Literal, Expr<'hir>> {
         match self.expr {
             ExprType::Literal(lit) => Ok(lit),
             _ => Err(self),
         }
     }
-    pub(crate) fn constexpr(self) -> CompileResult<Locatable<(Literal, Type)>> {
-        let folded = self.const_fold()?;
-        match folded.expr {
-            ExprType::Literal(token) => Ok(Locatable {
-                data: (token, folded.ctype),
-                location: folded.location,
-            }),
-            _ => Err(folded.location.error(SemanticError::NotConstant(folded))),
-        }
-    }
-    pub fn const_fold(self) -> CompileResult<Expr> {
+    pub fn const_fold(self) -> CompileResult<Expr<'hir>> {
+        let _folding = stats::enter(Phase::ConstantFolding);
         let location = self.location;
+        // conversions and shifts wrap instead of overflowing, like GCC
+        let wraps = matches!(
+            self.expr,
+            ExprType::Cast(_) | ExprType::Binary(BinaryOp::Shl, ..)
+        );
         let folded = match self.expr {
             ExprType::Literal(_) => self.expr,
-            ExprType::Id(ref name) => match &self.ctype {
-                Type::Enum(_, members) => {
-                    match members.iter().find(|member| member.0 == name.get().id) {
-                        Some(enum_literal) => ExprType::Literal(Int(enum_literal.1)),
-                        _ => self.expr,
-                    }
-                }
-                // TODO: if a variable were const, could we const fold Ids?
-                _ => self.expr,
-            },
+            // enumeration constants are already literals by the time they get here
+            // TODO: if a variable were const, could we const fold Ids?
+            ExprType::Id(_) => self.expr,
             ExprType::Sizeof(ctype) => {
-                let sizeof = ctype.sizeof().map_err(|data| Locatable {
-                    data: data.to_string(),
-                    location,
-                })?;
+                let sizeof = ctype
+                    .sizeof()
+                    .map_err(|err| location.error(SemanticError::InvalidSize(err)))?;
                 ExprType::Literal(UnsignedInt(sizeof))
             }
-            ExprType::Negate(expr) => expr.const_fold()?.map_literal(
+            ExprType::Negate(expr) => map_literal(
+                fold(expr)?,
                 &location,
                 |token| match token {
                     Int(i) => {
@@ -146,7 +142,8 @@ impl Expr {
                 },
                 ExprType::Negate,
             )?,
-            ExprType::BitwiseNot(expr) => expr.const_fold()?.map_literal(
+            ExprType::BitwiseNot(expr) => map_literal(
+                fold(expr)?,
                 &location,
                 |token| match token {
                     Int(i) => Ok(Int(!i)),
@@ -157,47 +154,39 @@ impl Expr {
                 ExprType::BitwiseNot,
             )?,
             ExprType::Binary(op, left, right) => {
-                fold_binary(*left, *right, op, &self.ctype, location)?
+                fold_binary(left, right, op, &self.ctype, location)?
             }
             ExprType::Comma(left, right) => {
-                let (left, right) = (left.const_fold()?, right.const_fold()?);
+                let (left, right) = (fold(left)?, fold(right)?);
                 // check if we can ignore left or it has side effects
                 if left.is_constexpr() {
-                    right.expr
+                    right.take().expr
                 } else {
-                    ExprType::Comma(Box::new(left), Box::new(right))
+                    ExprType::Comma(left, right)
                 }
             }
-            ExprType::Noop(inner) => {
-                let inner = inner.const_fold()?;
-                ExprType::Noop(Box::new(inner))
-            }
+            ExprType::Noop(inner) => ExprType::Noop(fold(inner)?),
             ExprType::Deref(expr) => {
-                let folded = expr.const_fold()?;
+                let folded = fold(expr)?;
                 if let ExprType::Literal(Int(0)) = folded.expr {
                     return Err(Locatable::new(
                         SemanticError::NullPointerDereference.into(),
                         location,
                     ));
                 }
-                ExprType::Deref(Box::new(folded))
+                ExprType::Deref(folded)
             }
             ExprType::Ternary(condition, then, otherwise) => {
-                let (condition, then, otherwise) = (
-                    condition.const_fold()?,
-                    then.const_fold()?,
-                    otherwise.const_fold()?,
-                );
+                let (condition, then, otherwise) =
+                    (fold(condition)?, fold(then)?, fold(otherwise)?);
                 match condition.expr {
-                    ExprType::Literal(Int(0)) => otherwise.expr,
-                    ExprType::Literal(Int(_)) => then.expr,
-                    _ => {
-                        ExprType::Ternary(Box::new(condition), Box::new(then), Box::new(otherwise))
-                    }
+                    ExprType::Literal(Int(0)) => otherwise.take().expr,
+                    ExprType::Literal(Int(_)) => then.take().expr,
+                    _ => ExprType::Ternary(condition, then, otherwise),
                 }
             }
             ExprType::FuncCall(func, params) => {
-                let func = func.const_fold()?;
+                let func = fold(func)?;
                 #[rustfmt::skip]
                 let params: Vec<Expr> = params
                     .into_iter()
@@ -206,19 +195,18 @@ impl Expr {
                 // function calls are always non-constant
                 // TODO: if we have access to the full source of a function, could we try to
                 // TODO: fold across function boundaries?
-                ExprType::FuncCall(Box::new(func), params)
-            }
-            ExprType::Member(expr, member) => {
-                let expr = expr.const_fold()?;
-                ExprType::Member(Box::new(expr), member)
+                ExprType::FuncCall(func, params)
             }
-            ExprType::PostIncrement(expr, increase) => {
-                let expr = expr.const_fold()?;
-                // this isn't constant for the same reason assignment isn't constant
-                ExprType::PostIncrement(Box::new(expr), increase)
-            }
-            ExprType::Cast(expr) => cast(*expr, &self.ctype)?,
-            ExprType::StaticRef(inner) => ExprType::StaticRef(Box::new(inner.const_fold()?)),
+            ExprType::Member(expr, member) => ExprType::Member(fold(expr)?, member),
+            ExprType::Let(symbol, init, body) => ExprType::Let(symbol, fold(init)?, fold(body)?),
+            ExprType::Cast(expr) => cast(expr, &self.ctype)?,
+            ExprType::StaticRef(inner) => ExprType::StaticRef(fold(inner)?),
+        };
+        let folded = match folded {
+            ExprType::Literal(token) => ExprType::Literal(
+                fit_to_type(token, &self.ctype, wraps).map_err(|err| location.error(err))?,
+            ),
+            other => other,
         };
         Ok(Expr {
             expr: folded,
@@ -226,70 +214,75 @@ impl Expr {
             ..self
         })
     }
-    ///
-    /// fold_func return values:
-    /// `Ok(Some(_))`: Successfuly folded
-    /// `Ok(None)`: Non-foldable expression
-    /// `Err(_)`: Error while folding
-    fn literal_bin_op<F>(
-        self,
-        other: Expr,
-        location: &Location,
-        fold_func: F,
-        op: BinaryOp,
-    ) -> CompileResult<ExprType>
-    where
-        F: FnOnce(&Literal, &Literal, &Type) -> Result<Option<Literal>, SemanticError>,
-    {
-        let (left, right) = (self.const_fold()?, other.const_fold()?);
-        let literal: Option<ExprType> = match (&left.expr, &right.expr) {
-            (ExprType::Literal(left_token), ExprType::Literal(right_token)) => {
-                match fold_func(left_token, right_token, &left.ctype) {
-                    Err(err) => {
-                        return Err(location.error(err));
-                    }
-                    Ok(token) => token.map(ExprType::Literal),
+}
+
+///
+/// fold_func return values:
+/// `Ok(Some(_))`: Successfuly folded
+/// `Ok(None)`: Non-foldable expression
+/// `Err(_)`: Error while folding
+fn literal_bin_op<'hir, F>(
+    left: &'hir mut Expr<'hir>,
+    right: &'hir mut Expr<'hir>,
+    location: &Location,
+    fold_func: F,
+    op: BinaryOp,
+) -> CompileResult<ExprType<'hir>>
+where
+    F: FnOnce(&Literal, &Literal, &Type) -> Result<Option<Literal>, SemanticError>,
+{
+    let literal = match (&left.expr, &right.expr) {
+        (ExprType::Literal(left_token), ExprType::Literal(right_token)) => {
+            match fold_func(left_token, right_token, &left.ctype) {
+                Err(err) => {
+                    return Err(location.error(err));
                 }
+                Ok(token) => token,
             }
-            _ => None,
-        };
-        Ok(literal.unwrap_or_else(|| ExprType::Binary(op, Box::new(left), Box::new(right))))
-    }
-    fn map_literal<F, C>(
-        self,
-        location: &Location,
-        literal_func: F,
-        constructor: C,
-    ) -> CompileResult<ExprType>
-    where
-        F: FnOnce(Literal) -> Result<Literal, SemanticError>,
-        C: FnOnce(Box<Expr>) -> ExprType,
-    {
-        match self.expr {
-            ExprType::Literal(token) => match literal_func(token) {
-                Ok(literal) => Ok(ExprType::Literal(literal)),
-                Err(error) => Err(location.error(error)),
-            },
-            _ => Ok(constructor(Box::new(self))),
         }
+        _ => None,
+    };
+    Ok(match literal {
+        Some(token) => ExprType::Literal(token),
+        None => ExprType::Binary(op, left, right),
+    })
+}
+
+fn map_literal<'hir, F, C>(
+    expr: &'hir mut Expr<'hir>,
+    location: &Location,
+    literal_func: F,
+    constructor: C,
+) -> CompileResult<ExprType<'hir>>
+where
+    F: FnOnce(Literal) -> Result<Literal, SemanticError>,
+    C: FnOnce(&'hir mut Expr<'hir>) -> ExprType<'hir>,
+{
+    match &expr.expr {
+        ExprType::Literal(token) => match literal_func(token.clone()) {
+            Ok(literal) => Ok(ExprType::Literal(literal)),
+            Err(error) => Err(location.error(error)),
+        },
+        _ => Ok(constructor(expr)),
     }
 }
 
-fn fold_binary(
-    left: Expr,
-    right: Expr,
+fn fold_binary<'hir>(
+    left: &'hir mut Expr<'hir>,
+    right: &'hir mut Expr<'hir>,
     op: BinaryOp,
     parent_type: &Type,
     location: Location,
-) -> CompileResult<ExprType> {
+) -> CompileResult<ExprType<'hir>> {
     use lex::ComparisonToken::*;
     use BinaryOp::*;
 
-    let left = left.const_fold()?;
-    let right = right.const_fold()?;
+    let left = fold(left)?;
+    let right = fold(right)?;
 
     match op {
-        Add => left.literal_bin_op(
+        Add => literal_bin_op(
+            left,
             right,
             &location,
             fold_scalar_bin_op(
@@ -300,7 +293,8 @@ fn fold_binary(
             ),
             Add,
         ),
-        Sub => left.literal_bin_op(
+        Sub => literal_bin_op(
+            left,
             right,
             &location,
             fold_scalar_bin_op(
@@ -311,7 +305,8 @@ fn fold_binary(
             ),
             Sub,
         ),
-        Mul => left.literal_bin_op(
+        Mul => literal_bin_op(
+            left,
             right,
             &location,
             fold_scalar_bin_op(
@@ -326,15 +321,21 @@ fn fold_binary(
             if right.ctype.is_integral() && right.is_zero() {
                 return Err(location.error(SemanticError::DivideByZero));
             }
-            left.literal_bin_op(
+            literal_bin_op(
+                left,
                 right,
                 &location,
-                fold_scalar_bin_op(
-                    f64::div,
-                    i64::overflowing_div,
-                    u64::wrapping_div,
-                    u8::wrapping_div,
-                ),
+                |a: &Literal, b: &Literal, ctype: &Type| {
+                    if quotient_overflows(a, b, ctype) {
+                        return Err(SemanticError::ConstOverflow { is_positive: true });
+                    }
+                    fold_scalar_bin_op(
+                        f64::div,
+                        i64::overflowing_div,
+                        u64::wrapping_div,
+                        u8::wrapping_div,
+                    )(a, b, ctype)
+                },
                 Div,
             )
         }
@@ -342,10 +343,15 @@ fn fold_binary(
             if right.is_zero() {
                 return Err(location.error(SemanticError::DivideByZero));
             }
-            left.literal_bin_op(
+            literal_bin_op(
+                left,
                 right,
                 &location,
-                |a: &Literal, b: &Literal, _| match (a, b) {
+                |a: &Literal, b: &Literal, ctype: &Type| match (a, b) {
+                    // `INT_MIN % -1` is undefined for the same reason as `INT_MIN / -1`
+                    (Int(_), Int(_)) if quotient_overflows(a, b, ctype) => {
+                        Err(SemanticError::ConstOverflow { is_positive: false })
+                    }
                     (Int(a), Int(b)) => {
                         let (value, overflowed) = a.overflowing_rem(*b);
 
@@ -363,12 +369,13 @@ fn fold_binary(
                 Mod,
             )
         }
-        Xor => left.literal_bin_op(right, &location, fold_int_bin_op!(^), Xor),
-        BitwiseAnd => left.literal_bin_op(right, &location, fold_int_bin_op!(&), BitwiseAnd),
-        BitwiseOr => left.literal_bin_op(right, &location, fold_int_bin_op!(|), BitwiseOr),
+        Xor => literal_bin_op(left, right, &location, fold_int_bin_op!(^), Xor),
+        BitwiseAnd => literal_bin_op(left, right, &location, fold_int_bin_op!(&), BitwiseAnd),
+        BitwiseOr => literal_bin_op(left, right, &location, fold_int_bin_op!(|), BitwiseOr),
         Shl => shift_left(left, right, parent_type, &location),
         Shr => shift_right(left, right, parent_type, &location),
-        LogicalAnd => left.literal_bin_op(
+        LogicalAnd => literal_bin_op(
+            left,
             right,
             &location,
             |left, right, _| match (left, right) {
@@ -378,7 +385,8 @@ fn fold_binary(
             },
             LogicalAnd,
         ),
-        LogicalOr => left.literal_bin_op(
+        LogicalOr => literal_bin_op(
+            left,
             right,
             &location,
             |left, right, _| match (left, right) {
@@ -391,11 +399,7 @@ fn fold_binary(
         Assign => {
             // TODO: could we propagate this information somehow?
             // e.g. fold `int main() { int x = 1; return x; }` to `return 1;`
-            Ok(ExprType::Binary(
-                BinaryOp::Assign,
-                Box::new(left),
-                Box::new(right),
-            ))
+            Ok(ExprType::Binary(BinaryOp::Assign, left, right))
         }
         Compare(Less) => Ok(fold_compare_op!(left, right, Compare, <, Less)),
         Compare(LessEqual) => Ok(fold_compare_op!(left, right, Compare, <=, LessEqual)),
@@ -406,6 +410,56 @@ fn fold_binary(
     }
 }
 
+/// Whether `a / b` is out of range for a signed `ctype` narrower than 64 bits.
+///
+/// The only way this can happen is `MIN / -1`, which would trap at runtime,
+/// so it has to be caught here instead of silently folding to `-MIN`.
+fn quotient_overflows(a: &Literal, b: &Literal, ctype: &Type) -> bool {
+    let (a, b) = match (a, b) {
+        (Int(a), Int(b)) => (*a, *b),
+        _ => return false,
+    };
+    let bits = match ctype.sizeof() {
+        Ok(size) if size < 8 => size * u64::from(CHAR_BIT),
+        _ => return false,
+    };
+    let max = (1_i64 << (bits - 1)) - 1;
+    match a.checked_div(b) {
+        Some(quotient) => quotient > max,
+        // overflowed even as a 64-bit value
+        None => true,
+    }
+}
+
+/// Make an integer fit in `ctype`, which may be narrower than the 64 bits used while folding.
+///
+/// Unsigned values always wrap around (6.2.5p9).
+/// Signed values wrap if `wraps` is set, e.g. for conversions (6.3.1.3p3);
+/// otherwise they overflow, the same as 64-bit values.
+fn fit_to_type(token: Literal, ctype: &Type, wraps: bool) -> Result<Literal, SemanticError> {
+    let bits = match ctype {
+        // `const_cast` already made these fit
+        Type::Bool | Type::Char(_) => return Ok(token),
+        _ if ctype.is_integral() => match ctype.sizeof() {
+            Ok(size) if size < 8 => size as u32 * u32::from(CHAR_BIT),
+            _ => return Ok(token),
+        },
+        _ => return Ok(token),
+    };
+    let shift = 64 - bits;
+    Ok(match token {
+        UnsignedInt(u) if !ctype.is_signed() => UnsignedInt(u << shift >> shift),
+        Int(i) if ctype.is_signed() => {
+            let wrapped = (i << shift) >> shift;
+            if wrapped != i && !wraps {
+                return Err(SemanticError::ConstOverflow { is_positive: i > 0 });
+            }
+            Int(wrapped)
+        }
+        other => other,
+    })
+}
+
 impl Literal {
     fn non_negative_int(&self) -> Result<u64, ()> {
         match *self {
@@ -417,16 +471,16 @@ impl Literal {
     }
 }
 
-fn cast(expr: Expr, ctype: &Type) -> CompileResult<ExprType> {
-    let expr = expr.const_fold()?;
+fn cast<'hir>(expr: &'hir mut Expr<'hir>, ctype: &Type) -> CompileResult<ExprType<'hir>> {
+    let expr = fold(expr)?;
     Ok(if let ExprType::Literal(ref token) = expr.expr {
         if let Some(token) = const_cast(token, ctype) {
             ExprType::Literal(token)
         } else {
-            ExprType::Cast(Box::new(expr))
+            ExprType::Cast(expr)
         }
     } else {
-        ExprType::Cast(Box::new(expr))
+        ExprType::Cast(expr)
     })
 }
 
@@ -467,65 +521,51 @@ fn const_cast(token: &Literal, ctype: &Type) -> Option<Literal> {
     Some(token)
 }
 
-fn shift_right(
-    left: Expr,
-    right: Expr,
+fn shift_right<'hir>(
+    left: &'hir mut Expr<'hir>,
+    right: &'hir mut Expr<'hir>,
     ctype: &Type,
     location: &Location,
-) -> CompileResult<ExprType> {
-    let (left, right) = (left.const_fold()?, right.const_fold()?);
-    if let ExprType::Literal(token) = right.expr {
+) -> CompileResult<ExprType<'hir>> {
+    if let ExprType::Literal(token) = &right.expr {
         let shift = match token.non_negative_int() {
             Ok(u) => u,
             Err(_) => {
                 return Err(location.error(SemanticError::NegativeShift { is_left: false }));
             }
         };
-        let sizeof = ctype.sizeof().map_err(|err| Locatable {
-            data: err.to_string(),
-            location: *location,
-        })?;
+        let sizeof = ctype
+            .sizeof()
+            .map_err(|err| location.error(SemanticError::InvalidSize(err)))?;
         // Rust panics if the shift is greater than the size of the type
-        if shift >= sizeof {
+        if shift >= u64::from(CHAR_BIT) * sizeof {
             return Ok(ExprType::Literal(if ctype.is_signed() {
                 Int(0)
             } else {
                 UnsignedInt(0)
             }));
         }
-        if let ExprType::Literal(token) = left.expr {
-            Ok(match token {
+        if let ExprType::Literal(token) = &left.expr {
+            Ok(match *token {
                 Int(i) => ExprType::Literal(Int(i.wrapping_shr(shift as u32))),
                 UnsignedInt(u) => ExprType::Literal(UnsignedInt(u.wrapping_shr(shift as u32))),
                 _ => unreachable!("only ints and unsigned ints can be right shifted"),
             })
         } else {
-            Ok(ExprType::Binary(
-                BinaryOp::Shr,
-                Box::new(left),
-                Box::new(Expr {
-                    expr: ExprType::Literal(token),
-                    ..right
-                }),
-            ))
+            Ok(ExprType::Binary(BinaryOp::Shr, left, right))
         }
     } else {
-        Ok(ExprType::Binary(
-            BinaryOp::Shr,
-            Box::new(left),
-            Box::new(right),
-        ))
+        Ok(ExprType::Binary(BinaryOp::Shr, left, right))
     }
 }
 
-fn shift_left(
-    left: Expr,
-    right: Expr,
+fn shift_left<'hir>(
+    left: &'hir mut Expr<'hir>,
+    right: &'hir mut Expr<'hir>,
     ctype: &Type,
     location: &Location,
-) -> CompileResult<ExprType> {
-    let (left, right) = (left.const_fold()?, right.const_fold()?);
-    if let ExprType::Literal(token) = right.expr {
+) -> CompileResult<ExprType<'hir>> {
+    if let ExprType::Literal(token) = &right.expr {
         let shift = match token.non_negative_int() {
             Ok(u) => u,
             Err(_) => {
@@ -536,12 +576,7 @@ fn shift_left(
         if left.ctype.is_signed() {
             let size = match left.ctype.sizeof() {
                 Ok(s) => s,
-                Err(err) => {
-                    return Err(Locatable::new(
-                        SemanticError::Generic(err.into()).into(),
-                        *location,
-                    ))
-                }
+                Err(err) => return Err(location.error(SemanticError::InvalidSize(err))),
             };
             let max_shift = u64::from(CHAR_BIT) * size;
             if shift >= max_shift {
@@ -564,31 +599,223 @@ fn shift_left(
             ExprType::Literal(UnsignedInt(u)) => {
                 ExprType::Literal(UnsignedInt(u.wrapping_shl(shift as u32)))
             }
-            _ => ExprType::Binary(
-                BinaryOp::Shl,
-                Box::new(left),
-                Box::new(Expr {
-                    expr: ExprType::Literal(token),
-                    ..right
-                }),
-            ),
+            _ => ExprType::Binary(BinaryOp::Shl, left, right),
         })
     } else {
-        Ok(ExprType::Binary(
-            BinaryOp::Shl,
-            Box::new(left),
-            Box::new(right),
-        ))
+        Ok(ExprType::Binary(BinaryOp::Shl, left, right))
+    }
+}
+
+/// The value of a constant expression, as computed by `const_eval`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConstValue {
+    /// A value of a signed integer type, including enums
+    Int(i64),
+    /// A value of an unsigned integer type, `_Bool`, or a pointer converted from an integer
+    Uint(u64),
+    /// A value of type `float` or `double`
+    Float(f64),
+    /// The address of `symbol`, plus `offset` bytes
+    Address { symbol: Symbol, offset: i64 },
+    /// The address of a string literal. The contents include the null terminator.
+    Str(Vec<u8>),
+}
+
+/// Why `const_eval` could not evaluate an expression.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConstEvalError {
+    /// The expression is not a constant expression.
+    ///
+    /// This is the innermost subexpression responsible, e.g. the `f()` in `1 + f()`,
+    /// printed as C.
+    NotConstant(Locatable<String>),
+    /// The expression is constant, but evaluating it failed,
+    /// e.g. because of overflow or division by zero.
+    Arithmetic(CompileError),
+}
+
+impl From<ConstEvalError> for CompileError {
+    fn from(err: ConstEvalError) -> Self {
+        match err {
+            ConstEvalError::NotConstant(expr) => {
+                expr.location.error(SemanticError::NotConstant(expr.data))
+            }
+            ConstEvalError::Arithmetic(err) => err,
+        }
+    }
+}
+
+/// Evaluate a constant expression (6.6) at compile time.
+///
+/// The following are constant:
+/// - literals, enum constants, and `sizeof`
+/// - unary `-` and `~`, and all binary operators except assignment,
+///   if their operands are constant
+/// - `?:` and `,` if the operands that are evaluated are constant
+/// - casts of constants; these truncate to the width of the new type
+/// - the address of a variable or function (this does not check that it has static storage),
+///   of a member of such a variable, or of a string literal
+/// - an address plus or minus a constant integer
+///
+/// Everything else, including function calls, assignments, and reading the value of a variable,
+/// is `NotConstant`.
+///
+/// Integers are computed in 64 bits and then converted to the type of the expression.
+/// Unsigned integers wrap around; signed integers overflow with an error, except after a cast or `<<`.
+///
+/// This is the evaluator used for array sizes, bit-field widths, enum values, `case` labels,
+/// static initializers, and `#if`.
+pub fn const_eval(expr: &Expr, target: &Target) -> Result<ConstValue, ConstEvalError> {
+    let not_constant =
+        |expr: &Expr| ConstEvalError::NotConstant(Locatable::new(expr.to_string(), expr.location));
+    // folding rebuilds the expression, so work on a copy
+    let scratch = Arena::new();
+    let folded = expr
+        .clone_in(&scratch)
+        .const_fold()
+        .map_err(ConstEvalError::Arithmetic)?;
+    match &folded.expr {
+        ExprType::Literal(token) if !folded.lval => {
+            Ok(literal_value(token.clone(), &folded.ctype, target))
+        }
+        // arrays and functions decay to their address
+        _ if !folded.lval || matches!(folded.ctype, Type::Array(..) | Type::Function(_)) => {
+            match address_value(&folded, target) {
+                Some(value) => Ok(value),
+                None => Err(not_constant(first_non_constant(&folded, target))),
+            }
+        }
+        // reads the value of a variable
+        _ => Err(not_constant(&folded)),
+    }
+}
+
+fn literal_value(token: Literal, ctype: &Type, target: &Target) -> ConstValue {
+    let signed = ctype.is_signed() && *ctype != Type::Bool;
+    match token {
+        Int(i) if signed => ConstValue::Int(i),
+        UnsignedInt(u) if signed => ConstValue::Int(u as i64),
+        Char(c) if signed => ConstValue::Int((c as i8).into()),
+        Int(i) => ConstValue::Uint(i as u64),
+        UnsignedInt(u) if ctype.is_pointer() => {
+            let shift = 64 - target.pointer_bits();
+            ConstValue::Uint(u << shift >> shift)
+        }
+        UnsignedInt(u) => ConstValue::Uint(u),
+        Char(c) => ConstValue::Uint(c.into()),
+        Float(f) => ConstValue::Float(f),
+        SingleFloat(f) => ConstValue::Float(f.into()),
+        Str(s) => ConstValue::Str(s),
+        // elements that are too large were already replaced with errors by the analyzer
+        WideStr(..) => match ArchData::new(target.triple()).string_data(&token) {
+            Ok(data) => ConstValue::Str(data.bytes),
+            Err(unit) => unreachable!("wide string element {:#x} was never checked", unit),
+        },
+    }
+}
+
+/// The address `expr` evaluates to, if it is an address constant (6.6p9).
+///
+/// In the HIR, an `Id` evaluates to the address of the variable,
+/// and only `Deref` loads the value at an address.
+fn address_value(expr: &Expr, target: &Target) -> Option<ConstValue> {
+    match &expr.expr {
+        ExprType::Id(symbol) => Some(ConstValue::Address {
+            symbol: *symbol,
+            offset: 0,
+        }),
+        // `"hello"` or `(int *)0`
+        ExprType::Literal(token) => Some(literal_value(token.clone(), &expr.ctype, target)),
+        ExprType::StaticRef(inner) | ExprType::Noop(inner) => address_value(inner, target),
+        ExprType::Cast(inner) if expr.ctype.is_pointer() => address_value(inner, target),
+        // `(size_t)&((struct s *)0)->member`, which is how `offsetof` is usually defined.
+        // Only addresses that are really integers can be converted; the address of a symbol isn't known yet.
+        ExprType::Cast(inner) if expr.ctype.is_integral() => {
+            let address = match address_value(inner, target)? {
+                ConstValue::Int(i) => Int(i),
+                ConstValue::Uint(u) => UnsignedInt(u),
+                _ => return None,
+            };
+            let value = const_cast(&address, &expr.ctype)?;
+            let value = fit_to_type(value, &expr.ctype, true).ok()?;
+            Some(literal_value(value, &expr.ctype, target))
+        }
+        ExprType::Member(inner, member) => {
+            let member_offset = match &inner.ctype {
+                Type::Struct(stype) => stype.offset(*member),
+                _ => 0,
+            };
+            offset_address(address_value(inner, target)?, member_offset as i64)
+        }
+        ExprType::Binary(op @ BinaryOp::Add, base, offset)
+        | ExprType::Binary(op @ BinaryOp::Sub, base, offset)
+            if expr.ctype.is_pointer() =>
+        {
+            let offset = match offset.expr {
+                ExprType::Literal(Int(i)) => i,
+                ExprType::Literal(UnsignedInt(u)) => u as i64,
+                _ => return None,
+            };
+            let offset = if *op == BinaryOp::Add {
+                offset
+            } else {
+                offset.wrapping_neg()
+            };
+            offset_address(address_value(base, target)?, offset)
+        }
+        _ => None,
+    }
+}
+
+fn offset_address(address: ConstValue, by: i64) -> Option<ConstValue> {
+    match address {
+        ConstValue::Address { symbol, offset } => Some(ConstValue::Address {
+            symbol,
+            offset: offset.wrapping_add(by),
+        }),
+        // `(struct s *)0 + 1`
+        ConstValue::Uint(address) => Some(ConstValue::Uint(address.wrapping_add(by as u64))),
+        // TODO: allow `"hello" + 1`
+        _ => None,
+    }
+}
+
+/// The innermost subexpression of `expr` that keeps it from being constant.
+fn first_non_constant<'a, 'hir>(expr: &'a Expr<'hir>, target: &Target) -> &'a Expr<'hir> {
+    let children: Vec<&Expr> = match &expr.expr {
+        ExprType::Negate(inner)
+        | ExprType::BitwiseNot(inner)
+        | ExprType::Noop(inner)
+        | ExprType::Cast(inner)
+        | ExprType::StaticRef(inner)
+        | ExprType::Member(inner, _) => vec![inner],
+        ExprType::Binary(BinaryOp::Assign, ..) => vec![],
+        ExprType::Binary(_, left, right) | ExprType::Comma(left, right) => vec![left, right],
+        ExprType::Ternary(condition, then, otherwise) => vec![condition, then, otherwise],
+        // the address being loaded from may well be constant; reading from it is what isn't
+        ExprType::Deref(_)
+        | ExprType::FuncCall(..)
+        | ExprType::Let(..)
+        | ExprType::Id(_)
+        | ExprType::Literal(_)
+        | ExprType::Sizeof(_) => vec![],
+    };
+    let is_constant = |child: &Expr| address_value(child, target).is_some();
+    match children.into_iter().find(|child| !is_constant(child)) {
+        Some(child) => first_non_constant(child, target),
+        None => expr,
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::analyze::test::analyze_expr;
-    use crate::data::hir::Expr;
+    use super::{const_eval, ConstEvalError, ConstValue};
+    use crate::analyze::test::{analyze_expr, arena};
+    use crate::data::hir::{Expr, Variable};
     use crate::data::*;
+    use crate::{PureAnalyzer, Target};
 
-    fn test_const_fold(s: &str) -> CompileResult<Expr> {
+    fn test_const_fold(s: &str) -> CompileResult<Expr<'static>> {
         analyze_expr(s).unwrap().const_fold()
     }
     fn assert_fold(original: &str, expected: &str) {
@@ -671,6 +898,13 @@ mod tests {
                 .data,
             SemanticError::ConstOverflow { is_positive: true }.into()
         );
+        assert_eq!(
+            test_const_fold("(int)(-0x7fffffff - 1) / (int)-1")
+                .unwrap_err()
+                .data,
+            SemanticError::ConstOverflow { is_positive: true }.into()
+        );
+        assert_fold("(int)(-0x7fffffff - 1) / (int)1", "(int)(-0x7fffffff - 1)");
     }
 
     #[test]
@@ -687,6 +921,12 @@ mod tests {
                 .data,
             SemanticError::ConstOverflow { is_positive: false }.into()
         );
+        assert_eq!(
+            test_const_fold("(int)(-0x7fffffff - 1) % (int)-1")
+                .unwrap_err()
+                .data,
+            SemanticError::ConstOverflow { is_positive: false }.into()
+        );
     }
 
     #[test]
@@ -725,6 +965,9 @@ mod tests {
     fn test_right_shift() {
         assert_fold("8 >> 0", "8");
         assert_fold("32 >> 5", "1");
+        // the shift is compared to the width in bits, not bytes
+        assert_fold("1024 >> 9", "2");
+        assert_fold("0x8000000000000000u >> 63", "1u");
         assert_eq!(
             test_const_fold("8 >> -1").unwrap_err().data,
             SemanticError::NegativeShift { is_left: false }.into()
@@ -767,4 +1010,127 @@ mod tests {
         assert_fold("(long)'0'", "48");
         assert_fold("(unsigned short)'0'", "48u");
     }
+
+    fn eval(s: &str) -> Result<ConstValue, ConstEvalError> {
+        const_eval(&analyze_expr(s).unwrap(), &Target::host())
+    }
+
+    #[test]
+    fn const_eval_values() {
+        use ConstValue::*;
+
+        for (expr, expected) in vec![
+            ("1 + 2", Int(3)),
+            ("-1", Int(-1)),
+            ("~0", Int(-1)),
+            ("1 ? 2 : 3", Int(2)),
+            ("(1, 2)", Int(2)),
+            ("3 > 2", Int(1)),
+            ("sizeof(long)", Uint(8)),
+            ("'a'", Int(97)),
+            ("1u + 2", Uint(3)),
+            // unsigned wraparound
+            // integer literals are `long` or `unsigned long`
+            ("0u - 1", Uint(u64::MAX)),
+            ("-1u", Uint(u64::MAX)),
+            ("(unsigned)0 - (unsigned)1", Uint(0xffff_ffff)),
+            ("(unsigned)4294967295 + (unsigned)2", Uint(1)),
+            ("-(unsigned short)1", Int(-1)),
+            // casts that truncate
+            ("(unsigned)-1", Uint(0xffff_ffff)),
+            ("(unsigned char)259", Uint(3)),
+            ("(signed char)255", Int(-1)),
+            ("(short)70000", Int(4464)),
+            ("(int)4294967297L", Int(1)),
+            ("(int)2.9", Int(2)),
+            ("(_Bool)2", Uint(1)),
+            ("(_Bool)0.1", Uint(1)),
+            ("!2 + (1 && 2)", Int(1)),
+            ("(long)(int)-1", Int(-1)),
+            ("(unsigned long)(unsigned)-1", Uint(0xffff_ffff)),
+            ("(int)1 << (int)31", Int(i64::from(i32::MIN))),
+            ("(void *)0", Uint(0)),
+            // `offsetof`
+            ("(long)((int *)0 + 2)", Int(8)),
+            ("(unsigned char)((char *)0 + 257)", Uint(1)),
+            ("(int)&((int *)0)[3]", Int(12)),
+        ] {
+            assert_eq!(eval(expr), Ok(expected), "{}", expr);
+        }
+        assert_eq!(eval("1.5 * 2"), Ok(Float(3.0)));
+        assert_eq!(eval("\"hi\""), Ok(Str(b"hi\0".to_vec())));
+    }
+
+    #[test]
+    fn const_eval_errors() {
+        let arithmetic = |expr| match eval(expr) {
+            Err(ConstEvalError::Arithmetic(err)) => err.data,
+            other => panic!("expected arithmetic error for {}, got {:?}", expr, other),
+        };
+        assert_eq!(arithmetic("1 / 0"), SemanticError::DivideByZero.into());
+        assert_eq!(arithmetic("1 % 0"), SemanticError::DivideByZero.into());
+        assert_eq!(
+            arithmetic("(int)2147483647 + (int)1"),
+            SemanticError::ConstOverflow { is_positive: true }.into()
+        );
+        assert_eq!(
+            arithmetic("(int)-2147483647 - (int)2"),
+            SemanticError::ConstOverflow { is_positive: false }.into()
+        );
+        assert_eq!(
+            arithmetic("0x7fffffffffffffffL * 2"),
+            SemanticError::ConstOverflow { is_positive: true }.into()
+        );
+    }
+
+    #[test]
+    fn const_eval_addresses() {
+        let variable = |name: &str, ctype| {
+            Variable {
+                id: name.into(),
+                ctype,
+                qualifiers: Default::default(),
+                storage_class: Default::default(),
+                location: Location::default(),
+            }
+            .insert()
+        };
+        let a = variable(
+            "a",
+            Type::Array(Box::new(Type::Int(true)), types::ArrayType::Fixed(4)),
+        );
+        let x = variable("x", Type::Int(true));
+        let f = variable(
+            "f",
+            Type::Function(types::FunctionType {
+                return_type: Box::new(Type::Int(true)),
+                params: vec![],
+                varargs: false,
+            }),
+        );
+        let mut analyzer = PureAnalyzer::with_scope(arena(), vec![a, x, f]);
+        let mut eval = |expr| {
+            let expr = analyzer.parse_expr_str(expr).unwrap();
+            const_eval(&expr, &Target::host())
+        };
+        let address = |symbol, offset| Ok(ConstValue::Address { symbol, offset });
+        assert_eq!(eval("&x"), address(x, 0));
+        assert_eq!(eval("a"), address(a, 0));
+        assert_eq!(eval("a + 3"), address(a, 12));
+        assert_eq!(eval("&a[1]"), address(a, 4));
+        assert_eq!(eval("(char *)&x"), address(x, 0));
+        assert_eq!(eval("f"), address(f, 0));
+
+        let not_constant = |result| match result {
+            Err(ConstEvalError::NotConstant(expr)) => expr.location.span,
+            other => panic!("expected a non-constant expression, got {:?}", other),
+        };
+        // points to `x`, not the whole expression
+        assert_eq!(not_constant(eval("1 + x")), (4..5).into());
+        assert_eq!(not_constant(eval("2 * (1 + f())")), (9..12).into());
+        assert_eq!(not_constant(eval("x = 1")), (0..5).into());
+        assert_eq!(not_constant(eval("sizeof(int) + f()")), (14..17).into());
+        // the address of a variable isn't known until link time
+        assert!(eval("(long)&x").is_err());
+    }
 }
@@ -0,0 +1,119 @@
+//! Internal compiler errors (ICEs), which are panics caused by bugs in the compiler itself.
+//!
+//! The parser, analyzer, and code generator record the location of each construct before processing it,
+//! so that a panic can be reported as an error pointing to the code that triggered it
+//! instead of unwinding into the caller.
+
+use std::any::Any;
+use std::cell::Cell;
+use std::panic::{self, AssertUnwindSafe};
+
+use crate::data::{error::Error, CompileError, CompileResult, Location};
+
+thread_local! {
+    /// The construct the compiler is processing on this thread, if any
+    static CURRENT_LOCATION: Cell<Option<Location>> = const { Cell::new(None) };
+}
+
+/// Record that the compiler is about to process the code at `location`.
+#[inline]
+pub(crate) fn set_location(location: Location) {
+    CURRENT_LOCATION.with(|current| current.set(Some(location)));
+}
+
+/// Run `f`, turning a panic into an internal compiler error.
+///
+/// The error is reported at the last location passed to `set_location` while running `f`,
+/// or at `fallback` if there wasn't one.
+pub(crate) fn catch<T>(fallback: Location, f: impl FnOnce() -> T) -> Result<T, CompileError> {
+    CURRENT_LOCATION.with(|current| current.set(None));
+    panic::catch_unwind(AssertUnwindSafe(f)).map_err(|payload| {
+        let location = CURRENT_LOCATION.with(Cell::take).unwrap_or(fallback);
+        location.error(Error::InternalCompilerError(panic_message(&*payload)))
+    })
+}
+
+/// Wrap an iterator of results so that a panic is returned as an internal compiler error.
+///
+/// The iterator stops after the first panic, since the compiler may be in an inconsistent state.
+pub(crate) fn catch_panics<I>(iter: I, fallback: Location) -> CatchPanics<I> {
+    CatchPanics {
+        iter,
+        fallback,
+        panicked: false,
+    }
+}
+
+/// See `catch_panics`.
+pub(crate) struct CatchPanics<I> {
+    iter: I,
+    fallback: Location,
+    panicked: bool,
+}
+
+impl<I> CatchPanics<I> {
+    pub(crate) fn get_mut(&mut self) -> &mut I {
+        &mut self.iter
+    }
+}
+
+impl<T, I: Iterator<Item = CompileResult<T>>> Iterator for CatchPanics<I> {
+    type Item = CompileResult<T>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.panicked {
+            return None;
+        }
+        let iter = &mut self.iter;
+        match catch(self.fallback, || iter.next()) {
+            Ok(next) => next,
+            Err(ice) => {
+                self.panicked = true;
+                Some(Err(ice))
+            }
+        }
+    }
+}
+
+/// The message passed to `panic!`, if it was a string.
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        (*message).to_owned()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn catch_panic() {
+        let fallback = Location::default();
+        let location = Location {
+            span: (5..10).into(),
+            ..fallback
+        };
+        assert_eq!(catch(fallback, || 1), Ok(1));
+        let err = catch(fallback, || -> () { unimplemented!("aggregate literals") }).unwrap_err();
+        assert_eq!(err.location, fallback);
+        assert_eq!(
+            err.data,
+            Error::InternalCompilerError("not implemented: aggregate literals".into())
+        );
+        let err = catch(fallback, || {
+            set_location(location);
+            panic!("{} {}", "formatted", "message");
+        })
+        .unwrap_err();
+        assert_eq!(err.location, location);
+        assert_eq!(
+            err.data,
+            Error::InternalCompilerError("formatted message".into())
+        );
+        // the location is reset each time
+        assert_eq!(catch(fallback, || panic!()).unwrap_err().location, fallback);
+    }
+}
@@ -108,6 +108,26 @@ impl From<String> for InternedStr {
     }
 }
 
+/// `InternedStr`s are written out as the string they stand for,
+/// since the ids depend on the order strings were interned.
+#[cfg(feature = "serialize")]
+mod serde_impl {
+    use super::InternedStr;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    impl Serialize for InternedStr {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_str(get_str!(self))
+        }
+    }
+
+    impl<'de> Deserialize<'de> for InternedStr {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            String::deserialize(deserializer).map(InternedStr::get_or_intern)
+        }
+    }
+}
+
 #[cfg(test)]
 mod proptest_impl {
     use super::InternedStr;
@@ -0,0 +1,184 @@
+//! Passing and returning structs and unions by value, following the System V x86-64 psABI (3.2.3).
+//!
+//! An aggregate of 16 bytes or less is split into eightbytes. Each eightbyte goes in a general purpose
+//! register if any of its members are integers or pointers, and in a floating point register otherwise.
+//! Larger aggregates are copied onto the stack as arguments,
+//! and returned through a hidden pointer passed by the caller.
+
+use cranelift::codegen::ir::{types, Type as IrType};
+
+use crate::data::types::ArrayType;
+use crate::data::Type;
+
+/// The size of each piece of an aggregate, in bytes
+pub(super) const EIGHTBYTE: u64 = 8;
+
+/// `rdi`, `rsi`, `rdx`, `rcx`, `r8`, and `r9`
+const INT_ARGUMENT_REGISTERS: usize = 6;
+/// `xmm0` through `xmm7`
+const FLOAT_ARGUMENT_REGISTERS: usize = 8;
+
+/// How a struct or union is passed to or returned from a function.
+#[derive(Clone, Debug, PartialEq)]
+pub(super) enum Class {
+    /// In registers, as an `I64` or `F64` for each eightbyte
+    Registers(Vec<IrType>),
+    /// On the stack as an argument, or through a hidden pointer as a return value
+    Memory,
+}
+
+/// Classify an aggregate the way the psABI does.
+pub(super) fn classify(ctype: &Type) -> Class {
+    let size = ctype
+        .sizeof()
+        .expect("structs passed by value should be complete");
+    if size > 2 * EIGHTBYTE {
+        return Class::Memory;
+    }
+    let mut classes = vec![None; eightbytes(size)];
+    classify_members(ctype, 0, &mut classes);
+    // an eightbyte with no members in it is only padding, so it doesn't matter where it goes
+    Class::Registers(
+        classes
+            .into_iter()
+            .map(|class| class.unwrap_or(types::I64))
+            .collect(),
+    )
+}
+
+/// Merge the class of every scalar in `ctype`, which starts `offset` bytes into the aggregate.
+fn classify_members(ctype: &Type, offset: u64, classes: &mut [Option<IrType>]) {
+    let size = |ctype: &Type| ctype.sizeof().expect("members should have a valid size");
+    match ctype {
+        Type::Struct(stype) => {
+            let mut member_offset: u64 = 0;
+            for member in stype.members().iter() {
+                let align = member
+                    .ctype
+                    .alignof()
+                    .expect("members should have a valid alignment");
+                member_offset = member_offset.div_ceil(align) * align;
+                classify_members(&member.ctype, offset + member_offset, classes);
+                member_offset += size(&member.ctype);
+            }
+        }
+        Type::Union(stype) => {
+            for member in stype.members().iter() {
+                classify_members(&member.ctype, offset, classes);
+            }
+        }
+        Type::Array(element, ArrayType::Fixed(len)) => {
+            let element_size = size(element);
+            for i in 0..*len {
+                classify_members(element, offset + i * element_size, classes);
+            }
+        }
+        // a flexible array member takes up no space
+        Type::Array(_, ArrayType::Unbounded) => {}
+        scalar => {
+            let class = &mut classes[(offset / EIGHTBYTE) as usize];
+            // an eightbyte with both integers and floats goes in a general purpose register
+            *class = if scalar.is_floating() && *class != Some(types::I64) {
+                Some(types::F64)
+            } else {
+                Some(types::I64)
+            };
+        }
+    }
+}
+
+/// The number of eightbytes needed to hold `size` bytes.
+pub(super) fn eightbytes(size: u64) -> usize {
+    size.div_ceil(EIGHTBYTE) as usize
+}
+
+/// The IR type of each eightbyte of `ctype` when it's passed as an argument.
+///
+/// Aggregates in memory are copied to the stack an eightbyte at a time.
+pub(super) fn argument_types(ctype: &Type) -> Vec<IrType> {
+    match classify(ctype) {
+        Class::Registers(types) => types,
+        Class::Memory => {
+            let size = ctype
+                .sizeof()
+                .expect("structs passed by value should be complete");
+            vec![types::I64; eightbytes(size)]
+        }
+    }
+}
+
+/// A value passed to a function, in the order of the IR signature.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(super) enum Arg {
+    /// Eightbyte number `eightbyte` of parameter number `param`, or the whole parameter if it's a scalar
+    Param {
+        param: usize,
+        eightbyte: usize,
+        ir_type: IrType,
+    },
+    /// An unused general purpose register, so that the integers after it go on the stack
+    Padding,
+}
+
+impl Arg {
+    pub(super) fn ir_type(self) -> IrType {
+        match self {
+            Arg::Param { ir_type, .. } => ir_type,
+            Arg::Padding => types::I64,
+        }
+    }
+}
+
+/// The order to pass `params` in, after the hidden pointer to the return value if `struct_return` is set.
+///
+/// Cranelift gives integers the next general purpose register and floats the next floating point register,
+/// then puts the rest on the stack in order, which is what the psABI does for scalars.
+/// But an aggregate that doesn't fit in the registers that are left goes on the stack as a whole,
+/// and the parameters after it can still use those registers.
+/// So everything passed in registers comes first, then enough padding to use up
+/// the general purpose registers, then everything passed on the stack.
+pub(super) fn arguments<'a>(
+    params: impl Iterator<Item = &'a Type>,
+    struct_return: bool,
+) -> Vec<Arg> {
+    let (mut ints, mut floats) = (if struct_return { 1 } else { 0 }, 0);
+    let (mut registers, mut stack) = (Vec::new(), Vec::new());
+    for (param, ctype) in params.enumerate() {
+        let arg = |eightbyte, ir_type| Arg::Param {
+            param,
+            eightbyte,
+            ir_type,
+        };
+        if !super::is_aggregate(ctype) {
+            let ir_type = ctype.as_ir_type();
+            if ir_type.is_float() && floats < FLOAT_ARGUMENT_REGISTERS {
+                floats += 1;
+                registers.push(arg(0, ir_type));
+            } else if !ir_type.is_float() && ints < INT_ARGUMENT_REGISTERS {
+                ints += 1;
+                registers.push(arg(0, ir_type));
+            } else {
+                stack.push(arg(0, ir_type));
+            }
+            continue;
+        }
+        let ir_types = argument_types(ctype);
+        let needs_floats = ir_types.iter().filter(|ir_type| ir_type.is_float()).count();
+        let needs_ints = ir_types.len() - needs_floats;
+        let fits = ints + needs_ints <= INT_ARGUMENT_REGISTERS
+            && floats + needs_floats <= FLOAT_ARGUMENT_REGISTERS;
+        if fits && classify(ctype) != Class::Memory {
+            ints += needs_ints;
+            floats += needs_floats;
+            registers.extend(ir_types.into_iter().enumerate().map(|(i, t)| arg(i, t)));
+        } else {
+            // only the bits matter on the stack, so these are never in floating point registers
+            stack.extend((0..ir_types.len()).map(|i| arg(i, types::I64)));
+        }
+    }
+    if stack.iter().any(|arg| !arg.ir_type().is_float()) {
+        registers.extend((ints..INT_ARGUMENT_REGISTERS).map(|_| Arg::Padding));
+    }
+    registers.extend(stack);
+    registers
+}
@@ -1,13 +1,15 @@
-use cranelift::codegen::ir::{condcodes, types, MemFlags};
+use cranelift::codegen::ir::{condcodes, types, MemFlags, TrapCode};
 use cranelift::prelude::{FunctionBuilder, InstBuilder, Type as IrType, Value as IrValue};
 use cranelift_module::Backend;
 
 use super::{Compiler, Id};
+use crate::analyze::BUILTIN_UNREACHABLE;
 use crate::data::*;
 use crate::data::{
-    hir::{BinaryOp, Expr, ExprType, Symbol, Variable},
+    hir::{BinaryOp, Declaration, Expr, ExprType, Initializer, Symbol, Variable},
     lex::{ComparisonToken, Literal},
 };
+use crate::ice;
 
 type IrResult = CompileResult<Value>;
 
@@ -30,6 +32,7 @@ impl<B: Backend> Compiler<B> {
     pub(super) fn compile_expr(&mut self, expr: Expr, builder: &mut FunctionBuilder) -> IrResult {
         let expr = expr.const_fold()?;
         let location = expr.location;
+        ice::set_location(location);
         let ir_type = if expr.lval {
             Type::ptr_type()
         } else {
@@ -43,20 +46,38 @@ impl<B: Backend> Compiler<B> {
 
             // unary operators
             ExprType::Deref(pointer) => {
-                let val = self.compile_expr(*pointer, builder)?;
+                // `*p` where `p` is a `restrict` parameter may already be known
+                let restrict = match expr.ctype {
+                    Type::Struct(_) | Type::Union(_) => None,
+                    _ => self.restrict.location(pointer),
+                };
+                if let Some(location) = restrict {
+                    if let Some(ir_val) = self.restrict.get(location, ir_type, builder) {
+                        return Ok(Value {
+                            ir_type,
+                            ctype: expr.ctype,
+                            ir_val,
+                        });
+                    }
+                }
+                let val = self.compile_expr(pointer.take(), builder)?;
                 let flags = MemFlags::new();
+                let ir_val = super::load(ir_type, flags, val.ir_val, builder);
+                if let Some(location) = restrict {
+                    self.restrict.loaded(location, ir_val, ir_type, builder);
+                }
                 Ok(Value {
                     ir_type,
                     ctype: expr.ctype,
-                    ir_val: builder.ins().load(ir_type, flags, val.ir_val, 0),
+                    ir_val,
                 })
             }
             // NOTE: this may be an implicit cast (float f = 1.2) not an explicit cast (1 + (int)1.2)
             // NOTE: it may also be a widening conversion (1 + 1.2)
-            ExprType::Cast(orig) => self.cast(*orig, expr.ctype, builder),
-            ExprType::Negate(expr) => self.negate(*expr, builder),
+            ExprType::Cast(orig) => self.cast(orig.take(), expr.ctype, builder),
+            ExprType::Negate(expr) => self.negate(expr.take(), builder),
             ExprType::BitwiseNot(expr) => self.unary_op(
-                *expr,
+                expr.take(),
                 builder,
                 |ir_val, ir_type, _, builder| match ir_type {
                     ty if ty.is_int() => builder.ins().bnot(ir_val),
@@ -65,32 +86,36 @@ impl<B: Backend> Compiler<B> {
             ),
             // binary operators
             ExprType::Binary(BinaryOp::LogicalOr, left, right) => {
-                self.logical_expr(*left, *right, false, builder)
+                self.logical_expr(left.take(), right.take(), false, builder)
             }
             ExprType::Binary(BinaryOp::LogicalAnd, left, right) => {
-                self.logical_expr(*left, *right, true, builder)
+                self.logical_expr(left.take(), right.take(), true, builder)
             }
             ExprType::Binary(BinaryOp::Assign, left, right) => {
-                self.assignment(*left, *right, builder)
+                self.assignment(left.take(), right.take(), builder)
             }
             ExprType::Binary(op, left, right) => {
-                self.binary_assign_op(*left, *right, expr.ctype, op, builder)
+                self.binary_assign_op(left.take(), right.take(), expr.ctype, op, builder)
             }
-            ExprType::FuncCall(func, args) => match func.expr {
-                ExprType::Id(var) => self.call(FuncCall::Named(var), func.ctype, args, builder),
-                _ => {
+            ExprType::FuncCall(func, args) => match func.take() {
+                Expr {
+                    expr: ExprType::Id(var),
+                    ctype,
+                    ..
+                } => self.call(FuncCall::Named(var), ctype, args, location, builder),
+                func => {
                     let ctype = func.ctype.clone();
-                    let val = self.compile_expr(*func, builder)?;
-                    self.call(FuncCall::Indirect(val), ctype, args, builder)
+                    let val = self.compile_expr(func, builder)?;
+                    self.call(FuncCall::Indirect(val), ctype, args, location, builder)
                 }
             },
             ExprType::Comma(left, right) => {
-                self.compile_expr(*left, builder)?;
-                self.compile_expr(*right, builder)
+                self.compile_expr(left.take(), builder)?;
+                self.compile_expr(right.take(), builder)
             }
             ExprType::Member(cstruct, id) => {
                 let ctype = cstruct.ctype.clone();
-                let pointer = self.compile_expr(*cstruct, builder)?;
+                let pointer = self.compile_expr(cstruct.take(), builder)?;
                 let offset = ctype
                     .member_offset(id)
                     .expect("only structs and unions can have members");
@@ -101,41 +126,22 @@ impl<B: Backend> Compiler<B> {
                     ctype,
                 })
             }
-            ExprType::PostIncrement(lval, increase) => {
-                let lval = self.compile_expr(*lval, builder)?;
-                let loaded_ctype = match lval.ctype {
-                    Type::Pointer(t, _) => *t,
-                    _ => lval.ctype,
+            ExprType::Let(symbol, init, body) => {
+                let decl = Declaration {
+                    symbol,
+                    init: Some(Initializer::Scalar(init)),
+                    attributes: Default::default(),
                 };
-                let ir_type = loaded_ctype.as_ir_type();
-                let previous_value = Value {
-                    ir_val: builder.ins().load(ir_type, MemFlags::new(), lval.ir_val, 0),
-                    ir_type,
-                    ctype: loaded_ctype,
-                };
-
-                let addend = if increase { 1 } else { -1 };
-                let (addend_ir, add_func): (_, fn(_, _, _) -> _) = match previous_value.ctype {
-                    Type::Double => (builder.ins().f64const(addend as f64), InstBuilder::fadd),
-                    Type::Float => (builder.ins().f32const(addend as f32), InstBuilder::fadd),
-                    _ => (
-                        builder.ins().iconst(previous_value.ir_type, addend),
-                        InstBuilder::iadd,
-                    ),
-                };
-                let new_value = add_func(builder.ins(), previous_value.ir_val, addend_ir);
-                builder
-                    .ins()
-                    .store(MemFlags::new(), new_value, lval.ir_val, 0);
-                Ok(previous_value)
+                self.declare_stack(decl, expr.location, builder)?;
+                self.compile_expr(body.take(), builder)
             }
             ExprType::Noop(inner) => {
-                let mut val = self.compile_expr(*inner, builder)?;
+                let mut val = self.compile_expr(inner.take(), builder)?;
                 val.ctype = expr.ctype;
                 Ok(val)
             }
             ExprType::Ternary(condition, left, right) => {
-                self.ternary(*condition, *left, *right, builder)
+                self.ternary(condition.take(), left.take(), right.take(), builder)
             }
             ExprType::Sizeof(_) => unimplemented!("sizeof variable length arrays"),
             ExprType::StaticRef(_) => {
@@ -222,7 +228,11 @@ impl<B: Backend> Compiler<B> {
             (Literal::Float(f), types::F32) => builder.ins().f32const(f as f32),
             (Literal::Float(f), types::F64) => builder.ins().f64const(f),
             (Literal::Char(c), _) => builder.ins().iconst(ir_type, i64::from(c)),
-            (Literal::Str(string), _) => {
+            (string @ Literal::Str(_), _) | (string @ Literal::WideStr(..), _) => {
+                let string = self
+                    .arch
+                    .string_data(&string)
+                    .expect("elements that are too large should be caught by the analyzer");
                 let str_id = self.compile_string(string, location)?;
                 let str_addr = self.module.declare_data_in_func(str_id, builder.func);
                 builder.ins().global_value(Type::ptr_type(), str_addr)
@@ -261,13 +271,17 @@ impl<B: Backend> Compiler<B> {
             self.compile_expr(left, builder)?,
             self.compile_expr(right, builder)?,
         );
-        Self::binary_assign_ir(left, right, ctype, op, builder)
+        Self::binary_assign_ir(left, right, ctype, op, self.optimize, builder)
     }
+    // NOTE: integer division by a runtime value is lowered to the native instruction,
+    // so `x / 0` and `INT_MIN / -1` trap (with SIGFPE on x86).
+    // When both operands are constants, these are diagnosed by `const_fold` instead.
     fn binary_assign_ir(
         left: Value,
         right: Value,
         ctype: Type,
         op: BinaryOp,
+        optimize: bool,
         builder: &mut FunctionBuilder,
     ) -> IrResult {
         use cranelift::codegen::ir::InstBuilder as b;
@@ -275,6 +289,17 @@ impl<B: Backend> Compiler<B> {
         assert_eq!(left.ir_type, right.ir_type);
         let ir_type = ctype.as_ir_type();
         let signed = ctype.is_signed();
+        if optimize && ir_type.is_int() {
+            if let Some(ir_val) =
+                Self::div_by_constant(left.ir_val, right.ir_val, ir_type, op, signed, builder)
+            {
+                return Ok(Value {
+                    ir_val,
+                    ir_type,
+                    ctype,
+                });
+            }
+        }
         let func = match (op, ir_type, signed) {
             (Add, ty, _) if ty.is_int() => b::iadd,
             (Add, ty, _) if ty.is_float() => b::fadd,
@@ -311,6 +336,57 @@ impl<B: Backend> Compiler<B> {
             ctype,
         })
     }
+    /// Lower `left / right` or `left % right` without a division instruction
+    /// if `right` is a constant.
+    ///
+    /// Unsigned division by a power of two becomes a shift (or a mask for `%`).
+    /// Signed division becomes `sdiv_imm`, which cranelift's pre-optimization pass
+    /// replaces with a multiplication by the inverse of the divisor.
+    /// Returns `None` if this isn't possible.
+    fn div_by_constant(
+        left: IrValue,
+        right: IrValue,
+        ir_type: IrType,
+        op: BinaryOp,
+        signed: bool,
+        builder: &mut FunctionBuilder,
+    ) -> Option<IrValue> {
+        use cranelift::codegen::ir::{InstructionData, Opcode, ValueDef};
+
+        let divisor: i64 = match builder.func.dfg.value_def(right) {
+            ValueDef::Result(inst, _) => match builder.func.dfg[inst] {
+                InstructionData::UnaryImm {
+                    opcode: Opcode::Iconst,
+                    imm,
+                } => imm.into(),
+                _ => return None,
+            },
+            ValueDef::Param(..) => return None,
+        };
+        // the immediate is not necessarily sign- or zero-extended, so do that here
+        let unused_bits = 64 - ir_type.bits();
+        let ins = builder.ins();
+        if signed {
+            let divisor = (divisor << unused_bits) >> unused_bits;
+            match (op, divisor) {
+                // leave these to trap at runtime
+                (_, 0) | (_, -1) => None,
+                (BinaryOp::Div, _) => Some(ins.sdiv_imm(left, divisor)),
+                (BinaryOp::Mod, _) => Some(ins.srem_imm(left, divisor)),
+                _ => None,
+            }
+        } else {
+            let divisor = ((divisor as u64) << unused_bits) >> unused_bits;
+            if !divisor.is_power_of_two() {
+                return None;
+            }
+            match op {
+                BinaryOp::Div => Some(ins.ushr_imm(left, i64::from(divisor.trailing_zeros()))),
+                BinaryOp::Mod => Some(ins.band_imm(left, (divisor - 1) as i64)),
+                _ => None,
+            }
+        }
+    }
     fn cast(&mut self, expr: Expr, ctype: Type, builder: &mut FunctionBuilder) -> IrResult {
         // calculate this here before it's moved to `compile_expr`
         let orig_signed = expr.ctype.is_signed();
@@ -397,9 +473,14 @@ impl<B: Backend> Compiler<B> {
                 let int_val = Self::cast_ir(b, types::I32, val, false, true, builder);
                 Self::cast_ir(types::I8, f, int_val, true, true, builder)
             }
+            // any non-zero value, including values between 0 and 1, converts to `true`
             (f, b) if b.is_bool() && f.is_float() => {
-                let int_val = Self::cast_ir(f, types::I32, val, true, true, builder);
-                Self::cast_ir(types::I8, b, int_val, true, false, builder)
+                let zero = if f == types::F32 {
+                    builder.ins().f32const(0.0)
+                } else {
+                    builder.ins().f64const(0.0)
+                };
+                builder.ins().fcmp(condcodes::FloatCC::NotEqual, val, zero)
             }
             _ => unreachable!("cast from {} to {}", from, to),
         }
@@ -466,6 +547,7 @@ impl<B: Backend> Compiler<B> {
     fn assignment(&mut self, lval: Expr, rval: Expr, builder: &mut FunctionBuilder) -> IrResult {
         let ctype = lval.ctype.clone();
         let location = lval.location;
+        let restrict = self.restrict.location(&lval);
         let (target, value) = (
             self.compile_expr(lval, builder)?,
             self.compile_expr(rval, builder)?,
@@ -478,70 +560,265 @@ impl<B: Backend> Compiler<B> {
                 .expect("if sizeof() succeeds so should alignof()")
                 .try_into()
                 .expect("align should never be more than 255 bytes");
-            builder.emit_small_memory_copy(
-                self.module.target_config(),
-                target.ir_val,
-                value.ir_val,
-                size,
-                align,
-                align,
-                // could be overlapping: `s = s;`
-                false,
-            );
+            self.copy_memory(target.ir_val, value.ir_val, size, align, builder);
+            self.restrict.stored(restrict, None, builder);
             return Ok(value);
         }
         // scalar assignment
         let target_val = target.ir_val;
-        builder
-            .ins()
-            .store(MemFlags::new(), value.ir_val, target_val, 0);
+        super::store(value.ir_val, target_val, builder);
+        let stored_type = builder.func.dfg.value_type(value.ir_val);
+        self.restrict
+            .stored(restrict, Some((value.ir_val, stored_type)), builder);
         Ok(value)
     }
+    /// Copy `size` bytes from `src` to `dest`, which may be the same: `s = s;`
+    ///
+    /// Cranelift calls `memmove` for large copies, which may not exist in a freestanding environment,
+    /// so in that case this copies in a loop instead.
+    pub(super) fn copy_memory(
+        &self,
+        dest: IrValue,
+        src: IrValue,
+        size: u64,
+        align: u8,
+        builder: &mut FunctionBuilder,
+    ) {
+        use condcodes::IntCC;
+
+        // the same size and threshold that `emit_small_memory_copy` uses
+        const THRESHOLD: u64 = 4;
+        let access_size = std::cmp::min(1 << size.trailing_zeros(), 8);
+        if !self.freestanding || size / access_size <= THRESHOLD {
+            let config = self.module.target_config();
+            builder.emit_small_memory_copy(config, dest, src, size, align, align, false);
+            return;
+        }
+        let (header, body, end) = (
+            builder.create_block(),
+            builder.create_block(),
+            builder.create_block(),
+        );
+        let offset = builder.append_block_param(header, Type::ptr_type());
+        let zero = builder.ins().iconst(Type::ptr_type(), 0);
+        builder.ins().jump(header, &[zero]);
+
+        builder.switch_to_block(header);
+        let done = builder.ins().icmp_imm(IntCC::Equal, offset, size as i64);
+        builder.ins().brnz(done, end, &[]);
+        builder.ins().jump(body, &[]);
+
+        // partially overlapping structs are undefined behavior (6.5.16.1p3),
+        // so there's no need to worry about the direction of the copy
+        builder.switch_to_block(body);
+        let int_type = IrType::int(access_size as u16 * 8).expect("access size is at most 8");
+        let src_addr = builder.ins().iadd(src, offset);
+        let dest_addr = builder.ins().iadd(dest, offset);
+        let val = builder.ins().load(int_type, MemFlags::new(), src_addr, 0);
+        builder.ins().store(MemFlags::new(), val, dest_addr, 0);
+        let next = builder.ins().iadd_imm(offset, access_size as i64);
+        builder.ins().jump(header, &[next]);
+
+        builder.switch_to_block(end);
+    }
+    /// Copy the struct or union of type `ctype` at `src` to a new stack slot and return its address.
+    pub(super) fn copy_aggregate(
+        &self,
+        src: IrValue,
+        ctype: &Type,
+        location: Location,
+        builder: &mut FunctionBuilder,
+    ) -> CompileResult<IrValue> {
+        use std::convert::TryFrom;
+
+        let err = |err: &str| location.with(err.to_string());
+        let size = ctype.sizeof().map_err(err)?;
+        let align = ctype.alignof().map_err(err)?;
+        let align = u8::try_from(align).expect("align should never be more than 255 bytes");
+        let addr = Self::eightbyte_slot(size, location, builder)?;
+        self.copy_memory(addr, src, size, align, builder);
+        Ok(addr)
+    }
+    /// Create a stack slot for an aggregate of `size` bytes that's passed an eightbyte at a time,
+    /// so the last eightbyte can be loaded or stored whole.
+    fn eightbyte_slot(
+        size: u64,
+        location: Location,
+        builder: &mut FunctionBuilder,
+    ) -> CompileResult<IrValue> {
+        use cranelift::codegen::ir::{StackSlotData, StackSlotKind};
+        use std::convert::TryFrom;
+
+        let size = super::abi::eightbytes(size) as u64 * super::abi::EIGHTBYTE;
+        let size = u32::try_from(size).map_err(|_| {
+            location.with("cannot pass items that are more than 4 GB by value".to_string())
+        })?;
+        let slot = builder.create_stack_slot(StackSlotData {
+            kind: StackSlotKind::ExplicitSlot,
+            size,
+            offset: None,
+        });
+        Ok(builder.ins().stack_addr(Type::ptr_type(), slot, 0))
+    }
+    /// Set `size` bytes starting at `dest` to 0, without calling `memset` in a freestanding environment.
+    pub(super) fn zero_memory(
+        &self,
+        dest: IrValue,
+        size: u64,
+        align: u8,
+        builder: &mut FunctionBuilder,
+    ) {
+        use condcodes::IntCC;
+
+        // the same threshold that `emit_small_memset` uses
+        const THRESHOLD: u64 = 4;
+        if !self.freestanding || size <= THRESHOLD * 8 {
+            let config = self.module.target_config();
+            builder.emit_small_memset(config, dest, 0, size, align);
+            return;
+        }
+        let (header, body, end) = (
+            builder.create_block(),
+            builder.create_block(),
+            builder.create_block(),
+        );
+        let offset = builder.append_block_param(header, Type::ptr_type());
+        let zero = builder.ins().iconst(Type::ptr_type(), 0);
+        builder.ins().jump(header, &[zero]);
+
+        builder.switch_to_block(header);
+        let done = builder.ins().icmp_imm(IntCC::Equal, offset, size as i64);
+        builder.ins().brnz(done, end, &[]);
+        builder.ins().jump(body, &[]);
+
+        builder.switch_to_block(body);
+        let byte = builder.ins().iconst(types::I8, 0);
+        let dest_addr = builder.ins().iadd(dest, offset);
+        builder.ins().store(MemFlags::new(), byte, dest_addr, 0);
+        let next = builder.ins().iadd_imm(offset, 1);
+        builder.ins().jump(header, &[next]);
+
+        builder.switch_to_block(end);
+    }
+    // NOTE: calls to `returns_twice` functions like `setjmp` need no special handling here.
+    // Every local is kept in its own stack slot and reloaded on each use,
+    // so nothing that `longjmp` could clobber is kept in a register across the call.
     fn call(
         &mut self,
         func: FuncCall,
         ctype: Type,
         args: Vec<Expr>,
+        location: Location,
         builder: &mut FunctionBuilder,
     ) -> IrResult {
         use crate::data::hir::Qualifiers;
-        use cranelift::codegen::ir::{AbiParam, ArgumentPurpose};
-
         let mut ftype = match ctype {
             Type::Function(ftype) => ftype,
             _ => unreachable!("parser should only allow calling functions"),
         };
-        let mut float_variadic = 0;
-        if ftype.varargs {
-            // needs to be done before we move the args by compiling them
-            if self.module.isa().name() != "x86" {
-                unimplemented!("variadic args for architectures other than x86");
+        if let FuncCall::Named(symbol) = &func {
+            if symbol.get().id == InternedStr::get_or_intern(BUILTIN_UNREACHABLE) {
+                builder.ins().trap(TrapCode::UnreachableCodeReached);
+                // anything after this is dead code, but still needs a block to go in
+                let dead = builder.create_block();
+                builder.switch_to_block(dead);
+                return Ok(Value {
+                    ir_val: builder.ins().iconst(types::I32, 0),
+                    ir_type: types::I32,
+                    ctype: Type::Void,
+                });
             }
+        }
+        // `int f(); f(1, 2)` passes the arguments as they are after the default promotions
+        let prototyped = ftype.has_prototype();
+        if ftype.varargs && self.module.isa().name() != "x86" {
+            unimplemented!("variadic args for architectures other than x86");
+        }
+        if ftype.varargs || !prototyped {
+            // needs to be done before we move the args by compiling them
             // this is an utter hack
             // https://github.com/CraneStation/cranelift/issues/212#issuecomment-549111736
             for arg in &args[ftype.params.len()..] {
-                if arg.ctype.is_floating() {
-                    float_variadic += 1;
-                }
                 ftype.params.push(
                     Variable {
                         ctype: arg.ctype.clone(),
                         id: Default::default(),
                         qualifiers: Qualifiers::NONE,
                         storage_class: StorageClass::Auto,
+                        location: arg.location,
                     }
                     .insert(),
                 );
             }
         }
-        let mut compiled_args: Vec<IrValue> = args
+        // `struct s; struct s f(void); f()`: there's no way to know how `s` is passed
+        if let Some(ctype) = ftype.incomplete_aggregate() {
+            let err = SemanticError::IncompleteDefinitionUsed(ctype);
+            return Err(location.with(err.into()));
+        }
+        // Arguments are evaluated left to right, after the function designator.
+        // The standard leaves the order unspecified (6.5.2.2p10), but it should at least be predictable.
+        let mut param_vals = Vec::with_capacity(args.len());
+        for arg in args {
+            let (ctype, arg_location) = (arg.ctype.clone(), arg.location);
+            let val = self.compile_expr(arg, builder)?;
+            // Structs and unions are passed by value, so copy them before evaluating the next argument.
+            // Otherwise a later argument could modify the original through a pointer.
+            // The copy is also rounded up to a whole eightbyte, so the last one can be loaded.
+            let ir_val = if super::is_aggregate(&ctype) {
+                self.copy_aggregate(val.ir_val, &ctype, arg_location, builder)?
+            } else {
+                val.ir_val
+            };
+            param_vals.push(ir_val);
+        }
+        // structs can be passed in a different order than they were declared, see `abi::arguments`
+        let mut compiled_args: Vec<_> = ftype
+            .arguments()
             .into_iter()
-            .map(|arg| self.compile_expr(arg, builder).map(|val| val.ir_val))
-            .collect::<CompileResult<_>>()?;
+            .map(|arg| match arg {
+                super::abi::Arg::Param {
+                    param,
+                    eightbyte,
+                    ir_type,
+                } => {
+                    if super::is_aggregate(&ftype.params[param].get().ctype) {
+                        let offset = eightbyte as i32 * super::abi::EIGHTBYTE as i32;
+                        let addr = param_vals[param];
+                        builder.ins().load(ir_type, MemFlags::new(), addr, offset)
+                    } else {
+                        param_vals[param]
+                    }
+                }
+                super::abi::Arg::Padding => builder.ins().iconst(types::I64, 0),
+            })
+            .collect();
         if ftype.varargs {
-            let float_ir = builder.ins().iconst(types::I8, float_variadic);
+            // `al` is an upper bound on the number of floating point registers used,
+            // including the eightbytes of structs and unions
+            let float_registers = compiled_args
+                .iter()
+                .filter(|&&arg| builder.func.dfg.value_type(arg).is_float())
+                .count()
+                .min(8);
+            let float_ir = builder.ins().iconst(types::I8, float_registers as i64);
             compiled_args.push(float_ir);
         }
+        // `struct s f(); f()`: the callee stores the result in a temporary owned by the caller,
+        // or returns it in registers if it's small enough
+        let struct_return = if ftype.returns_aggregate() {
+            let size = ftype
+                .return_type
+                .sizeof()
+                .map_err(|err| location.with(err.to_string()))?;
+            let addr = Self::eightbyte_slot(size, location, builder)?;
+            if ftype.returns_in_memory() {
+                compiled_args.insert(0, addr);
+            }
+            Some(addr)
+        } else {
+            None
+        };
         let call = match func {
             FuncCall::Named(func_name) => {
                 let func_id = match self.declarations.get(&func_name) {
@@ -551,23 +828,11 @@ impl<B: Backend> Compiler<B> {
                 let func_ref = self.module.declare_func_in_func(func_id, builder.func);
                 let call = builder.ins().call(func_ref, compiled_args.as_slice());
                 // stolen from https://github.com/bjorn3/rustc_codegen_cranelift/blob/82fde5b62281fa51a/src/abi/mod.rs#L535
-                if ftype.varargs {
+                if ftype.varargs || !prototyped {
+                    // `ftype` now has a parameter for each variadic or unprototyped argument
                     let call_sig = builder.func.dfg.call_signature(call).unwrap();
-                    let al = self
-                        .module
-                        .isa()
-                        .register_info()
-                        .parse_regunit("rax")
-                        .expect("x86 should have an rax register");
-                    let float_arg = AbiParam::special_reg(types::I8, ArgumentPurpose::Normal, al);
-                    // NOTE: this is added both here and in signature() because we overwrite the previous params
-                    let abi_params = ftype
-                        .params
-                        .into_iter()
-                        .map(|param| AbiParam::new(param.get().ctype.as_ir_type()))
-                        .chain(std::iter::once(float_arg))
-                        .collect();
-                    builder.func.dfg.signatures[call_sig].params = abi_params;
+                    let sig = ftype.signature(self.module.isa());
+                    builder.func.dfg.signatures[call_sig].params = sig.params;
                 }
                 call
             }
@@ -579,10 +844,23 @@ impl<B: Backend> Compiler<B> {
                     .call_indirect(sigref, callee.ir_val, compiled_args.as_slice())
             }
         };
-        let ir_val = match builder.inst_results(call).first() {
+        // the callee could store through a copy of a `restrict` pointer
+        self.restrict.clear();
+        let results = builder.inst_results(call).to_vec();
+        let ir_val = match (struct_return, results.first()) {
+            (Some(addr), _) => {
+                // a small struct came back in registers, so put it somewhere it can be addressed
+                if !ftype.returns_in_memory() {
+                    for (i, result) in results.into_iter().enumerate() {
+                        let offset = i as i32 * super::abi::EIGHTBYTE as i32;
+                        builder.ins().store(MemFlags::new(), result, addr, offset);
+                    }
+                }
+                addr
+            }
             // Just a placeholder.
-            None => builder.ins().iconst(types::I32, 0),
-            Some(ret) => *ret,
+            (None, None) => builder.ins().iconst(types::I32, 0),
+            (None, Some(ret)) => *ret,
         };
         Ok(Value {
             ir_val,
@@ -591,3 +869,76 @@ impl<B: Backend> Compiler<B> {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ir::CALLING_CONVENTION;
+    use cranelift::codegen::ir::{AbiParam, ExternalName, Function, Signature};
+    use cranelift::frontend::FunctionBuilderContext;
+    use cranelift_object::ObjectBackend;
+
+    /// Lower `x <op> divisor` for a parameter `x` and return the IR for the function
+    fn lower_div(ctype: Type, op: BinaryOp, divisor: i64, optimize: bool) -> String {
+        let ir_type = ctype.as_ir_type();
+        let mut sig = Signature::new(*CALLING_CONVENTION);
+        sig.params.push(AbiParam::new(ir_type));
+        sig.returns.push(AbiParam::new(ir_type));
+        let mut func = Function::with_name_signature(ExternalName::user(0, 0), sig);
+        let mut func_ctx = FunctionBuilderContext::new();
+        let mut builder = FunctionBuilder::new(&mut func, &mut func_ctx);
+        let block = builder.create_block();
+        builder.append_block_params_for_function_params(block);
+        builder.switch_to_block(block);
+        builder.seal_block(block);
+
+        let left = builder.block_params(block)[0];
+        let right = builder.ins().iconst(ir_type, divisor);
+        let value = |ir_val| Value {
+            ir_val,
+            ir_type,
+            ctype: ctype.clone(),
+        };
+        let result = Compiler::<ObjectBackend>::binary_assign_ir(
+            value(left),
+            value(right),
+            ctype.clone(),
+            op,
+            optimize,
+            &mut builder,
+        )
+        .unwrap();
+        builder.ins().return_(&[result.ir_val]);
+        builder.finalize();
+        func.display(None).to_string()
+    }
+
+    #[test]
+    fn unsigned_div_by_power_of_two() {
+        let ir = lower_div(Type::Int(false), BinaryOp::Div, 8, true);
+        assert!(ir.contains("ushr_imm v0, 3"), "{}", ir);
+        assert!(!ir.contains("udiv"), "{}", ir);
+
+        let ir = lower_div(Type::Int(false), BinaryOp::Mod, 8, true);
+        assert!(ir.contains("band_imm v0, 7"), "{}", ir);
+        assert!(!ir.contains("urem"), "{}", ir);
+
+        let ir = lower_div(Type::Int(false), BinaryOp::Div, 8, false);
+        assert!(ir.contains("udiv v0, v1"), "{}", ir);
+        let ir = lower_div(Type::Int(false), BinaryOp::Div, 7, true);
+        assert!(ir.contains("udiv v0, v1"), "{}", ir);
+    }
+
+    #[test]
+    fn signed_div_by_constant() {
+        let ir = lower_div(Type::Int(true), BinaryOp::Div, 7, true);
+        assert!(ir.contains("sdiv_imm v0, 7"), "{}", ir);
+        let ir = lower_div(Type::Long(true), BinaryOp::Mod, -3, true);
+        assert!(ir.contains("srem_imm v0, -3"), "{}", ir);
+        // these trap at runtime and should not be rewritten
+        let ir = lower_div(Type::Int(true), BinaryOp::Div, -1, true);
+        assert!(ir.contains("sdiv v0, v1"), "{}", ir);
+        let ir = lower_div(Type::Int(true), BinaryOp::Div, 0, true);
+        assert!(ir.contains("sdiv v0, v1"), "{}", ir);
+    }
+}
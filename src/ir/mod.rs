@@ -6,7 +6,7 @@ macro_rules! semantic_err {
         return Err(CompileError::semantic(Locatable {
             data: $message,
             location: $location,
-        }));
+        }))
     };
 }
 
@@ -20,15 +20,20 @@ macro_rules! const_assert {
     };
 }
 
+mod abi;
 mod expr;
+mod restrict;
 mod static_init;
 mod stmt;
+mod tail_call;
 
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::convert::TryFrom;
 
-use crate::arch::{CHAR_BIT, PTR_SIZE, SIZE_T, TARGET};
+use crate::arch::{ArchData, StringData, CHAR_BIT, PTR_SIZE, SIZE_T, TARGET};
 use crate::data::lex::ComparisonToken;
+use crate::ice;
+use crate::stats::{self, Counter};
 use cranelift::codegen::{
     self,
     ir::{
@@ -37,19 +42,28 @@ use cranelift::codegen::{
         function::Function,
         stackslot::{StackSlotData, StackSlotKind},
         types::{self, Type as IrType},
-        AbiParam, ArgumentPurpose, ExternalName, InstBuilder, MemFlags, Signature,
+        AbiParam, ArgumentPurpose, ExternalName, InstBuilder, MemFlags, Signature, TrapCode,
     },
     isa::{CallConv, TargetIsa},
     settings::{self, Configurable, Flags},
 };
 use cranelift::frontend::Switch;
-use cranelift::prelude::{Block, FunctionBuilder, FunctionBuilderContext};
-use cranelift_module::{self, Backend, DataId, FuncId, Linkage, Module};
-use cranelift_object::{ObjectBackend, ObjectBuilder};
+use cranelift::prelude::{Block, FunctionBuilder, FunctionBuilderContext, Value as IrValue};
+use cranelift_module::{self, Backend, DataId, FuncId, FuncOrDataId, Linkage, Module};
+use cranelift_object::object::{
+    elf,
+    write::{Relocation, SymbolId, SymbolSection},
+    RelocationEncoding, RelocationKind, SectionKind, SymbolFlags, SymbolScope,
+};
+use cranelift_object::{ObjectBackend, ObjectBuilder, ObjectProduct};
 use lazy_static::lazy_static;
+use target_lexicon::BinaryFormat;
 
 use crate::data::{
-    hir::{Declaration, Initializer, Stmt, Symbol},
+    hir::{
+        Declaration, ExprType, Initializer, LinkAttributes, Stmt, Symbol, Variable,
+        DEFAULT_INIT_PRIORITY,
+    },
     types::FunctionType,
     StorageClass, *,
 };
@@ -60,7 +74,7 @@ lazy_static! {
     pub(crate) static ref CALLING_CONVENTION: CallConv = CallConv::triple_default(&TARGET);
 }
 
-pub(crate) fn get_isa(jit: bool) -> Box<dyn TargetIsa + 'static> {
+pub(crate) fn get_isa(jit: bool, opt_level: u8) -> Box<dyn TargetIsa + 'static> {
     let mut flags_builder = cranelift::codegen::settings::builder();
     // `simplejit` requires non-PIC code
     if !jit {
@@ -77,38 +91,218 @@ pub(crate) fn get_isa(jit: bool) -> Box<dyn TargetIsa + 'static> {
     flags_builder
         .set("enable_probestack", "false")
         .expect("enable_probestack should be a valid option");
+    // this enables cranelift's pre-optimization pass, which (among other things)
+    // replaces division by a constant with multiplication by its inverse
+    if opt_level > 0 {
+        flags_builder
+            .set("opt_level", "speed")
+            .expect("opt_level should be a valid option");
+    }
     let flags = Flags::new(flags_builder);
     cranelift::codegen::isa::lookup(TARGET)
         .unwrap_or_else(|_| panic!("platform not supported: {}", TARGET))
         .finish(flags)
 }
 
-pub fn initialize_aot_module(name: String) -> Module<ObjectBackend> {
+/// Create a module for compiling to an object file.
+///
+/// `opt_level` should be the same as `Opt::opt_level`.
+pub fn initialize_aot_module(name: String, opt_level: u8) -> Module<ObjectBackend> {
     Module::new(ObjectBuilder::new(
-        get_isa(false),
+        get_isa(false, opt_level),
         name,
         cranelift_module::default_libcall_names(),
     ))
 }
 
+/// A module compiled by `compile`, with what Cranelift can't declare itself.
+pub struct CompiledModule<B: Backend> {
+    /// Every declaration in the program, except for what's in `pending`
+    pub module: Module<B>,
+    pub(crate) pending: Pending,
+}
+
+/// Finish a module created by `initialize_aot_module`, returning the object file.
+///
+/// Use this instead of `Module::finish`, which doesn't know about `__attribute__((alias))`,
+/// `__attribute__((weak))` declarations, or `__attribute__((constructor))`.
+pub fn finish_aot_module(compiled: CompiledModule<ObjectBackend>) -> ObjectProduct {
+    let CompiledModule { module, pending } = compiled;
+    let mut product = module.finish();
+    // Cranelift can't declare a symbol that's defined by another one,
+    // so aliases are declared as imports and defined here
+    for alias in pending.aliases {
+        let (symbol, target) = (
+            symbol_id(&product, alias.symbol),
+            symbol_id(&product, alias.target),
+        );
+        let target = product.object.symbol(target);
+        let (section, value, size, kind) = (target.section, target.value, target.size, target.kind);
+        let symbol = product.object.symbol_mut(symbol);
+        symbol.section = section;
+        symbol.value = value;
+        symbol.size = size;
+        symbol.kind = kind;
+        symbol.scope = if alias.local {
+            SymbolScope::Compilation
+        } else {
+            SymbolScope::Dynamic
+        };
+        symbol.weak = alias.weak;
+    }
+    // `object` writes every undefined ELF symbol as `STB_GLOBAL`, even if it's weak,
+    // so `extern void f(void) __attribute__((weak));` would fail to link if `f` isn't defined
+    if product.object.format() == BinaryFormat::Elf {
+        for id in pending.weak {
+            let symbol = product.object.symbol_mut(symbol_id(&product, id));
+            if symbol.section == SymbolSection::Undefined {
+                symbol.flags = SymbolFlags::Elf {
+                    st_info: (elf::STB_WEAK << 4) | elf::STT_NOTYPE,
+                    st_other: elf::STV_DEFAULT,
+                };
+            }
+        }
+    }
+    add_init_array(&mut product, ".init_array", &pending.constructors);
+    add_init_array(&mut product, ".fini_array", &pending.destructors);
+    product
+}
+
+fn symbol_id(product: &ObjectProduct, id: FuncOrDataId) -> SymbolId {
+    match id {
+        FuncOrDataId::Func(id) => product.function_symbol(id),
+        FuncOrDataId::Data(id) => product.data_symbol(id),
+    }
+}
+
+/// Add a pointer to each of `functions` to the section `name`, where the C runtime will call it.
+///
+/// Functions with a priority go in `name.<priority>` instead, which the linker sorts before `name`.
+fn add_init_array(product: &mut ObjectProduct, name: &str, functions: &[(u16, FuncId)]) {
+    let mut sections = HashMap::new();
+    for &(priority, func) in functions {
+        let name = if priority == DEFAULT_INIT_PRIORITY {
+            name.to_owned()
+        } else {
+            format!("{}.{:05}", name, priority)
+        };
+        let object = &mut product.object;
+        let section = *sections.entry(name).or_insert_with_key(|name| {
+            object.add_section(Vec::new(), name.as_bytes().to_vec(), SectionKind::Data)
+        });
+        let pointer = [0; PTR_SIZE as usize];
+        let offset = object.append_section_data(section, &pointer, u64::from(PTR_SIZE));
+        let relocation = Relocation {
+            offset,
+            size: (PTR_SIZE * 8) as u8,
+            kind: RelocationKind::Absolute,
+            encoding: RelocationEncoding::Generic,
+            symbol: product.functions[func].expect("constructors should be declared"),
+            addend: 0,
+        };
+        object
+            .add_relocation(section, relocation)
+            .expect("pointers to functions should always be relocatable");
+    }
+}
+
+/// A symbol declared with `__attribute__((alias("target")))`.
+struct Alias {
+    symbol: FuncOrDataId,
+    target: FuncOrDataId,
+    weak: bool,
+    /// Whether the alias was declared `static`
+    local: bool,
+}
+
+/// What `finish_aot_module` adds to the object file, since Cranelift can't declare it.
+pub(crate) struct Pending {
+    aliases: Vec<Alias>,
+    /// Everything declared with `__attribute__((weak))`, which may never be defined
+    weak: Vec<FuncOrDataId>,
+    /// Functions declared with `__attribute__((constructor))` and their priority, sorted by priority
+    pub(crate) constructors: Vec<(u16, FuncId)>,
+    /// Functions declared with `__attribute__((destructor))` and their priority, sorted by priority
+    ///
+    /// These are run in the opposite order, from the last to the first.
+    pub(crate) destructors: Vec<(u16, FuncId)>,
+}
+
+/// Store `val` at `addr`.
+///
+/// `_Bool` is `b1` in the IR, which Cranelift can't store, so it's stored as a byte instead.
+fn store(val: IrValue, addr: IrValue, builder: &mut FunctionBuilder) {
+    let val = if builder.func.dfg.value_type(val).is_bool() {
+        builder.ins().bint(types::I8, val)
+    } else {
+        val
+    };
+    builder.ins().store(MemFlags::new(), val, addr, 0);
+}
+
+/// Load a value of type `ir_type` from `addr`, which was stored by `store`.
+fn load(ir_type: IrType, flags: MemFlags, addr: IrValue, builder: &mut FunctionBuilder) -> IrValue {
+    if ir_type.is_bool() {
+        let byte = builder.ins().load(types::I8, flags, addr, 0);
+        builder.ins().icmp_imm(IntCC::NotEqual, byte, 0)
+    } else {
+        builder.ins().load(ir_type, flags, addr, 0)
+    }
+}
+
 enum Id {
     Function(FuncId),
     Global(DataId),
     Local(StackSlot),
 }
 
-struct Compiler<T: Backend> {
+/// Compiles declarations one at a time into a Cranelift `Module`.
+pub(crate) struct Compiler<T: Backend> {
     module: Module<T>,
     debug: bool,
+    // if set, the text of the IR of each function compiled so far
+    clif: Option<String>,
+    // whether to strength-reduce arithmetic; set by the optimization level of the module
+    optimize: bool,
+    // whether to turn self-recursive tail calls into jumps
+    optimize_sibling_calls: bool,
+    // if set, never generate calls to the C library
+    freestanding: bool,
+    // the byte order and pointer width to use for static data
+    arch: ArchData,
+    // set while compiling a function whose tail calls can be optimized
+    tail_call: Option<tail_call::TailCall>,
+    // values loaded through `restrict` parameters of the current function; only used when optimizing
+    restrict: restrict::RestrictLoads,
+    // set while compiling a function that returns a struct or union too large for registers;
+    // the address the caller passed in to store the return value
+    struct_return: Option<IrValue>,
     // if false, we last saw a switch
     last_saw_loop: bool,
-    strings: HashMap<Vec<u8>, DataId>,
+    strings: HashMap<StringData, DataId>,
     declarations: HashMap<Symbol, Id>,
+    // static data which has been given a value
+    defined_data: HashSet<DataId>,
+    // `int i;` without a later `int i = 1;`, which is defined as zero at the end of the file
+    tentative: Vec<(DataId, Symbol, Location)>,
+    // the number of `static` variables declared inside functions so far, used to give them unique names
+    local_statics: usize,
+    // the type each function is declared with, so every declaration has the same signature
+    prototypes: HashMap<InternedStr, FunctionType>,
+    // names declared with `__attribute__((weak))`
+    weak: HashSet<InternedStr>,
+    // names declared with `__attribute__((alias))`, with their target and whether they're `static`
+    aliases: HashMap<InternedStr, (InternedStr, bool)>,
+    // functions defined with `__attribute__((constructor))` and `destructor`, with their priority
+    constructors: Vec<(u16, FuncId)>,
+    destructors: Vec<(u16, FuncId)>,
+    // (continue target, break target) of each enclosing loop, innermost last
     loops: Vec<(Block, Block)>,
     // switch, default, end
     // if default is empty once we get to the end of a switch body,
     // we didn't see a default case
     switches: Vec<(Switch, Option<Block>, Block)>,
+    // labels in the current function, including ones only seen in a `goto` so far
     labels: HashMap<InternedStr, Block>,
     error_handler: ErrorHandler,
 }
@@ -118,49 +312,67 @@ pub(crate) fn compile<B: Backend>(
     module: Module<B>,
     program: Vec<Locatable<Declaration>>,
     debug: bool,
-) -> (Result<Module<B>, CompileError>, VecDeque<CompileWarning>) {
+    emit_clif: bool,
+    optimize_sibling_calls: bool,
+    freestanding: bool,
+) -> (
+    Result<CompiledModule<B>, CompileError>,
+    VecDeque<CompileWarning>,
+    Option<String>,
+) {
     // really we'd like to have all errors but that requires a refactor
     let mut err = None;
-    let mut compiler = Compiler::new(module, debug);
+    let mut compiler = Compiler::new(module, debug, optimize_sibling_calls, freestanding);
+    if emit_clif {
+        compiler.clif = Some(String::new());
+    }
+    compiler.collect_prototypes(&program);
     for decl in program {
-        let meta = decl.data.symbol.get();
-        if let StorageClass::Typedef = meta.storage_class {
-            continue;
-        }
-        let current = match &meta.ctype {
-            Type::Function(func_type) => match decl.data.init {
-                Some(Initializer::FunctionBody(stmts)) => {
-                    compiler.compile_func(decl.data.symbol, &func_type, stmts, decl.location)
-                }
-                None => compiler.declare_func(decl.data.symbol, false).map(|_| ()),
-                _ => unreachable!("functions can only be initialized by a FunctionBody"),
-            },
-            Type::Void | Type::Error => unreachable!("parser let an incomplete type through"),
-            _ => {
-                if let Some(Initializer::FunctionBody(_)) = &decl.data.init {
-                    unreachable!("only functions should have a function body")
-                }
-                compiler.store_static(decl.data.symbol, decl.data.init, decl.location)
-            }
-        };
-        if let Err(e) = current {
+        if let Err(e) = compiler.compile_decl(decl) {
             err = Some(e);
             break;
         }
     }
-    let warns = compiler.error_handler.warnings;
+    if err.is_none() {
+        err = compiler.define_tentative().err();
+    }
+    let pending = compiler.pending();
+    let warns = compiler.warnings();
+    let clif = compiler.clif;
     if let Some(err) = err {
-        (Err(err), warns)
+        (Err(err), warns, clif)
     } else {
-        (Ok(compiler.module), warns)
+        let module = compiler.module;
+        (Ok(CompiledModule { module, pending }), warns, clif)
     }
 }
 
 impl<B: Backend> Compiler<B> {
-    fn new(module: Module<B>, debug: bool) -> Compiler<B> {
+    pub(crate) fn new(
+        module: Module<B>,
+        debug: bool,
+        optimize_sibling_calls: bool,
+        freestanding: bool,
+    ) -> Compiler<B> {
+        let optimize = module.isa().flags().opt_level() != settings::OptLevel::None;
         Compiler {
+            optimize,
+            optimize_sibling_calls,
+            freestanding,
+            arch: ArchData::new(&TARGET),
+            tail_call: None,
+            restrict: Default::default(),
+            struct_return: None,
             module,
             declarations: HashMap::new(),
+            defined_data: HashSet::new(),
+            tentative: Vec::new(),
+            local_statics: 0,
+            prototypes: HashMap::new(),
+            weak: HashSet::new(),
+            aliases: HashMap::new(),
+            constructors: Vec::new(),
+            destructors: Vec::new(),
             loops: Vec::new(),
             switches: Vec::new(),
             labels: HashMap::new(),
@@ -168,9 +380,123 @@ impl<B: Backend> Compiler<B> {
             last_saw_loop: true,
             strings: Default::default(),
             error_handler: Default::default(),
+            clif: None,
             debug,
         }
     }
+    /// Compile a single declaration, adding it to the module.
+    pub(crate) fn compile_decl(&mut self, decl: Locatable<Declaration>) -> CompileResult<()> {
+        ice::set_location(decl.location);
+        let meta = decl.data.symbol.get();
+        if let StorageClass::Typedef = meta.storage_class {
+            return Ok(());
+        }
+        self.record_attributes(&meta, &decl.data.attributes);
+        match &meta.ctype {
+            Type::Function(func_type) => match decl.data.init {
+                Some(Initializer::FunctionBody(stmts)) => {
+                    self.compile_func(decl.data.symbol, func_type, stmts, decl.location)
+                }
+                None => self.declare_func(decl.data.symbol, false).map(|_| ()),
+                _ => unreachable!("functions can only be initialized by a FunctionBody"),
+            },
+            Type::Void | Type::Error => unreachable!("parser let an incomplete type through"),
+            _ => {
+                if let Some(Initializer::FunctionBody(_)) = &decl.data.init {
+                    unreachable!("only functions should have a function body")
+                }
+                self.store_static(decl.data.symbol, decl.data.init, decl.location)
+            }
+        }
+    }
+    /// Find the type to declare each function in `program` with.
+    ///
+    /// `int f(); int f(int a) { ... }` declares `f` twice, but every declaration has to have the same signature.
+    /// So use the type of the definition, or the first prototype if `f` is only declared.
+    fn collect_prototypes(&mut self, program: &[Locatable<Declaration>]) {
+        for decl in program {
+            let meta = decl.data.symbol.get();
+            if let Type::Function(ftype) = &meta.ctype {
+                if let Some(Initializer::FunctionBody(_)) = decl.data.init {
+                    self.prototypes.insert(meta.id, ftype.clone());
+                } else if ftype.has_prototype() {
+                    self.prototypes
+                        .entry(meta.id)
+                        .or_insert_with(|| ftype.clone());
+                }
+            }
+        }
+    }
+    /// Remember `attributes`, which apply to every declaration of `meta` from now on.
+    fn record_attributes(&mut self, meta: &Variable, attributes: &LinkAttributes) {
+        if attributes.weak {
+            self.weak.insert(meta.id);
+        }
+        if let Some(target) = attributes.alias {
+            let local = meta.storage_class == StorageClass::Static;
+            self.aliases.insert(meta.id, (target, local));
+        }
+    }
+    /// The linkage to declare `meta` with, given the linkage of a normal declaration.
+    ///
+    /// `__attribute__((weak))` makes both definitions and declarations preemptible,
+    /// and aliases are imported until they're defined by `finish_aot_module`.
+    fn linkage(&self, meta: &Variable, linkage: Linkage) -> Linkage {
+        if self.aliases.contains_key(&meta.id) {
+            Linkage::Import
+        } else if linkage != Linkage::Local && self.weak.contains(&meta.id) {
+            Linkage::Preemptible
+        } else {
+            linkage
+        }
+    }
+    /// Everything `finish_aot_module` needs to add, now that all the declarations have been compiled.
+    fn pending(&mut self) -> Pending {
+        let get_name = |name: InternedStr| self.module.get_name(&name.resolve_and_clone());
+        // find the symbols each alias refers to
+        let aliases = self
+            .aliases
+            .iter()
+            .filter_map(|(&name, &(target, local))| {
+                Some(Alias {
+                    symbol: get_name(name)?,
+                    // the analyzer has already checked that the target is defined,
+                    // but it may have been removed as dead code if nothing else uses it
+                    target: get_name(target)?,
+                    weak: self.weak.contains(&name),
+                    local,
+                })
+            })
+            .collect();
+        let weak = self
+            .weak
+            .iter()
+            .filter_map(|&name| get_name(name))
+            .collect();
+        // functions with the same priority run in the order they were defined
+        let mut constructors = std::mem::take(&mut self.constructors);
+        let mut destructors = std::mem::take(&mut self.destructors);
+        constructors.sort_by_key(|&(priority, _)| priority);
+        destructors.sort_by_key(|&(priority, _)| priority);
+        Pending {
+            aliases,
+            weak,
+            constructors,
+            destructors,
+        }
+    }
+    /// The module compiled so far.
+    #[cfg(feature = "jit")]
+    pub(crate) fn module_mut(&mut self) -> &mut Module<B> {
+        &mut self.module
+    }
+    /// Return all warnings seen so far.
+    ///
+    /// These warnings are consumed and will not be returned if you call
+    /// `warnings()` again.
+    pub(crate) fn warnings(&mut self) -> VecDeque<CompileWarning> {
+        std::mem::take(&mut self.error_handler.warnings)
+    }
     // we have to consider the following cases:
     // 1. declaration before definition
     // 2. 2nd declaration before definition
@@ -193,13 +519,21 @@ impl<B: Backend> Compiler<B> {
             Type::Function(func_type) => func_type,
             _ => unreachable!("bug in backend: only functions should be passed to `declare_func`"),
         };
-        let signature = func_type.signature(self.module.isa());
+        let func_type = self.prototypes.get(&metadata.id).unwrap_or(func_type);
+        let signature = if func_type.incomplete_aggregate().is_some() {
+            // `struct s; void f(struct s);`: `f` can't be called or defined, since `s` is never completed.
+            // So the signature doesn't matter, but `&f` still needs a declaration.
+            Signature::new(*CALLING_CONVENTION)
+        } else {
+            func_type.signature(self.module.isa())
+        };
         let linkage = match metadata.storage_class {
             StorageClass::Auto | StorageClass::Extern if is_definition => Linkage::Export,
             StorageClass::Auto | StorageClass::Extern => Linkage::Import,
             StorageClass::Static => Linkage::Local,
             StorageClass::Register | StorageClass::Typedef => unreachable!(),
         };
+        let linkage = self.linkage(&metadata, linkage);
         let func_id = self
             .module
             .declare_function(get_str!(metadata.id), linkage, &signature)
@@ -218,10 +552,22 @@ impl<B: Backend> Compiler<B> {
         if let StorageClass::Typedef = meta.storage_class {
             return Ok(());
         }
+        self.record_attributes(&meta, &decl.attributes);
         if let Type::Function(_) = &meta.ctype {
             self.declare_func(decl.symbol, false)?;
             return Ok(());
         }
+        // `{ extern int i; }` refers to the global `i`, not a new local variable
+        if let StorageClass::Extern = meta.storage_class {
+            return self.store_static(decl.symbol, None, location);
+        }
+        // `{ static int i; }` keeps its value between calls, so it can't go on the stack.
+        // Other functions can have their own `i`, so give it a name that can't be a C identifier.
+        if let StorageClass::Static = meta.storage_class {
+            let name = format!("{}.local.{}", meta.id, self.local_statics);
+            self.local_statics += 1;
+            return self.store_static_as(&name, decl.symbol, decl.init, location);
+        }
         let u64_size = match meta.ctype.sizeof() {
             Ok(size) => size,
             Err(err) => {
@@ -247,25 +593,94 @@ impl<B: Backend> Compiler<B> {
         let stack_slot = builder.create_stack_slot(data);
         self.declarations.insert(decl.symbol, Id::Local(stack_slot));
         if let Some(init) = decl.init {
-            self.store_stack(init, stack_slot, builder)?;
+            // TODO: replace with `builder.ins().stack_store(val.ir_val, stack_slot, 0);`
+            // when Cranelift implements stack_store for i8 and i16
+            let addr = builder.ins().stack_addr(Type::ptr_type(), stack_slot, 0);
+            if let Initializer::InitializerList(_) = init {
+                // members without an initializer are set to 0 (6.7.9p21)
+                let align = meta
+                    .ctype
+                    .alignof()
+                    .map_err(|err| location.with(err.to_string()))?;
+                let align = u8::try_from(align).expect("align should never be more than 255 bytes");
+                self.zero_memory(addr, u64_size, align, builder);
+            }
+            self.store_stack(init, &meta.ctype, addr, 0, location, builder)?;
         }
         Ok(())
     }
+    /// Store `init` at `addr + offset`, where the object being initialized has type `ctype`.
     fn store_stack(
         &mut self,
         init: Initializer,
-        stack_slot: StackSlot,
+        ctype: &Type,
+        addr: IrValue,
+        offset: u64,
+        location: Location,
         builder: &mut FunctionBuilder,
     ) -> CompileResult<()> {
+        let sizeof = |ctype: &Type| ctype.sizeof().map_err(|err| location.with(err.to_string()));
+        let dest = builder.ins().iadd_imm(addr, offset as i64);
         match init {
             Initializer::Scalar(expr) => {
-                let val = self.compile_expr(*expr, builder)?;
-                // TODO: replace with `builder.ins().stack_store(val.ir_val, stack_slot, 0);`
-                // when Cranelift implements stack_store for i8 and i16
-                let addr = builder.ins().stack_addr(Type::ptr_type(), stack_slot, 0);
-                builder.ins().store(MemFlags::new(), val.ir_val, addr, 0);
+                // `char s[4] = "abc";` or `wchar_t s[4] = L"abc";`
+                if let (
+                    Type::Array(..),
+                    ExprType::Literal(string @ (Literal::Str(_) | Literal::WideStr(..))),
+                ) = (ctype, &expr.expr)
+                {
+                    let string = self
+                        .arch
+                        .string_data(string)
+                        .expect("elements that are too large should be caught by the analyzer");
+                    // the null terminator is left out if there isn't room for it
+                    let len = std::cmp::min(string.bytes.len() as u64, sizeof(ctype)?);
+                    let align = string.element_size as u8;
+                    let val = self.compile_expr(expr.take(), builder)?;
+                    self.copy_memory(dest, val.ir_val, len, align, builder);
+                    return Ok(());
+                }
+                let val = self.compile_expr(expr.take(), builder)?;
+                // `struct s t = s;`
+                if let Type::Struct(_) | Type::Union(_) = ctype {
+                    let align = ctype
+                        .alignof()
+                        .map_err(|err| location.with(err.to_string()))?;
+                    let align = u8::try_from(align).expect("align should never be more than 255");
+                    self.copy_memory(dest, val.ir_val, sizeof(ctype)?, align, builder);
+                } else {
+                    store(val.ir_val, dest, builder);
+                }
             }
-            Initializer::InitializerList(_) => unimplemented!("aggregate dynamic initialization"),
+            Initializer::InitializerList(inits) => match ctype {
+                Type::Array(inner, _) => {
+                    let size = sizeof(inner)?;
+                    for (i, init) in inits.into_iter().enumerate() {
+                        let offset = offset + size * i as u64;
+                        self.store_stack(init, inner, addr, offset, location, builder)?;
+                    }
+                }
+                Type::Struct(struct_type) => {
+                    for (member, init) in struct_type.members().iter().zip(inits) {
+                        let offset = offset + struct_type.offset(member.id);
+                        self.store_stack(init, &member.ctype, addr, offset, location, builder)?;
+                    }
+                }
+                // only the first member of a union can be initialized
+                Type::Union(struct_type) => {
+                    if let (Some(member), Some(init)) =
+                        (struct_type.members().first(), inits.into_iter().next())
+                    {
+                        self.store_stack(init, &member.ctype, addr, offset, location, builder)?;
+                    }
+                }
+                // `int i = {1};`
+                _ => {
+                    for init in inits {
+                        self.store_stack(init, ctype, addr, offset, location, builder)?;
+                    }
+                }
+            },
             Initializer::FunctionBody(_) => unreachable!("functions can't be stored on the stack"),
         }
         Ok(())
@@ -275,21 +690,26 @@ impl<B: Backend> Compiler<B> {
     fn store_stack_params(
         &mut self,
         params: &[Symbol],
+        arguments: Vec<abi::Arg>,
         func_start: Block,
         location: &Location,
         builder: &mut FunctionBuilder,
     ) -> CompileResult<()> {
         // Cranelift requires that all block params are declared up front
-        let ir_vals: Vec<_> = params
-            .iter()
-            .map(|param| {
-                let ir_type = param.get().ctype.as_ir_type();
-                Ok(builder.append_block_param(func_start, ir_type))
-            })
-            .collect::<CompileResult<_>>()?;
-        for (&param, ir_val) in params.iter().zip(ir_vals) {
-            let u64_size = match param.get().ctype.sizeof() {
+        let mut ir_vals = vec![Vec::new(); params.len()];
+        for arg in arguments {
+            let ir_val = builder.append_block_param(func_start, arg.ir_type());
+            // each eightbyte of a struct comes after the one before it
+            if let abi::Arg::Param { param, .. } = arg {
+                ir_vals[param].push(ir_val);
+            }
+        }
+        for (&param, ir_vals) in params.iter().zip(ir_vals) {
+            let ctype = &param.get().ctype;
+            let u64_size = match ctype.sizeof() {
                 Err(data) => semantic_err!(data.into(), *location),
+                // the last eightbyte of a struct or union is stored whole
+                Ok(size) if is_aggregate(ctype) => abi::eightbytes(size) as u64 * abi::EIGHTBYTE,
                 Ok(size) => size,
             };
             let u32_size = match u32::try_from(u64_size) {
@@ -313,7 +733,15 @@ impl<B: Backend> Compiler<B> {
             // then this can be replaced with `builder.ins().stack_store(ir_val, slot, 0);`
             // See https://github.com/CraneStation/cranelift/issues/433
             let addr = builder.ins().stack_addr(Type::ptr_type(), slot, 0);
-            builder.ins().store(MemFlags::new(), ir_val, addr, 0);
+            if is_aggregate(ctype) {
+                // structs and unions arrive an eightbyte at a time
+                for (i, ir_val) in ir_vals.into_iter().enumerate() {
+                    let offset = i as i32 * abi::EIGHTBYTE as i32;
+                    builder.ins().store(MemFlags::new(), ir_val, addr, offset);
+                }
+            } else {
+                store(ir_vals[0], addr, builder);
+            }
             self.declarations.insert(param, Id::Local(slot));
         }
         Ok(())
@@ -325,7 +753,19 @@ impl<B: Backend> Compiler<B> {
         stmts: Vec<Stmt>,
         location: Location,
     ) -> CompileResult<()> {
+        stats::count(Counter::FunctionsCompiled);
+        if let Some(ctype) = func_type.incomplete_aggregate() {
+            let err = SemanticError::IncompleteDefinitionUsed(ctype);
+            return Err(location.with(err.into()));
+        }
         let func_id = self.declare_func(symbol, true)?;
+        let qualifiers = symbol.get().qualifiers.func;
+        if let Some(priority) = qualifiers.constructor {
+            self.constructors.push((priority, func_id));
+        }
+        if let Some(priority) = qualifiers.destructor {
+            self.destructors.push((priority, func_id));
+        }
         // TODO: make declare_func should take a `signature` after all?
         // This just calculates it twice, it's probably fine
         let signature = func_type.signature(self.module.isa());
@@ -342,30 +782,37 @@ impl<B: Backend> Compiler<B> {
         builder.switch_to_block(func_start);
 
         let should_ret = func_type.should_return();
+        // the hidden return pointer comes before any of the declared parameters
+        if func_type.returns_in_memory() {
+            self.struct_return = Some(builder.append_block_param(func_start, Type::ptr_type()));
+        }
         if func_type.has_params() {
             self.store_stack_params(
                 // TODO: get rid of this clone
                 &func_type.params,
+                func_type.arguments(),
                 func_start,
                 &location,
                 &mut builder,
             )?;
         }
-        self.compile_all(stmts, &mut builder)?;
+        self.start_tail_calls(symbol, func_type, &stmts, &mut builder);
+        if self.optimize {
+            self.restrict = restrict::RestrictLoads::new(func_type, &stmts);
+        }
+        let result = self.compile_all(stmts, &mut builder);
+        self.tail_call = None;
+        self.restrict = Default::default();
+        self.struct_return = None;
+        // 6.2.1p3: labels have function scope
+        self.labels.clear();
+        result?;
         if !builder.is_filled() {
-            let id = symbol.get().id;
-            if id == InternedStr::get_or_intern("main") {
-                let ir_int = func_type.return_type.as_ir_type();
-                let zero = [builder.ins().iconst(ir_int, 0)];
-                builder.ins().return_(&zero);
-            } else if should_ret {
-                semantic_err!(
-                    format!(
-                        "expected a return statement before end of function '{}' returning {}",
-                        id, func_type.return_type
-                    ),
-                    location
-                );
+            // NOTE: the analyzer already added `return 0;` to the end of `main`
+            if should_ret {
+                // 6.9.1p12: falling off the end of a non-void function is undefined behavior.
+                // The analyzer has already warned about this (`-Wreturn-type`).
+                builder.ins().trap(TrapCode::UnreachableCodeReached);
             } else {
                 // void function, return nothing
                 builder.ins().return_(&[]);
@@ -379,6 +826,10 @@ impl<B: Backend> Compiler<B> {
         if self.debug {
             println!("ir: {}", func);
         }
+        if let Some(clif) = &mut self.clif {
+            clif.push_str(&func.to_string());
+            clif.push('\n');
+        }
 
         if let Err(err) = codegen::verify_function(&func, &flags) {
             panic!(
@@ -408,17 +859,45 @@ impl FunctionType {
         !(self.params.len() == 1 && self.params[0].get().ctype == Type::Void)
     }
 
-    /// Generate the IR function signature for `self`
-    pub fn signature(&self, isa: &dyn TargetIsa) -> Signature {
-        let mut params = if self.params.len() == 1 && self.params[0].get().ctype == Type::Void {
-            // no arguments
-            Vec::new()
-        } else {
+    /// The values passed to this function, in the order of its signature.
+    ///
+    /// This doesn't include the hidden pointer to the return value, which always comes first.
+    fn arguments(&self) -> Vec<abi::Arg> {
+        let params: Vec<_> = if self.has_params() {
             self.params
                 .iter()
-                .map(|param| AbiParam::new(param.get().ctype.as_ir_type()))
+                .map(|param| param.get().ctype.clone())
                 .collect()
+        } else {
+            Vec::new()
         };
+        if params.iter().any(is_aggregate) {
+            abi::arguments(params.iter(), self.returns_in_memory())
+        } else {
+            // every scalar is in the same place either way, so keep the order they were declared in
+            params
+                .iter()
+                .enumerate()
+                .map(|(param, ctype)| abi::Arg::Param {
+                    param,
+                    eightbyte: 0,
+                    ir_type: ctype.as_ir_type(),
+                })
+                .collect()
+        }
+    }
+
+    /// Generate the IR function signature for `self`
+    pub fn signature(&self, isa: &dyn TargetIsa) -> Signature {
+        let mut params: Vec<_> = self
+            .arguments()
+            .into_iter()
+            .map(|arg| AbiParam::new(arg.ir_type()))
+            .collect();
+        // large structs and unions are returned through a pointer passed in by the caller
+        if self.returns_in_memory() {
+            params.insert(0, struct_return_param());
+        }
         if self.varargs {
             let al = isa
                 .register_info()
@@ -432,6 +911,14 @@ impl FunctionType {
         }
         let return_type = if !self.should_return() {
             vec![]
+        } else if self.returns_in_memory() {
+            vec![struct_return_param()]
+        } else if self.returns_aggregate() {
+            // small structs and unions are returned in `rax`, `rdx`, `xmm0`, and `xmm1`
+            abi::argument_types(&self.return_type)
+                .into_iter()
+                .map(AbiParam::new)
+                .collect()
         } else {
             vec![AbiParam::new(self.return_type.as_ir_type())]
         };
@@ -531,6 +1018,30 @@ impl FunctionType {
     fn should_return(&self) -> bool {
         *self.return_type != Type::Void
     }
+    fn returns_aggregate(&self) -> bool {
+        is_aggregate(&self.return_type)
+    }
+    /// Whether the return value is stored through a hidden pointer instead of returned in registers
+    fn returns_in_memory(&self) -> bool {
+        self.returns_aggregate() && abi::classify(&self.return_type) == abi::Class::Memory
+    }
+    /// A struct or union passed to or returned from this function that was never completed.
+    ///
+    /// These can't be classified, so the function can't be called or defined.
+    fn incomplete_aggregate(&self) -> Option<Type> {
+        let params = self.params.iter().map(|param| param.get().ctype.clone());
+        std::iter::once(*self.return_type.clone())
+            .chain(params)
+            .find(|ctype| is_aggregate(ctype) && ctype.sizeof().is_err())
+    }
+}
+
+fn is_aggregate(ctype: &Type) -> bool {
+    matches!(ctype, Type::Struct(_) | Type::Union(_))
+}
+
+fn struct_return_param() -> AbiParam {
+    AbiParam::special(Type::ptr_type(), ArgumentPurpose::StructReturn)
 }
 #[cfg(test)]
 #[test]
@@ -0,0 +1,178 @@
+//! Reuse values loaded through `restrict` pointers.
+//!
+//! If `p` is a `restrict` parameter, the object `*p` is only accessed through `p`
+//! while the function runs (6.7.3.1), so storing through a different `restrict` pointer
+//! can't change it and it doesn't need to be loaded again.
+//!
+//! This is deliberately conservative:
+//! - only parameters that are never assigned to and never have their address taken are tracked
+//! - values are only reused in the block they were loaded in
+//! - any call, or any store that isn't through a tracked parameter, forgets everything
+use std::collections::{HashMap, HashSet};
+
+use cranelift::codegen::cursor::Cursor;
+use cranelift::prelude::{Block, FunctionBuilder, Type as IrType, Value as IrValue};
+
+use crate::arch::Target;
+use crate::data::{
+    hir::{walk_expr, BinaryOp, Expr, ExprType, Stmt, Symbol, Visitor},
+    types::FunctionType,
+    *,
+};
+use crate::fold::{const_eval, ConstValue};
+
+/// The values known to be stored at an offset from a `restrict` parameter.
+#[derive(Default)]
+pub(super) struct RestrictLoads {
+    params: HashSet<Symbol>,
+    /// The block `values` were loaded in
+    block: Option<Block>,
+    values: HashMap<(Symbol, i64), (IrValue, IrType)>,
+}
+
+impl RestrictLoads {
+    /// Start tracking loads for the function with type `func_type` and body `stmts`.
+    pub(super) fn new(func_type: &FunctionType, stmts: &[Stmt]) -> Self {
+        let mut params: HashSet<_> = func_type
+            .params
+            .iter()
+            .copied()
+            .filter(|param| {
+                let meta = param.get();
+                match &meta.ctype {
+                    Type::Pointer(_, qualifiers) => {
+                        qualifiers.restrict && !meta.qualifiers.volatile
+                    }
+                    _ => false,
+                }
+            })
+            .collect();
+        if !params.is_empty() {
+            let mut modified = Modified {
+                params: &mut params,
+            };
+            for stmt in stmts {
+                modified.visit_stmt(stmt);
+            }
+        }
+        RestrictLoads {
+            params,
+            ..Self::default()
+        }
+    }
+    /// If `addr` points to a fixed offset from a tracked parameter, return the parameter and the offset.
+    pub(super) fn location(&self, addr: &Expr) -> Option<(Symbol, i64)> {
+        match &addr.expr {
+            // the value of `p`
+            ExprType::Deref(inner) => match inner.expr {
+                ExprType::Id(param) if self.params.contains(&param) => Some((param, 0)),
+                _ => None,
+            },
+            // `*(p + 1)` as an lvalue
+            ExprType::Noop(inner) => self.location(inner),
+            // `p->member`
+            ExprType::Member(inner, member) => {
+                let members = match &inner.ctype {
+                    Type::Struct(stype) | Type::Union(stype) => stype.members(),
+                    _ => return None,
+                };
+                let member = members.iter().find(|m| m.id == *member)?;
+                if member.qualifiers.volatile {
+                    return None;
+                }
+                let (param, offset) = self.location(inner)?;
+                let member_offset = inner.ctype.member_offset(member.id).ok()?;
+                Some((param, offset + member_offset as i64))
+            }
+            // `p[1]`; the index has already been multiplied by the size of the element
+            ExprType::Binary(BinaryOp::Add, base, index) if addr.ctype.is_pointer() => {
+                let (param, offset) = self.location(base)?;
+                // the left side of an assignment hasn't been folded yet
+                let index = match const_eval(index, &Target::host()).ok()? {
+                    ConstValue::Int(i) => i,
+                    ConstValue::Uint(u) => u as i64,
+                    _ => return None,
+                };
+                Some((param, offset.wrapping_add(index)))
+            }
+            _ => None,
+        }
+    }
+    /// The value of type `ir_type` at `location`, if it's already known in the current block.
+    pub(super) fn get(
+        &mut self,
+        location: (Symbol, i64),
+        ir_type: IrType,
+        builder: &mut FunctionBuilder,
+    ) -> Option<IrValue> {
+        self.enter(builder);
+        match self.values.get(&location) {
+            Some(&(value, known_type)) if known_type == ir_type => Some(value),
+            _ => None,
+        }
+    }
+    /// Remember that `value` was loaded from `location`.
+    pub(super) fn loaded(
+        &mut self,
+        location: (Symbol, i64),
+        value: IrValue,
+        ir_type: IrType,
+        builder: &mut FunctionBuilder,
+    ) {
+        self.enter(builder);
+        self.values.insert(location, (value, ir_type));
+    }
+    /// Record a store to `location`, or to some unknown address if `location` is `None`.
+    ///
+    /// If `value` is set, it's now the value at `location`.
+    pub(super) fn stored(
+        &mut self,
+        location: Option<(Symbol, i64)>,
+        value: Option<(IrValue, IrType)>,
+        builder: &mut FunctionBuilder,
+    ) {
+        self.enter(builder);
+        let location = match location {
+            Some(location) => location,
+            None => return self.values.clear(),
+        };
+        // stores through `p` can overlap anything else loaded through `p`
+        self.values.retain(|&(param, _), _| param != location.0);
+        if let Some(value) = value {
+            self.values.insert(location, value);
+        }
+    }
+    /// Forget all known values, e.g. after a function call.
+    pub(super) fn clear(&mut self) {
+        self.values.clear();
+    }
+    /// Values from another block may not dominate the current one.
+    fn enter(&mut self, builder: &mut FunctionBuilder) {
+        let current = builder.cursor().current_block();
+        if self.block != current {
+            self.values.clear();
+            self.block = current;
+        }
+    }
+}
+
+/// Stop tracking parameters that are assigned to or have their address taken.
+struct Modified<'a> {
+    params: &'a mut HashSet<Symbol>,
+}
+
+impl Visitor for Modified<'_> {
+    fn visit_expr(&mut self, expr: &Expr) {
+        match &expr.expr {
+            // reading the value of the pointer
+            ExprType::Deref(inner) if matches!(inner.expr, ExprType::Id(_)) => {}
+            // `p = q`, `p++`, or `&p`
+            ExprType::Id(symbol) => {
+                self.params.remove(symbol);
+            }
+            _ => walk_expr(self, expr),
+        }
+    }
+    // only expressions are interesting
+    fn visit_type(&mut self, _: &Type) {}
+}
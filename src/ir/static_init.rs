@@ -7,17 +7,17 @@ use cranelift::codegen::ir::types;
 use cranelift_module::{Backend, DataContext, DataId, Linkage};
 
 use super::{Compiler, Id};
-use crate::arch::{PTR_SIZE, TARGET};
-use crate::data::*;
+use crate::arch::{ArchData, StringData, Target, PTR_SIZE};
+use crate::data::{error::Warning, *};
 use crate::data::{
     hir::{Expr, ExprType, Initializer, Symbol},
     lex::Literal,
     types::ArrayType,
     StorageClass,
 };
+use crate::fold::{const_eval, ConstValue};
 
 const_assert!(PTR_SIZE <= std::usize::MAX as u16);
-const ZERO_PTR: [u8; PTR_SIZE as usize] = [0; PTR_SIZE as usize];
 
 impl<B: Backend> Compiler<B> {
     pub(super) fn store_static(
@@ -26,7 +26,17 @@ impl<B: Backend> Compiler<B> {
         init: Option<Initializer>,
         location: Location,
     ) -> CompileResult<()> {
-        use crate::get_str;
+        let name = symbol.get().id.to_string();
+        self.store_static_as(&name, symbol, init, location)
+    }
+    /// Same as `store_static`, but the symbol is named `name` in the object file.
+    pub(super) fn store_static_as(
+        &mut self,
+        name: &str,
+        symbol: Symbol,
+        init: Option<Initializer>,
+        location: Location,
+    ) -> CompileResult<()> {
         let metadata = symbol.get();
         if let StorageClass::Typedef = metadata.storage_class {
             return Ok(());
@@ -48,16 +58,11 @@ impl<B: Backend> Compiler<B> {
             // struct that was declared but never used
             return Ok(());
         }
-        let linkage = metadata.storage_class.try_into().map_err(err_closure)?;
+        let default_linkage = metadata.storage_class.try_into().map_err(err_closure)?;
+        let linkage = self.linkage(&metadata, default_linkage);
         let id = self
             .module
-            .declare_data(
-                get_str!(metadata.id),
-                linkage,
-                !metadata.qualifiers.c_const,
-                false,
-                Some(align),
-            )
+            .declare_data(name, linkage, !metadata.is_const(), false, Some(align))
             .map_err(|err| Locatable {
                 data: format!("error storing static value: {}", err),
                 location,
@@ -65,11 +70,51 @@ impl<B: Backend> Compiler<B> {
 
         self.declarations.insert(symbol, Id::Global(id));
 
-        if linkage == Linkage::Import {
+        // `extern int i __attribute__((weak));` is preemptible, but still not a definition
+        if linkage == Linkage::Import || default_linkage == Linkage::Import {
             debug_assert!(init.is_none());
             return Ok(());
         }
 
+        // 6.9.2p2: `int i;` is a tentative definition, which only defines `i` as zero
+        // if there's no other definition in the rest of the file
+        let init = match init {
+            Some(init) => init,
+            None => {
+                if !self.defined_data.contains(&id)
+                    && !self
+                        .tentative
+                        .iter()
+                        .any(|&(tentative, ..)| tentative == id)
+                {
+                    self.tentative.push((id, symbol, location));
+                }
+                return Ok(());
+            }
+        };
+        self.tentative.retain(|&(tentative, ..)| tentative != id);
+        self.define_static(id, symbol, Some(init), location)
+    }
+    /// Zero-initialize every tentative definition that was never given a value.
+    pub(super) fn define_tentative(&mut self) -> CompileResult<()> {
+        for (id, symbol, location) in std::mem::take(&mut self.tentative) {
+            self.define_static(id, symbol, None, location)?;
+        }
+        Ok(())
+    }
+    fn define_static(
+        &mut self,
+        id: DataId,
+        symbol: Symbol,
+        init: Option<Initializer>,
+        location: Location,
+    ) -> CompileResult<()> {
+        let metadata = symbol.get();
+        let err_closure = |err| Locatable {
+            data: err,
+            location,
+        };
+        self.defined_data.insert(id);
         let mut ctx = DataContext::new();
         // TODO: all of this should happen in the `analyze` module
         if let Some(init) = init {
@@ -79,6 +124,7 @@ impl<B: Backend> Compiler<B> {
                     Initializer::InitializerList(list) => Some(list.len()),
                     Initializer::Scalar(expr) => match &expr.expr {
                         ExprType::Literal(Literal::Str(s)) => Some(s.len()),
+                        ExprType::Literal(Literal::WideStr(_, units)) => Some(units.len()),
                         _ => None,
                     },
                     _ => None,
@@ -112,13 +158,16 @@ impl<B: Backend> Compiler<B> {
             })
         })
     }
+    /// Store a string literal, materialized by `ArchData::string_data`, in read-only data.
     pub(super) fn compile_string(
         &mut self,
-        string: Vec<u8>,
+        string: StringData,
         location: Location,
     ) -> CompileResult<DataId> {
         use std::collections::hash_map::Entry;
         let len = self.strings.len();
+        // wide strings have to be aligned to their elements
+        let align = string.element_size.try_into().ok();
         // TODO: it seems silly for both us and cranelift to store the string
         let (string, str_id) = match self.strings.entry(string) {
             Entry::Occupied(id) => return Ok(*id.get()),
@@ -126,7 +175,7 @@ impl<B: Backend> Compiler<B> {
                 let name = format!("str.{}", len);
                 let id = match self
                     .module
-                    .declare_data(&name, Linkage::Local, false, false, None)
+                    .declare_data(&name, Linkage::Local, false, false, align)
                 {
                     Ok(id) => id,
                     Err(err) => {
@@ -139,7 +188,7 @@ impl<B: Backend> Compiler<B> {
             }
         };
         let mut ctx = DataContext::new();
-        ctx.define(string.into_boxed_slice());
+        ctx.define(string.bytes.into_boxed_slice());
         self.module
             .define_data(str_id, &ctx)
             .map_err(|err| Locatable {
@@ -156,46 +205,69 @@ impl<B: Backend> Compiler<B> {
         expr: Expr,
     ) -> CompileResult<()> {
         let expr = expr.const_fold()?;
+        if let ExprType::Literal(token) = expr.expr {
+            token.write_bytes(
+                buf,
+                &expr.ctype,
+                &expr.location,
+                &mut self.error_handler,
+                &self.arch,
+            );
+            return Ok(());
+        }
         // static address-of
-        match expr.expr {
-            ExprType::StaticRef(inner) => match inner.expr {
-                ExprType::Id(symbol) => self.static_ref(symbol, 0, offset, ctx),
-                ExprType::Literal(Literal::Str(str_ref)) => {
-                    let str_id = self.compile_string(str_ref, expr.location)?;
-                    let str_addr = self.module.declare_data_in_data(str_id, ctx);
-                    ctx.write_data_addr(offset, str_addr, 0);
-                }
-                ExprType::Literal(ref token) if token.is_zero() => buf.copy_from_slice(&ZERO_PTR),
-                ExprType::Cast(ref inner) if inner.is_zero() => buf.copy_from_slice(&ZERO_PTR),
-                ExprType::Member(struct_expr, member) => {
-                    let member_offset = struct_expr
-                        .ctype
-                        .member_offset(member)
-                        .expect("parser shouldn't allow Member for non-struct types");
-                    if let ExprType::Id(symbol) = struct_expr.expr {
-                        self.static_ref(symbol, member_offset.try_into().unwrap(), offset, ctx);
-                    } else {
-                        semantic_err!(
-                            "expression is not a compile time constant".into(),
-                            struct_expr.location
-                        );
+        match const_eval(&expr, &Target::host())? {
+            ConstValue::Address {
+                symbol,
+                offset: addend,
+            } => self.static_ref(buf, symbol, addend, offset, ctx),
+            ConstValue::Str(bytes) => {
+                // `static wchar_t *p = L"abc";`
+                let element_size = match &expr.ctype {
+                    Type::Pointer(element, _) | Type::Array(element, _) => {
+                        element.sizeof().unwrap_or(1) as usize
                     }
-                }
-                _ => semantic_err!("cannot take the address of an rvalue".into(), expr.location),
-            },
-            ExprType::Literal(token) => {
-                let bytes =
-                    token.into_bytes(&expr.ctype, &expr.location, &mut self.error_handler)?;
-                buf.copy_from_slice(&bytes);
+                    _ => 1,
+                };
+                let string = StringData {
+                    bytes,
+                    element_size,
+                };
+                let str_id = self.compile_string(string, expr.location)?;
+                let str_addr = self.module.declare_data_in_data(str_id, ctx);
+                self.arch.write_ptr(buf, 0);
+                ctx.write_data_addr(offset, str_addr, 0);
+            }
+            // e.g. `offsetof`, or a null pointer with an offset
+            ConstValue::Int(i) => self.write_integer(buf, i as u64, &expr.ctype),
+            ConstValue::Uint(u) => self.write_integer(buf, u, &expr.ctype),
+            ConstValue::Float(_) => {
+                semantic_err!("cannot take the address of an rvalue".into(), expr.location)
             }
-            _ => semantic_err!(
-                "expression is not a compile time constant".into(),
-                expr.location
-            ),
         }
         Ok(())
     }
-    fn static_ref(&self, symbol: Symbol, member_offset: i64, offset: u32, ctx: &mut DataContext) {
+    /// Write `value` as an integer or pointer of type `ctype`.
+    fn write_integer(&self, buf: &mut [u8], value: u64, ctype: &Type) {
+        if let Type::Pointer(..) = ctype {
+            self.arch.write_ptr(buf, value);
+        } else {
+            let size = ctype.as_ir_type().bytes() as usize;
+            self.arch.write_int(buf, value, size);
+        }
+    }
+    /// Write the address of `symbol` to `buf`, which is at `offset` in the data being defined.
+    ///
+    /// The address isn't known until link time, so this writes a placeholder and records a relocation.
+    fn static_ref(
+        &self,
+        buf: &mut [u8],
+        symbol: Symbol,
+        member_offset: i64,
+        offset: u32,
+        ctx: &mut DataContext,
+    ) {
+        self.arch.write_ptr(buf, 0);
         match self.declarations.get(&symbol) {
             Some(Id::Function(func_id)) => {
                 let func_ref = self.module.declare_func_in_data(*func_id, ctx);
@@ -206,7 +278,9 @@ impl<B: Backend> Compiler<B> {
                 let global_val = self.module.declare_data_in_data(*data_id, ctx);
                 ctx.write_data_addr(offset, global_val, member_offset);
             }
-            Some(Id::Local(_)) => unreachable!("cannot have local variable at global scope"),
+            Some(Id::Local(_)) => {
+                unreachable!("cannot have local variable at global scope")
+            }
             None => unreachable!("parser should catch undeclared variables"),
         }
     }
@@ -214,7 +288,7 @@ impl<B: Backend> Compiler<B> {
         &mut self,
         ctx: &mut DataContext,
         buf: &mut [u8],
-        mut offset: u32,
+        offset: u32,
         initializer: Initializer,
         ctype: &Type,
         location: &Location,
@@ -252,22 +326,29 @@ impl<B: Backend> Compiler<B> {
                     location,
                 ),
                 Type::Struct(struct_ref) => {
-                    let mut current_offset = 0;
                     for (member, init) in struct_ref.members().iter().zip(initializers.into_iter())
                     {
                         let size_host: usize = member
                             .ctype
                             .sizeof()
                             .map_err(|err| CompileError::semantic(location.with(err.to_string())))?
-                            .try_into()
-                            .expect("cannot initialize struct larger than u32");
-                        let size: u32 = size_host
                             .try_into()
                             .expect("cannot intialize struct larger than host address space");
+                        // members may be padded to their alignment
+                        let member_offset: u32 = struct_ref
+                            .offset(member.id)
+                            .try_into()
+                            .expect("cannot initialize struct larger than u32");
+                        let current_offset = member_offset as usize;
                         let buf_slice = &mut buf[current_offset..current_offset + size_host];
-                        self.init_symbol(ctx, buf_slice, offset, init, &member.ctype, location)?;
-                        offset += size;
-                        current_offset += size_host;
+                        self.init_symbol(
+                            ctx,
+                            buf_slice,
+                            offset + member_offset,
+                            init,
+                            &member.ctype,
+                            location,
+                        )?;
                     }
                     Ok(())
                 }
@@ -275,7 +356,7 @@ impl<B: Backend> Compiler<B> {
                 Type::Void => unreachable!("initializer for void type"),
                 _ => unreachable!("scalar types should have been handled"),
             },
-            Initializer::Scalar(expr) => self.init_expr(ctx, buf, offset, *expr),
+            Initializer::Scalar(expr) => self.init_expr(ctx, buf, offset, expr.take()),
             Initializer::FunctionBody(_) => {
                 panic!("function definitions should go through compile_function, not store_static")
             }
@@ -325,13 +406,14 @@ impl<B: Backend> Compiler<B> {
 }
 
 macro_rules! cast {
-    ($i: expr, $from: ty, $to: ty, $ctype: expr, $location: expr, $handler: expr) => {{
+    ($i: expr, $from: ty, $to: ty, $literal: path, $ctype: expr, $location: expr, $handler: expr) => {{
         let cast = $i as $to;
         if cast as $from != $i {
             $handler.warn(
-                &format!(
-                    "conversion to {} loses precision ({} != {})",
-                    $ctype, cast as $from, $i
+                Warning::LossyConstantConversion(
+                    Type::clone($ctype),
+                    $literal(cast as $from),
+                    $literal($i),
                 ),
                 $location,
             )
@@ -340,90 +422,105 @@ macro_rules! cast {
     }};
 }
 
-macro_rules! bytes {
-    ($int: expr, $be: expr) => {{
-        let boxed: Box<[u8]> = if $be {
-            Box::new($int.to_be_bytes())
-        } else {
-            Box::new($int.to_le_bytes())
-        };
-        boxed
-    }};
-}
-
 impl Literal {
-    fn into_bytes(
+    /// Write this literal to the start of `buf` using the byte order of `arch`.
+    fn write_bytes(
         self,
+        buf: &mut [u8],
         ctype: &Type,
         location: &Location,
         error_handler: &mut ErrorHandler,
-    ) -> CompileResult<Box<[u8]>> {
+        arch: &ArchData,
+    ) {
         let ir_type = ctype.as_ir_type();
-        let big_endian = TARGET
-            .endianness()
-            .expect("target should be big or little endian")
-            == target_lexicon::Endianness::Big;
+        let size = ir_type.bytes() as usize;
 
         match self {
-            Literal::Int(i) => Ok(match ir_type {
-                types::I8 => bytes!(
-                    cast!(i, i64, i8, &ctype, *location, error_handler),
-                    big_endian
-                ),
-                types::I16 => bytes!(
-                    cast!(i, i64, i16, &ctype, *location, error_handler),
-                    big_endian
-                ),
-                types::I32 => bytes!(
-                    cast!(i, i64, i32, &ctype, *location, error_handler),
-                    big_endian
-                ),
-                types::I64 => bytes!(i, big_endian),
-                x => unreachable!(format!(
-                    "ir_type {} for integer {} is not of integer type",
-                    x, i
-                )),
-            }),
-            Literal::UnsignedInt(i) => Ok(match ir_type {
-                types::I8 => bytes!(
-                    cast!(i, u64, u8, &ctype, *location, error_handler),
-                    big_endian
-                ),
-                types::I16 => bytes!(
-                    cast!(i, u64, u16, &ctype, *location, error_handler),
-                    big_endian
-                ),
-                types::I32 => bytes!(
-                    cast!(i, u64, u32, &ctype, *location, error_handler),
-                    big_endian
-                ),
-                types::I64 => bytes!(i, big_endian),
-                x => unreachable!(format!(
-                    "ir_type {} for integer {} is not of integer type",
-                    x, i
-                )),
-            }),
-            Literal::Float(f) => Ok(match ir_type {
+            Literal::Int(i) => {
+                let value = match ir_type {
+                    types::I8 => {
+                        cast!(i, i64, i8, Literal::Int, &ctype, *location, error_handler).into()
+                    }
+                    types::I16 => {
+                        cast!(i, i64, i16, Literal::Int, &ctype, *location, error_handler).into()
+                    }
+                    types::I32 => {
+                        cast!(i, i64, i32, Literal::Int, &ctype, *location, error_handler).into()
+                    }
+                    types::I64 => i,
+                    x => unreachable!(format!(
+                        "ir_type {} for integer {} is not of integer type",
+                        x, i
+                    )),
+                };
+                arch.write_int(buf, value as u64, size);
+            }
+            Literal::UnsignedInt(i) => {
+                let value = match ir_type {
+                    types::I8 => cast!(
+                        i,
+                        u64,
+                        u8,
+                        Literal::UnsignedInt,
+                        &ctype,
+                        *location,
+                        error_handler
+                    )
+                    .into(),
+                    types::I16 => cast!(
+                        i,
+                        u64,
+                        u16,
+                        Literal::UnsignedInt,
+                        &ctype,
+                        *location,
+                        error_handler
+                    )
+                    .into(),
+                    types::I32 => cast!(
+                        i,
+                        u64,
+                        u32,
+                        Literal::UnsignedInt,
+                        &ctype,
+                        *location,
+                        error_handler
+                    )
+                    .into(),
+                    types::I64 => i,
+                    x => unreachable!(format!(
+                        "ir_type {} for integer {} is not of integer type",
+                        x, i
+                    )),
+                };
+                arch.write_int(buf, value, size);
+            }
+            Literal::Float(f) => match ir_type {
                 types::F32 => {
                     let cast = f as f32;
                     if (f64::from(cast) - f).abs() >= std::f64::EPSILON {
-                        let warning = format!(
-                            "conversion from double to float loses precision ({} is different from {} by more than DBL_EPSILON ({}))",
-                            f, std::f64::EPSILON, f64::from(cast)
-                        );
-                        error_handler.warn(&warning, *location);
+                        error_handler.warn(Warning::LossyFloatConversion(f), *location);
                     }
-                    let float_as_int = cast.to_bits();
-                    bytes!(float_as_int, big_endian)
+                    arch.write_float(buf, f, size);
                 }
-                types::F64 => bytes!(f.to_bits(), big_endian),
+                types::F64 => arch.write_float(buf, f, size),
                 x => unreachable!(format!(
                     "ir_type {} for float {} is not of integer type",
                     x, f
                 )),
-            }),
-            Literal::Str(string) => Ok(string.into_boxed_slice()),
-            Literal::Char(c) => Ok(Box::new([c])),
+            },
+            Literal::Str(_) | Literal::WideStr(..) => {
+                let string = arch
+                    .string_data(&self)
+                    .expect("elements that are too large should be caught by the analyzer");
+                // `char s[3] = "abc";` leaves off the null terminator
+                let len = string.bytes.len().min(buf.len());
+                buf[..len].copy_from_slice(&string.bytes[..len])
+            }
+            Literal::Char(c) => buf[0] = c,
+            Literal::SingleFloat(_) => {
+                unreachable!("`float` constants are lowered by the analyzer")
+            }
         }
     }
 }
@@ -443,3 +540,46 @@ impl TryFrom<StorageClass> for Linkage {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn initializer_bytes(literal: Literal, ctype: Type, target: &str) -> Vec<u8> {
+        let arch = ArchData::new(&target.parse().unwrap());
+        let mut buf = vec![0; ctype.sizeof().unwrap() as usize];
+        let mut error_handler = ErrorHandler::default();
+        literal.write_bytes(
+            &mut buf,
+            &ctype,
+            &Location::default(),
+            &mut error_handler,
+            &arch,
+        );
+        buf
+    }
+
+    #[test]
+    fn big_endian_initializers() {
+        let little = "x86_64-unknown-linux-gnu";
+        let big = "powerpc64-unknown-linux-gnu";
+        for (literal, ctype) in [
+            (Literal::Int(0x1234_5678), Type::Int(true)),
+            (Literal::Int(-2), Type::Short(true)),
+            (
+                Literal::UnsignedInt(0x0102_0304_0506_0708),
+                Type::Long(false),
+            ),
+            (Literal::Float(1.5), Type::Float),
+            (Literal::Float(-0.1), Type::Double),
+        ] {
+            let mut swapped = initializer_bytes(literal.clone(), ctype.clone(), little);
+            swapped.reverse();
+            assert_eq!(initializer_bytes(literal, ctype, big), swapped);
+        }
+        assert_eq!(
+            initializer_bytes(Literal::Int(1), Type::Int(true), big),
+            [0, 0, 0, 1]
+        );
+    }
+}
@@ -1,13 +1,15 @@
 use cranelift::codegen::cursor::Cursor;
 use cranelift::frontend::Switch;
-use cranelift::prelude::{Block, FunctionBuilder, InstBuilder};
+use cranelift::prelude::{Block, FunctionBuilder, InstBuilder, MemFlags};
 use cranelift_module::Backend;
+use std::convert::TryFrom;
 
 use super::Compiler;
 use crate::data::{
     hir::{Expr, Stmt, StmtType},
     *,
 };
+use crate::ice;
 
 impl<B: Backend> Compiler<B> {
     pub(super) fn compile_all(
@@ -25,8 +27,17 @@ impl<B: Backend> Compiler<B> {
         stmt: Stmt,
         builder: &mut FunctionBuilder,
     ) -> CompileResult<()> {
-        if builder.is_filled() && !stmt.data.is_jump_target() {
-            return Err(stmt.location.error(SemanticError::UnreachableStatement));
+        ice::set_location(stmt.location);
+        if builder.is_filled() {
+            if !stmt.data.is_jump_target() {
+                return Err(stmt.location.error(SemanticError::UnreachableStatement));
+            }
+            // `goto inside; { int x = 1; inside: ; }`
+            // the start of the block is dead code, but the label inside it isn't
+            if let StmtType::Compound(_) = stmt.data {
+                let dead = builder.create_block();
+                builder.switch_to_block(dead);
+            }
         }
         match stmt.data {
             StmtType::Compound(stmts) => self.compile_all(stmts, builder),
@@ -41,62 +52,82 @@ impl<B: Backend> Compiler<B> {
                 self.compile_expr(expr, builder)?;
                 Ok(())
             }
+            StmtType::Return(Some(expr)) if self.is_self_tail_call(&expr) => {
+                self.compile_tail_call(expr, builder)
+            }
             StmtType::Return(expr) => {
                 let mut ret = vec![];
                 if let Some(e) = expr {
+                    let (ctype, location) = (e.ctype.clone(), e.location);
                     let val = self.compile_expr(e, builder)?;
-                    ret.push(val.ir_val);
+                    // `return s;`: copy `s` to the caller's return slot
+                    if let Some(dest) = self.struct_return {
+                        let size = ctype
+                            .sizeof()
+                            .map_err(|err| location.with(err.to_string()))?;
+                        let align = ctype
+                            .alignof()
+                            .expect("if sizeof() succeeds so should alignof()");
+                        let align =
+                            u8::try_from(align).expect("align should never be more than 255");
+                        self.copy_memory(dest, val.ir_val, size, align, builder);
+                        ret.push(dest);
+                    } else if super::is_aggregate(&ctype) {
+                        // small structs and unions are returned in registers, an eightbyte at a time
+                        let copy = self.copy_aggregate(val.ir_val, &ctype, location, builder)?;
+                        let ir_types = super::abi::argument_types(&ctype);
+                        for (i, ir_type) in ir_types.into_iter().enumerate() {
+                            let offset = i as i32 * super::abi::EIGHTBYTE as i32;
+                            ret.push(builder.ins().load(ir_type, MemFlags::new(), copy, offset));
+                        }
+                    } else {
+                        ret.push(val.ir_val);
+                    }
                 }
                 builder.ins().return_(&ret);
                 Ok(())
             }
             StmtType::If(condition, body, otherwise) => {
-                self.if_stmt(condition, *body, otherwise, builder)
+                self.if_stmt(condition, body.take(), otherwise, builder)
+            }
+            StmtType::While(condition, body) => {
+                self.while_stmt(Some(condition), None, body.take(), builder)
             }
-            StmtType::While(condition, body) => self.while_stmt(Some(condition), *body, builder),
             StmtType::Break | StmtType::Continue => {
                 self.loop_exit(stmt.data == StmtType::Break, stmt.location, builder)
             }
             StmtType::For(init, condition, post_loop, body) => self.for_loop(
-                *init,
-                condition.map(|e| *e),
-                post_loop.map(|e| *e),
-                *body,
-                stmt.location,
+                init.take(),
+                condition.map(Expr::take),
+                post_loop.map(Expr::take),
+                body.take(),
                 builder,
             ),
-            StmtType::Do(body, condition) => self.do_loop(*body, condition, builder),
-            StmtType::Switch(condition, body) => self.switch(condition, *body, builder),
+            StmtType::Do(body, condition) => self.do_loop(body.take(), condition, builder),
+            StmtType::Switch(condition, body) => self.switch(condition, body.take(), builder),
+            // the analyzer has already checked that each label is declared exactly once
             StmtType::Label(name, inner) => {
-                let new_block = builder.create_block();
-                Self::jump_to_block(new_block, builder);
-                builder.switch_to_block(new_block);
-                if let Some(previous) = self.labels.insert(name, new_block) {
-                    Err(stmt
-                        .location
-                        .error(SemanticError::LabelRedeclaration(previous)))
-                } else {
-                    self.compile_stmt(*inner, builder)
-                }
+                let block = self.label_block(name, builder);
+                Self::jump_to_block(block, builder);
+                builder.switch_to_block(block);
+                self.compile_stmt(inner.take(), builder)
+            }
+            StmtType::Goto(name) => {
+                let block = self.label_block(name, builder);
+                Self::jump_to_block(block, builder);
+                Ok(())
             }
-            StmtType::Goto(name) => match self.labels.get(&name) {
-                Some(block) => {
-                    Self::jump_to_block(*block, builder);
-                    Ok(())
-                }
-                None => Err(stmt.location.error(SemanticError::UndeclaredLabel(name))),
-            },
             StmtType::Case(constexpr, inner) => {
-                self.case(constexpr, *inner, stmt.location, builder)
+                self.case(constexpr, inner.take(), stmt.location, builder)
             }
-            StmtType::Default(inner) => self.default(*inner, stmt.location, builder),
+            StmtType::Default(inner) => self.default(inner.take(), stmt.location, builder),
         }
     }
     fn if_stmt(
         &mut self,
         condition: Expr,
         body: Stmt,
-        otherwise: Option<Box<Stmt>>,
+        otherwise: Option<&mut Stmt>,
         builder: &mut FunctionBuilder,
     ) -> CompileResult<()> {
         // If condtion is zero:
@@ -119,7 +150,7 @@ impl<B: Backend> Compiler<B> {
             Self::jump_to_block(end_body, builder);
 
             builder.switch_to_block(else_body);
-            self.compile_stmt(*other, builder)?;
+            self.compile_stmt(other.take(), builder)?;
             if !builder.is_filled() {
                 builder.ins().jump(end_body, &[]);
                 builder.switch_to_block(end_body);
@@ -143,10 +174,16 @@ impl<B: Backend> Compiler<B> {
     /// Enter a loop context:
     /// - Create a new start and end block
     /// - Switch to the start block
+    /// - `continue` jumps to `continue_block` if given, or to the start block otherwise
     /// - Return (start, end, previous_last_saw_loop)
-    fn enter_loop(&mut self, builder: &mut FunctionBuilder) -> (Block, Block, bool) {
+    fn enter_loop(
+        &mut self,
+        continue_block: Option<Block>,
+        builder: &mut FunctionBuilder,
+    ) -> (Block, Block, bool) {
         let (loop_body, end_body) = (builder.create_block(), builder.create_block());
-        self.loops.push((loop_body, end_body));
+        self.loops
+            .push((continue_block.unwrap_or(loop_body), end_body));
         let old_saw_loop = self.last_saw_loop;
         self.last_saw_loop = true;
 
@@ -159,13 +196,17 @@ impl<B: Backend> Compiler<B> {
         self.loops.pop();
         self.last_saw_loop = old_saw_loop;
     }
+    /// `while (condition) body`, or `for (; condition; post_loop) body`
     fn while_stmt(
         &mut self,
         maybe_condition: Option<Expr>,
+        post_loop: Option<Expr>,
         body: Stmt,
         builder: &mut FunctionBuilder,
     ) -> CompileResult<()> {
-        let (loop_body, end_body, old_saw_loop) = self.enter_loop(builder);
+        // `continue` in a `for` loop still runs the post-loop expression
+        let post_block = post_loop.as_ref().map(|_| builder.create_block());
+        let (loop_body, end_body, old_saw_loop) = self.enter_loop(post_block, builder);
 
         // for loops can loop forever: `for (;;) {}`
         if let Some(condition) = maybe_condition {
@@ -175,6 +216,11 @@ impl<B: Backend> Compiler<B> {
         }
 
         self.compile_stmt(body, builder)?;
+        if let (Some(post_block), Some(post_loop)) = (post_block, post_loop) {
+            Self::jump_to_block(post_block, builder);
+            builder.switch_to_block(post_block);
+            self.compile_expr(post_loop, builder)?;
+        }
         Self::jump_to_block(loop_body, builder);
 
         builder.switch_to_block(end_body);
@@ -192,7 +238,9 @@ impl<B: Backend> Compiler<B> {
         condition: Expr,
         builder: &mut FunctionBuilder,
     ) -> CompileResult<()> {
-        let (loop_body, end_body, old_saw_loop) = self.enter_loop(builder);
+        // `continue` jumps to the condition, not the start of the body
+        let condition_block = builder.create_block();
+        let (loop_body, end_body, old_saw_loop) = self.enter_loop(Some(condition_block), builder);
 
         self.compile_stmt(body, builder)?;
         if builder.is_filled() {
@@ -200,6 +248,8 @@ impl<B: Backend> Compiler<B> {
                 .location
                 .error(SemanticError::UnreachableStatement));
         }
+        builder.ins().jump(condition_block, &[]);
+        builder.switch_to_block(condition_block);
         let condition = self.compile_expr(condition, builder)?;
         builder.ins().brz(condition.ir_val, end_body, &[]);
         Self::jump_to_block(loop_body, builder);
@@ -208,35 +258,16 @@ impl<B: Backend> Compiler<B> {
         self.exit_loop(old_saw_loop);
         Ok(())
     }
-    fn for_loop(
+    fn for_loop<'hir>(
         &mut self,
-        init: Stmt,
-        condition: Option<Expr>,
-        post_loop: Option<Expr>,
-        mut body: Stmt,
-        location: Location,
+        init: Stmt<'hir>,
+        condition: Option<Expr<'hir>>,
+        post_loop: Option<Expr<'hir>>,
+        body: Stmt<'hir>,
         builder: &mut FunctionBuilder,
     ) -> CompileResult<()> {
         self.compile_stmt(init, builder)?;
-        if let Some(post_loop) = post_loop {
-            let post_loop = Stmt {
-                data: StmtType::Expr(post_loop),
-                location,
-            };
-            if let Stmt {
-                data: StmtType::Compound(stmts),
-                ..
-            } = &mut body
-            {
-                stmts.push(post_loop);
-            } else {
-                body = Stmt {
-                    data: StmtType::Compound(vec![body, post_loop]),
-                    location,
-                };
-            };
-        }
-        self.while_stmt(condition, body, builder)
+        self.while_stmt(condition, post_loop, body, builder)
     }
     fn switch(
         &mut self,
@@ -290,7 +321,10 @@ impl<B: Backend> Compiler<B> {
             }
         };
         if switch.entries().contains_key(&constexpr) {
-            return Err(location.error(SemanticError::DuplicateCase { is_default: false }));
+            return Err(location.error(SemanticError::DuplicateCase {
+                value: constexpr.into(),
+                previous: None,
+            }));
         }
         if builder.is_pristine() {
             let current = builder.cursor().current_block().unwrap();
@@ -316,7 +350,7 @@ impl<B: Backend> Compiler<B> {
             }
         };
         if default.is_some() {
-            Err(location.error(SemanticError::DuplicateCase { is_default: true }))
+            Err(location.error(SemanticError::DuplicateDefault(None)))
         } else {
             let default_block = if builder.is_pristine() {
                 builder.cursor().current_block().unwrap()
@@ -336,35 +370,32 @@ impl<B: Backend> Compiler<B> {
         location: Location,
         builder: &mut FunctionBuilder,
     ) -> CompileResult<()> {
-        if self.last_saw_loop {
-            // break from loop
-            if let Some((loop_start, loop_end)) = self.loops.last() {
-                if is_break {
-                    Self::jump_to_block(*loop_end, builder);
-                } else {
-                    Self::jump_to_block(*loop_start, builder);
-                }
-                Ok(())
-            } else {
-                semantic_err!(
-                    format!(
-                        "'{}' statement not in loop or switch statement",
-                        if is_break { "break" } else { "continue" }
-                    ),
-                    location
-                );
-            }
-        } else if !is_break {
-            semantic_err!("'continue' not in loop".into(), location);
-        } else {
+        // `continue` always goes to the innermost loop, even inside a `switch`
+        if is_break && !self.last_saw_loop {
             // break from switch
             let (_, _, end_block) = self
                 .switches
                 .last()
                 .expect("should be in a switch if last_saw_loop is false");
             builder.ins().jump(*end_block, &[]);
-            Ok(())
+            return Ok(());
         }
+        match self.loops.last() {
+            Some((continue_block, end_block)) => {
+                let target = if is_break { end_block } else { continue_block };
+                Self::jump_to_block(*target, builder);
+                Ok(())
+            }
+            None if is_break => Err(location.error(SemanticError::BreakOutsideLoop)),
+            None => Err(location.error(SemanticError::ContinueOutsideLoop)),
+        }
+    }
+    /// The block for the label `name`, which may not have been seen yet: `goto end; ... end:`
+    fn label_block(&mut self, name: InternedStr, builder: &mut FunctionBuilder) -> Block {
+        *self
+            .labels
+            .entry(name)
+            .or_insert_with(|| builder.create_block())
     }
     #[inline]
     fn jump_to_block(block: Block, builder: &mut FunctionBuilder) {
@@ -374,10 +405,11 @@ impl<B: Backend> Compiler<B> {
     }
 }
 
-impl StmtType {
+impl StmtType<'_> {
     fn is_jump_target(&self) -> bool {
         match self {
             StmtType::Case(_, _) | StmtType::Default(_) | StmtType::Label(_, _) => true,
+            StmtType::Compound(stmts) => stmts.iter().any(|stmt| stmt.data.is_jump_target()),
             _ => false,
         }
     }
@@ -0,0 +1,159 @@
+//! Turn self-recursive tail calls into loops.
+//!
+//! `return f(args...)` in the body of `f` is compiled as
+//! 'evaluate `args`, store them in the parameters, jump to the start of the function'.
+//! This keeps accumulator-style recursion from overflowing the stack.
+//!
+//! This is only done when none of the parameters have their address taken,
+//! since otherwise a pointer into the previous call could see the new values,
+//! and when the function never calls a `returns_twice` function such as `setjmp`.
+use cranelift::prelude::{Block, FunctionBuilder, InstBuilder};
+use cranelift_module::Backend;
+
+use super::{Compiler, Id};
+use crate::data::{
+    hir::{walk_expr, walk_initializer, Expr, ExprType, Initializer, Stmt, Symbol, Visitor},
+    types::FunctionType,
+    *,
+};
+
+/// The function currently being compiled, if its tail calls can be optimized.
+pub(super) struct TailCall {
+    func: Symbol,
+    params: Vec<Symbol>,
+    /// The block right after the parameters are stored to the stack
+    body: Block,
+}
+
+impl<B: Backend> Compiler<B> {
+    /// If `func` is eligible for tail call optimization, start a new block
+    /// for `return func(...)` to jump to.
+    ///
+    /// This should be called after the parameters have been stored.
+    pub(super) fn start_tail_calls(
+        &mut self,
+        func: Symbol,
+        func_type: &FunctionType,
+        stmts: &[Stmt],
+        builder: &mut FunctionBuilder,
+    ) {
+        let params = if func_type.has_params() {
+            func_type.params.clone()
+        } else {
+            Vec::new()
+        };
+        if !self.optimize_sibling_calls
+            || func_type.varargs
+            || params.iter().any(|param| !param.get().ctype.is_scalar())
+            || stmts.iter().any(|stmt| takes_address(stmt, &params))
+            || stmts.iter().any(calls_returns_twice)
+        {
+            self.tail_call = None;
+            return;
+        }
+        let body = builder.create_block();
+        builder.ins().jump(body, &[]);
+        builder.switch_to_block(body);
+        self.tail_call = Some(TailCall { func, params, body });
+    }
+    /// Whether `expr` can be compiled with `compile_tail_call`
+    pub(super) fn is_self_tail_call(&self, expr: &Expr) -> bool {
+        let tail_call = match &self.tail_call {
+            Some(tail_call) => tail_call,
+            None => return false,
+        };
+        match &expr.expr {
+            ExprType::FuncCall(func, args) => {
+                func.expr == ExprType::Id(tail_call.func)
+                    && args.len() == tail_call.params.len()
+                    // old-style functions aren't converted to the type of the parameter
+                    && args
+                        .iter()
+                        .zip(&tail_call.params)
+                        .all(|(arg, param)| arg.ctype == param.get().ctype)
+            }
+            _ => false,
+        }
+    }
+    /// Compile `return func(args...)` as a jump back to the start of `func`.
+    pub(super) fn compile_tail_call(
+        &mut self,
+        expr: Expr,
+        builder: &mut FunctionBuilder,
+    ) -> CompileResult<()> {
+        let args = match expr.expr {
+            ExprType::FuncCall(_, args) => args,
+            _ => unreachable!("is_self_tail_call should be checked first"),
+        };
+        // evaluate all the arguments before overwriting any of the parameters,
+        // since the arguments may use the old values
+        let vals = args
+            .into_iter()
+            .map(|arg| self.compile_expr(arg, builder).map(|val| val.ir_val))
+            .collect::<CompileResult<Vec<_>>>()?;
+        let tail_call = self
+            .tail_call
+            .as_ref()
+            .expect("is_self_tail_call should be checked first");
+        for (param, val) in tail_call.params.iter().zip(vals) {
+            let slot = match self.declarations.get(param) {
+                Some(Id::Local(slot)) => *slot,
+                _ => unreachable!("parameters should always be stored on the stack"),
+            };
+            let addr = builder.ins().stack_addr(Type::ptr_type(), slot, 0);
+            super::store(val, addr, builder);
+        }
+        builder.ins().jump(tail_call.body, &[]);
+        Ok(())
+    }
+}
+
+/// Whether any expression in `stmt` (including subexpressions) satisfies `pred`
+fn stmt_contains(stmt: &Stmt, pred: &impl Fn(&Expr) -> bool) -> bool {
+    struct Contains<'a, F> {
+        pred: &'a F,
+        found: bool,
+    }
+    impl<F: Fn(&Expr) -> bool> Visitor for Contains<'_, F> {
+        fn visit_initializer(&mut self, init: &Initializer) {
+            if let Initializer::FunctionBody(_) = init {
+                unreachable!("nested functions should be rejected by the analyzer");
+            }
+            walk_initializer(self, init);
+        }
+        fn visit_expr(&mut self, expr: &Expr) {
+            if !self.found {
+                self.found = (self.pred)(expr);
+                walk_expr(self, expr);
+            }
+        }
+        // only expressions are interesting
+        fn visit_type(&mut self, _: &Type) {}
+    }
+
+    let mut contains = Contains { pred, found: false };
+    contains.visit_stmt(stmt);
+    contains.found
+}
+
+fn takes_address(stmt: &Stmt, params: &[Symbol]) -> bool {
+    stmt_contains(stmt, &|expr| match expr.expr {
+        // variables are always lvalues unless their address was taken (or they're arrays or functions)
+        ExprType::Id(symbol) => !expr.lval && params.contains(&symbol),
+        _ => false,
+    })
+}
+
+/// Whether `stmt` calls a function like `setjmp` that can return more than once.
+///
+/// Jumping back into a frame that has been reused by a tail call
+/// would see the parameters of the wrong call, so GCC also disables sibling calls here.
+fn calls_returns_twice(stmt: &Stmt) -> bool {
+    stmt_contains(stmt, &|expr| match &expr.expr {
+        ExprType::FuncCall(func, _) => match func.expr {
+            ExprType::Id(symbol) => symbol.get().qualifiers.func.returns_twice,
+            _ => false,
+        },
+        _ => false,
+    })
+}
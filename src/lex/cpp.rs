@@ -34,12 +34,15 @@ use std::rc::Rc;
 use super::files::FileProcessor;
 use super::replace::{replace, Definition, Definitions};
 use super::{Lexer, Token};
-use crate::arch::TARGET;
+use crate::arch::{Target, TARGET};
 use crate::data::error::CppError;
-use crate::data::lex::{Keyword, Literal};
+use crate::data::lex::{Keyword, Literal, Span};
+use crate::data::source::Expansion;
 use crate::data::*;
+use crate::fold::{const_eval, ConstValue};
 use crate::get_str;
-use crate::Files;
+use crate::stats::{self, Phase};
+use crate::SourceMap;
 
 /// An easier interface for constructing a preprocessor.
 ///
@@ -63,6 +66,8 @@ pub struct PreProcessorBuilder<'a> {
     search_path: Vec<Cow<'a, Path>>,
     /// The user-defined macros that should be defined at startup
     definitions: Definitions,
+    /// The version of C to accept
+    standard: Standard,
 }
 
 impl<'a> PreProcessorBuilder<'a> {
@@ -73,6 +78,7 @@ impl<'a> PreProcessorBuilder<'a> {
             buf: buf.into(),
             search_path: Vec::new(),
             definitions: Definitions::new(),
+            standard: Standard::default(),
         }
     }
     pub fn filename<P: Into<PathBuf>>(mut self, name: P) -> Self {
@@ -91,14 +97,20 @@ impl<'a> PreProcessorBuilder<'a> {
         self.definitions.insert(name, def.into());
         self
     }
+    pub fn standard(mut self, standard: Standard) -> Self {
+        self.standard = standard;
+        self
+    }
     pub fn build(self) -> PreProcessor<'a> {
-        PreProcessor::new(
+        let mut cpp = PreProcessor::new(
             self.buf,
             self.filename,
             self.debug,
             self.search_path,
             self.definitions,
-        )
+        );
+        cpp.set_standard(self.standard);
+        cpp
     }
 }
 
@@ -138,10 +150,35 @@ pub struct PreProcessor<'a> {
     search_path: Vec<Cow<'a, Path>>,
     /// The current macro definitions
     definitions: Definitions,
+    /// The location of the name of each macro in its definition.
+    ///
+    /// Predefined macros point into the `<builtin>` and `<command line>` pseudo-files.
+    definition_sites: HashMap<InternedStr, Location>,
+    /// Whether `__STDC_HOSTED__` was 1 at startup.
+    ///
+    /// In a freestanding implementation, the headers in `BUILTIN_HEADERS`
+    /// take precedence over the ones in the search path.
+    hosted: bool,
     /// Handles reading from files
     file_processor: FileProcessor,
+    /// Which version of C to accept, which determines the keywords
+    standard: Standard,
 }
 
+/// The headers a freestanding implementation has to provide (4p6),
+/// which may not be available on the host, since they usually come with the compiler.
+///
+/// Like the rest of `arch`, these assume x86_64.
+const BUILTIN_HEADERS: &[(&str, &str)] = &[
+    ("iso646.h", include_str!("headers/iso646.h")),
+    ("limits.h", include_str!("headers/limits.h")),
+    ("stdarg.h", include_str!("headers/stdarg.h")),
+    ("stdbool.h", include_str!("headers/stdbool.h")),
+    ("stddef.h", include_str!("headers/stddef.h")),
+    ("stdint.h", include_str!("headers/stdint.h")),
+    ("stdnoreturn.h", include_str!("headers/stdnoreturn.h")),
+];
+
 enum PendingToken {
     Replaced(Token),
     NeedsReplacement(Token),
@@ -163,8 +200,12 @@ impl TryFrom<&str> for Definition {
     type Error = error::LexError;
     fn try_from(value: &str) -> Result<Self, Self::Error> {
         let value = Rc::from(format!("{}\n", value));
-        let mut files = codespan::Files::new();
-        let dummy_id = files.add("<impl TryFrom<&str> for Definition>", Rc::clone(&value));
+        let mut files = SourceMap::new();
+        let source = crate::Source {
+            code: Rc::clone(&value),
+            path: PathBuf::new(),
+        };
+        let dummy_id = files.add("<impl TryFrom<&str> for Definition>", source);
         let lexer = Lexer::new(dummy_id, value, false);
         lexer
             .map(|res| match res {
@@ -176,6 +217,29 @@ impl TryFrom<&str> for Definition {
     }
 }
 
+impl Definition {
+    /// A function-like macro, as if by `#define name(params) body`.
+    ///
+    /// ```
+    /// use saltwater::{Definition, PreProcessor};
+    ///
+    /// let mut cpp = PreProcessor::new("twice(x)\n", "example.c", false, vec![], Default::default());
+    /// cpp.define("twice", Definition::function(&["a"], "a + a").unwrap());
+    /// let tokens: Vec<_> = cpp.map(|token| token.unwrap().data.to_string()).collect();
+    /// assert_eq!(tokens.concat().trim_end(), "x + x");
+    /// ```
+    pub fn function(params: &[&str], body: &str) -> Result<Definition, error::LexError> {
+        let body = match Definition::try_from(body)? {
+            Definition::Object(tokens) => tokens,
+            Definition::Function { .. } => unreachable!("`try_from` only returns object macros"),
+        };
+        Ok(Definition::Function {
+            params: params.iter().map(|&param| param.into()).collect(),
+            body,
+        })
+    }
+}
+
 /// Keeps track of the state of a conditional inclusion directive.
 ///
 /// `If` means we are currently processing an `#if`,
@@ -221,6 +285,7 @@ impl Iterator for PreProcessor<'_> {
     /// The preprocessor hides all internal complexity and returns only tokens.
     type Item = CppResult<Token>;
     fn next(&mut self) -> Option<Self::Item> {
+        let _preprocessing = stats::enter(Phase::Preprocessing);
         // We have two things we need to handle.
         // First, we could have gotten to the end of the file;
         // Second, the current token could be an identifier that was `#define`d to an empty token list.
@@ -279,9 +344,16 @@ impl<'a> PreProcessor<'a> {
             match token {
                 PendingToken::Replaced(t) => Some(Ok(Locatable::new(t, location))),
                 PendingToken::NeedsReplacement(token) => {
-                    let mut replacement_list =
-                        replace(&self.definitions, token, &mut self.file_processor, location)
-                            .into_iter();
+                    let name = match token {
+                        Token::Id(name) if self.definitions.contains_key(&name) => Some(name),
+                        _ => None,
+                    };
+                    let replacement_list =
+                        replace(&self.definitions, token, &mut self.file_processor, location);
+                    if let Some(name) = name {
+                        self.record_expansion(name, location, &replacement_list);
+                    }
+                    let mut replacement_list = replacement_list.into_iter();
                     let first = replacement_list.next();
                     for remaining in replacement_list {
                         match remaining {
@@ -299,8 +371,8 @@ impl<'a> PreProcessor<'a> {
         })) = &mut token
         {
             if let Token::Id(name) = &data {
-                if let Some(keyword) = KEYWORDS.get(get_str!(name)) {
-                    *data = Token::Keyword(*keyword);
+                if let Some(keyword) = keyword(get_str!(name), self.standard) {
+                    *data = Token::Keyword(keyword);
                 }
             }
         }
@@ -327,7 +399,7 @@ impl<'a> PreProcessor<'a> {
         let int = |i| Definition::Object(vec![Token::Literal(Literal::Int(i))]);
 
         #[allow(clippy::inconsistent_digit_grouping)]
-        let mut definitions = map! {
+        let builtin_definitions = map! {
             format!("__{}__", TARGET.architecture).into() => int(1),
             format!("__{}__", TARGET.operating_system).into() => int(1),
             "__STDC__".into() => int(1),
@@ -338,7 +410,6 @@ impl<'a> PreProcessor<'a> {
             "__STDC_NO_THREADS__".into() => int(1),
             "__STDC_NO_VLA__".into() => int(1),
         };
-        definitions.extend(user_definitions);
         let mut search_path = vec![
             PathBuf::from(format!("/usr/local/include/{}", system_path)).into(),
             Path::new("/usr/local/include").into(),
@@ -349,14 +420,57 @@ impl<'a> PreProcessor<'a> {
 
         let file_processor = FileProcessor::new(chars, filename, debug);
 
-        Self {
+        let mut cpp = Self {
             error_handler: Default::default(),
             nested_ifs: Default::default(),
             pending: Default::default(),
             search_path,
-            definitions,
+            definitions: Definitions::new(),
+            definition_sites: HashMap::new(),
+            hosted: true,
             file_processor,
-        }
+            standard: Standard::default(),
+        };
+        cpp.predefine("<builtin>", builtin_definitions);
+        cpp.predefine("<command line>", user_definitions);
+        cpp.hosted = match cpp.definitions.get(&"__STDC_HOSTED__".into()) {
+            Some(Definition::Object(tokens)) => tokens
+                .iter()
+                .filter(|token| !matches!(token, Token::Whitespace(_)))
+                .ne(&[Token::Literal(Literal::Int(0))]),
+            _ => true,
+        };
+        cpp
+    }
+    /// Create a preprocessor using the filename, search path, macros, and standard in `opt`.
+    ///
+    /// This is how `preprocess`, `parse`, and `compile` create their preprocessor.
+    pub fn with_options<S: Into<Rc<str>>>(chars: S, opt: &'a crate::Opt) -> Self {
+        let path = opt.search_path.iter().map(|path| path.into());
+        let filename = opt.filename.clone();
+        let mut cpp = PreProcessor::new(chars, filename, opt.debug_lex, path, opt.definitions());
+        cpp.set_standard(opt.standard);
+        cpp
+    }
+    /// Define the macro `name`, replacing any previous definition.
+    ///
+    /// This is the same as a `#define` before the first line of the file,
+    /// so it should be called before any tokens are read.
+    pub fn define<D: Into<Definition>>(&mut self, name: &str, definition: D) {
+        self.predefine("<command line>", map! { name.into() => definition.into() });
+    }
+    /// Look for `#include`d files in `path`, after all the paths already in the search path.
+    pub fn add_search_path<P: Into<Cow<'a, Path>>>(&mut self, path: P) {
+        self.search_path.push(path.into());
+    }
+    /// All macros that are currently defined, in no particular order.
+    ///
+    /// This includes the builtin macros, such as `__STDC__`,
+    /// and reflects every `#define` and `#undef` seen so far.
+    pub fn macros(&self) -> impl Iterator<Item = (InternedStr, &Definition)> {
+        self.definitions
+            .iter()
+            .map(|(&name, definition)| (name, definition))
     }
     /// Return the first valid token in the file,
     /// or None if there are no valid tokens.
@@ -389,11 +503,135 @@ impl<'a> PreProcessor<'a> {
         self.file_processor.eof()
     }
 
-    pub fn into_files(self) -> Files {
+    /// Set the version of C to accept.
+    ///
+    /// This determines which identifiers are keywords, whether `//` starts a comment,
+    /// and the value of `__STDC_VERSION__`, overriding any user definition.
+    /// This should be called before any tokens are read.
+    pub fn set_standard(&mut self, standard: Standard) {
+        self.standard = standard;
+        self.file_processor.set_standard(standard);
+        let name = "__STDC_VERSION__".into();
+        match standard.stdc_version() {
+            Some(version) => {
+                let version = Definition::Object(vec![Token::Literal(Literal::Int(version))]);
+                self.definitions.insert(name, version);
+            }
+            None => {
+                self.definitions.remove(&name);
+            }
+        }
+    }
+
+    /// Remember the location and text of every comment, including in `#include`d files.
+    ///
+    /// Comments are normally discarded as soon as they're lexed.
+    /// This should be called before any tokens are read.
+    pub fn retain_comments(&mut self) {
+        self.file_processor.retain_comments();
+    }
+
+    /// Return all comments seen so far, if `retain_comments` was called.
+    ///
+    /// These comments are consumed and will not be returned if you call
+    /// `comments()` again.
+    pub fn comments(&mut self) -> Vec<Locatable<Comment>> {
+        self.file_processor.take_comments()
+    }
+
+    /// All files loaded so far, along with `#line` directives and macro expansions.
+    pub fn files(&self) -> &SourceMap {
+        self.file_processor.source_map()
+    }
+
+    pub fn into_files(self) -> SourceMap {
         self.file_processor.into_files()
     }
 
     /* internal functions */
+    /// Remember that the macro `name` at `site` was replaced by `replacement_list`.
+    ///
+    /// A function macro that isn't followed by `(` replaces itself (along with any whitespace after it),
+    /// and isn't recorded.
+    fn record_expansion(
+        &mut self,
+        name: InternedStr,
+        site: Location,
+        replacement_list: &[CompileResult<Locatable<Token>>],
+    ) {
+        let mut tokens = replacement_list
+            .iter()
+            .filter(|token| !Self::is_whitespace(token));
+        if let (
+            Some(Ok(Locatable {
+                data: Token::Id(id),
+                ..
+            })),
+            None,
+        ) = (tokens.next(), tokens.next())
+        {
+            if *id == name {
+                return;
+            }
+        }
+        if let Some(&definition) = self.definition_sites.get(&name) {
+            self.file_processor
+                .source_map_mut()
+                .add_expansion(Expansion {
+                    name,
+                    definition,
+                    site,
+                });
+        }
+    }
+    /// Define macros that don't come from a source file.
+    ///
+    /// So that they still have a location, the definitions are written out as `#define` lines
+    /// in a new pseudo-file called `name`.
+    fn predefine(&mut self, name: &str, definitions: HashMap<InternedStr, Definition>) {
+        let mut definitions: Vec<_> = definitions.into_iter().collect();
+        definitions.sort_by_key(|(id, _)| id.resolve_and_clone());
+        let mut code = String::new();
+        let mut sites = Vec::new();
+        for (id, definition) in &definitions {
+            code.push_str("#define ");
+            let start = code.len() as u32;
+            code.push_str(get_str!(id));
+            sites.push((*id, Span::from(start..code.len() as u32)));
+            let body = match definition {
+                Definition::Object(body) => body,
+                Definition::Function { params, body } => {
+                    let params: Vec<_> = params
+                        .iter()
+                        .map(|param| param.resolve_and_clone())
+                        .collect();
+                    code.push_str(&format!("({})", params.join(", ")));
+                    body
+                }
+            };
+            for token in body {
+                if !matches!(token, Token::Whitespace(_)) {
+                    code.push_str(&format!(" {}", token));
+                }
+            }
+            code.push('\n');
+        }
+        let source = crate::Source {
+            code: code.into(),
+            path: name.into(),
+        };
+        let file = self.file_processor.source_map_mut().add(name, source);
+        for (id, span) in sites {
+            let location = Location {
+                span,
+                file,
+                expansion: None,
+            };
+            self.definition_sites.insert(id, location);
+        }
+        self.definitions.extend(definitions);
+    }
+
     fn span(&self, start: u32) -> Location {
         self.file_processor.span(start)
     }
@@ -543,11 +781,12 @@ impl<'a> PreProcessor<'a> {
                     Ok(())
                 }
             }
-            Define => self.define(start),
+            Define => self.define_directive(start),
             Undef => {
                 self.consume_whitespace_oneline(start, CppError::EmptyExpression)?;
                 let name = self.expect_id()?;
                 self.definitions.remove(&name.data);
+                self.definition_sites.remove(&name.data);
                 Ok(())
             }
             Pragma => {
@@ -578,14 +817,7 @@ impl<'a> PreProcessor<'a> {
                     .error(CppError::User(tokens), self.span(start));
                 Ok(())
             }
-            Line => {
-                self.error_handler.warn(
-                    WarningDiagnostic::Generic("#line is not yet implemented".into()),
-                    self.span(start),
-                );
-                drop(self.tokens_until_newline(false));
-                Ok(())
-            }
+            Line => self.line_directive(start),
             Include => self.include(start),
         }
     }
@@ -598,14 +830,14 @@ impl<'a> PreProcessor<'a> {
             .collect::<Result<_, CompileError>>()?;
         let location = self.span(start);
 
-        // TODO: is this unwrap safe? there should only be scalar types in a cpp directive...
-        match Self::cpp_expr(&self.definitions, lex_tokens.into_iter(), location)?
-            .truthy(&mut self.error_handler)
-            .constexpr()?
-            .data
-        {
-            (Literal::Int(i), Type::Bool) => Ok(i != 0),
-            _ => unreachable!("bug in const_fold or parser: cpp cond should be boolean"),
+        let arena = hir::Arena::new();
+        let condition =
+            Self::cpp_expr(&self.definitions, lex_tokens.into_iter(), location, &arena)?
+                .truthy(&arena, &mut self.error_handler);
+        match const_eval(&condition, &Target::host())? {
+            ConstValue::Int(i) => Ok(i != 0),
+            ConstValue::Uint(u) => Ok(u != 0),
+            _ => unreachable!("bug in const_eval or parser: cpp cond should be boolean"),
         }
     }
     // `#if defined(a)` or `#if defined a`
@@ -681,11 +913,13 @@ impl<'a> PreProcessor<'a> {
     ///
     /// Note that identifiers are replaced with a constant 0,
     /// as per [6.10.1](http://port70.net/~nsz/c/c11/n1570.html#6.10.1p4).
-    pub fn cpp_expr<L>(
+    /// The expression is allocated in `arena`.
+    pub fn cpp_expr<'hir, L>(
         definitions: &Definitions,
         mut lex_tokens: L,
         location: Location,
-    ) -> CompileResult<hir::Expr>
+        arena: &'hir hir::Arena<'hir>,
+    ) -> CompileResult<hir::Expr<'hir>>
     where
         L: Iterator<Item = Locatable<Token>>,
     {
@@ -748,7 +982,7 @@ impl<'a> PreProcessor<'a> {
         // TODO: catch expressions that aren't allowed
         // (see https://github.com/jyn514/rcc/issues/5#issuecomment-575339427)
         // TODO: can semantic errors happen here? should we check?
-        Ok(PureAnalyzer::new().expr(expr))
+        Ok(PureAnalyzer::new(arena).expr(expr))
     }
     /// We saw an `#if`, `#ifdef`, or `#ifndef` token at the start of the line
     /// and want to either take the branch or ignore the tokens within the directive.
@@ -895,7 +1129,7 @@ impl<'a> PreProcessor<'a> {
     // `#define a b` - object macro
     // `#define f(a) a` - function macro
     // `#define f (a) - object macro
-    fn define(&mut self, start: u32) -> Result<(), Locatable<Error>> {
+    fn define_directive(&mut self, start: u32) -> Result<(), Locatable<Error>> {
         let body = |this: &mut PreProcessor| {
             this.tokens_until_newline(true)
                 .into_iter()
@@ -925,13 +1159,97 @@ impl<'a> PreProcessor<'a> {
             let body = body(self)?;
             self.definitions
                 .insert(id.data, Definition::Function { params, body });
-            Ok(())
         } else {
             // object macro
             let tokens = body(self)?;
             self.definitions.insert(id.data, Definition::Object(tokens));
-            Ok(())
         }
+        self.definition_sites.insert(id.data, id.location);
+        Ok(())
+    }
+    // http://port70.net/~nsz/c/c11/n1570.html#6.10.4
+    // `#line 10` - the next line is line 10
+    // `#line 10 "file.c"` - the next line is line 10 of `file.c`
+    // Either form may be the result of macro replacement.
+    fn line_directive(&mut self, start: u32) -> Result<(), Locatable<Error>> {
+        let location = self.span(start);
+        let mut tokens = Vec::new();
+        for token in self.tokens_until_newline(false) {
+            let token = token?;
+            for replaced in replace(
+                &self.definitions,
+                token.data,
+                std::iter::empty(),
+                token.location,
+            ) {
+                let replaced = replaced?;
+                if !matches!(replaced.data, Token::Whitespace(_)) {
+                    tokens.push(replaced);
+                }
+            }
+        }
+        let mut tokens = tokens.into_iter();
+        let line = match tokens.next() {
+            Some(Locatable {
+                data: Token::Literal(Literal::Int(line)),
+                location,
+            }) => {
+                if line < 1 || line > i64::from(i32::MAX) {
+                    return Err(CompileError::new(
+                        CppError::LineOutOfRange(line).into(),
+                        location,
+                    ));
+                }
+                line as usize
+            }
+            Some(other) => {
+                return Err(CompileError::new(
+                    CppError::UnexpectedToken("line number", other.data).into(),
+                    other.location,
+                ))
+            }
+            None => {
+                return Err(CompileError::new(
+                    CppError::EndOfFile("line number").into(),
+                    location,
+                ))
+            }
+        };
+        let name = match tokens.next() {
+            Some(Locatable {
+                data: Token::Literal(Literal::Str(mut name)),
+                ..
+            }) => {
+                // remove the trailing NUL byte
+                name.pop();
+                Some(String::from_utf8_lossy(&name).into_owned())
+            }
+            Some(other) => {
+                return Err(CompileError::new(
+                    CppError::UnexpectedToken("file name", other.data).into(),
+                    other.location,
+                ))
+            }
+            None => None,
+        };
+        if let Some(extra) = tokens.next() {
+            return Err(CompileError::new(
+                CppError::UnexpectedToken("newline", extra.data).into(),
+                extra.location,
+            ));
+        }
+        let directive_line = self
+            .file_processor
+            .source_map()
+            .line_column(location.file, location.span.start)
+            .line;
+        self.file_processor.source_map_mut().add_line_directive(
+            location.file,
+            directive_line,
+            line,
+            name,
+        );
+        Ok(())
     }
     // http://port70.net/~nsz/c/c11/n1570.html#6.10.2
     // `#include <file>` - system include
@@ -1069,16 +1387,29 @@ impl<'a> PreProcessor<'a> {
         // Since in UTF8 no ASCII character can occur
         // within a multi-byte sequence, `filename` must be valid UTF8.
         let filename = String::from_utf8(filename).expect("passed invalid utf8 to start");
-        let resolved = self.find_include_path(filename.clone(), local, start)?;
-        let src = std::fs::read_to_string(&resolved)
-            .map_err(|err| Locatable {
-                data: CppError::IO(err.to_string()),
-                location: self.span(start),
-            })?
-            .into();
+        let builtin = BUILTIN_HEADERS
+            .iter()
+            .find(|(name, _)| *name == filename)
+            .map(|(name, src)| (Path::new("<builtin>").join(name), Rc::from(*src)));
+        let (resolved, src) = match builtin {
+            Some(builtin) if !self.hosted => builtin,
+            // in a hosted implementation, prefer the headers from the C library
+            _ => match self.find_include_path(filename.clone(), local, start) {
+                Ok(resolved) => {
+                    let src = std::fs::read_to_string(&resolved)
+                        .map_err(|err| Locatable {
+                            data: CppError::IO(err.to_string()),
+                            location: self.span(start),
+                        })?
+                        .into();
+                    (resolved, src)
+                }
+                Err(err) => builtin.ok_or(err)?,
+            },
+        };
         let source = crate::Source {
             path: resolved,
-            code: Rc::clone(&src),
+            code: src,
         };
         self.file_processor.add_file(filename, source);
         Ok(())
@@ -1176,6 +1507,20 @@ impl TryFrom<&str> for DirectiveKind {
     }
 }
 
+/// The keyword `name` refers to in `standard`, if any.
+fn keyword(name: &str, standard: Standard) -> Option<Keyword> {
+    match name {
+        // added in C99, although GNU89 already had `inline`
+        "restrict" if standard.version < Version::C99 => None,
+        "inline" if standard.version < Version::C99 && !standard.gnu => None,
+        _ => match KEYWORDS.get(name) {
+            Some(&keyword) => Some(keyword),
+            None if standard.version >= Version::C2x => C2X_KEYWORDS.get(name).copied(),
+            None => None,
+        },
+    }
+}
+
 lazy_static! {
     static ref KEYWORDS: HashMap<&'static str, Keyword> = map!{
         // control flow
@@ -1215,6 +1560,9 @@ lazy_static! {
         "const" => Keyword::Const,
         "volatile" => Keyword::Volatile,
         "restrict" => Keyword::Restrict,
+        // GNU spellings, allowed in every version
+        "__restrict" => Keyword::Restrict,
+        "__restrict__" => Keyword::Restrict,
         "_Atomic" => Keyword::Atomic,
         "_Thread_local" => Keyword::ThreadLocal,
 
@@ -1234,6 +1582,22 @@ lazy_static! {
         "_Alignas" => Keyword::Alignas,
         "_Generic" => Keyword::Generic,
         "_Static_assert" => Keyword::StaticAssert,
+
+        // GNU extensions
+        "__attribute__" => Keyword::Attribute,
+        "__attribute" => Keyword::Attribute,
+        "__extension__" => Keyword::Extension,
+    };
+    /// Keywords added in C2x, which were previously macros in the standard headers
+    static ref C2X_KEYWORDS: HashMap<&'static str, Keyword> = map!{
+        "bool" => Keyword::Bool,
+        "alignas" => Keyword::Alignas,
+        "alignof" => Keyword::Alignof,
+        "static_assert" => Keyword::StaticAssert,
+        "thread_local" => Keyword::ThreadLocal,
+        "true" => Keyword::True,
+        "false" => Keyword::False,
+        "nullptr" => Keyword::Nullptr,
     };
 }
 
@@ -1241,6 +1605,7 @@ lazy_static! {
 mod tests {
     use super::*;
     use crate::data::lex::test::{cpp, cpp_no_newline};
+    use crate::data::lex::{AssignmentToken, ComparisonToken};
 
     macro_rules! assert_err {
         ($src: expr, $err: pat, $description: expr $(,)?) => {
@@ -1260,6 +1625,80 @@ mod tests {
             _ => panic!("not a keyword: {:?}", token),
         }
     }
+    #[test]
+    fn define_with_api() {
+        let src = "int m = max(a, 2);\n";
+        let mut cpp = PreProcessorBuilder::new(src).build();
+        let max = Definition::function(&["x", "y"], "x > y ? x : y").unwrap();
+        cpp.define("max", max);
+        let tokens: Vec<_> = cpp
+            .filter(PreProcessor::is_not_whitespace)
+            .map(Result::unwrap)
+            .collect();
+        let kinds: Vec<_> = tokens.iter().map(|token| token.data.clone()).collect();
+        let (a, two) = (Token::Id("a".into()), Token::Literal(Literal::Int(2)));
+        assert_eq!(
+            kinds,
+            [
+                Token::Keyword(Keyword::Int),
+                Token::Id("m".into()),
+                Token::Assignment(AssignmentToken::Equal),
+                a.clone(),
+                Token::Comparison(ComparisonToken::Greater),
+                two.clone(),
+                Token::Question,
+                a,
+                Token::Colon,
+                two,
+                Token::Semicolon,
+            ]
+        );
+        // tokens from the body point to the name of the macro and remember the expansion
+        let name = Span::from(8..11);
+        for token in &[&tokens[4], &tokens[6], &tokens[8]] {
+            assert_eq!(token.location.span, name);
+            let expansions = token.location.expansions();
+            assert_eq!(expansions.len(), 1);
+            assert_eq!(expansions[0].name, "max".into());
+            assert_eq!(expansions[0].site.span, name);
+        }
+        // arguments keep their own location
+        for (token, span) in &[(&tokens[3], 12..13), (&tokens[9], 15..16)] {
+            assert_eq!(token.location.span, Span::from(span.clone()));
+            assert!(!token.location.is_from_macro());
+        }
+        assert_eq!(tokens[10].location.span, Span::from(17..18));
+    }
+    #[test]
+    fn query_macros() {
+        let mut cpp = PreProcessorBuilder::new("#define f(a) a\n#undef N\n").build();
+        cpp.define(
+            "N",
+            Definition::Object(vec![Token::Literal(Literal::Int(1))]),
+        );
+        cpp.define("M", Definition::try_from("2").unwrap());
+        assert!(cpp.next_non_whitespace().is_none());
+        let macros: HashMap<_, _> = cpp
+            .macros()
+            .map(|(name, definition)| (name.resolve_and_clone(), definition))
+            .collect();
+        assert!(!macros.contains_key("N"));
+        assert!(macros.contains_key("__STDC__"));
+        match macros["f"] {
+            Definition::Function { params, .. } => assert_eq!(params, &["a".into()]),
+            other => panic!("expected a function macro, got {:?}", other),
+        }
+        match macros["M"] {
+            Definition::Object(tokens) => {
+                let tokens: Vec<_> = tokens
+                    .iter()
+                    .filter(|token| !matches!(token, Token::Whitespace(_)))
+                    .collect();
+                assert_eq!(tokens, [&Token::Literal(Literal::Int(2))]);
+            }
+            other => panic!("expected an object macro, got {:?}", other),
+        }
+    }
     fn is_same_preprocessed(xs: PreProcessor, ys: PreProcessor) -> bool {
         let to_vec = |xs: PreProcessor| {
             xs.filter(PreProcessor::is_not_whitespace)
@@ -1489,10 +1928,86 @@ d
     }
     #[test]
     fn line() {
-        let src = "#line 1";
+        let src = "#line 10\na\n#define FILE \"b.c\"\n#line 1 FILE\nb\n#line 5\n\nc";
         let mut cpp = cpp(src);
-        assert!(cpp.next_non_whitespace().is_none());
-        assert!(cpp.warnings().pop_front().is_some());
+        let tokens: Vec<_> = cpp
+            .by_ref()
+            .filter(PreProcessor::is_not_whitespace)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert!(cpp.warnings().is_empty());
+        let files = cpp.into_files();
+        let presumed: Vec<_> = tokens
+            .iter()
+            .map(|token| {
+                let presumed = files.presumed_location(token.location);
+                (presumed.name.into_owned(), presumed.line)
+            })
+            .collect();
+        let main = files.name(tokens[0].location.file).to_string_lossy();
+        assert_eq!(
+            presumed,
+            vec![(main.into(), 10), ("b.c".into(), 1), ("b.c".into(), 6)]
+        );
+
+        assert_err!("#line", CppError::EndOfFile(_), "missing line number");
+        assert_err!("#line 0", CppError::LineOutOfRange(0), "line 0");
+        assert_err!(
+            "#line 2147483648",
+            CppError::LineOutOfRange(_),
+            "line out of range"
+        );
+        assert_err!("#line x", CppError::UnexpectedToken(..), "invalid line");
+        assert_err!(
+            "#line 1 \"a.c\" b",
+            CppError::UnexpectedToken(..),
+            "trailing tokens"
+        );
+    }
+    #[test]
+    fn expansions() {
+        let src = "#define a 1\n#define f(x) x\nint i = a + f + f(2) + __STDC__ + c;\n";
+        let mut cpp = PreProcessorBuilder::new(src)
+            .definition("c".into(), vec![Token::Literal(Literal::Int(2))])
+            .build();
+        let tokens: Vec<_> = cpp.by_ref().collect::<Result<_, _>>().unwrap();
+        let files = cpp.into_files();
+        let main = tokens[0].location.file;
+        let expansions: Vec<_> = files
+            .expansions()
+            .iter()
+            .map(|expansion| {
+                let definition = expansion.definition;
+                let span = definition.span.start as usize..definition.span.end as usize;
+                assert_eq!(
+                    &files.source(definition.file).code[span],
+                    get_str!(expansion.name)
+                );
+                (
+                    get_str!(expansion.name).to_string(),
+                    definition.file == main,
+                    files.name(definition.file).to_string_lossy().into_owned(),
+                )
+            })
+            .collect();
+        let name = |file: &str| file.to_string();
+        let main_name = files.name(main).to_string_lossy().into_owned();
+        assert_eq!(
+            expansions,
+            vec![
+                (name("a"), true, main_name.clone()),
+                (name("f"), true, main_name),
+                (name("__STDC__"), false, name("<builtin>")),
+                (name("c"), false, name("<command line>")),
+            ]
+        );
+        // the `1` in `int i = a` came from expanding `a`
+        let one = tokens
+            .iter()
+            .find(|token| token.data == Token::Literal(Literal::Int(1)))
+            .unwrap();
+        assert_eq!(files.expansion(one.location).unwrap().name, "a".into());
+        assert!(files.expansion(tokens[0].location).is_none());
     }
     #[test]
     fn warning() {
@@ -1580,6 +2095,21 @@ int main(){}
         assert_same(original, expected);
     }
     #[test]
+    fn function_whitespace_before_paren() {
+        assert_same("#define f(a) a + 1\nf (1)", "1 + 1");
+        assert_same("#define f(a) a + 1\nf\n\t(1)", "1 + 1");
+        // glibc uses this to ignore attributes on non-GNU compilers
+        assert_same(
+            "#define __attribute__(x)\nint x __attribute__ ((y));",
+            "int x;",
+        );
+    }
+    #[test]
+    fn function_without_arguments() {
+        assert_same_exact("#define f(a) a\nint f;", "\nint f;");
+        assert_same_exact("#define f(a) a\nf +f", "\nf +f");
+    }
+    #[test]
     // https://github.com/jyn514/rcc/issues/427
     fn mutually_recursive_function() {
         let original = "
@@ -1590,6 +2120,30 @@ int main(){}
         assert_same(original, "a(1)");
     }
     #[test]
+    fn freestanding() {
+        let freestanding = |src: &str| {
+            PreProcessorBuilder::new(format!("{}\n", src))
+                .definition(
+                    "__STDC_HOSTED__".into(),
+                    vec![Token::Literal(Literal::Int(0))],
+                )
+                .build()
+        };
+        assert!(is_same_preprocessed(cpp("__STDC_HOSTED__"), cpp("1")));
+        assert!(is_same_preprocessed(
+            freestanding("__STDC_HOSTED__"),
+            cpp("0")
+        ));
+        assert!(is_same_preprocessed(
+            freestanding("#include <stdbool.h>\ntrue false bool"),
+            cpp("1 0 _Bool"),
+        ));
+        assert!(is_same_preprocessed(
+            freestanding("#include <limits.h>\n#include <limits.h>\nCHAR_BIT"),
+            cpp("8"),
+        ));
+    }
+    #[test]
     // https://github.com/jyn514/rcc/issues/356
     fn preprocess_only() {
         assert_same_exact("int \t\n\r     main() {}", "int \t\n\r     main() {}");
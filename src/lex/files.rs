@@ -1,9 +1,9 @@
 use super::Lexer;
 use crate::{
-    data::{CompileResult, Locatable, Token},
+    data::{Comment, CompileResult, Locatable, Standard, Token},
     ErrorHandler, Location,
 };
-use crate::{Files, Source};
+use crate::{Source, SourceMap};
 use std::path::Path;
 use std::rc::Rc;
 
@@ -15,9 +15,11 @@ pub(super) struct FileProcessor {
     /// Each lexer represents a separate source file that is currently being processed.
     includes: Vec<Lexer>,
     /// All known files, including files which have already been read.
-    files: Files,
+    files: SourceMap,
     pub(super) error_handler: ErrorHandler,
     current: Option<CompileResult<Locatable<Token>>>,
+    /// Comments from files that have already been fully processed
+    comments: Vec<Locatable<Comment>>,
 }
 
 impl Iterator for FileProcessor {
@@ -35,6 +37,7 @@ impl Iterator for FileProcessor {
                 // finished this file, go on to the next one
                 None => {
                     self.error_handler.append(&mut lexer.error_handler);
+                    self.comments.append(&mut lexer.take_comments());
                     // this is the original source file
                     if self.includes.is_empty() {
                         return None;
@@ -53,7 +56,7 @@ impl FileProcessor {
         filename: impl Into<std::ffi::OsString>,
         debug: bool,
     ) -> Self {
-        let mut files = Files::new();
+        let mut files = SourceMap::new();
         let chars = chars.into();
         let filename = filename.into();
         let source = crate::Source {
@@ -67,6 +70,7 @@ impl FileProcessor {
             files,
             includes: Default::default(),
             current: None,
+            comments: Vec::new(),
         }
     }
 
@@ -89,8 +93,30 @@ impl FileProcessor {
     pub(super) fn add_file(&mut self, filename: String, source: Source) {
         let code = Rc::clone(&source.code);
         let id = self.files.add(filename, source);
-        self.includes
-            .push(Lexer::new(id, code, self.first_lexer.debug));
+        let mut lexer = Lexer::new(id, code, self.first_lexer.debug);
+        if self.first_lexer.comments.is_some() {
+            lexer.retain_comments();
+        }
+        lexer.standard = self.first_lexer.standard;
+        self.includes.push(lexer);
+    }
+
+    /// Remember comments in this file and all files it includes.
+    pub(super) fn retain_comments(&mut self) {
+        self.first_lexer.retain_comments();
+    }
+    /// Set the version of C to accept, in this file and all files it includes.
+    pub(super) fn set_standard(&mut self, standard: Standard) {
+        self.first_lexer.standard = standard;
+    }
+    /// Return all comments seen so far, in all files.
+    pub(super) fn take_comments(&mut self) -> Vec<Locatable<Comment>> {
+        let mut comments = std::mem::take(&mut self.comments);
+        comments.append(&mut self.first_lexer.take_comments());
+        for lexer in &mut self.includes {
+            comments.append(&mut lexer.take_comments());
+        }
+        comments
     }
 
     /// Return a `Location` representing the end of the first file.
@@ -99,13 +125,22 @@ impl FileProcessor {
         Location {
             span: (lex.chars.len() as u32..lex.chars.len() as u32).into(),
             file: lex.location.file,
+            expansion: None,
         }
     }
 
+    /// All files loaded so far, along with `#line` directives and macro expansions.
+    pub(super) fn source_map(&self) -> &SourceMap {
+        &self.files
+    }
+    pub(super) fn source_map_mut(&mut self) -> &mut SourceMap {
+        &mut self.files
+    }
+
     /// Return all files loaded by the preprocessor, consuming it in the process.
     ///
     /// Files can be loaded by C source using `#include` directives.
-    pub(super) fn into_files(self) -> Files {
+    pub(super) fn into_files(self) -> SourceMap {
         self.files
     }
 
@@ -0,0 +1,17 @@
+/* 7.9 Alternative spellings <iso646.h> */
+#ifndef __SALTWATER_ISO646_H
+#define __SALTWATER_ISO646_H
+
+#define and &&
+#define and_eq &=
+#define bitand &
+#define bitor |
+#define compl ~
+#define not !
+#define not_eq !=
+#define or ||
+#define or_eq |=
+#define xor ^
+#define xor_eq ^=
+
+#endif
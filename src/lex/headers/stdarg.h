@@ -0,0 +1,9 @@
+/* 7.16 Variable arguments <stdarg.h> */
+#ifndef __SALTWATER_STDARG_H
+#define __SALTWATER_STDARG_H
+
+typedef __builtin_va_list va_list;
+/* glibc uses this name */
+typedef __builtin_va_list __gnuc_va_list;
+
+#endif
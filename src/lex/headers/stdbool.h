@@ -0,0 +1,10 @@
+/* 7.18 Boolean type and values <stdbool.h> */
+#ifndef __SALTWATER_STDBOOL_H
+#define __SALTWATER_STDBOOL_H
+
+#define bool _Bool
+#define true 1
+#define false 0
+#define __bool_true_false_are_defined 1
+
+#endif
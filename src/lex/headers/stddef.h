@@ -0,0 +1,21 @@
+/* 7.19 Common definitions <stddef.h> */
+#ifndef __SALTWATER_STDDEF_H
+#define __SALTWATER_STDDEF_H
+
+typedef long ptrdiff_t;
+typedef unsigned long size_t;
+typedef int wchar_t;
+typedef struct {
+    long long __ll;
+    double __d;
+} max_align_t;
+
+#if __STDC_VERSION__ >= 202000L
+/* `nullptr` is `(void *)0` until there is a separate type for it */
+typedef void *nullptr_t;
+#endif
+
+#define NULL ((void *)0)
+#define offsetof(type, member) ((size_t)&((type *)0)->member)
+
+#endif
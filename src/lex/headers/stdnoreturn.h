@@ -0,0 +1,7 @@
+/* 7.23 _Noreturn <stdnoreturn.h> */
+#ifndef __SALTWATER_STDNORETURN_H
+#define __SALTWATER_STDNORETURN_H
+
+#define noreturn _Noreturn
+
+#endif
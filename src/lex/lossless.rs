@@ -0,0 +1,183 @@
+//! Lossless lexing
+//!
+//! The normal lexer throws away comments and collapses whitespace,
+//! which is fine for a compiler but not for tools that need to rewrite source code.
+//! This module wraps a `Lexer` and recovers everything it skipped,
+//! so that printing every `LosslessToken` in order reproduces the file byte-for-byte.
+
+use std::fmt;
+
+use super::{LexResult, Lexer};
+use crate::data::lex::{Comment, CommentKind, Locatable, Location, Token, Trivia};
+
+/// A token, along with all the whitespace, comments, and line splices before it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LosslessToken {
+    /// Everything that was skipped between the previous token and this one.
+    pub leading_trivia: Vec<Locatable<Trivia>>,
+    /// The token itself.
+    ///
+    /// This is `None` only for the last item in the stream,
+    /// which holds the trivia at the end of the file.
+    pub token: Option<Locatable<Token>>,
+    /// The exact source text of the token.
+    ///
+    /// This may differ from the `Display` of `token`,
+    /// e.g. for `0x10` or for tokens containing a line splice.
+    pub text: String,
+}
+
+impl fmt::Display for LosslessToken {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for trivia in &self.leading_trivia {
+            write!(f, "{}", trivia.data)?;
+        }
+        write!(f, "{}", self.text)
+    }
+}
+
+/// An iterator over the tokens of a file that keeps whitespace and comments.
+///
+/// Created by `Lexer::lossless`.
+pub struct Lossless {
+    lexer: Lexer,
+    /// The end of the text that has already been returned
+    previous_end: u32,
+    done: bool,
+}
+
+impl Lexer {
+    /// Return the tokens of this file along with everything between them.
+    ///
+    /// The tokens are the same as the ones returned by iterating over the lexer directly,
+    /// minus the `Token::Whitespace` tokens, which are replaced by `Trivia`.
+    /// This has no cost for the normal lexer; trivia is recovered from the source text
+    /// only for the gaps between tokens.
+    ///
+    /// ```
+    /// use saltwater::{codespan::Files, Lexer};
+    ///
+    /// let source = "int /* the answer */ i = 42; // done\n";
+    /// let mut files = Files::new();
+    /// let file = files.add("<example>", source);
+    /// let tokens = Lexer::new(file, source, false)
+    ///     .lossless()
+    ///     .collect::<Result<Vec<_>, _>>()
+    ///     .unwrap();
+    /// let roundtrip: String = tokens.iter().map(ToString::to_string).collect();
+    /// assert_eq!(roundtrip, source);
+    /// ```
+    pub fn lossless(self) -> Lossless {
+        Lossless {
+            previous_end: self.location.offset,
+            lexer: self,
+            done: false,
+        }
+    }
+}
+
+impl Iterator for Lossless {
+    type Item = LexResult<LosslessToken>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let token = loop {
+            match self.lexer.next() {
+                Some(Ok(Locatable {
+                    data: Token::Whitespace(_),
+                    ..
+                })) => continue,
+                // The text of an invalid token becomes part of the next item
+                Some(Err(err)) => return Some(Err(err)),
+                Some(Ok(token)) => break Some(token),
+                None => break None,
+            }
+        };
+        let len = self.lexer.chars.len() as u32;
+        let (token_start, token_end) = match &token {
+            Some(token) => (token.location.span.start, token.location.span.end),
+            None => {
+                self.done = true;
+                (len, len)
+            }
+        };
+        let start = self.previous_end;
+        let end = token_end.max(start).min(len);
+        let (leading_trivia, trivia_end) = self.trivia(start, token_start.max(start).min(end));
+        self.previous_end = end;
+        if token.is_none() && leading_trivia.is_empty() && trivia_end == end {
+            return None;
+        }
+        Some(Ok(LosslessToken {
+            leading_trivia,
+            token,
+            text: self.lexer.chars[trivia_end as usize..end as usize].to_owned(),
+        }))
+    }
+}
+
+impl Lossless {
+    /// Split the source between `start` and `end` into trivia.
+    ///
+    /// Returns the trivia and where it stopped.
+    /// This is normally `end`, but may be earlier if there was text the lexer rejected.
+    fn trivia(&self, start: u32, end: u32) -> (Vec<Locatable<Trivia>>, u32) {
+        let bytes = &self.lexer.chars.as_bytes()[..end as usize];
+        let text = |start: usize, end: usize| self.lexer.chars[start..end].to_owned();
+        let mut trivia = Vec::new();
+        let mut i = start as usize;
+        while i < bytes.len() {
+            let begin = i;
+            let data = if bytes[i..].starts_with(b"\\\n") {
+                i += 2;
+                Trivia::LineSplice
+            } else if bytes[i].is_ascii_whitespace() {
+                while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+                    i += 1;
+                }
+                Trivia::Whitespace(text(begin, i))
+            } else if bytes[i..].starts_with(b"//") {
+                i += 2;
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += if bytes[i..].starts_with(b"\\\n") {
+                        2
+                    } else {
+                        1
+                    };
+                }
+                Trivia::Comment(Comment {
+                    text: text(begin + 2, i),
+                    kind: CommentKind::Line,
+                })
+            } else if bytes[i..].starts_with(b"/*") {
+                match find_comment_end(&bytes[i + 2..]) {
+                    Some(len) => {
+                        i += 2 + len;
+                        Trivia::Comment(Comment {
+                            text: text(begin + 2, i - 2),
+                            kind: CommentKind::Block,
+                        })
+                    }
+                    // unterminated comment, the lexer already reported an error
+                    None => break,
+                }
+            } else {
+                break;
+            };
+            let location = Location {
+                span: (begin as u32..i as u32).into(),
+                file: self.lexer.location.file,
+                expansion: None,
+            };
+            trivia.push(Locatable::new(data, location));
+        }
+        (trivia, i as u32)
+    }
+}
+
+/// Given the text after `/*`, return the length of the comment up to and including `*/`.
+fn find_comment_end(bytes: &[u8]) -> Option<usize> {
+    bytes.windows(2).position(|w| w == b"*/").map(|i| i + 2)
+}
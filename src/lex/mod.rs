@@ -3,11 +3,17 @@ use std::rc::Rc;
 
 use codespan::FileId;
 
-use super::data::{error::LexError, lex::*, *};
+use super::data::{
+    error::{LexError, Warning},
+    lex::*,
+    *,
+};
 use super::intern::InternedStr;
+use crate::stats::{self, Counter, Phase};
 
 mod cpp;
 mod files;
+mod lossless;
 pub mod replace;
 #[cfg(test)]
 mod tests;
@@ -15,10 +21,36 @@ mod tests;
 #[allow(unreachable_pub)]
 pub use cpp::{PreProcessor, PreProcessorBuilder};
 #[allow(unreachable_pub)]
+pub use lossless::{Lossless, LosslessToken};
+#[allow(unreachable_pub)]
 pub use replace::Definition;
 
 type LexResult<T = Token> = Result<T, Locatable<LexError>>;
 
+/// Whether a comment marks a `case` that intentionally falls through, e.g. `/* fallthrough */`.
+///
+/// Like GCC, this ignores case, spaces, and punctuation,
+/// so `FALLTHROUGH`, `fall through.` and `falls-thru` are all accepted.
+pub(crate) fn is_fallthrough_comment(comment: &str) -> bool {
+    let letters: String = comment
+        .chars()
+        .filter(char::is_ascii_alphabetic)
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    matches!(
+        letters.as_str(),
+        "fallthrough" | "fallsthrough" | "fallthru" | "fallsthru"
+    )
+}
+
+/// The text of `comment` without its delimiters.
+fn comment_text(comment: &str, kind: CommentKind) -> &str {
+    match kind {
+        CommentKind::Line => &comment[2..],
+        CommentKind::Block => &comment[2..comment.len() - 2],
+    }
+}
+
 /// A Lexer takes the source code and turns it into tokens with location information.
 ///
 /// Tokens are either literals, keywords, identifiers, or builtin operations.
@@ -46,6 +78,12 @@ pub struct Lexer {
     error_handler: ErrorHandler<LexError>,
     /// Whether or not to display each token as it is processed
     debug: bool,
+    /// Each comment seen so far, if comments are being retained
+    comments: Option<Vec<Locatable<Comment>>>,
+    /// Which version of C to accept, e.g. `//` doesn't start a comment in C89
+    pub(crate) standard: Standard,
+    /// Whether a `//` comment has been reported as an extension in this file
+    reported_line_comment: bool,
 }
 
 // returned when lexing a string literal
@@ -75,6 +113,39 @@ impl Lexer {
             current: None,
             lookahead: None,
             error_handler: ErrorHandler::new(),
+            comments: None,
+            standard: Standard::default(),
+            reported_line_comment: false,
+        }
+    }
+
+    /// Remember the location and text of each comment, instead of discarding them.
+    pub(crate) fn retain_comments(&mut self) {
+        self.comments.get_or_insert_with(Vec::new);
+    }
+    /// Return all comments seen so far, if `retain_comments` was called.
+    ///
+    /// These comments are consumed and will not be returned if you call
+    /// `take_comments()` again.
+    pub(crate) fn take_comments(&mut self) -> Vec<Locatable<Comment>> {
+        self.comments
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+    /// Record the comment between `start` and the current position, if comments are being retained.
+    fn record_comment(&mut self, start: u32, kind: CommentKind) {
+        let location = self.span(start);
+        if let Some(comments) = &mut self.comments {
+            let text = comment_text(
+                &self.chars[location.span.start as usize..location.span.end as usize],
+                kind,
+            );
+            let comment = Comment {
+                text: text.to_owned(),
+                kind,
+            };
+            comments.push(Locatable::new(comment, location));
         }
     }
 
@@ -184,6 +255,7 @@ impl Lexer {
         Location {
             span: (start..self.location.offset).into(),
             file: self.location.file,
+            expansion: None,
         }
     }
 
@@ -222,7 +294,16 @@ impl Lexer {
             // comments
             if self.peek() == Some(b'/') {
                 match self.peek_next() {
-                    Some(b'/') => self.consume_line_comment(),
+                    Some(b'/') if self.standard.line_comments() => {
+                        let start = self.location.offset;
+                        self.consume_line_comment();
+                        // like GCC, only report this once per file
+                        if self.standard.version < Version::C99 && !self.reported_line_comment {
+                            self.reported_line_comment = true;
+                            let extension = Warning::C99Extension("'//' comments");
+                            self.error_handler.warn(extension, self.span(start));
+                        }
+                    }
                     Some(b'*') => {
                         self.next_char();
                         self.next_char();
@@ -247,9 +328,10 @@ impl Lexer {
     /// Before: u8s{"blah `invalid tokens``\nhello // blah"}
     /// After:  chars{"hello // blah"}
     fn consume_line_comment(&mut self) {
+        let start = self.location.offset;
         loop {
             match self.peek() {
-                None | Some(b'\n') => return,
+                None | Some(b'\n') => return self.record_comment(start, CommentKind::Line),
                 _ => {
                     self.next_char();
                 }
@@ -271,6 +353,7 @@ impl Lexer {
                 if whitespace.is_empty() {
                     whitespace.push(' '); // For the case `a/* */b`
                 }
+                self.record_comment(start, CommentKind::Block);
                 return Ok(whitespace);
             }
             if c == b'\n' {
@@ -298,7 +381,6 @@ impl Lexer {
             "main loop should only pass [-.0-9] as start to parse_num"
         );
         let span_start = self.location.offset - 1; // -1 for `start`
-        let float_literal = |f| Token::Literal(Literal::Float(f));
         let mut buf = String::new();
         buf.push(start as char);
         // check for radix other than 10 - but if we see b'.', use 10
@@ -310,7 +392,7 @@ impl Lexer {
                 Radix::Hexadecimal
             } else if self.match_next(b'.') {
                 // float: 0.431
-                return self.parse_float(Radix::Decimal, buf).map(float_literal);
+                return self.parse_float(Radix::Decimal, buf).map(Token::Literal);
             } else {
                 // octal: 0755 => 493
                 Radix::Octal
@@ -332,13 +414,12 @@ impl Lexer {
             }
         };
         if self.match_next(b'.') {
-            return self.parse_float(radix, buf).map(float_literal);
+            return self.parse_float(radix, buf).map(Token::Literal);
         }
         if let Some(b'e') | Some(b'E') | Some(b'p') | Some(b'P') = self.peek() {
             buf.push_str(".0"); // hexf doesn't like floats without a decimal point
             let float = self.parse_exponent(radix == Radix::Hexadecimal, buf);
-            self.consume_float_suffix();
-            return float.map(float_literal);
+            return Ok(self.float_suffix(float?).into());
         }
         let literal = if self.match_next(b'u') || self.match_next(b'U') {
             let unsigned = u64::try_from(digits).map_err(|_| LexError::IntegerOverflow {
@@ -357,15 +438,14 @@ impl Lexer {
         } else if self.match_next(b'L') {
             self.match_next(b'L');
         }
-        if radix == Radix::Binary {
+        if radix == Radix::Binary && self.standard.version < Version::C2x {
             let span = self.span(span_start);
-            self.error_handler
-                .warn("binary number literals are an extension", span);
+            self.error_handler.warn(Warning::BinaryLiteral, span);
         }
         Ok(Token::Literal(literal))
     }
     // at this point we've already seen a '.', if we see one again it's an error
-    fn parse_float(&mut self, radix: Radix, mut buf: String) -> Result<f64, LexError> {
+    fn parse_float(&mut self, radix: Radix, mut buf: String) -> Result<Literal, LexError> {
         buf.push('.');
         // parse fraction: second {digits} in regex
         while let Some(c) = self.peek() {
@@ -380,14 +460,19 @@ impl Lexer {
         // in case of an empty mantissa, hexf doesn't like having the exponent right after the .
         // if the mantissa isn't empty, .12 is the same as .120
         //buf.push(b'0');
-        let float = self.parse_exponent(radix == Radix::Hexadecimal, buf);
-        self.consume_float_suffix();
-        float
+        let float = self.parse_exponent(radix == Radix::Hexadecimal, buf)?;
+        Ok(self.float_suffix(float))
     }
-    fn consume_float_suffix(&mut self) {
-        // Ignored for compatibility reasons
-        if !(self.match_next(b'f') || self.match_next(b'F') || self.match_next(b'l')) {
-            self.match_next(b'L');
+    /// `1.5f` is a `float`; `1.5` and `1.5L` are `double`s.
+    fn float_suffix(&mut self, float: f64) -> Literal {
+        if self.match_next(b'f') || self.match_next(b'F') {
+            Literal::SingleFloat(float as f32)
+        } else {
+            // `long double` is the same as `double`
+            if !self.match_next(b'l') {
+                self.match_next(b'L');
+            }
+            Literal::Float(float)
         }
     }
     // should only be called at the end of a number. mostly error handling
@@ -497,11 +582,40 @@ impl Lexer {
     /// Before: u8s{"\b'"}
     /// After:  chars{"'"}
     fn parse_single_char(&mut self, string: bool) -> Result<u8, CharError> {
+        self.parse_escaped_char(string, u8::MAX.into())
+            .map(|c| c as u8)
+    }
+    /// Read a logical character of a wide string and add its code units in `encoding` to `units`.
+    ///
+    /// Unlike `parse_single_char`, this decodes UTF-8 and allows escapes larger than a byte.
+    /// `ArchData::string_data` checks that each code unit fits in the element type.
+    fn parse_wide_char(
+        &mut self,
+        encoding: Encoding,
+        units: &mut Vec<u32>,
+    ) -> Result<(), CharError> {
+        match self.peek() {
+            // the first byte of a multi-byte UTF-8 character
+            Some(c) if c >= 0x80 => {
+                let len = match c {
+                    0xc0..=0xdf => 2,
+                    0xe0..=0xef => 3,
+                    _ => 4,
+                };
+                let bytes: Vec<_> = (0..len).filter_map(|_| self.next_char()).collect();
+                units.extend(encoding.encode(&bytes));
+            }
+            _ => units.push(self.parse_escaped_char(true, u32::MAX)?),
+        }
+        Ok(())
+    }
+    /// Read a single byte, or an escape no larger than `max`.
+    fn parse_escaped_char(&mut self, string: bool, max: u32) -> Result<u32, CharError> {
         let terminator = if string { b'"' } else { b'\'' };
         if let Some(c) = self.next_char() {
             if c == b'\\' {
                 if let Some(c) = self.next_char() {
-                    Ok(match c {
+                    Ok(u32::from(match c {
                         // escaped newline: "a\
                         // b"
                         b'\n' => unreachable!("should be handled earlier"),
@@ -517,14 +631,14 @@ impl Lexer {
                         b'f' => b'\x0c', // form feed
                         b'?' => b'?',    // a literal b'?', for trigraphs
                         b'0'..=b'9' => {
-                            return self.parse_octal_char_escape(c).map_err(|err| {
+                            return self.parse_octal_char_escape(c, max).map_err(|err| {
                                 // try to avoid extraneous errors, but don't try too hard
                                 self.match_next(b'\'');
                                 err
                             });
                         }
                         b'x' => {
-                            return self.parse_hex_char_escape().map_err(|err| {
+                            return self.parse_hex_char_escape(max).map_err(|err| {
                                 // try to avoid extraneous errors, but don't try too hard
                                 self.match_next(b'\'');
                                 err
@@ -532,12 +646,12 @@ impl Lexer {
                         }
                         _ => {
                             self.error_handler.warn(
-                                &format!("unknown character escape '\\{}'", c),
+                                Warning::UnknownEscape(c as char),
                                 self.span(self.location.offset - 1),
                             );
                             c
                         }
-                    })
+                    }))
                 } else {
                     Err(CharError::Eof)
                 }
@@ -546,28 +660,32 @@ impl Lexer {
             } else if c == terminator {
                 Err(CharError::Terminator)
             } else {
-                Ok(c)
+                Ok(c.into())
             }
         } else {
             Err(CharError::Eof)
         }
     }
-    fn parse_octal_char_escape(&mut self, start: u8) -> Result<u8, CharError> {
-        let mut base: u16 = (start - b'0').into();
+    fn parse_octal_char_escape(&mut self, start: u8, max: u32) -> Result<u32, CharError> {
+        let mut base: u32 = (start - b'0').into();
         // at most 3 digits in an octal constant, `start` is the first so only 2 possible left
         for _ in 0..2 {
             match self.peek() {
                 Some(c) if b'0' <= c && c < b'8' => {
                     self.next_char();
                     base <<= 3; // base *= 8
-                    base += u16::from(c - b'0');
+                    base += u32::from(c - b'0');
                 }
                 _ => break,
             }
         }
-        base.try_into().map_err(|_| CharError::OctalTooLarge)
+        if base > max {
+            Err(CharError::OctalTooLarge)
+        } else {
+            Ok(base)
+        }
     }
-    fn parse_hex_char_escape(&mut self) -> Result<u8, CharError> {
+    fn parse_hex_char_escape(&mut self, max: u32) -> Result<u32, CharError> {
         // first, consume the hex literal so overflow errors don't cascade
         let mut buf = Vec::new();
         let mut update = |this: &mut Self, c| {
@@ -590,7 +708,10 @@ impl Lexer {
             // NOTE: because we shifted in a 0 and c < 16, this can't overflow
             base += u64::from(digit);
         }
-        base.try_into().or(Err(CharError::HexTooLarge))
+        u32::try_from(base)
+            .ok()
+            .filter(|&c| c <= max)
+            .ok_or(CharError::HexTooLarge)
     }
     /// Parse a character literal, starting after the opening quote.
     ///
@@ -623,21 +744,25 @@ impl Lexer {
             Err(CharError::OctalTooLarge) => Err(LexError::CharEscapeOutOfRange(Radix::Octal)),
         }
     }
-    /// Parse a string literal, starting before the opening quote.
+    /// Parse a string literal, starting before the opening quote and after any prefix.
     ///
     /// Concatenates multiple adjacent literals into one string.
     /// Adds a terminating null character, even if a null character has already been found.
     ///
     /// Before: u8s{"hello" "you" "it's me" mary}
     /// After:  chars{mary}
-    fn parse_string(&mut self) -> Result<Token, LexError> {
-        let mut literal = Vec::new();
+    fn parse_string(&mut self, encoding: Option<Encoding>) -> Result<Token, LexError> {
+        let mut units = Vec::new();
         // allow multiple adjacent strings
         while self.peek() == Some(b'"') {
             self.next_char(); // start quote
             loop {
-                match self.parse_single_char(true) {
-                    Ok(c) => literal.push(c),
+                let unit = match encoding {
+                    Some(encoding) => self.parse_wide_char(encoding, &mut units),
+                    None => self.parse_single_char(true).map(|c| units.push(c.into())),
+                };
+                match unit {
+                    Ok(()) => {}
                     Err(CharError::Eof) => {
                         return Err(LexError::MissingEndQuote { string: true });
                     }
@@ -665,8 +790,12 @@ impl Lexer {
                 self.unput(b'\n');
             }
         }
-        literal.push(b'\0');
-        Ok(Literal::Str(literal).into())
+        units.push(0);
+        let literal = match encoding {
+            Some(encoding) => Literal::WideStr(encoding, units),
+            None => Literal::Str(units.into_iter().map(|c| c as u8).collect()),
+        };
+        Ok(literal.into())
     }
     /// Parse an identifier or keyword, given the starting letter.
     ///
@@ -713,6 +842,7 @@ impl Iterator for Lexer {
     /// Any item may be an error, but items will always have an associated location.
     /// The file may be empty to start, in which case the iterator will return None.
     fn next(&mut self) -> Option<Self::Item> {
+        let _lexing = stats::enter(Phase::Lexing);
         // sanity check
         if self.chars.len() == 0 {
             return None;
@@ -867,7 +997,7 @@ impl Iterator for Lexer {
                 b'.' => match self.peek() {
                     Some(c) if c.is_ascii_digit() => {
                         match self.parse_float(Radix::Decimal, String::new()) {
-                            Ok(f) => Literal::Float(f).into(),
+                            Ok(literal) => literal.into(),
                             Err(err) => {
                                 return Some(Err(Locatable {
                                     data: err,
@@ -895,13 +1025,34 @@ impl Iterator for Lexer {
                         return Some(Err(span.with(err)));
                     }
                 },
-                b'a'..=b'z' | b'A'..=b'Z' | b'_' => match self.parse_id(c) {
-                    Ok(id) => id,
-                    Err(err) => {
-                        let span = self.span(span_start);
-                        return Some(Err(span.with(err)));
+                b'a'..=b'z' | b'A'..=b'Z' | b'_' => {
+                    let encoding = match c {
+                        b'L' => Some(Encoding::Wide),
+                        b'u' => Some(Encoding::Utf16),
+                        b'U' => Some(Encoding::Utf32),
+                        _ => None,
+                    };
+                    // L"abc"
+                    let token = if encoding.is_some() && self.peek() == Some(b'"') {
+                        self.parse_string(encoding)
+                    // `u8"abc"` is an array of `char`, like a string without a prefix
+                    } else if c == b'u'
+                        && self.peek() == Some(b'8')
+                        && self.peek_next() == Some(b'"')
+                    {
+                        self.next_char();
+                        self.parse_string(None)
+                    } else {
+                        self.parse_id(c)
+                    };
+                    match token {
+                        Ok(id) => id,
+                        Err(err) => {
+                            let span = self.span(span_start);
+                            return Some(Err(span.with(err)));
+                        }
                     }
-                },
+                }
                 b'\'' => match self.parse_char() {
                     Ok(id) => id,
                     Err(err) => {
@@ -911,7 +1062,7 @@ impl Iterator for Lexer {
                 },
                 b'"' => {
                     self.unput(b'"');
-                    match self.parse_string() {
+                    match self.parse_string(None) {
                         Ok(id) => id,
                         Err(err) => {
                             let span = self.span(span_start);
@@ -941,8 +1092,11 @@ impl Iterator for Lexer {
             self.location.offset += 1;
             return Some(Err(location.with(LexError::NoNewlineAtEOF)));
         }
-        if self.debug {
-            if let Some(Ok(token)) = &c {
+        if let Some(Ok(token)) = &c {
+            if !matches!(token.data, Token::Whitespace(_)) {
+                stats::count(Counter::TokensLexed);
+            }
+            if self.debug {
                 println!("token: {}", token.data);
             }
         }
@@ -4,7 +4,9 @@
 
 use super::{cpp::CppResult, files::FileProcessor};
 use crate::{
-    error::CppError, CompileError, CompileResult, InternedStr, Locatable, Location, Token,
+    error::CppError,
+    stats::{self, Counter},
+    CompileError, CompileResult, InternedStr, Locatable, Location, Token,
 };
 use std::collections::{HashMap, HashSet, VecDeque};
 
@@ -48,7 +50,7 @@ impl<I: Peekable + ?Sized> Peekable for &mut I {
 }
 
 /// A macro definition.
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Definition {
     /// An object macro: `#define a b + 1`
     Object(Vec<Token>),
@@ -120,7 +122,9 @@ pub enum Definition {
 /// You can also use it if you have an underlying stream of tokens
 /// that you want to use in addition to the tokens generated by replacing `token`.
 ///
-/// `location` is used only for errors; in all other cases it is ignored.
+/// `location` is the location of `token`.
+/// Tokens from a replacement list are given the location of the token they replaced,
+/// along with a new [`Expansion`](crate::data::lex::Expansion) recording which macro they came from.
 #[must_use = "does not change internal state"]
 pub fn replace(
     definitions: &Definitions,
@@ -143,13 +147,14 @@ pub fn replace(
         // first step: perform (recursive) substitution on the ID
         if let Ok(Locatable {
             data: Token::Id(id),
-            ..
+            location,
         }) = token
         {
             if !ids_seen.contains(&id) {
                 match definitions.get(&id) {
                     Some(Definition::Object(replacement_list)) => {
                         ids_seen.insert(id);
+                        stats::count(Counter::MacroExpansions);
                         // prepend the new tokens to the pending tokens
                         // They need to go before, not after. For instance:
                         // ```c
@@ -159,12 +164,14 @@ pub fn replace(
                         // ```
                         // should replace to `1 + 2 c d`, not `c d 1 + 2`
                         let mut new_pending = VecDeque::new();
+                        let expanded = location.expanded_from(id);
                         // we need a `clone()` because `self.definitions` needs to keep its copy of the definition
                         new_pending.extend(
                             replacement_list
                                 .iter()
                                 .cloned()
-                                .map(|t| Ok(location.with(t))),
+                                .map(|t| expanded.with(t))
+                                .map(Ok),
                         );
                         new_pending.append(&mut pending);
                         pending = new_pending;
@@ -203,6 +210,8 @@ fn replace_function(
     use std::mem;
 
     let mut errors = Vec::new();
+    // whitespace between `f` and `(`, which has to be put back if this isn't a function call
+    let mut whitespace = Vec::new();
 
     loop {
         match incoming.front().or_else(|| inner.peek()) {
@@ -212,6 +221,14 @@ fn replace_function(
                 // TODO: need to figure out what should happen if an error token happens during replacement
                 errors.push(Err(next.unwrap().unwrap_err()));
             }
+            // `f (`
+            Some(Ok(Locatable {
+                data: Token::Whitespace(_),
+                ..
+            })) => {
+                let next = incoming.pop_front().or_else(|| inner.next());
+                whitespace.push(next.unwrap());
+            }
             // f (
             Some(Ok(Locatable {
                 data: Token::LeftParen,
@@ -223,8 +240,12 @@ fn replace_function(
                 }
                 break;
             }
-            // `f ;` or `f <EOF>`
-            Some(_) | None => return errors,
+            // `f ;` or `f <EOF>`: not a function call, so leave `f` alone
+            Some(_) | None => {
+                errors.push(Ok(location.with(Token::Id(id))));
+                errors.extend(whitespace);
+                return errors;
+            }
         }
     }
 
@@ -270,8 +291,7 @@ fn replace_function(
             // f( + )
             _ => {}
         }
-        // TODO: keep the location
-        current_arg.push(next.data);
+        current_arg.push(next);
     }
 
     let (params, body) = match definitions.get(&id) {
@@ -292,6 +312,8 @@ fn replace_function(
         }
     }
 
+    stats::count(Counter::MacroExpansions);
+    let expanded = location.expanded_from(id);
     for token in body {
         if let Token::Id(id) = *token {
             // #define f(a) { a + 1 } \n f(b) => b + 1
@@ -299,16 +321,15 @@ fn replace_function(
                 let replacement = args[index].clone();
                 replacements.extend(replacement);
             } else {
-                let token = Token::Id(id);
-                replacements.push(token);
+                replacements.push(expanded.with(Token::Id(id)));
             }
         } else {
-            replacements.push(token.clone());
+            replacements.push(expanded.with(token.clone()));
         }
     }
     // TODO: this collect is useless
     errors
         .into_iter()
-        .chain(replacements.into_iter().map(|t| Ok(location.with(t))))
+        .chain(replacements.into_iter().map(Ok))
         .collect()
 }
@@ -1,4 +1,4 @@
-use super::{CompileResult, Literal, Locatable, Token};
+use super::{Comment, CommentKind, CompileResult, Literal, Locatable, Token};
 use crate::data::lex::test::{cpp, cpp_no_newline};
 use crate::intern::InternedStr;
 
@@ -163,8 +163,16 @@ fn test_float_literals() {
         )));
     assert_float("0x.ep0", 0.875);
     assert_float("0x.ep-0l", 0.875);
-    assert_float("0xe.p-4f", 0.875);
-    assert_float("0xep-4f", 0.875);
+    // `f` makes a `float` constant
+    let single = |s| match lex(s).unwrap().unwrap().data {
+        Token::Literal(Literal::SingleFloat(f)) => f,
+        other => panic!("expected a float for {}, got {:?}", s, other),
+    };
+    assert_eq!(single("0xe.p-4f"), 0.875);
+    assert_eq!(single("0xep-4f"), 0.875);
+    assert_eq!(single(".5F"), 0.5);
+    assert_eq!(single("1e3f"), 1000.0);
+    assert_eq!(single("0.1f"), 0.1);
     assert_float("0x.000000000000000000102p0", 1.333_828_737_741_757E-23);
     // DBL_MAX is actually 1.79769313486231570814527423731704357e+308L
     // TODO: change this whenever https://github.com/rust-lang/rust/issues/31407 is closed
@@ -242,6 +250,48 @@ fn test_comments() {
     assert_eq!(lex(&"/* */".repeat(10_000)), None);
 }
 #[test]
+fn test_retain_comments() {
+    let comments = |input| {
+        let mut cpp = cpp(input);
+        cpp.retain_comments();
+        assert!(cpp.by_ref().all(|token| token.is_ok()));
+        cpp.comments()
+            .into_iter()
+            .map(|comment| {
+                let Comment { text, kind } = comment.data;
+                (text, kind, comment.location.span.start)
+            })
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(comments("int i;"), vec![]);
+    assert_eq!(
+        comments("int i; // line\n/* multi\nline */ int j;"),
+        vec![
+            (" line".into(), CommentKind::Line, 7),
+            (" multi\nline ".into(), CommentKind::Block, 15)
+        ]
+    );
+    // comments are discarded by default
+    let mut cpp = cpp("/* comment */");
+    assert!(cpp.by_ref().all(|token| token.is_ok()));
+    assert_eq!(cpp.comments(), vec![]);
+}
+#[test]
+fn test_fallthrough_comments() {
+    use super::is_fallthrough_comment;
+    for comment in &[
+        " fallthrough ",
+        "FALLTHROUGH",
+        " Fall through. ",
+        "falls-thru",
+    ] {
+        assert!(is_fallthrough_comment(comment), "{}", comment);
+    }
+    for comment in &["", "fall", "no fallthrough", "fallthrough to the next case"] {
+        assert!(!is_fallthrough_comment(comment), "{}", comment);
+    }
+}
+#[test]
 fn test_characters() {
     assert!(match_char(lex("'a'"), b'a'));
     assert!(match_char(lex("'0'"), b'0'));
@@ -300,6 +350,40 @@ fn test_strings() {
     // regression test for https://github.com/jyn514/rcc/issues/350
     let newlines = " \"a\" \n \"b\" ";
     assert!(match_str(lex(newlines), "ab"));
+    // each element of a string has to fit in a `char`
+    use crate::data::{
+        error::{Error, LexError},
+        Radix,
+    };
+    match lex_all("\"a\\x100\"").remove(0).unwrap_err().data {
+        Error::Lex(LexError::CharEscapeOutOfRange(Radix::Hexadecimal)) => {}
+        other => panic!("expected an out of range escape, got {}", other),
+    }
+}
+
+#[test]
+fn test_wide_strings() {
+    use crate::data::Encoding;
+    let wide = |input: &str| match lex(input).unwrap().unwrap().data {
+        Token::Literal(Literal::WideStr(encoding, units)) => (encoding, units),
+        other => panic!("expected a wide string for {}, got {}", input, other),
+    };
+    assert_eq!(wide("L\"ab\""), (Encoding::Wide, vec![97, 98, 0]));
+    assert_eq!(wide("U\"é\""), (Encoding::Utf32, vec![0xe9, 0]));
+    // characters outside the BMP take two code units in UTF-16
+    assert_eq!(wide("u\"😀\""), (Encoding::Utf16, vec![0xd83d, 0xde00, 0]));
+    // escapes can be larger than a byte; whether they fit is checked later
+    assert_eq!(wide("u\"\\x12345\""), (Encoding::Utf16, vec![0x12345, 0]));
+    // a string without a prefix right after a wide string is part of it
+    assert_eq!(wide("L\"a\" \"b\""), (Encoding::Wide, vec![97, 98, 0]));
+    // `u8` strings are arrays of `char`
+    assert!(match_str(lex("u8\"é\""), "é"));
+    // but the prefixes are still identifiers on their own
+    for id in &["L", "u", "U", "u8", "L8"] {
+        let token = lex(id).unwrap().unwrap().data;
+        assert_eq!(token, Token::Id(InternedStr::get_or_intern(*id)));
+    }
+    assert_eq!(lex_all("u8 \"a\"").len(), 2);
 }
 
 #[test]
@@ -326,3 +410,86 @@ fn test_for_loop() {
     .into_iter()
     .all(|x| x.is_ok()))
 }
+
+fn lossless(input: &str) -> Vec<super::LexResult<super::LosslessToken>> {
+    let mut files = codespan::Files::new();
+    let file = files.add("<test suite>", input);
+    super::Lexer::new(file, input, false).lossless().collect()
+}
+fn assert_roundtrip(input: &str) {
+    let roundtrip: String = lossless(input)
+        .into_iter()
+        .filter_map(Result::ok)
+        .map(|token| token.to_string())
+        .collect();
+    assert_eq!(roundtrip, input);
+}
+const LOSSLESS_FIXTURES: &[&str] = &[
+    include_str!("../../tests/runner-tests/readme.c"),
+    include_str!("../../tests/runner-tests/hello_world.c"),
+    include_str!("../../tests/runner-tests/factorial.c"),
+    include_str!("../../tests/runner-tests/stmt/strlen.c"),
+    "int\\\n i = 1; // line comment \\\n continued\n/**/  /* multi\nline */\tint j;\n",
+    "  \n\n\\\n  // only trivia\n",
+    "#define f(x) /* body */ x \\\n + 1\nint k = f(2)  ;\n",
+    "char *s = \"a /* not a comment */ string\"; char c = '\\'';\n",
+    "in\\\nt x = 0x10 + 1e5 + .5f;/*no space*/x\n",
+];
+#[test]
+fn test_lossless_roundtrip() {
+    for fixture in LOSSLESS_FIXTURES {
+        assert_roundtrip(fixture);
+    }
+    assert_roundtrip("");
+    // invalid input still round-trips
+    assert_roundtrip("int @ x; /* unterminated\n");
+    assert_roundtrip("char c = 'ab\n");
+    assert_roundtrip("int i; // no newline");
+}
+#[test]
+fn test_lossless_tokens() {
+    for fixture in LOSSLESS_FIXTURES {
+        let mut files = codespan::Files::new();
+        let file = files.add("<test suite>", *fixture);
+        let mut lexer = super::Lexer::new(file, *fixture, false);
+        let normal: Vec<_> = std::iter::from_fn(|| lexer.next_non_whitespace()).collect();
+        let lossless: Vec<_> = lossless(fixture)
+            .into_iter()
+            .filter_map(|token| token.map(|token| token.token).transpose())
+            .collect();
+        assert_eq!(normal, lossless);
+    }
+}
+#[test]
+fn test_lossless_trivia() {
+    use crate::data::lex::Trivia;
+    let tokens: Vec<_> = lossless("// a\n/* b */ \\\nint\n")
+        .into_iter()
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(tokens.len(), 2);
+    let trivia: Vec<_> = tokens[0]
+        .leading_trivia
+        .iter()
+        .map(|trivia| (trivia.data.clone(), trivia.location.span.start))
+        .collect();
+    let comment = |text: &str, kind| {
+        Trivia::Comment(Comment {
+            text: text.into(),
+            kind,
+        })
+    };
+    assert_eq!(
+        trivia,
+        vec![
+            (comment(" a", CommentKind::Line), 0),
+            (Trivia::Whitespace("\n".into()), 4),
+            (comment(" b ", CommentKind::Block), 5),
+            (Trivia::Whitespace(" ".into()), 12),
+            (Trivia::LineSplice, 13),
+        ]
+    );
+    assert_eq!(tokens[0].text, "int");
+    assert_eq!(tokens[1].token, None);
+    assert_eq!(tokens[1].to_string(), "\n");
+}
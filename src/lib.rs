@@ -6,6 +6,7 @@
 #![deny(unsafe_code)]
 #![deny(unused_extern_crates)]
 
+use data::error::{Warning, WarningGroup, WarningLevel};
 use std::collections::{HashMap, VecDeque};
 use std::io;
 use std::path::{Path, PathBuf};
@@ -17,7 +18,7 @@ pub use codespan;
 use cranelift_module::{Backend, Module};
 
 #[cfg(feature = "codegen")]
-pub use ir::initialize_aot_module;
+pub use ir::{finish_aot_module, initialize_aot_module, CompiledModule};
 
 #[cfg(all(feature = "color-backtrace", not(feature = "cc")))]
 compile_error!(concat!(
@@ -26,27 +27,6 @@ compile_error!(concat!(
     "` binary."
 ));
 
-/// The `Source` type for `codespan::Files`.
-///
-/// Used to store extra metadata about the file, like the absolute filename.
-///
-/// NOTE: If `path` is empty (e.g. by using `my_string.into()`),
-/// then the path will be relative to the _compiler_, not to the current file.
-/// This is recommended only for test code and proof of concepts,
-/// since it does not adhere to the C standard.
-#[derive(Debug, Clone)]
-pub struct Source {
-    pub code: Rc<str>,
-    pub path: PathBuf,
-}
-
-impl AsRef<str> for Source {
-    fn as_ref(&self) -> &str {
-        self.code.as_ref()
-    }
-}
-
-pub type Files = codespan::Files<Source>;
 #[cfg(feature = "codegen")]
 pub type Product = <cranelift_object::ObjectBackend as Backend>::Product;
 /// A result which includes all warnings, even for `Err` variants.
@@ -60,7 +40,7 @@ pub struct Program<T, E = VecDeque<CompileError>> {
     /// The warnings emitted while compiling the program
     pub warnings: VecDeque<CompileWarning>,
     /// The files that were `#include`d by the preprocessor
-    pub files: Files,
+    pub files: SourceMap,
 }
 
 impl<T, E> Program<T, E> {
@@ -73,11 +53,123 @@ impl<T, E> Program<T, E> {
     }
 }
 
+impl<T> Program<T> {
+    /// Remove the warnings disabled by `options` and turn the ones passed to `-Werror` into errors.
+    fn apply_warning_options(mut self, options: &WarningOptions) -> Self {
+        let errors = options.apply(&mut self.warnings);
+        if !errors.is_empty() {
+            match &mut self.result {
+                Ok(_) => self.result = Err(errors),
+                Err(existing) => existing.extend(errors),
+            }
+        }
+        self
+    }
+}
+
+/// Remove pedantic warnings inside code marked with `__extension__`.
+pub(crate) fn suppress_marked_extensions(
+    warnings: &mut VecDeque<CompileWarning>,
+    extensions: &[Location],
+) {
+    warnings.retain(|warning| {
+        warning.data.group() != WarningGroup::Pedantic
+            || !extensions
+                .iter()
+                .any(|extension| extension.contains(warning.location))
+    });
+}
+
+/// Remove `-Wimplicit-fallthrough` warnings for labels on the line after a `/* fallthrough */` comment.
+fn suppress_commented_fallthrough(
+    warnings: &mut VecDeque<CompileWarning>,
+    comments: &[Locatable<Comment>],
+    files: &SourceMap,
+) {
+    let line = |file, offset: u32| files.line_column(file, offset).line;
+    warnings.retain(|warning| {
+        let label = warning.location;
+        !matches!(warning.data, Warning::ImplicitFallthrough)
+            || !comments.iter().any(|comment| {
+                comment.location.file == label.file
+                    && line(comment.location.file, comment.location.span.end) + 1
+                        == line(label.file, label.span.start)
+                    && lex::is_fallthrough_comment(&comment.data.text)
+            })
+    });
+}
+
+/// Sends diagnostics to a `DiagnosticSink`, after applying `WarningOptions`.
+struct Diagnostics<'a, S> {
+    sink: S,
+    options: &'a WarningOptions,
+    /// The number of errors emitted so far, for `Opt::max_errors`
+    errors: usize,
+    /// Every comment seen so far, for `suppress_commented_fallthrough`
+    comments: Vec<Locatable<Comment>>,
+}
+
+impl<'a, S: DiagnosticSink> Diagnostics<'a, S> {
+    fn new(sink: S, options: &'a WarningOptions) -> Self {
+        Self {
+            sink,
+            options,
+            errors: 0,
+            comments: Vec::new(),
+        }
+    }
+    /// Emit all diagnostics found since the last call, sorted by location.
+    ///
+    /// This also takes any new warnings and comments from `cpp`.
+    fn flush(
+        &mut self,
+        errors: impl IntoIterator<Item = CompileError>,
+        mut warnings: VecDeque<CompileWarning>,
+        extensions: &[Location],
+        cpp: &mut PreProcessor,
+    ) {
+        warnings.extend(cpp.warnings());
+        self.comments.extend(cpp.comments());
+        let files = cpp.files();
+        suppress_commented_fallthrough(&mut warnings, &self.comments, files);
+        suppress_marked_extensions(&mut warnings, extensions);
+        let errors = errors.into_iter().chain(self.options.apply(&mut warnings));
+        let diagnostic = |kind, location| Diagnostic {
+            kind,
+            location,
+            presumed: files.presumed_location(location).into_owned(),
+        };
+        let mut diagnostics: Vec<_> =
+            errors
+                .map(|err| diagnostic(DiagnosticKind::Error(err.data), err.location))
+                .chain(warnings.into_iter().map(|warning| {
+                    diagnostic(DiagnosticKind::Warning(warning.data), warning.location)
+                }))
+                .collect();
+        diagnostics.sort_by_key(|diag| (diag.location.file, diag.location.span.start));
+        for diag in diagnostics {
+            if diag.is_error() {
+                self.errors += 1;
+            }
+            self.sink.emit(diag);
+        }
+    }
+    /// Whether to stop because of `Opt::max_errors` or `DiagnosticSink::should_abort`.
+    fn should_abort(&self, opt: &Opt) -> bool {
+        opt.error_limit_reached(self.errors) || self.sink.should_abort()
+    }
+}
+
 pub use analyze::{Analyzer, PureAnalyzer};
+pub use arch::Target;
 pub use data::*;
+pub use fold::{const_eval, ConstEvalError, ConstValue};
+#[cfg(feature = "codegen")]
+pub use opt::Artifacts;
+pub use opt::{OptBuilder, OptError};
 // https://github.com/rust-lang/rust/issues/64762
 #[allow(unreachable_pub)]
-pub use lex::{Definition, Lexer, PreProcessor, PreProcessorBuilder};
+pub use lex::{Definition, Lexer, Lossless, LosslessToken, PreProcessor, PreProcessorBuilder};
 pub use parse::Parser;
 
 #[macro_use]
@@ -85,12 +177,18 @@ mod macros;
 mod analyze;
 mod arch;
 pub mod data;
+#[cfg(feature = "codegen")]
+mod dead_code;
 mod fold;
+mod ice;
 pub mod intern;
 #[cfg(feature = "codegen")]
 mod ir;
 mod lex;
+mod opt;
 mod parse;
+pub mod repl;
+pub mod stats;
 
 pub use lex::replace;
 
@@ -159,7 +257,10 @@ impl RecursionGuard {
     }
 }
 
-#[derive(Default)]
+/// The options for compiling a program.
+///
+/// Use `Opt::builder()` to check that the options are valid before using them.
+#[derive(Clone, Debug, Default, PartialEq)]
 pub struct Opt {
     /// If set, print all tokens found by the lexer in addition to compiling.
     pub debug_lex: bool,
@@ -181,12 +282,32 @@ pub struct Opt {
     /// If set, compile and assemble but do not link. Object file is machine-dependent.
     pub no_link: bool,
 
+    /// How much to optimize the generated code, the same as `-O<n>` for `cc`.
+    ///
+    /// 0 disables all optimizations. Note that the optimizations are performed
+    /// by the module, so this must match the level passed to `initialize_aot_module`.
+    pub opt_level: u8,
+
+    /// If set, turn self-recursive tail calls (`return f(...)` inside `f`) into loops.
+    ///
+    /// `swcc` enables this by default at `-O2` and above.
+    pub optimize_sibling_calls: bool,
+
+    /// If set, compile for a freestanding environment, the same as `-ffreestanding` for `cc`.
+    ///
+    /// This defines `__STDC_HOSTED__` to 0, prefers the builtin headers (e.g. `stddef.h`),
+    /// treats `main` like any other function,
+    /// and never generates calls to the C library, e.g. `memmove` for struct copies.
+    pub freestanding: bool,
+
     #[cfg(feature = "jit")]
     /// If set, compile and emit JIT code, and do not emit object files and binaries.
     pub jit: bool,
 
-    /// The maximum number of errors to allow before giving up.
+    /// The maximum number of errors to allow before giving up, the same as `-ferror-limit` for `clang`.
     /// If None, allows an unlimited number of errors.
+    ///
+    /// Warnings don't count towards the limit.
     pub max_errors: Option<std::num::NonZeroUsize>,
 
     /// The directories to consider as part of the system search path.
@@ -195,6 +316,23 @@ pub struct Opt {
     /// The pre-defined macros to have as part of the preprocessor.
     pub definitions: HashMap<InternedStr, Definition>,
 
+    /// Which warnings to report, and which to turn into errors.
+    pub warnings: WarningOptions,
+
+    /// If set, don't warn about unused variables, parameters, and functions whose names start with `_`.
+    pub allow_unused_underscore: bool,
+
+    /// Which version of C to accept, the same as `-std=` for `cc`.
+    ///
+    /// This affects the keywords, the value of `__STDC_VERSION__`, whether `//` comments are allowed,
+    /// whether declarations can follow statements, and whether calling an undeclared function is an error.
+    pub standard: Standard,
+
+    /// The platform to compile for.
+    ///
+    /// Only the host is currently supported.
+    pub target: Target,
+
     /// The path of the original file.
     ///
     /// This allows looking for local includes relative to that file.
@@ -203,17 +341,41 @@ pub struct Opt {
     pub filename: PathBuf,
 }
 
+impl Opt {
+    /// The macros to predefine, including the ones implied by other options.
+    pub(crate) fn definitions(&self) -> HashMap<InternedStr, Definition> {
+        let mut definitions = self.definitions.clone();
+        if self.freestanding {
+            // 6.10.8.1: "The integer constant 1 if the implementation is a hosted implementation
+            // or the integer constant 0 if it is not."
+            definitions
+                .entry("__STDC_HOSTED__".into())
+                .or_insert_with(|| Definition::Object(vec![Token::Literal(Literal::Int(0))]));
+        }
+        definitions
+    }
+    /// Whether the front-end should stop after seeing `errors` errors.
+    fn error_limit_reached(&self, errors: usize) -> bool {
+        self.max_errors.is_some_and(|max| errors >= max.get())
+    }
+}
+
 /// Preprocess the source and return the tokens.
 pub fn preprocess(buf: &str, opt: Opt) -> Program<VecDeque<Locatable<Token>>> {
-    let path = opt.search_path.iter().map(|p| p.into());
-    let mut cpp = PreProcessor::new(buf, opt.filename, opt.debug_lex, path, opt.definitions);
+    let mut cpp = PreProcessor::with_options(buf, &opt);
 
     let mut tokens = VecDeque::new();
     let mut errs = VecDeque::new();
-    for result in &mut cpp {
+    let fallback = cpp.eof();
+    for result in ice::catch_panics(&mut cpp, fallback) {
         match result {
             Ok(token) => tokens.push_back(token),
-            Err(err) => errs.push_back(err),
+            Err(err) => {
+                errs.push_back(err);
+                if opt.error_limit_reached(errs.len()) {
+                    break;
+                }
+            }
         }
     }
     let result = if errs.is_empty() {
@@ -226,70 +388,287 @@ pub fn preprocess(buf: &str, opt: Opt) -> Program<VecDeque<Locatable<Token>>> {
         warnings: cpp.warnings(),
         files: cpp.into_files(),
     }
+    .apply_warning_options(&opt.warnings)
 }
 
-/// Perform semantic analysis, including type checking and constant folding.
-pub fn check_semantics(buf: &str, opt: Opt) -> Program<Vec<Locatable<hir::Declaration>>> {
-    let path = opt.search_path.iter().map(|p| p.into());
-    let mut cpp = PreProcessor::new(buf, opt.filename, opt.debug_lex, path, opt.definitions);
-
+/// Find the first token of the program, which the parser needs to start.
+///
+/// Returns the errors instead if there are no tokens, or if there were too many errors before the first one.
+fn first_token(
+    cpp: &mut PreProcessor,
+    opt: &Opt,
+) -> Result<(Locatable<Token>, VecDeque<CompileError>), VecDeque<CompileError>> {
     let mut errs = VecDeque::new();
-
-    macro_rules! handle_err {
-        ($err: expr) => {{
-            errs.push_back($err);
-            if let Some(max) = opt.max_errors {
-                if errs.len() >= max.into() {
-                    return Program::from_cpp(cpp, Err(errs));
+    let fallback = cpp.eof();
+    loop {
+        let next = ice::catch(fallback, || cpp.next_non_whitespace()).unwrap_or_else(|ice| {
+            errs.push_back(ice);
+            None
+        });
+        match next {
+            Some(Ok(token)) => return Ok((token, errs)),
+            Some(Err(err)) => {
+                errs.push_back(err);
+                if opt.error_limit_reached(errs.len()) {
+                    return Err(errs);
                 }
             }
-        }};
-    }
-    let first = loop {
-        match cpp.next_non_whitespace() {
-            Some(Ok(token)) => break Some(token),
-            Some(Err(err)) => handle_err!(err),
-            None => break None,
+            None => {
+                if errs.is_empty() {
+                    errs.push_back(cpp.eof().error(SemanticError::EmptyProgram));
+                }
+                return Err(errs);
+            }
         }
-    };
+    }
+}
 
-    let first = match first {
-        Some(token) => token,
-        None => {
-            if errs.is_empty() {
-                errs.push_back(cpp.eof().error(SemanticError::EmptyProgram));
+/// Parse the program without performing semantic analysis.
+///
+/// The AST has no type checking or validation. This is mostly useful for debugging the parser.
+pub fn parse(buf: &str, opt: Opt) -> Program<Vec<Locatable<ast::ExternalDeclaration>>> {
+    let mut cpp = PreProcessor::with_options(buf, &opt);
+    let (first, mut errs) = match first_token(&mut cpp, &opt) {
+        Ok(first) => first,
+        Err(errs) => return Program::from_cpp(cpp, Err(errs)).apply_warning_options(&opt.warnings),
+    };
+    let mut ast = vec![];
+    let fallback = first.location;
+    let mut parser = Parser::new(first, &mut cpp, opt.debug_ast).standard(opt.standard);
+    for res in ice::catch_panics(&mut parser, fallback) {
+        match res {
+            Ok(decl) => ast.push(decl),
+            Err(err) => {
+                errs.push_back(err);
+                if opt.error_limit_reached(errs.len()) {
+                    break;
+                }
             }
-            return Program::from_cpp(cpp, Err(errs));
         }
-    };
+    }
+    let mut warnings = parser.warnings();
+    suppress_marked_extensions(&mut warnings, parser.extensions());
+    warnings.extend(cpp.warnings());
+    let result = if !errs.is_empty() { Err(errs) } else { Ok(ast) };
+    Program {
+        result,
+        warnings,
+        files: cpp.into_files(),
+    }
+    .apply_warning_options(&opt.warnings)
+}
+
+/// The result of `analyze`: the HIR for a program, along with any errors and warnings.
+///
+/// Unlike `check_semantics`, this keeps the HIR even if there were errors.
+/// Expressions that couldn't be analyzed have the type `Type::Error`.
+pub struct Analysis<'hir> {
+    /// Every declaration in the program, in the order they were declared
+    pub hir: Vec<Locatable<hir::Declaration<'hir>>>,
+    /// The errors found while analyzing the program, including warnings turned into errors by `-Werror`
+    pub errors: VecDeque<CompileError>,
+    /// The warnings found while analyzing the program, after applying `Opt::warnings`
+    pub warnings: VecDeque<CompileWarning>,
+    /// The files that were `#include`d by the preprocessor
+    pub files: SourceMap,
+    /// Which declaration each identifier refers to
+    pub references: hir::References,
+}
+
+/// Preprocess, parse, and type check the program, without generating code.
+///
+/// This is meant for tools that want to inspect C programs.
+/// The metadata for each `hir::Symbol` is stored per thread,
+/// so the HIR should be inspected on the same thread that called `analyze`.
+/// It is fine to call `analyze` many times in the same process.
+///
+/// Expressions and statements are allocated in `arena`,
+/// and are all freed at once when it is dropped.
+///
+/// # Example
+/// ```
+/// use saltwater::{analyze, hir::{Arena, Initializer}, Opt, Type};
+///
+/// let arena = Arena::new();
+/// let analysis = analyze("long l = 1 + 2;", Opt::default(), &arena);
+/// assert!(analysis.errors.is_empty());
+/// let decl = &analysis.hir[0].data;
+/// let variable = decl.symbol.get();
+/// assert_eq!(variable.id.resolve_and_clone(), "l");
+/// match &decl.init {
+///     Some(Initializer::Scalar(init)) => assert_eq!(init.ctype, Type::Long(true)),
+///     _ => panic!("expected a scalar initializer"),
+/// }
+/// ```
+pub fn analyze<'hir>(buf: &str, opt: Opt, arena: &'hir hir::Arena<'hir>) -> Analysis<'hir> {
+    let mut sink = CollectingSink::new();
+    let (hir, files, references) = analyze_inner(buf, opt, arena, &mut sink);
+    Analysis {
+        hir,
+        errors: sink.errors,
+        warnings: sink.warnings,
+        files,
+        references,
+    }
+}
 
+/// Like `analyze`, but send errors and warnings to `sink` as soon as they are found.
+///
+/// The diagnostics for each declaration are sent as soon as it has been analyzed, sorted by location.
+/// `Opt::warnings` has already been applied, so disabled warnings are never sent,
+/// and warnings passed to `-Werror` are sent as errors.
+///
+/// Analysis stops early if there are `Opt::max_errors` errors or if `sink.should_abort()` returns true.
+///
+/// # Example
+/// ```
+/// use saltwater::{analyze_with_sink, hir::Arena, Opt, WriteSink};
+///
+/// let arena = Arena::new();
+/// let mut sink = WriteSink::new(Vec::new());
+/// let opt = Opt {
+///     filename: "example.c".into(),
+///     ..Opt::default()
+/// };
+/// let (hir, _files) = analyze_with_sink("int i;;", opt, &arena, &mut sink);
+/// assert_eq!(hir.len(), 1);
+/// let output = String::from_utf8(sink.into_inner().unwrap()).unwrap();
+/// assert!(output.starts_with("example.c:1:7: warning: "), "{}", output);
+/// ```
+pub fn analyze_with_sink<'hir>(
+    buf: &str,
+    opt: Opt,
+    arena: &'hir hir::Arena<'hir>,
+    sink: impl DiagnosticSink,
+) -> (Vec<Locatable<hir::Declaration<'hir>>>, SourceMap) {
+    let (hir, files, _) = analyze_inner(buf, opt, arena, sink);
+    (hir, files)
+}
+
+fn analyze_inner<'hir>(
+    buf: &str,
+    opt: Opt,
+    arena: &'hir hir::Arena<'hir>,
+    sink: impl DiagnosticSink,
+) -> (
+    Vec<Locatable<hir::Declaration<'hir>>>,
+    SourceMap,
+    hir::References,
+) {
+    let mut cpp = PreProcessor::with_options(buf, &opt);
+    // `/* fallthrough */` comments are only needed for `-Wimplicit-fallthrough`
+    if opt.warnings.level(&Warning::ImplicitFallthrough) != WarningLevel::Ignore {
+        cpp.retain_comments();
+    }
+    let mut diagnostics = Diagnostics::new(sink, &opt.warnings);
     let mut hir = vec![];
-    let mut parser = Analyzer::new(Parser::new(first, &mut cpp, opt.debug_ast), opt.debug_hir);
-    for res in &mut parser {
-        match res {
-            Ok(decl) => hir.push(decl),
-            Err(err) => handle_err!(err),
+    let mut references = hir::References::default();
+    match first_token(&mut cpp, &opt) {
+        Ok((first, errs)) => {
+            let fallback = first.location;
+            diagnostics.flush(errs, VecDeque::new(), &[], &mut cpp);
+            let parser = Parser::new(first, &mut cpp, opt.debug_ast).standard(opt.standard);
+            let mut analyzer = Analyzer::new(parser, arena, opt.debug_hir)
+                .standard(opt.standard)
+                .freestanding(opt.freestanding)
+                .allow_unused_underscore(opt.allow_unused_underscore);
+            let mut results = ice::catch_panics(&mut analyzer, fallback);
+            while !diagnostics.should_abort(&opt) {
+                let (done, errs) = match results.next() {
+                    Some(Ok(decl)) => {
+                        hir.push(decl);
+                        (false, None)
+                    }
+                    Some(Err(err)) => (false, Some(err)),
+                    None => (true, None),
+                };
+                let analyzer = results.get_mut();
+                let warnings = analyzer.warnings();
+                let extensions = analyzer.extensions().to_vec();
+                diagnostics.flush(errs, warnings, &extensions, analyzer.tokens_mut());
+                if done {
+                    break;
+                }
+            }
+            references = results.get_mut().inner.references().clone();
         }
+        Err(errs) => diagnostics.flush(errs, VecDeque::new(), &[], &mut cpp),
     }
-
-    let mut warnings = parser.inner.warnings();
-    warnings.extend(cpp.warnings());
-    if hir.is_empty() && errs.is_empty() {
-        errs.push_back(cpp.eof().error(SemanticError::EmptyProgram));
+    if hir.is_empty() && diagnostics.errors == 0 {
+        let empty = cpp.eof().error(SemanticError::EmptyProgram);
+        diagnostics.flush(Some(empty), VecDeque::new(), &[], &mut cpp);
     }
-    let result = if !errs.is_empty() { Err(errs) } else { Ok(hir) };
+    (hir, cpp.into_files(), references)
+}
+
+/// Perform semantic analysis, including type checking and constant folding.
+///
+/// This returns the HIR only if there were no errors; use `analyze` to get it regardless.
+/// Like `analyze`, the HIR is allocated in `arena`.
+pub fn check_semantics<'hir>(
+    buf: &str,
+    opt: Opt,
+    arena: &'hir hir::Arena<'hir>,
+) -> Program<Vec<Locatable<hir::Declaration<'hir>>>> {
+    let Analysis {
+        hir,
+        errors,
+        warnings,
+        files,
+        ..
+    } = analyze(buf, opt, arena);
+    let result = if errors.is_empty() {
+        Ok(hir)
+    } else {
+        Err(errors)
+    };
     Program {
         result,
         warnings,
-        files: cpp.into_files(),
+        files,
     }
 }
 
 #[cfg(feature = "codegen")]
 /// Compile and return the declarations and warnings.
-pub fn compile<B: Backend>(module: Module<B>, buf: &str, opt: Opt) -> Program<Module<B>> {
-    let debug_asm = opt.debug_asm;
-    let mut program = check_semantics(buf, opt);
+pub fn compile<B: Backend>(module: Module<B>, buf: &str, opt: Opt) -> Program<CompiledModule<B>> {
+    let program = compile_inner(module, buf, opt, false);
+    Program {
+        result: program.result.map(|(module, _)| module),
+        warnings: program.warnings,
+        files: program.files,
+    }
+}
+
+#[cfg(feature = "codegen")]
+/// Compile the program, and also return the Cranelift IR for each function as text.
+pub fn compile_with_clif<B: Backend>(
+    module: Module<B>,
+    buf: &str,
+    opt: Opt,
+) -> Program<(CompiledModule<B>, String)> {
+    let program = compile_inner(module, buf, opt, true);
+    Program {
+        result: program
+            .result
+            .map(|(module, clif)| (module, clif.unwrap_or_default())),
+        warnings: program.warnings,
+        files: program.files,
+    }
+}
+
+#[cfg(feature = "codegen")]
+fn compile_inner<B: Backend>(
+    module: Module<B>,
+    buf: &str,
+    opt: Opt,
+    emit_clif: bool,
+) -> Program<(CompiledModule<B>, Option<String>)> {
+    let (debug_asm, optimize_sibling_calls) = (opt.debug_asm, opt.optimize_sibling_calls);
+    let (freestanding, opt_level) = (opt.freestanding, opt.opt_level);
+    let warning_options = opt.warnings.clone();
+    let arena = hir::Arena::new();
+    let mut program = check_semantics(buf, opt, &arena);
     let hir = match program.result {
         Ok(hir) => hir,
         Err(err) => {
@@ -300,13 +679,35 @@ pub fn compile<B: Backend>(module: Module<B>, buf: &str, opt: Opt) -> Program<Mo
             }
         }
     };
-    let (result, ir_warnings) = ir::compile(module, hir, debug_asm);
+    // `check_semantics` never returns an empty program
+    let fallback = hir[0].location;
+    let hir = if opt_level > 0 {
+        dead_code::eliminate_dead_code(hir)
+    } else {
+        hir
+    };
+    let compiled = ice::catch(fallback, || {
+        let _codegen = stats::enter(stats::Phase::Codegen);
+        ir::compile(
+            module,
+            hir,
+            debug_asm,
+            emit_clif,
+            optimize_sibling_calls,
+            freestanding,
+        )
+    });
+    let (result, ir_warnings, clif) =
+        compiled.unwrap_or_else(|ice| (Err(ice), VecDeque::new(), None));
     program.warnings.extend(ir_warnings);
     Program {
-        result: result.map_err(|errs| vec_deque![errs]),
+        result: result
+            .map(|module| (module, clif))
+            .map_err(|errs| vec_deque![errs]),
         warnings: program.warnings,
         files: program.files,
     }
+    .apply_warning_options(&warning_options)
 }
 
 #[cfg(feature = "codegen")]
@@ -314,28 +715,56 @@ pub fn assemble(product: Product, output: &Path) -> Result<(), Error> {
     use io::Write;
     use std::fs::File;
 
+    let _emission = stats::enter(stats::Phase::Emission);
     let bytes = product.emit().map_err(Error::Platform)?;
     File::create(output)?
         .write_all(&bytes)
         .map_err(io::Error::into)
 }
 
+/// Options for the link step. Each is the same as the corresponding flag for `cc`.
+#[derive(Clone, Debug, Default)]
+pub struct LinkOptions {
+    /// `-nostartfiles`: don't link the C runtime startup files, which define `_start`.
+    pub no_start_files: bool,
+    /// `-nostdlib`: don't link the startup files or the C library.
+    pub no_std_lib: bool,
+    /// `-e <entry>`: the symbol where the program starts executing.
+    pub entry: Option<String>,
+    /// Other object files to link into the program.
+    pub objects: Vec<PathBuf>,
+}
+
 pub fn link(obj_file: &Path, output: &Path) -> Result<(), io::Error> {
+    link_with(obj_file, output, &LinkOptions::default())
+}
+
+/// Same as `link`, but with custom options.
+pub fn link_with(obj_file: &Path, output: &Path, options: &LinkOptions) -> Result<(), io::Error> {
     use std::io::{Error, ErrorKind};
+    let _linking = stats::enter(stats::Phase::Linking);
     // link the .o file using host linker
-    let status = Command::new("cc")
-        .args(&[&obj_file, Path::new("-o"), output])
-        .status()
-        .map_err(|err| {
-            if err.kind() == ErrorKind::NotFound {
-                Error::new(
-                    ErrorKind::NotFound,
-                    "could not find host cc (for linking). Is it on your PATH?",
-                )
-            } else {
-                err
-            }
-        })?;
+    let mut cc = Command::new("cc");
+    cc.arg(obj_file).args(&options.objects);
+    if options.no_start_files {
+        cc.arg("-nostartfiles");
+    }
+    if options.no_std_lib {
+        cc.arg("-nostdlib");
+    }
+    if let Some(entry) = &options.entry {
+        cc.args(["-e", entry]);
+    }
+    let status = cc.arg("-o").arg(output).status().map_err(|err| {
+        if err.kind() == ErrorKind::NotFound {
+            Error::new(
+                ErrorKind::NotFound,
+                "could not find host cc (for linking). Is it on your PATH?",
+            )
+        } else {
+            err
+        }
+    })?;
     if !status.success() {
         Err(Error::new(ErrorKind::Other, "linking program failed"))
     } else {
@@ -343,6 +772,37 @@ pub fn link(obj_file: &Path, output: &Path) -> Result<(), io::Error> {
     }
 }
 
+#[cfg(feature = "codegen")]
+/// Compile `buf` to an executable at `output`, the same as `cc -o output`.
+///
+/// The object file is written next to `output` with an `.o` extension, and removed after linking.
+/// `opt.no_link` is ignored; use `compile` and `assemble` to only make an object file.
+pub fn compile_executable(
+    buf: &str,
+    opt: Opt,
+    output: &Path,
+    options: &LinkOptions,
+) -> Program<(), Error> {
+    let module = initialize_aot_module("saltwater_main".to_owned(), opt.opt_level);
+    let Program {
+        result,
+        warnings,
+        files,
+    } = compile(module, buf, opt);
+    let object = output.with_extension("o");
+    let result = result.map_err(Error::from).and_then(|module| {
+        assemble(finish_aot_module(module), &object)?;
+        let linked = link_with(&object, output, options);
+        std::fs::remove_file(&object)?;
+        linked.map_err(Error::from)
+    });
+    Program {
+        result,
+        warnings,
+        files,
+    }
+}
+
 #[cfg(feature = "jit")]
 pub use jit::*;
 
@@ -350,30 +810,48 @@ pub use jit::*;
 mod jit {
     use super::*;
     use crate::ir::get_isa;
+    use cranelift_module::FuncId;
     use cranelift_simplejit::{SimpleJITBackend, SimpleJITBuilder};
     use std::convert::TryFrom;
 
-    pub fn initialize_jit_module() -> Module<SimpleJITBackend> {
+    /// Create a module for compiling to memory.
+    ///
+    /// `opt_level` should be the same as `Opt::opt_level`.
+    pub fn initialize_jit_module(opt_level: u8) -> Module<SimpleJITBackend> {
         let libcall_names = cranelift_module::default_libcall_names();
-        Module::new(SimpleJITBuilder::with_isa(get_isa(true), libcall_names))
+        Module::new(SimpleJITBuilder::with_isa(
+            get_isa(true, opt_level),
+            libcall_names,
+        ))
     }
 
     /// Structure used to handle compiling C code to memory instead of to disk.
     ///
     /// You can use [`from_string`] to create a JIT instance.
     /// Alternatively, if you don't care about compile warnings, you can use `JIT::try_from` instead.
-    /// If you already have a `Module`, you can use `JIT::from` to avoid having to `unwrap()`.
+    /// If you already have a `CompiledModule`, you can use `JIT::from` to avoid having to `unwrap()`.
     ///
     /// JIT stands for 'Just In Time' compiled, the way that Java and JavaScript work.
     ///
     /// [`from_string`]: #method.from_string
     pub struct JIT {
         module: Module<SimpleJITBackend>,
+        /// Functions declared with `__attribute__((constructor))`, in the order to run them before `main`
+        constructors: Vec<FuncId>,
+        /// Functions declared with `__attribute__((destructor))`, in the order to run them after `main`
+        destructors: Vec<FuncId>,
     }
 
-    impl From<Module<SimpleJITBackend>> for JIT {
-        fn from(module: Module<SimpleJITBackend>) -> Self {
-            Self { module }
+    impl From<CompiledModule<SimpleJITBackend>> for JIT {
+        fn from(compiled: CompiledModule<SimpleJITBackend>) -> Self {
+            let CompiledModule { module, pending } = compiled;
+            let constructors = pending.constructors.into_iter().map(|(_, id)| id);
+            let destructors = pending.destructors.into_iter().rev().map(|(_, id)| id);
+            Self {
+                module,
+                constructors: constructors.collect(),
+                destructors: destructors.collect(),
+            }
         }
     }
 
@@ -388,7 +866,7 @@ mod jit {
         /// Compile string and return JITed code.
         pub fn from_string<R: Into<Rc<str>>>(source: R, opt: Opt) -> Program<Self, Error> {
             let source = source.into();
-            let module = initialize_jit_module();
+            let module = initialize_jit_module(opt.opt_level);
             let program = compile(module, &source, opt);
             let result = match program.result {
                 Ok(module) => Ok(JIT::from(module)),
@@ -432,6 +910,7 @@ mod jit {
         }
         /// Given a module, run the `main` function.
         ///
+        /// Like the C runtime, this runs constructors before `main` and destructors after it returns.
         /// This automatically calls `self.finalize()`.
         /// If `main()` does not exist in the module, returns `None`; otherwise returns the exit code.
         ///
@@ -459,8 +938,24 @@ mod jit {
             // and **guaranteed** to be non-null
             let main: unsafe extern "C" fn(i32, *const *const u8) -> i32 =
                 std::mem::transmute(main);
+            run_all(&mut self.module, &self.constructors);
             // though transmute is safe, invoking this function is unsafe because we invoke C code.
-            Some(main(argc, argv.as_ptr() as *const *const u8))
+            let status = main(argc, argv.as_ptr() as *const *const u8);
+            run_all(&mut self.module, &self.destructors);
+            Some(status)
+        }
+    }
+
+    /// Call each of `functions`, which take no arguments.
+    ///
+    /// # Safety
+    /// `module` must be finalized.
+    #[allow(unsafe_code)]
+    unsafe fn run_all(module: &mut Module<SimpleJITBackend>, functions: &[FuncId]) {
+        for &id in functions {
+            let func: unsafe extern "C" fn() =
+                std::mem::transmute(module.get_finalized_function(id));
+            func();
         }
     }
 }
@@ -477,9 +972,10 @@ impl<T: Into<Rc<str>>> From<T> for Source {
 #[cfg(test)]
 mod tests {
     use super::*;
-    fn compile(src: &str) -> Result<Vec<hir::Declaration>, Error> {
+    use crate::analyze::test::arena;
+    fn compile(src: &str) -> Result<Vec<hir::Declaration<'static>>, Error> {
         let options = Opt::default();
-        let res = super::check_semantics(src, options).result;
+        let res = super::check_semantics(src, options, arena()).result;
         match res {
             Ok(decls) => Ok(decls.into_iter().map(|l| l.data).collect()),
             Err(errs) => Err(Error::Source(errs)),
@@ -508,4 +1004,707 @@ mod tests {
         assert!(parse_err.is_empty());
         assert!(err.unwrap().data.is_syntax_err());
     }
+    #[test]
+    fn error_limit() {
+        let src: String = (0..100)
+            .map(|i| format!("int f{0}() {{ return x{0}; }}\n", i))
+            .collect();
+        let errors = |max_errors| {
+            let opt = Opt {
+                max_errors: std::num::NonZeroUsize::new(max_errors),
+                ..Opt::default()
+            };
+            check_semantics(&src, opt, arena())
+                .result
+                .unwrap_err()
+                .len()
+        };
+        assert_eq!(errors(1), 1);
+        assert_eq!(errors(20), 20);
+        assert_eq!(errors(0), 100);
+        let opt = Opt {
+            max_errors: std::num::NonZeroUsize::new(3),
+            ..Opt::default()
+        };
+        assert_eq!(
+            preprocess("`\n`\n`\n`\n`\n", opt).result.unwrap_err().len(),
+            3
+        );
+    }
+    #[test]
+    fn diagnostic_sink() {
+        /// Remember whether each diagnostic was an error, and its line.
+        struct Lines {
+            seen: Vec<(bool, usize)>,
+            max_errors: usize,
+        }
+        impl DiagnosticSink for Lines {
+            fn emit(&mut self, diag: Diagnostic) {
+                self.seen.push((diag.is_error(), diag.presumed.line));
+            }
+            fn should_abort(&self) -> bool {
+                self.seen.iter().filter(|(error, _)| *error).count() >= self.max_errors
+            }
+        }
+        let analyze = |max_errors| {
+            let src = "int a;;\nint b = x;\nint c;;\nint d = y;\nint e = 1;;\n";
+            let mut sink = Lines {
+                seen: Vec::new(),
+                max_errors,
+            };
+            let (hir, _) = analyze_with_sink(src, Opt::default(), arena(), &mut sink);
+            (hir.len(), sink.seen)
+        };
+        let (decls, seen) = analyze(usize::MAX);
+        assert_eq!(decls, 5);
+        assert_eq!(
+            seen,
+            [(false, 1), (true, 2), (false, 3), (true, 4), (false, 5)]
+        );
+        // stops right after the first error
+        let (decls, seen) = analyze(1);
+        assert_eq!(decls, 1);
+        assert_eq!(seen, [(false, 1), (true, 2)]);
+    }
+    #[test]
+    fn warning_flags() {
+        use data::error::{Error, Warning};
+
+        fn check(flags: &[&str]) -> Program<Vec<Locatable<hir::Declaration<'static>>>> {
+            let mut opt = Opt::default();
+            for flag in flags {
+                opt.warnings.add_flag(flag).unwrap();
+            }
+            check_semantics("#pragma once\nchar c = '\\q';\n", opt, arena())
+        }
+        fn warnings(flags: &[&str]) -> Vec<Warning> {
+            let program = check(flags);
+            assert!(program.result.is_ok());
+            program.warnings.into_iter().map(|w| w.data).collect()
+        }
+        fn errors(flags: &[&str]) -> Vec<Error> {
+            let program = check(flags);
+            assert!(program.warnings.is_empty());
+            let errors = program.result.unwrap_err().into_iter();
+            errors.map(|err| err.data).collect()
+        }
+
+        assert_eq!(warnings(&[]), vec![Warning::UnknownEscape('q')]);
+        assert_eq!(
+            warnings(&["all"]),
+            vec![Warning::IgnoredPragma, Warning::UnknownEscape('q')]
+        );
+        assert_eq!(warnings(&["no-unknown-escape-sequence"]), vec![]);
+        assert_eq!(
+            errors(&["error"]),
+            vec![Error::Warning(Warning::UnknownEscape('q'))]
+        );
+        assert_eq!(
+            errors(&["error=unknown-pragmas", "no-unknown-escape-sequence"]),
+            vec![Error::Warning(Warning::IgnoredPragma)]
+        );
+        // later flags override earlier ones
+        assert_eq!(
+            warnings(&["no-unknown-escape-sequence", "unknown-escape-sequence"]).len(),
+            1
+        );
+        assert_eq!(
+            warnings(&["unknown-escape-sequence", "no-unknown-escape-sequence"]),
+            vec![]
+        );
+        assert_eq!(warnings(&["error", "no-error"]).len(), 1);
+        assert_eq!(
+            warnings(&["all", "error=unknown-pragmas", "no-unknown-pragmas"]),
+            vec![Warning::UnknownEscape('q')]
+        );
+        assert_eq!(
+            errors(&["no-error=unknown-escape-sequence", "error"]).len(),
+            1
+        );
+    }
+    #[test]
+    fn implicit_fallthrough() {
+        use data::error::Warning;
+
+        // the lines that have a `-Wimplicit-fallthrough` warning
+        fn fallthrough(cases: &str) -> Vec<usize> {
+            let src = format!(
+                "void abort(void); int f(int x) {{\n  switch (x) {{\n{}\n  }}\n  return x;\n}}\n",
+                cases
+            );
+            let mut opt = Opt::default();
+            opt.warnings.add_flag("implicit-fallthrough").unwrap();
+            let program = check_semantics(&src, opt, arena());
+            assert!(program.result.is_ok());
+            program
+                .warnings
+                .iter()
+                .filter(|w| matches!(w.data, Warning::ImplicitFallthrough))
+                .map(|w| src[..w.location.span.start as usize].matches('\n').count() + 1)
+                .collect()
+        }
+
+        // the annotation is needed when `serde_json` adds `PartialEq<Value>` impls for integers
+        let none: Vec<usize> = vec![];
+        assert_eq!(fallthrough("case 1: x++;\ncase 2: x++;"), vec![4]);
+        assert_eq!(fallthrough("case 1: x++;\ndefault: x++;"), vec![4]);
+        assert_eq!(fallthrough("case 1: { x++; }\ncase 2: x++;"), vec![4]);
+        // stacked labels
+        assert_eq!(fallthrough("case 1: case 2: x++;\ncase 3: ;"), vec![4]);
+        assert_eq!(fallthrough("case 1:\ncase 2: x++; break;\ncase 3: ;"), none);
+        assert_eq!(fallthrough("case 1: ;\ncase 2: x++;"), none);
+        // jumps
+        assert_eq!(fallthrough("case 1: x++; break;\ncase 2: x++;"), none);
+        assert_eq!(fallthrough("case 1: return 1;\ncase 2: x++;"), none);
+        assert_eq!(fallthrough("case 1: abort();\ncase 2: x++;"), none);
+        assert_eq!(
+            fallthrough("case 1: if (x) return 1; else break;\ncase 2: x++;"),
+            none
+        );
+        // explicit fallthrough
+        let suppressed = [
+            "case 1: x++; __attribute__((fallthrough));\ncase 2: x++;",
+            "case 1: x++; __attribute__((__fallthrough__));\ncase 2: x++;",
+            "case 1: x++; [[fallthrough]];\ncase 2: x++;",
+            "case 1: { x++; [[fallthrough]]; }\ncase 2: x++;",
+            "case 1: x++;\n/* fallthrough */\ncase 2: x++;",
+            "case 1: x++; // FALLS THROUGH.\ncase 2: x++;",
+        ];
+        for cases in &suppressed {
+            assert_eq!(fallthrough(cases), none, "{}", cases);
+        }
+        // the comment has to be on the line before the label
+        assert_eq!(
+            fallthrough("case 1: x++; /* fallthrough */\n\ncase 2: x++;"),
+            vec![5]
+        );
+        assert_eq!(
+            fallthrough("case 1: x++; /* not a fallthrough */\ncase 2: x++;"),
+            vec![4]
+        );
+    }
+    #[test]
+    fn empty_body() {
+        use data::error::Warning;
+
+        let empty_bodies = |body: &str| {
+            let src = format!(
+                "#define WAIT(x) while (x);\nint spin(void);\nint main(void) {{\n  int i, x = 1;\n{}\n  return 0;\n}}\n",
+                body
+            );
+            let program = check_semantics(&src, Opt::default(), arena());
+            assert!(program.result.is_ok(), "{}", body);
+            program
+                .warnings
+                .into_iter()
+                .filter_map(|w| match w.data {
+                    Warning::EmptyBody(kind) => Some(kind),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            empty_bodies("  while (x > 0);\n  {\n    x--;\n  }"),
+            vec!["while loop"]
+        );
+        assert_eq!(empty_bodies("  for (i = 0; i < 3; i++);"), vec!["for loop"]);
+        assert_eq!(empty_bodies("  if (x); x = 2;"), vec!["if statement"]);
+        // deliberate busy loops still warn; `{}` is the way to write them
+        assert_eq!(empty_bodies("  while (spin());"), vec!["while loop"]);
+        for silent in &[
+            "  while (spin()) {}",
+            "  for (i = 0; i < 3; i++)\n    ;",
+            "  if (x) {} else ;",
+            "  do ; while (0);",
+            "  WAIT(spin())",
+        ] {
+            assert_eq!(empty_bodies(silent), Vec::<&str>::new(), "{}", silent);
+        }
+    }
+    #[test]
+    fn noreturn_calls() {
+        use data::error::Warning;
+
+        let warnings = |body: &str| {
+            let src = format!(
+                "void abort(void);\nvoid exit(int);\nint f(int x);\nint f(int x) {{\n{}\n}}\n",
+                body
+            );
+            let mut opt = Opt::default();
+            opt.warnings.add_flag("extra").unwrap();
+            let program = check_semantics(&src, opt, arena());
+            assert!(program.result.is_ok(), "{}", body);
+            program
+                .warnings
+                .into_iter()
+                .map(|w| w.data)
+                .collect::<Vec<_>>()
+        };
+        // nothing here can be reached without a value for `y` or a non-zero `x`
+        for silent in &[
+            "  if (!x) abort();\n  return 1 / x;",
+            "  int y;\n  if (x) y = 1; else exit(1);\n  return y;",
+            "  if (x) return 1;\n  __builtin_unreachable();",
+            "  exit(1);\n  return x;",
+            "  switch (x) {\n  case 1: abort();\n  case 2: return 2;\n  default: abort(); break;\n  }\n  return 0;",
+        ] {
+            assert_eq!(warnings(silent), vec![], "{}", silent);
+        }
+        assert_eq!(
+            warnings("  exit(1);\n  x = 2;\n  x = 3;\n  return x;"),
+            vec![Warning::UnreachableCode]
+        );
+        assert_eq!(
+            warnings("  if (x) { abort(); x++; }\n  return x;"),
+            vec![Warning::UnreachableCode]
+        );
+    }
+    #[test]
+    fn fix_its() {
+        use data::error::FixIt;
+
+        // returns the span and replacement of the only fix-it for `body`
+        fn fix_it(body: &str) -> (String, std::ops::Range<usize>, String) {
+            let src = format!(
+                "struct s {{ int a; }};\nint f(int *);\nint main(void) {{\n  struct s st, *p = &st;\n  int count = 0;\n  {}\n}}\n",
+                body
+            );
+            let program = check_semantics(&src, Opt::default(), arena());
+            let fix_its: Vec<FixIt> = match program.result {
+                Err(errs) => errs
+                    .into_iter()
+                    .flat_map(|err| err.data.fix_its(err.location))
+                    .collect(),
+                Ok(_) => program
+                    .warnings
+                    .into_iter()
+                    .flat_map(|warning| warning.data.fix_its(warning.location))
+                    .collect(),
+            };
+            assert_eq!(fix_its.len(), 1, "{:?}", fix_its);
+            let span = fix_its[0].location.span;
+            let span = span.start as usize..span.end as usize;
+            (src, span, fix_its[0].replacement.clone())
+        }
+        fn assert_fix_it(body: &str, expected: &str, offset: usize, len: usize, replacement: &str) {
+            let (src, span, actual) = fix_it(body);
+            let start = src.find(expected).unwrap() + offset;
+            assert_eq!(span, start..start + len, "{}", body);
+            assert_eq!(actual, replacement);
+        }
+
+        // missing semicolon: insert after the previous token, not before the next one
+        assert_fix_it("count = 1\n  return 0;", "count = 1", 9, 0, ";");
+        // `.` on a pointer
+        assert_fix_it("p.a = 1;", "p.a", 1, 1, "->");
+        // `->` on a struct
+        assert_fix_it("st->a = 1;", "st->a", 2, 2, ".");
+        // missing `&`
+        assert_fix_it("f(count);", "f(count)", 2, 0, "&");
+        // typo in a variable name
+        assert_fix_it("return cout;", "cout", 0, 4, "count");
+        // `=` in a condition
+        assert_fix_it("if (count = 1) return 1;", "= 1)", 0, 1, "==");
+        // `;` as the body of a loop
+        assert_fix_it("while (count);", "while (count);", 13, 1, "{}");
+
+        let no_fix_its = |body: &str| {
+            let src = format!("int main(void) {{\n  int count = 0;\n  {}\n}}\n", body);
+            let program = check_semantics(&src, Opt::default(), arena());
+            assert!(program.warnings.is_empty());
+            if let Err(errs) = program.result {
+                for err in errs {
+                    assert_eq!(err.data.fix_its(err.location), vec![]);
+                }
+            }
+        };
+        no_fix_its("if ((count = 1)) return 1;");
+        no_fix_its("if (count == 1) return 1;");
+        no_fix_its("return something_else;");
+        // `int main(void)` has an unnamed parameter, which shouldn't be suggested
+        no_fix_its("return f();");
+    }
+    #[test]
+    fn standards() {
+        // whether `program` compiles without errors with `-std=<standard>`
+        let accepts = |standard: &str, program: &str| {
+            let opt = Opt {
+                standard: standard.parse().unwrap(),
+                ..Opt::default()
+            };
+            check_semantics(program, opt, arena()).result.is_ok()
+        };
+        let cases: &[(&str, &[&str], &[&str])] = &[
+            (
+                "int main(void) { return 0; } // comment\n",
+                &["gnu89", "c99", "c11", "c2x"],
+                &["c89"],
+            ),
+            (
+                "int main(void) { int i = 0; i++; int j = i; return j; }\n",
+                &["gnu89", "c99", "gnu11"],
+                &["c89"],
+            ),
+            (
+                "int main(void) { for (int i = 0; i < 2; i++) {} return 0; }\n",
+                &["gnu89", "c99", "c17"],
+                &["c89"],
+            ),
+            (
+                "int f(void); int main(void) { return f(); }\n",
+                &["c89", "c99", "c11"],
+                &[],
+            ),
+            // implicit function declarations
+            (
+                "int main(void) { return f(); }\n",
+                &["c89", "gnu89"],
+                &["c99", "gnu11", "c2x"],
+            ),
+            ("int inline, restrict;\n", &["c89"], &["gnu89", "c99"]),
+            ("int restrict;\n", &["gnu89"], &["c99", "gnu11"]),
+            (
+                "inline int f(void) { return 0; } int *restrict p;\n",
+                &["c99", "gnu11"],
+                &["c89"],
+            ),
+            (
+                "int bool, true, false, nullptr, static_assert;\n",
+                &["c89", "c11", "gnu17"],
+                &["c2x", "gnu2x"],
+            ),
+            (
+                "bool b = true; int *p = nullptr; void (*fp)(void) = nullptr;\n",
+                &["c2x", "gnu2x"],
+                &["c11", "gnu17"],
+            ),
+            (
+                "int main(void) { true; return false ? 1 : nullptr == 0; }\n",
+                &["c2x"],
+                &["c11"],
+            ),
+            // `()` means `(void)` in C2x
+            (
+                "int f(); int main(void) { return f(1); }\n",
+                &["c11", "gnu17"],
+                &["c2x", "gnu2x"],
+            ),
+            (
+                "#include <stddef.h>\nnullptr_t p = nullptr;\n",
+                &["c2x"],
+                &["c11"],
+            ),
+            (
+                "[[deprecated]] int f(void); int i [[maybe_unused]];\n",
+                &["gnu89", "gnu11", "c2x"],
+                &["c89", "c11", "c17"],
+            ),
+            (
+                "int main(void) { switch (0) { case 0: [[fallthrough]]; default: break; } }\n",
+                &["gnu11", "c2x"],
+                &["c11"],
+            ),
+        ];
+        for (program, accepted, rejected) in cases {
+            for standard in accepted.iter() {
+                assert!(accepts(standard, program), "-std={}: {}", standard, program);
+            }
+            for standard in rejected.iter() {
+                assert!(
+                    !accepts(standard, program),
+                    "-std={}: {}",
+                    standard,
+                    program
+                );
+            }
+        }
+
+        let stdc_version = |standard: &str| {
+            let opt = Opt {
+                standard: standard.parse().unwrap(),
+                ..Opt::default()
+            };
+            let tokens = preprocess("__STDC_VERSION__\n", opt).result.unwrap();
+            tokens
+                .into_iter()
+                .map(|token| token.data.to_string())
+                .collect::<String>()
+                .trim()
+                .to_owned()
+        };
+        assert_eq!(stdc_version("c89"), "__STDC_VERSION__");
+        assert_eq!(stdc_version("gnu99"), "199901");
+        assert_eq!(stdc_version("c11"), "201112");
+        assert_eq!(stdc_version("gnu17"), "201710");
+        assert_eq!(stdc_version("c2x"), "202000");
+
+        // an implicit declaration is only a warning in C89
+        let opt = Opt {
+            standard: "c89".parse().unwrap(),
+            ..Opt::default()
+        };
+        let program = check_semantics("int main() { return f(); }\n", opt, arena());
+        assert!(program.result.is_ok());
+        let warnings: Vec<_> = program.warnings.into_iter().map(|w| w.data).collect();
+        assert_eq!(
+            warnings,
+            vec![Warning::ImplicitFunctionDeclaration("f".into())]
+        );
+    }
+    #[test]
+    fn analyze_with_errors() {
+        let program = "int i = 1; int j = undeclared; long k = 2;";
+        for _ in 0..2 {
+            let analysis = analyze(program, Opt::default(), arena());
+            assert_eq!(analysis.errors.len(), 1, "{:?}", analysis.errors);
+            let names: Vec<_> = analysis
+                .hir
+                .iter()
+                .map(|decl| decl.data.symbol.get().id.resolve_and_clone())
+                .collect();
+            assert_eq!(names, ["i", "j", "k"]);
+            let init_type = |decl: &Locatable<hir::Declaration>| match &decl.data.init {
+                Some(hir::Initializer::Scalar(init)) => init.ctype.clone(),
+                other => panic!("expected a scalar initializer, got {:?}", other),
+            };
+            assert_eq!(init_type(&analysis.hir[1]), Type::Error);
+            assert_eq!(init_type(&analysis.hir[2]), Type::Long(true));
+        }
+        // `check_semantics` only returns the HIR if there were no errors
+        assert!(check_semantics(program, Opt::default(), arena())
+            .result
+            .is_err());
+    }
+    #[test]
+    fn references_to_global() {
+        let program = "int counter;\nint first(void) { return counter; }\nint second(void) { return counter + 1; }\n";
+        let analysis = analyze(program, Opt::default(), arena());
+        assert!(analysis.errors.is_empty(), "{:?}", analysis.errors);
+        let file = analysis.hir[0].location.file;
+        let offset = |needle: &str| program.rfind(needle).unwrap() as u32;
+
+        let symbol = analysis
+            .references
+            .symbol_at_offset(file, offset("counter + 1") + 2)
+            .expect("`counter` should be resolved");
+        assert_eq!(symbol, analysis.hir[0].data.symbol);
+        let meta = symbol.get();
+        assert_eq!(meta.location.span.start, 4);
+        assert_eq!(meta.ctype, Type::Int(true));
+        assert_eq!(meta.storage_class, StorageClass::Auto);
+        let references: Vec<_> = analysis.references.references(symbol).collect();
+        assert_eq!(references.len(), 2);
+        assert_eq!(references[1].span.start, offset("counter + 1"));
+        // the declaration can be looked up too
+        assert_eq!(analysis.references.symbol_at_offset(file, 4), Some(symbol));
+        // `return` isn't an identifier
+        assert_eq!(
+            analysis.references.symbol_at_offset(file, offset("return")),
+            None
+        );
+    }
+    #[test]
+    fn references_to_local() {
+        let program = "int f(void) {\n  int x = 1, y = 2;\n  return x + y * x;\n}\n";
+        let analysis = analyze(program, Opt::default(), arena());
+        assert!(analysis.errors.is_empty(), "{:?}", analysis.errors);
+        let file = analysis.hir[0].location.file;
+        let declared = program.find("x = 1").unwrap() as u32;
+        let x = analysis
+            .references
+            .symbol_at_offset(file, declared)
+            .unwrap();
+        assert_eq!(x.get().id, "x".into());
+        assert_eq!(x.get().location.span.start, declared);
+        let references: Vec<_> = analysis
+            .references
+            .references(x)
+            .map(|location| location.span.start)
+            .collect();
+        let first = program.find("x +").unwrap() as u32;
+        let second = program.rfind('x').unwrap() as u32;
+        assert_eq!(references, [first, second]);
+    }
+    #[test]
+    fn va_list_initializer() {
+        // this used to be an internal compiler error
+        let mut errs = compile_err("int i;\n__builtin_va_list v = {0};\nint j;\n");
+        let err = errs.pop_front().unwrap();
+        assert!(errs.is_empty());
+        assert!(!err.is_internal_compiler_error());
+        assert_eq!(
+            err.location.span.start,
+            "int i;\n__builtin_va_list ".len() as u32
+        );
+    }
+    #[cfg(feature = "codegen")]
+    #[test]
+    fn internal_compiler_error_in_codegen() {
+        let module = initialize_aot_module("internal_compiler_error".to_owned(), 0);
+        let program = super::compile(
+            module,
+            "int f(int n, ...) {\n  return n;\n}\n",
+            Opt::default(),
+        );
+        let errs = program.result.err().unwrap();
+        assert_eq!(errs.len(), 1);
+        assert!(errs[0].is_internal_compiler_error());
+        // defining a variadic function isn't implemented
+        assert_eq!(
+            errs[0].location.span.start,
+            "int f(int n, ...) {\n  return ".len() as u32
+        );
+    }
+    #[test]
+    fn pedantic() {
+        use data::error::Error;
+
+        let check = |standard: &str, flags: &[&str], program: &str| {
+            let mut opt = Opt {
+                standard: standard.parse().unwrap(),
+                ..Opt::default()
+            };
+            for flag in flags {
+                opt.warnings.add_flag(flag).unwrap();
+            }
+            check_semantics(program, opt, arena())
+        };
+        let long_long = Warning::C99Extension("'long long' types");
+        let void_pointer = Type::Pointer(Box::new(Type::Void), Default::default());
+        let function_pointer = Type::Pointer(
+            Box::new(Type::Function(data::types::FunctionType {
+                return_type: Box::new(Type::Int(true)),
+                params: Vec::new(),
+                varargs: false,
+            })),
+            Default::default(),
+        );
+        let cases = [
+            ("gnu11", "int i = 0b101;\n", Warning::BinaryLiteral),
+            (
+                "gnu89",
+                "int main(void) { return 0; } // comment\n// another comment\n",
+                Warning::C99Extension("'//' comments"),
+            ),
+            (
+                "gnu89",
+                "int main(void) { int i = 0; i++; int j = i; return j; }\n",
+                Warning::C99Extension("declarations after statements"),
+            ),
+            (
+                "gnu89",
+                "int main(void) { for (int i = 0; i < 2; i++) {} return 0; }\n",
+                Warning::C99Extension("declarations in 'for' loops"),
+            ),
+            ("c89", "long long i;\n", long_long.clone()),
+            (
+                "c89",
+                "enum { A, B, } e;\n",
+                Warning::C99Extension("trailing commas in enumerator lists"),
+            ),
+            (
+                "gnu11",
+                "[[deprecated]] int f(void);\n",
+                Warning::C2xExtension("[[]] attributes"),
+            ),
+            (
+                "gnu11",
+                "int f(int i) { return i ?: 1; }\n",
+                Warning::GnuConditionalOmittedOperand,
+            ),
+            (
+                "gnu11",
+                "void *v; int f(void) { int (*fp)(void) = v; return fp != 0; }\n",
+                Warning::FunctionPointerConversion(void_pointer, function_pointer),
+            ),
+            (
+                "gnu11",
+                "void *v, *w; int f(void) { return v < w; }\n",
+                Warning::VoidPointerOrdering(
+                    data::lex::ComparisonToken::Less,
+                    Type::Void,
+                    Type::Void,
+                ),
+            ),
+            (
+                "gnu11",
+                "char *f(void *v) { return v + 1; }\n",
+                Warning::VoidPointerArithmetic,
+            ),
+        ];
+        for (standard, program, warning) in &cases {
+            // nothing by default
+            let default = check(standard, &[], program);
+            assert!(default.result.is_ok(), "{}", program);
+            assert!(default.warnings.is_empty(), "{}", program);
+            // exactly one warning with -pedantic
+            let pedantic = check(standard, &["pedantic"], program);
+            assert!(pedantic.result.is_ok(), "{}", program);
+            let warnings: Vec<_> = pedantic.warnings.into_iter().map(|w| w.data).collect();
+            assert_eq!(warnings, vec![warning.clone()], "{}", program);
+            // and an error with -pedantic-errors
+            let errors = check(standard, &["error=pedantic"], program);
+            assert!(errors.warnings.is_empty(), "{}", program);
+            let errors: Vec<_> = errors
+                .result
+                .unwrap_err()
+                .into_iter()
+                .map(|e| e.data)
+                .collect();
+            assert_eq!(errors, vec![Error::Warning(warning.clone())], "{}", program);
+        }
+
+        // constructs allowed by the selected standard aren't extensions
+        let allowed = [
+            ("c2x", "int i = 0b101; [[deprecated]] int f(void);\n"),
+            (
+                "c99",
+                "long long i; enum { A, } e; int main(void) { i++; int j = i; return j; } // comment\n",
+            ),
+        ];
+        for (standard, program) in &allowed {
+            let program = check(standard, &["error=pedantic"], program);
+            assert!(program.result.is_ok());
+            assert!(program.warnings.is_empty());
+        }
+
+        // `__extension__` suppresses pedantic warnings, but not other warnings
+        let marked = [
+            ("gnu11", "int i = __extension__(0b101 + 0b1) - 1;\n"),
+            ("c89", "__extension__ long long i;\n"),
+            ("c89", "__extension__ typedef long long ll; ll i;\n"),
+            (
+                "gnu89",
+                "int main(void) { int i = 0; i++; __extension__ int j = i; return j; }\n",
+            ),
+        ];
+        for (standard, program) in &marked {
+            let program = check(standard, &["error=pedantic"], program);
+            assert!(program.result.is_ok());
+            assert!(program.warnings.is_empty());
+        }
+        let program = check(
+            "c89",
+            &["pedantic"],
+            "int main(void) { __extension__ int j; long long i; }\n",
+        );
+        let warnings: Vec<_> = program.warnings.into_iter().map(|w| w.data).collect();
+        assert_eq!(warnings, vec![long_long]);
+
+        // `()` is a prototype in C2x
+        let old_style = "int f() { return 0; } int (*fp)() = f;\n";
+        let program = check(
+            "c2x",
+            &["strict-prototypes", "old-style-definition"],
+            old_style,
+        );
+        assert!(program.result.is_ok());
+        assert!(program.warnings.is_empty());
+        let program = check(
+            "c11",
+            &["strict-prototypes", "old-style-definition"],
+            old_style,
+        );
+        assert_eq!(program.warnings.len(), 3);
+    }
 }
@@ -1,19 +1,27 @@
+use std::alloc::{GlobalAlloc, Layout, System};
 use std::collections::VecDeque;
 use std::fs::File;
 use std::io::{self, Read};
 use std::num::NonZeroUsize;
+use std::panic::{self, AssertUnwindSafe};
 use std::path::{Path, PathBuf};
 use std::process;
 use std::rc::Rc;
 use std::sync::atomic::{AtomicUsize, Ordering};
 
-use ansi_term::{ANSIString, Colour};
+use ansi_term::{Colour, Style};
 use git_testament::git_testament_macros;
 use pico_args::Arguments;
 use saltwater::{
-    assemble, compile,
-    data::{error::CompileWarning, Location},
-    link, preprocess, Error, Files, Opt, Program,
+    assemble, check_semantics, compile, compile_with_clif,
+    data::{
+        error::{CompileWarning, FixIt, Warning, WarningLevel},
+        hir::{Arena, CPrinter, PrintOptions},
+        Location,
+    },
+    link_with, parse, preprocess,
+    stats::{self, Counter, Phase},
+    Error, LinkOptions, Opt, Program, SourceMap, Standard, Version, WarningOptions,
 };
 use std::ffi::OsStr;
 use tempfile::NamedTempFile;
@@ -21,8 +29,61 @@ use tempfile::NamedTempFile;
 static ERRORS: AtomicUsize = AtomicUsize::new(0);
 static WARNINGS: AtomicUsize = AtomicUsize::new(0);
 
+/// The number of heap allocations so far, for `-ftime-report`
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+/// The number of bytes currently allocated on the heap
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+/// The most bytes that have been allocated at once
+static PEAK_ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+/// The system allocator, but keeping track of how much memory is used for `-ftime-report`.
+struct CountingAllocator;
+
+impl CountingAllocator {
+    fn allocated(size: usize) {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        let current = ALLOCATED.fetch_add(size, Ordering::Relaxed) + size;
+        PEAK_ALLOCATED.fetch_max(current, Ordering::Relaxed);
+    }
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            Self::allocated(layout.size());
+        }
+        ptr
+    }
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            Self::allocated(layout.size());
+        }
+        ptr
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new = System.realloc(ptr, layout, new_size);
+        if !new.is_null() {
+            ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+            Self::allocated(new_size);
+        }
+        new
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
 git_testament_macros!(version);
 
+/// The exit code for internal compiler errors, `EX_SOFTWARE` from `sysexits.h`.
+const ICE_EXIT_CODE: i32 = 70;
+
 const HELP: &str = concat!(
     env!("CARGO_PKG_NAME"), " ", env!("CARGO_PKG_VERSION"), "\n",
     "Joshua Nelson <jyn514@gmail.com>\n",
@@ -38,25 +99,70 @@ FLAGS:
                             This does type checking and validation and also desugars various expressions.
         --debug-ir         If set, print the intermediate representation (IR) of the program in addition to compiling.
         --debug-lex        If set, print all tokens found by the lexer in addition to compiling.
+        --emit <kinds>     Write intermediate representations instead of an executable.
+                            A comma-separated list of `tokens`, `ast`, `hir`, `c`, `clif`, `asm`, and `obj`.
+                            Each is written to <file>.<kind> (`.s` for `asm`, `.o` for `obj`, `.hir.c` for `c`)
+                            unless `-o` is given. Only the stages needed for the requested kinds are run.
+                            `c` is the analyzed program printed back out as C.
+                            `json-hir` (written to `.hir.json`) is the analyzed program as JSON.
+                            NOTE: `json-hir` only works if saltwater was compiled with the `serialize` feature.
+        --show-implicit-casts  With `--emit c`, print the conversions inserted by the compiler as casts.
         --jit              If set, will use JIT compilation for C code and instantly run compiled code (No files produced).
                             NOTE: this option only works if saltwater was compiled with the `jit` feature.
+        --repl             Read C from stdin interactively, running each statement and printing each expression.
+                            Declarations and functions stay defined for later input.
+                            `:type <expr>` shows the type of an expression and `:quit` exits.
+                            NOTE: this option only works if saltwater was compiled with the `jit` feature.
     -h, --help             Prints help information
     -c, --no-link          If set, compile and assemble but do not link. Object file is machine-dependent.
+    -O<level>              The optimization level to use, from 0 to 3. `-O` is the same as `-O1`. [default: 0]
+                            `-O2` and above also enable `-foptimize-sibling-calls`.
+        -foptimize-sibling-calls     Turn self-recursive tail calls into loops.
+        -fno-optimize-sibling-calls  Don't turn tail calls into loops, even with `-O2`.
+        -ffreestanding     Compile for a freestanding environment, such as a kernel.
+                            This defines `__STDC_HOSTED__` to 0, prefers the builtin headers,
+                            and never generates calls to the C library.
+        -nostartfiles      Don't link the C runtime startup files.
+        -nostdlib          Don't link the C runtime startup files or the C library.
     -E, --preprocess-only  If set, preprocess only, but do not do anything else.
                             Note that preprocessing discards whitespace and comments.
                             There is not currently a way to disable this behavior.
+        --force            Write an object file or executable to stdout even if it is a terminal.
     -V, --version          Prints version information
 
 OPTIONS:
         --color <when>       When to use color. May be \"never\", \"auto\", or \"always\". [default: auto]
-    -o, --output <output>    The output file to use. [default: a.out]
+                              `auto` uses color if stderr is a terminal.
+        -fdiagnostics-color=<when>  The same as `--color`.
+                              `-fdiagnostics-color` and `-fno-diagnostics-color` are the same as `always` and `never`.
+        -ftime-report        Print the time spent in each phase of the compiler and other statistics to stderr.
+        -ftime-report=<format>  The same, but in the given format. May be \"table\" or \"json\". [default: table]
+    -o, --output <output>    The output file to use. \"-\" means stdout.
+                              [default: stdout with `-E`, <file>.o with `-c`, otherwise a.out]
+    -x <language>            The language of the input file. Only `c` is supported.
+        --target <triple>    The platform to compile for, e.g. `x86_64-unknown-linux-gnu`.
+                              Only the host is currently supported. [default: the host]
+        -std=<standard>      The version of C to accept. [default: gnu11]
+                              May be `c89`, `c99`, `c11`, `c17`, or `c2x`, or the same with `gnu` instead of `c`
+                              to also allow GNU extensions. `-ansi` is the same as `-std=c89`.
         --max-errors <max>   The maximum number of errors to allow before giving up.
                              Use 0 to allow unlimited errors. [default: 10]
+        -ferror-limit=<max>  The same as `--max-errors`.
     -I, --include <dir>      Add a directory to the local include path (`#include \"file.h\"`).
                               Can be specified multiple times to add multiple directories.
     -D, --define <id[=val]>  Define an object-like macro.
                               Can be specified multiple times to add multiple macros.
                               `val` defaults to `1`.
+    -e <entry>               The symbol where the program starts executing. [default: _start]
+    -W<name>                 Enable the warning called <name>. Later flags override earlier ones.
+        -Wno-<name>          Disable the warning called <name>.
+        -Wall                Enable the warnings that are usually useful.
+        -Wextra              Enable more warnings that are sometimes useful.
+        -Werror              Report all warnings as errors.
+        -Werror=<name>       Report the warning called <name> as an error.
+        -pedantic            Warn about extensions to the selected standard. The same as `-Wpedantic`.
+        -pedantic-errors     Report extensions to the selected standard as errors.
+        -fallow-unused-underscore  Don't warn about unused declarations whose names start with `_`.
 
 ARGS:
     <file>    The file to read C source from. \"-\" means stdin (use ./- to read a file called '-').
@@ -65,8 +171,10 @@ ARGS:
 
 const USAGE: &str = "\
 usage: swcc [--help | -h] [--version | -V] [--debug-ir] [--debug-ast] [--debug-lex]
-           [--debug-hir] [--jit] [--no-link | -c] [--preprocess-only | -E]
-           [-I <dir>] [-D <id[=val]>] [<file>]";
+           [--debug-hir] [--jit] [--repl] [--no-link | -c] [--preprocess-only | -E] [-O<level>]
+           [-ffreestanding] [-nostartfiles] [-nostdlib] [-e <entry>] [-W<name>]
+           [-pedantic] [-std=<standard>] [-ftime-report] [--emit <kinds>]
+           [--show-implicit-casts] [-o <output>] [-x <language>] [--target <triple>] [--force] [-I <dir>] [-D <id[=val]>] [<file>]";
 
 struct BinOpt {
     /// The options that will be passed to `compile()`
@@ -78,6 +186,99 @@ struct BinOpt {
     preprocess_only: bool,
     /// Whether or not to use color
     color: ColorChoice,
+    /// The options to pass to the linker
+    link: LinkOptions,
+    /// Write binary output to stdout even if it's a terminal
+    force: bool,
+    /// The intermediate representations to write instead of an executable, in pipeline order.
+    ///
+    /// If empty, compile and link as usual.
+    emit: Vec<Emit>,
+    /// Whether `--emit c` prints implicit conversions as casts
+    show_implicit_casts: bool,
+    /// How to print the time spent in each phase, if at all
+    time_report: Option<TimeReport>,
+    /// Read C interactively from stdin instead of compiling a file
+    #[cfg(feature = "jit")]
+    repl: bool,
+}
+
+/// The format for `-ftime-report`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TimeReport {
+    /// An aligned table meant for people
+    Table,
+    /// A single JSON object meant for scripts
+    Json,
+}
+
+impl std::str::FromStr for TimeReport {
+    type Err = &'static str;
+    fn from_str(s: &str) -> Result<TimeReport, &'static str> {
+        match s {
+            "table" => Ok(TimeReport::Table),
+            "json" => Ok(TimeReport::Json),
+            _ => Err("Invalid time report format"),
+        }
+    }
+}
+
+/// An intermediate representation that can be written with `--emit`.
+///
+/// These are ordered by how far through the pipeline they are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Emit {
+    /// Tokens after preprocessing, one per line with their locations
+    Tokens,
+    /// The parsed program, before semantic analysis
+    Ast,
+    /// The analyzed program, using the tree dump from `hir::Declaration::dump`
+    Hir,
+    /// The analyzed program, printed back out as C by `hir::CPrinter`
+    C,
+    /// The analyzed program as JSON, using `hir::TranslationUnit`
+    #[cfg(feature = "serialize")]
+    JsonHir,
+    /// Cranelift IR for each function
+    Clif,
+    /// The disassembled object file
+    Asm,
+    /// The object file
+    Obj,
+}
+
+impl Emit {
+    fn extension(self) -> &'static str {
+        match self {
+            Emit::Tokens => "tokens",
+            Emit::Ast => "ast",
+            Emit::Hir => "hir",
+            Emit::C => "hir.c",
+            #[cfg(feature = "serialize")]
+            Emit::JsonHir => "hir.json",
+            Emit::Clif => "clif",
+            Emit::Asm => "s",
+            Emit::Obj => "o",
+        }
+    }
+}
+
+impl std::str::FromStr for Emit {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Emit, String> {
+        match s {
+            "tokens" => Ok(Emit::Tokens),
+            "ast" => Ok(Emit::Ast),
+            "hir" => Ok(Emit::Hir),
+            "c" => Ok(Emit::C),
+            #[cfg(feature = "serialize")]
+            "json-hir" => Ok(Emit::JsonHir),
+            "clif" => Ok(Emit::Clif),
+            "asm" => Ok(Emit::Asm),
+            "obj" => Ok(Emit::Obj),
+            _ => Err(format!("unknown --emit kind '{}'", s)),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -120,7 +321,10 @@ macro_rules! sw_try {
 
 // TODO: when std::process::termination is stable, make err_exit an impl for CompileError
 // TODO: then we can move this into `main` and have main return `Result<(), Error>`
-fn real_main(buf: Rc<str>, bin_opt: BinOpt, output: &Path) -> Result<(), (Error, Files)> {
+fn real_main(buf: Rc<str>, bin_opt: BinOpt, output: &Path) -> Result<(), (Error, SourceMap)> {
+    if !bin_opt.emit.is_empty() {
+        return emit_main(&buf, bin_opt, output);
+    }
     let opt = if bin_opt.preprocess_only {
         use std::io::{BufWriter, Write};
 
@@ -131,11 +335,17 @@ fn real_main(buf: Rc<str>, bin_opt: BinOpt, output: &Path) -> Result<(), (Error,
         } = preprocess(&buf, bin_opt.opt);
         handle_warnings(warnings, &files, bin_opt.color);
 
+        let tokens = sw_try!(tokens, files);
         let stdout = io::stdout();
-        let mut stdout_buf = BufWriter::new(stdout.lock());
-        for token in sw_try!(tokens, files) {
-            write!(stdout_buf, "{}", token.data).expect("failed to write to stdout");
+        let mut out: BufWriter<Box<dyn Write>> = if is_stdout(output) {
+            BufWriter::new(Box::new(stdout.lock()))
+        } else {
+            BufWriter::new(Box::new(sw_try!(File::create(output), files)))
+        };
+        for token in tokens {
+            sw_try!(write!(out, "{}", token.data), files);
         }
+        sw_try!(out.flush(), files);
 
         return Ok(());
     } else {
@@ -144,9 +354,9 @@ fn real_main(buf: Rc<str>, bin_opt: BinOpt, output: &Path) -> Result<(), (Error,
     #[cfg(feature = "jit")]
     {
         if !opt.jit {
-            aot_main(&buf, opt, output, bin_opt.color)
+            aot_main(&buf, opt, output, bin_opt.color, &bin_opt.link)
         } else {
-            let module = saltwater::initialize_jit_module();
+            let module = saltwater::initialize_jit_module(opt.opt_level);
             let Program {
                 result,
                 warnings,
@@ -154,6 +364,8 @@ fn real_main(buf: Rc<str>, bin_opt: BinOpt, output: &Path) -> Result<(), (Error,
             } = compile(module, &buf, opt);
             handle_warnings(warnings, &files, bin_opt.color);
             let mut jit = saltwater::JIT::from(sw_try!(result, files));
+            // `run_main` may never return, so print the report before running the program
+            print_time_report(bin_opt.time_report);
             if let Some(exit_code) = unsafe { jit.run_main() } {
                 std::process::exit(exit_code);
             }
@@ -161,13 +373,19 @@ fn real_main(buf: Rc<str>, bin_opt: BinOpt, output: &Path) -> Result<(), (Error,
         }
     }
     #[cfg(not(feature = "jit"))]
-    aot_main(&buf, opt, output, bin_opt.color)
+    aot_main(&buf, opt, output, bin_opt.color, &bin_opt.link)
 }
 
 #[inline]
-fn aot_main(buf: &str, opt: Opt, output: &Path, color: ColorChoice) -> Result<(), (Error, Files)> {
+fn aot_main(
+    buf: &str,
+    opt: Opt,
+    output: &Path,
+    color: ColorChoice,
+    link: &LinkOptions,
+) -> Result<(), (Error, SourceMap)> {
     let no_link = opt.no_link;
-    let module = saltwater::initialize_aot_module("saltwater_main".to_owned());
+    let module = saltwater::initialize_aot_module("saltwater_main".to_owned(), opt.opt_level);
     let Program {
         result,
         warnings,
@@ -175,29 +393,335 @@ fn aot_main(buf: &str, opt: Opt, output: &Path, color: ColorChoice) -> Result<()
     } = compile(module, buf, opt);
     handle_warnings(warnings, &files, color);
 
-    let product = sw_try!(result.map(|x| x.finish()), files);
+    let product = sw_try!(
+        result.map(|module| stats::time(Phase::Emission, || saltwater::finish_aot_module(module))),
+        files
+    );
+    // neither the object writer nor the linker can write to stdout, so give them a file and copy it afterwards
+    let stdout_file = if is_stdout(output) {
+        Some(sw_try!(NamedTempFile::new(), files))
+    } else {
+        None
+    };
+    let target = stdout_file.as_ref().map_or(output, |file| file.path());
     if no_link {
-        sw_try!(assemble(product, output), files);
+        sw_try!(assemble(product, target), files);
+    } else {
+        let tmp_file = sw_try!(NamedTempFile::new(), files);
+        sw_try!(assemble(product, tmp_file.as_ref()), files);
+        sw_try!(link_with(tmp_file.as_ref(), target, link), files);
+    }
+    if let Some(file) = stdout_file {
+        let mut compiled = sw_try!(File::open(file.path()), files);
+        sw_try!(io::copy(&mut compiled, &mut io::stdout().lock()), files);
+    }
+    Ok(())
+}
+
+/// Write each of the representations in `bin_opt.emit`, running only the stages they need.
+///
+/// Only the warnings from the last stage are shown, since each stage repeats the warnings of the ones before it.
+fn emit_main(buf: &str, bin_opt: BinOpt, output: &Path) -> Result<(), (Error, SourceMap)> {
+    let BinOpt {
+        opt,
+        color,
+        emit,
+        show_implicit_casts,
+        ..
+    } = bin_opt;
+    let last = *emit
+        .last()
+        .expect("emit_main should only be called for --emit");
+    let finish = |warnings, files: &SourceMap, is_last| {
+        if is_last {
+            handle_warnings(warnings, files, color);
+        }
+    };
+    for &kind in &emit {
+        let path = emit_path(output, &opt.filename, kind);
+        let is_last = kind == last;
+        match kind {
+            Emit::Tokens => {
+                let Program {
+                    result,
+                    warnings,
+                    files,
+                } = preprocess(buf, opt.clone());
+                let tokens = match result {
+                    Ok(tokens) => tokens,
+                    Err(errs) => {
+                        handle_warnings(warnings, &files, color);
+                        return Err((errs.into(), files));
+                    }
+                };
+                finish(warnings, &files, is_last);
+                let mut dump = String::new();
+                for token in tokens {
+                    if let saltwater::data::lex::Token::Whitespace(_) = token.data {
+                        continue;
+                    }
+                    let start = files.presumed_location(token.location);
+                    dump.push_str(&format!(
+                        "{}:{}:{}: {}\n",
+                        start.name, start.line, start.column, token.data
+                    ));
+                }
+                sw_try!(write_output(&path, dump.as_bytes()), files);
+            }
+            Emit::Ast => {
+                let Program {
+                    result,
+                    warnings,
+                    files,
+                } = parse(buf, opt.clone());
+                let ast = match result {
+                    Ok(ast) => ast,
+                    Err(errs) => {
+                        handle_warnings(warnings, &files, color);
+                        return Err((errs.into(), files));
+                    }
+                };
+                finish(warnings, &files, is_last);
+                let dump: String = ast.iter().map(|decl| format!("{}\n", decl.data)).collect();
+                sw_try!(write_output(&path, dump.as_bytes()), files);
+            }
+            Emit::Hir => {
+                let arena = Arena::new();
+                let Program {
+                    result,
+                    warnings,
+                    files,
+                } = check_semantics(buf, opt.clone(), &arena);
+                let hir = match result {
+                    Ok(hir) => hir,
+                    Err(errs) => {
+                        handle_warnings(warnings, &files, color);
+                        return Err((errs.into(), files));
+                    }
+                };
+                finish(warnings, &files, is_last);
+                let dump: String = hir.iter().map(|decl| decl.data.dump()).collect();
+                sw_try!(write_output(&path, dump.as_bytes()), files);
+            }
+            Emit::C => {
+                let arena = Arena::new();
+                let Program {
+                    result,
+                    warnings,
+                    files,
+                } = check_semantics(buf, opt.clone(), &arena);
+                let hir = match result {
+                    Ok(hir) => hir,
+                    Err(errs) => {
+                        handle_warnings(warnings, &files, color);
+                        return Err((errs.into(), files));
+                    }
+                };
+                finish(warnings, &files, is_last);
+                let mut printer = CPrinter::new(PrintOptions {
+                    implicit_casts: show_implicit_casts,
+                });
+                for decl in &hir {
+                    printer.declaration(&decl.data);
+                }
+                sw_try!(write_output(&path, printer.finish().as_bytes()), files);
+            }
+            #[cfg(feature = "serialize")]
+            Emit::JsonHir => {
+                let arena = Arena::new();
+                let Program {
+                    result,
+                    warnings,
+                    files,
+                } = check_semantics(buf, opt.clone(), &arena);
+                let hir = match result {
+                    Ok(hir) => hir,
+                    Err(errs) => {
+                        handle_warnings(warnings, &files, color);
+                        return Err((errs.into(), files));
+                    }
+                };
+                finish(warnings, &files, is_last);
+                let unit = saltwater::data::hir::TranslationUnit::from(hir);
+                let mut json = serde_json::to_string_pretty(&unit)
+                    .expect("every symbol in the HIR should be in the translation unit");
+                json.push('\n');
+                sw_try!(write_output(&path, json.as_bytes()), files);
+            }
+            // the backend produces all of these at once
+            Emit::Clif | Emit::Asm | Emit::Obj => {
+                return emit_codegen(buf, opt, &emit, output, color);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Run code generation and write whichever of `clif`, `asm`, and `obj` were requested.
+fn emit_codegen(
+    buf: &str,
+    opt: Opt,
+    emit: &[Emit],
+    output: &Path,
+    color: ColorChoice,
+) -> Result<(), (Error, SourceMap)> {
+    let filename = opt.filename.clone();
+    let module = saltwater::initialize_aot_module("saltwater_main".to_owned(), opt.opt_level);
+    let Program {
+        result,
+        warnings,
+        files,
+    } = compile_with_clif(module, buf, opt);
+    handle_warnings(warnings, &files, color);
+    let (module, clif) = sw_try!(result, files);
+    if emit.contains(&Emit::Clif) {
+        let path = emit_path(output, &filename, Emit::Clif);
+        sw_try!(write_output(&path, clif.as_bytes()), files);
+    }
+    if !emit.contains(&Emit::Asm) && !emit.contains(&Emit::Obj) {
         return Ok(());
     }
-    let tmp_file = sw_try!(NamedTempFile::new(), files);
-    sw_try!(assemble(product, tmp_file.as_ref()), files);
-    sw_try!(link(tmp_file.as_ref(), output), files);
+    let object = sw_try!(NamedTempFile::new(), files);
+    let product = stats::time(Phase::Emission, || saltwater::finish_aot_module(module));
+    sw_try!(assemble(product, object.path()), files);
+    if emit.contains(&Emit::Asm) {
+        // cranelift can't print assembly itself, so disassemble the object file instead
+        let disassembly = process::Command::new("objdump")
+            .args(["-d", "--no-show-raw-insn"])
+            .arg(object.path())
+            .output();
+        let disassembly = match disassembly {
+            Ok(out) if out.status.success() => {
+                // skip the header, which names the temporary file
+                let text = String::from_utf8_lossy(&out.stdout);
+                let start = text.find("Disassembly").unwrap_or(0);
+                text[start..].as_bytes().to_vec()
+            }
+            Ok(out) => {
+                let err = io::Error::other(format!(
+                    "objdump failed: {}",
+                    String::from_utf8_lossy(&out.stderr).trim_end()
+                ));
+                return Err((err.into(), files));
+            }
+            Err(err) => {
+                let err = io::Error::new(err.kind(), format!("failed to run objdump: {}", err));
+                return Err((err.into(), files));
+            }
+        };
+        let path = emit_path(output, &filename, Emit::Asm);
+        sw_try!(write_output(&path, &disassembly), files);
+    }
+    if emit.contains(&Emit::Obj) {
+        let object = sw_try!(std::fs::read(object.path()), files);
+        let path = emit_path(output, &filename, Emit::Obj);
+        sw_try!(write_output(&path, &object), files);
+    }
     Ok(())
 }
 
-fn handle_warnings(warnings: VecDeque<CompileWarning>, file_db: &Files, color: ColorChoice) {
-    WARNINGS.fetch_add(warnings.len(), Ordering::Relaxed);
-    let tag = if color.use_color_for(atty::Stream::Stdout) {
-        Colour::Yellow.bold().paint("warning")
+/// Where to write `kind` for `--emit`. An empty `output` means `-o` wasn't given.
+fn emit_path(output: &Path, input: &Path, kind: Emit) -> PathBuf {
+    if output != Path::new("") {
+        return output.to_owned();
+    }
+    // `output_path` has already checked that this isn't stdin
+    let stem = input.file_stem().expect("input file should have a name");
+    Path::new(stem).with_extension(kind.extension())
+}
+
+/// Write `contents` to `path`, or to stdout if `path` is `-`.
+fn write_output(path: &Path, contents: &[u8]) -> io::Result<()> {
+    use std::io::Write;
+
+    if is_stdout(path) {
+        let stdout = io::stdout();
+        let mut stdout = stdout.lock();
+        stdout.write_all(contents)?;
+        stdout.flush()
     } else {
-        ANSIString::from("warning")
+        std::fs::write(path, contents)
+    }
+}
+
+/// Whether `path` is `-`, which means stdout when used as an output file.
+fn is_stdout(path: &Path) -> bool {
+    path == Path::new("-")
+}
+
+/// Decide where to write the output if `-o` wasn't given, and check that it's somewhere sensible.
+fn output_path(bin_opt: &BinOpt, output: Option<PathBuf>) -> Result<PathBuf, &'static str> {
+    let input = &bin_opt.opt.filename;
+    if !bin_opt.emit.is_empty() {
+        return emit_output_path(bin_opt, output);
+    }
+    let output = match output {
+        Some(output) => output,
+        // like `cc -E`, preprocessed output goes to stdout unless told otherwise
+        None if bin_opt.preprocess_only => "-".into(),
+        // `swcc -c dir/file.c` writes `file.o`
+        None if bin_opt.opt.no_link => match input.file_stem() {
+            Some(stem) if !is_stdout(input) => Path::new(stem).with_extension("o"),
+            _ => return Err("-o is required with -c when reading from stdin"),
+        },
+        None => "a.out".into(),
+    };
+    if is_stdout(&output)
+        && !bin_opt.preprocess_only
+        && !bin_opt.force
+        && atty::is(atty::Stream::Stdout)
+    {
+        return Err("refusing to write binary output to a terminal (use --force to do it anyway)");
+    }
+    Ok(output)
+}
+
+/// Check `-o` for `--emit`. If it isn't given, this returns an empty path,
+/// and each kind is written next to the input file by `emit_path`.
+fn emit_output_path(bin_opt: &BinOpt, output: Option<PathBuf>) -> Result<PathBuf, &'static str> {
+    let output = match output {
+        Some(output) => output,
+        None if is_stdout(&bin_opt.opt.filename) => {
+            return Err("-o is required with --emit when reading from stdin")
+        }
+        None => return Ok(PathBuf::new()),
     };
+    if !is_stdout(&output) && bin_opt.emit.len() > 1 {
+        return Err("-o can only be a file when emitting one kind (use `-o -` to write all of them to stdout)");
+    }
+    if is_stdout(&output)
+        && bin_opt.emit.contains(&Emit::Obj)
+        && !bin_opt.force
+        && atty::is(atty::Stream::Stdout)
+    {
+        return Err("refusing to write binary output to a terminal (use --force to do it anyway)");
+    }
+    Ok(output)
+}
+
+fn handle_warnings(warnings: VecDeque<CompileWarning>, file_db: &SourceMap, color: ColorChoice) {
+    WARNINGS.fetch_add(warnings.len(), Ordering::Relaxed);
+    let color = color.use_color_for(atty::Stream::Stderr);
     for warning in warnings {
-        print!(
+        eprint!(
             "{}",
-            pretty_print(tag.clone(), warning.data, warning.location, file_db)
+            pretty_print(
+                Severity::Warning,
+                format!("{} [-W{}]", warning.data, warning.data.name()),
+                warning.location,
+                file_db,
+                color
+            )
         );
+        for note in warning.data.notes() {
+            eprint!(
+                "{}",
+                pretty_print(Severity::Note, note.data, note.location, file_db, color)
+            );
+        }
+        for fix_it in warning.data.fix_its(warning.location) {
+            eprint!("{}", render_fix_it(&fix_it, file_db, color));
+        }
     }
 }
 
@@ -216,10 +740,21 @@ fn main() {
             std::process::exit(1);
         }
     };
+    let output = output_path(&opt, output).unwrap_or_else(|err| {
+        eprintln!("{}: {}", env!("CARGO_PKG_NAME"), err);
+        process::exit(1);
+    });
 
     #[cfg(feature = "color-backtrace")]
     backtrace::install(opt.color);
 
+    #[cfg(feature = "jit")]
+    {
+        if opt.repl {
+            repl_main(opt.opt);
+        }
+    }
+
     // NOTE: only holds valid UTF-8; will panic otherwise
     let mut buf = String::new();
     opt.opt.filename = if opt.opt.filename == PathBuf::from("-") {
@@ -241,11 +776,150 @@ fn main() {
             });
         opt.opt.filename
     };
+    install_ice_hook(opt.opt.filename.clone());
     let buf: Rc<_> = buf.into();
     let max_errors = opt.opt.max_errors;
     let color_choice = opt.color;
-    real_main(buf, opt, &output)
-        .unwrap_or_else(|(err, files)| err_exit(err, max_errors, color_choice, &files));
+    let time_report = opt.time_report;
+    if time_report.is_some() {
+        stats::start();
+    }
+    // the library reports its own panics as errors, this catches any others
+    let result = panic::catch_unwind(AssertUnwindSafe(|| real_main(buf, opt, &output)))
+        .unwrap_or_else(|_| {
+            print_bug_report_request();
+            process::exit(ICE_EXIT_CODE);
+        });
+    print_time_report(time_report);
+    result.unwrap_or_else(|(err, files)| err_exit(err, max_errors, color_choice, &files));
+}
+
+/// Read C from stdin a line at a time, running each input as soon as it's complete.
+#[cfg(feature = "jit")]
+fn repl_main(opt: Opt) -> ! {
+    use io::{BufRead, Write};
+    use saltwater::repl::{self, Repl, Response};
+
+    let interactive = atty::is(atty::Stream::Stdin);
+    let mut session = Repl::new(opt);
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    let mut input = String::new();
+    loop {
+        if interactive {
+            print!("{}", if input.is_empty() { ">> " } else { ".. " });
+            let _ = io::stdout().flush();
+        }
+        let line = match lines.next() {
+            Some(Ok(line)) => line,
+            Some(Err(err)) => {
+                eprintln!("Failed to read stdin: {}", err);
+                process::exit(1);
+            }
+            None => break,
+        };
+        input.push_str(&line);
+        input.push('\n');
+        if !repl::is_complete(&input) {
+            continue;
+        }
+        let result = unsafe { session.eval(&input) };
+        input.clear();
+        for warning in session.warnings() {
+            eprintln!("warning: {} [-W{}]", warning.data, warning.data.name());
+        }
+        match result {
+            Ok(Response::Done) => {}
+            Ok(Response::Print(output)) => println!("{}", output),
+            Ok(Response::Quit) => break,
+            Err(err) => eprintln!("error: {}", err.data),
+        }
+    }
+    process::exit(0)
+}
+
+/// Print the statistics collected since `stats::start` to stderr, if `-ftime-report` was given.
+fn print_time_report(format: Option<TimeReport>) {
+    use std::fmt::Write;
+
+    let (format, stats) = match (format, stats::finish()) {
+        (Some(format), Some(stats)) => (format, stats),
+        _ => return,
+    };
+    let millis = |duration: std::time::Duration| duration.as_secs_f64() * 1000.0;
+    let total = millis(stats.total_time());
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let peak = PEAK_ALLOCATED.load(Ordering::Relaxed);
+    // writing to a `String` can't fail
+    let mut report = String::new();
+    match format {
+        TimeReport::Table => {
+            let percent = |ms: f64| if total > 0.0 { ms / total * 100.0 } else { 0.0 };
+            writeln!(report, "{:<22}{:>12}{:>9}", "phase", "time (ms)", "%").unwrap();
+            for &phase in Phase::ALL.iter() {
+                let ms = millis(stats.time(phase));
+                let (name, percent) = (phase.name(), percent(ms));
+                writeln!(report, "{:<22}{:>12.3}{:>8.1}%", name, ms, percent).unwrap();
+            }
+            writeln!(report, "{:<22}{:>12.3}{:>8.1}%", "total", total, 100.0).unwrap();
+            writeln!(report).unwrap();
+            for &counter in Counter::ALL.iter() {
+                writeln!(report, "{:<22}{:>12}", counter.name(), stats.count(counter)).unwrap();
+            }
+            writeln!(report, "{:<22}{:>12}", "heap allocations", allocations).unwrap();
+            writeln!(report, "{:<22}{:>8} KiB", "peak heap usage", peak / 1024).unwrap();
+        }
+        TimeReport::Json => {
+            let key = |name: &str| name.replace(' ', "_");
+            report.push_str("{\"time_ms\":{");
+            for &phase in Phase::ALL.iter() {
+                let ms = millis(stats.time(phase));
+                write!(report, "\"{}\":{:.3},", key(phase.name()), ms).unwrap();
+            }
+            write!(report, "\"total\":{:.3}}},\"counts\":{{", total).unwrap();
+            for &counter in Counter::ALL.iter() {
+                write!(
+                    report,
+                    "\"{}\":{},",
+                    key(counter.name()),
+                    stats.count(counter)
+                )
+                .unwrap();
+            }
+            writeln!(
+                report,
+                "\"heap_allocations\":{},\"peak_heap_bytes\":{}}}}}",
+                allocations, peak
+            )
+            .unwrap();
+        }
+    }
+    eprint!("{}", report);
+}
+
+/// Print which version of the compiler crashed, and on which file, before the panic message.
+///
+/// The library turns panics into internal compiler errors pointing to the code that caused them,
+/// which are printed afterwards by `err_exit`.
+fn install_ice_hook(filename: PathBuf) {
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        eprintln!(
+            "{} {}: internal compiler error while compiling {}",
+            env!("CARGO_PKG_NAME"),
+            version_testament!(),
+            filename.display()
+        );
+        previous(info);
+    }));
+}
+
+fn print_bug_report_request() {
+    eprintln!(
+        "note: this is a bug in {}. Please report it at {}issues, along with the smallest program that reproduces it.",
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_REPOSITORY")
+    );
 }
 
 fn os_str_to_path_buf(os_str: &OsStr) -> Result<PathBuf, bool> {
@@ -257,10 +931,18 @@ macro_rules! type_sizes {
         $(println!("{}: {}", stringify!($type), std::mem::size_of::<$type>());)*
     };
 }
-fn parse_args() -> Result<(BinOpt, PathBuf), pico_args::Error> {
-    use std::collections::HashMap;
+fn parse_args() -> Result<(BinOpt, Option<PathBuf>), pico_args::Error> {
+    parse_args_from(std::env::args_os().skip(1).collect())
+}
 
-    let mut input = Arguments::from_env();
+/// Parse `args`, not including the name of the program.
+fn parse_args_from(
+    mut args: Vec<std::ffi::OsString>,
+) -> Result<(BinOpt, Option<PathBuf>), pico_args::Error> {
+    // pico_args doesn't support values attached to short flags, so handle `-O` by hand
+    let opt_level = take_opt_level(&mut args)?;
+    let warning_flags = take_warning_flags(&mut args);
+    let mut input = Arguments::from_vec(args);
     if input.contains("-h") {
         println!("{}", USAGE);
         std::process::exit(1);
@@ -293,82 +975,229 @@ fn parse_args() -> Result<(BinOpt, PathBuf), pico_args::Error> {
             Token,
         );
     }
-    let output = input
-        .opt_value_from_os_str(["-o", "--output"], os_str_to_path_buf)?
-        .unwrap_or_else(|| "a.out".into());
-    let max_errors = input
-        .opt_value_from_fn("--max-errors", |s| {
-            usize::from_str_radix(s, 10).map(NonZeroUsize::new)
-        })?
-        .unwrap_or_else(|| Some(NonZeroUsize::new(10).unwrap()));
-    let color_choice = input
-        .opt_value_from_str("--color")?
-        .unwrap_or(ColorChoice::Auto);
-    let mut search_path = Vec::new();
+    let output = input.opt_value_from_os_str(["-o", "--output"], os_str_to_path_buf)?;
+    // there's only one language for now, but accept `-x c` for compatibility with other compilers
+    if let Some(language) = input.opt_value_from_str::<_, String>("-x")? {
+        if language != "c" {
+            return Err(pico_args::Error::ArgumentParsingFailed {
+                cause: format!("unsupported language '{}'", language),
+            });
+        }
+    }
+    let parse_max_errors = |s: &str| usize::from_str_radix(s, 10).map(NonZeroUsize::new);
+    let mut max_errors = input.opt_value_from_fn("--max-errors", parse_max_errors)?;
+    if let Some(limit) = input.opt_value_from_fn("-ferror-limit", parse_max_errors)? {
+        max_errors = Some(limit);
+    }
+    let max_errors = max_errors.unwrap_or_else(|| Some(NonZeroUsize::new(10).unwrap()));
+    let mut builder = Opt::builder().opt_level(opt_level).max_errors(max_errors);
+    if input.contains("-foptimize-sibling-calls") {
+        builder = builder.optimize_sibling_calls(true);
+    } else if input.contains("-fno-optimize-sibling-calls") {
+        builder = builder.optimize_sibling_calls(false);
+    }
+    if let Some(target) = input.opt_value_from_str::<_, String>("--target")? {
+        builder = builder.target(&target);
+    }
+    let mut color_choice = input.opt_value_from_str("--color")?;
+    // check for the flags without a value first so that `-fdiagnostics-color file.c`
+    // doesn't treat `file.c` as the value
+    if input.contains("-fdiagnostics-color") {
+        color_choice = Some(ColorChoice::Always);
+    } else if input.contains("-fno-diagnostics-color") {
+        color_choice = Some(ColorChoice::Never);
+    }
+    if let Some(choice) = input.opt_value_from_str("-fdiagnostics-color")? {
+        color_choice = Some(choice);
+    }
+    let color_choice = color_choice.unwrap_or(ColorChoice::Auto);
+    // unknown warnings are only a warning on the command line, so skip them here
+    // instead of letting `build()` reject them
+    let mut warnings = WarningOptions::default();
+    for flag in warning_flags {
+        match warnings.add_flag(&flag) {
+            Ok(()) => builder = builder.warning_flag(&flag),
+            Err(unknown) => {
+                if warnings.level(&unknown) != WarningLevel::Ignore {
+                    warn_without_location(unknown, color_choice);
+                }
+            }
+        }
+    }
+    let link = LinkOptions {
+        no_start_files: input.contains("-nostartfiles"),
+        no_std_lib: input.contains("-nostdlib"),
+        entry: input.opt_value_from_str("-e")?,
+        objects: Vec::new(),
+    };
     while let Some(include) =
         input.opt_value_from_os_str(["-I", "--include"], os_str_to_path_buf)?
     {
-        search_path.push(include);
+        builder = builder.include_path(include);
     }
-    let mut definitions = HashMap::new();
     while let Some(arg) = input.opt_value_from_str::<_, String>(["-D", "--define"])? {
-        use pico_args::Error::ArgumentParsingFailed;
-        use saltwater::data::error::LexError;
-        use std::convert::TryInto;
-
         let mut iter = arg.splitn(2, '=');
         let key = iter
             .next()
             .expect("apparently I don't understand pico_args");
-        let val = iter.next().unwrap_or("1");
-        let def = val
-            .try_into()
-            .map_err(|err: LexError| ArgumentParsingFailed {
-                cause: err.to_string(),
-            })?;
-        definitions.insert(key.into(), def);
+        builder = builder.define(key, iter.next().unwrap_or("1"));
     }
+    let mut standard = input
+        .opt_value_from_fn("-std", str::parse)?
+        .unwrap_or_default();
+    if input.contains("-ansi") {
+        standard = Standard {
+            version: Version::C89,
+            gnu: false,
+        };
+    }
+    let mut time_report = None;
+    // like `-fdiagnostics-color`, check for the flag without a value first
+    if input.contains("-ftime-report") {
+        time_report = Some(TimeReport::Table);
+    }
+    if let Some(format) = input.opt_value_from_str("-ftime-report")? {
+        time_report = Some(format);
+    }
+    let force = input.contains("--force");
+    let show_implicit_casts = input.contains("--show-implicit-casts");
+    let mut emit = match input.opt_value_from_str::<_, String>("--emit")? {
+        Some(kinds) => kinds
+            .split(',')
+            .map(str::parse)
+            .collect::<Result<Vec<Emit>, _>>()
+            .map_err(|cause| pico_args::Error::ArgumentParsingFailed { cause })?,
+        None => Vec::new(),
+    };
+    emit.sort();
+    emit.dedup();
+    let preprocess_only = input.contains(["-E", "--preprocess-only"]);
+    if !emit.is_empty() && preprocess_only {
+        return Err(pico_args::Error::ArgumentParsingFailed {
+            cause: "--emit cannot be used with -E (use --emit=tokens instead)".into(),
+        });
+    }
+    let builder = builder
+        .debug_lex(input.contains("--debug-lex"))
+        .debug_asm(input.contains("--debug-ir"))
+        .debug_ast(input.contains("--debug-ast"))
+        .debug_hir(input.contains("--debug-hir"))
+        .no_link(input.contains(["-c", "--no-link"]))
+        .freestanding(input.contains("-ffreestanding"))
+        .allow_unused_underscore(input.contains("-fallow-unused-underscore"))
+        .standard(standard);
+    #[cfg(feature = "jit")]
+    let builder = builder.jit(input.contains("--jit"));
+    #[cfg(feature = "jit")]
+    let repl = input.contains("--repl");
+    // This is a little odd because `free` expects no arguments to be left,
+    // so we have to parse it last.
+    let builder = builder.filename(
+        input
+            .free_from_os_str(os_str_to_path_buf)?
+            .unwrap_or_else(|| PathBuf::from("-")),
+    );
+    let opt = builder
+        .build()
+        .map_err(|err| pico_args::Error::ArgumentParsingFailed {
+            cause: err.to_string(),
+        })?;
     let bin_opt = BinOpt {
-        preprocess_only: input.contains(["-E", "--preprocess-only"]),
-        opt: Opt {
-            debug_lex: input.contains("--debug-lex"),
-            debug_asm: input.contains("--debug-ir"),
-            debug_ast: input.contains("--debug-ast"),
-            debug_hir: input.contains("--debug-hir"),
-            no_link: input.contains(["-c", "--no-link"]),
-            #[cfg(feature = "jit")]
-            jit: input.contains("--jit"),
-            max_errors,
-            definitions,
-            search_path,
-            // This is a little odd because `free` expects no arguments to be left,
-            // so we have to parse it last.
-            filename: input
-                .free_from_os_str(os_str_to_path_buf)?
-                .unwrap_or_else(|| "-".into()),
-        },
+        preprocess_only,
+        opt,
         color: color_choice,
+        link,
+        force,
+        emit,
+        show_implicit_casts,
+        time_report,
+        #[cfg(feature = "jit")]
+        repl,
     };
     Ok((bin_opt, output))
 }
 
-fn err_exit(err: Error, max_errors: Option<NonZeroUsize>, color: ColorChoice, files: &Files) -> ! {
+/// Remove all `-O` flags from `args` and return the optimization level of the last one.
+///
+/// `-O` is the same as `-O1`. The default is `-O0`.
+fn take_opt_level(args: &mut Vec<std::ffi::OsString>) -> Result<u8, pico_args::Error> {
+    let mut opt_level = 0;
+    let mut err = None;
+    args.retain(|arg| {
+        let level = match arg.to_str() {
+            Some(arg) if arg.starts_with("-O") => &arg["-O".len()..],
+            _ => return true,
+        };
+        match level {
+            "" => opt_level = 1,
+            "0" | "1" | "2" | "3" => opt_level = level.parse().unwrap(),
+            _ => {
+                err = Some(pico_args::Error::ArgumentParsingFailed {
+                    cause: format!("invalid optimization level '{}'", level),
+                })
+            }
+        }
+        false
+    });
+    match err {
+        Some(err) => Err(err),
+        None => Ok(opt_level),
+    }
+}
+
+/// Remove all `-W` flags from `args` and return them in order, without the leading `-W`.
+///
+/// `-pedantic` and `-pedantic-errors` are returned as `pedantic` and `error=pedantic`.
+fn take_warning_flags(args: &mut Vec<std::ffi::OsString>) -> Vec<String> {
+    let mut flags = Vec::new();
+    args.retain(|arg| match arg.to_str() {
+        Some("-pedantic") => {
+            flags.push("pedantic".to_owned());
+            false
+        }
+        Some("-pedantic-errors") => {
+            flags.push("error=pedantic".to_owned());
+            false
+        }
+        Some(arg) if arg.starts_with("-W") && arg.len() > "-W".len() => {
+            flags.push(arg["-W".len()..].to_owned());
+            false
+        }
+        _ => true,
+    });
+    flags
+}
+
+fn err_exit(
+    err: Error,
+    max_errors: Option<NonZeroUsize>,
+    color: ColorChoice,
+    files: &SourceMap,
+) -> ! {
     use Error::*;
     match err {
         Source(errs) => {
             for err in &errs {
                 error(&err.data, err.location(), files, color);
+                for note in err.notes() {
+                    print_note(&note.data, note.location, files, color);
+                }
+                print_fix_its(&err.data.fix_its(err.location), files, color);
             }
             if let Some(max) = max_errors {
                 if usize::from(max) <= errs.len() {
-                    println!(
-                        "fatal: too many errors (--max-errors {}), stopping now",
+                    eprintln!(
+                        "fatal: too many errors emitted, stopping now [-ferror-limit={}]",
                         max
                     );
                 }
             }
             let (num_warnings, num_errors) = (get_warnings(), get_errors());
             print_issues(num_warnings, num_errors);
+            if errs.iter().any(|err| err.is_internal_compiler_error()) {
+                print_bug_report_request();
+                process::exit(ICE_EXIT_CODE);
+            }
             process::exit(2);
         }
         IO(err) => fatal(&err, 3, color),
@@ -390,56 +1219,212 @@ fn print_issues(warnings: usize, errors: usize) {
     eprintln!("{} generated", msg);
 }
 
-fn error<T: std::fmt::Display>(msg: T, location: Location, file_db: &Files, color: ColorChoice) {
+fn error<T: std::fmt::Display>(
+    msg: T,
+    location: Location,
+    file_db: &SourceMap,
+    color: ColorChoice,
+) {
     ERRORS.fetch_add(1, Ordering::Relaxed);
-    let prefix = if color.use_color_for(atty::Stream::Stdout) {
-        Colour::Red.bold().paint("error")
+    let color = color.use_color_for(atty::Stream::Stderr);
+    eprint!(
+        "{}",
+        pretty_print(Severity::Error, msg, location, file_db, color)
+    );
+}
+
+fn print_note(msg: &str, location: Location, file_db: &SourceMap, color: ColorChoice) {
+    let color = color.use_color_for(atty::Stream::Stderr);
+    eprint!(
+        "{}",
+        pretty_print(Severity::Note, msg, location, file_db, color)
+    );
+}
+
+fn print_fix_its(fix_its: &[FixIt], file_db: &SourceMap, color: ColorChoice) {
+    let color = color.use_color_for(atty::Stream::Stderr);
+    for fix_it in fix_its {
+        eprint!("{}", render_fix_it(fix_it, file_db, color));
+    }
+}
+
+/// Print a warning that doesn't come from a source file, such as an unknown flag.
+fn warn_without_location(warning: Warning, color: ColorChoice) {
+    WARNINGS.fetch_add(1, Ordering::Relaxed);
+    let label = if color.use_color_for(atty::Stream::Stderr) {
+        Severity::Warning.style().paint(Severity::Warning.label())
     } else {
-        ANSIString::from("error")
+        Style::new().paint(Severity::Warning.label())
     };
-    print!("{}", pretty_print(prefix, msg, location, file_db,));
+    eprintln!(
+        "{}: {} {} [-W{}]",
+        env!("CARGO_PKG_NAME"),
+        label,
+        warning,
+        warning.name()
+    );
+}
+
+/// How serious a diagnostic is. This determines the label and color it's printed with.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Severity {
+    Error,
+    Warning,
+    Note,
+    Help,
 }
 
+impl Severity {
+    fn label(self) -> &'static str {
+        match self {
+            Severity::Error => "error:",
+            Severity::Warning => "warning:",
+            Severity::Note => "note:",
+            Severity::Help => "help:",
+        }
+    }
+    fn style(self) -> Style {
+        match self {
+            Severity::Error => Colour::Red.bold(),
+            Severity::Warning => Colour::Purple.bold(),
+            Severity::Note => Colour::Cyan.bold(),
+            Severity::Help => Colour::Green.bold(),
+        }
+    }
+}
+
+/// Render a diagnostic in the same style as GCC and Clang:
+///
+/// ```text
+/// file.c:1:9: error: message
+/// int i = "hello";
+///         ^~~~~~~
+/// ```
+///
+/// Spans covering multiple lines only underline the first line, followed by `...`.
 #[must_use]
 fn pretty_print<T: std::fmt::Display>(
-    prefix: ANSIString,
+    severity: Severity,
     msg: T,
     location: Location,
-    file_db: &Files,
+    file_db: &SourceMap,
+    color: bool,
 ) -> String {
+    let paint = |style: Style, s: &str| paint(color, style, s);
     let file = location.file;
-    let start = file_db
-        .location(file, location.span.start)
-        .expect("start location should be in bounds");
+    let presumed = file_db.presumed_location(location);
+    let position = format!("{}:{}:{}:", presumed.name, presumed.line, presumed.column);
     let buf = format!(
-        "{}:{}:{} {}: {}\n",
-        file_db.name(file).to_string_lossy(),
-        start.line.number(),
-        start.column.number(),
-        prefix,
-        msg
+        "{} {} {}\n",
+        paint(Style::new().bold(), &position),
+        paint(severity.style(), severity.label()),
+        paint(Style::new().bold(), &msg.to_string()),
     );
     // avoid printing spurious newline for errors and EOF
     if location.span.end == 0 {
         return buf;
     }
-    let end = file_db
-        .location(file, location.span.end)
-        .expect("end location should be in bounds");
-    if start.line == end.line {
-        let line = file_db
-            .line_span(file, start.line)
-            .expect("line should be in bounds");
-        format!(
-            "{}{}{}{}\n",
-            buf,
-            file_db.source_slice(file, line).unwrap(),
-            " ".repeat(start.column.0 as usize),
-            "^".repeat((end.column - start.column).0 as usize)
-        )
+    let start = file_db.line_column(file, location.span.start);
+    let end = file_db.line_column(file, location.span.end);
+    let line = file_db.line_text(file, start.line);
+    let line_start = file_db.line_span(file, start.line).start as usize;
+    let multiline = start.line != end.line;
+    let start_byte = (location.span.start as usize - line_start).min(line.len());
+    let end_byte = if multiline {
+        line.len()
+    } else {
+        (location.span.end as usize - line_start).min(line.len())
+    };
+    let (line, columns) = expand_tabs(line);
+    let (start_col, end_col) = (columns[start_byte], columns[end_byte]);
+    let mut marker = String::from("^");
+    marker.push_str(&"~".repeat(end_col.saturating_sub(start_col + 1)));
+    if multiline {
+        marker.push_str("...");
+    }
+    format!(
+        "{}{}\n{}{}\n",
+        buf,
+        line,
+        " ".repeat(start_col),
+        paint(Colour::Green.bold(), &marker)
+    )
+}
+
+/// Render a suggested edit as the source line with the edit applied:
+///
+/// ```text
+/// help: use '->' to access a member through a pointer
+///     p->a = 1;
+///      ^~
+/// ```
+fn render_fix_it(fix_it: &FixIt, file_db: &SourceMap, color: bool) -> String {
+    let paint = |style: Style, s: &str| paint(color, style, s);
+    let buf = format!(
+        "{} {}\n",
+        paint(Severity::Help.style(), Severity::Help.label()),
+        paint(Style::new().bold(), &fix_it.message),
+    );
+    let (file, span) = (fix_it.location.file, fix_it.location.span);
+    let start = file_db.line_column(file, span.start);
+    // don't try to show edits that span multiple lines
+    if start.line != file_db.line_column(file, span.end).line {
+        return buf;
+    }
+    let line = file_db.line_text(file, start.line);
+    let line_start = file_db.line_span(file, start.line).start as usize;
+    let start_byte = (span.start as usize - line_start).min(line.len());
+    let end_byte = (span.end as usize - line_start).min(line.len());
+    let fixed = format!(
+        "{}{}{}",
+        &line[..start_byte],
+        fix_it.replacement,
+        &line[end_byte..]
+    );
+    let (fixed, columns) = expand_tabs(&fixed);
+    let start_col = columns[start_byte];
+    let end_col = columns[start_byte + fix_it.replacement.len()];
+    let mut marker = String::from("^");
+    marker.push_str(&"~".repeat(end_col.saturating_sub(start_col + 1)));
+    format!(
+        "{}{}\n{}{}\n",
+        buf,
+        fixed,
+        " ".repeat(start_col),
+        paint(Colour::Green.bold(), &marker)
+    )
+}
+
+fn paint(color: bool, style: Style, s: &str) -> String {
+    if color {
+        style.paint(s).to_string()
     } else {
-        buf
+        s.to_string()
+    }
+}
+
+/// Replace each tab in `line` with spaces up to the next tab stop.
+///
+/// Returns the expanded line and the column where each byte of the original line is displayed,
+/// plus one more entry for the end of the line.
+fn expand_tabs(line: &str) -> (String, Vec<usize>) {
+    const TAB_WIDTH: usize = 8;
+    let mut expanded = String::with_capacity(line.len());
+    let mut columns = Vec::with_capacity(line.len() + 1);
+    let mut column = 0;
+    for c in line.chars() {
+        columns.resize(columns.len() + c.len_utf8(), column);
+        if c == '\t' {
+            let width = TAB_WIDTH - column % TAB_WIDTH;
+            expanded.push_str(&" ".repeat(width));
+            column += width;
+        } else {
+            expanded.push(c);
+            column += 1;
+        }
     }
+    columns.push(column);
+    (expanded, columns)
 }
 
 #[inline]
@@ -484,27 +1469,349 @@ mod backtrace {
 
 #[cfg(test)]
 mod test {
-    use super::{Files, Location};
-    use ansi_term::Style;
+    use super::{Location, Opt, Severity, SourceMap};
+    use saltwater::data::error::WarningLevel;
     use saltwater::data::lex::Span;
 
-    fn pp<S: Into<Span>>(span: S, source: &str) -> String {
-        let mut file_db = Files::new();
+    fn pp_with<S: Into<Span>>(severity: Severity, span: S, source: &str, color: bool) -> String {
+        let mut file_db = SourceMap::new();
         let source = String::from(source).into();
         let file = file_db.add("<test-suite>", source);
         let location = Location {
             file,
             span: span.into(),
+            expansion: None,
         };
-        let ansi_str = Style::new().paint("");
-        super::pretty_print(ansi_str, "", location, &file_db)
+        super::pretty_print(severity, "message", location, &file_db, color)
+    }
+    fn pp<S: Into<Span>>(span: S, source: &str) -> String {
+        pp_with(Severity::Error, span, source, false)
     }
     #[test]
     fn pretty_print() {
         assert_eq!(
             dbg!(pp(8..15, "int i = \"hello\";\n")).lines().nth(2),
-            Some("        ^^^^^^^")
+            Some("        ^~~~~~~")
         );
         pp(0..0, "");
     }
+    #[test]
+    fn render_error() {
+        assert_eq!(
+            pp(8..15, "int i = \"hello\";\n"),
+            "\
+<test-suite>:1:9: error: message
+int i = \"hello\";
+        ^~~~~~~
+"
+        );
+        assert_eq!(
+            pp(17..18, "int main() {\n    x;\n}\n"),
+            "\
+<test-suite>:2:5: error: message
+    x;
+    ^
+"
+        );
+    }
+    #[test]
+    fn render_warning() {
+        assert_eq!(
+            pp_with(Severity::Warning, 4..5, "int x;\n", false),
+            "\
+<test-suite>:1:5: warning: message
+int x;
+    ^
+"
+        );
+        assert_eq!(
+            pp_with(Severity::Warning, 4..5, "int x;\n", true),
+            "\
+\u{1b}[1m<test-suite>:1:5:\u{1b}[0m \u{1b}[1;35mwarning:\u{1b}[0m \u{1b}[1mmessage\u{1b}[0m
+int x;
+    \u{1b}[1;32m^\u{1b}[0m
+"
+        );
+    }
+    #[test]
+    fn render_notes() {
+        let arena = saltwater::hir::Arena::new();
+        let program = saltwater::check_semantics(
+            "int x;\nlong x;\nint y = 1;\nint y = 2;\n",
+            Opt {
+                filename: "<stdin>".into(),
+                ..Opt::default()
+            },
+            &arena,
+        );
+        let (errors, files) = (program.result.unwrap_err(), program.files);
+        let rendered: Vec<String> = errors
+            .iter()
+            .map(|err| {
+                let mut rendered =
+                    super::pretty_print(Severity::Error, &err.data, err.location, &files, false);
+                for note in err.notes() {
+                    rendered += &super::pretty_print(
+                        Severity::Note,
+                        note.data,
+                        note.location,
+                        &files,
+                        false,
+                    );
+                }
+                rendered
+            })
+            .collect();
+        assert_eq!(rendered.len(), 2);
+        assert!(rendered[0].ends_with(
+            "\
+long x;
+     ^
+<stdin>:1:5: note: previous declaration is here
+int x;
+    ^
+"
+        ));
+        assert_eq!(
+            rendered[1],
+            "\
+<stdin>:4:5: error: invalid program: redefinition of 'y'
+int y = 2;
+    ^
+<stdin>:3:5: note: previous definition is here
+int y = 1;
+    ^
+"
+        );
+    }
+    #[test]
+    fn render_expansion_notes() {
+        let arena = saltwater::hir::Arena::new();
+        let program = saltwater::check_semantics(
+            "#define DEREF(p) (*p)\nint f(int i) { return DEREF(i); }\n",
+            Opt {
+                filename: "<stdin>".into(),
+                ..Opt::default()
+            },
+            &arena,
+        );
+        let (errors, files) = (program.result.unwrap_err(), program.files);
+        assert_eq!(errors.len(), 1);
+        let notes = errors[0].notes();
+        assert_eq!(notes.len(), 1);
+        let note = &notes[0];
+        assert_eq!(
+            super::pretty_print(Severity::Note, &note.data, note.location, &files, false),
+            "\
+<stdin>:2:23: note: in expansion of macro 'DEREF'
+int f(int i) { return DEREF(i); }
+                      ^~~~~
+"
+        );
+    }
+    #[test]
+    fn render_expression_spans() {
+        // the first error in `statement`, with the line it's on
+        fn render(statement: &str) -> String {
+            let src = format!(
+                "struct s {{ int a; }} s;\nint f(int);\nconst int c = 1;\nint main(int i, double d) {{\n{}\n}}\n",
+                statement
+            );
+            let arena = saltwater::hir::Arena::new();
+            let program = saltwater::check_semantics(
+                &src,
+                Opt {
+                    filename: "<stdin>".into(),
+                    ..Opt::default()
+                },
+                &arena,
+            );
+            let errors = program.result.unwrap_err();
+            let err = errors.iter().next().unwrap();
+            super::pretty_print(
+                Severity::Error,
+                &err.data,
+                err.location,
+                &program.files,
+                false,
+            )
+        }
+        // binary expressions include the parentheses around their operands
+        assert!(render("\treturn (i + 1) * s;").ends_with(
+            "\
+\treturn (i + 1) * s;
+\t       ^~~~~~~~~~~
+"
+            .replace('\t', "        ")
+            .as_str()
+        ));
+        // a call covers its arguments
+        assert!(render("    return s - f(i);").ends_with(
+            "\
+    return s - f(i);
+           ^~~~~~~~
+"
+        ));
+        // unary operators cover their operand
+        assert!(render("    return ~d;").ends_with(
+            "\
+    return ~d;
+           ^~
+"
+        ));
+        assert!(render("    return (int)(int *)d;").ends_with(
+            "\
+    return (int)(int *)d;
+                ^~~~~~~~
+"
+        ));
+        // assignments point at the operator
+        assert_eq!(
+            render("    c += 2;"),
+            "\
+<stdin>:5:7: error: invalid program: cannot assign to variable 'c' with `const` qualifier
+    c += 2;
+      ^~
+"
+        );
+    }
+    #[test]
+    fn render_fix_its() {
+        fn help(body: &str) -> String {
+            let src = format!(
+                "struct s {{ int a; }};\nint f(int *);\nint main(void) {{\n\tstruct s st, *p = &st;\n\tint count = 0;\n{}\n}}\n",
+                body
+            );
+            let arena = saltwater::hir::Arena::new();
+            let program = saltwater::check_semantics(&src, Opt::default(), &arena);
+            let mut fix_its = program
+                .warnings
+                .iter()
+                .flat_map(|warning| warning.data.fix_its(warning.location))
+                .collect::<Vec<_>>();
+            if let Err(errs) = &program.result {
+                fix_its.extend(errs.iter().flat_map(|err| err.data.fix_its(err.location)));
+            }
+            fix_its
+                .iter()
+                .map(|fix_it| super::render_fix_it(fix_it, &program.files, false))
+                .collect()
+        }
+        assert_eq!(
+            help("\tcount = 1\n\treturn 0;"),
+            "\
+help: insert ';'
+\tcount = 1;
+\t         ^
+"
+            .replace('\t', "        ")
+        );
+        assert_eq!(
+            help("    p.a = 1;"),
+            "\
+help: use '->' to access a member through a pointer
+    p->a = 1;
+     ^~
+"
+        );
+        assert_eq!(
+            help("    st->a = 1;"),
+            "\
+help: use '.' to access a member of a struct or union
+    st.a = 1;
+      ^
+"
+        );
+        assert_eq!(
+            help("    f(count);"),
+            "\
+help: take the address with '&'
+    f(&count);
+      ^
+"
+        );
+        assert_eq!(
+            help("    return cout;"),
+            "\
+help: did you mean 'count'?
+    return count;
+           ^~~~~
+"
+        );
+        assert_eq!(
+            help("    if (count = 1) return 1;"),
+            "\
+help: use '==' to compare for equality
+    if (count == 1) return 1;
+              ^~
+"
+        );
+        assert_eq!(
+            help("    int scanf(const char *, ...);\n    scanf(\"%d\", count);"),
+            "\
+help: take the address with '&'
+    scanf(\"%d\", &count);
+                ^
+"
+        );
+    }
+    #[test]
+    fn render_tabs() {
+        // the tab before `x` is expanded to the next tab stop,
+        // and the tab inside the span is underlined as well
+        assert_eq!(
+            pp(4..9, "int\tx =\t1;\n"),
+            "\
+<test-suite>:1:5: error: message
+int     x =     1;
+        ^~~~~~~~~
+"
+        );
+    }
+    #[test]
+    fn render_multiline() {
+        assert_eq!(
+            pp(8..17, "int i = (1 +\n 2);\n"),
+            "\
+<test-suite>:1:9: error: message
+int i = (1 +
+        ^~~~...
+"
+        );
+    }
+    #[test]
+    fn args_match_builder() {
+        use saltwater::{Standard, Version};
+        use std::num::NonZeroUsize;
+
+        let args = "-O2 -std=c99 -D NDEBUG -D X=2 -I inc -Wno-main -Werror=return-type -c file.c";
+        let (bin_opt, output) =
+            super::parse_args_from(args.split(' ').map(Into::into).collect()).unwrap();
+        let expected = Opt::builder()
+            .opt_level(2)
+            .max_errors(NonZeroUsize::new(10))
+            .standard(Standard {
+                version: Version::C99,
+                gnu: false,
+            })
+            .define("NDEBUG", "1")
+            .define("X", "2")
+            .include_path("inc")
+            .warning("main", WarningLevel::Ignore)
+            .warning("return-type", WarningLevel::Error)
+            .no_link(true)
+            .filename("file.c")
+            .build()
+            .unwrap();
+        assert_eq!(bin_opt.opt, expected);
+        assert_eq!(output, None);
+    }
+    #[test]
+    fn invalid_args() {
+        let parse = |args: &str| super::parse_args_from(args.split(' ').map(Into::into).collect());
+        assert!(parse("--target not-a-target file.c").is_err());
+        assert!(parse("-D 1x file.c").is_err());
+        // unknown warnings are only a warning
+        assert!(parse("-Wno-not-a-warning file.c").is_ok());
+    }
 }
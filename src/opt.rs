@@ -0,0 +1,395 @@
+//! Building and checking `Opt`
+//!
+//! `swcc` parses its command line into an `OptBuilder`,
+//! so anything the binary can do can also be done by embedders, with the same defaults.
+
+#[cfg(feature = "codegen")]
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::num::NonZeroUsize;
+#[cfg(feature = "codegen")]
+use std::path::Path;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use target_lexicon::Triple;
+
+#[cfg(test)]
+use crate::data::error::WarningOptions;
+use crate::data::error::{LexError, WarningLevel};
+#[cfg(feature = "codegen")]
+use crate::{stats, Error, Program, SourceMap};
+use crate::{Definition, Opt, Standard, Target};
+
+/// An option, or combination of options, that `OptBuilder::build` rejected.
+#[derive(Clone, Debug, PartialEq, thiserror::Error)]
+pub enum OptError {
+    #[error("invalid target '{0}'")]
+    InvalidTarget(String),
+
+    /// (the target, the host)
+    #[error("unsupported target '{0}' (only the host, '{1}', is currently supported)")]
+    UnsupportedTarget(Triple, Triple),
+
+    #[error("invalid optimization level {0} (expected 0, 1, 2, or 3)")]
+    InvalidOptLevel(u8),
+
+    #[error("unknown warning option '{0}'")]
+    UnknownWarning(String),
+
+    #[error("invalid macro name '{0}'")]
+    InvalidMacroName(String),
+
+    #[error("invalid definition for macro '{name}': {error}")]
+    InvalidDefinition { name: String, error: LexError },
+
+    #[error("{0} cannot be used with {1}")]
+    Conflict(&'static str, &'static str),
+}
+
+/// A builder for `Opt` which checks that the options make sense together.
+///
+/// ```
+/// use saltwater::{data::error::WarningLevel, Opt, Standard, Version};
+///
+/// let opt = Opt::builder()
+///     .standard(Standard { version: Version::C11, gnu: false })
+///     .define("NDEBUG", "1")
+///     .include_path("include")
+///     .warning("unused-variable", WarningLevel::Error)
+///     .opt_level(2)
+///     .build()
+///     .unwrap();
+/// assert!(opt.optimize_sibling_calls);
+/// assert!(Opt::builder().opt_level(4).build().is_err());
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct OptBuilder {
+    opt: Opt,
+    target: Option<String>,
+    /// (name, replacement list) in the order they were given
+    definitions: Vec<(String, String)>,
+    /// `-W` flags, without the leading `-W`
+    warning_flags: Vec<String>,
+    /// If `None`, this is enabled at `-O2` and above
+    optimize_sibling_calls: Option<bool>,
+}
+
+impl Opt {
+    /// Start building an `Opt`. Options which aren't set have the same value as `Opt::default()`.
+    pub fn builder() -> OptBuilder {
+        OptBuilder::default()
+    }
+}
+
+impl OptBuilder {
+    /// The platform to compile for, as a target triple like `x86_64-unknown-linux-gnu`.
+    ///
+    /// Only the host is currently supported. The default is the host.
+    pub fn target(mut self, triple: &str) -> Self {
+        self.target = Some(triple.to_owned());
+        self
+    }
+    /// The version of C to accept, the same as `-std=` for `cc`.
+    pub fn standard(mut self, standard: Standard) -> Self {
+        self.opt.standard = standard;
+        self
+    }
+    /// Define an object-like macro, the same as `-D name=value`.
+    ///
+    /// Later definitions of the same macro replace earlier ones.
+    pub fn define(mut self, name: &str, value: &str) -> Self {
+        self.definitions.push((name.to_owned(), value.to_owned()));
+        self
+    }
+    /// Add a directory to the include path, the same as `-I`.
+    pub fn include_path<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.opt.search_path.push(path.into());
+        self
+    }
+    /// Set the level of the warning called `name`, e.g. `unused-variable`.
+    ///
+    /// `name` can also be a group, such as `all` or `extra`.
+    /// Later calls override earlier ones.
+    pub fn warning(self, name: &str, level: WarningLevel) -> Self {
+        match level {
+            WarningLevel::Ignore => self.warning_flag(&format!("no-{}", name)),
+            WarningLevel::Warn => self
+                .warning_flag(name)
+                .warning_flag(&format!("no-error={}", name)),
+            WarningLevel::Error => self.warning_flag(&format!("error={}", name)),
+        }
+    }
+    /// Add a `-W` flag, without the leading `-W`. For example, `all`, `no-main` or `error=main`.
+    pub fn warning_flag(mut self, flag: &str) -> Self {
+        self.warning_flags.push(flag.to_owned());
+        self
+    }
+    /// How much to optimize, from 0 to 3, the same as `-O<level>`.
+    pub fn opt_level(mut self, level: u8) -> Self {
+        self.opt.opt_level = level;
+        self
+    }
+    /// Whether to turn self-recursive tail calls into loops.
+    ///
+    /// If this isn't set, it is enabled at `-O2` and above.
+    pub fn optimize_sibling_calls(mut self, yes: bool) -> Self {
+        self.optimize_sibling_calls = Some(yes);
+        self
+    }
+    /// Whether to compile for a freestanding environment, the same as `-ffreestanding`.
+    pub fn freestanding(mut self, yes: bool) -> Self {
+        self.opt.freestanding = yes;
+        self
+    }
+    /// The maximum number of errors before giving up. `None` allows unlimited errors.
+    pub fn max_errors(mut self, max: Option<NonZeroUsize>) -> Self {
+        self.opt.max_errors = max;
+        self
+    }
+    /// Whether to skip warning about unused declarations whose names start with `_`.
+    pub fn allow_unused_underscore(mut self, yes: bool) -> Self {
+        self.opt.allow_unused_underscore = yes;
+        self
+    }
+    /// Whether to compile and assemble, but not link.
+    pub fn no_link(mut self, yes: bool) -> Self {
+        self.opt.no_link = yes;
+        self
+    }
+    #[cfg(feature = "jit")]
+    /// Whether to compile to memory and run the program instead of writing an object file.
+    pub fn jit(mut self, yes: bool) -> Self {
+        self.opt.jit = yes;
+        self
+    }
+    /// Whether to print each token in addition to compiling.
+    pub fn debug_lex(mut self, yes: bool) -> Self {
+        self.opt.debug_lex = yes;
+        self
+    }
+    /// Whether to print the AST in addition to compiling.
+    pub fn debug_ast(mut self, yes: bool) -> Self {
+        self.opt.debug_ast = yes;
+        self
+    }
+    /// Whether to print the HIR in addition to compiling.
+    pub fn debug_hir(mut self, yes: bool) -> Self {
+        self.opt.debug_hir = yes;
+        self
+    }
+    /// Whether to print the Cranelift IR in addition to compiling.
+    pub fn debug_asm(mut self, yes: bool) -> Self {
+        self.opt.debug_asm = yes;
+        self
+    }
+    /// The path of the file being compiled, used for diagnostics and local includes.
+    pub fn filename<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.opt.filename = path.into();
+        self
+    }
+
+    /// Check the options and return the `Opt` they describe.
+    ///
+    /// If more than one option is invalid, only the first is reported.
+    pub fn build(self) -> Result<Opt, OptError> {
+        let mut opt = self.opt;
+        if opt.opt_level > 3 {
+            return Err(OptError::InvalidOptLevel(opt.opt_level));
+        }
+        opt.optimize_sibling_calls = self.optimize_sibling_calls.unwrap_or(opt.opt_level >= 2);
+        #[cfg(feature = "jit")]
+        {
+            if opt.jit && opt.no_link {
+                return Err(OptError::Conflict("--jit", "--no-link"));
+            }
+        }
+        if let Some(target) = self.target {
+            let triple =
+                Triple::from_str(&target).map_err(|_| OptError::InvalidTarget(target.clone()))?;
+            let host = Target::host();
+            if &triple != host.triple() {
+                return Err(OptError::UnsupportedTarget(triple, host.triple().clone()));
+            }
+            opt.target = Target::new(triple);
+        }
+        for flag in &self.warning_flags {
+            if opt.warnings.add_flag(flag).is_err() {
+                return Err(OptError::UnknownWarning(flag.clone()));
+            }
+        }
+        for (name, value) in self.definitions {
+            if !is_identifier(&name) {
+                return Err(OptError::InvalidMacroName(name));
+            }
+            let definition = match Definition::try_from(value.as_str()) {
+                Ok(definition) => definition,
+                Err(error) => return Err(OptError::InvalidDefinition { name, error }),
+            };
+            opt.definitions.insert(name.into(), definition);
+        }
+        Ok(opt)
+    }
+}
+
+/// Whether `name` can be the name of a macro.
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .map_or(false, |c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// The result of compiling a program with `Opt::compile`.
+#[cfg(feature = "codegen")]
+#[derive(Clone, Debug)]
+pub struct Artifacts {
+    /// The object file, in the native format of the target (e.g. ELF on Linux).
+    pub object: Vec<u8>,
+}
+
+#[cfg(feature = "codegen")]
+impl Opt {
+    /// Compile `source` to an object file.
+    ///
+    /// This is the same as `swcc -c`, except that the object file is returned instead of written.
+    pub fn compile(&self, source: &str) -> Program<Artifacts, Error> {
+        let module = crate::initialize_aot_module("saltwater_main".to_owned(), self.opt_level);
+        let Program {
+            result,
+            warnings,
+            files,
+        } = crate::compile(module, source, self.clone());
+        let result = result.map_err(Error::Source).and_then(|module| {
+            let _emission = stats::enter(stats::Phase::Emission);
+            let object = crate::finish_aot_module(module)
+                .emit()
+                .map_err(Error::Platform)?;
+            Ok(Artifacts { object })
+        });
+        Program {
+            result,
+            warnings,
+            files,
+        }
+    }
+    /// Read and compile the file at `path`, using `path` as the filename.
+    pub fn compile_file<P: AsRef<Path>>(&self, path: P) -> Program<Artifacts, Error> {
+        let path = path.as_ref();
+        match std::fs::read_to_string(path) {
+            Ok(source) => Opt {
+                filename: path.to_owned(),
+                ..self.clone()
+            }
+            .compile(&source),
+            Err(err) => Program {
+                result: Err(err.into()),
+                warnings: VecDeque::new(),
+                files: SourceMap::new(),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Version;
+
+    #[test]
+    fn defaults() {
+        assert_eq!(Opt::builder().build().unwrap(), Opt::default());
+        assert!(
+            Opt::builder()
+                .opt_level(2)
+                .build()
+                .unwrap()
+                .optimize_sibling_calls
+        );
+        assert!(
+            !Opt::builder()
+                .opt_level(2)
+                .optimize_sibling_calls(false)
+                .build()
+                .unwrap()
+                .optimize_sibling_calls
+        );
+    }
+
+    #[test]
+    fn options() {
+        let c11 = Standard {
+            version: Version::C11,
+            gnu: false,
+        };
+        let opt = Opt::builder()
+            .target(&Target::host().triple().to_string())
+            .standard(c11)
+            .define("NDEBUG", "1")
+            .define("NDEBUG", "2")
+            .include_path("include")
+            .warning("unused-variable", WarningLevel::Error)
+            .build()
+            .unwrap();
+        assert_eq!(opt.standard, c11);
+        assert_eq!(opt.search_path, vec![PathBuf::from("include")]);
+        assert_eq!(opt.definitions.len(), 1);
+        let mut warnings = WarningOptions::default();
+        warnings.add_flag("error=unused-variable").unwrap();
+        assert_eq!(opt.warnings, warnings);
+    }
+
+    #[test]
+    fn compile() {
+        let opt = Opt::builder().build().unwrap();
+        let artifacts = opt.compile("int main(void) { return 0; }\n").result;
+        assert!(!artifacts.unwrap().object.is_empty());
+        match opt.compile("int main(void) { return x; }\n").result {
+            Err(Error::Source(errs)) => assert_eq!(errs.len(), 1),
+            _ => panic!("expected an error"),
+        }
+        assert!(matches!(
+            opt.compile_file("does/not/exist.c").result,
+            Err(Error::IO(_))
+        ));
+    }
+
+    #[test]
+    fn validation() {
+        let err = |builder: OptBuilder| builder.build().unwrap_err();
+        assert_eq!(
+            err(Opt::builder().target("not-a-target")),
+            OptError::InvalidTarget("not-a-target".into())
+        );
+        assert!(matches!(
+            err(Opt::builder().target("wasm32-unknown-unknown")),
+            OptError::UnsupportedTarget(..)
+        ));
+        assert_eq!(
+            err(Opt::builder().opt_level(4)),
+            OptError::InvalidOptLevel(4)
+        );
+        assert_eq!(
+            err(Opt::builder().warning("not-a-warning", WarningLevel::Warn)),
+            OptError::UnknownWarning("not-a-warning".into())
+        );
+        assert_eq!(
+            err(Opt::builder().define("1x", "1")),
+            OptError::InvalidMacroName("1x".into())
+        );
+        assert!(matches!(
+            err(Opt::builder().define("X", "'")),
+            OptError::InvalidDefinition { .. }
+        ));
+    }
+
+    #[cfg(feature = "jit")]
+    #[test]
+    fn conflicts() {
+        assert_eq!(
+            Opt::builder().jit(true).no_link(true).build().unwrap_err(),
+            OptError::Conflict("--jit", "--no-link")
+        );
+    }
+}
@@ -15,9 +15,10 @@ enum InternalDeclaratorType {
     },
     Array {
         size: Option<Box<Expr>>,
+        qualifiers: Vec<DeclarationSpecifier>,
     },
     Function {
-        params: Vec<TypeName>,
+        params: Vec<Locatable<TypeName>>,
         varargs: bool,
     },
 }
@@ -42,8 +43,19 @@ impl<I: Lexer> Parser<I> {
     /// ```
     /// <http://www.quut.com/c/ANSI-C-grammar-y.html#external_declaration>
     pub fn external_declaration(&mut self) -> SyntaxResult<Locatable<ExternalDeclaration>> {
+        // `__extension__ long long i;`
+        if let Some(keyword) = self.match_keywords(&[Keyword::Extension]) {
+            return self.extension(keyword.location, Self::external_declaration);
+        }
         let (specifiers, specifier_locations) = self.specifiers()?;
-
+        self.declaration_after_specifiers(specifiers, specifier_locations)
+    }
+    /// The rest of an `external_declaration`, after the specifiers have already been parsed.
+    pub(super) fn declaration_after_specifiers(
+        &mut self,
+        specifiers: Vec<DeclarationSpecifier>,
+        specifier_locations: Option<Location>,
+    ) -> SyntaxResult<Locatable<ExternalDeclaration>> {
         // allow `int;`
         if let Some(token) = self.match_next(&Token::Semicolon) {
             let location = token.location.maybe_merge(specifier_locations);
@@ -70,11 +82,25 @@ impl<I: Lexer> Parser<I> {
                 return Err(location.with(SyntaxError::FunctionInitializer(init)));
             }
 
-            let body = self.compound_statement()?;
+            // parameters hide typedefs for the whole body
+            self.typedefs.enter();
+            for param in &func.params {
+                if let Some(id) = param.data.declarator.id {
+                    self.typedefs.insert(id, false);
+                }
+            }
+            let body = self.compound_statement();
+            self.typedefs.exit();
+            let body = body?;
             let location = location.merge(body.location);
             // int () {}
             let err = location.with(SyntaxError::MissingFunctionName);
             let id = declarator.data.declarator.id.ok_or(err)?;
+            // `void f(void) __attribute__((constructor)) {}` is the same as putting the attribute first
+            let mut specifiers = specifiers;
+            if !declarator.data.attributes.is_empty() {
+                specifiers.push(DeclarationSpecifier::Attributes(declarator.data.attributes));
+            }
             let def = FunctionDefinition {
                 id,
                 body: body.data,
@@ -87,17 +113,15 @@ impl<I: Lexer> Parser<I> {
         let has_typedef = specifiers
             .iter()
             .any(|s| *s == DeclarationSpecifier::Unit(crate::data::ast::UnitSpecifier::Typedef));
-        while self.match_next(&Token::Semicolon).is_none() {
-            self.expect(Token::Comma)?;
+        while self.match_next(&Token::Comma).is_some() {
             let decl = self.init_declarator()?;
             location = location.merge(decl.location);
             decls.push(decl);
         }
-        if has_typedef {
-            // `int *;` is caught later
-            for id in decls.iter().filter_map(|d| d.data.declarator.id) {
-                self.typedefs.insert(id, ());
-            }
+        self.expect(Token::Semicolon)?;
+        // `int *;` is caught later
+        for id in decls.iter().filter_map(|d| d.data.declarator.id) {
+            self.typedefs.insert(id, has_typedef);
         }
         let declaration = Declaration {
             specifiers,
@@ -139,20 +163,36 @@ impl<I: Lexer> Parser<I> {
         };
         Ok(Locatable::new(type_name, location))
     }
-    fn specifiers(&mut self) -> SyntaxResult<(Vec<DeclarationSpecifier>, Option<Location>)> {
+    pub(super) fn specifiers(
+        &mut self,
+    ) -> SyntaxResult<(Vec<DeclarationSpecifier>, Option<Location>)> {
         let mut specifiers = Vec::new();
         let mut all_locs = None;
-        let mut seen_typedef = false;
-        while let Some(&Token::Keyword(keyword)) = self.peek_token() {
+        let mut seen_type = false;
+        loop {
+            // `[[deprecated]] int f(void);`
+            if let Some(attributes) = self.standard_attributes()? {
+                all_locs = Some(attributes.location.maybe_merge(all_locs));
+                specifiers.push(DeclarationSpecifier::Attributes(attributes.data));
+                continue;
+            }
+            let keyword = match self.peek_token() {
+                Some(&Token::Keyword(keyword)) => keyword,
+                _ => break,
+            };
             let location = self.next_token().unwrap().location;
             let spec = match keyword {
                 Keyword::Struct => self.struct_specifier(true, location)?,
                 Keyword::Union => self.struct_specifier(false, location)?,
                 Keyword::Enum => self.enum_specifier(location)?,
+                Keyword::Attribute => self
+                    .attribute_specifier(location)?
+                    .map(DeclarationSpecifier::Attributes),
                 Keyword::UserTypedef(name) => {
                     // absolute hack: allow awful code like `typedef int I; { I I; }`
-                    if !seen_typedef {
-                        seen_typedef = true;
+                    // 6.7.2p2: after a type specifier, a typedef name must be the declarator,
+                    // as in `typedef int I; typedef int I;`
+                    if !seen_type {
                         Locatable::new(DeclarationSpecifier::Typedef(name), location)
                     } else {
                         self.unput(Some(Locatable::new(Token::Id(name), location)));
@@ -165,6 +205,7 @@ impl<I: Lexer> Parser<I> {
                 }
                 _ => Locatable::new(keyword.try_into().unwrap(), location),
             };
+            seen_type |= keyword.is_type_specifier();
             all_locs = all_locs.map_or(Some(spec.location), |existing: Location| {
                 Some(existing.merge(spec.location))
             });
@@ -201,7 +242,7 @@ impl<I: Lexer> Parser<I> {
                 }
                 if let Some(token) = self.match_next(&Token::Semicolon) {
                     self.error_handler.warn(
-                        Warning::ExtraneousSemicolon("struct declaration is not allowed by ISO"),
+                        Warning::ExtraneousSemicolon("in struct declaration is not allowed by ISO"),
                         token.location,
                     );
                     continue;
@@ -248,10 +289,7 @@ impl<I: Lexer> Parser<I> {
                     spec_location = Some(d.location.maybe_merge(spec_location));
                     let mut decl = d.data.parse_declarator();
                     if decl.id.is_none() {
-                        let err = Locatable::new(
-                            SyntaxError::Generic("struct members must have an id".into()),
-                            d.location,
-                        );
+                        let err = Locatable::new(SyntaxError::MissingMemberName, d.location);
                         self.error_handler.push_back(err);
                         decl.id = Some("<unnamed member>".into());
                     }
@@ -267,6 +305,8 @@ impl<I: Lexer> Parser<I> {
             } else {
                 None
             };
+            // attributes on members are ignored
+            self.attributes()?;
             declarators.push(ast::StructDeclarator { decl, bitfield });
             if self.match_next(&Token::Comma).is_none() {
                 break self.expect(Token::Semicolon)?.location;
@@ -328,8 +368,21 @@ impl<I: Lexer> Parser<I> {
                     None
                 };
                 body.push((enumerator.data, value));
-                if self.match_next(&Token::Comma).is_none() {
-                    let token = self.expect(Token::RightBrace)?;
+                let comma = match self.match_next(&Token::Comma) {
+                    Some(comma) => comma,
+                    None => {
+                        let token = self.expect(Token::RightBrace)?;
+                        location = location.merge(token.location);
+                        break;
+                    }
+                };
+                // `enum { A, }`
+                if let Some(token) = self.match_next(&Token::RightBrace) {
+                    if self.standard.version < Version::C99 {
+                        let extension =
+                            Warning::C99Extension("trailing commas in enumerator lists");
+                        self.error_handler.warn(extension, comma.location);
+                    }
                     location = location.merge(token.location);
                     break;
                 }
@@ -347,6 +400,7 @@ impl<I: Lexer> Parser<I> {
 
     fn init_declarator(&mut self) -> SyntaxResult<Locatable<ast::InitDeclarator>> {
         let decl = self.declarator(false)?;
+        let attributes = self.attributes()?;
         let init = if self.match_next(&Token::EQUAL).is_some() {
             Some(self.initializer()?)
         } else {
@@ -358,8 +412,130 @@ impl<I: Lexer> Parser<I> {
         Ok(decl.map(|d| ast::InitDeclarator {
             declarator: InternalDeclarator::parse_declarator(d),
             init,
+            attributes,
         }))
     }
+    /// Parse any number of attribute specifiers, e.g. after a declarator.
+    fn attributes(&mut self) -> SyntaxResult<Vec<ast::Attribute>> {
+        let mut attributes = Vec::new();
+        loop {
+            if let Some(keyword) = self.match_keywords(&[Keyword::Attribute]) {
+                attributes.extend(self.attribute_specifier(keyword.location)?.data);
+            } else if let Some(standard) = self.standard_attributes()? {
+                attributes.extend(standard.data);
+            } else {
+                break Ok(attributes);
+            }
+        }
+    }
+    /// ```yacc
+    /// attribute_specifier
+    /// : '__attribute__' '(' '(' attribute_list ')' ')'
+    /// ;
+    ///
+    /// attribute_list
+    /// : attribute?
+    /// | attribute_list ',' attribute?
+    /// ;
+    ///
+    /// attribute
+    /// : identifier
+    /// | identifier '(' argument_expression_list? ')'
+    /// ;
+    /// ```
+    /// <https://gcc.gnu.org/onlinedocs/gcc/Attribute-Syntax.html>
+    // we've already seen an `__attribute__` token, `location` is where we saw it
+    fn attribute_specifier(
+        &mut self,
+        location: Location,
+    ) -> SyntaxResult<Locatable<Vec<ast::Attribute>>> {
+        self.expect(Token::LeftParen)?;
+        self.expect(Token::LeftParen)?;
+        let attributes = self.attribute_list(&Token::RightParen)?;
+        let end = self.expect(Token::RightParen)?.location;
+        Ok(Locatable::new(attributes, location.merge(end)))
+    }
+    /// Parse `[[attribute_list]]`, if the standard allows it and the next two tokens are `[[`.
+    ///
+    /// ```yacc
+    /// standard_attribute_specifier
+    /// : '[' '[' attribute_list ']' ']'
+    /// ;
+    /// ```
+    ///
+    /// Attributes can also have a prefix, as in `[[gnu::unused]]`.
+    /// The `gnu` prefix is ignored, so that is the same as `[[unused]]`.
+    pub(super) fn standard_attributes(
+        &mut self,
+    ) -> SyntaxResult<Option<Locatable<Vec<ast::Attribute>>>> {
+        if !self.standard.standard_attributes()
+            || self.peek_token() != Some(&Token::LeftBracket)
+            || self.peek_next_token() != Some(&Token::LeftBracket)
+        {
+            return Ok(None);
+        }
+        let start = self.next_token().unwrap().location;
+        self.next_token();
+        let attributes = self.attribute_list(&Token::RightBracket)?;
+        let end = self.expect(Token::RightBracket)?.location;
+        let location = start.merge(end);
+        if self.standard.version < Version::C2x {
+            let extension = Warning::C2xExtension("[[]] attributes");
+            self.error_handler.warn(extension, location);
+        }
+        Ok(Some(Locatable::new(attributes, location)))
+    }
+    /// Parse comma-separated attributes up to and including the first `end` token.
+    ///
+    /// Empty attributes are allowed, as in `__attribute__(())` and `__attribute__((a,))`.
+    fn attribute_list(&mut self, end: &Token) -> SyntaxResult<Vec<ast::Attribute>> {
+        let mut attributes = Vec::new();
+        while self.match_next(end).is_none() {
+            if self.match_next(&Token::Comma).is_some() {
+                continue;
+            }
+            let mut name = self.attribute_name()?;
+            // `[[gnu::unused]]`
+            if *end == Token::RightBracket && self.match_next(&Token::Colon).is_some() {
+                self.expect(Token::Colon)?;
+                let prefix = name;
+                name = self.attribute_name()?;
+                if prefix != InternedStr::get_or_intern("gnu") {
+                    name = InternedStr::get_or_intern(format!("{}::{}", prefix, name));
+                }
+            }
+            let mut args = Vec::new();
+            if self.match_next(&Token::LeftParen).is_some() {
+                while self.match_next(&Token::RightParen).is_none() {
+                    args.push(self.assignment_expr()?);
+                    if self.match_next(&Token::Comma).is_none() {
+                        self.expect(Token::RightParen)?;
+                        break;
+                    }
+                }
+            }
+            attributes.push(ast::Attribute { name, args });
+            if self.match_next(&Token::Comma).is_none() {
+                self.expect(end.clone())?;
+                break;
+            }
+        }
+        Ok(attributes)
+    }
+    fn attribute_name(&mut self) -> SyntaxResult<InternedStr> {
+        Ok(match self.peek_token() {
+            Some(&Token::Keyword(Keyword::UserTypedef(name))) => {
+                self.next_token();
+                name
+            }
+            // `__attribute__((const))`
+            Some(&Token::Keyword(keyword)) => {
+                self.next_token();
+                InternedStr::get_or_intern(keyword.to_string())
+            }
+            _ => self.expect_id()?.data,
+        })
+    }
 
     fn merge_decls(
         current: Locatable<InternalDeclaratorType>,
@@ -522,16 +698,16 @@ impl<I: Lexer> Parser<I> {
             _ if allow_abstract => None,
             Some(x) => {
                 let err = Err(Locatable::new(
-                    SyntaxError::from(format!("expected variable name or '(', got '{}'", x)),
+                    SyntaxError::ExpectedVariableOrParen(Some(x.clone())),
                     self.next_location(),
                 ));
                 self.panic();
                 return err;
             }
             None => {
-                return Err(self.next_location().with(SyntaxError::from(
-                    "expected variable name or '(', got <end-of-of-file>",
-                )));
+                return Err(self
+                    .next_location()
+                    .with(SyntaxError::ExpectedVariableOrParen(None)));
             }
         };
         self.postfix_type(decl, allow_abstract)
@@ -554,17 +730,39 @@ impl<I: Lexer> Parser<I> {
         mut prefix: Option<Locatable<InternalDeclarator>>,
         allow_abstract: bool,
     ) -> SyntaxResult<Option<Locatable<InternalDeclarator>>> {
-        while let Some(data) = self.peek_token() {
+        loop {
+            // `int i [[maybe_unused]]` is an attribute, not an array
+            let attribute = self.standard.standard_attributes()
+                && self.peek_token() == Some(&Token::LeftBracket)
+                && self.peek_next_token() == Some(&Token::LeftBracket);
+            let data = match self.peek_token() {
+                Some(data) if !attribute => data,
+                _ => break,
+            };
             let current = match data {
                 // Array; Specified in section 6.7.6.2 of the C11 spec
                 Token::LeftBracket => {
                     self.expect(Token::LeftBracket).unwrap();
-                    if let Some(token) = self.match_next(&Token::Keyword(Keyword::Static)) {
-                        if !allow_abstract {
-                            self.error_handler.push_back(Locatable::new(
-                                SyntaxError::StaticInConcreteArray,
-                                token.location,
-                            ));
+                    // `int a[static restrict 5]` or `int a[const static 5]`
+                    let mut qualifiers = Vec::new();
+                    while let Some(token) = self.match_any(&[
+                        &Token::Keyword(Keyword::Static),
+                        &Token::Keyword(Keyword::Const),
+                        &Token::Keyword(Keyword::Volatile),
+                        &Token::Keyword(Keyword::Restrict),
+                        &Token::Keyword(Keyword::Atomic),
+                    ]) {
+                        match token.data {
+                            Token::Keyword(Keyword::Static) => {
+                                if !allow_abstract {
+                                    self.error_handler.push_back(Locatable::new(
+                                        SyntaxError::StaticInConcreteArray,
+                                        token.location,
+                                    ));
+                                }
+                            }
+                            Token::Keyword(keyword) => qualifiers.push(keyword.try_into().unwrap()),
+                            _ => unreachable!("only matched keywords"),
                         }
                     }
                     let (size, location) =
@@ -574,7 +772,7 @@ impl<I: Lexer> Parser<I> {
                             let expr = Box::new(self.expr()?);
                             (Some(expr), self.expect(Token::RightBracket)?.location)
                         };
-                    Locatable::new(InternalDeclaratorType::Array { size }, location)
+                    Locatable::new(InternalDeclaratorType::Array { size, qualifiers }, location)
                 }
                 Token::LeftParen => self.parameter_type_list()?,
                 _ => break,
@@ -631,8 +829,16 @@ impl<I: Lexer> Parser<I> {
                     left_paren.merge(right_paren),
                 ));
             }
-            let param = self.type_name()?;
-            params.push(param.data);
+            let mut param = self.type_name()?;
+            // `int argc __attribute__((unused))`
+            let attributes = self.attributes()?;
+            if !attributes.is_empty() {
+                param
+                    .data
+                    .specifiers
+                    .push(ast::DeclarationSpecifier::Attributes(attributes));
+            }
+            params.push(param);
             if self.match_next(&Token::Comma).is_none() {
                 let right_paren = self.expect(Token::RightParen)?.location;
                 let location = left_paren.merge(right_paren);
@@ -697,9 +903,10 @@ impl InternalDeclarator {
                     to: Box::new(current),
                     qualifiers,
                 },
-                Array { size } => DeclaratorType::Array {
+                Array { size, qualifiers } => DeclaratorType::Array {
                     of: Box::new(current),
                     size,
+                    qualifiers,
                 },
                 Function { params, varargs } => DeclaratorType::from(ast::FunctionDeclarator {
                     return_type: Box::new(current),
@@ -751,7 +958,30 @@ impl Token {
 }
 
 impl Keyword {
-    pub(super) fn is_decl_specifier(self) -> bool {
+    fn is_type_specifier(self) -> bool {
+        use Keyword::*;
+        matches!(
+            self,
+            Unsigned
+                | Signed
+                | Bool
+                | Char
+                | Short
+                | Int
+                | Long
+                | Float
+                | Double
+                | Void
+                | Struct
+                | Union
+                | Enum
+                | VaList
+                | Complex
+                | Imaginary
+                | UserTypedef(_)
+        )
+    }
+    pub(crate) fn is_decl_specifier(self) -> bool {
         use Keyword::*;
         match self {
             // type specifier
@@ -765,7 +995,9 @@ impl Keyword {
             // qualifier
             | Const | Volatile | Restrict | Atomic | ThreadLocal
             // function qualifier
-            | Inline | NoReturn => true,
+            | Inline | NoReturn
+            // GNU extensions
+            | Attribute => true,
             _ => false,
         }
     }
@@ -806,6 +1038,30 @@ pub(crate) mod test {
         assert_no_change("const int (*volatile (*restrict jynelson)(_Atomic int (*const volatile )(_Thread_local int)));")
     }
     #[test]
+    fn array_parameter_qualifiers() {
+        assert_no_change("void f(int a[restrict], int b[const volatile 5]);");
+        assert_display(
+            "void f(int a[static restrict 5]);",
+            "void f(int a[restrict 5]);",
+        );
+        assert_same("int *__restrict p;", "int *restrict p;");
+    }
+    #[test]
+    fn test_attributes() {
+        assert_no_change("int f() __attribute__((noreturn));");
+        assert_no_change("__attribute__((unused)) int x;");
+        assert_display(
+            "int f(int) __attribute ((__returns_twice__, aligned(8)));",
+            "int f(int) __attribute__((__returns_twice__, aligned(8)));",
+        );
+        assert_same(
+            "struct s { int x __attribute__((packed)); } y;",
+            "struct s { int x; } y;",
+        );
+        assert!(decl("int f() __attribute__(noreturn);").is_err());
+        assert!(decl("int f() __attribute__((noreturn);").is_err());
+    }
+    #[test]
     fn test_precedence() {
         assert_same("char (*(*f));", "char **f;");
     }
@@ -829,7 +1085,20 @@ pub(crate) mod test {
     }
     #[test]
     fn test_struct() {
-        assert!(decl("struct s { int *; };").is_err());
+        assert_eq!(
+            decl("struct s { int *; };").unwrap_err().data,
+            Error::Syntax(SyntaxError::MissingMemberName)
+        );
+    }
+    #[test]
+    fn test_expected_token() {
+        assert_eq!(
+            decl("int f(int;").unwrap_err().data,
+            Error::Syntax(SyntaxError::ExpectedToken {
+                expected: Token::RightParen,
+                found: Some(Token::Semicolon),
+            })
+        );
     }
     #[test]
     fn test_cursed_function_declarator() {
@@ -854,7 +1123,7 @@ pub(crate) mod test {
                     varargs: false,
                     return_type: Box::new(DeclaratorType::End),
                 });
-                assert_eq!(params[0].declarator.decl, cursed);
+                assert_eq!(params[0].data.declarator.decl, cursed);
             }
             _ => panic!("wrong declarator parsed"),
         }
@@ -52,7 +52,8 @@ impl BinaryPrecedence {
             _ => true,
         }
     }
-    fn constructor(self) -> impl Fn(Expr, Expr) -> ExprType {
+    /// `location` is the location of the operator itself
+    fn constructor(self, location: Location) -> impl Fn(Expr, Expr) -> ExprType {
         use crate::data::lex::ComparisonToken;
         use BinaryPrecedence::*;
         use ExprType::*;
@@ -75,8 +76,10 @@ impl BinaryPrecedence {
             BitOr => Box::new(BitwiseOr),
             LogAnd => Box::new(LogicalAnd),
             LogOr => Box::new(LogicalOr),
-            Self::Assignment(token) => Box::new(move |a, b| Assign(a, b, token)),
-            Self::Ternary => panic!("lol no"),
+            Self::Assignment(token) => {
+                Box::new(move |a, b| Assign(a, b, Locatable::new(token, location)))
+            }
+            Self::Ternary => unreachable!("ternaries are handled by binary_expr"),
             Self::Comma => Box::new(ExprType::Comma),
         };
         move |a, b| func(Box::new(a), Box::new(b))
@@ -145,7 +148,7 @@ impl<I: Lexer> Parser<I> {
             if prec < max_precedence {
                 break;
             }
-            self.next_token();
+            let op_location = self.next_token().unwrap().location;
             let location = left.location;
             let right = if binop.left_associative() {
                 let inner_left = self.unary_expr()?;
@@ -156,13 +159,22 @@ impl<I: Lexer> Parser<I> {
                 // | logical_or_expression '?' expression ':' conditional_expression
                 // ;
                 // <http://www.quut.com/c/ANSI-C-grammar-y.html#conditional_expression>
-                let inner = self.expr()?;
-                self.expect(Token::Colon)?;
+                //
+                // GNU also allows leaving out the middle operand: `a ?: b`
+                let inner = if let Some(colon) = self.match_next(&Token::Colon) {
+                    self.error_handler
+                        .warn(Warning::GnuConditionalOmittedOperand, colon.location);
+                    None
+                } else {
+                    let inner = self.expr()?;
+                    self.expect(Token::Colon)?;
+                    Some(Box::new(inner))
+                };
                 let right_start = self.unary_expr()?;
                 let right = self.binary_expr(right_start, BinaryPrecedence::Ternary.prec())?;
 
-                let location = left.location.merge(&inner.location).merge(&right.location);
-                let ternary = ExprType::Ternary(Box::new(left), Box::new(inner), Box::new(right));
+                let location = left.location.merge(right.location);
+                let ternary = ExprType::Ternary(Box::new(left), inner, Box::new(right));
                 left = Expr::new(ternary, location);
                 continue;
             } else {
@@ -170,7 +182,7 @@ impl<I: Lexer> Parser<I> {
                 self.binary_expr(inner_left, prec)?
             };
 
-            let constructor = binop.constructor();
+            let constructor = binop.constructor(op_location);
             let location = location.merge(&right.location);
             left = location.with(constructor(left, right));
         }
@@ -231,11 +243,16 @@ impl<I: Lexer> Parser<I> {
                         keyword.location,
                     ));
                 }
+            // `__extension__ 0b1`
+            } else if let Some(keyword) = self.match_keywords(&[Keyword::Extension]) {
+                break self.extension(keyword.location, Self::unary_expr)?;
             } else {
                 break self.primary_expr()?;
             }
         };
+        // `-x` covers both the operator and its operand
         while let Some((constructor, location)) = prefixes.pop() {
+            let location = location.merge(inner.location);
             inner = Locatable::new(constructor(inner), location);
         }
         Ok(inner)
@@ -260,12 +277,65 @@ impl<I: Lexer> Parser<I> {
         } else if let Some(loc) = self.match_id() {
             loc.map(ExprType::Id)
         } else if let Some(literal) = self.match_literal() {
-            literal.map(ExprType::Literal)
+            self.concat_strings(literal)?.map(ExprType::Literal)
+        } else if let Some(constant) =
+            self.match_keywords(&[Keyword::True, Keyword::False, Keyword::Nullptr])
+        {
+            Self::c2x_constant(constant)
         } else {
             return Err(self.next_location().with(SyntaxError::MissingPrimary));
         };
         self.postfix_expr(primary)
     }
+    /// Join adjacent string literals, like `"a" L"b"` or a string followed by a macro that expands to one.
+    ///
+    /// Strings without a prefix that are next to each other in the source were already joined by the lexer.
+    fn concat_strings(
+        &mut self,
+        mut literal: Locatable<Literal>,
+    ) -> SyntaxResult<Locatable<Literal>> {
+        while literal.data.is_string() {
+            match self.peek_token() {
+                Some(Token::Literal(next)) if next.is_string() => {}
+                _ => break,
+            }
+            let next = self.match_literal().unwrap();
+            let location = literal.location.merge(next.location);
+            literal = match literal.data.concat(next.data) {
+                Ok(joined) => location.with(joined),
+                Err((left, right)) => {
+                    return Err(location.with(SyntaxError::MixedStringPrefixes(left, right)))
+                }
+            };
+        }
+        Ok(literal)
+    }
+    /// `true`, `false`, or `nullptr`, which are keywords in C2x.
+    ///
+    /// There's no `nullptr_t` yet, so these are desugared to `(_Bool)1`, `(_Bool)0`, and `(void *)0`.
+    fn c2x_constant(constant: Locatable<Keyword>) -> Expr {
+        use crate::data::ast::{DeclarationSpecifier, Declarator, DeclaratorType, UnitSpecifier};
+
+        let (specifier, decl, value) = match constant.data {
+            Keyword::True => (UnitSpecifier::Bool, DeclaratorType::End, 1),
+            Keyword::False => (UnitSpecifier::Bool, DeclaratorType::End, 0),
+            Keyword::Nullptr => {
+                let pointer = DeclaratorType::Pointer {
+                    to: Box::new(DeclaratorType::End),
+                    qualifiers: Vec::new(),
+                };
+                (UnitSpecifier::Void, pointer, 0)
+            }
+            _ => unreachable!("only called for C2x constants"),
+        };
+        let ctype = TypeName {
+            specifiers: vec![DeclarationSpecifier::Unit(specifier)],
+            declarator: Declarator { decl, id: None },
+        };
+        let location = constant.location;
+        let literal = location.with(ExprType::Literal(Literal::Int(value)));
+        location.with(ExprType::Cast(ctype, Box::new(literal)))
+    }
 
     // `expr` should be a primary expression
     fn postfix_expr(&mut self, mut expr: Expr) -> SyntaxResult<Expr> {
@@ -312,12 +382,17 @@ impl<I: Lexer> Parser<I> {
     // '[' expr ']' | '(' argument* ')' | '.' ID | '->' ID | '++' | '--'
     fn match_postfix_op(&mut self) -> SyntaxResult<Option<Locatable<impl UnaryExprFn>>> {
         let next_location = |this: &mut Parser<_>| this.next_token().unwrap().location;
-        let needs_id = |this: &mut Self, constructor: fn(Box<Expr>, InternedStr) -> ExprType| {
-            let start = next_location(this);
-            let Locatable { data: id, location } = this.expect_id()?;
-            let location = start.merge(&location);
-            Ok((Box::new(move |expr| constructor(expr, id)) as _, location))
-        };
+        let needs_id =
+            |this: &mut Self, constructor: fn(Box<Expr>, Locatable<InternedStr>) -> ExprType| {
+                let start = next_location(this);
+                let Locatable { data: id, location } = this.expect_id()?;
+                let location = start.merge(location);
+                let member = Locatable::new(id, location);
+                Ok((
+                    Box::new(move |expr| constructor(expr, member)) as _,
+                    location,
+                ))
+            };
         // postfix operator
         let (func, location): (Box<dyn FnOnce(_) -> _>, _) = match self.peek_token() {
             Some(Token::Dot) => needs_id(self, ExprType::Member)?,
@@ -348,7 +423,8 @@ impl<I: Lexer> Parser<I> {
                 } else {
                     loop {
                         // TODO: maybe we could do some error handling here and consume the end right paren
-                        let arg = self.ternary_expr()?;
+                        // 6.5.2: each argument is an assignment-expression, so `f(i = 1)` is allowed
+                        let arg = self.assignment_expr()?;
                         start.merge(&arg.location);
                         args.push(arg);
                         if let Some(token) = self.match_next(&Token::Comma) {
@@ -432,6 +508,7 @@ mod test {
         assert_expr_display("a--", "(a)--");
         assert_expr_display("a++--->b.c[d]", "(((((a)++)--)->b).c)[d]");
         assert_expr_display("a(1, 2)(3)(4+5)", "(((a)(1, 2))(3))((4) + (5))");
+        assert_expr_display("a(i = 1, j += 2)", "(a)((i) = (1), (j) += (2))");
         // lol why not
         assert_expr_display("1()()()", "(((1)())())()");
     }
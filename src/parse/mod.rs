@@ -7,8 +7,9 @@ use std::iter::Iterator;
 use std::mem;
 
 use crate::data::*;
-use crate::data::{ast::ExternalDeclaration, hir::Scope, lex::Keyword};
-use crate::RecursionGuard;
+use crate::data::{ast::ExternalDeclaration, error::Warning, hir::Scope, lex::Keyword};
+use crate::stats::{self, Phase};
+use crate::{ice, RecursionGuard};
 
 type Lexeme = CompileResult<Locatable<Token>>;
 type SyntaxResult<T> = Result<T, Locatable<SyntaxError>>;
@@ -27,7 +28,9 @@ impl<I: Iterator<Item = Result<Locatable<Token>, E>>, E: Into<CompileError>> Lex
 #[derive(Debug)]
 pub struct Parser<I: Lexer> {
     /// hack so that we know that `typedef int i; i j;` is legal
-    pub(crate) typedefs: Scope<InternedStr, ()>,
+    ///
+    /// `false` means an ordinary identifier hides a typedef from an outer scope.
+    pub(crate) typedefs: Scope<InternedStr, bool>,
     /// we iterate lazily over the tokens, so if we have a program that's mostly valid but
     /// breaks at the end, we don't only show lex errors
     tokens: I,
@@ -37,6 +40,8 @@ pub struct Parser<I: Lexer> {
     pending: VecDeque<Locatable<ExternalDeclaration>>,
     /// in case we get to the end of the file and want to show an error
     last_location: Location,
+    /// the location of the last token returned by `next_token`, used for fix-it hints
+    prev_location: Option<Location>,
     /// the last token we saw from the Lexer. None if we haven't looked ahead.
     /// Should only be used in this module.
     current: Option<Locatable<Token>>,
@@ -50,6 +55,12 @@ pub struct Parser<I: Lexer> {
     error_handler: ErrorHandler,
     /// Internal API which prevents segfaults due to stack overflow
     recursion_guard: RecursionGuard,
+    /// Which version of C to accept
+    standard: Standard,
+    /// The code marked with `__extension__`, where pedantic warnings shouldn't be reported
+    extensions: Vec<Location>,
+    /// Every `;` that is the first token on its line, which marks an empty body as deliberate
+    own_line_semicolons: Vec<Location>,
 }
 
 impl<I: Lexer> Parser<I> {
@@ -65,13 +76,22 @@ impl<I: Lexer> Parser<I> {
             tokens,
             pending: Default::default(),
             last_location: first.location,
+            prev_location: None,
             current: Some(first),
             next: None,
             debug,
             error_handler: ErrorHandler::new(),
             recursion_guard: Default::default(),
+            standard: Standard::default(),
+            extensions: Vec::new(),
+            own_line_semicolons: Vec::new(),
         }
     }
+    /// Parse the given version of C, e.g. only allowing `[[attributes]]` in C2x and GNU modes.
+    pub fn standard(mut self, standard: Standard) -> Self {
+        self.standard = standard;
+        self
+    }
     /// Return whether this parser has fully finished parsing.
     ///
     /// This can be used if, for example, you call `parser.expr()`
@@ -79,6 +99,20 @@ impl<I: Lexer> Parser<I> {
     pub fn is_empty(&mut self) -> bool {
         self.peek_token().is_none()
     }
+    /// Remove and return all errors seen so far that weren't returned by `next()`.
+    pub(crate) fn take_errors(&mut self) -> VecDeque<CompileError> {
+        std::iter::from_fn(|| self.error_handler.pop_front()).collect()
+    }
+    /// The tokens this parser is reading from.
+    pub(crate) fn tokens_mut(&mut self) -> &mut I {
+        &mut self.tokens
+    }
+    /// The locations of all the code marked with `__extension__` seen so far.
+    ///
+    /// Pedantic warnings inside these locations shouldn't be reported.
+    pub fn extensions(&self) -> &[Location] {
+        &self.extensions
+    }
 }
 
 impl<I: Lexer> Iterator for Parser<I> {
@@ -101,6 +135,7 @@ impl<I: Lexer> Iterator for Parser<I> {
     /// ```
     /// <http://www.quut.com/c/ANSI-C-grammar-y.html#translation_unit>
     fn next(&mut self) -> Option<Self::Item> {
+        let _parsing = stats::enter(Phase::Parsing);
         loop {
             // check for pending changes from the last declaration
             if let Some(err) = self.error_handler.pop_front() {
@@ -116,14 +151,17 @@ impl<I: Lexer> Iterator for Parser<I> {
 
             // Remove extra semicolons
             while let Some(locatable) = self.match_next(&Token::Semicolon) {
-                self.error_handler
-                    .warn("extraneous semicolon at top level", locatable.location);
+                self.error_handler.warn(
+                    Warning::ExtraneousSemicolon("at top level"),
+                    locatable.location,
+                );
             }
 
             // Check for end of file
             if self.peek_token().is_none() {
                 return None;
             } else {
+                ice::set_location(self.next_location());
                 match self.external_declaration() {
                     Ok(decls) => {
                         self.pending.push_back(decls);
@@ -146,20 +184,27 @@ impl<I: Lexer> Parser<I> {
     }
     // don't use this, use next_token instead
     fn __impl_next_token(&mut self) -> Option<Locatable<Token>> {
+        let mut newline = false;
         loop {
             match self.tokens.next() {
                 Some(Ok(Locatable {
-                    data: Token::Whitespace(_),
+                    data: Token::Whitespace(whitespace),
                     ..
-                })) => continue,
+                })) => {
+                    newline |= whitespace.contains('\n');
+                    continue;
+                }
                 Some(Ok(mut token)) => {
                     self.last_location = token.location;
+                    if newline && token.data == Token::Semicolon {
+                        self.own_line_semicolons.push(token.location);
+                    }
                     // This is _such_ a hack
                     // I'd much rather use `Token::is_decl_specifier()` at the various places it's necessary,
                     // but that runs into limits of the lifetime system since `peek_token()` takes `&mut self`:
                     // https://doc.rust-lang.org/nomicon/lifetime-mismatch.html#limits-of-lifetimes
                     if let Token::Id(id) = token.data {
-                        if self.typedefs.get(&id).is_some() {
+                        if self.is_typedef(id) {
                             token.data = Token::Keyword(Keyword::UserTypedef(id));
                         }
                     }
@@ -174,16 +219,20 @@ impl<I: Lexer> Parser<I> {
         }
     }
     fn next_token(&mut self) -> Option<Locatable<Token>> {
-        if self.current.is_some() {
+        let token = if self.current.is_some() {
             let tmp = mem::take(&mut self.next);
             mem::replace(&mut self.current, tmp)
         } else {
             self.__impl_next_token()
+        };
+        if let Some(token) = &token {
+            self.prev_location = Some(token.location);
         }
+        token
     }
     fn peek_token(&mut self) -> Option<&Token> {
         if self.current.is_none() {
-            self.current = self.next.take().or_else(|| self.next_token());
+            self.current = self.next.take().or_else(|| self.__impl_next_token());
         }
         self.current.as_ref().map(|x| &x.data)
     }
@@ -197,7 +246,19 @@ impl<I: Lexer> Parser<I> {
         }
         self.next.as_ref().map(|x| &x.data)
     }
-    fn next_location(&self) -> Location {
+    /// Parse the code after `__extension__` and remember where it was.
+    fn extension<T>(
+        &mut self,
+        keyword: Location,
+        parse: impl FnOnce(&mut Self) -> SyntaxResult<T>,
+    ) -> SyntaxResult<T> {
+        let parsed = parse(self)?;
+        // the location of the parsed code may not include all of its tokens (e.g. for binary operators)
+        let end = self.prev_location.unwrap_or(keyword);
+        self.extensions.push(keyword.merge(end));
+        Ok(parsed)
+    }
+    pub(crate) fn next_location(&self) -> Location {
         if let Some(token) = &self.current {
             token.location
         } else {
@@ -239,6 +300,10 @@ impl<I: Lexer> Parser<I> {
             None
         }
     }
+    /// Whether `id` names a type in the current scope.
+    fn is_typedef(&self, id: InternedStr) -> bool {
+        self.typedefs.get(&id) == Some(&true)
+    }
     fn match_next(&mut self, next: &Token) -> Option<Locatable<Token>> {
         self.match_any(&[next])
     }
@@ -280,11 +345,15 @@ impl<I: Lexer> Parser<I> {
         }
     }
     fn expect(&mut self, next: Token) -> SyntaxResult<Locatable<Token>> {
+        let prev_location = self.prev_location;
         let token = match self.peek_token() {
             Some(t) => t,
             None => {
                 let err = Err(Locatable {
-                    data: SyntaxError::from(format!("expected '{}', got '<end-of-file>'", next)),
+                    data: SyntaxError::ExpectedToken {
+                        expected: next,
+                        found: None,
+                    },
                     // TODO: we don't actually want this, we want the end of the file
                     location: self.last_location,
                 });
@@ -294,9 +363,23 @@ impl<I: Lexer> Parser<I> {
         };
         if token.same_kind(&next) {
             Ok(self.next_token().unwrap())
+        } else if let (Token::Semicolon, Some(prev)) = (&next, prev_location) {
+            // point just past the previous token, which is where the `;` should go
+            let err = Err(Locatable {
+                data: SyntaxError::ExpectedSemicolon(token.clone()),
+                location: Location {
+                    span: (prev.span.end..prev.span.end).into(),
+                    ..prev
+                },
+            });
+            self.panic();
+            err
         } else {
             let err = Err(Locatable {
-                data: SyntaxError::from(format!("expected '{}', got '{}'", next, token)),
+                data: SyntaxError::ExpectedToken {
+                    expected: next,
+                    found: Some(token.clone()),
+                },
                 location: self.next_location(),
             });
             self.panic();
@@ -1,12 +1,22 @@
 use super::{Lexer, Parser, SyntaxResult};
 use crate::data::*;
 use crate::data::{
-    ast::{CompoundStatement, Declaration, Expr, ExternalDeclaration, Stmt, StmtType},
+    ast::{
+        CompoundStatement, Declaration, DeclarationSpecifier, Expr, ExternalDeclaration, Stmt,
+        StmtType,
+    },
+    error::Warning,
     lex::Keyword,
 };
 
 type StmtResult = SyntaxResult<Stmt>;
 
+/// `fallthrough` or `__fallthrough__`
+fn is_fallthrough(attribute: InternedStr) -> bool {
+    let name = attribute.resolve_and_clone();
+    name.trim_start_matches("__").trim_end_matches("__") == "fallthrough"
+}
+
 impl<I: Lexer> Parser<I> {
     pub fn compound_statement(&mut self) -> SyntaxResult<Locatable<CompoundStatement>> {
         let mut location = self
@@ -15,6 +25,7 @@ impl<I: Lexer> Parser<I> {
             .location;
         let mut stmts = vec![];
         let mut pending_errs = vec![];
+        self.typedefs.enter();
         while self.peek_token() != Some(&Token::RightBrace) {
             match self.statement() {
                 Ok(stmt) => {
@@ -31,11 +42,12 @@ impl<I: Lexer> Parser<I> {
                 }
             }
         }
-        if self.expect(Token::RightBrace).is_err() {
+        self.typedefs.exit();
+        if let Ok(brace) = self.expect(Token::RightBrace) {
+            location = location.merge(brace.location);
+        } else {
             assert!(self.peek_token().is_none()); // from the 'break' above
-            let actual_err = self
-                .last_location
-                .with(SyntaxError::from("unclosed '{' delimeter at end of file"));
+            let actual_err = self.last_location.with(SyntaxError::UnclosedBrace);
             pending_errs.push(actual_err);
         }
         if let Some(err) = pending_errs.pop() {
@@ -47,11 +59,42 @@ impl<I: Lexer> Parser<I> {
     }
     fn declaration(&mut self) -> SyntaxResult<Stmt> {
         let decl = self.external_declaration()?;
+        Self::declaration_statement(decl)
+    }
+    fn declaration_statement(decl: Locatable<ExternalDeclaration>) -> SyntaxResult<Stmt> {
         match decl.data.into_declaration() {
             Err(err) => Err(decl.location.with(err)),
             Ok(declaration) => Ok(Stmt::new(StmtType::Decl(declaration), decl.location)),
         }
     }
+    /// `__attribute__((fallthrough));`, `[[fallthrough]];`,
+    /// or a declaration that starts with attributes
+    fn attribute_statement(&mut self) -> StmtResult {
+        let (specifiers, location) = self.specifiers()?;
+        let only_attributes = specifiers
+            .iter()
+            .all(|spec| matches!(spec, DeclarationSpecifier::Attributes(_)));
+        if only_attributes {
+            if let Some(semicolon) = self.match_next(&Token::Semicolon) {
+                let fallthrough = specifiers.iter().any(|spec| match spec {
+                    DeclarationSpecifier::Attributes(attributes) => {
+                        attributes.iter().any(|attr| is_fallthrough(attr.name))
+                    }
+                    _ => false,
+                });
+                // other attributes on null statements are ignored
+                let data = if fallthrough {
+                    StmtType::Fallthrough
+                } else {
+                    StmtType::default()
+                };
+                let location = semicolon.location.maybe_merge(location);
+                return Ok(Stmt { data, location });
+            }
+        }
+        let decl = self.declaration_after_specifiers(specifiers, location)?;
+        Self::declaration_statement(decl)
+    }
     /// ```yacc
     /// statement
     /// : labeled_statement
@@ -72,6 +115,7 @@ impl<I: Lexer> Parser<I> {
         let _guard = self.recursion_check();
         // take out 2 guards since this goes through `compound_statement` before calling itself again
         let _guard2 = self.recursion_check();
+        let standard_attributes = self.standard.standard_attributes();
         match self.peek_token() {
             Some(Token::LeftBrace) => Ok(self.compound_statement()?.map(StmtType::Compound)),
             Some(Token::Keyword(k)) => match k {
@@ -103,7 +147,13 @@ impl<I: Lexer> Parser<I> {
                 // iteration_statement
                 Keyword::While => self.while_statement(),
                 Keyword::Do => self.do_while_statement(),
-                Keyword::For => self.for_statement(),
+                Keyword::For => {
+                    // `for (int i;;)` declares `i` in a new scope
+                    self.typedefs.enter();
+                    let stmt = self.for_statement();
+                    self.typedefs.exit();
+                    stmt
+                }
 
                 // jump_statement
                 Keyword::Goto => self.goto_statement(),
@@ -124,19 +174,30 @@ impl<I: Lexer> Parser<I> {
                     })
                 }
                 Keyword::Return => self.return_statement(),
+                Keyword::Attribute => self.attribute_statement(),
+                // `__extension__ long long i;` or `__extension__ 0b1;`
+                Keyword::Extension => {
+                    let keyword = self.next_token().unwrap().location;
+                    self.extension(keyword, Self::statement)
+                }
 
                 // start of an expression statement
                 Keyword::Sizeof
                 | Keyword::StaticAssert
                 | Keyword::Alignas
                 | Keyword::Alignof
-                | Keyword::Generic => self.expression_statement(),
+                | Keyword::Generic
+                | Keyword::True
+                | Keyword::False
+                | Keyword::Nullptr => self.expression_statement(),
                 decl if decl.is_decl_specifier() => self.declaration(),
                 other => {
                     let err = SyntaxError::NotAStatement(*other);
                     Err(self.next_location().with(err))
                 }
             },
+            // `[[fallthrough]];` or `[[maybe_unused]] int i;`
+            Some(Token::LeftBracket) if standard_attributes => self.attribute_statement(),
             Some(Token::Semicolon) => {
                 let Locatable { location, .. } = self.next_token().expect("peek is broken");
                 Ok(Stmt {
@@ -159,7 +220,7 @@ impl<I: Lexer> Parser<I> {
                         location: id.location,
                     });
                 }
-                let is_typedef = self.typedefs.get(&id.data).is_some();
+                let is_typedef = self.is_typedef(id.data);
                 self.unput(Some(Locatable {
                     data: Token::Id(id.data),
                     location: id.location,
@@ -203,6 +264,7 @@ impl<I: Lexer> Parser<I> {
         self.expect(Token::LeftParen)?;
         let condition = self.expr()?;
         self.expect(Token::RightParen)?;
+        self.check_empty_body("if statement");
         let body = self.statement()?;
         let otherwise = if self.match_next(&Token::Keyword(Keyword::Else)).is_some() {
             // NOTE: `if (1) ; else ;` is legal!
@@ -216,6 +278,20 @@ impl<I: Lexer> Parser<I> {
             location: start.location,
         })
     }
+    /// Check the body of an `if`, `while`, or `for` statement.
+    ///
+    /// `if (x);` is usually a typo, so this warns if the body is a `;` on the same line (`-Wempty-body`).
+    /// Writing `{}` or putting the `;` on its own line shows that the empty body is deliberate.
+    ///
+    /// This should be called just before parsing the body.
+    fn check_empty_body(&mut self, kind: &'static str) {
+        if self.peek_token() == Some(&Token::Semicolon) {
+            let semicolon = self.next_location();
+            if !semicolon.is_from_macro() && !self.own_line_semicolons.contains(&semicolon) {
+                self.error_handler.warn(Warning::EmptyBody(kind), semicolon);
+            }
+        }
+    }
     /// switch_statement: SWITCH '(' expr ')' statement
     fn switch_statement(&mut self) -> StmtResult {
         let start = self.expect(Token::Keyword(Keyword::Switch))?;
@@ -235,6 +311,7 @@ impl<I: Lexer> Parser<I> {
         self.expect(Token::LeftParen)?;
         let condition = self.expr()?;
         self.expect(Token::RightParen)?;
+        self.check_empty_body("while loop");
         let body = self.statement()?;
         Ok(Stmt {
             data: StmtType::While(condition, Box::new(body)),
@@ -301,7 +378,7 @@ impl<I: Lexer> Parser<I> {
             Some(Token::Keyword(k)) if k.is_decl_specifier() => self.declaration()?,
             Some(Token::Id(id)) => {
                 let id = *id;
-                if self.typedefs.get(&id).is_some() {
+                if self.is_typedef(id) {
                     self.declaration()?
                 } else {
                     expr_opt(self)?
@@ -320,6 +397,7 @@ impl<I: Lexer> Parser<I> {
         });
         let controlling_expr = self.expr_opt(Token::Semicolon)?;
         let iter_expr = self.expr_opt(Token::RightParen)?;
+        self.check_empty_body("for loop");
         let body = Box::new(self.statement()?);
         Ok(Stmt {
             data: StmtType::For {
@@ -387,14 +465,29 @@ mod tests {
         let expected = Ok(Stmt {
             data: StmtType::Expr(parser("1").expr().unwrap()),
             location: Location {
-                file: Location::default().file,
                 span: (0..2).into(),
+                ..Location::default()
             },
         });
         assert_eq!(parsed, expected);
         assert_eq!(parsed.unwrap().location, expected.unwrap().location);
     }
     #[test]
+    fn test_attribute_statements() {
+        assert_no_change("__attribute__((fallthrough));");
+        assert_stmt_display(
+            "__attribute__((__fallthrough__));",
+            "__attribute__((fallthrough));",
+        );
+        assert_stmt_display("[[fallthrough]];", "__attribute__((fallthrough));");
+        // unknown attributes are ignored
+        assert_stmt_display("__attribute__((hot));", "{\n}");
+        assert_stmt_display("[[nodiscard]];", "{\n}");
+        // but declarations can still start with attributes
+        assert_no_change("__attribute__((unused)) int i;");
+        assert!(stmt("[[fallthrough]]").is_err());
+    }
+    #[test]
     fn test_goto() {
         assert_no_change("goto a;");
     }
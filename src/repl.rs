@@ -0,0 +1,455 @@
+//! An interactive session that compiles and runs C a line at a time.
+//!
+//! [`Input`] and [`is_complete`] decide what to do with the text the user typed,
+//! and [`Repl`] (only available with the `jit` feature) runs it.
+//!
+//! [`Input`]: enum.Input.html
+//! [`is_complete`]: fn.is_complete.html
+//! [`Repl`]: struct.Repl.html
+
+use crate::data::lex::{Keyword, Token};
+use crate::intern::InternedStr;
+use crate::PreProcessorBuilder;
+
+#[cfg(feature = "jit")]
+pub use session::{Repl, Response};
+
+/// What the user asked the REPL to do.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Input<'a> {
+    /// A function definition or a list of declarations, which stay defined for later inputs
+    Declaration(&'a str),
+    /// A statement to run, like `x = 1;` or `for (;;) {}`
+    Statement(&'a str),
+    /// An expression whose value should be printed
+    Expression(&'a str),
+    /// `:type <expr>`: print the type of an expression without running it
+    Type(&'a str),
+    /// `:quit`
+    Quit,
+    /// Only whitespace or comments
+    Empty,
+    /// A `:` command that isn't one of the above
+    UnknownCommand(&'a str),
+}
+
+impl<'a> Input<'a> {
+    /// Decide what kind of input `input` is.
+    ///
+    /// `is_typedef` says whether an identifier is currently a typedef,
+    /// since `T x;` is a declaration but `f(x);` is not.
+    ///
+    /// This only looks at the first token (and the last character for statements),
+    /// so it does not check that the input is valid.
+    pub fn classify(input: &'a str, is_typedef: impl Fn(InternedStr) -> bool) -> Self {
+        let trimmed = input.trim();
+        if let Some(command) = trimmed.strip_prefix(':') {
+            let (name, rest) = match command.find(char::is_whitespace) {
+                Some(end) => (&command[..end], command[end..].trim()),
+                None => (command, ""),
+            };
+            return match name {
+                "type" | "t" => Input::Type(rest),
+                "quit" | "q" => Input::Quit,
+                _ => Input::UnknownCommand(name),
+            };
+        }
+        let mut cpp = PreProcessorBuilder::new(input).build();
+        let first = match cpp.next_non_whitespace() {
+            None => return Input::Empty,
+            // let the parser report the error
+            Some(Err(_)) => return Input::Expression(trimmed),
+            Some(Ok(token)) => token.data,
+        };
+        match first {
+            Token::Keyword(k) if k.is_decl_specifier() => Input::Declaration(trimmed),
+            Token::Id(id) if is_typedef(id) => Input::Declaration(trimmed),
+            Token::Keyword(Keyword::If)
+            | Token::Keyword(Keyword::Do)
+            | Token::Keyword(Keyword::While)
+            | Token::Keyword(Keyword::For)
+            | Token::Keyword(Keyword::Switch)
+            | Token::Keyword(Keyword::Case)
+            | Token::Keyword(Keyword::Default)
+            | Token::Keyword(Keyword::Break)
+            | Token::Keyword(Keyword::Continue)
+            | Token::Keyword(Keyword::Return)
+            | Token::Keyword(Keyword::Goto)
+            | Token::Keyword(Keyword::StaticAssert)
+            | Token::LeftBrace => Input::Statement(trimmed),
+            _ if trimmed.ends_with(';') => Input::Statement(trimmed),
+            _ => Input::Expression(trimmed),
+        }
+    }
+}
+
+/// Whether `input` can be run, or whether the REPL should keep reading lines.
+///
+/// Input is incomplete if it has more opening than closing parentheses, brackets, or braces,
+/// if it ends inside a block comment, or if its last line ends with a backslash.
+pub fn is_complete(input: &str) -> bool {
+    let mut depth = 0_i32;
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            // a string or character literal; unterminated literals end at the end of the line
+            '"' | '\'' => {
+                while let Some(next) = chars.next() {
+                    match next {
+                        '\\' => {
+                            chars.next();
+                        }
+                        '\n' => break,
+                        _ if next == c => break,
+                        _ => {}
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                chars.find(|&next| next == '\n');
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut star = false;
+                loop {
+                    match chars.next() {
+                        None => return false,
+                        Some('/') if star => break,
+                        Some(next) => star = next == '*',
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    depth <= 0 && !input.trim_end().ends_with('\\')
+}
+
+#[cfg(feature = "jit")]
+mod session {
+    use std::collections::VecDeque;
+
+    use cranelift_module::FuncOrDataId;
+    use cranelift_simplejit::SimpleJITBackend;
+
+    use super::Input;
+    use crate::data::error::WarningOptions;
+    use crate::data::{hir::Arena, types::Type, *};
+    use crate::ir::Compiler;
+    use crate::{ice, initialize_jit_module, Opt, PureAnalyzer};
+
+    /// What to do after running an input.
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub enum Response {
+        /// Nothing to show; read the next input
+        Done,
+        /// Show this to the user, then read the next input
+        Print(String),
+        /// End the session
+        Quit,
+    }
+
+    /// How to return the value of an expression from its wrapper function.
+    #[derive(Clone, Copy)]
+    enum Value {
+        Signed,
+        Unsigned,
+        Pointer,
+        Float,
+        Double,
+    }
+
+    /// An interactive session, where each input is compiled into the same JIT module.
+    ///
+    /// Declarations and functions defined by one input can be used by all later ones.
+    /// Statements and expressions are compiled into a new function, which is run immediately.
+    pub struct Repl {
+        analyzer: PureAnalyzer<'static>,
+        compiler: Compiler<SimpleJITBackend>,
+        warning_options: WarningOptions,
+        warnings: VecDeque<CompileWarning>,
+        // how many functions have been generated for statements and expressions
+        wrappers: usize,
+    }
+
+    impl Repl {
+        /// Start a new session.
+        ///
+        /// Only the options for analysis, code generation, and warnings are used.
+        pub fn new(opt: Opt) -> Self {
+            // The HIR for every input is referenced by the analyzer's scope until the session ends,
+            // and sessions usually last as long as the program, so never free it.
+            let arena: &'static Arena<'static> = Box::leak(Box::new(Arena::new()));
+            let analyzer = PureAnalyzer::new(arena)
+                .standard(opt.standard)
+                .freestanding(opt.freestanding);
+            let module = initialize_jit_module(opt.opt_level);
+            let compiler = Compiler::new(
+                module,
+                opt.debug_asm,
+                opt.optimize_sibling_calls,
+                opt.freestanding,
+            );
+            Self {
+                analyzer,
+                compiler,
+                warning_options: opt.warnings,
+                warnings: VecDeque::new(),
+                wrappers: 0,
+            }
+        }
+
+        /// Return all warnings seen so far.
+        ///
+        /// These warnings are consumed and will not be returned if you call
+        /// `warnings()` again.
+        pub fn warnings(&mut self) -> VecDeque<CompileWarning> {
+            std::mem::take(&mut self.warnings)
+        }
+
+        /// Compile `input` and run it, if it's a statement or expression.
+        ///
+        /// `input` should be complete (see `is_complete`).
+        /// Only the first error is returned; warnings are available through `warnings()`.
+        ///
+        /// # Safety
+        /// This function runs arbitrary C code.
+        /// It can segfault, access out-of-bounds memory, cause data races, or do anything else C can do.
+        #[allow(unsafe_code)]
+        pub unsafe fn eval(&mut self, input: &str) -> CompileResult<Response> {
+            let scope = self.analyzer.scope();
+            let is_typedef = |id| {
+                scope
+                    .get(&id)
+                    .is_some_and(|symbol| symbol.get().storage_class == StorageClass::Typedef)
+            };
+            match Input::classify(input, is_typedef) {
+                Input::Empty => Ok(Response::Done),
+                Input::Quit => Ok(Response::Quit),
+                Input::UnknownCommand(name) => Ok(Response::Print(format!(
+                    "unknown command ':{}'. commands are ':type <expr>' and ':quit'",
+                    name
+                ))),
+                Input::Type(src) => {
+                    let expr = self.analyzer.parse_expr_str(src);
+                    self.check_warnings()?;
+                    Ok(Response::Print(expr?.ctype.to_string()))
+                }
+                Input::Declaration(src) => {
+                    let decls = self.analyzer.parse_external_decl_str(src);
+                    self.check_warnings()?;
+                    self.compile(decls?)?;
+                    Ok(Response::Done)
+                }
+                Input::Statement(src) => {
+                    self.run_statement(src)?;
+                    Ok(Response::Done)
+                }
+                Input::Expression(src) => self.run_expression(src),
+            }
+        }
+
+        /// Run `src` as the body of a new function returning `void`.
+        #[allow(unsafe_code)]
+        unsafe fn run_statement(&mut self, src: &str) -> CompileResult<()> {
+            let name = self.compile_wrapper("void", src)?;
+            let func = self.get_function(&name);
+            let func: unsafe extern "C" fn() = std::mem::transmute(func);
+            func();
+            Ok(())
+        }
+
+        /// Evaluate the expression `src` and show its value.
+        #[allow(unsafe_code)]
+        unsafe fn run_expression(&mut self, src: &str) -> CompileResult<Response> {
+            let expr = self.analyzer.parse_expr_str(src);
+            // the same warnings will be given again when the wrapper is analyzed
+            self.analyzer.warnings();
+            let ctype = expr?.ctype;
+            let (return_type, value) = match &ctype {
+                Type::Float => ("double", Value::Float),
+                Type::Double => ("double", Value::Double),
+                Type::Pointer(..) | Type::Array(..) | Type::Function(_) => {
+                    ("unsigned long", Value::Pointer)
+                }
+                _ if ctype.is_integral() && ctype.is_signed() => ("long", Value::Signed),
+                _ if ctype.is_integral() => ("unsigned long", Value::Unsigned),
+                // `void` and aggregates don't have a value that can be printed
+                _ => {
+                    self.run_statement(&format!("{};", src))?;
+                    return Ok(match ctype {
+                        Type::Void => Response::Done,
+                        _ => Response::Print(format!("=> ({})", ctype)),
+                    });
+                }
+            };
+            // the newlines keep a trailing `//` comment from swallowing the end of the wrapper
+            let body = format!("return ({})(\n{}\n);", return_type, src);
+            let name = self.compile_wrapper(return_type, &body)?;
+            let func = self.get_function(&name);
+            let shown = match value {
+                Value::Signed => {
+                    let func: unsafe extern "C" fn() -> i64 = std::mem::transmute(func);
+                    func().to_string()
+                }
+                Value::Unsigned => {
+                    let func: unsafe extern "C" fn() -> u64 = std::mem::transmute(func);
+                    func().to_string()
+                }
+                Value::Pointer => {
+                    let func: unsafe extern "C" fn() -> u64 = std::mem::transmute(func);
+                    format!("{:#x}", func())
+                }
+                Value::Float => {
+                    let func: unsafe extern "C" fn() -> f64 = std::mem::transmute(func);
+                    format!("{:?}", func() as f32)
+                }
+                Value::Double => {
+                    let func: unsafe extern "C" fn() -> f64 = std::mem::transmute(func);
+                    format!("{:?}", func())
+                }
+            };
+            Ok(Response::Print(format!("=> ({}) {}", ctype, shown)))
+        }
+
+        /// Define a new function returning `return_type` with `body`, and return its name.
+        fn compile_wrapper(&mut self, return_type: &str, body: &str) -> CompileResult<String> {
+            let name = format!("__repl_{}", self.wrappers);
+            self.wrappers += 1;
+            let src = format!("static {} {}(void) {{\n{}\n}}", return_type, name, body);
+            let decls = self.analyzer.parse_external_decl_str(&src);
+            self.check_warnings()?;
+            self.compile(decls?)?;
+            Ok(name)
+        }
+
+        /// Add `decls` to the module and make them available to run.
+        fn compile(
+            &mut self,
+            decls: Vec<Locatable<hir::Declaration<'static>>>,
+        ) -> CompileResult<()> {
+            let fallback = match decls.first() {
+                Some(decl) => decl.location,
+                // `int;`
+                None => return Ok(()),
+            };
+            let compiler = &mut self.compiler;
+            ice::catch(fallback, || -> CompileResult<()> {
+                for decl in decls {
+                    compiler.compile_decl(decl)?;
+                }
+                compiler.module_mut().finalize_definitions();
+                Ok(())
+            })??;
+            self.check_warnings()
+        }
+
+        /// The address of a function compiled by `compile_wrapper`.
+        fn get_function(&mut self, name: &str) -> *const u8 {
+            let module = self.compiler.module_mut();
+            match module.get_name(name) {
+                Some(FuncOrDataId::Func(id)) => module.get_finalized_function(id),
+                _ => unreachable!("{} should have been compiled by compile_wrapper", name),
+            }
+        }
+
+        /// Collect new warnings, returning an error if any of them were made into errors with `-Werror`.
+        fn check_warnings(&mut self) -> CompileResult<()> {
+            let mut warnings = self.analyzer.warnings();
+            warnings.extend(self.compiler.warnings());
+            let mut errors = self.warning_options.apply(&mut warnings);
+            self.warnings.extend(warnings);
+            match errors.pop_front() {
+                Some(err) => Err(err),
+                None => Ok(()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn classify(input: &str) -> Input<'_> {
+        Input::classify(input, |id| id == "T".into())
+    }
+
+    #[test]
+    fn declarations() {
+        for input in &[
+            "int x;",
+            "int sq(int x) { return x * x; }",
+            "static const char *s = \"hi\";",
+            "struct s { int i; };",
+            "typedef int T;",
+            "T t = 1;",
+        ] {
+            assert_eq!(classify(input), Input::Declaration(input), "{}", input);
+        }
+    }
+
+    #[test]
+    fn statements() {
+        for input in &[
+            "x = 1;",
+            "f();",
+            "for (int i = 0; i < 10; i++) x += i;",
+            "if (x) puts(\"x\")",
+            "{ int y = x; }",
+            "return",
+        ] {
+            assert_eq!(classify(input), Input::Statement(input), "{}", input);
+        }
+    }
+
+    #[test]
+    fn expressions() {
+        for input in &["1 + 2", "sq(3)", "x = 1", "U", "sizeof(int)", "\"a\" \"b\""] {
+            assert_eq!(classify(input), Input::Expression(input), "{}", input);
+        }
+        assert_eq!(classify("  1  \n"), Input::Expression("1"));
+    }
+
+    #[test]
+    fn commands() {
+        assert_eq!(classify(":type 1 + 2"), Input::Type("1 + 2"));
+        assert_eq!(classify(":t x"), Input::Type("x"));
+        assert_eq!(classify(":quit"), Input::Quit);
+        assert_eq!(classify("  :q\n"), Input::Quit);
+        assert_eq!(classify(":help"), Input::UnknownCommand("help"));
+        assert_eq!(classify(""), Input::Empty);
+        assert_eq!(classify(" // nothing\n"), Input::Empty);
+    }
+
+    #[test]
+    fn complete() {
+        for input in &[
+            "1 + 2",
+            "int sq(int x) { return x * x; }",
+            "puts(\"{\")",
+            "char c = '(';",
+            "x // (\n",
+            "/* { */ x",
+            "}",
+        ] {
+            assert!(is_complete(input), "{}", input);
+        }
+    }
+
+    #[test]
+    fn incomplete() {
+        for input in &[
+            "int sq(int x) {",
+            "int sq(int x) {\n  return x * x;\n",
+            "f(1,",
+            "int a[",
+            "/* {",
+            "#define f(x) \\",
+        ] {
+            assert!(!is_complete(input), "{}", input);
+        }
+    }
+}
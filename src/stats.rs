@@ -0,0 +1,239 @@
+//! Per-phase timing and statistics, used for `-ftime-report`.
+//!
+//! The compiler is a pipeline of iterators, so the phases run interleaved:
+//! the analyzer asks the parser for a declaration, which asks the preprocessor for tokens, and so on.
+//! Each phase marks the code it runs with `enter`, and the time for a phase
+//! excludes the time spent in the phases nested inside it.
+//!
+//! Nothing is recorded unless `start` has been called on the current thread.
+
+use std::cell::RefCell;
+use std::time::{Duration, Instant};
+
+use crate::intern::STRINGS;
+
+/// A part of the compiler that can be timed separately.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Phase {
+    /// Directives and macro replacement
+    Preprocessing,
+    /// Turning characters into tokens
+    Lexing,
+    /// Building the AST
+    Parsing,
+    /// Type checking and lowering to HIR
+    Analysis,
+    /// Evaluating constant expressions
+    ConstantFolding,
+    /// Generating Cranelift IR and machine code
+    Codegen,
+    /// Writing the object file
+    Emission,
+    /// Running the linker
+    Linking,
+}
+
+impl Phase {
+    /// Every phase, in pipeline order.
+    pub const ALL: [Phase; 8] = [
+        Phase::Preprocessing,
+        Phase::Lexing,
+        Phase::Parsing,
+        Phase::Analysis,
+        Phase::ConstantFolding,
+        Phase::Codegen,
+        Phase::Emission,
+        Phase::Linking,
+    ];
+    /// The name shown in the report.
+    pub fn name(self) -> &'static str {
+        match self {
+            Phase::Preprocessing => "preprocessing",
+            Phase::Lexing => "lexing",
+            Phase::Parsing => "parsing",
+            Phase::Analysis => "semantic analysis",
+            Phase::ConstantFolding => "constant folding",
+            Phase::Codegen => "codegen",
+            Phase::Emission => "object emission",
+            Phase::Linking => "linking",
+        }
+    }
+}
+
+/// Something the compiler counts while it runs.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Counter {
+    /// Tokens returned by the lexer, not including whitespace
+    TokensLexed,
+    /// Uses of object-like macros and calls of function-like macros that were replaced
+    MacroExpansions,
+    /// Expressions type checked by the analyzer
+    ExpressionsAnalyzed,
+    /// Function definitions passed to Cranelift
+    FunctionsCompiled,
+    /// Distinct identifiers and strings in the string interner, when the report was finished
+    InternedStrings,
+}
+
+impl Counter {
+    /// Every counter, in the order they are reported.
+    pub const ALL: [Counter; 5] = [
+        Counter::TokensLexed,
+        Counter::MacroExpansions,
+        Counter::ExpressionsAnalyzed,
+        Counter::FunctionsCompiled,
+        Counter::InternedStrings,
+    ];
+    /// The name shown in the report.
+    pub fn name(self) -> &'static str {
+        match self {
+            Counter::TokensLexed => "tokens lexed",
+            Counter::MacroExpansions => "macro expansions",
+            Counter::ExpressionsAnalyzed => "expressions analyzed",
+            Counter::FunctionsCompiled => "functions compiled",
+            Counter::InternedStrings => "interned strings",
+        }
+    }
+}
+
+/// The time spent in each phase and the value of each counter.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Stats {
+    times: [Duration; Phase::ALL.len()],
+    counts: [usize; Counter::ALL.len()],
+}
+
+impl Stats {
+    /// The time spent in `phase`, not including the phases nested inside it.
+    pub fn time(&self, phase: Phase) -> Duration {
+        self.times[phase as usize]
+    }
+    /// The time spent in all phases.
+    pub fn total_time(&self) -> Duration {
+        self.times.iter().sum()
+    }
+    pub fn count(&self, counter: Counter) -> usize {
+        self.counts[counter as usize]
+    }
+}
+
+#[derive(Default)]
+struct Recorder {
+    stats: Stats,
+    /// The phases currently running, innermost last, and when each last started or resumed
+    running: Vec<(Phase, Instant)>,
+}
+
+thread_local! {
+    /// The statistics for this thread, if `start` has been called
+    static RECORDER: RefCell<Option<Recorder>> = const { RefCell::new(None) };
+}
+
+/// Start recording statistics on this thread, discarding any that were already recorded.
+pub fn start() {
+    RECORDER.with(|recorder| *recorder.borrow_mut() = Some(Recorder::default()));
+}
+
+/// Stop recording statistics on this thread and return the ones recorded since `start`.
+///
+/// Returns `None` if `start` wasn't called, or if `finish` was already called since then.
+pub fn finish() -> Option<Stats> {
+    let mut stats = RECORDER
+        .with(|recorder| recorder.borrow_mut().take())?
+        .stats;
+    if let Ok(strings) = STRINGS.read() {
+        stats.counts[Counter::InternedStrings as usize] = strings.len();
+    }
+    Some(stats)
+}
+
+/// Time `f` as part of `phase`.
+pub fn time<T>(phase: Phase, f: impl FnOnce() -> T) -> T {
+    let _phase = enter(phase);
+    f()
+}
+
+/// Count the time until the returned guard is dropped as part of `phase`.
+///
+/// The phase that was running before is paused until then.
+pub(crate) fn enter(phase: Phase) -> PhaseGuard {
+    let recording = RECORDER.with(|recorder| match &mut *recorder.borrow_mut() {
+        Some(recorder) => {
+            let now = Instant::now();
+            if let Some(&(outer, resumed)) = recorder.running.last() {
+                recorder.stats.times[outer as usize] += now - resumed;
+            }
+            recorder.running.push((phase, now));
+            true
+        }
+        None => false,
+    });
+    PhaseGuard { recording }
+}
+
+/// See `enter`.
+#[must_use = "the phase ends as soon as the guard is dropped"]
+pub(crate) struct PhaseGuard {
+    recording: bool,
+}
+
+impl Drop for PhaseGuard {
+    // this also runs while unwinding, so a panic inside a phase doesn't leave it running forever
+    fn drop(&mut self) {
+        if !self.recording {
+            return;
+        }
+        RECORDER.with(|recorder| {
+            if let Some(recorder) = &mut *recorder.borrow_mut() {
+                let now = Instant::now();
+                if let Some((phase, resumed)) = recorder.running.pop() {
+                    recorder.stats.times[phase as usize] += now - resumed;
+                }
+                if let Some(outer) = recorder.running.last_mut() {
+                    outer.1 = now;
+                }
+            }
+        });
+    }
+}
+
+/// Add one to `counter`, if statistics are being recorded.
+#[inline]
+pub(crate) fn count(counter: Counter) {
+    RECORDER.with(|recorder| {
+        if let Some(recorder) = &mut *recorder.borrow_mut() {
+            recorder.stats.counts[counter as usize] += 1;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nested_phases() {
+        let sleep = |ms| move || std::thread::sleep(Duration::from_millis(ms));
+        count(Counter::TokensLexed);
+        assert_eq!(finish(), None);
+
+        start();
+        time(Phase::Parsing, || {
+            sleep(20)();
+            count(Counter::TokensLexed);
+            time(Phase::Lexing, sleep(100));
+            count(Counter::TokensLexed);
+        });
+        let stats = finish().unwrap();
+        assert_eq!(finish(), None);
+        assert_eq!(stats.count(Counter::TokensLexed), 2);
+        assert_eq!(stats.count(Counter::FunctionsCompiled), 0);
+        // the time spent lexing isn't counted as parsing
+        let (parsing, lexing) = (stats.time(Phase::Parsing), stats.time(Phase::Lexing));
+        assert!(parsing >= Duration::from_millis(20), "{:?}", parsing);
+        assert!(parsing < Duration::from_millis(100), "{:?}", parsing);
+        assert!(lexing >= Duration::from_millis(100), "{:?}", lexing);
+        assert_eq!(stats.total_time(), parsing + lexing);
+        assert_eq!(stats.time(Phase::Codegen), Duration::default());
+    }
+}
@@ -0,0 +1,297 @@
+//! Tests for the command line interface of `swcc`.
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+fn swcc(args: &[&str], source: &str) -> Output {
+    let dir = tempfile::tempdir().expect("should be able to create a temporary directory");
+    let path = dir.path().join("test.c");
+    std::fs::write(&path, source).expect("should be able to write a temporary file");
+    Command::new(env!("CARGO_BIN_EXE_swcc"))
+        .args(args)
+        .args(["--color", "never", "-o"])
+        .arg(dir.path().join("a.out"))
+        .arg(&path)
+        .output()
+        .expect("should be able to run swcc")
+}
+
+/// Run swcc in `dir` with `source` as stdin.
+fn swcc_stdin(dir: &std::path::Path, args: &[&str], source: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_swcc"))
+        .current_dir(dir)
+        .args(["--color", "never"])
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("should be able to run swcc");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(source.as_bytes())
+        .expect("should be able to write to stdin");
+    child.wait_with_output().unwrap()
+}
+
+/// Return the lines of stderr that start a new diagnostic
+fn diagnostics(output: &Output) -> Vec<String> {
+    String::from_utf8_lossy(&output.stderr)
+        .lines()
+        .filter(|line| line.contains(": error: ") || line.starts_with("fatal: "))
+        .map(String::from)
+        .collect()
+}
+
+#[test]
+fn error_limit() {
+    let source: String = (0..100)
+        .map(|i| format!("int f{0}() {{ return x{0}; }}\n", i))
+        .collect();
+    for limit in &[1, 5, 20] {
+        let output = swcc(&[&format!("-ferror-limit={}", limit)], &source);
+        assert_eq!(output.status.code(), Some(2));
+        let diagnostics = diagnostics(&output);
+        assert_eq!(diagnostics.len(), limit + 1, "{:#?}", diagnostics);
+        assert_eq!(
+            diagnostics.last().unwrap(),
+            &format!(
+                "fatal: too many errors emitted, stopping now [-ferror-limit={}]",
+                limit
+            )
+        );
+    }
+    // 0 means no limit
+    let output = swcc(&["-ferror-limit=0"], &source);
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(diagnostics(&output).len(), 100);
+}
+
+#[test]
+fn error_limit_ignores_warnings() {
+    let source = "#line 1\n#line 2\nint main() { return 0; }\n";
+    let output = swcc(&["-ferror-limit=1"], source);
+    assert!(output.status.success(), "{:?}", output);
+}
+
+#[test]
+fn stdin() {
+    let dir = tempfile::tempdir().unwrap();
+    let output = swcc_stdin(dir.path(), &["-"], "int main(){return 42;}");
+    assert!(output.status.success(), "{:?}", output);
+    let status = Command::new(dir.path().join("a.out")).status().unwrap();
+    assert_eq!(status.code(), Some(42));
+
+    let output = swcc_stdin(dir.path(), &["-x", "c", "-"], "int main(){return x;}");
+    assert_eq!(output.status.code(), Some(2));
+    assert!(diagnostics(&output)[0].starts_with("<stdin>:1:19: error: "));
+
+    // there's no file name to derive the object file name from
+    let output = swcc_stdin(dir.path(), &["-c", "-"], "int main(){return 0;}");
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn stdout() {
+    let dir = tempfile::tempdir().unwrap();
+    let output = swcc_stdin(dir.path(), &["-E", "-o", "-", "-"], "#define X 1\nX\n");
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "1");
+
+    let output = swcc_stdin(dir.path(), &["-c", "-o", "-", "-"], "int main(){return 0;}");
+    assert!(output.status.success(), "{:?}", output);
+    assert!(output.stdout.starts_with(b"\x7fELF"));
+    assert!(!dir.path().join("a.out").exists());
+}
+
+#[test]
+fn emit() {
+    let dir = tempfile::tempdir().unwrap();
+    let source = include_str!("emit/prog.c");
+    std::fs::write(dir.path().join("prog.c"), source).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_swcc"))
+        .current_dir(dir.path())
+        .args(["--color", "never", "--emit=hir,ast", "prog.c"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let read = |name| std::fs::read_to_string(dir.path().join(name)).unwrap();
+    assert_eq!(read("prog.ast"), include_str!("emit/prog.ast"));
+    assert_eq!(read("prog.hir"), include_str!("emit/prog.hir"));
+    // analysis only, so nothing should have been compiled
+    assert!(!dir.path().join("prog.o").exists());
+    assert!(!dir.path().join("a.out").exists());
+
+    let output = swcc_stdin(dir.path(), &["--emit=clif,obj", "-o", "-", "-"], source);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(output.stdout.starts_with(b"function u0:0("));
+    assert!(!dir.path().join("a.out").exists());
+
+    // each kind needs its own file
+    let output = swcc_stdin(dir.path(), &["--emit=ast,hir", "-o", "out", "-"], source);
+    assert_eq!(output.status.code(), Some(1));
+    let output = swcc_stdin(dir.path(), &["--emit=asm,bogus", "-o", "-", "-"], source);
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn emit_c() {
+    let dir = tempfile::tempdir().unwrap();
+    let source = "int f(char c) { return c + 1; }\n";
+    let output = swcc_stdin(dir.path(), &["--emit=c", "-o", "-", "-"], source);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "int f(char c) {\n    return c + 1;\n}\n\n"
+    );
+    let args = ["--emit=c", "--show-implicit-casts", "-o", "-", "-"];
+    let output = swcc_stdin(dir.path(), &args, source);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "int f(char c) {\n    return (int)((long)c + 1);\n}\n\n"
+    );
+    // the printed program is still valid C
+    std::fs::write(dir.path().join("prog.c"), include_str!("emit/prog.c")).unwrap();
+    let output = swcc_stdin(dir.path(), &["--emit=c", "prog.c"], "");
+    assert!(output.status.success(), "{:?}", output);
+    let printed = std::fs::read_to_string(dir.path().join("prog.hir.c")).unwrap();
+    let output = swcc_stdin(dir.path(), &["-c", "-o", "-", "-"], &printed);
+    assert!(output.status.success(), "{:?}\n{}", output, printed);
+}
+
+#[cfg(feature = "serialize")]
+#[test]
+fn emit_json_hir() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("tiny.c"), include_str!("emit/tiny.c")).unwrap();
+    let output = swcc_stdin(dir.path(), &["--emit=json-hir", "tiny.c"], "");
+    assert!(output.status.success(), "{:?}", output);
+    let json = std::fs::read_to_string(dir.path().join("tiny.hir.json")).unwrap();
+    assert_eq!(json, include_str!("emit/tiny.hir.json"));
+}
+
+#[test]
+fn header_location() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("header.h"), "int i;\nint j = ;\n").unwrap();
+    // the same contents again, to make sure it's not confused with the first header
+    std::fs::write(dir.path().join("copy.h"), "int i;\nint j = ;\n").unwrap();
+    let source = "#include \"header.h\"\n#include \"copy.h\"\n#line 20 \"renamed.c\"\nint k = ;\n";
+    std::fs::write(dir.path().join("main.c"), source).unwrap();
+    let output = swcc_stdin(dir.path(), &["-c", "-o", "main.o", "main.c"], "");
+    assert!(!output.status.success());
+    let errors: Vec<_> = diagnostics(&output)
+        .into_iter()
+        .map(|line| line.split(" error: ").next().unwrap().to_owned())
+        .collect();
+    assert_eq!(
+        errors,
+        ["header.h:2:9:", "copy.h:2:9:", "renamed.c:20:9:"],
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn standard() {
+    let dir = tempfile::tempdir().unwrap();
+    let version = |flags: &[&str]| {
+        let mut args = flags.to_vec();
+        args.extend(&["-E", "-"]);
+        let output = swcc_stdin(dir.path(), &args, "__STDC_VERSION__\n");
+        assert!(output.status.success(), "{:?}", output);
+        String::from_utf8_lossy(&output.stdout).trim().to_owned()
+    };
+    assert_eq!(version(&[]), "201112");
+    assert_eq!(version(&["-std=c99"]), "199901");
+    assert_eq!(version(&["-std=gnu17"]), "201710");
+    assert_eq!(version(&["-std=c89"]), "__STDC_VERSION__");
+    assert_eq!(version(&["-ansi"]), "__STDC_VERSION__");
+
+    let output = swcc_stdin(dir.path(), &["-std=c++11", "-E", "-"], "");
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn pedantic() {
+    let dir = tempfile::tempdir().unwrap();
+    let compile = |flags: &[&str]| {
+        let mut args = flags.to_vec();
+        args.extend(&["-c", "-o", "out.o", "-"]);
+        swcc_stdin(dir.path(), &args, "int i = 0b101;\n")
+    };
+    let output = compile(&[]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(output.stderr.is_empty(), "{:?}", output);
+
+    let output = compile(&["-pedantic"]);
+    assert!(output.status.success(), "{:?}", output);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("[-Wgnu-binary-literal]"), "{}", stderr);
+
+    let output = compile(&["-pedantic-errors"]);
+    assert!(!output.status.success(), "{:?}", output);
+    let output = compile(&["-pedantic-errors", "-Wno-gnu-binary-literal"]);
+    assert!(output.status.success(), "{:?}", output);
+}
+
+#[test]
+fn internal_compiler_error() {
+    let dir = tempfile::tempdir().unwrap();
+    // defining variadic functions isn't implemented yet
+    let program = "int f(int n, ...) {\n  return n;\n}\n";
+    let output = swcc_stdin(dir.path(), &["-c", "-o", "out.o", "-"], program);
+    assert_eq!(output.status.code(), Some(70), "{:?}", output);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("internal compiler error while compiling <stdin>"),
+        "{}",
+        stderr
+    );
+    assert!(
+        stderr.contains("<stdin>:2:10: error: internal compiler error: "),
+        "{}",
+        stderr
+    );
+    assert!(stderr.contains("Please report it at"), "{}", stderr);
+}
+
+#[test]
+fn time_report() {
+    let dir = tempfile::tempdir().unwrap();
+    let program = "int f(void) { return 1; }\nint g(void) { return 2; }\nint main(void) { return f() + g(); }\n";
+    let compile = |flags: &[&str]| {
+        let mut args = flags.to_vec();
+        args.extend(&["-c", "-o", "out.o", "-"]);
+        swcc_stdin(dir.path(), &args, program)
+    };
+    let output = compile(&[]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(output.stderr.is_empty(), "{:?}", output);
+
+    let output = compile(&["-ftime-report"]);
+    assert!(output.status.success(), "{:?}", output);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    for phase in &["preprocessing", "semantic analysis", "codegen", "total"] {
+        assert!(
+            stderr.lines().any(|line| line.starts_with(phase)),
+            "{}",
+            stderr
+        );
+    }
+    let functions: Vec<_> = stderr
+        .lines()
+        .find(|line| line.starts_with("functions compiled"))
+        .expect("should count compiled functions")
+        .split_whitespace()
+        .collect();
+    assert_eq!(functions, ["functions", "compiled", "3"]);
+
+    let output = compile(&["-ftime-report=json"]);
+    assert!(output.status.success(), "{:?}", output);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.starts_with("{\"time_ms\":{"), "{}", stderr);
+    assert!(stderr.contains("\"functions_compiled\":3,"), "{}", stderr);
+}
@@ -0,0 +1,251 @@
+//! Differential tests against the host C compiler.
+//!
+//! Every program in `tests/conformance` is compiled with both saltwater and `cc`,
+//! and the two executables must agree on their exit status and standard output.
+//! See `tests/conformance/README.md` for how to add new cases.
+//!
+//! Run with `cargo test --features conformance --test conformance`.
+#![cfg(feature = "conformance")]
+
+use std::collections::{HashSet, VecDeque};
+use std::env;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use saltwater::{CompileError, Error, LinkOptions, Opt};
+
+const CORPUS: &str = "tests/conformance";
+/// How long a compiled program may run before it's considered hung.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The observable behavior of a program.
+#[derive(Debug, PartialEq)]
+enum Outcome {
+    Exited { status: Option<i32>, stdout: String },
+    TimedOut,
+}
+
+/// Why saltwater and `cc` disagreed about a program.
+enum Divergence {
+    Compile(String),
+    Behavior { saltwater: Outcome, cc: Outcome },
+}
+
+#[test]
+fn corpus() {
+    let skip = skip_list();
+    let dir = tempfile::tempdir().expect("cannot create tempdir");
+    let mut programs: Vec<PathBuf> = fs::read_dir(CORPUS)
+        .expect("cannot read conformance corpus")
+        .map(|entry| entry.expect("cannot read directory entry").path())
+        .filter(|path| path.extension().map_or(false, |ext| ext == "c"))
+        .collect();
+    programs.sort();
+    assert!(!programs.is_empty(), "no programs found in {}", CORPUS);
+
+    let mut failures = Vec::new();
+    for path in &programs {
+        let source = fs::read_to_string(path).expect("cannot read program");
+        match check(&source, path, dir.path(), &[], &skip) {
+            Ok(()) => {}
+            Err(divergence) => failures.push((path.clone(), divergence)),
+        }
+    }
+    report(programs.len(), &failures);
+}
+
+/// Compare saltwater against `cc` on randomly generated programs.
+///
+/// Does nothing unless `csmith` is on the PATH.
+/// `CSMITH_HOME` must point to the csmith source tree (for `runtime/csmith.h`)
+/// and `CSMITH_COUNT` sets the number of programs to generate (default 10).
+#[test]
+fn csmith() {
+    let runtime = match env::var_os("CSMITH_HOME") {
+        Some(home) if Command::new("csmith").arg("--version").output().is_ok() => {
+            Path::new(&home).join("runtime")
+        }
+        _ => {
+            eprintln!("skipping csmith conformance tests: csmith or CSMITH_HOME not found");
+            return;
+        }
+    };
+    let count = env::var("CSMITH_COUNT").ok().map_or(10, |count| {
+        count.parse().expect("CSMITH_COUNT should be a number")
+    });
+    let skip = skip_list();
+    let dir = tempfile::tempdir().expect("cannot create tempdir");
+    let runtime = runtime.to_str().expect("CSMITH_HOME should be valid UTF-8");
+    let cpp_args = ["-I", runtime];
+
+    let mut failures = Vec::new();
+    for i in 0..count {
+        let output = Command::new("csmith")
+            .output()
+            .expect("failed to run csmith");
+        assert!(output.status.success(), "csmith failed");
+        let generated = String::from_utf8(output.stdout).expect("csmith output should be UTF-8");
+        // saltwater's preprocessor can't parse all of the system headers csmith includes
+        let preprocessed = Command::new("cc")
+            .args(&["-E", "-P", "-"])
+            .args(&cpp_args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .and_then(|mut cpp| {
+                use std::io::Write;
+                cpp.stdin.take().unwrap().write_all(generated.as_bytes())?;
+                cpp.wait_with_output()
+            })
+            .expect("failed to run cpp");
+        let source = String::from_utf8(preprocessed.stdout).expect("cpp output should be UTF-8");
+        let path = dir.path().join(format!("csmith-{}.c", i));
+        fs::write(&path, &generated).expect("cannot save generated program");
+        match check(&source, &path, dir.path(), &cpp_args, &skip) {
+            Ok(()) => {}
+            Err(divergence) => {
+                // keep the program around so it can be reproduced
+                let kept = env::temp_dir().join(format!("saltwater-csmith-{}.c", i));
+                fs::copy(&path, &kept).expect("cannot save failing program");
+                failures.push((kept, divergence));
+            }
+        }
+    }
+    report(count, &failures);
+}
+
+/// Compile `source` with both compilers and compare the results.
+///
+/// Programs saltwater rejects with only skip-listed errors are not counted as failures.
+fn check(
+    source: &str,
+    path: &Path,
+    dir: &Path,
+    cc_args: &[&str],
+    skip: &HashSet<String>,
+) -> Result<(), Divergence> {
+    let stem = path.file_stem().unwrap().to_string_lossy();
+    let cc_exe = dir.join(format!("{}-cc", stem));
+    let status = Command::new("cc")
+        .args(cc_args)
+        .args(&["-w", "-o"])
+        .arg(&cc_exe)
+        .arg(path)
+        .status()
+        .expect("failed to run cc");
+    assert!(status.success(), "cc failed to compile {}", path.display());
+
+    let sw_exe = dir.join(format!("{}-saltwater", stem));
+    let opt = Opt {
+        filename: path.to_owned(),
+        ..Opt::default()
+    };
+    let program = saltwater::compile_executable(source, opt, &sw_exe, &LinkOptions::default());
+    match program.result {
+        Ok(()) => {}
+        Err(Error::Source(errs)) => {
+            if errs.iter().all(|err| skip.contains(err.data.code())) {
+                eprintln!("skipping {}: {}", path.display(), codes(&errs));
+                return Ok(());
+            }
+            return Err(Divergence::Compile(codes(&errs)));
+        }
+        Err(err) => return Err(Divergence::Compile(err.to_string())),
+    }
+
+    let cc = run(&cc_exe);
+    // csmith occasionally generates programs that take forever; those can't tell us anything
+    if cc == Outcome::TimedOut {
+        eprintln!(
+            "skipping {}: timed out when compiled with cc",
+            path.display()
+        );
+        return Ok(());
+    }
+    let saltwater = run(&sw_exe);
+    if saltwater != cc {
+        return Err(Divergence::Behavior { saltwater, cc });
+    }
+    Ok(())
+}
+
+/// Run a program, killing it if it takes longer than `TIMEOUT`.
+fn run(exe: &Path) -> Outcome {
+    let mut child = Command::new(exe)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap_or_else(|err| panic!("failed to run {}: {}", exe.display(), err));
+    // read stdout on another thread so a chatty program can't fill the pipe and block forever
+    let mut stdout = child.stdout.take().unwrap();
+    let reader = thread::spawn(move || {
+        let mut buf = Vec::new();
+        stdout.read_to_end(&mut buf).map(|_| buf)
+    });
+    let deadline = Instant::now() + TIMEOUT;
+    let status: ExitStatus = loop {
+        if let Some(status) = child.try_wait().expect("failed to wait on program") {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Outcome::TimedOut;
+        }
+        thread::sleep(Duration::from_millis(10));
+    };
+    let stdout = reader
+        .join()
+        .unwrap()
+        .expect("failed to read program output");
+    Outcome::Exited {
+        status: status.code(),
+        stdout: String::from_utf8_lossy(&stdout).into_owned(),
+    }
+}
+
+/// Error codes which are known to be unsupported, one per line.
+///
+/// Everything after a `#` is a comment.
+fn skip_list() -> HashSet<String> {
+    let list = fs::read_to_string(Path::new(CORPUS).join("skip")).expect("cannot read skip list");
+    list.lines()
+        .map(|line| line.split('#').next().unwrap().trim())
+        .filter(|code| !code.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+fn codes(errs: &VecDeque<CompileError>) -> String {
+    let codes: Vec<_> = errs
+        .iter()
+        .map(|err| format!("{} ({})", err.data, err.data.code()))
+        .collect();
+    codes.join(", ")
+}
+
+fn report(total: usize, failures: &[(PathBuf, Divergence)]) {
+    for (path, divergence) in failures {
+        match divergence {
+            Divergence::Compile(errs) => {
+                eprintln!("{}: saltwater failed to compile: {}", path.display(), errs)
+            }
+            Divergence::Behavior { saltwater, cc } => eprintln!(
+                "{}: behavior differs\n  saltwater: {:?}\n  cc:        {:?}",
+                path.display(),
+                saltwater,
+                cc
+            ),
+        }
+    }
+    assert!(
+        failures.is_empty(),
+        "{} of {} programs diverged from cc",
+        failures.len(),
+        total
+    );
+}
@@ -0,0 +1,10 @@
+int main(void) {
+    int a = 0xf0, b = 0x3c;
+    if ((a & b) != 0x30) return 1;
+    if ((a | b) != 0xfc) return 2;
+    if ((a ^ b) != 0xcc) return 3;
+    if ((~a & 0xff) != 0x0f) return 4;
+    unsigned m = 0;
+    for (int i = 0; i < 8; i += 2) m |= 1u << i;
+    return m != 0x55;
+}
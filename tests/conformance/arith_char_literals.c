@@ -0,0 +1,9 @@
+int main(void) {
+    if ('a' != 97) return 1;
+    if ('\n' != 10) return 2;
+    if ('\0' != 0) return 3;
+    if ('\x41' != 'A') return 4;
+    if ('\101' != 'A') return 5;
+    char s[] = "hi\tthere";
+    return s[2] != 9;
+}
@@ -0,0 +1,23 @@
+#include <stdio.h>
+
+int steps(long n) {
+    int count = 0;
+    while (n != 1) {
+        n = n % 2 ? 3 * n + 1 : n / 2;
+        count++;
+    }
+    return count;
+}
+
+int main(void) {
+    int longest = 0, arg = 0;
+    for (int i = 1; i < 1000; i++) {
+        int s = steps(i);
+        if (s > longest) {
+            longest = s;
+            arg = i;
+        }
+    }
+    printf("%d takes %d steps\n", arg, longest);
+    return arg != 871;
+}
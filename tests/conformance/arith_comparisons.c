@@ -0,0 +1,11 @@
+int main(void) {
+    int results = 0;
+    results += 1 < 2;
+    results += 2 <= 2;
+    results += 3 > 2;
+    results += 3 >= 4;
+    results += 5 == 5;
+    results += 5 != 5;
+    results += -1 < 0u; /* false: -1 converts to unsigned */
+    return results != 4;
+}
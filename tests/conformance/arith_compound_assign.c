@@ -0,0 +1,14 @@
+int main(void) {
+    int x = 10;
+    x += 5;
+    x -= 3;
+    x *= 4;
+    x /= 6;
+    x %= 5;
+    x <<= 3;
+    x >>= 1;
+    x |= 1;
+    x &= 13;
+    x ^= 6;
+    return x != 11;
+}
@@ -0,0 +1,16 @@
+int main(void) {
+    int i = 300;
+    unsigned char c = i;
+    if (c != 44) return 1;
+    signed char sc = (signed char)200;
+    if (sc != -56) return 2;
+    long l = -1;
+    unsigned u = (unsigned)l;
+    if (u != 4294967295u) return 3;
+    double d = 1e10;
+    long big = (long)d;
+    if (big != 10000000000L) return 4;
+    float f = 16777217;
+    if (f != 16777216.0f) return 5;
+    return 0;
+}
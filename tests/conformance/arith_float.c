@@ -0,0 +1,12 @@
+int main(void) {
+    double d = 1.5;
+    float f = 2.25f;
+    if (d + f != 3.75) return 1;
+    if (d * 2 != 3.0) return 2;
+    if (f / 0.5f != 4.5f) return 3;
+    if ((int)3.99 != 3) return 4;
+    if ((int)-3.99 != -3) return 5;
+    double third = 1.0 / 3;
+    if (third * 3 < 0.999 || third * 3 > 1.001) return 6;
+    return 0;
+}
@@ -0,0 +1,15 @@
+#include <stdio.h>
+
+unsigned gcd(unsigned a, unsigned b) {
+    while (b) {
+        unsigned t = a % b;
+        a = b;
+        b = t;
+    }
+    return a;
+}
+
+int main(void) {
+    printf("%u %u %u\n", gcd(48, 18), gcd(17, 5), gcd(1071, 462));
+    return gcd(48, 18) != 6;
+}
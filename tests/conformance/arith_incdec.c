@@ -0,0 +1,11 @@
+int main(void) {
+    int i = 5;
+    int a = i++;
+    int b = ++i;
+    int c = i--;
+    int d = --i;
+    if (a != 5 || b != 7 || c != 7 || d != 5 || i != 5) return 1;
+    double x = 0.5;
+    x++;
+    return x != 1.5;
+}
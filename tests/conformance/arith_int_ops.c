@@ -0,0 +1,11 @@
+int main(void) {
+    int a = 17, b = 5;
+    if (a + b != 22) return 1;
+    if (a - b != 12) return 2;
+    if (a * b != 85) return 3;
+    if (a / b != 3) return 4;
+    if (a % b != 2) return 5;
+    if (-a / b != -3) return 6;
+    if (-a % b != -2) return 7;
+    return 0;
+}
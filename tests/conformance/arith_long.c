@@ -0,0 +1,10 @@
+int main(void) {
+    long a = 3000000000L;
+    long b = a * 3;
+    if (b != 9000000000L) return 1;
+    long long c = -9000000000LL;
+    if (c / 1000 != -9000000) return 2;
+    unsigned long d = 18446744073709551615UL;
+    if (d + 1 != 0) return 3;
+    return 0;
+}
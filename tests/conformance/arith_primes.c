@@ -0,0 +1,14 @@
+#include <stdio.h>
+
+int main(void) {
+    char composite[1000] = {0};
+    int count = 0;
+    for (int i = 2; i < 1000; i++) {
+        if (composite[i]) continue;
+        count++;
+        for (int j = i * i; j < 1000; j += i)
+            composite[j] = 1;
+    }
+    printf("%d primes below 1000\n", count);
+    return count != 168;
+}
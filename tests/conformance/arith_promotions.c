@@ -0,0 +1,12 @@
+int main(void) {
+    unsigned char a = 200, b = 100;
+    int sum = a + b;
+    if (sum != 300) return 1;
+    signed char c = -1;
+    unsigned int u = 1;
+    if (!(c < u)) return 2; /* c converts to UINT_MAX */
+    short s = -3;
+    if (s * 2 != -6) return 3;
+    if (sizeof(a + b) != sizeof(int)) return 4;
+    return 0;
+}
@@ -0,0 +1,12 @@
+int main(void) {
+    unsigned x = 1;
+    if ((x << 31) != 0x80000000u) return 1;
+    if ((0x80000000u >> 31) != 1) return 2;
+    int n = -16;
+    if ((n >> 2) != -4) return 3;
+    long l = 1L << 40;
+    if (l != 1099511627776L) return 4;
+    unsigned long ul = ~0UL;
+    if ((ul >> 63) != 1) return 5;
+    return 0;
+}
@@ -0,0 +1,11 @@
+int main(void) {
+    if (sizeof(char) != 1) return 1;
+    if (sizeof(short) != 2) return 2;
+    if (sizeof(int) != 4) return 3;
+    if (sizeof(long) != 8) return 4;
+    if (sizeof(void *) != 8) return 5;
+    if (sizeof(double) != 8) return 6;
+    int arr[7];
+    if (sizeof arr / sizeof arr[0] != 7) return 7;
+    return 0;
+}
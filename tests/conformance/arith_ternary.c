@@ -0,0 +1,10 @@
+int max(int a, int b) { return a > b ? a : b; }
+int main(void) {
+    if (max(3, 9) != 9) return 1;
+    if (max(-3, -9) != -3) return 2;
+    int x = 0;
+    int y = x ? 1 : x == 0 ? 2 : 3;
+    if (y != 2) return 3;
+    double d = 1 ? 1 : 2.5;
+    return d != 1.0;
+}
@@ -0,0 +1,13 @@
+int main(void) {
+    unsigned int u = 0;
+    u -= 1;
+    if (u != 0xffffffffu) return 1;
+    u += 2;
+    if (u != 1) return 2;
+    unsigned char c = 255;
+    c++;
+    if (c != 0) return 3;
+    unsigned short s = 65535;
+    s += 1;
+    return s;
+}
@@ -0,0 +1,13 @@
+int main(void) {
+    int sum = 0;
+    for (int i = 0; i < 100; i++) {
+        if (i % 2) continue;
+        if (i > 10) break;
+        sum += i;
+    }
+    int n = 0;
+    while (1) {
+        if (++n == 7) break;
+    }
+    return sum != 30 || n != 7;
+}
@@ -0,0 +1,22 @@
+#include <stdio.h>
+
+void sort(int *arr, int n) {
+    for (int i = 0; i < n - 1; i++)
+        for (int j = 0; j < n - 1 - i; j++)
+            if (arr[j] > arr[j + 1]) {
+                int t = arr[j];
+                arr[j] = arr[j + 1];
+                arr[j + 1] = t;
+            }
+}
+
+int main(void) {
+    int arr[] = {9, -2, 7, 0, 3, 3, 11, -8};
+    int n = sizeof arr / sizeof *arr;
+    sort(arr, n);
+    for (int i = 0; i < n; i++) printf("%d ", arr[i]);
+    printf("\n");
+    for (int i = 1; i < n; i++)
+        if (arr[i - 1] > arr[i]) return 1;
+    return 0;
+}
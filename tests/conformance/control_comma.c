@@ -0,0 +1,7 @@
+int main(void) {
+    int i, j;
+    for (i = 0, j = 10; i < j; i++, j--)
+        ;
+    int x = (i++, i + j);
+    return i != 6 || j != 5 || x != 11;
+}
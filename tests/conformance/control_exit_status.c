@@ -0,0 +1,6 @@
+/* a program that doesn't return 0 on purpose: the harness compares exit statuses */
+int main(void) {
+    int status = 0;
+    for (int i = 1; i <= 6; i++) status += i;
+    return status;
+}
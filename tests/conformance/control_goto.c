@@ -0,0 +1,11 @@
+int main(void) {
+    int i = 0;
+again:
+    i++;
+    if (i < 5) goto again;
+    if (i != 5) return 1;
+    goto done;
+    return 2;
+done:
+    return 0;
+}
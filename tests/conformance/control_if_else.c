@@ -0,0 +1,12 @@
+int classify(int n) {
+    if (n < 0)
+        return -1;
+    else if (n == 0)
+        return 0;
+    else
+        return 1;
+}
+
+int main(void) {
+    return classify(-5) + classify(0) + classify(7) != 0;
+}
@@ -0,0 +1,11 @@
+int main(void) {
+    int total = 0;
+    for (int i = 0; i < 10; i++) total += i;
+    int j = 0;
+    while (j < 5) j++;
+    int k = 100;
+    do {
+        k++;
+    } while (k < 50);
+    return total != 45 || j != 5 || k != 101;
+}
@@ -0,0 +1,13 @@
+#include <stdio.h>
+
+int main(void) {
+    int count = 0;
+    for (int a = 1; a < 30; a++)
+        for (int b = a; b < 30; b++)
+            for (int c = b; c < 30; c++)
+                if (a * a + b * b == c * c) {
+                    printf("%d %d %d\n", a, b, c);
+                    count++;
+                }
+    return count != 10;
+}
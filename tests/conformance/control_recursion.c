@@ -0,0 +1,13 @@
+long fib(int n) { return n < 2 ? n : fib(n - 1) + fib(n - 2); }
+
+long fact(int n) { return n ? n * fact(n - 1) : 1; }
+
+int is_even(unsigned n);
+int is_odd(unsigned n) { return n ? is_even(n - 1) : 0; }
+int is_even(unsigned n) { return n ? is_odd(n - 1) : 1; }
+
+int main(void) {
+    if (fib(20) != 6765) return 1;
+    if (fact(15) != 1307674368000L) return 2;
+    return !is_even(10) || is_odd(10);
+}
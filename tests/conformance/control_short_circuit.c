@@ -0,0 +1,16 @@
+int calls;
+
+int touch(int value) {
+    calls++;
+    return value;
+}
+
+int main(void) {
+    calls = 0;
+    if (touch(0) && touch(1)) return 1;
+    if (calls != 1) return 2;
+    if (!(touch(1) || touch(0))) return 3;
+    if (calls != 2) return 4;
+    int x = touch(1) && touch(2) && touch(0);
+    return x != 0 || calls != 5;
+}
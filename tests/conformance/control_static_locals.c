@@ -0,0 +1,10 @@
+int next(void) {
+    static int n = 10;
+    return n++;
+}
+
+int main(void) {
+    next();
+    next();
+    return next() != 12;
+}
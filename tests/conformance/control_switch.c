@@ -0,0 +1,19 @@
+int days(int month) {
+    switch (month) {
+    case 2:
+        return 28;
+    case 4:
+    case 6:
+    case 9:
+    case 11:
+        return 30;
+    default:
+        return 31;
+    }
+}
+
+int main(void) {
+    int total = 0;
+    for (int m = 1; m <= 12; m++) total += days(m);
+    return total != 365;
+}
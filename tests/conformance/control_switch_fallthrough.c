@@ -0,0 +1,17 @@
+int main(void) {
+    int count = 0;
+    for (int i = 0; i < 4; i++) {
+        switch (i) {
+        case 0:
+            count += 1;
+        case 1:
+            count += 10;
+            break;
+        case 2:
+            count += 100;
+        default:
+            count += 1000;
+        }
+    }
+    return count != 1 + 10 + 10 + 100 + 1000 + 1000;
+}
@@ -0,0 +1,12 @@
+int main(void) {
+    int grid[3][4];
+    for (int i = 0; i < 3; i++)
+        for (int j = 0; j < 4; j++)
+            grid[i][j] = i * 4 + j;
+    if (grid[2][3] != 11) return 1;
+    int *flat = &grid[0][0];
+    if (flat[9] != 9) return 2;
+    int (*row)[4] = grid + 1;
+    if ((*row)[2] != 6) return 3;
+    return sizeof grid != 48;
+}
@@ -0,0 +1,14 @@
+int main(void) {
+    int arr[10];
+    for (int i = 0; i < 10; i++) arr[i] = i * i;
+    int *p = arr;
+    if (*(p + 3) != 9) return 1;
+    p += 5;
+    if (*p != 25) return 2;
+    if (p[-2] != 9) return 3;
+    if (p - arr != 5) return 4;
+    int *end = arr + 10;
+    int sum = 0;
+    for (int *q = arr; q < end; q++) sum += *q;
+    return sum != 285;
+}
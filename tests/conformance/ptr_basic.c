@@ -0,0 +1,10 @@
+int main(void) {
+    int x = 1;
+    int *p = &x;
+    *p = 42;
+    if (x != 42) return 1;
+    int **pp = &p;
+    **pp += 1;
+    if (x != 43) return 2;
+    return p != &x;
+}
@@ -0,0 +1,13 @@
+int sum(const int *p, int n) {
+    int total = 0;
+    for (int i = 0; i < n; i++) total += p[i];
+    return total;
+}
+
+int main(void) {
+    const int values[] = {1, 2, 3, 4};
+    int x = 5;
+    int *const fixed = &x;
+    *fixed = 6;
+    return sum(values, 4) + x != 16;
+}
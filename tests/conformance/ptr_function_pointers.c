@@ -0,0 +1,12 @@
+int add(int a, int b) { return a + b; }
+int sub(int a, int b) { return a - b; }
+int mul(int a, int b) { return a * b; }
+
+int apply(int (*f)(int, int), int a, int b) { return f(a, b); }
+
+int main(void) {
+    int (*ops[3])(int, int) = {add, sub, mul};
+    int acc = 0;
+    for (int i = 0; i < 3; i++) acc += apply(ops[i], 7, 3);
+    return acc != 10 + 4 + 21;
+}
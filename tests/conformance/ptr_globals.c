@@ -0,0 +1,12 @@
+int counter;
+int *counter_ptr = &counter;
+int table[] = {5, 6, 7};
+int *middle = &table[1];
+
+int main(void) {
+    *counter_ptr = 3;
+    if (counter != 3) return 1;
+    if (*middle != 6) return 2;
+    if (middle[1] != 7) return 3;
+    return 0;
+}
@@ -0,0 +1,15 @@
+struct node {
+    int value;
+    struct node *next;
+};
+
+int main(void) {
+    struct node nodes[5];
+    for (int i = 0; i < 5; i++) {
+        nodes[i].value = i + 1;
+        nodes[i].next = i < 4 ? &nodes[i + 1] : 0;
+    }
+    int sum = 0;
+    for (struct node *n = &nodes[0]; n; n = n->next) sum += n->value;
+    return sum != 15;
+}
@@ -0,0 +1,10 @@
+int main(void) {
+    long data[4] = {10, 20, 30, 40};
+    long *last = &data[3];
+    int i = -3;
+    if (last[i] != 10) return 1;
+    if (*(last - 1) != 30) return 2;
+    char buf[4] = "abc";
+    char *c = buf + 2;
+    return c[-2] != 'a';
+}
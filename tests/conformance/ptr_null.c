@@ -0,0 +1,13 @@
+int *find(int *arr, int n, int value) {
+    for (int i = 0; i < n; i++)
+        if (arr[i] == value) return &arr[i];
+    return 0;
+}
+
+int main(void) {
+    int arr[] = {3, 1, 4, 1, 5};
+    if (find(arr, 5, 9)) return 1;
+    int *p = find(arr, 5, 4);
+    if (!p || p - arr != 2) return 2;
+    return 0;
+}
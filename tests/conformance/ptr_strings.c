@@ -0,0 +1,24 @@
+unsigned long length(const char *s) {
+    const char *start = s;
+    while (*s) s++;
+    return s - start;
+}
+
+void reverse(char *s) {
+    char *end = s + length(s) - 1;
+    while (s < end) {
+        char t = *s;
+        *s++ = *end;
+        *end-- = t;
+    }
+}
+
+int main(void) {
+    char buf[] = "saltwater";
+    if (length(buf) != 9) return 1;
+    reverse(buf);
+    const char *expected = "retawtlas";
+    for (int i = 0; buf[i]; i++)
+        if (buf[i] != expected[i]) return 2;
+    return 0;
+}
@@ -0,0 +1,14 @@
+void fill(void *dst, unsigned char value, unsigned long n) {
+    unsigned char *p = dst;
+    while (n--) *p++ = value;
+}
+
+int main(void) {
+    int arr[4];
+    fill(arr, 0xff, sizeof arr);
+    for (int i = 0; i < 4; i++)
+        if (arr[i] != -1) return 1;
+    void *v = arr;
+    int *back = v;
+    return back != arr;
+}
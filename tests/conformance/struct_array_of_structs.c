@@ -0,0 +1,16 @@
+#include <stdio.h>
+
+struct item {
+    const char *name;
+    int weight;
+};
+
+int main(void) {
+    struct item items[] = {{"apple", 3}, {"bread", 5}, {"cheese", 2}};
+    int total = 0;
+    for (unsigned long i = 0; i < sizeof items / sizeof items[0]; i++) {
+        printf("%s: %d\n", items[i].name, items[i].weight);
+        total += items[i].weight;
+    }
+    return total != 10;
+}
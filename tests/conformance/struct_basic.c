@@ -0,0 +1,12 @@
+struct point {
+    int x, y;
+};
+
+int main(void) {
+    struct point p;
+    p.x = 3;
+    p.y = 4;
+    struct point *q = &p;
+    q->x *= 2;
+    return p.x * p.x + q->y * q->y != 52;
+}
@@ -0,0 +1,31 @@
+struct vec {
+    double x, y;
+};
+
+struct big {
+    int id;
+    char name[40];
+    long values[8];
+};
+
+double dot(struct vec a, struct vec b) { return a.x * b.x + a.y * b.y; }
+
+long total(struct big b) {
+    long sum = b.id;
+    for (int i = 0; i < 8; i++) sum += b.values[i];
+    b.values[0] = -1000; /* the caller's copy is unchanged */
+    return sum;
+}
+
+int main(void) {
+    struct vec a = {1.5, 2}, b = {4, -0.5};
+    if (dot(a, b) != 5) return 1;
+    struct big big = {7, "big", {1, 2, 3, 4, 5, 6, 7, 8}};
+    long sum = 0;
+    for (int i = 0; i < 4; i++) {
+        sum += total(big);
+        big.values[0] += 10;
+    }
+    if (sum != 4 * 43 + 10 + 20 + 30) return 2;
+    return big.values[0] != 41;
+}
@@ -0,0 +1,15 @@
+struct pair {
+    long a;
+    double b;
+};
+
+int main(void) {
+    struct pair x = {1, 2.5};
+    struct pair y;
+    y = x;
+    x.a = 10;
+    if (y.a != 1 || y.b != 2.5) return 1;
+    struct pair arr[3];
+    arr[2] = y;
+    return arr[2].b != 2.5;
+}
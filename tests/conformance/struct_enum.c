@@ -0,0 +1,20 @@
+enum color { RED, GREEN = 5, BLUE };
+
+struct shape {
+    enum color color;
+    int sides;
+};
+
+int main(void) {
+    struct shape s = {BLUE, 4};
+    if (s.color != 6) return 1;
+    switch (s.color) {
+    case RED:
+        return 2;
+    case GREEN:
+        return 3;
+    case BLUE:
+        break;
+    }
+    return 0;
+}
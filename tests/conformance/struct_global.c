@@ -0,0 +1,15 @@
+struct config {
+    int verbose;
+    int level;
+    const char *name;
+};
+
+struct config defaults = {0, 3, "default"};
+static struct config current;
+
+int main(void) {
+    current = defaults;
+    current.level++;
+    if (current.level != 4 || defaults.level != 3) return 1;
+    return current.name[0] != 'd';
+}
@@ -0,0 +1,17 @@
+struct rgb {
+    unsigned char r, g, b;
+};
+
+struct pixel {
+    int x, y;
+    struct rgb color;
+};
+
+int main(void) {
+    struct pixel p = {1, 2, {255, 128, 0}};
+    if (p.color.g != 128) return 1;
+    struct pixel q = {5};
+    if (q.y != 0 || q.color.r != 0) return 2;
+    struct rgb palette[2] = {{1, 2, 3}, {4, 5, 6}};
+    return palette[1].b != 6;
+}
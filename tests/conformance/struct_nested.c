@@ -0,0 +1,19 @@
+struct inner {
+    int values[3];
+};
+
+struct outer {
+    char tag;
+    struct inner in;
+    struct inner *link;
+};
+
+int main(void) {
+    struct outer o;
+    o.tag = 'x';
+    for (int i = 0; i < 3; i++) o.in.values[i] = i + 10;
+    o.link = &o.in;
+    if (o.link->values[2] != 12) return 1;
+    o.link->values[0] = 99;
+    return o.in.values[0] != 99;
+}
@@ -0,0 +1,12 @@
+struct counter {
+    int count;
+    int step;
+};
+
+void tick(struct counter *c) { c->count += c->step; }
+
+int main(void) {
+    struct counter c = {0, 3};
+    for (int i = 0; i < 5; i++) tick(&c);
+    return c.count != 15;
+}
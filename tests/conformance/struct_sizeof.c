@@ -0,0 +1,22 @@
+struct a {
+    char c;
+    int i;
+};
+
+struct b {
+    char c1;
+    double d;
+    char c2;
+};
+
+struct c {
+    short s;
+    char c;
+};
+
+int main(void) {
+    if (sizeof(struct a) != 8) return 1;
+    if (sizeof(struct b) != 24) return 2;
+    if (sizeof(struct c) != 4) return 3;
+    return 0;
+}
@@ -0,0 +1,16 @@
+struct stack {
+    int data[16];
+    int top;
+};
+
+void push(struct stack *s, int v) { s->data[s->top++] = v; }
+int pop(struct stack *s) { return s->data[--s->top]; }
+
+int main(void) {
+    struct stack s;
+    s.top = 0;
+    for (int i = 1; i <= 5; i++) push(&s, i);
+    int result = 0;
+    while (s.top) result = result * 10 + pop(&s);
+    return result != 54321;
+}
@@ -0,0 +1,13 @@
+typedef struct {
+    int w, h;
+} rect;
+
+typedef rect *rect_ptr;
+
+int area(rect_ptr r) { return r->w * r->h; }
+
+int main(void) {
+    rect r = {6, 7};
+    rect_ptr p = &r;
+    return area(p) != 42;
+}
@@ -0,0 +1,12 @@
+union value {
+    int i;
+    unsigned char bytes[4];
+};
+
+int main(void) {
+    union value v;
+    v.i = 0x01020304;
+    /* x86_64 is little-endian */
+    if (v.bytes[0] != 4 || v.bytes[3] != 1) return 1;
+    return sizeof(union value) != 4;
+}
@@ -0,0 +1,11 @@
+int square(int x) {
+    return x * x;
+}
+
+int main(void) {
+    int total = 0;
+    for (int i = 0; i < 3; i++) {
+        total += square(i);
+    }
+    return total == 5 ? 0 : 1;
+}
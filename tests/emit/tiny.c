@@ -0,0 +1,5 @@
+struct point { int x; } origin;
+
+int main(void) {
+    return origin.x;
+}
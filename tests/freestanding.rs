@@ -0,0 +1,151 @@
+//! Tests for `-ffreestanding` and linking without the C library.
+#![cfg(all(target_os = "linux", target_arch = "x86_64"))]
+
+extern crate env_logger;
+extern crate log;
+extern crate tempfile;
+
+use std::path::Path;
+use std::process::Command;
+
+use saltwater::{LinkOptions, Opt};
+use tempfile::TempPath;
+
+/// `sys_exit` for programs that don't have a C library
+const EXIT_STUB: &str = "
+    .globl sys_exit
+sys_exit:
+    mov $60, %eax
+    syscall
+";
+
+fn temp_path() -> TempPath {
+    tempfile::NamedTempFile::new()
+        .expect("cannot create tempfile")
+        .into_temp_path()
+}
+
+fn compile_freestanding(program: &str) -> TempPath {
+    let opt = Opt {
+        freestanding: true,
+        ..Default::default()
+    };
+    let module = saltwater::initialize_aot_module(program.to_owned(), opt.opt_level);
+    let module = saltwater::compile(module, program, opt)
+        .result
+        .unwrap_or_else(|err| panic!("failed to compile program '{}': {:?}", program, err));
+    let product = saltwater::finish_aot_module(module);
+    let obj = temp_path();
+    saltwater::assemble(product, &obj).expect("failed to assemble program");
+    obj
+}
+
+fn assemble_stub() -> TempPath {
+    let src = tempfile::Builder::new()
+        .suffix(".s")
+        .tempfile()
+        .expect("cannot create tempfile")
+        .into_temp_path();
+    std::fs::write(&src, EXIT_STUB).unwrap();
+    let obj = temp_path();
+    let status = Command::new("cc")
+        .args(["-c", "-x", "assembler"])
+        .arg(&src)
+        .arg("-o")
+        .arg(&obj)
+        .status()
+        .expect("failed to run cc");
+    assert!(status.success(), "failed to assemble stub");
+    obj
+}
+
+fn link_freestanding(obj: &Path, entry: &str) -> TempPath {
+    let stub = assemble_stub();
+    let options = LinkOptions {
+        no_std_lib: true,
+        entry: Some(entry.into()),
+        objects: vec![stub.to_path_buf()],
+        ..Default::default()
+    };
+    let output = temp_path();
+    saltwater::link_with(obj, &output, &options).expect("failed to link program");
+    output
+}
+
+fn run(program: &Path) -> i32 {
+    Command::new(program)
+        .status()
+        .expect("failed to run program")
+        .code()
+        .expect("program should exit normally")
+}
+
+#[test]
+fn custom_entry_point() {
+    let _ = env_logger::try_init();
+    let program = "
+        #include <stddef.h>
+        #include <stdint.h>
+        void sys_exit(int code);
+        void my_entry(void) {
+            size_t size = sizeof(uint32_t);
+            sys_exit(__STDC_HOSTED__ == 0 && size == 4 ? 42 : 1);
+        }
+    ";
+    let obj = compile_freestanding(program);
+    assert_eq!(run(&link_freestanding(&obj, "my_entry")), 42);
+}
+
+#[test]
+fn struct_copy_without_libc() {
+    let _ = env_logger::try_init();
+    // large enough that cranelift would call `memmove` in a hosted environment
+    let program = "
+        #include <stdint.h>
+        struct big {
+            uint64_t a[16];
+            char tail[3];
+        };
+        void sys_exit(int code);
+        void _start(void) {
+            struct big x, y;
+            for (int i = 0; i < 16; i++) {
+                x.a[i] = i;
+            }
+            x.tail[2] = 7;
+            y = x;
+            x = x;
+            uint64_t sum = 0;
+            for (int i = 0; i < 16; i++) {
+                sum += y.a[i];
+            }
+            sys_exit(sum == 120 && y.tail[2] == 7 && x.a[15] == 15 ? 0 : 1);
+        }
+    ";
+    let obj = compile_freestanding(program);
+    let undefined = Command::new("nm")
+        .arg("--undefined-only")
+        .arg(&obj)
+        .output()
+        .expect("failed to run nm");
+    assert_eq!(
+        String::from_utf8_lossy(&undefined.stdout).trim(),
+        "U sys_exit"
+    );
+    assert_eq!(run(&link_freestanding(&obj, "_start")), 0);
+}
+
+#[test]
+fn main_is_not_special() {
+    let _ = env_logger::try_init();
+    // in a hosted environment, this would warn about the signature
+    let program = "void main(char c) {}";
+    let opt = Opt {
+        freestanding: true,
+        ..Default::default()
+    };
+    let arena = saltwater::hir::Arena::new();
+    let program = saltwater::check_semantics(program, opt, &arena);
+    assert!(program.result.is_ok());
+    assert!(program.warnings.is_empty(), "{:?}", program.warnings);
+}
@@ -12,3 +12,58 @@ fn jit_readme() -> Result<(), Box<dyn std::error::Error>> {
     assert_eq!(code, Some(6));
     Ok(())
 }
+
+#[test]
+fn jit_constructors() -> Result<(), Box<dyn std::error::Error>> {
+    let _ = env_logger::try_init();
+    let program = "
+        int value;
+        __attribute__((constructor)) static void second(void) { value = value * 10 + 2; }
+        __attribute__((constructor(101))) static void first(void) { value = 1; }
+        int main(void) { return value; }
+    ";
+    let Program { result: jit, .. } = JIT::from_string(program, Opt::default());
+    let code = unsafe { jit?.run_main() };
+    assert_eq!(code, Some(12));
+    Ok(())
+}
+
+#[test]
+fn repl_session() -> Result<(), Box<dyn std::error::Error>> {
+    use saltwater::repl::{Repl, Response};
+
+    let _ = env_logger::try_init();
+    let mut repl = Repl::new(Opt::default());
+    let mut eval = |input: &str| unsafe { repl.eval(input) }.map_err(|err| err.data.to_string());
+    assert_eq!(eval("int sq(int x) { return x * x; }")?, Response::Done);
+    assert_eq!(eval("sq(3)")?, Response::Print("=> (int) 9".into()));
+    assert_eq!(eval("int total = 0;")?, Response::Done);
+    assert_eq!(
+        eval("for (int i = 1; i <= 4; i++) total += sq(i);")?,
+        Response::Done
+    );
+    assert_eq!(eval("total")?, Response::Print("=> (int) 30".into()));
+    assert_eq!(eval(":type total * 1.0")?, Response::Print("double".into()));
+    assert!(eval("undeclared").is_err());
+    assert_eq!(eval(":quit")?, Response::Quit);
+    Ok(())
+}
+
+#[test]
+fn repl_double() -> Result<(), Box<dyn std::error::Error>> {
+    use saltwater::repl::{Repl, Response};
+
+    let mut repl = Repl::new(Opt::default());
+    let mut eval = |input: &str| unsafe { repl.eval(input) }.map_err(|err| err.data.to_string());
+    assert_eq!(eval("1.5 * 2")?, Response::Print("=> (double) 3.0".into()));
+    assert_eq!(eval("double half = 0.5;")?, Response::Done);
+    assert_eq!(
+        eval("-half / 4")?,
+        Response::Print("=> (double) -0.125".into())
+    );
+    assert_eq!(
+        eval("(float)half")?,
+        Response::Print("=> (float) 0.5".into())
+    );
+    Ok(())
+}
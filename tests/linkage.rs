@@ -0,0 +1,429 @@
+//! Tests for the symbols, sections, and relocations in the object file,
+//! like `__attribute__((weak))`, `__attribute__((alias))` and `__attribute__((constructor))`,
+//! and for calling functions compiled by the system C compiler.
+#![cfg(all(target_os = "linux", target_arch = "x86_64"))]
+
+extern crate env_logger;
+extern crate log;
+extern crate tempfile;
+
+use std::path::Path;
+use std::process::Command;
+
+use cranelift_object::ObjectBackend;
+use saltwater::{CompiledModule, LinkOptions, Opt};
+use tempfile::TempPath;
+
+fn temp_path() -> TempPath {
+    tempfile::NamedTempFile::new()
+        .expect("cannot create tempfile")
+        .into_temp_path()
+}
+
+fn compile_module(program: &str) -> CompiledModule<ObjectBackend> {
+    let opt = Opt::default();
+    let module = saltwater::initialize_aot_module(program.to_owned(), opt.opt_level);
+    saltwater::compile(module, program, opt)
+        .result
+        .unwrap_or_else(|err| panic!("failed to compile program '{}': {:?}", program, err))
+}
+
+fn assemble(module: CompiledModule<ObjectBackend>) -> TempPath {
+    let obj = temp_path();
+    saltwater::assemble(saltwater::finish_aot_module(module), &obj)
+        .expect("failed to assemble program");
+    obj
+}
+
+fn compile_object(program: &str) -> TempPath {
+    assemble(compile_module(program))
+}
+
+/// The type and address `nm` shows for `name` in `obj`, e.g. `("W", Some(0))`.
+fn nm(obj: &Path, name: &str) -> (String, Option<u64>) {
+    let output = Command::new("nm")
+        .arg(obj)
+        .output()
+        .expect("failed to run nm");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    for line in stdout.lines() {
+        // undefined symbols don't have an address
+        let fields: Vec<_> = line.split_whitespace().collect();
+        match fields.as_slice() {
+            [address, kind, symbol] if *symbol == name => {
+                let address = u64::from_str_radix(address, 16).expect("invalid address");
+                return ((*kind).to_owned(), Some(address));
+            }
+            [kind, symbol] if *symbol == name => return ((*kind).to_owned(), None),
+            _ => {}
+        }
+    }
+    panic!("`{}` is not in the object file:\n{}", name, stdout);
+}
+
+fn link_and_run(objects: &[&Path]) -> i32 {
+    let options = LinkOptions {
+        objects: objects[1..].iter().map(|obj| obj.to_path_buf()).collect(),
+        ..Default::default()
+    };
+    let output = temp_path();
+    saltwater::link_with(objects[0], &output, &options).expect("failed to link program");
+    Command::new(&output)
+        .status()
+        .expect("failed to run program")
+        .code()
+        .expect("program should exit normally")
+}
+
+#[test]
+fn weak_symbols() {
+    let _ = env_logger::try_init();
+    let obj = compile_object(
+        "
+        int defined(void) __attribute__((weak));
+        int defined(void) { return 1; }
+        __attribute__((weak)) int data = 1;
+        int strong(void) { return defined() + data; }
+    ",
+    );
+    assert_eq!(nm(&obj, "defined").0, "W");
+    assert_eq!(nm(&obj, "data").0, "V");
+    assert_eq!(nm(&obj, "strong").0, "T");
+}
+
+#[test]
+fn aliases() {
+    let _ = env_logger::try_init();
+    let obj = compile_object(
+        "
+        int alias(void) __attribute__((alias(\"target\")));
+        static int local(void) __attribute__((alias(\"target\")));
+        int weak_alias(void) __attribute__((weak, alias(\"target\")));
+        int target(void) { return local(); }
+        int data = 1;
+        extern int data_alias __attribute__((alias(\"data\")));
+    ",
+    );
+    let (kind, target) = nm(&obj, "target");
+    assert_eq!(kind, "T");
+    assert_eq!(nm(&obj, "alias"), ("T".to_owned(), target));
+    assert_eq!(nm(&obj, "local"), ("t".to_owned(), target));
+    assert_eq!(nm(&obj, "weak_alias"), ("W".to_owned(), target));
+    let (kind, data) = nm(&obj, "data");
+    assert_eq!(kind, "D");
+    assert_eq!(nm(&obj, "data_alias"), ("D".to_owned(), data));
+}
+
+#[test]
+fn strong_definition_overrides_weak() {
+    let _ = env_logger::try_init();
+    let main = compile_object(
+        "
+        int value(void) __attribute__((weak));
+        int value(void) { return 1; }
+        int get_value(void) __attribute__((alias(\"value\")));
+        int main(void) { return get_value() + value(); }
+    ",
+    );
+    assert_eq!(link_and_run(&[&main]), 2);
+    let strong = compile_object("int value(void) { return 21; }");
+    // the alias still refers to the weak definition
+    assert_eq!(link_and_run(&[&main, &strong]), 22);
+}
+
+#[test]
+fn undefined_weak_references() {
+    let _ = env_logger::try_init();
+    let main = compile_object(
+        "
+        int f(void) __attribute__((weak));
+        extern int data __attribute__((weak));
+        int main(void) { return (f ? f() : 40) + (&data ? data : 2); }
+    ",
+    );
+    assert_eq!(nm(&main, "f").0, "w");
+    assert_eq!(nm(&main, "data").0, "w");
+    // both resolve to null
+    assert_eq!(link_and_run(&[&main]), 42);
+    let strong = compile_object("int f(void) { return 1; } int data = 2;");
+    assert_eq!(link_and_run(&[&main, &strong]), 3);
+}
+
+#[test]
+fn modules_finish_independently() {
+    let _ = env_logger::try_init();
+    let first = compile_module(
+        "
+        int target(void) { return 1; }
+        int alias(void) __attribute__((alias(\"target\")));
+    ",
+    );
+    let second = compile_module(
+        "
+        int other(void) { return 2; }
+        int other_alias(void) __attribute__((alias(\"other\")));
+    ",
+    );
+    let second = assemble(second);
+    let first = assemble(first);
+    let (_, target) = nm(&first, "target");
+    assert_eq!(nm(&first, "alias"), ("T".to_owned(), target));
+    let (_, other) = nm(&second, "other");
+    assert_eq!(nm(&second, "other_alias"), ("T".to_owned(), other));
+}
+
+#[test]
+fn constructor_sections() {
+    let _ = env_logger::try_init();
+    let obj = compile_object(
+        "
+        __attribute__((constructor)) void init(void) {}
+        __attribute__((constructor(101))) void early_init(void) {}
+        __attribute__((destructor)) void fini(void) {}
+    ",
+    );
+    let output = Command::new("objdump")
+        .arg("-h")
+        .arg(&*obj)
+        .output()
+        .expect("failed to run objdump");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let sections: Vec<_> = stdout
+        .lines()
+        .filter_map(|line| line.split_whitespace().nth(1))
+        .collect();
+    for name in &[".init_array", ".init_array.00101", ".fini_array"] {
+        assert!(sections.contains(name), "missing {}:\n{}", name, stdout);
+    }
+}
+
+#[test]
+fn constructors_stay_with_their_module() {
+    let _ = env_logger::try_init();
+    let first = compile_module(
+        "
+        int x;
+        __attribute__((constructor)) void init(void) { x = 5; }
+        int main(void) { return x; }
+    ",
+    );
+    let second = compile_module("__attribute__((destructor)) void fini(void) {}");
+    let second = assemble(second);
+    let first = assemble(first);
+    assert_eq!(link_and_run(&[&first]), 5);
+    assert_eq!(link_and_run(&[&first, &second]), 5);
+}
+
+#[test]
+fn extern_data_references() {
+    let _ = env_logger::try_init();
+    let main = compile_object(
+        "
+        extern int value, arr[];
+        int *pvalue = &value;
+        int *mid = &arr[2];
+        int main(void) { return *pvalue + *mid; }
+    ",
+    );
+    let data = compile_object("int value = 5; int arr[4] = {1, 2, 30, 4};");
+    assert_eq!(link_and_run(&[&main, &data]), 35);
+}
+
+#[test]
+fn shared_library_data_relocations() {
+    let _ = env_logger::try_init();
+    let lib = compile_object(
+        "
+        static int add(int x) { return x + 1; }
+        static int sub(int x) { return x - 1; }
+        int (*ops[])(int) = { add, sub };
+        static char message[] = \"hello\";
+        char *tail = message + 3;
+        int value = 7;
+        int *pvalue = &value;
+    ",
+    );
+    let dir = tempfile::tempdir().expect("cannot create tempdir");
+    let shared = dir.path().join("libdata.so");
+    // this fails if the data has relocations that aren't position-independent
+    let status = Command::new("cc")
+        .arg("-shared")
+        .arg(&*lib)
+        .arg("-o")
+        .arg(&shared)
+        .status()
+        .expect("failed to run cc");
+    assert!(status.success(), "failed to link shared library");
+    let main = compile_object(
+        "
+        extern int (*ops[])(int);
+        extern char *tail;
+        extern int *pvalue;
+        int main(void) { return ops[0](1) + ops[1](10) + (*tail == 'l') + *pvalue; }
+    ",
+    );
+    let output = temp_path();
+    let status = Command::new("cc")
+        .arg(&*main)
+        .arg(&shared)
+        .arg(format!("-Wl,-rpath,{}", dir.path().display()))
+        .arg("-o")
+        .arg(&output)
+        .status()
+        .expect("failed to run cc");
+    assert!(status.success(), "failed to link program");
+    let code = Command::new(&output)
+        .status()
+        .expect("failed to run program")
+        .code();
+    assert_eq!(code, Some(19));
+}
+
+/// Compile `program` with the system C compiler.
+fn cc_object(program: &str) -> TempPath {
+    let dir = tempfile::tempdir().expect("cannot create tempdir");
+    let source = dir.path().join("program.c");
+    std::fs::write(&source, program).expect("cannot write program");
+    let obj = temp_path();
+    let status = Command::new("cc")
+        .arg("-c")
+        .arg(&source)
+        .arg("-o")
+        .arg(&obj)
+        .status()
+        .expect("failed to run cc");
+    assert!(status.success(), "cc failed to compile program");
+    obj
+}
+
+const AGGREGATES: &str = "
+    struct ii { int a, b; };
+    struct ld { long l; double d; };
+    struct dd { double x, y; };
+    struct fff { float x, y, z; };
+    struct big { long a, b, c; };
+    struct c3 { char c[3]; };
+    union fi { int i; float f; };
+";
+
+/// Functions that take and return structs and unions by value,
+/// covering each way the System V ABI passes them.
+const CALLEES: &str = "
+    long sum_ii(struct ii s) { return s.a + s.b; }
+    double sum_ld(struct ld s) { return s.l + s.d; }
+    double sum_dd(struct dd s) { return s.x * s.y; }
+    float sum_fff(struct fff s) { return s.x + s.y + s.z; }
+    long sum_big(struct big s) { return s.a + s.b * 10 + s.c * 100; }
+    int sum_c3(struct c3 s) { return s.c[0] + s.c[1] + s.c[2]; }
+    /* `s` doesn't fit in the registers that are left, so it goes on the stack but `g` doesn't */
+    long int_spill(long a, long b, long c, long d, long e, struct ii s, long g) {
+        return a + b + c + d + e + s.a * 100 + s.b * 1000 + g * 10000;
+    }
+    double float_spill(double a, double b, double c, double d, double e, double f, double g,
+                       struct dd s, double h) {
+        return a + b + c + d + e + f + g + s.x * 100 + s.y * 1000 + h * 10000;
+    }
+    struct ii make_ii(int a, int b) { struct ii s = {a, b}; return s; }
+    struct ld make_ld(long l, double d) { struct ld s = {l, d}; return s; }
+    struct dd make_dd(double x, double y) { struct dd s = {x, y}; return s; }
+    struct fff make_fff(float x) { struct fff s = {x, x * 2, x * 3}; return s; }
+    struct big make_big(long a) { struct big s = {a, a + 1, a + 2}; return s; }
+    struct c3 make_c3(void) { struct c3 s = {{1, 2, 3}}; return s; }
+    union fi make_fi(float f) { union fi u; u.f = f; return u; }
+";
+
+/// Call each of `CALLEES` and return the number of the first one that gave the wrong answer.
+const CALLER: &str = "
+    long sum_ii(struct ii s);
+    double sum_ld(struct ld s);
+    double sum_dd(struct dd s);
+    float sum_fff(struct fff s);
+    long sum_big(struct big s);
+    int sum_c3(struct c3 s);
+    long int_spill(long a, long b, long c, long d, long e, struct ii s, long g);
+    double float_spill(double a, double b, double c, double d, double e, double f, double g,
+                       struct dd s, double h);
+    struct ii make_ii(int a, int b);
+    struct ld make_ld(long l, double d);
+    struct dd make_dd(double x, double y);
+    struct fff make_fff(float x);
+    struct big make_big(long a);
+    struct c3 make_c3(void);
+    union fi make_fi(float f);
+    int main(void) {
+        struct ii ii = {3, 4};
+        struct ld ld = {5, 0.5};
+        struct dd dd = {2, 3};
+        struct fff fff = {1.5, 2, 3};
+        struct big big = {1, 2, 3};
+        struct c3 c3 = {{10, 20, 30}};
+        if (sum_ii(ii) != 7) return 1;
+        if (sum_ld(ld) != 5.5) return 2;
+        if (sum_dd(dd) != 6) return 3;
+        if (sum_fff(fff) != 6.5) return 4;
+        if (sum_big(big) != 321) return 5;
+        if (sum_c3(c3) != 60) return 6;
+        if (int_spill(1, 2, 3, 4, 5, ii, 6) != 64315) return 7;
+        if (float_spill(1, 1, 1, 1, 1, 1, 1, dd, 9) != 93207) return 8;
+        ii = make_ii(7, 8);
+        if (ii.a != 7 || ii.b != 8) return 9;
+        ld = make_ld(9, 0.25);
+        if (ld.l != 9 || ld.d != 0.25) return 10;
+        dd = make_dd(2.5, 3.5);
+        if (dd.x != 2.5 || dd.y != 3.5) return 11;
+        fff = make_fff(1.5);
+        if (fff.x != 1.5 || fff.y != 3 || fff.z != 4.5) return 12;
+        big = make_big(100);
+        if (big.a != 100 || big.b != 101 || big.c != 102) return 13;
+        c3 = make_c3();
+        if (c3.c[0] != 1 || c3.c[1] != 2 || c3.c[2] != 3) return 14;
+        if (make_fi(2.5).f != 2.5) return 15;
+        return 0;
+    }
+";
+
+#[test]
+fn aggregates_are_passed_like_cc() {
+    let _ = env_logger::try_init();
+    let callees = format!("{}{}", AGGREGATES, CALLEES);
+    let caller = format!("{}{}", AGGREGATES, CALLER);
+    let (cc_callees, cc_caller) = (cc_object(&callees), cc_object(&caller));
+    let (callees, caller) = (compile_object(&callees), compile_object(&caller));
+    assert_eq!(link_and_run(&[&caller, &cc_callees]), 0);
+    assert_eq!(link_and_run(&[&cc_caller, &callees]), 0);
+    assert_eq!(link_and_run(&[&caller, &callees]), 0);
+}
+
+#[test]
+fn variadic_aggregates_are_passed_like_cc() {
+    let _ = env_logger::try_init();
+    // saltwater doesn't support `va_arg` yet, so only the caller is compiled with saltwater
+    let callee = cc_object(
+        "
+        #include <stdarg.h>
+        struct dd { double x, y; };
+        double sum_products(int n, ...) {
+            va_list ap;
+            double total = 0;
+            va_start(ap, n);
+            for (int i = 0; i < n; i++) {
+                struct dd s = va_arg(ap, struct dd);
+                total += s.x * s.y;
+            }
+            va_end(ap);
+            return total;
+        }
+    ",
+    );
+    let caller = compile_object(
+        "
+        struct dd { double x, y; };
+        double sum_products(int n, ...);
+        int main(void) {
+            struct dd a = {2, 3}, b = {4, 5};
+            return sum_products(2, a, b);
+        }
+    ",
+    );
+    assert_eq!(link_and_run(&[&caller, &callee]), 26);
+}
@@ -0,0 +1,288 @@
+//! Tests for optimizations which can't be observed with a runner test,
+//! because they need options other than the default.
+mod utils;
+
+extern crate env_logger;
+extern crate log;
+
+use saltwater::Opt;
+use std::process::Output;
+
+fn run_with(program: &str, opt: Opt) -> Output {
+    let output = utils::compile_with(program, opt, false)
+        .unwrap_or_else(|err| panic!("failed to compile program '{}': {}", program, err));
+    utils::run(&output, &[]).expect("failed to run program")
+}
+
+fn sibling_calls() -> Opt {
+    Opt {
+        optimize_sibling_calls: true,
+        ..Default::default()
+    }
+}
+
+const FACTORIAL: &str = "
+    int printf(const char *, ...);
+    unsigned long factorial(unsigned long n, unsigned long acc) {
+        if (n <= 1) {
+            return acc;
+        }
+        return factorial(n - 1, acc * n);
+    }
+    int main() {
+        printf(\"%lu\\n\", factorial(N, 1));
+    }
+";
+
+#[test]
+fn tail_call_same_result() {
+    let _ = env_logger::try_init();
+    let program = format!("#define N 20\n{}", FACTORIAL);
+    let unoptimized = run_with(&program, Opt::default());
+    let optimized = run_with(&program, sibling_calls());
+    assert!(unoptimized.status.success());
+    assert_eq!(optimized.stdout, unoptimized.stdout);
+    assert_eq!(optimized.stdout, b"2432902008176640000\n");
+}
+
+#[test]
+fn tail_call_does_not_overflow_stack() {
+    let _ = env_logger::try_init();
+    let program = format!("#define N 1000000\n{}", FACTORIAL);
+    let output = run_with(&program, sibling_calls());
+    assert!(output.status.success(), "{:?}", output);
+}
+
+#[test]
+fn tail_call_evaluates_arguments_first() {
+    let _ = env_logger::try_init();
+    // if `a` were overwritten before `b` was evaluated, this would return 0
+    let program = "
+        int swap(int a, int b, int times) {
+            if (times == 0) {
+                return a - b;
+            }
+            return swap(b, a, times - 1);
+        }
+        int main() {
+            return swap(2, 1, 3) == -1 ? 0 : 1;
+        }
+    ";
+    assert!(run_with(program, sibling_calls()).status.success());
+}
+
+#[test]
+fn tail_call_address_taken() {
+    let _ = env_logger::try_init();
+    // `p` points to the `n` of the first call, so this can't be turned into a loop
+    let program = "
+        int f(int n, int *p) {
+            if (p == 0) {
+                return f(n + 1, &n);
+            }
+            if (n == 3) {
+                return *p;
+            }
+            return f(n + 1, p);
+        }
+        int main() {
+            return f(1, 0) == 1 ? 0 : 1;
+        }
+    ";
+    assert!(run_with(program, sibling_calls()).status.success());
+}
+
+#[test]
+fn setjmp_volatile_local() {
+    let _ = env_logger::try_init();
+    let program = "
+        #include <setjmp.h>
+        jmp_buf env;
+        void jump(int n) {
+            longjmp(env, n);
+        }
+        int main() {
+            volatile int count = 0;
+            int r = setjmp(env);
+            if (r < 3) {
+                count++;
+                jump(r + 1);
+            }
+            return r == 3 && count == 3 ? 0 : 1;
+        }
+    ";
+    let opt = Opt {
+        opt_level: 2,
+        optimize_sibling_calls: true,
+        ..Default::default()
+    };
+    assert!(run_with(program, opt).status.success());
+}
+
+#[test]
+fn setjmp_disables_tail_calls() {
+    let _ = env_logger::try_init();
+    // if `count_down` reused its frame, the `n` seen after `longjmp` would be 0 instead of 3
+    let program = "
+        #include <setjmp.h>
+        jmp_buf env;
+        int count_down(int n, int jumped) {
+            if (n == 0) {
+                longjmp(env, 1);
+            }
+            if (!jumped && setjmp(env)) {
+                return n;
+            }
+            return count_down(n - 1, 1);
+        }
+        int main() {
+            return count_down(3, 0) == 3 ? 0 : 1;
+        }
+    ";
+    assert!(run_with(program, sibling_calls()).status.success());
+}
+
+fn o1() -> Opt {
+    Opt {
+        opt_level: 1,
+        ..Default::default()
+    }
+}
+
+/// The object file for `program`, compiled with `opt`.
+fn object(program: &str, opt: Opt) -> Vec<u8> {
+    // the module name is written to the object file, so it can't be the program
+    let module = saltwater::initialize_aot_module("object".to_owned(), opt.opt_level);
+    let module = saltwater::compile(module, program, opt)
+        .result
+        .unwrap_or_else(|err| panic!("failed to compile program '{}': {:?}", program, err));
+    saltwater::finish_aot_module(module)
+        .emit()
+        .expect("failed to emit object file")
+}
+
+/// The Cranelift IR for `program`, compiled with `opt`.
+fn clif(program: &str, opt: Opt) -> String {
+    let module = saltwater::initialize_aot_module("clif".to_owned(), opt.opt_level);
+    let (_, clif) = saltwater::compile_with_clif(module, program, opt)
+        .result
+        .unwrap_or_else(|err| panic!("failed to compile program '{}': {:?}", program, err));
+    clif
+}
+
+fn contains(haystack: &[u8], needle: &str) -> bool {
+    haystack
+        .windows(needle.len())
+        .any(|window| window == needle.as_bytes())
+}
+
+#[test]
+fn unused_static_removed() {
+    let _ = env_logger::try_init();
+    let program = "
+        static int unused_helper(int x) { return x * 2; }
+        static inline int used_helper(int x) { return x + 1; }
+        int main() { return used_helper(-1); }
+    ";
+    let unoptimized = object(program, Opt::default());
+    assert!(contains(&unoptimized, "unused_helper"));
+    let optimized = object(program, o1());
+    assert!(!contains(&optimized, "unused_helper"));
+    assert!(contains(&optimized, "used_helper"));
+    assert!(run_with(program, o1()).status.success());
+}
+
+#[test]
+fn constant_condition_removed() {
+    let _ = env_logger::try_init();
+    let program = "
+        int f(void) {
+            if (0) {
+                return 12345;
+            } else {
+                return 54321;
+            }
+        }
+        int main() { return f() == 54321 ? 0 : 1; }
+    ";
+    // 12345 is 0x3039 and 54321 is 0xd431
+    assert!(clif(program, Opt::default()).contains("0x3039"));
+    let optimized = clif(program, o1());
+    assert!(optimized.contains("0xd431"), "{}", optimized);
+    assert!(!optimized.contains("0x3039"), "{}", optimized);
+    assert!(run_with(program, o1()).status.success());
+}
+
+#[test]
+fn label_in_dead_code_kept() {
+    let _ = env_logger::try_init();
+    let program = "
+        int main() {
+            int x = 1;
+            if (x) goto inside;
+            if (0) {
+            inside:
+                x = 0;
+            }
+            return x;
+        }
+    ";
+    assert!(run_with(program, o1()).status.success());
+}
+
+#[test]
+fn restrict_reload_removed() {
+    let _ = env_logger::try_init();
+    let program = |qualifier: &str| {
+        format!(
+            "
+            void fill(int *{0} dst, const int *{0} src) {{
+                dst[0] = *src;
+                dst[1] = *src;
+                dst[2] = *src;
+            }}
+            int main() {{
+                int a[3], x = 7;
+                fill(a, &x);
+                return a[0] + a[1] + a[2] == 21 ? 0 : 1;
+            }}
+            ",
+            qualifier
+        )
+    };
+    let loads = |qualifier, opt| {
+        let clif = clif(&program(qualifier), opt);
+        // only count the loads in `fill`, which is compiled first
+        let fill = clif.split("\n}").next().unwrap().to_owned();
+        fill.matches("load.i32").count()
+    };
+    // without `restrict`, `*src` has to be reloaded after every store through `dst`
+    assert_eq!(loads("", o1()), 3);
+    assert_eq!(loads("restrict", Opt::default()), 3);
+    assert_eq!(loads("restrict", o1()), 1);
+    assert!(run_with(&program("restrict"), o1()).status.success());
+}
+
+#[test]
+fn volatile_member_read_kept() {
+    let _ = env_logger::try_init();
+    let program = |qualifier: &str| {
+        format!(
+            "
+            struct s {{ int x; int y; }};
+            {} struct s v;
+            int main() {{
+                v.x;
+                v.y, 0;
+                return 0;
+            }}
+            ",
+            qualifier
+        )
+    };
+    let loads = |qualifier| clif(&program(qualifier), o1()).matches("load.i32").count();
+    // the values are unused, but reading a `volatile` object is a side effect
+    assert_eq!(loads(""), 0);
+    assert_eq!(loads("volatile"), 2);
+    assert!(run_with(&program("volatile"), o1()).status.success());
+}
@@ -0,0 +1,3 @@
+// code: 3
+struct { char c; int i; long l; } s = { 1, 2, 3 };
+int main() { return s.c == 1 && s.i == 2 ? s.l : 0; }
@@ -0,0 +1,16 @@
+// code: 18
+int sum(int n, const int a[n]);
+int sum(int n, const int *a) {
+    int total = 0;
+    for (int i = 0; i < n; i++) {
+        total += a[i];
+    }
+    return total;
+}
+int pointer_size(int a[100]) {
+    return (int)sizeof(a) / (int)sizeof(int *);
+}
+int main(void) {
+    int a[4] = {2, 4, 5, 6};
+    return sum(4, a) + pointer_size(a);
+}
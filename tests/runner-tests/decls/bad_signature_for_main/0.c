@@ -1,2 +1,3 @@
-// fail
-int main(int);
+// succeeds
+// nonstandard, but only a warning
+int main(int argc) { return 0; }
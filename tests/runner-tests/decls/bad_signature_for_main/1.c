@@ -1,2 +1,3 @@
-// fail
-int main(char **);
+// succeeds
+// nonstandard, but only a warning
+int main(char **argv) { return 0; }
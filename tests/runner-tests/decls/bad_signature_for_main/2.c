@@ -1,2 +1,3 @@
-// fail
+// compile
+// nonstandard, but only a warning
 int main(int, char **, ...);
@@ -0,0 +1,3 @@
+// compile
+// nonstandard, but only a warning; the exit status is unspecified
+void main() {}
@@ -0,0 +1,4 @@
+// succeeds
+int main(int argc, char **argv, char **envp) {
+    return envp == 0;
+}
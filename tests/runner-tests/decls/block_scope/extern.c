@@ -0,0 +1,12 @@
+// code: 11
+int g = 7;
+int main(void) {
+    {
+        extern int g;
+        extern int g;
+        g++;
+    }
+    extern int h;
+    return g + h;
+}
+int h = 3;
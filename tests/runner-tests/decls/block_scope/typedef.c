@@ -0,0 +1,10 @@
+// code: 5
+typedef int I;
+typedef int I;
+int main(void) {
+    typedef long L;
+    typedef long L;
+    L l = 2;
+    I I = 3;
+    return l + I;
+}
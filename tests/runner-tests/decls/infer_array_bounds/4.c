@@ -0,0 +1,12 @@
+// code: 0
+// the bound of a local array comes from its initializer too (6.7.9p22)
+int global[] = {1, 2, 3, 4};
+
+int main() {
+    int local[] = {9, -2, 7};
+    char s[] = "hi\tthere";
+    if (sizeof global != 16) return 1;
+    if (sizeof local / sizeof local[0] != 3) return 2;
+    if (sizeof s != 9 || s[2] != '\t') return 3;
+    return 0;
+}
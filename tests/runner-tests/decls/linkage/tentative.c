@@ -0,0 +1,10 @@
+// code: 3
+// `int i;` is only defined as zero if nothing else defines it
+int i;
+int i = 1;
+static int j;
+int get_j(void) { return j; }
+static int j = 2;
+int k = 0;
+int k;
+int main(void) { return i + get_j() + k; }
@@ -0,0 +1,26 @@
+// code: 0
+struct s { char c; int i; long l; };
+union u { int i; char c[8]; };
+int main(void) {
+    int a[4] = {1, 2};
+    struct s x = {'a', 2};
+    struct s y = x;
+    union u u = {0x01020304};
+    char str[4] = "abc";
+    char short_str[3] = "abc";
+    int m[2][3] = {{1, 2, 3}, {4}};
+    int flat[2][2] = {1, 2, 3};
+    int scalar = {5};
+    if (a[0] != 1 || a[1] != 2 || a[2] != 0 || a[3] != 0) return 1;
+    if (x.c != 'a' || x.i != 2 || x.l != 0) return 2;
+    if (y.c != 'a' || y.i != 2 || y.l != 0) return 3;
+    if (u.i != 0x01020304) return 4;
+    if (str[0] != 'a' || str[2] != 'c' || str[3] != 0) return 5;
+    if (short_str[2] != 'c') return 6;
+    if (m[0][2] != 3 || m[1][0] != 4 || m[1][2] != 0) return 7;
+    if (flat[1][0] != 3 || flat[1][1] != 0) return 8;
+    struct s arr[2] = {{1, 2, 3}, {4}};
+    if (arr[0].l != 3 || arr[1].c != 4 || arr[1].i != 0) return 9;
+    if (scalar != 5) return 10;
+    return 0;
+}
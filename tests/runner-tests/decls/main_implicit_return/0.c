@@ -0,0 +1,7 @@
+// succeeds
+int f() {
+    return 42;
+}
+int main() {
+    f();
+}
@@ -0,0 +1,6 @@
+// succeeds
+int main(int argc) {
+    if (argc > 100) {
+        return 1;
+    }
+}
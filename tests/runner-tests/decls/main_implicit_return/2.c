@@ -0,0 +1,4 @@
+// compile
+// `main` with internal linkage isn't the program's entry point,
+// so falling off the end is warned about instead of returning 0
+static int main() {}
@@ -0,0 +1,11 @@
+// code: 7
+struct s { int x, y; };
+void bump(int *p) { *p += 100; }
+int derived(int *restrict p) { int *q = p; int x = *p; *q = 5; return x + *p; }
+int called(int *restrict p) { int x = *p; bump(p); return x + *p; }
+int members(struct s *restrict s, int *restrict o) { int a = s->y; *o = 3; s->x = 4; return a + s->y + s->x + *o; }
+int main(void) {
+    int v = 1, w = 2, o = 0;
+    struct s st = {10, 20};
+    return (derived(&v) == 6) + (called(&w) == 104) * 2 + (members(&st, &o) == 47) * 4;
+}
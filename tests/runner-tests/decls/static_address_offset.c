@@ -0,0 +1,7 @@
+// code: 3
+int a[] = {1, 2, 3, 4};
+int *p = a + 2;
+int *q = &a[3];
+struct s { int i, j; } my_s = {1, 3};
+int *r = &my_s.j;
+int main(void) { return *p + (*q - 4) + (*r - 3); }
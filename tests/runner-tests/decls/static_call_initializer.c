@@ -0,0 +1,3 @@
+// compile-fail
+int rand(void);
+static int x = sizeof(int) + rand();
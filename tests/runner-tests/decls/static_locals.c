@@ -0,0 +1,28 @@
+// code: 0
+// `static` variables inside functions keep their value between calls
+int g = 5;
+
+int counter() {
+    static int n = 10;
+    return n++;
+}
+
+int other_counter() {
+    static int n;
+    return n++;
+}
+
+const char *name() {
+    static const char *s = "saltwater";
+    static int *p = &g;
+    return s + *p - 5;
+}
+
+int main() {
+    counter();
+    counter();
+    if (counter() != 12) return 1;
+    if (other_counter() != 0 || other_counter() != 1) return 2;
+    if (name()[0] != 's') return 3;
+    return 0;
+}
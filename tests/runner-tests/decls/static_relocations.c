@@ -0,0 +1,31 @@
+// code: 0
+// static data that holds the addresses of other globals, including ones defined later
+int twice(int);
+static int add(int x) { return x + 1; }
+static int sub(int x) { return x - 1; }
+static int (*ops[])(int) = { add, sub, twice };
+
+static char message[] = "hello world";
+static char *word = message + 6;
+static int arr[5] = {1, 2, 3, 4, 5};
+static int *mid = &arr[3];
+
+extern int later;
+int *plater = &later;
+
+// linked static structures, using a tentative definition of `b`
+struct link { struct link *next; int value; };
+static struct link b;
+static struct link a = { &b, 1 };
+static struct link b = { &a, 2 };
+
+int twice(int x) { return x * 2; }
+int later = 9;
+
+int main(void) {
+    if (ops[0](1) != 2 || ops[1](1) != 0 || ops[2](3) != 6) return 1;
+    if (*word != 'w' || *mid != 4 || mid[-3] != 1) return 2;
+    if (*plater != 9) return 3;
+    if (a.next->next != &a || a.next->value != 2 || b.next->value != 1) return 4;
+    return 0;
+}
@@ -0,0 +1,21 @@
+// code: 127
+#include <stddef.h>
+struct s { char a; short b; int c; double d; };
+static size_t n = sizeof(struct s) * 4;
+static int tab[sizeof(long)] = {0, 1, 2, 3, 4, 5, 6, 7};
+static size_t count = sizeof(tab) / sizeof(tab[0]);
+static char buf[10] = "abcdefghi";
+static char *mid = buf + sizeof(buf) / 2;
+static char *last = &buf[sizeof(buf) - 2];
+static int *tp = &tab[sizeof(tab) / sizeof(tab[0]) - 1];
+static int offsets[] = { offsetof(struct s, a), offsetof(struct s, b), offsetof(struct s, c), offsetof(struct s, d) };
+static size_t d_offset = offsetof(struct s, d);
+int main(void) {
+    return (n == 64)
+        + (count == 8) * 2
+        + (*mid == 'f') * 4
+        + (*last == 'i') * 8
+        + (*tp == 7) * 16
+        + (offsets[0] == 0 && offsets[1] == 2 && offsets[2] == 4 && offsets[3] == 8) * 32
+        + (d_offset == 8) * 64;
+}
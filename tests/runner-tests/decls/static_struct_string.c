@@ -0,0 +1,17 @@
+// code: 0
+// string literals inside static aggregates are stored as pointers to the string
+struct config {
+    int level;
+    const char *name;
+};
+
+struct config defaults = {3, "default"};
+const char *names[] = {"a", "bc"};
+
+int main() {
+    static struct config local = {1, "local"};
+    if (defaults.name[0] != 'd' || defaults.name[6] != 't') return 1;
+    if (names[1][1] != 'c') return 2;
+    if (local.name[4] != 'l') return 3;
+    return 0;
+}
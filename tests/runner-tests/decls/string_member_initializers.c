@@ -0,0 +1,20 @@
+// code: 0
+// string literals can initialize character arrays inside a struct or array initializer
+struct named {
+    int id;
+    char name[8];
+    long values[2];
+};
+
+struct named global = {1, "global", {2, 3}};
+struct named globals[2] = {{1, "a"}, {2, "bc", {5}}};
+
+int main() {
+    struct named local = {7, "local", {1, 2}};
+    char grid[][3] = {"xy", "z"};
+    if (global.name[5] != 'l' || global.values[1] != 3) return 1;
+    if (globals[1].name[1] != 'c' || globals[1].values[0] != 5) return 2;
+    if (local.name[4] != 'l' || local.name[5] != 0 || local.values[1] != 2) return 3;
+    if (sizeof grid != 6 || grid[1][0] != 'z') return 4;
+    return 0;
+}
@@ -0,0 +1,6 @@
+// compile-fail
+// a wide string literal can't initialize a plain character array
+struct named {
+    char name[4];
+};
+struct named n = { L"abc" };
@@ -0,0 +1,2 @@
+// fail
+char s[2] = "abc";
@@ -1,4 +1,4 @@
-// errors: 8
+// errors: 5
 void i = {1};
 void i = 1;
 int a[](*fp)() = {0};
@@ -0,0 +1,3 @@
+// code: 5
+union { int i; long l; } u = { 5 };
+int main() { return u.l; }
@@ -0,0 +1,16 @@
+// code: 0
+// prefixed string literals can initialize arrays of the matching element type inside an initializer
+typedef int wchar_t;
+struct named {
+    char name[4];
+    wchar_t wide[4];
+};
+
+struct named global = { "abc", L"xyz" };
+
+int main() {
+    struct named local = { "de", L"fg" };
+    if (global.wide[2] != 'z' || global.wide[3] != 0) return 1;
+    if (local.wide[1] != 'g' || local.name[1] != 'e') return 2;
+    return 0;
+}
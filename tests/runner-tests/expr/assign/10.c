@@ -1,4 +1,5 @@
-// code: 5
+// compile-fail
+// the result of an assignment isn't an lvalue, so neither are its members (6.5.2.3p3)
 struct S {
     int x;
 };
@@ -0,0 +1,5 @@
+// compile-fail
+int main(void) {
+    const int arr[3] = {1, 2, 3};
+    arr[1]++;
+}
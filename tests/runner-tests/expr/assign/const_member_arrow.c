@@ -0,0 +1,6 @@
+// compile-fail
+struct s { const int x; };
+int main(void) {
+    struct s s = {0}, *p = &s;
+    p->x = 1;
+}
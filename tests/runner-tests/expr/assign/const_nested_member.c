@@ -0,0 +1,7 @@
+// compile-fail
+struct inner { int m; const int c; };
+struct outer { struct inner in; };
+int main(void) {
+    struct outer a = {{0, 0}}, b = {{1, 2}};
+    a = b;
+}
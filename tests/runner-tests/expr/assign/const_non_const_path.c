@@ -0,0 +1,14 @@
+// code: 16
+struct s { int x; const int *p; };
+int main(void) {
+    int i = 1;
+    const int *p = &i;
+    int *const q = &i;
+    *q += 2;
+    p = q;
+    struct s s = {0, &i};
+    const struct s *sp = &s;
+    s.x = *sp->p + 1;
+    i *= 2;
+    return *p + s.x + *q;
+}
@@ -0,0 +1,6 @@
+// compile-fail
+int main(void) {
+    int i = 0;
+    const int *p = &i;
+    *p = 3;
+}
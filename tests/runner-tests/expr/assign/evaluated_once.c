@@ -0,0 +1,11 @@
+// code: 33
+int calls, x;
+int *f() {
+    calls++;
+    return &x;
+}
+int main() {
+    // `f()` is called exactly once per compound assignment
+    while ((*f() += 1) < 3);
+    return calls * 10 + x;
+}
@@ -0,0 +1,22 @@
+// code: 0
+// `p->next->count++` and `(*f())[i]--` compute the object they modify once, and yield its old value
+struct node {
+    struct node *next;
+    int count;
+};
+int calls;
+int arr[3] = {5, 6, 7};
+int (*f(void))[3] {
+    calls++;
+    return &arr;
+}
+int main(void) {
+    struct node second = {0, 10}, first = {&second, 0};
+    struct node *p = &first;
+    int old = p->next->count++;
+    if (old != 10 || second.count != 11 || first.count != 0) return 1;
+    int i = 1;
+    int old2 = (*f())[i]--;
+    if (old2 != 6 || arr[1] != 5 || calls != 1) return 2;
+    return 0;
+}
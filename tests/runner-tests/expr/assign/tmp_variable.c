@@ -0,0 +1,12 @@
+// code: 19
+int main() {
+    int tmp = 5, x = 1;
+    {
+        int *tmp = &x;
+        *tmp += 2;
+        (*tmp)++;
+    }
+    tmp += x;
+    int old = tmp++;
+    return old + tmp;
+}
@@ -0,0 +1,3 @@
+// code: 7
+int a[~0 & 7];
+int main() { return sizeof(a) / sizeof(a[0]); }
@@ -0,0 +1,15 @@
+// code: 5
+int f(int x) { return x; }
+int main(void) {
+    int a[4];
+    int *p = a;
+    int (*fp)(int) = f;
+    int r = 0;
+    r += a == p;
+    r += a < a + 2;
+    r += f == fp;
+    r += fp != f;
+    r += a + 3 >= p;
+    r += &a[2] < p;
+    return r + (f != 0);
+}
@@ -0,0 +1,5 @@
+// code: 20
+int main(void) {
+    _Bool b = 1;
+    return sizeof(1 < 2) + sizeof(!b) + sizeof(b && b) + sizeof(b == b) + sizeof(b + b);
+}
@@ -0,0 +1,10 @@
+// code: 3
+struct s { int i; } outer;
+int main(void) {
+    struct s inner = { 3 };
+    {
+        struct s nested = inner;
+        outer = nested;
+    }
+    return outer.i;
+}
@@ -0,0 +1,8 @@
+// compile-fail
+// the inner `struct s` is a different type, even though it looks the same
+struct s { int i; } outer;
+int main(void) {
+    struct s { int i; } inner = { 1 };
+    outer = inner;
+    return outer.i;
+}
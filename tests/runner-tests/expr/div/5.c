@@ -0,0 +1,6 @@
+// signal: 8
+// division by a runtime zero traps with SIGFPE
+int main() {
+    int zero = 0;
+    return 1 / zero;
+}
@@ -0,0 +1,5 @@
+// signal: 8
+int main() {
+    int min = -2147483647 - 1, neg = -1;
+    return min / neg;
+}
@@ -0,0 +1,2 @@
+// fail
+int main() { return (int)(-2147483647 - 1) / (int)-1; }
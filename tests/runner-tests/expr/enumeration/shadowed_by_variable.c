@@ -0,0 +1,7 @@
+// code: 1
+enum e { A, B };
+int main() {
+    // `A` is a variable here, not the enumeration constant
+    enum e A = B;
+    return A;
+}
@@ -0,0 +1,9 @@
+// code: 6
+int main(void) {
+    double tenth = 0.1;
+    float half = 0.5f;
+    int two = 2;
+    _Bool implicit = tenth;
+    return ((_Bool)2 == 1) + ((_Bool)0.1 == 1) + ((_Bool)two == 1)
+        + ((_Bool)half == 1) + (implicit == 1) + ((_Bool)0.0 == 0);
+}
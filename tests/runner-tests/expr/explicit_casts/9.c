@@ -0,0 +1,17 @@
+// code: 14
+int f(int x) { return x + 1; }
+int main(void) {
+    int i = 5;
+    int *p = &i;
+    long addr = (long)p;
+    int *q = (int *)addr;
+    unsigned short us = 1;
+    int *s = (int *)us;
+    int *z = (int *)(char)0;
+    void *v = (void *)f;
+    int (*fp)(int) = (int (*)(int))v;
+    long fl = (long)f;
+    int (*fp2)(int) = (int (*)(int))fl;
+    _Bool b = (_Bool)p;
+    return *q + fp(1) + fp2(2) + b + (s == (int *)1) + (z == 0) + (q == p);
+}
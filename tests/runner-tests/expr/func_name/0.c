@@ -0,0 +1,12 @@
+// output: BEGIN: first second END
+int printf(const char *, ...);
+void first() {
+    printf("%s ", __func__);
+}
+void second() {
+    printf("%s", __func__);
+}
+int main() {
+    first();
+    second();
+}
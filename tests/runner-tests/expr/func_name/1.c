@@ -0,0 +1,11 @@
+// succeeds
+int strcmp(const char *, const char *);
+int function_name() {
+    return sizeof __func__ == sizeof "function_name"
+        && sizeof(__FUNCTION__) == 14
+        && strcmp(__PRETTY_FUNCTION__, "function_name") == 0;
+}
+int main() {
+    const char (*p)[5] = &__func__;
+    return !(function_name() && **p == 'm' && *p == __func__);
+}
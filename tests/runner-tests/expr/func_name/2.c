@@ -0,0 +1,2 @@
+// fail
+const char *p = __func__;
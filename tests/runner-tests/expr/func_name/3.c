@@ -0,0 +1,6 @@
+// code: 3
+// a local variable named `__func__` isn't special
+int main() {
+    int __func__ = 3;
+    return __func__;
+}
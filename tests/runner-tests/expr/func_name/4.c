@@ -0,0 +1,5 @@
+// compile-fail
+// `__func__` is `static const char __func__[]`, so it can't be modified
+int main(void) {
+    __func__[0] = 'x';
+}
@@ -0,0 +1,11 @@
+// code: 3
+int main(void) {
+    int i = 1;
+    int *p = &i, *null = 0;
+    void *v = p;
+    int *q = v;
+    _Bool b = p, n = null;
+    _Bool *bp = &b;
+    *bp = *bp && q;
+    return b + !n + *q;
+}
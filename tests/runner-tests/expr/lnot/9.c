@@ -0,0 +1,6 @@
+// code: 3
+int main() {
+    int x = 5, *p = &x, *q = 0;
+    double d = 0.0;
+    return !q + !d + !!p - !p;
+}
@@ -0,0 +1,5 @@
+// signal: 8
+int main() {
+    unsigned zero = 0;
+    return 1u % zero;
+}
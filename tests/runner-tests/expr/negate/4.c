@@ -0,0 +1,5 @@
+// code: 4
+int main() {
+    char c = 1;
+    return sizeof(-c) + (-c == -1) - 1;
+}
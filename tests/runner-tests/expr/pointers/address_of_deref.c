@@ -0,0 +1,12 @@
+// code: 13
+int g(void) { return 4; }
+int arr[3] = {1, 2, 3};
+int main(void) {
+    int (*fp)(void) = &*g;
+    int *p = &*arr;
+    int x = 5;
+    *&x = 7;
+    void *vp = &x;
+    int *ip = &*vp;
+    return fp() + p[1] + *&x + *ip - 7;
+}
@@ -0,0 +1,18 @@
+// code: 0
+// `p1 - p2` is the number of elements between them, with type `ptrdiff_t`
+struct big { long a, b, c; };
+
+int main() {
+    int arr[10];
+    int *p = &arr[7];
+    const int *q = arr + 2;
+    if (p - arr != 7 || arr - p != -7 || p - q != 5) return 1;
+    if (sizeof(p - q) != sizeof(long)) return 2;
+    struct big bigs[4];
+    struct big *end = bigs + 4;
+    if (end - bigs != 4 || &bigs[1] - end != -3) return 3;
+    char s[] = "saltwater";
+    char *c = s;
+    while (*c) c++;
+    return c - s != 9;
+}
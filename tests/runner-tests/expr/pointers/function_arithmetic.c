@@ -0,0 +1,7 @@
+// compile-fail
+int f(void) { return 0; }
+int main(void) {
+    int (*fp)(void) = f;
+    fp++;
+    return fp();
+}
@@ -0,0 +1,17 @@
+// code: 23
+int main() {
+    int a[4] = {1, 2, 3, 4};
+    int *p = a;
+    ++p;
+    p++;
+    if (*p-- != 3 || *p != 2 || p - 1 != a) {
+        return 1;
+    }
+    double d[2] = {1.5, 2.5};
+    double *dp = d + 1;
+    --dp;
+    if (*dp++ != 1.5 || *dp != 2.5) {
+        return 2;
+    }
+    return *--p * 20 + *(p += 2);
+}
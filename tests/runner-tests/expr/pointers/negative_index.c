@@ -0,0 +1,20 @@
+// code: 0
+// negative offsets are scaled as signed values, not converted to a huge unsigned offset
+struct big { long a, b, c; };
+
+int main() {
+    int arr[6] = {1, 2, 3, 4, 5, 6};
+    int *p = &arr[4];
+    int i = -3, back = 2;
+    unsigned char u = 1;
+    if (p[-2] != 3 || *(p - 3) != 2 || p[i] != 2 || *(p + i) != 2) return 1;
+    if (&p[-4] != arr || p - back != &arr[2] || p + -u != &arr[3]) return 2;
+    struct big bigs[3] = {{1, 2, 3}, {4, 5, 6}, {7, 8, 9}};
+    struct big *q = &bigs[2];
+    long j = -2;
+    if (q[j].b != 2 || (q - 1)->c != 6 || (char *)(q + j) != (char *)bigs) return 3;
+    p += i;
+    p -= -1;
+    if (*p != 3) return 4;
+    return 0;
+}
@@ -0,0 +1,10 @@
+// code: 7
+int main(void) {
+    char buf[8] = "abcdefg";
+    void *v = buf + 1;
+    v += 2;
+    v++;
+    --v;
+    // GNU C treats `sizeof(void)` as 1, so this is `buf + 3`
+    return *(char *)v - 'a' + ((char *)(v + 1))[0] - 'a';
+}
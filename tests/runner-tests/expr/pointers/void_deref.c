@@ -0,0 +1,7 @@
+// compile-fail
+int main() {
+    int x = 1;
+    void *p = &x;
+    *p;
+    return 0;
+}
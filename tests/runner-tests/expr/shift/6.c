@@ -0,0 +1,9 @@
+// code: 0
+// shifting an `unsigned int` by more than its size in bytes is not the same as by its size in bits
+int main() {
+    unsigned x = 0x80000000u;
+    if ((x >> 31) != 1) return 1;
+    if ((0x80000000u >> 31) != 1) return 2;
+    if ((x >> 8) != 0x800000) return 3;
+    return 0;
+}
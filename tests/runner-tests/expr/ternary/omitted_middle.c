@@ -0,0 +1,11 @@
+// code: 12
+int calls;
+int f() {
+    return ++calls;
+}
+int main() {
+    int zero = 0;
+    // `f()` is only evaluated once, and `zero ?: 10` is `10`
+    int result = (f() ?: 5) + (zero ?: 10);
+    return result + calls;
+}
@@ -0,0 +1,16 @@
+// code: 16
+#include <stddef.h>
+wchar_t g[] = L"héllo";
+static const unsigned short *u16 = u"a😀";
+int main(void) {
+    wchar_t local[4] = L"ab" "c";
+    const unsigned int *p = U"x" U"\x10FFFF";
+    const char *s = u8"é";
+    if (sizeof(L"ab") != 12) return 1;
+    if (g[1] != 0xe9 || g[5] != 0) return 2;
+    if (local[2] != 'c' || local[3] != 0) return 3;
+    if (p[1] != 0x10FFFF) return 4;
+    if ((unsigned char)s[0] != 0xc3) return 5;
+    if (u16[1] != 0xd83d || u16[2] != 0xde00) return 6;
+    return sizeof(u"abc") + sizeof(U"a");
+}
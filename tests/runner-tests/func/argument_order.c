@@ -0,0 +1,21 @@
+// code: 0
+// arguments are evaluated left to right, after the function designator (see IMPLEMENTATION_DEFINED.md)
+int g;
+
+int next(void) { return ++g; }
+int observe(int a, int b) { return g * 100 + a * 10 + b; }
+int add_g(int x) { return x + g; }
+int (*pick(void))(int) {
+    g = 10;
+    return add_g;
+}
+
+int main() {
+    g = 0;
+    // `g` is 2 by the time `observe` runs
+    if (observe(next(), next()) != 212) return 1;
+    g = 0;
+    // `pick` sets `g` before `next` increments it
+    if (pick()(next()) != 22) return 2;
+    return 0;
+}
@@ -0,0 +1,9 @@
+// code: 0
+// each function argument is an assignment-expression (6.5.2), so no parentheses are needed
+int add(int a, int b) { return a + b; }
+
+int main() {
+    int i, j = 1;
+    if (add(i = 2, j += 3) != 6) return 1;
+    return i != 2 || j != 4;
+}
@@ -0,0 +1,16 @@
+// code: 8
+int length(const char *s) {
+    int i = 0;
+    while (s[i]) i++;
+    return i;
+}
+const int *identity(int *p) { return p; }
+int first(const char *const *strings) { return strings[0][0]; }
+int main(void) {
+    char buf[4] = "abc";
+    char *s = buf;
+    int x = 2;
+    const void *p = &x;
+    char *strings[1] = { buf };
+    return length(s) + *identity(&x) + (p == &x) + first(strings) - 'a' + 2;
+}
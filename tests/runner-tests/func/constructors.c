@@ -0,0 +1,18 @@
+// output: BEGIN: first second main 3 destructor END
+int printf(const char *, ...);
+int value;
+__attribute__((constructor)) static void second(void) {
+    value += 2;
+    printf("second ");
+}
+__attribute__((constructor(101))) static void first(void) {
+    value = 1;
+    printf("first ");
+}
+void destructor(void) __attribute__((destructor));
+void destructor(void) {
+    printf("destructor");
+}
+int main(void) {
+    printf("main %d ", value);
+}
@@ -0,0 +1,7 @@
+// compile-fail
+struct s;
+struct s *p;
+void g(struct s);
+int main(void) {
+    g(*p);
+}
@@ -0,0 +1,8 @@
+// succeeds
+// `s` is never completed, so these can be declared but not called or defined
+struct s;
+struct s f(void);
+void g(struct s);
+int main(void) {
+    return 0;
+}
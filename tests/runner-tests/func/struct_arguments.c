@@ -0,0 +1,43 @@
+// code: 0
+// structs passed by value are copied when the argument is evaluated
+struct s {
+    int a;
+    long b[4];
+};
+
+int sum(struct s v) {
+    int total = v.a + v.b[3];
+    // modifying the parameter doesn't change the caller's struct
+    v.a = 1000;
+    v.b[3] = 1000;
+    return total;
+}
+
+int clobber(struct s *p) {
+    p->a = 50;
+    return 0;
+}
+
+int first(struct s v, int unused) { return v.a; }
+
+union u {
+    int i;
+    char c;
+};
+
+int get(union u x) { return x.i; }
+
+int main() {
+    struct s v = {1, {0, 0, 0, 2}};
+    int total = 0;
+    for (int i = 0; i < 3; i++) {
+        total += sum(v);
+        v.a = i + 10;
+    }
+    if (total != 3 + 12 + 13 || v.a != 12 || v.b[3] != 2) return 1;
+    // the first argument is copied before `clobber` runs
+    v.a = 7;
+    if (first(v, clobber(&v)) != 7 || v.a != 50) return 2;
+    union u x = {42};
+    return get(x) != 42;
+}
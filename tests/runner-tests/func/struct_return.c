@@ -0,0 +1,18 @@
+// code: 56
+struct s { int a; long b; char c[20]; };
+struct s make(int a) {
+    struct s r;
+    r.a = a;
+    r.b = a * 2;
+    r.c[19] = 3;
+    return r;
+}
+struct s pass(int a) { return make(a + 1); }
+double f(void) { return 1; }
+int *g(void) { return 0; }
+int main(void) {
+    struct s x = make(4);
+    struct s y;
+    y = pass(10);
+    return x.a + x.b + y.a + y.b + x.c[19] + (int)f() + (g() == 0) + make(1).a + make(5).a;
+}
@@ -0,0 +1,9 @@
+// code: 7
+// `f` is called before its prototype is seen, with more arguments than it takes
+int f();
+int g();
+int g(int a) { return a; }
+int main(void) {
+    return f(1, 2, 3) + g(2);
+}
+int f(int a) { return a + 4; }
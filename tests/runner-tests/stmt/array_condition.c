@@ -0,0 +1,14 @@
+// code: 7
+int arr[3];
+int f(void) { return 1; }
+int main(void) {
+    int result = 0;
+    if (arr) result += 1;
+    if (f) result += 2;
+    if (!arr) result += 8;
+    for (;;) {
+        result += 4;
+        break;
+    }
+    return result;
+}
@@ -1,4 +1,4 @@
-// fail
+// no-main
 int f() {
     if (0) { return 0; }
 }
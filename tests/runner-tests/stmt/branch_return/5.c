@@ -0,0 +1,7 @@
+// crash
+int f(int x) {
+    if (x) { return 0; }
+}
+int main() {
+    return f(0);
+}
@@ -0,0 +1,23 @@
+// code: 3
+int f(void) {
+    goto done;
+done:
+    return 1;
+}
+int g(void) {
+    int i = 0;
+    goto done;
+done:
+    return i + 2;
+}
+int main(void) {
+    int y = 2;
+    goto inside;
+    {
+        int x = 5;
+    inside:
+        // `x` was never initialized, so only use it after assigning
+        x = y;
+        return x + f() + g() - 2;
+    }
+}
@@ -0,0 +1,32 @@
+// code: 135
+int main(void) {
+    int i, total = 0, odd = 0;
+    for (i = 0; i < 10; i++) {
+        switch (i % 2) {
+        case 0:
+            continue;
+        default:
+            break;
+        }
+        odd++;
+    }
+    i = 0;
+    do {
+        i++;
+        if (i < 5) continue;
+        total += 100;
+    } while (i < 5);
+    int n = 0;
+    switch (1) {
+    case 1:
+        switch (2) {
+        case 1: n += 1000; break;
+        case 2: n += 10; break;
+        }
+        n += 20;
+        break;
+    case 2:
+        n += 3000;
+    }
+    return odd + total + n;
+}
@@ -0,0 +1,11 @@
+// code: 25
+int main(void) {
+    int sum = 0;
+    // `continue` still runs `i++`
+    for (int i = 0; i < 10; i++) {
+        if (i % 2 == 0)
+            continue;
+        sum += i;
+    }
+    return sum;
+}
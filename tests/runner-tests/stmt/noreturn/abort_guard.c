@@ -0,0 +1,11 @@
+// code: 5
+#include <stdlib.h>
+int divide(int x, int y) {
+    int result;
+    if (y != 0) result = x / y;
+    else abort();
+    return result;
+}
+int main(void) {
+    return divide(10, 2);
+}
@@ -0,0 +1,12 @@
+// code: 3
+int classify(int x) {
+    switch (x) {
+    case 0: return 1;
+    case 1: return 2;
+    case 2: return 3;
+    }
+    __builtin_unreachable();
+}
+int main(void) {
+    return classify(2);
+}
@@ -1,2 +1,2 @@
-// fail
+// no-main
 int f() {}
@@ -0,0 +1,15 @@
+// code: 0
+// control can't reach the end of `classify`, even though there's no `return` at the end
+int classify(int x) {
+    switch (x) {
+    case 1: return 10;
+    default:
+        if (x < 0) return -1;
+        return 0;
+    }
+}
+
+int main(void) {
+    if (classify(1) != 10 || classify(-5) != -1) return 1;
+    return classify(7);
+}
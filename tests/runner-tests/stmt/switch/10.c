@@ -0,0 +1,8 @@
+// compile-fail
+int main() {
+    switch(1) {
+        default: break;
+        case 1: return 1;
+        default: return 2;
+    }
+}
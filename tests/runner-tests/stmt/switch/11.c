@@ -0,0 +1,7 @@
+// compile-fail
+int main() {
+    switch(1) {
+        case 1: return 1;
+        case 1L: return 2;
+    }
+}
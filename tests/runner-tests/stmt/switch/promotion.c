@@ -0,0 +1,17 @@
+// code: 63
+int main(void) {
+    char c = -1;
+    unsigned char uc = 200;
+    int r = 0;
+    switch (c) { case -1: r += 1; break; case 0x180: r += 100; break; }
+    switch (uc) { case 200: r += 2; break; case -56: r += 100; break; }
+    enum e { A, B } e = B;
+    switch (e) { case 1: r += 4; break; }
+    _Bool b = 1;
+    switch (b) { case 1: r += 8; break; }
+    unsigned u = -1;
+    switch (u) { case -1: r += 16; break; }
+    long l = -1;
+    switch (l) { case 0xffffffff: r += 100; break; case -1: r += 32; }
+    return r;
+}
@@ -60,6 +60,19 @@ fn run_one(path: &path::Path) -> Result<(), io::Error> {
                     .expect("tests should have an integer after code:");
                 utils::assert_code(&program, path, code);
                 return Ok(());
+            // `signal: x` - it should compile, run, and be killed by signal x
+            } else if line.starts_with("// signal: ") {
+                let signal = line["// signal: ".len()..]
+                    .parse()
+                    .expect("tests should have an integer after signal:");
+                #[cfg(unix)]
+                utils::assert_signal(&program, path, signal);
+                #[cfg(not(unix))]
+                {
+                    let _ = signal;
+                    utils::assert_crash(&program, path);
+                }
+                return Ok(());
             // `errors: x` - it should not compile and rcc should output `x` errors
             } else if line.starts_with("// errors: ") {
                 let errors = line["// errors: ".len()..]
@@ -0,0 +1,218 @@
+//! Feed arbitrary sequences of tokens through the whole compiler.
+//!
+//! Almost none of these are valid C, but every one of them should give diagnostics
+//! (or compile) instead of panicking.
+use proptest::prelude::*;
+use saltwater::{initialize_aot_module, Opt};
+
+const TOKENS: &[&str] = &[
+    // punctuation
+    "(",
+    ")",
+    "[",
+    "]",
+    "{",
+    "}",
+    ";",
+    ",",
+    ":",
+    "?",
+    ".",
+    "->",
+    "...",
+    "*",
+    "&",
+    "+",
+    "-",
+    "/",
+    "%",
+    "<<",
+    ">>",
+    "<",
+    ">",
+    "<=",
+    ">=",
+    "==",
+    "!=",
+    "^",
+    "|",
+    "||",
+    "&&",
+    "!",
+    "~",
+    "++",
+    "--",
+    "=",
+    "+=",
+    "-=",
+    "*=",
+    "/=",
+    "%=",
+    "<<=",
+    ">>=",
+    "&=",
+    "^=",
+    "|=",
+    // keywords
+    "int",
+    "char",
+    "short",
+    "long",
+    "unsigned",
+    "signed",
+    "float",
+    "double",
+    "void",
+    "_Bool",
+    "const",
+    "volatile",
+    "restrict",
+    "static",
+    "extern",
+    "auto",
+    "register",
+    "typedef",
+    "inline",
+    "_Noreturn",
+    "struct",
+    "union",
+    "enum",
+    "sizeof",
+    "_Alignof",
+    "return",
+    "if",
+    "else",
+    "for",
+    "while",
+    "do",
+    "switch",
+    "case",
+    "default",
+    "break",
+    "continue",
+    "goto",
+    "__builtin_va_list",
+    "__attribute__",
+    // literals and identifiers
+    "0",
+    "1",
+    "-1",
+    "255",
+    "1.5",
+    "0x7fffffffffffffff",
+    "18446744073709551615u",
+    "'a'",
+    "\"s\"",
+    "a",
+    "b",
+    "p",
+    "f",
+    "s",
+    "u",
+    "x",
+    "T",
+    "main",
+];
+
+/// Declarations that give the token soup something to refer to.
+const PRELUDE: &str = "
+typedef int T;
+struct s { int x; struct s *next; int bits : 3; };
+union u { int i; float f; };
+enum e { A, B = 2 };
+int a[3], *p, f(int, ...);
+struct s x;
+";
+
+/// Programs that used to crash the compiler, with a space between each token.
+///
+/// These are run as-is by `regressions` and used as starting points for `mutations`.
+const SEEDS: &[&str] = &[
+    "__builtin_va_list l = { 1 } ;",
+    "struct t { __builtin_va_list l ; } y = { 1 } ;",
+    "int g ( ) { __builtin_va_list l [ 2 ] = { 1 , 2 } ; }",
+    "int g ( ) { T T ; T y ; }",
+    "int g ( ) { typedef int U ; } U y ;",
+    "int g ( int T ) { T y ; }",
+    "int g ( ) { for ( int T = 0 ; ; ) ; return T ; }",
+    "int y = sizeof ( struct t ) ;",
+    "int y = _Alignof ( union v ) ;",
+    "struct t ; int g ( ) { return sizeof ( struct t ) ; }",
+    "char y [ 2 ] = \"abc\" ;",
+    "int g ( ) { char y [ 1 ] = \"s\" \"s\" ; }",
+];
+
+fn soup() -> impl Strategy<Value = String> {
+    prop::collection::vec(prop::sample::select(TOKENS), 0..40).prop_map(|tokens| tokens.join(" "))
+}
+
+/// A seed with a few tokens inserted, removed, or replaced.
+fn mutation() -> impl Strategy<Value = String> {
+    let edit = (
+        any::<prop::sample::Index>(),
+        0..3u8,
+        prop::sample::select(TOKENS),
+    );
+    (
+        prop::sample::select(SEEDS),
+        prop::collection::vec(edit, 1..4),
+    )
+        .prop_map(|(seed, edits)| {
+            let mut tokens: Vec<&str> = seed.split(' ').collect();
+            for (index, kind, token) in edits {
+                let i = index.index(tokens.len() + 1);
+                match kind {
+                    0 => tokens.insert(i, token),
+                    1 if i < tokens.len() => {
+                        tokens.remove(i);
+                    }
+                    _ if i < tokens.len() => tokens[i] = token,
+                    _ => tokens.push(token),
+                }
+            }
+            tokens.join(" ")
+        })
+}
+
+/// Run `program` through every stage, from preprocessing to code generation.
+fn compile(program: &str) -> bool {
+    let module = initialize_aot_module("token_soup".to_owned(), 0);
+    saltwater::compile(module, program, Opt::default())
+        .result
+        .is_ok()
+}
+
+#[test]
+fn regressions() {
+    for seed in SEEDS {
+        let program = format!("{}{}\n", PRELUDE, seed);
+        assert!(!compile(&program), "should be an error: {}", seed);
+    }
+}
+
+proptest! {
+    #[test]
+    fn file_scope(tokens in soup()) {
+        compile(&format!("{}{}\n", PRELUDE, tokens));
+    }
+
+    #[test]
+    fn declaration(tokens in soup()) {
+        compile(&format!("{}int declared = {};\n", PRELUDE, tokens));
+    }
+
+    #[test]
+    fn function_body(tokens in soup()) {
+        compile(&format!("{}int g(int i, struct s *q) {{ {} }}\n", PRELUDE, tokens));
+    }
+
+    #[test]
+    fn expression(tokens in soup()) {
+        compile(&format!("{}int g(int i, struct s *q) {{ return {}; }}\n", PRELUDE, tokens));
+    }
+
+    #[test]
+    fn mutations(program in mutation()) {
+        compile(&format!("{}{}\n", PRELUDE, program));
+    }
+}
@@ -49,8 +49,18 @@ pub fn compile(
         filename,
         ..Default::default()
     };
-    let module = saltwater::initialize_aot_module(program.to_owned());
-    let module = saltwater::compile(module, program, opts).result?.finish();
+    compile_with(program, opts, no_link)
+}
+
+/// Same as `compile`, but with custom options.
+pub fn compile_with(
+    program: &str,
+    opts: saltwater::Opt,
+    no_link: bool,
+) -> Result<tempfile::TempPath, Error> {
+    let module = saltwater::initialize_aot_module(program.to_owned(), opts.opt_level);
+    let module = saltwater::compile(module, program, opts).result?;
+    let module = saltwater::finish_aot_module(module);
     let output = tempfile::NamedTempFile::new()
         .expect("cannot create tempfile")
         .into_temp_path();
@@ -74,7 +84,7 @@ pub fn run(program: &Path, args: &[&str]) -> Result<Output, std::io::Error> {
 
 pub fn assert_compiles(program: &str, path: PathBuf) {
     assert!(
-        compile(program, path, true).is_err(),
+        compile(program, path, true).is_ok(),
         "{} failed to compile",
         program
     );
@@ -124,6 +134,26 @@ pub fn assert_crash(program: &str, path: PathBuf) {
     }
 }
 
+/// Assert that the program crashes with the given signal, e.g. 8 for SIGFPE.
+#[cfg(unix)]
+pub fn assert_signal(program: &str, path: PathBuf, signal: i32) {
+    use std::os::unix::process::ExitStatusExt;
+
+    let output = compile(program, path, false).expect("could not compile program");
+    let path: &Path = output.as_ref();
+    let status = Command::new(path)
+        .status()
+        .expect("could not start compiled program");
+    assert_eq!(
+        status.signal(),
+        Some(signal),
+        "{} should have crashed with signal {} (got {})",
+        program,
+        signal,
+        status
+    );
+}
+
 pub fn assert_output(program: &str, path: PathBuf, output: &str) {
     match compile_and_run(program, path, &[]) {
         Err(_) => panic!("program failed to compile or run: {}", program),